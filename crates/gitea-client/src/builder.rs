@@ -0,0 +1,177 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use reqwest::{
+    Certificate, Client,
+    header::{AUTHORIZATION, HeaderMap, HeaderValue},
+};
+
+use crate::{client::GiteaClient, error::ClientError, retry::RetryPolicy};
+
+/// Time allowed to establish a connection unless configured otherwise.
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How the client authenticates with the API.
+enum Auth {
+    Basic { username: String, password: String },
+    Token(String),
+}
+
+/// Builds a [`GiteaClient`] with custom HTTP and TLS options.
+///
+/// Connections time out after 10 seconds by default, while requests have
+/// no timeout as migrations and archive downloads may take long.
+pub struct GiteaClientBuilder {
+    endpoint: String,
+    auth: Option<Auth>,
+    connect_timeout: Duration,
+    request_timeout: Option<Duration>,
+    accept_invalid_certs: bool,
+    ca_cert: Option<Vec<u8>>,
+    retry: RetryPolicy,
+}
+
+impl GiteaClientBuilder {
+    /// Starts building an anonymous client of the Gitea server at the given
+    /// endpoint.
+    pub fn new(endpoint: String) -> Self {
+        Self {
+            endpoint,
+            auth: None,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            request_timeout: None,
+            accept_invalid_certs: false,
+            ca_cert: None,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Authenticates with a username and password.
+    pub fn basic_auth(mut self, username: String, password: String) -> Self {
+        self.auth = Some(Auth::Basic { username, password });
+        self
+    }
+
+    /// Authenticates with a personal access token, for servers where
+    /// password authentication is disabled.
+    pub fn token(mut self, token: String) -> Self {
+        self.auth = Some(Auth::Token(token));
+        self
+    }
+
+    /// Sets the time allowed to establish a connection.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Sets the time allowed for a whole request, from connecting until the
+    /// response body is read.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Accepts any server certificate, even expired or self-signed ones.
+    /// Only meant for development, as it allows man-in-the-middle attacks.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Trusts the PEM encoded certificates of private certificate
+    /// authorities, on top of the system roots.
+    pub fn ca_cert_pem(mut self, pem: Vec<u8>) -> Self {
+        self.ca_cert = Some(pem);
+        self
+    }
+
+    /// Sets how requests failing transiently are retried.
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Builds the client.
+    ///
+    /// A username containing a colon, credentials with control characters
+    /// such as a stray newline from a secret file, or an empty token are
+    /// rejected with [`ClientError::InvalidCredentials`]. An unreadable CA
+    /// certificate is rejected with [`ClientError::InvalidCertificate`].
+    pub fn build(self) -> Result<GiteaClient, ClientError> {
+        let mut headers = HeaderMap::new();
+        if let Some(auth) = &self.auth {
+            headers.insert(AUTHORIZATION, authorization(auth)?);
+        }
+
+        // reqwest verifies certificates with the process-wide rustls crypto
+        // provider, ring being the one built in
+        let _ = rustls::crypto::ring::default_provider().install_default();
+
+        let mut builder = Client::builder()
+            .default_headers(headers)
+            .connect_timeout(self.connect_timeout)
+            .tls_danger_accept_invalid_certs(self.accept_invalid_certs);
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(pem) = &self.ca_cert {
+            let certs = Certificate::from_pem_bundle(pem)
+                .map_err(|e| ClientError::InvalidCertificate(e.to_string()))?;
+            if certs.is_empty() {
+                return Err(ClientError::InvalidCertificate("no certificate found".into()));
+            }
+            builder = builder.tls_certs_merge(certs);
+        }
+
+        let base_url = format!("{}/api/v1", self.endpoint);
+        Ok(GiteaClient { client: builder.build()?, base_url, retry: self.retry })
+    }
+}
+
+/// Builds the authorization header sent with every request.
+fn authorization(auth: &Auth) -> Result<HeaderValue, ClientError> {
+    let value = match auth {
+        Auth::Basic { username, password } => {
+            if username.contains(':') {
+                return Err(ClientError::InvalidCredentials("username contains a colon".into()));
+            }
+            if username.chars().chain(password.chars()).any(char::is_control) {
+                return Err(ClientError::InvalidCredentials(
+                    "credentials contain a control character".into(),
+                ));
+            }
+            format!("Basic {}", STANDARD.encode(format!("{username}:{password}")))
+        }
+        Auth::Token(token) => {
+            if token.is_empty() {
+                return Err(ClientError::InvalidCredentials("token is empty".into()));
+            }
+            if token.chars().any(char::is_control) {
+                return Err(ClientError::InvalidCredentials(
+                    "token contains a control character".into(),
+                ));
+            }
+            format!("token {token}")
+        }
+    };
+
+    let mut header = HeaderValue::from_str(&value)
+        .map_err(|e| ClientError::InvalidCredentials(e.to_string()))?;
+    header.set_sensitive(true);
+    Ok(header)
+}
@@ -17,7 +17,7 @@ use reqwest::StatusCode;
 use crate::{
     client::GiteaClient,
     error::{ClientError, Result},
-    types::{CreateHookRequest, Hook, HookType},
+    types::{CreateHookRequest, EditHookRequest, Hook, HookType},
 };
 
 impl GiteaClient {
@@ -77,6 +77,106 @@ impl GiteaClient {
         self.list_hooks(&format!("orgs/{org}/hooks")).await
     }
 
+    /// Updates a webhook of an organization.
+    ///
+    /// # Arguments
+    /// * `org` - The name of the organization
+    /// * `id` - The ID of the hook
+    /// * `req` - The hook edit request payload
+    ///
+    /// # Possible Responses
+    /// - 200: Hook updated successfully (returns `Hook`)
+    /// - 404: Organization or hook not found
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/organization/operation/orgEditHook
+    pub async fn update_org_hook(&self, org: &str, id: u64, req: EditHookRequest) -> Result<Hook> {
+        let response = self.patch(&format!("orgs/{org}/hooks/{id}"), &req).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json::<Hook>().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Deletes a webhook of an organization.
+    ///
+    /// # Arguments
+    /// * `org` - The name of the organization
+    /// * `id` - The ID of the hook
+    ///
+    /// # Possible Responses
+    /// - 204: Hook deleted successfully
+    /// - 404: Organization or hook not found
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/organization/operation/orgDeleteHook
+    pub async fn delete_org_hook(&self, org: &str, id: u64) -> Result<()> {
+        let response = self.delete(&format!("orgs/{org}/hooks/{id}")).await?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(()),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Creates a new hook for a repository.
+    ///
+    /// # Arguments
+    /// * `owner` - The owner of the repository
+    /// * `repo` - The name of the repository
+    /// * `req` - The hook creation request payload
+    ///
+    /// # Possible Responses
+    /// - 201: Hook created successfully (returns `Hook`)
+    /// - 404: Repository not found
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/repository/operation/repoCreateHook
+    #[inline]
+    pub async fn create_repo_hook(
+        &self,
+        owner: &str,
+        repo: &str,
+        req: CreateHookRequest,
+    ) -> Result<Hook> {
+        self.create_hook(&format!("repos/{owner}/{repo}/hooks"), req).await
+    }
+
+    /// Lists all webhooks of a repository.
+    ///
+    /// # Arguments
+    /// * `owner` - The owner of the repository
+    /// * `repo` - The name of the repository
+    ///
+    /// # Possible Responses
+    /// - 200: List of repository hooks returned successfully (returns `Vec<Hook>`).
+    /// - 404: Repository not found
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/repository/operation/repoListHooks
+    #[inline]
+    pub async fn list_repo_hooks(&self, owner: &str, repo: &str) -> Result<Vec<Hook>> {
+        self.list_hooks(&format!("repos/{owner}/{repo}/hooks")).await
+    }
+
+    /// Deletes a webhook of a repository.
+    ///
+    /// # Arguments
+    /// * `owner` - The owner of the repository
+    /// * `repo` - The name of the repository
+    /// * `id` - The ID of the hook
+    ///
+    /// # Possible Responses
+    /// - 204: Hook deleted successfully
+    /// - 404: Repository or hook not found
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/repository/operation/repoDeleteHook
+    pub async fn delete_repo_hook(&self, owner: &str, repo: &str, id: u64) -> Result<()> {
+        let response = self.delete(&format!("repos/{owner}/{repo}/hooks/{id}")).await?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(()),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
     /// Creates a new hook at the specified path.
     ///
     /// This is an internal helper function used by both admin and repository hook creation.
@@ -0,0 +1,91 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use reqwest::StatusCode;
+
+use crate::{
+    client::GiteaClient,
+    error::{ClientError, Result},
+    types::{CreateKeyRequest, DeployKey},
+};
+
+impl GiteaClient {
+    /// Adds a deploy key to a repository.
+    ///
+    /// # Arguments
+    /// * `owner` - The owner of the repository
+    /// * `repo` - The name of the repository
+    /// * `req` - The key creation request payload
+    ///
+    /// # Possible Responses
+    /// - 201: Key added successfully (returns `DeployKey`)
+    /// - 404: Repository not found
+    /// - 422: Invalid or already used key
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/repository/operation/repoCreateKey
+    pub async fn create_deploy_key(
+        &self,
+        owner: &str,
+        repo: &str,
+        req: CreateKeyRequest,
+    ) -> Result<DeployKey> {
+        let response = self.post(&format!("repos/{owner}/{repo}/keys"), &req).await?;
+
+        match response.status() {
+            StatusCode::CREATED => Ok(response.json::<DeployKey>().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Lists the deploy keys of a repository.
+    ///
+    /// # Arguments
+    /// * `owner` - The owner of the repository
+    /// * `repo` - The name of the repository
+    ///
+    /// # Possible Responses
+    /// - 200: List of deploy keys returned successfully (returns `Vec<DeployKey>`)
+    /// - 404: Repository not found
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/repository/operation/repoListKeys
+    pub async fn list_deploy_keys(&self, owner: &str, repo: &str) -> Result<Vec<DeployKey>> {
+        let response = self.get(&format!("repos/{owner}/{repo}/keys")).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json::<Vec<DeployKey>>().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Removes a deploy key from a repository.
+    ///
+    /// # Arguments
+    /// * `owner` - The owner of the repository
+    /// * `repo` - The name of the repository
+    /// * `id` - The ID of the key
+    ///
+    /// # Possible Responses
+    /// - 204: Key removed successfully
+    /// - 404: Repository or key not found
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/repository/operation/repoDeleteKey
+    pub async fn delete_deploy_key(&self, owner: &str, repo: &str, id: i64) -> Result<()> {
+        let response = self.delete(&format!("repos/{owner}/{repo}/keys/{id}")).await?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(()),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+}
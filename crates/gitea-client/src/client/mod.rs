@@ -13,6 +13,7 @@
 // limitations under the License.
 
 pub mod hook;
+pub mod key;
 pub mod organization;
 pub mod repository;
 pub mod user;
@@ -20,46 +21,71 @@ pub mod user;
 use reqwest::{Client, Error, Response};
 use serde::Serialize;
 
+use crate::{builder::GiteaClientBuilder, error::ClientError, retry::RetryPolicy};
+
 /// A client for interacting with the Gitea API.
 pub struct GiteaClient {
     pub(crate) client: Client,
     pub(crate) base_url: String,
-    pub(crate) username: String,
-    pub(crate) password: String,
+    pub(crate) retry: RetryPolicy,
 }
 
 impl GiteaClient {
-    /// Creates a new `GiteaClient` instance.
-    pub fn new(endpoint: String, username: String, password: String) -> Self {
-        GiteaClient {
-            client: Client::new(),
-            base_url: format!("{endpoint}/api/v1"),
-            username,
-            password,
-        }
+    /// Creates a new `GiteaClient` instance authenticating with a username
+    /// and password.
+    ///
+    /// A username containing a colon, or credentials with control characters
+    /// such as a stray newline from a secret file, are rejected with
+    /// [`ClientError::InvalidCredentials`].
+    pub fn new(endpoint: String, username: String, password: String) -> Result<Self, ClientError> {
+        Self::builder(endpoint).basic_auth(username, password).build()
+    }
+
+    /// Creates a new `GiteaClient` instance authenticating with a personal
+    /// access token, for servers where password authentication is disabled.
+    ///
+    /// Empty tokens, or tokens with control characters, are rejected with
+    /// [`ClientError::InvalidCredentials`].
+    pub fn with_token(endpoint: String, token: String) -> Result<Self, ClientError> {
+        Self::builder(endpoint).token(token).build()
+    }
+
+    /// Starts building a client with custom HTTP and TLS options.
+    pub fn builder(endpoint: String) -> GiteaClientBuilder {
+        GiteaClientBuilder::new(endpoint)
+    }
+
+    /// Sets how requests failing transiently are retried.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
     }
 
     /// Sends a GET request.
     pub(crate) async fn get(&self, path: &str) -> Result<Response, Error> {
         let url = format!("{}/{}", self.base_url, path);
-        self.client.get(&url).basic_auth(&self.username, Some(&self.password)).send().await
+        self.retry.send(self.client.get(&url), true).await
     }
 
     /// Sends a POST request with a JSON body.
     pub(crate) async fn post<T: Serialize>(&self, path: &str, body: &T) -> Result<Response, Error> {
         let url = format!("{}/{}", self.base_url, path);
-        self.client
-            .post(&url)
-            .basic_auth(&self.username, Some(&self.password))
-            .json(body)
-            .send()
-            .await
+        self.retry.send(self.client.post(&url).json(body), false).await
+    }
+
+    /// Sends a PATCH request with a JSON body.
+    pub(crate) async fn patch<T: Serialize>(
+        &self,
+        path: &str,
+        body: &T,
+    ) -> Result<Response, Error> {
+        let url = format!("{}/{}", self.base_url, path);
+        self.retry.send(self.client.patch(&url).json(body), false).await
     }
 
     /// Sends a DELETE request.
-    #[allow(dead_code)]
     pub(crate) async fn delete(&self, path: &str) -> Result<Response, Error> {
         let url = format!("{}/{}", self.base_url, path);
-        self.client.delete(&url).basic_auth(&self.username, Some(&self.password)).send().await
+        self.retry.send(self.client.delete(&url), true).await
     }
 }
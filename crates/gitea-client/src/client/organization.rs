@@ -17,7 +17,9 @@ use reqwest::StatusCode;
 use crate::{
     client::GiteaClient,
     error::{ClientError, Result},
-    types::{CreateOrganizationRequest, CreateRepositoryRequest, Organization, Repository},
+    types::{
+        CreateOrganizationRequest, CreateRepositoryRequest, Organization, Pagination, Repository,
+    },
 };
 
 impl GiteaClient {
@@ -58,6 +60,25 @@ impl GiteaClient {
         }
     }
 
+    /// Deletes an organization. Gitea refuses while it still owns
+    /// repositories.
+    ///
+    /// # Possible Responses
+    /// - 204: Organization deleted successfully.
+    /// - 403: Forbidden (insufficient permissions).
+    /// - 404: Organization not found.
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/organization/operation/orgDelete
+    pub async fn delete_organization(&self, name: &str) -> Result<()> {
+        let endpoint = format!("orgs/{name}");
+        let response = self.delete(&endpoint).await?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(()),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
     /// Creates a new repository in an organization.
     ///
     /// # Possible Responses
@@ -80,4 +101,25 @@ impl GiteaClient {
             _ => Err(ClientError::from_response(response).await),
         }
     }
+
+    /// Lists a page of the repositories of an organization.
+    ///
+    /// # Possible Responses
+    /// - 200: Page of repositories returned successfully (returns `Vec<Repository>`).
+    /// - 404: Organization not found.
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/organization/operation/orgListRepos
+    pub async fn list_org_repositories(
+        &self,
+        org: &str,
+        pagination: Pagination,
+    ) -> Result<Vec<Repository>> {
+        let endpoint = format!("orgs/{org}/repos?{}", pagination.query());
+        let response = self.get(&endpoint).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json::<Vec<Repository>>().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
 }
@@ -12,12 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use bytes::Bytes;
+use futures_util::{Stream, TryStreamExt};
 use reqwest::StatusCode;
 
 use crate::{
     client::GiteaClient,
     error::{ClientError, Result},
-    types::{CreateRepositoryRequest, GenerateRepositoryRequest, Repository},
+    types::{
+        BranchProtection, Commit, CreateBranchProtectionRequest, CreateRepositoryRequest,
+        EditRepositoryRequest, GenerateRepositoryRequest, ListCommitsOptions, MigrateRepoRequest,
+        Repository, TransferRepositoryRequest,
+    },
 };
 
 impl GiteaClient {
@@ -87,4 +93,222 @@ impl GiteaClient {
             _ => Err(ClientError::from_response(response).await),
         }
     }
+
+    /// Migrates a repository from another service, e.g. another Gitea.
+    ///
+    /// # Possible Responses
+    /// - 201: Repository migrated successfully (returns `Repository`).
+    /// - 403: Forbidden (migrations disabled or insufficient permissions).
+    /// - 409: Repository with the same name already exists.
+    /// - 422: Input validation failed, e.g. the source rejected the credentials.
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/repository/operation/repoMigrate
+    pub async fn migrate_repository(&self, request: MigrateRepoRequest) -> Result<Repository> {
+        let response = self.post("repos/migrate", &request).await?;
+
+        match response.status() {
+            StatusCode::CREATED => Ok(response.json::<Repository>().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Edits the properties of a repository, e.g. renames or archives it.
+    ///
+    /// # Possible Responses
+    /// - 200: Repository edited successfully (returns `Repository`).
+    /// - 403: Forbidden (insufficient permissions).
+    /// - 404: Repository not found.
+    /// - 422: Input validation failed, e.g. the new name is already taken.
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/repository/operation/repoEdit
+    pub async fn edit_repository(
+        &self,
+        owner: &str,
+        repo: &str,
+        request: EditRepositoryRequest,
+    ) -> Result<Repository> {
+        let endpoint = format!("repos/{owner}/{repo}");
+        let response = self.patch(&endpoint, &request).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json::<Repository>().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Archives a repository, making it read-only.
+    ///
+    /// # Possible Responses
+    /// - 200: Repository archived successfully (returns `Repository`).
+    /// - 403: Forbidden (insufficient permissions).
+    /// - 404: Repository not found.
+    /// - 422: Input validation failed.
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/repository/operation/repoEdit
+    #[inline]
+    pub async fn archive_repository(&self, owner: &str, repo: &str) -> Result<Repository> {
+        let request = EditRepositoryRequest { archived: Some(true), ..Default::default() };
+        self.edit_repository(owner, repo, request).await
+    }
+
+    /// Transfers a repository to another user or organization.
+    ///
+    /// # Possible Responses
+    /// - 202: Transfer accepted, or pending if the new owner has to accept it (returns
+    ///   `Repository`).
+    /// - 403: Forbidden (insufficient permissions).
+    /// - 404: Repository or new owner not found.
+    /// - 422: Input validation failed, e.g. the new owner has a repository of the same name.
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/repository/operation/repoTransfer
+    pub async fn transfer_repository(
+        &self,
+        owner: &str,
+        repo: &str,
+        new_owner: &str,
+    ) -> Result<Repository> {
+        let endpoint = format!("repos/{owner}/{repo}/transfer");
+        let request =
+            TransferRepositoryRequest { new_owner: new_owner.to_string(), team_ids: None };
+        let response = self.post(&endpoint, &request).await?;
+
+        match response.status() {
+            StatusCode::ACCEPTED => Ok(response.json::<Repository>().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Deletes a repository.
+    ///
+    /// # Possible Responses
+    /// - 204: Repository deleted successfully.
+    /// - 403: Forbidden (insufficient permissions).
+    /// - 404: Repository not found.
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/repository/operation/repoDelete
+    pub async fn delete_repository(&self, owner: &str, repo: &str) -> Result<()> {
+        let endpoint = format!("repos/{owner}/{repo}");
+        let response = self.delete(&endpoint).await?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(()),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Gets the raw content of a file at the given reference (branch, tag or
+    /// commit SHA).
+    ///
+    /// # Possible Responses
+    /// - 200: File content returned as bytes.
+    /// - 404: Repository, reference or file not found.
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/repository/operation/repoGetRawFile
+    pub async fn get_raw_file(
+        &self,
+        owner: &str,
+        repo: &str,
+        path: &str,
+        reference: &str,
+    ) -> Result<Vec<u8>> {
+        let endpoint = format!("repos/{owner}/{repo}/raw/{path}?ref={reference}");
+        let response = self.get(&endpoint).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.bytes().await?.to_vec()),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Gets a gzipped tarball of the repository at the given reference
+    /// (branch, tag or commit SHA), streamed as it is downloaded.
+    ///
+    /// # Possible Responses
+    /// - 200: Archive returned as a stream of bytes.
+    /// - 404: Repository or reference not found.
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/repository/operation/repoGetArchive
+    pub async fn get_archive(
+        &self,
+        owner: &str,
+        repo: &str,
+        reference: &str,
+    ) -> Result<impl Stream<Item = Result<Bytes>> + use<>> {
+        let endpoint = format!("repos/{owner}/{repo}/archive/{reference}.tar.gz");
+        let response = self.get(&endpoint).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.bytes_stream().map_err(ClientError::Network)),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Lists a page of the commits of a repository, newest first.
+    ///
+    /// # Possible Responses
+    /// - 200: Page of commits returned successfully (returns `Vec<Commit>`).
+    /// - 404: Repository or reference not found.
+    /// - 409: The repository is empty.
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/repository/operation/repoGetAllCommits
+    pub async fn list_commits(
+        &self,
+        owner: &str,
+        repo: &str,
+        options: ListCommitsOptions,
+    ) -> Result<Vec<Commit>> {
+        let endpoint = format!("repos/{owner}/{repo}/commits?{}", options.query());
+        let response = self.get(&endpoint).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json::<Vec<Commit>>().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Gets a branch protection rule of a repository by its name.
+    ///
+    /// # Possible Responses
+    /// - 200: Rule found successfully (returns `BranchProtection`).
+    /// - 404: Repository or rule not found.
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/repository/operation/repoGetBranchProtection
+    pub async fn get_branch_protection(
+        &self,
+        owner: &str,
+        repo: &str,
+        name: &str,
+    ) -> Result<BranchProtection> {
+        let endpoint = format!("repos/{owner}/{repo}/branch_protections/{name}");
+        let response = self.get(&endpoint).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json::<BranchProtection>().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Creates a branch protection rule for a repository.
+    ///
+    /// # Possible Responses
+    /// - 201: Rule created successfully (returns `BranchProtection`).
+    /// - 403: Forbidden, e.g. a rule with the same name already exists.
+    /// - 404: Repository not found.
+    /// - 422: Input validation failed.
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/repository/operation/repoCreateBranchProtection
+    pub async fn create_branch_protection(
+        &self,
+        owner: &str,
+        repo: &str,
+        request: CreateBranchProtectionRequest,
+    ) -> Result<BranchProtection> {
+        let endpoint = format!("repos/{owner}/{repo}/branch_protections");
+        let response = self.post(&endpoint, &request).await?;
+
+        match response.status() {
+            StatusCode::CREATED => Ok(response.json::<BranchProtection>().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
 }
@@ -20,11 +20,14 @@ use crate::{
     types::{CreateUserRequest, User},
 };
 
+/// Number of users requested per page when listing, Gitea's default maximum.
+const PAGE_SIZE: usize = 50;
+
 impl GiteaClient {
     /// Get a user by username.
     ///
     /// # Possible Responses
-    /// - 201: User created successfully (returns `User`).
+    /// - 200: User found successfully (returns `User`).
     /// - 404: User not found.
     ///
     /// https://docs.gitea.com/api/1.24/#tag/user/operation/userGet
@@ -54,4 +57,50 @@ impl GiteaClient {
             _ => Err(ClientError::from_response(response).await),
         }
     }
+
+    /// Deletes a user.
+    ///
+    /// # Possible Responses
+    /// - 204: User deleted successfully.
+    /// - 403: Forbidden (not an admin).
+    /// - 404: User not found.
+    /// - 422: The user still owns repositories or organizations.
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/admin/operation/adminDeleteUser
+    pub async fn delete_user(&self, username: &str) -> Result<()> {
+        let response = self.delete(&format!("admin/users/{username}")).await?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(()),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Lists all users, fetching every page.
+    ///
+    /// # Possible Responses
+    /// - 200: Page of users returned successfully (returns `Vec<User>`).
+    /// - 403: Forbidden (not an admin).
+    ///
+    /// https://docs.gitea.com/api/1.24/#tag/admin/operation/adminSearchUsers
+    pub async fn list_users(&self) -> Result<Vec<User>> {
+        let mut users = Vec::new();
+        for page in 1.. {
+            let endpoint = format!("admin/users?page={page}&limit={PAGE_SIZE}");
+            let response = self.get(&endpoint).await?;
+
+            let batch = match response.status() {
+                StatusCode::OK => response.json::<Vec<User>>().await?,
+                _ => return Err(ClientError::from_response(response).await),
+            };
+
+            let last = batch.len() < PAGE_SIZE;
+            users.extend(batch);
+            if last {
+                break;
+            }
+        }
+
+        Ok(users)
+    }
 }
@@ -23,6 +23,12 @@ pub enum ClientError {
     #[error("Network error: {0}")]
     Network(#[from] reqwest::Error),
 
+    #[error("Invalid credentials: {0}")]
+    InvalidCredentials(String),
+
+    #[error("Invalid certificate: {0}")]
+    InvalidCertificate(String),
+
     #[error("Bad request: {0}")]
     BadRequest(String),
 
@@ -12,10 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod builder;
 mod client;
 mod error;
+mod retry;
 pub mod types;
 
 // Re-exports
+pub use builder::GiteaClientBuilder;
 pub use client::*;
 pub use error::ClientError;
+pub use retry::RetryPolicy;
@@ -0,0 +1,79 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A protection rule of the branches of a repository.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchProtection {
+    /// Name of the rule, the branch name or a glob matching branches.
+    pub rule_name: String,
+
+    /// Whether pushes are allowed at all.
+    pub enable_push: bool,
+
+    /// Whether pushes are restricted to the users on the allowlist.
+    pub enable_push_whitelist: bool,
+
+    /// Users allowed to push when restricted.
+    pub push_whitelist_usernames: Option<Vec<String>>,
+
+    /// Whether force pushes are allowed at all.
+    pub enable_force_push: bool,
+
+    /// Whether force pushes are restricted to the users on the allowlist.
+    pub enable_force_push_allowlist: bool,
+
+    /// Users allowed to force push when restricted.
+    pub force_push_allowlist_usernames: Option<Vec<String>>,
+
+    /// Timestamp when the rule was created.
+    pub created_at: DateTime<Utc>,
+
+    /// Timestamp when the rule was last updated.
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Request body for protecting the branches of a repository. Protected
+/// branches can never be deleted.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct CreateBranchProtectionRequest {
+    /// Name of the rule, the branch name or a glob matching branches.
+    pub rule_name: String,
+
+    /// Whether pushes are allowed at all.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_push: Option<bool>,
+
+    /// Whether pushes are restricted to the users on the allowlist.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_push_whitelist: Option<bool>,
+
+    /// Users allowed to push when restricted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub push_whitelist_usernames: Option<Vec<String>>,
+
+    /// Whether force pushes are allowed at all.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_force_push: Option<bool>,
+
+    /// Whether force pushes are restricted to the users on the allowlist.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_force_push_allowlist: Option<bool>,
+
+    /// Users allowed to force push when restricted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub force_push_allowlist_usernames: Option<Vec<String>>,
+}
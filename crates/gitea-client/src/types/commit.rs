@@ -15,7 +15,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use super::PartialUser;
+use super::{Pagination, PartialUser};
 
 /// A partial representation of a commit,
 /// containing only the most essential fields.
@@ -39,3 +39,107 @@ pub struct PartialCommit {
     /// The timestamp when the commit was created
     pub timestamp: DateTime<Utc>,
 }
+
+/// A commit of a repository, as listed from its history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Commit {
+    /// The SHA of the commit
+    pub sha: String,
+
+    /// URL to view the commit in the source control system
+    pub html_url: String,
+
+    /// The timestamp when the commit was created
+    pub created: DateTime<Utc>,
+
+    /// The git object of the commit
+    pub commit: RepoCommit,
+}
+
+impl Commit {
+    /// The commit message describing the changes.
+    pub fn message(&self) -> &str {
+        &self.commit.message
+    }
+
+    /// The timestamp when the commit was committed, e.g. rebased.
+    pub fn committed_at(&self) -> DateTime<Utc> {
+        self.commit.committer.date
+    }
+}
+
+/// The git object of a commit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepoCommit {
+    /// The commit message describing the changes
+    pub message: String,
+
+    /// The author who originally created the changes
+    pub author: CommitUser,
+
+    /// The committer who actually committed the changes
+    pub committer: CommitUser,
+}
+
+/// The identity of an author or committer of a commit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitUser {
+    /// Name of the person
+    pub name: String,
+
+    /// Email address of the person
+    pub email: String,
+
+    /// The timestamp of the authorship or commit
+    pub date: DateTime<Utc>,
+}
+
+/// Options for listing the commits of a repository. Gitea computes the
+/// stats, verification and changed files of every commit unless disabled.
+#[derive(Debug, Clone, Default)]
+pub struct ListCommitsOptions {
+    /// Branch, tag or commit SHA to list the history of, the default
+    /// branch if unset
+    pub sha: Option<String>,
+
+    /// Whether to include the diff stats of each commit
+    pub stat: Option<bool>,
+
+    /// Whether to include the signature verification of each commit
+    pub verification: Option<bool>,
+
+    /// Whether to include the changed files of each commit
+    pub files: Option<bool>,
+
+    /// The page of the history to list
+    pub pagination: Pagination,
+}
+
+impl ListCommitsOptions {
+    /// Lists only the latest commit, without any of the expensive details.
+    pub fn latest() -> Self {
+        Self {
+            stat: Some(false),
+            verification: Some(false),
+            files: Some(false),
+            pagination: Pagination::new(1, 1),
+            ..Default::default()
+        }
+    }
+
+    /// Formats the query string of the options.
+    pub(crate) fn query(&self) -> String {
+        let mut query = self.pagination.query();
+        if let Some(sha) = &self.sha {
+            query.push_str(&format!("&sha={sha}"));
+        }
+        for (name, value) in
+            [("stat", self.stat), ("verification", self.verification), ("files", self.files)]
+        {
+            if let Some(value) = value {
+                query.push_str(&format!("&{name}={value}"));
+            }
+        }
+        query
+    }
+}
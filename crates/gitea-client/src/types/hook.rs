@@ -62,6 +62,17 @@ pub fn matching(hook: &Hook, req: &CreateHookRequest) -> bool {
         hook.config.get("content_type") == req.config.get("content_type")
 }
 
+/// Checks if a hook delivers to the same receiver as a create request, i.e.
+/// has the same type, events and authorization, but no longer matches it,
+/// e.g. after the receiving endpoint moved. Such a hook is patched in place
+/// instead of adding a second one.
+pub fn outdated(hook: &Hook, req: &CreateHookRequest) -> bool {
+    hook.kind == req.kind &&
+        hook.events == req.events &&
+        hook.authorization_header == req.authorization_header &&
+        !matching(hook, req)
+}
+
 /// Request body for creating a hook.
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct CreateHookRequest {
@@ -85,6 +96,43 @@ pub struct CreateHookRequest {
     pub kind: String,
 }
 
+/// Request body for editing a hook. Properties left unset are not changed.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct EditHookRequest {
+    /// Indicates whether the hook is active.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+
+    /// Authorization header for the hook.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorization_header: Option<String>,
+
+    /// Branch filter for the hook.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch_filter: Option<String>,
+
+    /// Configuration for the hook.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<HashMap<String, String>>,
+
+    /// Events that trigger the hook.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<String>>,
+}
+
+impl From<CreateHookRequest> for EditHookRequest {
+    /// Edits a hook to the full configuration of a create request.
+    fn from(req: CreateHookRequest) -> Self {
+        Self {
+            active: Some(req.active),
+            authorization_header: req.authorization_header,
+            branch_filter: req.branch_filter,
+            config: Some(req.config),
+            events: Some(req.events),
+        }
+    }
+}
+
 /// Represents the type of hooks to list.
 #[derive(Debug, Clone, Copy, Default)]
 pub enum HookType {
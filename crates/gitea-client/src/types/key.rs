@@ -0,0 +1,57 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A deploy key granting access to a single repository.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeployKey {
+    /// Unique identifier of the key.
+    pub id: i64,
+
+    /// Identifier of the key among the public keys of the server.
+    pub key_id: i64,
+
+    /// The public key, in OpenSSH format.
+    pub key: String,
+
+    /// URL of the key in the API.
+    pub url: String,
+
+    /// Title of the key.
+    pub title: String,
+
+    /// Fingerprint of the key.
+    pub fingerprint: String,
+
+    /// Timestamp when the key was added.
+    pub created_at: DateTime<Utc>,
+
+    /// Indicates whether the key can only be used to pull.
+    pub read_only: bool,
+}
+
+/// Request body for adding a deploy key to a repository.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct CreateKeyRequest {
+    /// The public key, in OpenSSH format.
+    pub key: String,
+
+    /// Indicates whether the key can only be used to pull.
+    pub read_only: bool,
+
+    /// Title of the key.
+    pub title: String,
+}
@@ -12,19 +12,25 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod branch;
 mod commit;
 mod event;
 mod hook;
+mod key;
 mod organization;
+mod pagination;
 mod repository;
 mod team;
 mod user;
 
 // Re-exports
+pub use branch::*;
 pub use commit::*;
 pub use event::*;
 pub use hook::*;
+pub use key::*;
 pub use organization::*;
+pub use pagination::*;
 pub use repository::*;
 pub use team::*;
 pub use user::*;
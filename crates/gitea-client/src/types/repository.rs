@@ -359,6 +359,93 @@ pub struct GenerateRepositoryRequest {
     pub webhooks: Option<bool>,
 }
 
+/// Request body for editing the properties of a repository. Properties left
+/// unset are not changed.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct EditRepositoryRequest {
+    /// Whether the repository is archived.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived: Option<bool>,
+
+    /// A description of the repository.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// The new name of the repository.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Whether the repository is private.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub private: Option<bool>,
+}
+
+/// Request body for transferring a repository to another owner.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct TransferRepositoryRequest {
+    /// The user or organization to transfer the repository to.
+    pub new_owner: String,
+
+    /// IDs of the teams of the new organization to grant access to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team_ids: Option<Vec<i64>>,
+}
+
+/// Request body for migrating a repository from another service.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct MigrateRepoRequest {
+    /// Password used to authenticate against the source.
+    pub auth_password: Option<String>,
+
+    /// Access token used to authenticate against the source.
+    pub auth_token: Option<String>,
+
+    /// Username used to authenticate against the source.
+    pub auth_username: Option<String>,
+
+    /// The clone URL of the source repository.
+    pub clone_addr: String,
+
+    /// A description of the new repository.
+    pub description: Option<String>,
+
+    /// Whether to migrate the issues.
+    pub issues: Option<bool>,
+
+    /// Whether to migrate the labels.
+    pub labels: Option<bool>,
+
+    /// Whether to migrate the LFS objects.
+    pub lfs: Option<bool>,
+
+    /// Whether to migrate the milestones.
+    pub milestones: Option<bool>,
+
+    /// Whether the new repository is a pull mirror of the source.
+    pub mirror: Option<bool>,
+
+    /// Whether the new repository should be private.
+    pub private: Option<bool>,
+
+    /// Whether to migrate the pull requests.
+    pub pull_requests: Option<bool>,
+
+    /// Whether to migrate the releases.
+    pub releases: Option<bool>,
+
+    /// The name of the new repository.
+    pub repo_name: String,
+
+    /// The organization or person who will own the new repository.
+    pub repo_owner: Option<String>,
+
+    /// The kind of the source service, e.g. "git" or "gitea".
+    pub service: Option<String>,
+
+    /// Whether to migrate the wiki.
+    pub wiki: Option<bool>,
+}
+
 /// A partial representation of a repository,
 /// containing only the most essential fields.
 #[derive(Debug, Serialize, Deserialize)]
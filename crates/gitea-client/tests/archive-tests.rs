@@ -0,0 +1,52 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Repository archives against a mocked Gitea API.
+
+use futures_util::TryStreamExt;
+use gitea_client::{ClientError, GiteaClient};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+#[tokio::test]
+async fn test_archive_is_streamed() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/stackclass/redis/archive/0123abc.tar.gz"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(b"tarball".to_vec()))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap();
+    let stream = client.get_archive("stackclass", "redis", "0123abc").await.unwrap();
+    let chunks: Vec<_> = stream.try_collect().await.unwrap();
+    assert_eq!(chunks.concat(), b"tarball");
+}
+
+#[tokio::test]
+async fn test_missing_reference_is_not_found() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/stackclass/redis/archive/gone.tar.gz"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let client = GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap();
+    let result = client.get_archive("stackclass", "redis", "gone").await;
+    assert!(matches!(result, Err(ClientError::NotFound)));
+}
@@ -0,0 +1,95 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Branch protection rules against a mocked Gitea API.
+
+use gitea_client::{ClientError, GiteaClient, types::CreateBranchProtectionRequest};
+use serde_json::{Value, json};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_json, method, path},
+};
+
+const TIMESTAMP: &str = "2025-01-01T00:00:00Z";
+
+fn protection(name: &str) -> Value {
+    json!({
+        "rule_name": name,
+        "branch_name": name,
+        "enable_push": true,
+        "enable_push_whitelist": false,
+        "push_whitelist_usernames": null,
+        "enable_force_push": true,
+        "enable_force_push_allowlist": true,
+        "force_push_allowlist_usernames": ["admin"],
+        "created_at": TIMESTAMP,
+        "updated_at": TIMESTAMP
+    })
+}
+
+fn client(server: &MockServer) -> GiteaClient {
+    GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap()
+}
+
+#[tokio::test]
+async fn test_create_branch_protection() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/repos/stackclass/redis/branch_protections"))
+        .and(body_json(json!({
+            "rule_name": "main",
+            "enable_push": true,
+            "enable_force_push": true,
+            "enable_force_push_allowlist": true,
+            "force_push_allowlist_usernames": ["admin"]
+        })))
+        .respond_with(ResponseTemplate::new(201).set_body_json(protection("main")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let req = CreateBranchProtectionRequest {
+        rule_name: "main".to_string(),
+        enable_push: Some(true),
+        enable_force_push: Some(true),
+        enable_force_push_allowlist: Some(true),
+        force_push_allowlist_usernames: Some(vec!["admin".to_string()]),
+        ..Default::default()
+    };
+    let rule = client(&server).create_branch_protection("stackclass", "redis", req).await.unwrap();
+    assert_eq!(rule.rule_name, "main");
+    assert_eq!(rule.force_push_allowlist_usernames, Some(vec!["admin".to_string()]));
+}
+
+#[tokio::test]
+async fn test_get_branch_protection() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/stackclass/redis/branch_protections/main"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(protection("main")))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/stackclass/redis/branch_protections/dev"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let rule = client.get_branch_protection("stackclass", "redis", "main").await.unwrap();
+    assert!(rule.enable_push && rule.push_whitelist_usernames.is_none());
+
+    let result = client.get_branch_protection("stackclass", "redis", "dev").await;
+    assert!(matches!(result, Err(ClientError::NotFound)));
+}
@@ -0,0 +1,101 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! HTTP and TLS options of clients built with `GiteaClientBuilder`.
+
+use std::time::Duration;
+
+use gitea_client::{ClientError, GiteaClient, RetryPolicy};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{header, method, path},
+};
+
+/// A self-signed certificate authority.
+const CA_CERT: &str = "-----BEGIN CERTIFICATE-----
+MIIBkTCCATegAwIBAgIUDXoTgm72MK5Fmdsk4gG09RmOvK8wCgYIKoZIzj0EAwIw
+HTEbMBkGA1UEAwwSU3RhY2tDbGFzcyBUZXN0IENBMCAXDTI2MTAxNjIwNTIwMloY
+DzIxMjYwOTIyMjA1MjAyWjAdMRswGQYDVQQDDBJTdGFja0NsYXNzIFRlc3QgQ0Ew
+WTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAR6QdgBATJP+Q3y+hzabNLevsBImTRl
+JeLkB6Q3hVDsoWEKnaKn1nQOmILBPG9NF4c6seKcvY1D/VWknqr8nlLQo1MwUTAd
+BgNVHQ4EFgQUNc04WLAHXw4nUUvwdoD42s/jE3YwHwYDVR0jBBgwFoAUNc04WLAH
+Xw4nUUvwdoD42s/jE3YwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBF
+AiEAwowaMfF9f+wV3euMi1CHye5/ZeNXfAzl60YM4BY1HxACIAEN/MGlez9tUr0P
+Y5EyBD9f7XjwwQHTJO3NKcWXif2s
+-----END CERTIFICATE-----
+";
+
+#[tokio::test]
+async fn test_hung_server_times_out() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v1/repos/stackclass/redis"))
+        .respond_with(ResponseTemplate::new(204).set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+
+    let client = GiteaClient::builder(server.uri())
+        .basic_auth("admin".into(), "password".into())
+        .request_timeout(Duration::from_millis(100))
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap();
+
+    let result = client.delete_repository("stackclass", "redis").await;
+    assert!(matches!(result, Err(ClientError::Network(e)) if e.is_timeout()));
+}
+
+#[tokio::test]
+async fn test_builder_authenticates_with_token() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v1/repos/stackclass/redis"))
+        .and(header("authorization", "token secret"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = GiteaClient::builder(server.uri())
+        .token("secret".into())
+        .ca_cert_pem(CA_CERT.as_bytes().to_vec())
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    client.delete_repository("stackclass", "redis").await.unwrap();
+}
+
+#[test]
+fn test_invalid_ca_cert_is_rejected() {
+    let result = GiteaClient::builder("http://git.local".into())
+        .ca_cert_pem(
+            b"-----BEGIN CERTIFICATE-----\nnot base64\n-----END CERTIFICATE-----\n".to_vec(),
+        )
+        .build();
+    assert!(matches!(result, Err(ClientError::InvalidCertificate(_))));
+
+    let result = GiteaClient::builder("http://git.local".into()).ca_cert_pem(b"".to_vec()).build();
+    assert!(matches!(result, Err(ClientError::InvalidCertificate(_))));
+}
+
+#[test]
+fn test_builder_validates_credentials() {
+    let result = GiteaClient::builder("http://git.local".into()).token(String::new()).build();
+    assert!(matches!(result, Err(ClientError::InvalidCredentials(_))));
+
+    let result = GiteaClient::builder("http://git.local".into())
+        .basic_auth("admin".into(), "password\n".into())
+        .build();
+    assert!(matches!(result, Err(ClientError::InvalidCredentials(_))));
+}
@@ -0,0 +1,115 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Commit history against a mocked Gitea API.
+
+use gitea_client::{
+    ClientError, GiteaClient,
+    types::{ListCommitsOptions, Pagination},
+};
+use serde_json::{Value, json};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path, query_param},
+};
+
+fn commit(sha: &str, message: &str, date: &str) -> Value {
+    let user = json!({ "name": "Alice", "email": "alice@example.com", "date": date });
+    json!({
+        "sha": sha,
+        "url": "",
+        "html_url": format!("https://git.example.com/stackclass/redis/commit/{sha}"),
+        "created": date,
+        "commit": {
+            "message": message,
+            "author": user,
+            "committer": user,
+            "url": "",
+            "tree": { "sha": "", "url": "", "created": date },
+        },
+        "author": null,
+        "committer": null,
+        "parents": [],
+    })
+}
+
+fn client(server: &MockServer) -> GiteaClient {
+    GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap()
+}
+
+#[tokio::test]
+async fn test_list_latest_commit() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/stackclass/redis/commits"))
+        .and(query_param("page", "1"))
+        .and(query_param("limit", "1"))
+        .and(query_param("stat", "false"))
+        .and(query_param("verification", "false"))
+        .and(query_param("files", "false"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![commit(
+            "abc123",
+            "Pass stage 2\n",
+            "2025-01-02T03:04:05+02:00",
+        )]))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let commits = client(&server)
+        .list_commits("stackclass", "redis", ListCommitsOptions::latest())
+        .await
+        .unwrap();
+    assert_eq!(commits.len(), 1);
+    assert_eq!(commits[0].sha, "abc123");
+    assert_eq!(commits[0].message(), "Pass stage 2\n");
+    assert_eq!(commits[0].committed_at().to_rfc3339(), "2025-01-02T01:04:05+00:00");
+}
+
+#[tokio::test]
+async fn test_list_commits_of_reference() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/stackclass/redis/commits"))
+        .and(query_param("sha", "main"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(Vec::<Value>::new()))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let options = ListCommitsOptions {
+        sha: Some("main".to_string()),
+        pagination: Pagination::default().next(),
+        ..Default::default()
+    };
+    let commits = client(&server).list_commits("stackclass", "redis", options).await.unwrap();
+    assert!(commits.is_empty());
+}
+
+#[tokio::test]
+async fn test_list_commits_of_empty_repository() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/stackclass/redis/commits"))
+        .respond_with(
+            ResponseTemplate::new(409)
+                .set_body_json(json!({ "message": "Git Repository is empty." })),
+        )
+        .mount(&server)
+        .await;
+
+    let result = client(&server).list_commits("stackclass", "redis", Default::default()).await;
+    assert!(matches!(result, Err(ClientError::Conflict(_))));
+}
@@ -0,0 +1,108 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Gitea API fixtures shared by the mocked server tests.
+
+#![allow(dead_code)]
+
+use serde_json::{Value, json};
+
+/// Time of all records of the mocked Gitea server.
+pub const TIMESTAMP: &str = "2025-01-01T00:00:00Z";
+
+/// A user as the Gitea API returns it.
+pub fn user(login: &str) -> Value {
+    json!({
+        "active": true,
+        "avatar_url": "",
+        "created": TIMESTAMP,
+        "description": "",
+        "email": "",
+        "followers_count": 0,
+        "following_count": 0,
+        "full_name": "",
+        "html_url": format!("https://git.example.com/{login}"),
+        "id": 1,
+        "is_admin": false,
+        "language": "",
+        "last_login": TIMESTAMP,
+        "location": "",
+        "login": login,
+        "login_name": "",
+        "prohibit_login": false,
+        "restricted": false,
+        "source_id": 0,
+        "starred_repos_count": 0,
+        "username": login,
+        "visibility": "public",
+        "website": ""
+    })
+}
+
+/// A repository as the Gitea API returns it.
+pub fn repository(owner: &str, name: &str) -> Value {
+    json!({
+        "allow_fast_forward_only_merge": false,
+        "allow_merge_commits": true,
+        "allow_rebase": true,
+        "allow_rebase_explicit": true,
+        "allow_rebase_update": true,
+        "allow_squash_merge": true,
+        "archived": false,
+        "avatar_url": "",
+        "clone_url": format!("https://git.example.com/{owner}/{name}.git"),
+        "created_at": TIMESTAMP,
+        "default_allow_maintainer_edit": false,
+        "default_branch": "main",
+        "default_delete_branch_after_merge": false,
+        "default_merge_style": "merge",
+        "description": "",
+        "empty": false,
+        "fork": false,
+        "forks_count": 0,
+        "full_name": format!("{owner}/{name}"),
+        "has_issues": false,
+        "has_projects": false,
+        "has_pull_requests": false,
+        "has_wiki": false,
+        "html_url": format!("https://git.example.com/{owner}/{name}"),
+        "id": 42,
+        "ignore_whitespace_conflicts": false,
+        "internal": false,
+        "language": "",
+        "languages_url": "",
+        "licenses": [],
+        "link": "",
+        "mirror": false,
+        "mirror_interval": "",
+        "name": name,
+        "object_format_name": "sha1",
+        "open_issues_count": 0,
+        "open_pr_counter": 0,
+        "original_url": "",
+        "owner": user(owner),
+        "private": true,
+        "projects_mode": "all",
+        "release_counter": 0,
+        "size": 128,
+        "ssh_url": "",
+        "stars_count": 0,
+        "template": false,
+        "topics": [],
+        "updated_at": TIMESTAMP,
+        "url": "",
+        "watchers_count": 0,
+        "website": ""
+    })
+}
@@ -0,0 +1,63 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Repository and organization deletion against a mocked Gitea API.
+
+use gitea_client::{ClientError, GiteaClient};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+#[tokio::test]
+async fn test_delete_repository() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v1/repos/stackclass/redis"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap();
+    client.delete_repository("stackclass", "redis").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_delete_organization() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v1/orgs/stackclass"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap();
+    client.delete_organization("stackclass").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_delete_missing_organization_is_not_found() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v1/orgs/gone"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let client = GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap();
+    let result = client.delete_organization("gone").await;
+    assert!(matches!(result, Err(ClientError::NotFound)));
+}
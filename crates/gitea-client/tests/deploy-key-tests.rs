@@ -0,0 +1,100 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deploy keys of repositories against a mocked Gitea API.
+
+use gitea_client::{ClientError, GiteaClient, types::CreateKeyRequest};
+use serde_json::{Value, json};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_json, method, path},
+};
+
+const KEY: &str =
+    "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHtwo7ThLlQIzZ4ZBdj1BPf5UZSqy5CCy+mmp3sNcRqN";
+
+fn deploy_key() -> Value {
+    json!({
+        "id": 3,
+        "key_id": 12,
+        "key": KEY,
+        "url": "https://git.example.com/api/v1/repos/stackclass/redis/keys/3",
+        "title": "stackclass-pipeline",
+        "fingerprint": "SHA256:bS0Y2iEbWjXkYk2VbGJ9m2gl3uuAo1hBpTZTvTY2D7w",
+        "created_at": "2025-01-01T00:00:00Z",
+        "read_only": true,
+    })
+}
+
+#[tokio::test]
+async fn test_deploy_keys() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/repos/stackclass/redis/keys"))
+        .and(body_json(json!({
+            "key": KEY,
+            "read_only": true,
+            "title": "stackclass-pipeline",
+        })))
+        .respond_with(ResponseTemplate::new(201).set_body_json(deploy_key()))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/stackclass/redis/keys"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![deploy_key()]))
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v1/repos/stackclass/redis/keys/3"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap();
+    let req = CreateKeyRequest {
+        key: KEY.to_string(),
+        read_only: true,
+        title: "stackclass-pipeline".to_string(),
+    };
+    let created = client.create_deploy_key("stackclass", "redis", req).await.unwrap();
+    assert!(created.read_only);
+
+    let keys = client.list_deploy_keys("stackclass", "redis").await.unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].key, KEY);
+    client.delete_deploy_key("stackclass", "redis", created.id).await.unwrap();
+
+    let result = client.list_deploy_keys("stackclass", "missing").await;
+    assert!(matches!(result, Err(ClientError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_create_duplicate_deploy_key() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/repos/stackclass/redis/keys"))
+        .respond_with(
+            ResponseTemplate::new(422)
+                .set_body_json(json!({ "message": "Key content has been used as non-deploy key" })),
+        )
+        .mount(&server)
+        .await;
+
+    let client = GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap();
+    let req = CreateKeyRequest { key: KEY.to_string(), ..Default::default() };
+    let result = client.create_deploy_key("stackclass", "redis", req).await;
+    assert!(matches!(result, Err(ClientError::ValidationError(_))));
+}
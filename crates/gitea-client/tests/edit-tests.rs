@@ -0,0 +1,112 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Repository edits and transfers against a mocked Gitea API.
+
+#![recursion_limit = "256"]
+
+mod common;
+
+use gitea_client::{ClientError, GiteaClient, types::EditRepositoryRequest};
+use serde_json::{Value, json};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_json, method, path},
+};
+
+use common::repository;
+
+fn request() -> EditRepositoryRequest {
+    EditRepositoryRequest { name: Some("redis-merged".to_string()), ..Default::default() }
+}
+
+async fn setup(status: u16, body: Value) -> (MockServer, GiteaClient) {
+    let server = MockServer::start().await;
+    Mock::given(method("PATCH"))
+        .and(path("/api/v1/repos/stackclass/redis"))
+        .and(body_json(json!({ "name": "redis-merged" })))
+        .respond_with(ResponseTemplate::new(status).set_body_json(body))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client =
+        GiteaClient::new(server.uri(), "admin".to_string(), "password".to_string()).unwrap();
+    (server, client)
+}
+
+#[tokio::test]
+async fn test_rename_repository() {
+    let (_server, client) = setup(200, repository("stackclass", "redis-merged")).await;
+
+    let repo = client.edit_repository("stackclass", "redis", request()).await.unwrap();
+    assert_eq!(repo.full_name, "stackclass/redis-merged");
+}
+
+#[tokio::test]
+async fn test_rename_repository_not_found() {
+    let (_server, client) = setup(404, json!({ "message": "Not Found" })).await;
+
+    let err = client.edit_repository("stackclass", "redis", request()).await.unwrap_err();
+    assert!(matches!(err, ClientError::NotFound), "{err}");
+}
+
+#[tokio::test]
+async fn test_archive_repository() {
+    let server = MockServer::start().await;
+    let mut archived = repository("stackclass", "redis");
+    archived["archived"] = json!(true);
+    Mock::given(method("PATCH"))
+        .and(path("/api/v1/repos/stackclass/redis"))
+        .and(body_json(json!({ "archived": true })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(archived))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap();
+    let repo = client.archive_repository("stackclass", "redis").await.unwrap();
+    assert!(repo.archived);
+}
+
+#[tokio::test]
+async fn test_transfer_repository() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/repos/stackclass/redis/transfer"))
+        .and(body_json(json!({ "new_owner": "stackclass-next" })))
+        .respond_with(
+            ResponseTemplate::new(202).set_body_json(repository("stackclass-next", "redis")),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/repos/stackclass/git/transfer"))
+        .respond_with(
+            ResponseTemplate::new(422)
+                .set_body_json(json!({ "message": "repository already exists" })),
+        )
+        .mount(&server)
+        .await;
+
+    let client = GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap();
+    let repo = client.transfer_repository("stackclass", "redis", "stackclass-next").await.unwrap();
+    assert_eq!(repo.full_name, "stackclass-next/redis");
+
+    let result = client.transfer_repository("stackclass", "git", "stackclass-next").await;
+    assert!(matches!(result, Err(ClientError::ValidationError(_))));
+    let result = client.transfer_repository("stackclass", "missing", "stackclass-next").await;
+    assert!(matches!(result, Err(ClientError::NotFound)));
+}
@@ -0,0 +1,178 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Organization and repository hooks against a mocked Gitea API.
+
+use std::collections::HashMap;
+
+use gitea_client::{
+    ClientError, GiteaClient,
+    types::{CreateHookRequest, EditHookRequest, Hook, matching, outdated},
+};
+use serde_json::{Value, json};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_json, method, path},
+};
+
+fn hook(url: &str, branch_filter: &str) -> Value {
+    json!({
+        "active": true,
+        "authorization_header": "Basic secret",
+        "branch_filter": branch_filter,
+        "config": { "content_type": "json", "url": url },
+        "created_at": "2025-01-01T00:00:00Z",
+        "events": ["push"],
+        "id": 7,
+        "type": "gitea",
+        "updated_at": "2025-01-01T00:00:00Z",
+    })
+}
+
+fn request(url: &str) -> CreateHookRequest {
+    CreateHookRequest {
+        active: true,
+        authorization_header: Some("Basic secret".to_string()),
+        branch_filter: Some("main".to_string()),
+        config: HashMap::from([
+            ("content_type".to_string(), "json".to_string()),
+            ("url".to_string(), url.to_string()),
+        ]),
+        events: vec!["push".to_string()],
+        kind: "gitea".to_string(),
+    }
+}
+
+#[test]
+fn test_outdated_hook() {
+    let req = request("https://api.example.com/v1/webhooks/gitea");
+
+    let current: Hook = serde_json::from_value(hook(&req.config["url"], "main")).unwrap();
+    assert!(matching(&current, &req));
+    assert!(!outdated(&current, &req));
+
+    let moved: Hook =
+        serde_json::from_value(hook("https://old.example.com/v1/webhooks/gitea", "main")).unwrap();
+    assert!(outdated(&moved, &req));
+
+    let filtered: Hook = serde_json::from_value(hook(&req.config["url"], "*")).unwrap();
+    assert!(outdated(&filtered, &req));
+
+    // A hook delivering to someone else is left alone.
+    let mut foreign = moved.clone();
+    foreign.authorization_header = None;
+    assert!(!outdated(&foreign, &req));
+}
+
+#[tokio::test]
+async fn test_update_org_hook() {
+    let url = "https://api.example.com/v1/webhooks/gitea";
+    let server = MockServer::start().await;
+    Mock::given(method("PATCH"))
+        .and(path("/api/v1/orgs/stackclass/hooks/7"))
+        .and(body_json(json!({
+            "active": true,
+            "authorization_header": "Basic secret",
+            "branch_filter": "main",
+            "config": { "content_type": "json", "url": url },
+            "events": ["push"],
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(hook(url, "main")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap();
+    let req = EditHookRequest::from(request(url));
+    let hook = client.update_org_hook("stackclass", 7, req).await.unwrap();
+    assert_eq!(hook.config["url"], url);
+}
+
+#[tokio::test]
+async fn test_update_org_hook_sends_only_set_properties() {
+    let server = MockServer::start().await;
+    Mock::given(method("PATCH"))
+        .and(path("/api/v1/orgs/stackclass/hooks/7"))
+        .and(body_json(json!({ "active": false })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(hook("https://x", "main")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap();
+    let req = EditHookRequest { active: Some(false), ..Default::default() };
+    client.update_org_hook("stackclass", 7, req).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_delete_org_hook() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v1/orgs/stackclass/hooks/7"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v1/orgs/stackclass/hooks/8"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let client = GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap();
+    client.delete_org_hook("stackclass", 7).await.unwrap();
+    let result = client.delete_org_hook("stackclass", 8).await;
+    assert!(matches!(result, Err(ClientError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_repo_hooks() {
+    let url = "https://api.example.com/v1/webhooks/gitea";
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/repos/stackclass/redis/hooks"))
+        .and(body_json(json!({
+            "active": true,
+            "authorization_header": "Basic secret",
+            "branch_filter": "main",
+            "config": { "content_type": "json", "url": url },
+            "events": ["push"],
+            "type": "gitea",
+        })))
+        .respond_with(ResponseTemplate::new(201).set_body_json(hook(url, "main")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/stackclass/redis/hooks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![hook(url, "main")]))
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v1/repos/stackclass/redis/hooks/7"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap();
+    let created = client.create_repo_hook("stackclass", "redis", request(url)).await.unwrap();
+    let hooks = client.list_repo_hooks("stackclass", "redis").await.unwrap();
+    assert_eq!(hooks.len(), 1);
+    assert!(matching(&hooks[0], &request(url)));
+    client.delete_repo_hook("stackclass", "redis", created.id).await.unwrap();
+
+    let result = client.list_repo_hooks("stackclass", "missing").await;
+    assert!(matches!(result, Err(ClientError::NotFound)));
+}
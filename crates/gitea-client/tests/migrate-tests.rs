@@ -0,0 +1,90 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Repository migrations against a mocked Gitea API.
+
+#![recursion_limit = "256"]
+
+mod common;
+
+use gitea_client::{ClientError, GiteaClient, types::MigrateRepoRequest};
+use serde_json::{Value, json};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_partial_json, header_exists, method, path},
+};
+
+use common::repository;
+
+fn request() -> MigrateRepoRequest {
+    MigrateRepoRequest {
+        clone_addr: "https://old.example.com/learners/redis.git".to_string(),
+        repo_name: "redis".to_string(),
+        repo_owner: Some("stackclass".to_string()),
+        auth_token: Some("secret".to_string()),
+        service: Some("gitea".to_string()),
+        mirror: Some(false),
+        ..Default::default()
+    }
+}
+
+async fn setup(status: u16, body: Value) -> (MockServer, GiteaClient) {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/repos/migrate"))
+        .and(header_exists("authorization"))
+        .and(body_partial_json(json!({
+            "clone_addr": "https://old.example.com/learners/redis.git",
+            "repo_owner": "stackclass",
+            "auth_token": "secret",
+        })))
+        .respond_with(ResponseTemplate::new(status).set_body_json(body))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client =
+        GiteaClient::new(server.uri(), "admin".to_string(), "password".to_string()).unwrap();
+    (server, client)
+}
+
+#[tokio::test]
+async fn test_migrate_repository() {
+    let mut migrated = repository("stackclass", "redis");
+    migrated["original_url"] = json!("https://old.example.com/learners/redis");
+    let (_server, client) = setup(201, migrated).await;
+
+    let repo = client.migrate_repository(request()).await.unwrap();
+    assert_eq!(repo.full_name, "stackclass/redis");
+    assert_eq!(repo.owner.login, "stackclass");
+    assert!(!repo.mirror);
+}
+
+#[tokio::test]
+async fn test_migrate_repository_conflict() {
+    let message = "The repository with the same name already exists.";
+    let (_server, client) = setup(409, json!({ "message": message })).await;
+
+    let err = client.migrate_repository(request()).await.unwrap_err();
+    assert!(matches!(err, ClientError::Conflict(ref m) if m == message), "{err}");
+}
+
+#[tokio::test]
+async fn test_migrate_repository_authentication_failed() {
+    let message = "Authentication failed: Remote Gitea rejected the credentials.";
+    let (_server, client) = setup(422, json!({ "message": message })).await;
+
+    let err = client.migrate_repository(request()).await.unwrap_err();
+    assert!(matches!(err, ClientError::ValidationError(ref m) if m == message), "{err}");
+}
@@ -0,0 +1,66 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Organization repository listing against a mocked Gitea API.
+
+#![recursion_limit = "256"]
+
+mod common;
+
+use gitea_client::{ClientError, GiteaClient, types::Pagination};
+use serde_json::Value;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path, query_param},
+};
+
+use common::repository;
+
+fn client(server: &MockServer) -> GiteaClient {
+    GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap()
+}
+
+#[tokio::test]
+async fn test_list_org_repositories() {
+    let server = MockServer::start().await;
+    let page: Vec<Value> =
+        ["redis", "git"].iter().map(|name| repository("stackclass", name)).collect();
+    Mock::given(method("GET"))
+        .and(path("/api/v1/orgs/stackclass/repos"))
+        .and(query_param("page", "2"))
+        .and(query_param("limit", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(page))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let pagination = Pagination::new(1, 2).next();
+    let repos = client(&server).list_org_repositories("stackclass", pagination).await.unwrap();
+    let names: Vec<_> = repos.iter().map(|repo| repo.name.as_str()).collect();
+    assert_eq!(names, ["redis", "git"]);
+    assert!(!pagination.is_last(repos.len()));
+}
+
+#[tokio::test]
+async fn test_list_repositories_of_missing_org() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/orgs/missing/repos"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let result = client(&server).list_org_repositories("missing", Pagination::default()).await;
+    assert!(matches!(result, Err(ClientError::NotFound)));
+}
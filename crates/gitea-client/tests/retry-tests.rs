@@ -0,0 +1,116 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retries of requests failing transiently, against a mocked Gitea API.
+
+use std::time::Duration;
+
+use gitea_client::{ClientError, GiteaClient, RetryPolicy, types::GenerateRepositoryRequest};
+use reqwest::StatusCode;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+/// A client retrying twice without waiting.
+fn client(server: &MockServer) -> GiteaClient {
+    let retry =
+        RetryPolicy { max_retries: 2, initial_backoff: Duration::ZERO, ..Default::default() };
+    GiteaClient::new(server.uri(), "admin".into(), "password".into())
+        .unwrap()
+        .with_retry_policy(retry)
+}
+
+#[tokio::test]
+async fn test_idempotent_request_is_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v1/repos/stackclass/redis"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .expect(2)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v1/repos/stackclass/redis"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    client(&server).delete_repository("stackclass", "redis").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_retries_are_bounded() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/stackclass/redis"))
+        .respond_with(ResponseTemplate::new(502))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let result = client(&server).get_repository("stackclass", "redis").await;
+    assert!(matches!(result, Err(ClientError::UnexpectedStatusCode(StatusCode::BAD_GATEWAY))));
+}
+
+#[tokio::test]
+async fn test_post_is_not_retried_after_a_response() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/repos/stackclass/redis/generate"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let request = GenerateRepositoryRequest::default();
+    let result = client(&server).generate_repository("stackclass", "redis", request).await;
+    let unavailable = StatusCode::SERVICE_UNAVAILABLE;
+    assert!(
+        matches!(result, Err(ClientError::UnexpectedStatusCode(status)) if status == unavailable)
+    );
+}
+
+#[tokio::test]
+async fn test_refused_connection_is_retried() {
+    // Nothing listens on the port until the server starts after a while
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let retry = RetryPolicy {
+        max_retries: 10,
+        initial_backoff: Duration::from_millis(50),
+        max_backoff: Duration::from_millis(100),
+    };
+    let client = GiteaClient::new(format!("http://{addr}"), "admin".into(), "password".into())
+        .unwrap()
+        .with_retry_policy(retry);
+
+    // Without mocks the server answers 404, which tells the request got through
+    let server = async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let listener = std::net::TcpListener::bind(addr).unwrap();
+        MockServer::builder().listener(listener).start().await
+    };
+
+    let request = GenerateRepositoryRequest::default();
+    let (result, server) =
+        tokio::join!(client.generate_repository("stackclass", "redis", request), server);
+    assert!(matches!(result, Err(ClientError::NotFound)));
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}
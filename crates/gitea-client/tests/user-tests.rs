@@ -0,0 +1,132 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! User management against a mocked Gitea API.
+
+#![recursion_limit = "256"]
+
+mod common;
+
+use gitea_client::{ClientError, GiteaClient, types::CreateUserRequest};
+use serde_json::{Value, json};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_partial_json, method, path, query_param},
+};
+
+use common::user;
+
+fn client(server: &MockServer) -> GiteaClient {
+    GiteaClient::new(server.uri(), "admin".into(), "password".into()).unwrap()
+}
+
+#[tokio::test]
+async fn test_create_user() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/admin/users"))
+        .and(body_partial_json(json!({ "username": "alice", "email": "alice@example.com" })))
+        .respond_with(ResponseTemplate::new(201).set_body_json(user("alice")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let req = CreateUserRequest {
+        email: "alice@example.com".to_string(),
+        username: "alice".to_string(),
+        ..Default::default()
+    };
+    let user = client(&server).create_user(req).await.unwrap();
+    assert_eq!(user.login, "alice");
+}
+
+#[tokio::test]
+async fn test_create_invalid_user_is_rejected() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/admin/users"))
+        .respond_with(
+            ResponseTemplate::new(422).set_body_json(json!({ "message": "user already exists" })),
+        )
+        .mount(&server)
+        .await;
+
+    let req = CreateUserRequest { username: "alice".to_string(), ..Default::default() };
+    let result = client(&server).create_user(req).await;
+    assert!(matches!(result, Err(ClientError::ValidationError(m)) if m == "user already exists"));
+}
+
+#[tokio::test]
+async fn test_get_user() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/users/alice"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(user("alice")))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/users/bob"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    assert_eq!(client.get_user("alice").await.unwrap().login, "alice");
+    assert!(matches!(client.get_user("bob").await, Err(ClientError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_delete_user() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v1/admin/users/alice"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v1/admin/users/bob"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    client.delete_user("alice").await.unwrap();
+    assert!(matches!(client.delete_user("bob").await, Err(ClientError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_list_users_fetches_every_page() {
+    let server = MockServer::start().await;
+    let first: Vec<Value> = (0..50).map(|i| user(&format!("user{i}"))).collect();
+    Mock::given(method("GET"))
+        .and(path("/api/v1/admin/users"))
+        .and(query_param("page", "1"))
+        .and(query_param("limit", "50"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(first))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/admin/users"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![user("last")]))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let users = client(&server).list_users().await.unwrap();
+    assert_eq!(users.len(), 51);
+    assert_eq!(users[50].login, "last");
+}
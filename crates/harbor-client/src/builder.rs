@@ -0,0 +1,118 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use reqwest::{Certificate, Client};
+
+use crate::{client::HarborClient, error::ClientError, retry::RetryPolicy};
+
+/// Time allowed to establish a connection unless configured otherwise.
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Builds a [`HarborClient`] with custom HTTP and TLS options.
+///
+/// Connections time out after 10 seconds by default, while requests have
+/// no timeout.
+pub struct HarborClientBuilder {
+    endpoint: String,
+    username: String,
+    password: String,
+    connect_timeout: Duration,
+    request_timeout: Option<Duration>,
+    accept_invalid_certs: bool,
+    ca_cert: Option<Vec<u8>>,
+    retry: RetryPolicy,
+}
+
+impl HarborClientBuilder {
+    /// Starts building a client of the Harbor server at the given endpoint,
+    /// authenticating with a username and password.
+    pub fn new(endpoint: String, username: String, password: String) -> Self {
+        Self {
+            endpoint,
+            username,
+            password,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            request_timeout: None,
+            accept_invalid_certs: false,
+            ca_cert: None,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Sets the time allowed to establish a connection.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Sets the time allowed for a whole request, from connecting until the
+    /// response body is read.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Accepts any server certificate, even expired or self-signed ones.
+    /// Only meant for development, as it allows man-in-the-middle attacks.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Trusts the PEM encoded certificates of private certificate
+    /// authorities, on top of the system roots.
+    pub fn ca_cert_pem(mut self, pem: Vec<u8>) -> Self {
+        self.ca_cert = Some(pem);
+        self
+    }
+
+    /// Sets how requests failing transiently are retried.
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Builds the client. An unreadable CA certificate is rejected with
+    /// [`ClientError::InvalidCertificate`].
+    pub fn build(self) -> Result<HarborClient, ClientError> {
+        // reqwest verifies certificates with the process-wide rustls crypto
+        // provider, ring being the one built in
+        let _ = rustls::crypto::ring::default_provider().install_default();
+
+        let mut builder = Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .tls_danger_accept_invalid_certs(self.accept_invalid_certs);
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(pem) = &self.ca_cert {
+            let certs = Certificate::from_pem_bundle(pem)
+                .map_err(|e| ClientError::InvalidCertificate(e.to_string()))?;
+            if certs.is_empty() {
+                return Err(ClientError::InvalidCertificate("no certificate found".into()));
+            }
+            builder = builder.tls_certs_merge(certs);
+        }
+
+        Ok(HarborClient {
+            client: builder.build()?,
+            base_url: format!("{}/api/v2.0", self.endpoint),
+            username: self.username,
+            password: self.password,
+            retry: self.retry,
+        })
+    }
+}
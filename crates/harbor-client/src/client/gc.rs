@@ -0,0 +1,109 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use reqwest::{StatusCode, header::LOCATION};
+
+use crate::{
+    client::HarborClient,
+    error::{ClientError, Result},
+    types::{GcJob, GcScheduleRequest, ScheduleObj},
+};
+
+impl HarborClient {
+    /// Trigger a garbage collection right away, returning the ID of its job.
+    ///
+    /// # Possible Responses
+    /// - 201: Garbage collection triggered successfully.
+    /// - 400: Bad request.
+    /// - 401: Unauthorized.
+    /// - 403: Forbidden.
+    /// - 409: Conflict (a garbage collection is already running).
+    /// - 500: Internal server error.
+    ///
+    /// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+    pub async fn trigger_garbage_collection(&self) -> Result<i64> {
+        let request = GcScheduleRequest::new(ScheduleObj::manual());
+        let response = self.post("system/gc/schedule", &request).await?;
+
+        match response.status() {
+            StatusCode::CREATED => {
+                // The ID is only returned as the last segment of the location
+                let location = response.headers().get(LOCATION).and_then(|v| v.to_str().ok());
+                location
+                    .and_then(|location| location.rsplit('/').next())
+                    .and_then(|id| id.parse().ok())
+                    .ok_or_else(|| {
+                        ClientError::InvalidResponse(format!("invalid location: {location:?}"))
+                    })
+            }
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Get the status of a garbage collection job by ID.
+    ///
+    /// # Possible Responses
+    /// - 200: Job returned successfully.
+    /// - 401: Unauthorized.
+    /// - 403: Forbidden.
+    /// - 404: Job not found.
+    /// - 500: Internal server error.
+    ///
+    /// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+    pub async fn get_gc_status(&self, id: i64) -> Result<GcJob> {
+        let response = self.get(&format!("system/gc/{id}")).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Get the schedule of the periodic garbage collection.
+    ///
+    /// # Possible Responses
+    /// - 200: Schedule returned successfully.
+    /// - 401: Unauthorized.
+    /// - 403: Forbidden.
+    /// - 500: Internal server error.
+    ///
+    /// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+    pub async fn get_gc_schedule(&self) -> Result<GcJob> {
+        let response = self.get("system/gc/schedule").await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Replace the schedule of the periodic garbage collection.
+    ///
+    /// # Possible Responses
+    /// - 200: Schedule updated successfully.
+    /// - 400: Bad request (invalid cron expression).
+    /// - 401: Unauthorized.
+    /// - 403: Forbidden.
+    /// - 500: Internal server error.
+    ///
+    /// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+    pub async fn set_gc_schedule(&self, request: GcScheduleRequest) -> Result<()> {
+        let response = self.put("system/gc/schedule", &request).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(()),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+}
@@ -12,59 +12,81 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod gc;
 pub mod project;
+pub mod repository;
+pub mod robot;
+pub mod webhook;
 
 use reqwest::{Client, Error, Response};
 use serde::Serialize;
 
+use crate::{builder::HarborClientBuilder, retry::RetryPolicy};
+
 /// A client for interacting with the Harbor API.
 pub struct HarborClient {
     pub(crate) client: Client,
     pub(crate) base_url: String,
     pub(crate) username: String,
     pub(crate) password: String,
+    pub(crate) retry: RetryPolicy,
 }
 
 impl HarborClient {
-    /// Creates a new `HarborClient` instance.
+    /// Creates a new `HarborClient` instance with the default options.
+    ///
+    /// # Panics
+    /// Like `reqwest::Client::new`, if the TLS backend can not be initialized.
     pub fn new(endpoint: String, username: String, password: String) -> Self {
-        HarborClient {
-            client: Client::new(),
-            base_url: format!("{endpoint}/api/v2.0"),
-            username,
-            password,
-        }
+        Self::builder(endpoint, username, password).build().expect("failed to build HarborClient")
+    }
+
+    /// Starts building a client with custom HTTP and TLS options.
+    pub fn builder(endpoint: String, username: String, password: String) -> HarborClientBuilder {
+        HarborClientBuilder::new(endpoint, username, password)
+    }
+
+    /// Sets how requests failing transiently are retried.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
     }
 
     /// Sends a GET request.
-    #[allow(dead_code)]
     pub(crate) async fn get(&self, path: &str) -> Result<Response, Error> {
         let url = format!("{}/{}", self.base_url, path);
-        self.client.get(&url).basic_auth(&self.username, Some(&self.password)).send().await
+        let request = self.client.get(&url).basic_auth(&self.username, Some(&self.password));
+        self.retry.send(request, true).await
     }
 
     /// Sends a HEAD request.
     #[allow(dead_code)]
     pub(crate) async fn head(&self, path: &str) -> Result<Response, Error> {
         let url = format!("{}/{}", self.base_url, path);
-        self.client.head(&url).basic_auth(&self.username, Some(&self.password)).send().await
+        let request = self.client.head(&url).basic_auth(&self.username, Some(&self.password));
+        self.retry.send(request, true).await
     }
 
     /// Sends a POST request with a JSON body.
     pub(crate) async fn post<T: Serialize>(&self, path: &str, body: &T) -> Result<Response, Error> {
         let url = format!("{}/{}", self.base_url, path);
-        self.client
-            .post(&url)
-            .basic_auth(&self.username, Some(&self.password))
-            .json(body)
-            .send()
-            .await
+        let request =
+            self.client.post(&url).basic_auth(&self.username, Some(&self.password)).json(body);
+        self.retry.send(request, false).await
+    }
+
+    /// Sends a PUT request with a JSON body.
+    pub(crate) async fn put<T: Serialize>(&self, path: &str, body: &T) -> Result<Response, Error> {
+        let url = format!("{}/{}", self.base_url, path);
+        let request =
+            self.client.put(&url).basic_auth(&self.username, Some(&self.password)).json(body);
+        self.retry.send(request, true).await
     }
 
     /// Sends a DELETE request.
-    #[allow(dead_code)]
     pub(crate) async fn delete(&self, path: &str) -> Result<Response, Error> {
         let url = format!("{}/{}", self.base_url, path);
-        self.client.delete(&url).basic_auth(&self.username, Some(&self.password)).send().await
+        let request = self.client.delete(&url).basic_auth(&self.username, Some(&self.password));
+        self.retry.send(request, true).await
     }
 }
@@ -17,7 +17,7 @@ use reqwest::StatusCode;
 use crate::{
     client::HarborClient,
     error::{ClientError, Result},
-    types::CreateProjectRequest,
+    types::{CreateProjectRequest, ProjectSummary, UpdateProjectRequest},
 };
 
 impl HarborClient {
@@ -56,4 +56,70 @@ impl HarborClient {
             _ => Err(ClientError::from_response(response).await),
         }
     }
+
+    /// Update a project by name or ID.
+    ///
+    /// # Possible Responses
+    /// - 200: Project updated successfully.
+    /// - 400: Bad request (invalid input format).
+    /// - 401: Unauthorized.
+    /// - 403: Forbidden.
+    /// - 404: Project not found.
+    /// - 409: Conflict (storage limit below the current usage).
+    /// - 500: Internal server error.
+    ///
+    /// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+    pub async fn update_project(
+        &self,
+        name_or_id: &str,
+        request: UpdateProjectRequest,
+    ) -> Result<()> {
+        let response = self.put(&format!("projects/{name_or_id}"), &request).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(()),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Delete a project by name or ID, which must hold no repositories.
+    ///
+    /// # Possible Responses
+    /// - 200: Project deleted successfully.
+    /// - 400: Bad request.
+    /// - 401: Unauthorized.
+    /// - 403: Forbidden.
+    /// - 404: Project not found.
+    /// - 412: Precondition failed (the project still holds repositories).
+    /// - 500: Internal server error.
+    ///
+    /// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+    pub async fn delete_project(&self, name_or_id: &str) -> Result<()> {
+        let response = self.delete(&format!("projects/{name_or_id}")).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(()),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Get the summary of a project by name or ID.
+    ///
+    /// # Possible Responses
+    /// - 200: Summary returned successfully.
+    /// - 400: Bad request.
+    /// - 401: Unauthorized.
+    /// - 403: Forbidden.
+    /// - 404: Project not found.
+    /// - 500: Internal server error.
+    ///
+    /// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+    pub async fn get_project_summary(&self, name_or_id: &str) -> Result<ProjectSummary> {
+        let response = self.get(&format!("projects/{name_or_id}/summary")).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
 }
@@ -0,0 +1,110 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use reqwest::StatusCode;
+
+use crate::{
+    client::HarborClient,
+    error::{ClientError, Result},
+    types::{Artifact, ArtifactQuery, Pagination, Repository, encode},
+};
+
+impl HarborClient {
+    /// List the repositories of a project.
+    ///
+    /// # Possible Responses
+    /// - 200: Repositories listed successfully.
+    /// - 400: Bad request.
+    /// - 401: Unauthorized.
+    /// - 403: Forbidden.
+    /// - 404: Project not found.
+    /// - 500: Internal server error.
+    ///
+    /// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+    pub async fn list_repositories(
+        &self,
+        project: &str,
+        pagination: Pagination,
+    ) -> Result<Vec<Repository>> {
+        let path = format!("projects/{}/repositories?{}", encode(project), pagination.query());
+        let response = self.get(&path).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// List the artifacts of a repository, where the repository is named
+    /// without its project.
+    ///
+    /// # Possible Responses
+    /// - 200: Artifacts listed successfully.
+    /// - 400: Bad request.
+    /// - 401: Unauthorized.
+    /// - 403: Forbidden.
+    /// - 404: Project or repository not found.
+    /// - 500: Internal server error.
+    ///
+    /// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+    pub async fn list_artifacts(
+        &self,
+        project: &str,
+        repository: &str,
+        query: &ArtifactQuery,
+    ) -> Result<Vec<Artifact>> {
+        let path = format!("{}/artifacts?{}", repository_path(project, repository), query.query());
+        let response = self.get(&path).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Delete an artifact of a repository by tag or digest, along with all
+    /// its tags.
+    ///
+    /// # Possible Responses
+    /// - 200: Artifact deleted successfully.
+    /// - 400: Bad request.
+    /// - 401: Unauthorized.
+    /// - 403: Forbidden.
+    /// - 404: Artifact not found.
+    /// - 500: Internal server error.
+    ///
+    /// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+    pub async fn delete_artifact(
+        &self,
+        project: &str,
+        repository: &str,
+        reference: &str,
+    ) -> Result<()> {
+        // Tags and digests consist of characters safe in a path
+        let path = format!("{}/artifacts/{reference}", repository_path(project, repository));
+        let response = self.delete(&path).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(()),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+}
+
+/// Path of a repository. Slashes in its name are encoded twice, as Harbor
+/// decodes the path before routing it.
+fn repository_path(project: &str, repository: &str) -> String {
+    let repository = encode(repository).replace("%2F", "%252F");
+    format!("projects/{}/repositories/{repository}", encode(project))
+}
@@ -0,0 +1,63 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use reqwest::StatusCode;
+
+use crate::{
+    client::HarborClient,
+    error::{ClientError, Result},
+    types::{CreateRobotRequest, RobotCreated},
+};
+
+impl HarborClient {
+    /// Create a new robot account.
+    ///
+    /// # Possible Responses
+    /// - 201: Robot account created successfully.
+    /// - 400: Bad request (invalid input format).
+    /// - 401: Unauthorized.
+    /// - 403: Forbidden.
+    /// - 404: Project not found.
+    /// - 500: Internal server error.
+    ///
+    /// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml#L5893
+    pub async fn create_robot(&self, request: CreateRobotRequest) -> Result<RobotCreated> {
+        let response = self.post("robots", &request).await?;
+
+        match response.status() {
+            StatusCode::CREATED => Ok(response.json().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Delete a robot account by ID.
+    ///
+    /// # Possible Responses
+    /// - 200: Robot account deleted successfully.
+    /// - 400: Bad request.
+    /// - 401: Unauthorized.
+    /// - 403: Forbidden.
+    /// - 404: Robot account not found.
+    /// - 500: Internal server error.
+    ///
+    /// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml#L5977
+    pub async fn delete_robot(&self, robot_id: i64) -> Result<()> {
+        let response = self.delete(&format!("robots/{robot_id}")).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(()),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+}
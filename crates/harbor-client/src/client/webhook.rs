@@ -0,0 +1,87 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use reqwest::StatusCode;
+
+use crate::{
+    client::HarborClient,
+    error::{ClientError, Result},
+    types::{CreateWebhookPolicyRequest, WebhookPolicy},
+};
+
+impl HarborClient {
+    /// Create a webhook policy of a project by name or ID.
+    ///
+    /// # Possible Responses
+    /// - 201: Webhook policy created successfully.
+    /// - 400: Bad request (invalid input format).
+    /// - 401: Unauthorized.
+    /// - 403: Forbidden.
+    /// - 409: Conflict (a policy of the same name exists).
+    /// - 500: Internal server error.
+    ///
+    /// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+    pub async fn create_webhook_policy(
+        &self,
+        project: &str,
+        request: CreateWebhookPolicyRequest,
+    ) -> Result<()> {
+        let response = self.post(&format!("projects/{project}/webhook/policies"), &request).await?;
+
+        match response.status() {
+            StatusCode::CREATED => Ok(()),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// List the webhook policies of a project by name or ID.
+    ///
+    /// # Possible Responses
+    /// - 200: Webhook policies returned successfully.
+    /// - 400: Bad request.
+    /// - 401: Unauthorized.
+    /// - 403: Forbidden.
+    /// - 404: Project not found.
+    /// - 500: Internal server error.
+    ///
+    /// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+    pub async fn list_webhook_policies(&self, project: &str) -> Result<Vec<WebhookPolicy>> {
+        let response = self.get(&format!("projects/{project}/webhook/policies")).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Delete a webhook policy of a project by ID.
+    ///
+    /// # Possible Responses
+    /// - 200: Webhook policy deleted successfully.
+    /// - 400: Bad request.
+    /// - 401: Unauthorized.
+    /// - 403: Forbidden.
+    /// - 404: Webhook policy not found.
+    /// - 500: Internal server error.
+    ///
+    /// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+    pub async fn delete_webhook_policy(&self, project: &str, id: i64) -> Result<()> {
+        let response = self.delete(&format!("projects/{project}/webhook/policies/{id}")).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(()),
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+}
@@ -23,6 +23,9 @@ pub enum ClientError {
     #[error("Network error: {0}")]
     Network(#[from] reqwest::Error),
 
+    #[error("Invalid certificate: {0}")]
+    InvalidCertificate(String),
+
     #[error("Bad request: {0}")]
     BadRequest(String),
 
@@ -53,6 +56,9 @@ pub enum ClientError {
     #[error("Internal server error: {0}")]
     InternalServerError(String),
 
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+
     #[error("Unexpected status code: {0}")]
     UnexpectedStatusCode(StatusCode),
 }
@@ -0,0 +1,99 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use reqwest::{Error, RequestBuilder, Response};
+
+/// How requests failing transiently are retried.
+///
+/// Idempotent requests are retried on connection errors and 5xx responses.
+/// Other requests are only retried when the connection could not be
+/// established, as the server may otherwise have acted on them already.
+/// Retries wait an exponentially growing backoff, with full jitter so that
+/// clients failing together do not retry together.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt
+    pub max_retries: u32,
+
+    /// Backoff before the first retry, doubled for every following one
+    pub initial_backoff: Duration,
+
+    /// Upper bound of the backoff
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy sending every request once.
+    pub fn none() -> Self {
+        Self { max_retries: 0, ..Default::default() }
+    }
+
+    /// Upper bound of the backoff before the given retry, starting at 0.
+    fn ceiling(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry);
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+
+    /// Sends a request, retrying it as allowed by the policy.
+    pub(crate) async fn send(
+        &self,
+        request: RequestBuilder,
+        idempotent: bool,
+    ) -> Result<Response, Error> {
+        let mut retry = 0;
+        loop {
+            // Requests with a streamed body can not be sent again
+            let Some(attempt) = request.try_clone().filter(|_| retry < self.max_retries) else {
+                return request.send().await;
+            };
+
+            match attempt.send().await {
+                Ok(res) if idempotent && res.status().is_server_error() => {}
+                Err(e) if e.is_connect() || (idempotent && e.is_request()) => {}
+                result => return result,
+            }
+
+            let ceiling = self.ceiling(retry).as_millis() as u64;
+            tokio::time::sleep(Duration::from_millis(fastrand::u64(0..=ceiling))).await;
+            retry += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ceiling() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.ceiling(0), Duration::from_millis(200));
+        assert_eq!(policy.ceiling(1), Duration::from_millis(400));
+        assert_eq!(policy.ceiling(3), Duration::from_millis(1600));
+        assert_eq!(policy.ceiling(4), Duration::from_secs(2));
+        assert_eq!(policy.ceiling(40), Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// When a job runs, e.g. a garbage collection.
+/// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ScheduleObj {
+    /// The type of the schedule, one of "Hourly", "Daily", "Weekly",
+    /// "Custom", "Manual", "None" or "Schedule".
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// The cron expression of a custom schedule, with a seconds field.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+
+    /// The next time the job is scheduled to run.
+    #[serde(skip_serializing)]
+    pub next_scheduled_time: Option<DateTime<Utc>>,
+}
+
+impl ScheduleObj {
+    /// Runs the job once, right away.
+    pub fn manual() -> Self {
+        Self { kind: "Manual".to_string(), ..Default::default() }
+    }
+
+    /// Runs the job periodically, as the cron expression says.
+    pub fn custom(cron: impl ToString) -> Self {
+        Self { kind: "Custom".to_string(), cron: Some(cron.to_string()), ..Default::default() }
+    }
+
+    /// Never runs the job periodically.
+    pub fn none() -> Self {
+        Self { kind: "None".to_string(), ..Default::default() }
+    }
+}
+
+/// Request body for triggering or scheduling a garbage collection.
+/// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcScheduleRequest {
+    /// When the garbage collection runs.
+    pub schedule: ScheduleObj,
+
+    /// The parameters of the garbage collection.
+    pub parameters: GcParameters,
+}
+
+impl GcScheduleRequest {
+    /// Collects garbage with the default parameters on the given schedule.
+    pub fn new(schedule: ScheduleObj) -> Self {
+        Self { schedule, parameters: GcParameters::default() }
+    }
+
+    /// Sets the parameters of the garbage collection.
+    pub fn with_parameters(mut self, parameters: GcParameters) -> Self {
+        self.parameters = parameters;
+        self
+    }
+}
+
+/// The parameters of a garbage collection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcParameters {
+    /// Whether to delete the untagged artifacts too.
+    pub delete_untagged: bool,
+
+    /// Whether to only report what would be deleted.
+    pub dry_run: bool,
+
+    /// The number of workers deleting blobs in parallel.
+    pub workers: u32,
+}
+
+impl Default for GcParameters {
+    fn default() -> Self {
+        Self { delete_untagged: true, dry_run: false, workers: 1 }
+    }
+}
+
+/// A garbage collection job, or the schedule of them.
+/// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcJob {
+    /// The ID of the job.
+    pub id: i64,
+
+    /// The name of the job.
+    pub job_name: Option<String>,
+
+    /// The kind of the job, "MANUAL" or "SCHEDULE".
+    pub job_kind: Option<String>,
+
+    /// The parameters of the job, as a JSON string.
+    pub job_parameters: Option<String>,
+
+    /// The schedule of the job.
+    pub schedule: Option<ScheduleObj>,
+
+    /// The status of the job, e.g. "Pending", "Running", "Success" or "Error".
+    pub job_status: Option<String>,
+
+    /// Whether the job is deleted.
+    #[serde(default)]
+    pub deleted: bool,
+
+    /// The creation time of the job.
+    pub creation_time: Option<DateTime<Utc>>,
+
+    /// The last update time of the job.
+    pub update_time: Option<DateTime<Utc>>,
+}
@@ -12,7 +12,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod gc;
+mod pagination;
 mod project;
+mod repository;
+mod robot;
+mod webhook;
 
 // Re-exports
+pub use gc::*;
+pub use pagination::*;
 pub use project::*;
+pub use repository::*;
+pub use robot::*;
+pub use webhook::*;
@@ -0,0 +1,52 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// A page of a listing, as requested from paged endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+    /// Number of the page, starting at 1.
+    pub page: u32,
+
+    /// Maximum number of items of the page. Harbor caps it at 100.
+    pub page_size: u32,
+}
+
+impl Pagination {
+    /// Creates the pagination of the given page.
+    pub fn new(page: u32, page_size: u32) -> Self {
+        Self { page, page_size }
+    }
+
+    /// Returns the page after this one.
+    pub fn next(self) -> Self {
+        Self { page: self.page + 1, ..self }
+    }
+
+    /// Whether a page with that many items is the last one.
+    pub fn is_last(&self, len: usize) -> bool {
+        len < self.page_size as usize
+    }
+
+    /// Formats the query string of the page.
+    pub(crate) fn query(&self) -> String {
+        format!("page={}&page_size={}", self.page, self.page_size)
+    }
+}
+
+impl Default for Pagination {
+    /// The first page of 10 items, as Harbor defaults to.
+    fn default() -> Self {
+        Self::new(1, 10)
+    }
+}
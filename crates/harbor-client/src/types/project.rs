@@ -67,6 +67,89 @@ impl CreateProjectRequest {
     }
 }
 
+/// Request body for updating a project. Properties left unset are not
+/// changed.
+/// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct UpdateProjectRequest {
+    /// deprecated, reserved for project creation in replication
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public: Option<bool>,
+
+    /// The metadata of the project.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ProjectMetadata>,
+
+    /// The CVE allowlist of the project.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cve_allowlist: Option<CVEAllowlist>,
+
+    /// The storage quota of the project in bytes, -1 for unlimited.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage_limit: Option<i64>,
+}
+
+impl UpdateProjectRequest {
+    /// Sets the storage quota of the project in bytes, -1 for unlimited.
+    pub fn with_storage_limit(mut self, bytes: i64) -> Self {
+        self.storage_limit = Some(bytes);
+        self
+    }
+
+    /// Sets the metadata of the project.
+    pub fn with_metadata(mut self, metadata: ProjectMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+}
+
+/// Summary of a project: its repositories, members and quota.
+/// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ProjectSummary {
+    /// The number of the repositories under this project.
+    #[serde(default)]
+    pub repo_count: i64,
+
+    /// The total number of project admin members.
+    #[serde(default)]
+    pub project_admin_count: i64,
+
+    /// The total number of maintainer members.
+    #[serde(default)]
+    pub maintainer_count: i64,
+
+    /// The total number of developer members.
+    #[serde(default)]
+    pub developer_count: i64,
+
+    /// The total number of guest members.
+    #[serde(default)]
+    pub guest_count: i64,
+
+    /// The quota of the project, unset when quotas are disabled.
+    #[serde(default)]
+    pub quota: Option<ProjectQuota>,
+}
+
+/// The quota of a project and its usage.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ProjectQuota {
+    /// The limits of the resources.
+    pub hard: ResourceList,
+
+    /// The usage of the resources.
+    pub used: ResourceList,
+}
+
+/// Amounts of the resources a quota applies to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+pub struct ResourceList {
+    /// Storage in bytes, -1 for unlimited in a limit.
+    #[serde(default)]
+    pub storage: i64,
+}
+
 /// Project metadata configuration
 /// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml#L7272
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -0,0 +1,148 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::types::Pagination;
+
+/// A repository of images in a project.
+/// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Repository {
+    /// The ID of the repository.
+    pub id: i64,
+
+    /// The ID of the project the repository belongs to.
+    pub project_id: i64,
+
+    /// The full name of the repository, prefixed with the project name.
+    pub name: String,
+
+    /// The description of the repository.
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// The number of artifacts in the repository.
+    #[serde(default)]
+    pub artifact_count: i64,
+
+    /// The number of times the repository was pulled.
+    #[serde(default)]
+    pub pull_count: i64,
+
+    /// The creation time of the repository.
+    pub creation_time: Option<DateTime<Utc>>,
+
+    /// The update time of the repository.
+    pub update_time: Option<DateTime<Utc>>,
+}
+
+/// An artifact of a repository, e.g. an image.
+/// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artifact {
+    /// The ID of the artifact.
+    pub id: i64,
+
+    /// The type of the artifact, e.g. "IMAGE".
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// The media type of the artifact.
+    pub media_type: String,
+
+    /// The ID of the project the artifact belongs to.
+    pub project_id: i64,
+
+    /// The ID of the repository the artifact belongs to.
+    pub repository_id: i64,
+
+    /// The digest of the artifact, which references it.
+    pub digest: String,
+
+    /// The size of the artifact in bytes.
+    #[serde(default)]
+    pub size: i64,
+
+    /// The latest push time of the artifact.
+    pub push_time: Option<DateTime<Utc>>,
+
+    /// The latest pull time of the artifact.
+    pub pull_time: Option<DateTime<Utc>>,
+
+    /// The tags of the artifact, only listed when asked for.
+    #[serde(default)]
+    pub tags: Option<Vec<Tag>>,
+}
+
+/// A tag of an artifact.
+/// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    /// The ID of the tag.
+    pub id: i64,
+
+    /// The name of the tag, e.g. "latest".
+    pub name: String,
+
+    /// The latest push time of the tag.
+    pub push_time: Option<DateTime<Utc>>,
+
+    /// The latest pull time of the tag.
+    pub pull_time: Option<DateTime<Utc>>,
+}
+
+/// Query of an artifact listing.
+#[derive(Debug, Clone, Default)]
+pub struct ArtifactQuery {
+    /// Filter in the Harbor query syntax, e.g. "tags=latest".
+    pub q: Option<String>,
+
+    /// Whether the tags of the artifacts are listed.
+    pub with_tag: bool,
+
+    /// The page of the listing.
+    pub pagination: Pagination,
+}
+
+impl ArtifactQuery {
+    /// Sets the filter of the listing.
+    pub fn with_filter(mut self, q: impl ToString) -> Self {
+        self.q = Some(q.to_string());
+        self
+    }
+
+    /// Formats the query string of the listing.
+    pub(crate) fn query(&self) -> String {
+        let mut query = format!("{}&with_tag={}", self.pagination.query(), self.with_tag);
+        if let Some(q) = &self.q {
+            query.push_str(&format!("&q={}", encode(q)));
+        }
+        query
+    }
+}
+
+/// Percent-encodes a value for a query string or a path segment.
+pub(crate) fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
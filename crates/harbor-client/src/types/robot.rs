@@ -0,0 +1,126 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Request body for creating a robot account.
+/// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml#L9624
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct CreateRobotRequest {
+    /// The name of the robot account.
+    pub name: String,
+
+    /// The description of the robot account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// The level of the robot account, "system" or "project".
+    pub level: String,
+
+    /// Whether the robot account is disabled.
+    pub disable: bool,
+
+    /// The duration of the robot account in days, -1 never expires.
+    pub duration: i64,
+
+    /// The permissions of the robot account.
+    pub permissions: Vec<RobotPermission>,
+}
+
+impl CreateRobotRequest {
+    /// Creates a project level robot account with the given access to the
+    /// project, which never expires.
+    pub fn project(project: impl ToString, name: impl ToString, access: Vec<Access>) -> Self {
+        Self {
+            name: name.to_string(),
+            level: "project".to_string(),
+            duration: -1,
+            permissions: vec![RobotPermission {
+                kind: "project".to_string(),
+                namespace: project.to_string(),
+                access,
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// Sets the description of the robot account.
+    pub fn with_description(mut self, value: impl ToString) -> Self {
+        self.description = Some(value.to_string());
+        self
+    }
+
+    /// Sets the duration of the robot account in days.
+    pub fn with_duration(mut self, days: i64) -> Self {
+        self.duration = days;
+        self
+    }
+}
+
+/// The permissions of a robot account on a namespace.
+/// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml#L9666
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RobotPermission {
+    /// The kind of the permission, "system" or "project".
+    pub kind: String,
+
+    /// The name of the project, or "/" for system permissions.
+    pub namespace: String,
+
+    /// The access granted on the namespace.
+    pub access: Vec<Access>,
+}
+
+/// A single action on a resource.
+/// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml#L9681
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct Access {
+    /// The resource of the access, e.g. "repository".
+    pub resource: String,
+
+    /// The action of the access, e.g. "push" or "pull".
+    pub action: String,
+
+    /// The effect of the access, "allow" when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effect: Option<String>,
+}
+
+impl Access {
+    /// Allows an action on a resource.
+    pub fn new(resource: impl ToString, action: impl ToString) -> Self {
+        Self { resource: resource.to_string(), action: action.to_string(), effect: None }
+    }
+}
+
+/// Response body of a created robot account.
+/// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml#L9648
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RobotCreated {
+    /// The ID of the robot account.
+    pub id: i64,
+
+    /// The full name of the robot account, e.g. "robot$project+name".
+    pub name: String,
+
+    /// The secret of the robot account.
+    pub secret: String,
+
+    /// The creation time of the robot account.
+    pub creation_time: Option<DateTime<Utc>>,
+
+    /// The expiration time of the robot account, in seconds since epoch.
+    pub expires_at: Option<i64>,
+}
@@ -0,0 +1,136 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Request body for creating a webhook policy of a project.
+/// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct CreateWebhookPolicyRequest {
+    /// The name of the webhook policy, unique in the project.
+    pub name: String,
+
+    /// The description of the webhook policy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// The targets the events are delivered to.
+    pub targets: Vec<WebhookTarget>,
+
+    /// The types of the events delivered, e.g. "PUSH_ARTIFACT".
+    pub event_types: Vec<String>,
+
+    /// Whether the webhook policy is enabled.
+    pub enabled: bool,
+}
+
+impl CreateWebhookPolicyRequest {
+    /// Creates an enabled policy delivering the given types of events to an
+    /// HTTP address.
+    pub fn new(name: impl ToString, address: impl ToString, event_types: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            targets: vec![WebhookTarget::http(address)],
+            event_types: event_types.iter().map(|kind| kind.to_string()).collect(),
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the description of the webhook policy.
+    pub fn with_description(mut self, value: impl ToString) -> Self {
+        self.description = Some(value.to_string());
+        self
+    }
+
+    /// Sets the Authorization header sent along the events to every target.
+    pub fn with_auth_header(mut self, value: impl ToString) -> Self {
+        for target in &mut self.targets {
+            target.auth_header = Some(value.to_string());
+        }
+        self
+    }
+}
+
+/// A receiver of the events of a webhook policy.
+/// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct WebhookTarget {
+    /// The type of the target, "http" or "slack".
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// The address the events are delivered to.
+    pub address: String,
+
+    /// The value of the Authorization header sent along the events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_header: Option<String>,
+
+    /// Whether the certificate of the address is not verified.
+    #[serde(default)]
+    pub skip_cert_verify: bool,
+
+    /// The format of the payload, "Default" or "CloudEvents".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload_format: Option<String>,
+}
+
+impl WebhookTarget {
+    /// Delivers the events to an HTTP address in the default format.
+    pub fn http(address: impl ToString) -> Self {
+        Self {
+            kind: "http".to_string(),
+            address: address.to_string(),
+            payload_format: Some("Default".to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// A webhook policy of a project.
+/// https://github.com/goharbor/harbor/blob/v2.13.1/api/v2.0/swagger.yaml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookPolicy {
+    /// The ID of the webhook policy.
+    pub id: i64,
+
+    /// The name of the webhook policy.
+    pub name: String,
+
+    /// The description of the webhook policy.
+    pub description: Option<String>,
+
+    /// The ID of the project the webhook policy belongs to.
+    pub project_id: Option<i64>,
+
+    /// The targets the events are delivered to.
+    #[serde(default)]
+    pub targets: Vec<WebhookTarget>,
+
+    /// The types of the events delivered.
+    #[serde(default)]
+    pub event_types: Vec<String>,
+
+    /// Whether the webhook policy is enabled.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// The creation time of the webhook policy.
+    pub creation_time: Option<DateTime<Utc>>,
+
+    /// The last update time of the webhook policy.
+    pub update_time: Option<DateTime<Utc>>,
+}
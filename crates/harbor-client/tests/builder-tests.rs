@@ -0,0 +1,52 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! HTTP and TLS options of clients built with `HarborClientBuilder`.
+
+use std::time::Duration;
+
+use harbor_client::{ClientError, HarborClient, RetryPolicy};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+#[tokio::test]
+async fn test_hung_server_times_out() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v2.0/robots/7"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+
+    let client = HarborClient::builder(server.uri(), "admin".into(), "password".into())
+        .request_timeout(Duration::from_millis(100))
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap();
+
+    let result = client.delete_robot(7).await;
+    assert!(matches!(result, Err(ClientError::Network(e)) if e.is_timeout()));
+}
+
+#[test]
+fn test_invalid_ca_cert_is_rejected() {
+    let result = HarborClient::builder("http://docker.local".into(), "a".into(), "a".into())
+        .ca_cert_pem(
+            b"-----BEGIN CERTIFICATE-----\nnot base64\n-----END CERTIFICATE-----\n".to_vec(),
+        )
+        .build();
+    assert!(matches!(result, Err(ClientError::InvalidCertificate(_))));
+}
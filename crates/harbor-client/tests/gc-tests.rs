@@ -0,0 +1,134 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Garbage collection jobs and their schedule against a mocked Harbor API.
+
+use harbor_client::{
+    ClientError, HarborClient,
+    types::{GcScheduleRequest, ScheduleObj},
+};
+use serde_json::json;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_json, method, path},
+};
+
+#[tokio::test]
+async fn test_trigger_garbage_collection() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v2.0/system/gc/schedule"))
+        .and(body_json(json!({
+            "schedule": { "type": "Manual" },
+            "parameters": { "delete_untagged": true, "dry_run": false, "workers": 1 }
+        })))
+        .respond_with(
+            ResponseTemplate::new(201).insert_header("Location", "/api/v2.0/system/gc/42"),
+        )
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/v2.0/system/gc/schedule"))
+        .respond_with(ResponseTemplate::new(409).set_body_json(json!({
+            "errors": [{ "code": "CONFLICT", "message": "a previous GC job is still running" }]
+        })))
+        .mount(&server)
+        .await;
+
+    let client = HarborClient::new(server.uri(), "admin".into(), "password".into());
+    assert_eq!(client.trigger_garbage_collection().await.unwrap(), 42);
+
+    let result = client.trigger_garbage_collection().await;
+    assert!(matches!(result, Err(ClientError::Conflict(m)) if m.contains("still running")));
+}
+
+#[tokio::test]
+async fn test_trigger_garbage_collection_without_location() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v2.0/system/gc/schedule"))
+        .respond_with(ResponseTemplate::new(201))
+        .mount(&server)
+        .await;
+
+    let client = HarborClient::new(server.uri(), "admin".into(), "password".into());
+    let result = client.trigger_garbage_collection().await;
+    assert!(matches!(result, Err(ClientError::InvalidResponse(_))));
+}
+
+#[tokio::test]
+async fn test_get_gc_status() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v2.0/system/gc/42"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": 42,
+            "job_name": "GARBAGE_COLLECTION",
+            "job_kind": "MANUAL",
+            "job_parameters": "{\"delete_untagged\":true}",
+            "schedule": { "type": "Manual" },
+            "job_status": "Running",
+            "deleted": false,
+            "creation_time": "2025-06-01T10:00:00Z",
+            "update_time": "2025-06-01T10:00:05Z"
+        })))
+        .mount(&server)
+        .await;
+
+    let client = HarborClient::new(server.uri(), "admin".into(), "password".into());
+    let job = client.get_gc_status(42).await.unwrap();
+    assert_eq!(job.id, 42);
+    assert_eq!(job.job_status.as_deref(), Some("Running"));
+    assert_eq!(job.schedule.unwrap().kind, "Manual");
+
+    let result = client.get_gc_status(43).await;
+    assert!(matches!(result, Err(ClientError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_gc_schedule() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v2.0/system/gc/schedule"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": 7,
+            "job_kind": "SCHEDULE",
+            "schedule": {
+                "type": "Custom",
+                "cron": "0 0 3 * * *",
+                "next_scheduled_time": "2025-06-02T03:00:00Z"
+            }
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/api/v2.0/system/gc/schedule"))
+        .and(body_json(json!({
+            "schedule": { "type": "Custom", "cron": "0 0 3 * * *" },
+            "parameters": { "delete_untagged": true, "dry_run": false, "workers": 1 }
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = HarborClient::new(server.uri(), "admin".into(), "password".into());
+    let schedule = client.get_gc_schedule().await.unwrap().schedule.unwrap();
+    assert_eq!(schedule.cron.as_deref(), Some("0 0 3 * * *"));
+    assert!(schedule.next_scheduled_time.is_some());
+
+    let request = GcScheduleRequest::new(ScheduleObj::custom("0 0 3 * * *"));
+    client.set_gc_schedule(request).await.unwrap();
+}
@@ -0,0 +1,112 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Quotas, summaries and deletions of projects against a mocked Harbor API.
+
+use harbor_client::{
+    ClientError, HarborClient,
+    types::{ProjectMetadata, UpdateProjectRequest},
+};
+use serde_json::json;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_json, method, path},
+};
+
+#[tokio::test]
+async fn test_update_project() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/api/v2.0/projects/stackclass-redis"))
+        .and(body_json(json!({
+            "metadata": { "auto_scan": "true" },
+            "storage_limit": 1073741824
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/api/v2.0/projects/stackclass-full"))
+        .respond_with(ResponseTemplate::new(409).set_body_json(json!({
+            "errors": [{ "code": "CONFLICT", "message": "quota below current usage" }]
+        })))
+        .mount(&server)
+        .await;
+
+    let client = HarborClient::new(server.uri(), "admin".into(), "password".into());
+    let metadata = ProjectMetadata { auto_scan: Some("true".into()), ..Default::default() };
+    let request =
+        UpdateProjectRequest::default().with_storage_limit(1 << 30).with_metadata(metadata);
+    client.update_project("stackclass-redis", request).await.unwrap();
+
+    let request = UpdateProjectRequest::default().with_storage_limit(1);
+    let result = client.update_project("stackclass-full", request).await;
+    assert!(matches!(result, Err(ClientError::Conflict(m)) if m == "quota below current usage"));
+}
+
+#[tokio::test]
+async fn test_get_project_summary() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v2.0/projects/stackclass-redis/summary"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "repo_count": 4,
+            "project_admin_count": 1,
+            "quota": {
+                "hard": { "storage": -1 },
+                "used": { "storage": 52428800 }
+            }
+        })))
+        .mount(&server)
+        .await;
+
+    let client = HarborClient::new(server.uri(), "admin".into(), "password".into());
+    let summary = client.get_project_summary("stackclass-redis").await.unwrap();
+    assert_eq!(summary.repo_count, 4);
+    let quota = summary.quota.unwrap();
+    assert_eq!((quota.hard.storage, quota.used.storage), (-1, 52428800));
+
+    let result = client.get_project_summary("missing").await;
+    assert!(matches!(result, Err(ClientError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_delete_project() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v2.0/projects/stackclass-redis"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v2.0/projects/stackclass-full"))
+        .respond_with(ResponseTemplate::new(412).set_body_json(json!({
+            "errors": [{ "code": "PRECONDITION", "message": "the project contains repositories" }]
+        })))
+        .mount(&server)
+        .await;
+
+    let client = HarborClient::new(server.uri(), "admin".into(), "password".into());
+    client.delete_project("stackclass-redis").await.unwrap();
+
+    let result = client.delete_project("stackclass-full").await;
+    assert!(
+        matches!(result, Err(ClientError::PreconditionFailed(m)) if m == "the project contains repositories")
+    );
+
+    let result = client.delete_project("missing").await;
+    assert!(matches!(result, Err(ClientError::NotFound)));
+}
@@ -0,0 +1,128 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Repositories and artifacts of projects against a mocked Harbor API.
+
+use harbor_client::{
+    ClientError, HarborClient,
+    types::{ArtifactQuery, Pagination},
+};
+use serde_json::{Value, json};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path, query_param},
+};
+
+fn artifact(id: i64, digest: &str) -> Value {
+    json!({
+        "id": id,
+        "type": "IMAGE",
+        "media_type": "application/vnd.oci.image.config.v1+json",
+        "manifest_media_type": "application/vnd.oci.image.manifest.v1+json",
+        "project_id": 2,
+        "repository_id": 5,
+        "digest": digest,
+        "size": 1024,
+        "push_time": "2025-01-01T00:00:00Z",
+        "pull_time": "0001-01-01T00:00:00Z",
+        "tags": [{
+            "id": 9,
+            "repository_id": 5,
+            "artifact_id": id,
+            "name": "latest",
+            "push_time": "2025-01-01T00:00:00Z",
+            "pull_time": "0001-01-01T00:00:00Z",
+            "immutable": false
+        }]
+    })
+}
+
+#[tokio::test]
+async fn test_list_repositories() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v2.0/projects/stackclass-redis/repositories"))
+        .and(query_param("page", "2"))
+        .and(query_param("page_size", "1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+            "id": 5,
+            "project_id": 2,
+            "name": "stackclass-redis/0198b0f4",
+            "artifact_count": 1,
+            "pull_count": 0,
+            "creation_time": "2025-01-01T00:00:00Z",
+            "update_time": "2025-01-01T00:00:00Z"
+        }])))
+        .mount(&server)
+        .await;
+
+    let client = HarborClient::new(server.uri(), "admin".into(), "password".into());
+    let pagination = Pagination::new(1, 1).next();
+    let repositories = client.list_repositories("stackclass-redis", pagination).await.unwrap();
+    assert_eq!(repositories.len(), 1);
+    assert_eq!(repositories[0].name, "stackclass-redis/0198b0f4");
+    assert!(!pagination.is_last(repositories.len()));
+
+    let result = client.list_repositories("missing", Pagination::default()).await;
+    assert!(matches!(result, Err(ClientError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_list_and_delete_artifacts() {
+    let digest = "sha256:4d8c0e2f";
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v2.0/projects/stackclass-redis/repositories/0198b0f4/artifacts"))
+        .and(query_param("with_tag", "true"))
+        .and(query_param("q", "tags=latest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![artifact(3, digest)]))
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path(format!(
+            "/api/v2.0/projects/stackclass-redis/repositories/0198b0f4/artifacts/{digest}"
+        )))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = HarborClient::new(server.uri(), "admin".into(), "password".into());
+    let query = ArtifactQuery { with_tag: true, ..Default::default() }.with_filter("tags=latest");
+    let artifacts = client.list_artifacts("stackclass-redis", "0198b0f4", &query).await.unwrap();
+    assert_eq!(artifacts.len(), 1);
+    assert_eq!(artifacts[0].tags.as_ref().unwrap()[0].name, "latest");
+
+    let artifact = &artifacts[0];
+    client.delete_artifact("stackclass-redis", "0198b0f4", &artifact.digest).await.unwrap();
+
+    let result = client.delete_artifact("stackclass-redis", "0198b0f4", "sha256:gone").await;
+    assert!(matches!(result, Err(ClientError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_nested_repository_name_is_encoded_twice() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v2.0/projects/stackclass/repositories/tools%252Ftester/artifacts"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = HarborClient::new(server.uri(), "admin".into(), "password".into());
+    let query = ArtifactQuery::default();
+    let artifacts = client.list_artifacts("stackclass", "tools/tester", &query).await.unwrap();
+    assert!(artifacts.is_empty());
+}
@@ -0,0 +1,72 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retries of requests failing transiently, against a mocked Harbor API.
+
+use std::time::Duration;
+
+use harbor_client::{
+    ClientError, HarborClient, RetryPolicy,
+    types::{Access, CreateRobotRequest},
+};
+use reqwest::StatusCode;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+/// A client retrying twice without waiting.
+fn client(server: &MockServer) -> HarborClient {
+    let retry =
+        RetryPolicy { max_retries: 2, initial_backoff: Duration::ZERO, ..Default::default() };
+    HarborClient::new(server.uri(), "admin".into(), "password".into()).with_retry_policy(retry)
+}
+
+#[tokio::test]
+async fn test_idempotent_request_is_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v2.0/robots/7"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .expect(2)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v2.0/robots/7"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    client(&server).delete_robot(7).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_post_is_not_retried_after_a_response() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v2.0/robots"))
+        .respond_with(ResponseTemplate::new(502))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let access = vec![Access::new("repository", "push")];
+    let request = CreateRobotRequest::project("stackclass", "pipeline", access);
+    let result = client(&server).create_robot(request).await;
+    let bad_gateway = StatusCode::BAD_GATEWAY;
+    assert!(matches!(result, Err(ClientError::UnexpectedStatusCode(s)) if s == bad_gateway));
+}
@@ -0,0 +1,111 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Webhook policies of projects against a mocked Harbor API.
+
+use harbor_client::{ClientError, HarborClient, types::CreateWebhookPolicyRequest};
+use serde_json::json;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_json, method, path},
+};
+
+#[tokio::test]
+async fn test_create_webhook_policy() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v2.0/projects/stackclass-redis/webhook/policies"))
+        .and(body_json(json!({
+            "name": "stackclass",
+            "targets": [{
+                "type": "http",
+                "address": "http://api.local/v1/webhooks/harbor",
+                "auth_header": "Basic YWRtaW46c2VjcmV0",
+                "skip_cert_verify": false,
+                "payload_format": "Default"
+            }],
+            "event_types": ["PUSH_ARTIFACT"],
+            "enabled": true
+        })))
+        .respond_with(ResponseTemplate::new(201))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/v2.0/projects/stackclass-redis/webhook/policies"))
+        .respond_with(ResponseTemplate::new(409).set_body_json(json!({
+            "errors": [{ "code": "CONFLICT", "message": "policy stackclass already exists" }]
+        })))
+        .mount(&server)
+        .await;
+
+    let client = HarborClient::new(server.uri(), "admin".into(), "password".into());
+    let request = || {
+        CreateWebhookPolicyRequest::new(
+            "stackclass",
+            "http://api.local/v1/webhooks/harbor",
+            &["PUSH_ARTIFACT"],
+        )
+        .with_auth_header("Basic YWRtaW46c2VjcmV0")
+    };
+    client.create_webhook_policy("stackclass-redis", request()).await.unwrap();
+
+    let result = client.create_webhook_policy("stackclass-redis", request()).await;
+    assert!(matches!(result, Err(ClientError::Conflict(m)) if m.contains("already exists")));
+}
+
+#[tokio::test]
+async fn test_list_webhook_policies() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v2.0/projects/stackclass-redis/webhook/policies"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+            "id": 3,
+            "name": "stackclass",
+            "project_id": 12,
+            "targets": [{ "type": "http", "address": "http://api.local/v1/webhooks/harbor" }],
+            "event_types": ["PUSH_ARTIFACT"],
+            "enabled": true,
+            "creation_time": "2025-06-01T10:00:00Z"
+        }])))
+        .mount(&server)
+        .await;
+
+    let client = HarborClient::new(server.uri(), "admin".into(), "password".into());
+    let policies = client.list_webhook_policies("stackclass-redis").await.unwrap();
+    assert_eq!(policies.len(), 1);
+    assert_eq!((policies[0].id, policies[0].name.as_str()), (3, "stackclass"));
+    assert_eq!(policies[0].targets[0].address, "http://api.local/v1/webhooks/harbor");
+    assert_eq!(policies[0].event_types, ["PUSH_ARTIFACT"]);
+
+    let result = client.list_webhook_policies("missing").await;
+    assert!(matches!(result, Err(ClientError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_delete_webhook_policy() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v2.0/projects/stackclass-redis/webhook/policies/3"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = HarborClient::new(server.uri(), "admin".into(), "password".into());
+    client.delete_webhook_policy("stackclass-redis", 3).await.unwrap();
+
+    let result = client.delete_webhook_policy("stackclass-redis", 4).await;
+    assert!(matches!(result, Err(ClientError::NotFound)));
+}
@@ -0,0 +1,10 @@
+-- Migration to support verified git identities on enrollments
+-- Courses may require learners to prove they control their repository
+-- before the enrollment gets activated
+
+ALTER TABLE courses
+ADD COLUMN require_verified_identity BOOLEAN NOT NULL DEFAULT false;
+
+ALTER TABLE user_courses
+ADD COLUMN identity_verified BOOLEAN NOT NULL DEFAULT false,
+ADD COLUMN identity_nonce TEXT;
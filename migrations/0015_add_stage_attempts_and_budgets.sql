@@ -0,0 +1,25 @@
+-- Migration to track pipeline attempts per user stage
+-- Stages may cap the number of graded attempts, with a course-level default
+
+ALTER TABLE courses
+ADD COLUMN max_attempts INTEGER;
+
+ALTER TABLE stages
+ADD COLUMN max_attempts INTEGER,
+ADD COLUMN content_hash TEXT NOT NULL DEFAULT '';
+
+ALTER TABLE user_stages
+ADD COLUMN granted_attempts INTEGER NOT NULL DEFAULT 0;
+
+-- Stage attempts table (one row per triggered or refused pipeline run)
+CREATE TABLE stage_attempts (
+    id UUID PRIMARY KEY,
+    user_stage_id UUID NOT NULL REFERENCES user_stages(id) ON DELETE CASCADE,
+    pipeline_run TEXT,
+    status TEXT NOT NULL CHECK (status IN ('pending', 'passed', 'failed', 'budget_exhausted')),
+    content_hash TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_stage_attempts_user_stage_id ON stage_attempts(user_stage_id);
+CREATE INDEX idx_stage_attempts_pipeline_run ON stage_attempts(pipeline_run);
@@ -0,0 +1,13 @@
+-- Migration to record administrative actions
+
+CREATE TABLE audit_logs (
+    id UUID PRIMARY KEY,
+    actor TEXT NOT NULL,
+    action TEXT NOT NULL,
+    target TEXT NOT NULL,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_audit_logs_target ON audit_logs(target);
+CREATE INDEX idx_audit_logs_created_at ON audit_logs(created_at);
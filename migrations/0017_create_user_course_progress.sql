@@ -0,0 +1,44 @@
+-- Migration for the denormalized per-enrollment progress summary
+-- The view computes progress from scratch, the table caches it for dashboards
+
+CREATE VIEW user_course_progress_live AS
+SELECT
+    uc.id AS user_course_id,
+    uc.course_id,
+    COUNT(us.id) FILTER (WHERE us.status = 'completed')::INTEGER AS completed_stage_count,
+    GREATEST(MAX(us.started_at), MAX(us.completed_at)) AS last_activity_at,
+    COALESCE(
+        (
+            SELECT jsonb_object_agg(x.slug, x.completed)
+            FROM (
+                SELECT e.slug, COUNT(*) AS completed
+                FROM user_stages ues
+                JOIN stages s ON ues.stage_id = s.id
+                JOIN extensions e ON s.extension_id = e.id
+                WHERE ues.user_course_id = uc.id AND ues.status = 'completed'
+                GROUP BY e.slug
+            ) x
+        ),
+        '{}'::JSONB
+    ) AS extension_progress
+FROM user_courses uc
+LEFT JOIN user_stages us ON us.user_course_id = uc.id
+GROUP BY uc.id;
+
+CREATE TABLE user_course_progress (
+    user_course_id UUID PRIMARY KEY REFERENCES user_courses(id) ON DELETE CASCADE,
+    course_id UUID NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    completed_stage_count INTEGER NOT NULL DEFAULT 0,
+    last_activity_at TIMESTAMP WITH TIME ZONE,
+    extension_progress JSONB NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_user_course_progress_course_id ON user_course_progress(course_id);
+
+-- Backfill existing enrollments
+INSERT INTO user_course_progress (
+    user_course_id, course_id, completed_stage_count, last_activity_at, extension_progress
+)
+SELECT user_course_id, course_id, completed_stage_count, last_activity_at, extension_progress
+FROM user_course_progress_live;
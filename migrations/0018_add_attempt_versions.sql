@@ -0,0 +1,10 @@
+-- Migration to record which course version each attempt was graded against
+
+ALTER TABLE courses
+ADD COLUMN commit_sha TEXT NOT NULL DEFAULT '';
+
+ALTER TABLE stage_attempts
+ADD COLUMN course_commit TEXT NOT NULL DEFAULT '',
+ADD COLUMN tester_image TEXT NOT NULL DEFAULT '';
+
+CREATE INDEX idx_stage_attempts_content_hash ON stage_attempts(content_hash);
@@ -0,0 +1,9 @@
+-- Migration to queue pipeline triggers while the cluster is unavailable
+-- Queued attempts carry no pipeline run until they are dispatched
+
+ALTER TABLE stage_attempts
+DROP CONSTRAINT stage_attempts_status_check,
+ADD CONSTRAINT stage_attempts_status_check
+    CHECK (status IN ('queued', 'pending', 'passed', 'failed', 'budget_exhausted'));
+
+CREATE INDEX idx_stage_attempts_queued ON stage_attempts(created_at) WHERE status = 'queued';
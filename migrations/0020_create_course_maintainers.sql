@@ -0,0 +1,11 @@
+-- Migration to let course authors manage their own course
+
+CREATE TABLE course_maintainers (
+    course_id UUID NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    added_by TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (course_id, user_id)
+);
+
+CREATE INDEX idx_course_maintainers_user_id ON course_maintainers(user_id);
@@ -0,0 +1,25 @@
+-- Migration to surface suspicious completion patterns to instructors
+
+-- Completions are analyzed once, in the background
+ALTER TABLE user_stages
+ADD COLUMN analyzed_at TIMESTAMP WITH TIME ZONE;
+
+-- Only completions made from now on are analyzed
+UPDATE user_stages SET analyzed_at = NOW() WHERE status = 'completed';
+
+CREATE INDEX idx_user_stages_unanalyzed ON user_stages(completed_at)
+WHERE status = 'completed' AND analyzed_at IS NULL;
+
+CREATE TABLE integrity_flags (
+    id UUID PRIMARY KEY,
+    user_stage_id UUID NOT NULL REFERENCES user_stages(id) ON DELETE CASCADE,
+    rule TEXT NOT NULL,
+    features JSONB NOT NULL DEFAULT '{}',
+    dismissed_at TIMESTAMP WITH TIME ZONE,
+    dismissed_by TEXT,
+    note TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (user_stage_id, rule)
+);
+
+CREATE INDEX idx_integrity_flags_rule ON integrity_flags(rule);
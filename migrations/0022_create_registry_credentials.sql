@@ -0,0 +1,20 @@
+-- Migration to give each course its own registry robot account
+
+CREATE TABLE registry_credentials (
+    id UUID PRIMARY KEY,
+    course_id UUID NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    project TEXT NOT NULL,
+    robot_id BIGINT NOT NULL,
+    robot_name TEXT NOT NULL,
+    secret_name TEXT NOT NULL,
+    encrypted_secret TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    retired_at TIMESTAMP WITH TIME ZONE
+);
+
+-- A course has at most one set of credentials handed to new pipelines
+CREATE UNIQUE INDEX idx_registry_credentials_active ON registry_credentials(course_id)
+WHERE retired_at IS NULL;
+
+CREATE INDEX idx_registry_credentials_retired ON registry_credentials(retired_at)
+WHERE retired_at IS NOT NULL;
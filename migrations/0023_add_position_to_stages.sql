@@ -0,0 +1,18 @@
+-- Migration to order stages by an explicit position within their group
+
+-- Position of a stage among the base stages of its course, or among the
+-- stages of its extension. The weight column is still written during the
+-- transition, but no longer read.
+ALTER TABLE stages
+ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+
+-- Backfill from the weights, which order stages within each group
+UPDATE stages s
+SET position = ranked.position
+FROM (
+    SELECT id, ROW_NUMBER() OVER (PARTITION BY course_id, extension_id ORDER BY weight, slug) - 1 AS position
+    FROM stages
+) ranked
+WHERE s.id = ranked.id;
+
+CREATE INDEX idx_stages_position ON stages(course_id, extension_id, position);
@@ -0,0 +1,21 @@
+-- Migration to let visitors try the first stage of a course without signing up
+
+CREATE TABLE trials (
+    id UUID PRIMARY KEY,
+    course_id UUID NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    stage_id UUID NOT NULL REFERENCES stages(id) ON DELETE CASCADE,
+    client_key TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'active',
+    test TEXT,
+    pipeline_run TEXT,
+    user_course_id UUID REFERENCES user_courses(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- Active trials are counted against the global cap and reaped once expired
+CREATE INDEX idx_trials_active ON trials(expires_at) WHERE status = 'active';
+
+-- Trials are rate limited per client
+CREATE INDEX idx_trials_client ON trials(client_key, created_at);
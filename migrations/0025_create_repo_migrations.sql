@@ -0,0 +1,20 @@
+-- Migration to import learner repositories from another Gitea instance
+
+ALTER TABLE user_courses ADD COLUMN repository_status TEXT NOT NULL DEFAULT 'ready';
+
+CREATE TABLE repo_migrations (
+    id UUID PRIMARY KEY,
+    user_course_id UUID NOT NULL UNIQUE REFERENCES user_courses(id) ON DELETE CASCADE,
+    source_url TEXT NOT NULL,
+    source_repo TEXT NOT NULL,
+    auth_username TEXT,
+    encrypted_password TEXT,
+    encrypted_token TEXT,
+    status TEXT NOT NULL DEFAULT 'pending',
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Pending migrations are claimed in the order they were requested
+CREATE INDEX idx_repo_migrations_pending ON repo_migrations(created_at) WHERE status = 'pending';
@@ -0,0 +1,14 @@
+-- Migration to run courses as timed assessments
+-- Attempts submitted after the deadline of the learner are recorded but do
+-- not count toward completion
+
+ALTER TABLE courses
+ADD COLUMN opens_at TIMESTAMP WITH TIME ZONE,
+ADD COLUMN closes_at TIMESTAMP WITH TIME ZONE;
+
+-- Deadline granted to a learner beyond the close of the course
+ALTER TABLE user_courses
+ADD COLUMN extended_deadline TIMESTAMP WITH TIME ZONE;
+
+ALTER TABLE stage_attempts
+ADD COLUMN late BOOLEAN NOT NULL DEFAULT FALSE;
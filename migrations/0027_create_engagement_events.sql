@@ -0,0 +1,26 @@
+-- Migration to collect first-party engagement events of learners
+
+CREATE TABLE engagement_events (
+    id UUID PRIMARY KEY,
+    user_course_id UUID NOT NULL REFERENCES user_courses(id) ON DELETE CASCADE,
+    stage_id UUID NOT NULL REFERENCES stages(id) ON DELETE CASCADE,
+    event_type TEXT NOT NULL,
+    occurred_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    received_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Events are rate limited per enrollment and purged once out of retention
+CREATE INDEX idx_engagement_events_user_course ON engagement_events(user_course_id, received_at);
+CREATE INDEX idx_engagement_events_received_at ON engagement_events(received_at);
+
+-- Counts of the purged events, kept per learner so that conversions can
+-- still be computed
+CREATE TABLE engagement_rollups (
+    user_course_id UUID NOT NULL REFERENCES user_courses(id) ON DELETE CASCADE,
+    stage_id UUID NOT NULL REFERENCES stages(id) ON DELETE CASCADE,
+    event_type TEXT NOT NULL,
+    event_count BIGINT NOT NULL,
+    PRIMARY KEY (user_course_id, stage_id, event_type)
+);
+
+CREATE INDEX idx_engagement_rollups_stage ON engagement_rollups(stage_id);
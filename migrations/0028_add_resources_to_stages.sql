@@ -0,0 +1,4 @@
+-- Migration to let stages pick the resource profile of their test pods
+
+ALTER TABLE stages
+ADD COLUMN resources TEXT;
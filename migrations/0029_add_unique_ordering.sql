@@ -0,0 +1,29 @@
+-- Migration to keep the order of extensions and stages unambiguous
+
+-- Renumber extensions densely within their course, ties broken by slug
+UPDATE extensions e
+SET weight = ranked.weight
+FROM (
+    SELECT id, ROW_NUMBER() OVER (PARTITION BY course_id ORDER BY weight, slug) - 1 AS weight
+    FROM extensions
+) ranked
+WHERE e.id = ranked.id;
+
+-- ... and the same for stages within their group
+UPDATE stages s
+SET position = ranked.position
+FROM (
+    SELECT id, ROW_NUMBER() OVER (PARTITION BY course_id, extension_id ORDER BY position, slug) - 1 AS position
+    FROM stages
+) ranked
+WHERE s.id = ranked.id;
+
+-- Course syncs shift the existing order out of the way before writing the
+-- new one, so no intermediate state collides
+ALTER TABLE extensions
+ADD CONSTRAINT unique_extension_weight UNIQUE (course_id, weight);
+
+ALTER TABLE stages
+ADD CONSTRAINT unique_stage_position UNIQUE NULLS NOT DISTINCT (course_id, extension_id, position);
+
+DROP INDEX idx_stages_position;
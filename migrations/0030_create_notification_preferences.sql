@@ -0,0 +1,19 @@
+-- Migration to let learners choose which notifications they receive and where
+
+CREATE TABLE user_notification_preferences (
+    user_id TEXT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    channel TEXT NOT NULL DEFAULT 'email' CHECK (channel IN ('email', 'webhook')),
+    destination TEXT,
+    announcements BOOLEAN NOT NULL DEFAULT true,
+    feedback BOOLEAN NOT NULL DEFAULT true,
+    deadline_reminders BOOLEAN NOT NULL DEFAULT true,
+    accountability BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT webhook_destination CHECK (channel = 'email' OR destination IS NOT NULL)
+);
+
+-- Learners who opted in to accountability emails keep receiving them for the
+-- courses they enabled them on
+INSERT INTO user_notification_preferences (user_id, accountability)
+SELECT DISTINCT user_id, true FROM user_courses WHERE accountability;
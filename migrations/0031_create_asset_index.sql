@@ -0,0 +1,18 @@
+-- Migration to index the assets of courses for offline-capable clients
+
+-- Hash over the content of the course, its stages and assets
+ALTER TABLE courses ADD COLUMN content_hash TEXT NOT NULL DEFAULT '';
+
+-- Files shipped with a course besides its definition files, as found in the
+-- course repository at import time
+CREATE TABLE asset_index (
+    course_id UUID NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    stage_id UUID REFERENCES stages(id) ON DELETE CASCADE,
+    path TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('asset', 'hint')),
+    sha256 TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    PRIMARY KEY (course_id, path)
+);
+
+CREATE INDEX idx_asset_index_stage ON asset_index(stage_id);
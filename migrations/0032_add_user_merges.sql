@@ -0,0 +1,17 @@
+-- Migration to merge duplicate accounts issued by the identity provider
+
+-- Tokens of a merged-away user act on behalf of the user it was merged into
+ALTER TABLE users
+ADD COLUMN merged_into TEXT REFERENCES users(id),
+ADD COLUMN merged_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX idx_users_merged_into ON users(merged_into);
+
+-- Enrollments losing a merge conflict stay with the merged-away user
+ALTER TABLE user_courses
+ADD COLUMN archived_at TIMESTAMP WITH TIME ZONE;
+
+-- Conflicting enrollments swap their owners within the merge transaction
+ALTER TABLE user_courses
+DROP CONSTRAINT unique_user_course,
+ADD CONSTRAINT unique_user_course UNIQUE (user_id, course_id) DEFERRABLE INITIALLY IMMEDIATE;
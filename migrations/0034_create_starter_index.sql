@@ -0,0 +1,27 @@
+-- Migration to index the starter code of stages and what it changes
+
+-- Files of the starter code of a stage, as found in the starter directory
+-- of the course repository at import time
+CREATE TABLE starter_files (
+    course_id UUID NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    stage_id UUID NOT NULL REFERENCES stages(id) ON DELETE CASCADE,
+    path TEXT NOT NULL,
+    sha256 TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    PRIMARY KEY (stage_id, path)
+);
+
+CREATE INDEX idx_starter_files_course ON starter_files(course_id);
+
+-- Files the starter code of a stage changes compared to the stage before it
+CREATE TABLE starter_changes (
+    course_id UUID NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    stage_id UUID NOT NULL REFERENCES stages(id) ON DELETE CASCADE,
+    path TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('added', 'modified', 'removed')),
+    diff TEXT,
+    truncated BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (stage_id, path)
+);
+
+CREATE INDEX idx_starter_changes_course ON starter_changes(course_id);
@@ -0,0 +1,15 @@
+-- Migration to pre-provision learner repositories ahead of course launches
+
+-- Spare repositories generated from the template of a course, named after
+-- their reserved id, and renamed to the user course that claims them
+CREATE TABLE repo_pool (
+    id UUID PRIMARY KEY,
+    course_id UUID NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    commit_sha TEXT NOT NULL,
+    claimed BOOLEAN NOT NULL DEFAULT FALSE,
+    claimed_as TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    claimed_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_repo_pool_unclaimed ON repo_pool(course_id, created_at) WHERE NOT claimed;
@@ -0,0 +1,8 @@
+-- Migration to store settings changed at runtime, shared by all replicas
+
+CREATE TABLE system_settings (
+    key TEXT PRIMARY KEY,
+    value JSONB NOT NULL,
+    updated_by TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
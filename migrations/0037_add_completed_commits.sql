@@ -0,0 +1,7 @@
+-- Migration to record which commit of the learner passed each stage
+
+ALTER TABLE stage_attempts
+ADD COLUMN repo_commit TEXT NOT NULL DEFAULT '';
+
+ALTER TABLE user_stages
+ADD COLUMN completed_commit TEXT;
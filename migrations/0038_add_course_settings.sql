@@ -0,0 +1,7 @@
+-- Migration to let courses leave their settings to instance defaults, and
+-- let admins override them at runtime
+
+ALTER TABLE courses
+ADD COLUMN resources TEXT,
+ADD COLUMN tester_image TEXT,
+ADD COLUMN setting_overrides JSONB NOT NULL DEFAULT '{}';
@@ -0,0 +1,15 @@
+-- Migration to order rows created within the same instant by insertion,
+-- as timestamps alone do not break ties
+
+ALTER TABLE stage_attempts
+ADD COLUMN seq BIGINT GENERATED ALWAYS AS IDENTITY;
+
+ALTER TABLE audit_logs
+ADD COLUMN seq BIGINT GENERATED ALWAYS AS IDENTITY;
+
+ALTER TABLE engagement_events
+ADD COLUMN seq BIGINT GENERATED ALWAYS AS IDENTITY;
+
+DROP INDEX idx_audit_logs_created_at;
+CREATE INDEX idx_audit_logs_created_at ON audit_logs(created_at, seq);
+CREATE INDEX idx_stage_attempts_created_at ON stage_attempts(created_at, seq);
@@ -0,0 +1,10 @@
+-- Migration to record the deploy keys pipelines clone repositories with
+
+CREATE TABLE deploy_keys (
+    owner TEXT NOT NULL,
+    repo TEXT NOT NULL,
+    key_id BIGINT NOT NULL,
+    encrypted_private_key TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (owner, repo)
+);
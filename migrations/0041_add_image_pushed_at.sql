@@ -0,0 +1,4 @@
+-- Migration to record when the course image of a user stage was pushed
+
+ALTER TABLE user_stages
+ADD COLUMN image_pushed_at TIMESTAMP WITH TIME ZONE;
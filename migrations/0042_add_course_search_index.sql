@@ -0,0 +1,8 @@
+-- Migration to search courses by keywords without scanning the table, the
+-- expression must match the one of CourseRepository::search
+
+CREATE INDEX idx_courses_search ON courses USING GIN ((
+    setweight(to_tsvector('english', name), 'A') ||
+    setweight(to_tsvector('english', summary), 'B') ||
+    setweight(to_tsvector('english', description), 'C')
+));
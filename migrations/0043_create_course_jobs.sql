@@ -0,0 +1,14 @@
+-- Migration to create courses in the background and track their progress
+
+CREATE TABLE course_jobs (
+    id UUID PRIMARY KEY,
+    repository TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    course_slug TEXT,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Pending jobs are claimed in the order they were queued
+CREATE INDEX idx_course_jobs_pending ON course_jobs(created_at) WHERE status = 'pending';
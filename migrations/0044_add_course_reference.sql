@@ -0,0 +1,5 @@
+-- Migration to pin courses to a branch, tag or commit of their repository
+
+ALTER TABLE courses ADD COLUMN reference TEXT;
+
+ALTER TABLE course_jobs ADD COLUMN reference TEXT;
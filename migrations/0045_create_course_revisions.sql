@@ -0,0 +1,15 @@
+-- Migration to record what each sync of a course changed
+
+CREATE TABLE course_revisions (
+    id UUID PRIMARY KEY,
+    course_id UUID NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    commit_sha TEXT NOT NULL,
+    actor TEXT NOT NULL,
+    stages_added TEXT[] NOT NULL DEFAULT '{}',
+    stages_removed TEXT[] NOT NULL DEFAULT '{}',
+    stages_updated TEXT[] NOT NULL DEFAULT '{}',
+    synced_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Revisions are listed per course, newest first
+CREATE INDEX idx_course_revisions_course_id ON course_revisions(course_id, synced_at DESC);
@@ -0,0 +1,11 @@
+-- Migration to archive removed stages learners still reference, instead of
+-- deleting them along with their progress
+
+ALTER TABLE stages ADD COLUMN archived BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Archived stages keep their last position, which the current stages may
+-- take over
+ALTER TABLE stages DROP CONSTRAINT unique_stage_position;
+
+CREATE UNIQUE INDEX unique_stage_position ON stages(course_id, extension_id, position)
+    NULLS NOT DISTINCT WHERE NOT archived;
@@ -0,0 +1,8 @@
+-- Migration to group enrollments into instructor-led cohorts
+
+ALTER TABLE user_courses
+ADD COLUMN cohort TEXT,
+ADD COLUMN cohort_deadline TIMESTAMP WITH TIME ZONE;
+
+-- Progress is listed per cohort of a course
+CREATE INDEX idx_user_courses_cohort ON user_courses(course_id, cohort) WHERE cohort IS NOT NULL;
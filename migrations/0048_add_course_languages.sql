@@ -0,0 +1,8 @@
+-- Migration to offer courses in several programming languages
+
+ALTER TABLE courses
+ADD COLUMN languages TEXT[] NOT NULL DEFAULT '{}';
+
+-- Language variant the learner enrolled with, none for single-language courses
+ALTER TABLE user_courses
+ADD COLUMN language TEXT;
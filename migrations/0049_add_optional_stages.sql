@@ -0,0 +1,10 @@
+-- Migration to let learners skip the stages course authors mark optional
+
+ALTER TABLE stages
+ADD COLUMN optional BOOLEAN NOT NULL DEFAULT false;
+
+-- Skipped stages are left behind without counting as completed
+ALTER TABLE user_stages
+DROP CONSTRAINT user_stages_status_check,
+ADD CONSTRAINT user_stages_status_check
+    CHECK (status IN ('in_progress', 'completed', 'skipped'));
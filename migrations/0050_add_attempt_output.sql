@@ -0,0 +1,6 @@
+-- Migration to keep the output of the test runs grading attempts
+
+ALTER TABLE stage_attempts
+ADD COLUMN reason TEXT,
+ADD COLUMN log TEXT,
+ADD COLUMN results JSONB NOT NULL DEFAULT '[]';
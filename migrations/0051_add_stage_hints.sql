@@ -0,0 +1,12 @@
+-- Migration to let learners unlock the hints of a stage one at a time
+
+CREATE TABLE stage_hints (
+    stage_id UUID NOT NULL REFERENCES stages(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    PRIMARY KEY (stage_id, position)
+);
+
+-- Hints are revealed in order, so the count of unlocked ones is enough
+ALTER TABLE user_stages
+ADD COLUMN hints_unlocked INTEGER NOT NULL DEFAULT 0;
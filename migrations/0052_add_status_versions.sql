@@ -0,0 +1,6 @@
+-- Migration to number the updates of the status of user courses
+
+-- Bumped along with every notification of the status streams, and sent as
+-- the id of their events so reconnecting clients can tell what they missed
+ALTER TABLE user_courses
+ADD COLUMN status_version BIGINT NOT NULL DEFAULT 0;
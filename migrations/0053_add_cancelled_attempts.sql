@@ -0,0 +1,6 @@
+-- Migration to record attempts whose pipeline run a newer push cancelled
+
+ALTER TABLE stage_attempts
+DROP CONSTRAINT stage_attempts_status_check,
+ADD CONSTRAINT stage_attempts_status_check
+    CHECK (status IN ('queued', 'pending', 'passed', 'failed', 'budget_exhausted', 'cancelled'));
@@ -0,0 +1,8 @@
+-- Migration to let courses set up the PipelineRuns grading them
+
+-- Declared under `pipeline` in course.yml, none keeps the instance default
+ALTER TABLE courses
+ADD COLUMN pipeline_ref TEXT,
+ADD COLUMN workspace_size TEXT,
+ADD COLUMN pipeline_timeout TEXT,
+ADD COLUMN service_account TEXT;
@@ -0,0 +1,8 @@
+-- Migration to record when the PipelineRun of an attempt finished
+
+-- Set once the outcome of the run is recorded, through its webhook or the
+-- reconciliation of runs whose webhook was lost
+ALTER TABLE stage_attempts
+ADD COLUMN finished_at TIMESTAMPTZ;
+
+CREATE INDEX idx_stage_attempts_pending ON stage_attempts(created_at) WHERE status = 'pending';
@@ -12,14 +12,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 use axum::http::header::{self, HeaderValue};
+use tokio::signal;
 use tower_http::cors::{Any, CorsLayer};
 use tracing::{error, info};
 
 use crate::{
+    config::ExecutionBackend,
     context::Context,
+    jobs::{
+        AnalyzeCompletions, CreateCourses, DispatchQueuedAttempts, MigrateRepositories,
+        PruneWorkspaces, ReapExpiredTrials, ReapRepoPool, ReconcilePipelineRuns,
+        RemoveExpiredPipelineRuns, RemoveOrphanedTestCases, RemoveRetiredCredentials,
+        RollUpEngagementEvents,
+    },
     routes,
     service::{RegistryService, RepoService},
     swagger,
@@ -40,10 +48,17 @@ async fn initialize(ctx: Arc<Context>) -> Result<(), Box<dyn std::error::Error>>
     // Fetch required organization and setup webhook for it
     let repo_service = RepoService::new(ctx.clone());
     repo_service.fetch_organization(namespace).await?;
-    repo_service.setup_webhook(namespace).await?;
+    repo_service.setup_webhooks(namespace).await?;
 
-    // Ensure the namespace exists as a project in Harbor
-    RegistryService::ensure_project(&ctx, namespace).await?;
+    // ... and the same for the organization of trial repositories
+    repo_service.fetch_organization(&ctx.config.trial_org).await?;
+    repo_service.setup_webhooks(&ctx.config.trial_org).await?;
+
+    // Ensure the namespace exists as a project in Harbor, standalone
+    // deployments have none
+    if ctx.config.execution_backend == ExecutionBackend::Tekton {
+        RegistryService::ensure_project(&ctx, namespace).await?;
+    }
 
     Ok(())
 }
@@ -58,24 +73,84 @@ pub async fn run(ctx: Arc<Context>) {
         std::process::exit(1);
     }
 
+    // Push the updates of user courses to the status streams
+    if let Err(e) = ctx.status.start(ctx.database.pool()).await {
+        error!("Failed to listen to status updates: {}", e);
+        std::process::exit(1);
+    }
+
+    // Start the background jobs, those for the cluster and registry only
+    // with the tekton backend
+    if ctx.config.execution_backend == ExecutionBackend::Tekton {
+        ctx.jobs.spawn(DispatchQueuedAttempts::new(ctx.clone()));
+        ctx.jobs.spawn(ReconcilePipelineRuns::new(ctx.clone()));
+        ctx.jobs.spawn(RemoveExpiredPipelineRuns::new(ctx.clone()));
+        ctx.jobs.spawn(RemoveRetiredCredentials::new(ctx.clone()));
+        ctx.jobs.spawn(RemoveOrphanedTestCases::new(ctx.clone()));
+    }
+    ctx.jobs.spawn(AnalyzeCompletions::new(ctx.clone()));
+    ctx.jobs.spawn(ReapExpiredTrials::new(ctx.clone()));
+    ctx.jobs.spawn(ReapRepoPool::new(ctx.clone()));
+    ctx.jobs.spawn(MigrateRepositories::new(ctx.clone()));
+    ctx.jobs.spawn(CreateCourses::new(ctx.clone()));
+    ctx.jobs.spawn(RollUpEngagementEvents::new(ctx.clone()));
+    ctx.jobs.spawn(PruneWorkspaces::new(ctx.clone()));
+
     // Build our application with a route
     let Ok(cors) = configure_cors(&ctx.config.allowed_origin) else {
         error!("Invalid CORS configuration: invalid origin format");
         std::process::exit(1);
     };
 
-    let app = routes::build().merge(swagger::build()).layer(cors).with_state(ctx);
+    let app = routes::build(ctx.clone()).merge(swagger::build()).layer(cors);
 
     // Run our app with hyper, and serve it over HTTP
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
     info!("Server running on {}", addr);
 
-    // Run this server for ... forever!
-    if let Err(err) = axum::serve(listener, app).await {
+    // Run this server until asked to stop, with the peer address of every
+    // connection at hand
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    if let Err(err) = axum::serve(listener, app).with_graceful_shutdown(shutdown_signal()).await {
         tracing::error!("Server error: {}", err);
         std::process::exit(1)
     }
+
+    // Let the running background jobs finish
+    info!("Shutting down background jobs");
+    ctx.jobs.shutdown(Duration::from_secs(ctx.config.job_drain_timeout)).await;
+}
+
+/// Resolves when the process receives Ctrl+C or SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
 }
 
 /// Configures CORS middleware based on the allowed origin
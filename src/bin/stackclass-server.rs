@@ -28,7 +28,9 @@ async fn main() -> anyhow::Result<()> {
     // Parse our configuration from the environment.
     // This will exit with a help message if something is wrong.
     // Then, initialize the shared context.
-    let ctx = Arc::new(Context::new(Config::parse()).await?);
+    let config = Config::parse();
+    config.validate()?;
+    let ctx = Arc::new(Context::new(config).await?);
 
     // Running the application in a loop.
     app::run(ctx.clone()).await;
@@ -26,7 +26,33 @@
 //
 // See `.env.example` in the repository root for details.
 
-use std::path::PathBuf;
+use std::{net::IpAddr, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+use crate::{schema::ResourceProfile, utils::resources::PodResources};
+
+/// Where attempts are graded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExecutionBackend {
+    /// Tekton PipelineRuns in the Kubernetes cluster, with images in Harbor
+    Tekton,
+
+    /// Containers of the local Docker or Podman daemon, for standalone
+    /// deployments
+    Local,
+}
+
+/// Error type for settings that clap can not check on its own
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("--{0} is required by the tekton execution backend")]
+    Missing(&'static str),
+
+    #[error("The local execution backend needs a build with the `local-runner` feature")]
+    Unsupported,
+}
 
 #[derive(Clone, clap::Parser)]
 pub struct Config {
@@ -38,6 +64,15 @@ pub struct Config {
     #[clap(long, env)]
     pub cache_dir: PathBuf,
 
+    /// Base directory for the working clones of template repositories kept
+    /// between course syncs, `<cache_dir>/workspaces` when unset.
+    #[clap(long, env)]
+    pub work_dir: Option<PathBuf>,
+
+    /// Time in seconds a working clone is kept after its last sync.
+    #[clap(long, env, default_value = "604800")]
+    pub workspace_ttl: u64,
+
     /// A personal token to use for authentication.
     #[clap(long, env)]
     pub github_token: Option<String>,
@@ -66,10 +101,47 @@ pub struct Config {
     #[clap(long, env)]
     pub git_server_password: String,
 
+    /// Personal access token for the API of the git server, used instead of
+    /// the password when set.
+    #[clap(long, env)]
+    pub git_server_token: Option<String>,
+
+    /// Time limit in seconds of requests to the API of the git server,
+    /// unlimited when unset.
+    #[clap(long, env)]
+    pub git_server_timeout_secs: Option<u64>,
+
+    /// PEM file of a private CA certificate the git server is signed by,
+    /// trusted on top of the system roots.
+    #[clap(long, env)]
+    pub git_server_ca_cert: Option<PathBuf>,
+
+    /// SSH endpoint of the git server, e.g. `ssh://git@git.local:2222`. When
+    /// set, each generated repository gets a read-only deploy key, which the
+    /// pipelines clone it over SSH with.
+    #[clap(long, env)]
+    pub git_server_ssh_endpoint: Option<String>,
+
+    /// Host keys of the SSH endpoint of the git server, in `known_hosts`
+    /// format. Pipelines accept the presented host key when unset.
+    #[clap(long, env)]
+    pub git_server_ssh_known_hosts: Option<String>,
+
     /// Webhook handler endpoint.
     #[clap(long, env)]
     pub webhook_endpoint: String,
 
+    /// Whether the push webhook is attached to each generated repository
+    /// instead of the organizations, so that unrelated repositories of the
+    /// namespace do not reach us. Turning it on moves the webhook of the
+    /// existing repositories over at the next start.
+    #[clap(long, env, default_value = "false", action = clap::ArgAction::Set)]
+    pub repo_webhooks: bool,
+
+    /// Base URL of the frontend, where shared links redirect to.
+    #[clap(long, env, default_value = "https://stackclass.dev")]
+    pub frontend_base_url: String,
+
     /// Git committer name.
     #[clap(long, env, default_value = "StackClass")]
     pub git_committer_name: String,
@@ -78,23 +150,337 @@ pub struct Config {
     #[clap(long, env, default_value = "hello@stackclass.dev")]
     pub git_committer_email: String,
 
+    /// Default branch of the template and learner repositories, the one
+    /// whose pushes are graded.
+    #[clap(long, env, default_value = "main")]
+    pub default_branch: String,
+
     /// Kubernetes namespace where StackClass is running.
     #[clap(long, env)]
     pub namespace: String,
 
-    /// Docker registry endpoint.
-    #[clap(long, env)]
+    /// Docker registry endpoint, only needed by the tekton execution backend.
+    #[clap(long, env, default_value = "")]
     pub docker_registry_endpoint: String,
 
     /// Username for authenticating with the harbor server.
-    #[clap(long, env)]
+    #[clap(long, env, default_value = "")]
     pub docker_registry_username: String,
 
     /// Password for authenticating with the harbor server.
-    #[clap(long, env)]
+    #[clap(long, env, default_value = "")]
     pub docker_registry_password: String,
 
+    /// Time in seconds rotated registry credentials stay valid, so that
+    /// pipelines started before the rotation can still push.
+    #[clap(long, env, default_value = "7200")]
+    pub registry_credentials_grace: i64,
+
+    /// Whether the registry projects notify us of pushed images, so that
+    /// learners see their image built before the tests start.
+    #[clap(long, env, default_value = "false", action = clap::ArgAction::Set)]
+    pub registry_webhooks: bool,
+
     /// Password hashing or signature secret key.
     #[clap(long, env)]
     pub auth_secret: String,
+
+    /// Time until which pipeline events signed in the format used before
+    /// runs were bound to their name are still accepted, so that the runs
+    /// triggered before an upgrade can complete, e.g.
+    /// `2026-10-19T00:00:00Z`. They are rejected when unset.
+    #[clap(long, env)]
+    pub legacy_signatures_until: Option<DateTime<Utc>>,
+
+    /// Maximum number of concurrent status streams per user.
+    #[clap(long, env, default_value = "10")]
+    pub max_streams_per_user: usize,
+
+    /// Maximum number of concurrent status streams across all users.
+    #[clap(long, env, default_value = "1000")]
+    pub max_streams_total: usize,
+
+    /// Maximum lifetime of a status stream in seconds, after which clients
+    /// are told to reconnect.
+    #[clap(long, env, default_value = "1800")]
+    pub max_stream_secs: u64,
+
+    /// Whether attempt budgets reset when a course sync changes the stage content.
+    #[clap(long, env, default_value = "true", action = clap::ArgAction::Set)]
+    pub reset_attempts_on_change: bool,
+
+    /// Number of failed graded attempts after which the solution of a stage
+    /// is unlocked. Solutions only unlock once the stage is completed when
+    /// unset.
+    #[clap(long, env)]
+    pub solution_unlock_failures: Option<u32>,
+
+    /// Whether the repository of a learner is archived, i.e. made read-only,
+    /// once the last stage of the course is completed.
+    #[clap(long, env, default_value = "false", action = clap::ArgAction::Set)]
+    pub archive_completed_repositories: bool,
+
+    /// Resources of test pods of the `small` profile, the default one, as
+    /// `cpu=<request>/<limit>,memory=<request>/<limit>`.
+    #[clap(long, env, default_value = "cpu=250m/500m,memory=256Mi/512Mi")]
+    pub pipeline_resources_small: PodResources,
+
+    /// Resources of test pods of the `medium` profile.
+    #[clap(long, env, default_value = "cpu=500m/1,memory=512Mi/1Gi")]
+    pub pipeline_resources_medium: PodResources,
+
+    /// Resources of test pods of the `large` profile.
+    #[clap(long, env, default_value = "cpu=1/2,memory=1Gi/2Gi")]
+    pub pipeline_resources_large: PodResources,
+
+    /// Largest test cases JSON in bytes passed to pipelines as a param,
+    /// larger ones are passed in a ConfigMap mounted as a workspace.
+    #[clap(long, env, default_value = "4096")]
+    pub pipeline_test_cases_limit: usize,
+
+    /// Container of the test pod running the tester, whose output is
+    /// streamed to learners.
+    #[clap(long, env, default_value = "step-test")]
+    pub tester_container: String,
+
+    /// Maximum number of tester output lines per second streamed to a
+    /// learner, further lines are dropped.
+    #[clap(long, env, default_value = "50")]
+    pub log_stream_rate: u32,
+
+    /// Where attempts are graded, `tekton` or `local`. The local backend
+    /// does without Kubernetes and Harbor.
+    #[clap(long, env, value_enum, default_value = "tekton")]
+    pub execution_backend: ExecutionBackend,
+
+    /// Socket of the Docker or Podman daemon used by the local backend, the
+    /// default local socket when unset.
+    #[clap(long, env)]
+    pub local_runner_socket: Option<String>,
+
+    /// Maximum number of attempts graded at the same time by the local backend.
+    #[clap(long, env, default_value = "2")]
+    pub local_runner_concurrency: usize,
+
+    /// Time in seconds after which the local backend stops a test run.
+    #[clap(long, env, default_value = "600")]
+    pub local_runner_timeout: u64,
+
+    /// Interval in seconds between Kubernetes API health checks, which also
+    /// dispatch the pipeline runs queued during an outage.
+    #[clap(long, env, default_value = "15")]
+    pub cluster_health_interval: u64,
+
+    /// Maximum number of PipelineRuns running at once for a user, further
+    /// pushes are queued until one finishes. Zero lifts the limit.
+    #[clap(long, env, default_value = "3")]
+    pub max_concurrent_pipelines_per_user: usize,
+
+    /// Time in seconds finished PipelineRuns are kept for debugging before
+    /// they are swept, along with their workspace.
+    #[clap(long, env, default_value = "3600")]
+    pub pipeline_run_retention: u64,
+
+    /// Age in seconds after which PipelineRuns that are still not finished
+    /// are swept as stuck.
+    #[clap(long, env, default_value = "172800")]
+    pub pipeline_run_max_age: u64,
+
+    /// Interval in seconds between sweeps of expired PipelineRuns.
+    #[clap(long, env, default_value = "600")]
+    pub pipeline_sweep_interval: u64,
+
+    /// Time in seconds the settings changed at runtime, like the maintenance
+    /// mode, are cached by each replica.
+    #[clap(long, env, default_value = "5")]
+    pub settings_cache_ttl: u64,
+
+    /// Maximum size in bytes of a push through the git proxy.
+    #[clap(long, env, default_value = "104857600")]
+    pub max_receive_pack_size: u64,
+
+    /// Maximum size in bytes of a clone or fetch through the git proxy,
+    /// 0 disables the limit.
+    #[clap(long, env, default_value = "0")]
+    pub max_upload_pack_size: u64,
+
+    /// Number of completions within the integrity window that flag a learner
+    /// as completing stages suspiciously fast, 0 disables the rule.
+    #[clap(long, env, default_value = "5")]
+    pub integrity_rapid_completions: usize,
+
+    /// Length in seconds of the window of the rapid completions rule.
+    #[clap(long, env, default_value = "600")]
+    pub integrity_rapid_window: i64,
+
+    /// Minimum time in seconds between starting and completing a stage,
+    /// 0 disables the rule.
+    #[clap(long, env, default_value = "0")]
+    pub integrity_min_stage_seconds: i64,
+
+    /// Median failed attempts per stage of a course at or above which passing a
+    /// stage without any failure is flagged, 0 disables the rule.
+    #[clap(long, env, default_value = "4")]
+    pub integrity_median_failures: f64,
+
+    /// Gitea organization holding the repositories of trials.
+    #[clap(long, env, default_value = "trials")]
+    pub trial_org: String,
+
+    /// Time in seconds a trial repository is kept before it is deleted.
+    #[clap(long, env, default_value = "3600")]
+    pub trial_ttl: i64,
+
+    /// Maximum number of active trials across all visitors.
+    #[clap(long, env, default_value = "100")]
+    pub max_active_trials: i64,
+
+    /// Maximum number of trials a client may start within the rate window.
+    #[clap(long, env, default_value = "3")]
+    pub trial_rate_limit: i64,
+
+    /// Length in seconds of the window of the trial rate limit.
+    #[clap(long, env, default_value = "3600")]
+    pub trial_rate_window: i64,
+
+    /// URL verifying the captcha tokens of trial requests, in the siteverify
+    /// format shared by hCaptcha, reCAPTCHA and Turnstile. Trials are
+    /// disabled when unset.
+    #[clap(long, env)]
+    pub captcha_verify_url: Option<String>,
+
+    /// Secret key sent along with captcha tokens to the verification URL.
+    #[clap(long, env)]
+    pub captcha_secret: Option<String>,
+
+    /// Addresses of the reverse proxies in front of the server, e.g. the
+    /// ingress, whose `X-Forwarded-For` header names the client of a trial.
+    /// Requests from other peers are attributed to the peer itself.
+    #[clap(long, env, value_delimiter = ',')]
+    pub trusted_proxies: Vec<IpAddr>,
+
+    /// Maximum number of repositories imported from another Gitea instance
+    /// at the same time.
+    #[clap(long, env, default_value = "4")]
+    pub repo_migration_concurrency: usize,
+
+    /// Time in seconds a pre-provisioned repository is kept in the pool
+    /// before it is deleted.
+    #[clap(long, env, default_value = "172800")]
+    pub repo_pool_ttl: i64,
+
+    /// Maximum number of engagement events a user may report within the rate
+    /// window.
+    #[clap(long, env, default_value = "120")]
+    pub engagement_rate_limit: i64,
+
+    /// Length in seconds of the window of the engagement rate limit.
+    #[clap(long, env, default_value = "60")]
+    pub engagement_rate_window: i64,
+
+    /// Time in seconds raw engagement events are kept before they are rolled
+    /// up into per learner counts and purged.
+    #[clap(long, env, default_value = "2592000")]
+    pub engagement_retention: i64,
+
+    /// URL of the relay sending notification emails, e.g. in front of an
+    /// SMTP server. Email notifications are not sent when unset.
+    #[clap(long, env)]
+    pub notification_relay_url: Option<String>,
+
+    /// Names of the background jobs that must not run.
+    #[clap(long, env, value_delimiter = ',')]
+    pub disabled_jobs: Vec<String>,
+
+    /// Time in seconds granted to running background jobs on shutdown.
+    #[clap(long, env, default_value = "30")]
+    pub job_drain_timeout: u64,
+}
+
+impl Config {
+    /// Checks the settings which are only required by some execution
+    /// backends.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        match self.execution_backend {
+            ExecutionBackend::Tekton => {
+                let registry = [
+                    ("docker-registry-endpoint", &self.docker_registry_endpoint),
+                    ("docker-registry-username", &self.docker_registry_username),
+                    ("docker-registry-password", &self.docker_registry_password),
+                ];
+                match registry.into_iter().find(|(_, value)| value.is_empty()) {
+                    Some((name, _)) => Err(ConfigError::Missing(name)),
+                    None => Ok(()),
+                }
+            }
+            ExecutionBackend::Local if cfg!(feature = "local-runner") => Ok(()),
+            ExecutionBackend::Local => Err(ConfigError::Unsupported),
+        }
+    }
+
+    /// Full reference of the default branch, as found in push events.
+    pub fn default_ref(&self) -> String {
+        format!("refs/heads/{}", self.default_branch)
+    }
+
+    /// Base directory for the working clones of template repositories.
+    pub fn workspace_dir(&self) -> PathBuf {
+        self.work_dir.clone().unwrap_or_else(|| self.cache_dir.join("workspaces"))
+    }
+
+    /// Resources of test pods of the given profile.
+    pub fn pod_resources(&self, profile: ResourceProfile) -> &PodResources {
+        match profile {
+            ResourceProfile::Small => &self.pipeline_resources_small,
+            ResourceProfile::Medium => &self.pipeline_resources_medium,
+            ResourceProfile::Large => &self.pipeline_resources_large,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Config {
+        let required = [
+            "stackclass-server",
+            "--cache-dir=/tmp/stackclass",
+            "--database-url=postgres://localhost/stackclass",
+            "--git-proxy-endpoint=http://git.local",
+            "--git-server-endpoint=http://git.local",
+            "--git-server-username=admin",
+            "--git-server-password=admin",
+            "--webhook-endpoint=http://api.local",
+            "--namespace=stackclass",
+            "--auth-secret=secret",
+        ];
+        Config::parse_from(required.iter().chain(args))
+    }
+
+    #[test]
+    fn test_registry_is_required_by_tekton() {
+        let config = parse(&["--docker-registry-endpoint=http://docker.local"]);
+        assert_eq!(config.validate(), Err(ConfigError::Missing("docker-registry-username")));
+
+        let config = parse(&[
+            "--docker-registry-endpoint=http://docker.local",
+            "--docker-registry-username=admin",
+            "--docker-registry-password=admin",
+        ]);
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_local_backend_needs_no_registry() {
+        let config = parse(&["--execution-backend=local"]);
+        assert_eq!(config.execution_backend, ExecutionBackend::Local);
+
+        match cfg!(feature = "local-runner") {
+            true => assert_eq!(config.validate(), Ok(())),
+            false => assert_eq!(config.validate(), Err(ConfigError::Unsupported)),
+        }
+    }
 }
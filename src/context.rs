@@ -12,17 +12,39 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::{sync::Arc, time::Duration};
+
+use axum::http::Uri;
 use gitea_client::GiteaClient;
 use harbor_client::HarborClient;
 use reqwest::Client;
 
-use crate::{config::Config, database::Database, errors::Result};
+#[cfg(feature = "local-runner")]
+use crate::service::LocalRunner;
+use crate::{
+    config::{Config, ExecutionBackend},
+    database::Database,
+    errors::Result,
+    jobs::JobRegistry,
+    service::StorageError,
+    utils::{
+        clock::{Clock, SystemClock},
+        endpoints::Endpoints,
+        health::ClusterHealth,
+        settings::SettingsCache,
+        status::StatusUpdates,
+        stream::StreamTracker,
+    },
+};
 
 /// The core type through which handler functions can access common API state.
 pub struct Context {
     /// Application configuration settings
     pub config: Config,
 
+    /// External endpoints derived from the configuration
+    pub endpoints: Endpoints,
+
     /// Database connection pool and operations
     pub database: Database,
 
@@ -35,20 +57,52 @@ pub struct Context {
     /// Kubernetes client for cluster operations
     pub k8s: kube::Client,
 
+    /// Availability of the Kubernetes API server
+    pub cluster: ClusterHealth,
+
     /// HTTP client for making external requests
     pub http: Client,
+
+    /// Accounting for the open status streams
+    pub streams: StreamTracker,
+
+    /// Updates of user courses, pushed to their status streams
+    pub status: StatusUpdates,
+
+    /// Background jobs of the server
+    pub jobs: JobRegistry,
+
+    /// Settings changed at runtime, shared by all replicas
+    pub settings: SettingsCache,
+
+    /// Source of the current time
+    pub clock: Arc<dyn Clock>,
+
+    /// Runner of the test containers, with the local execution backend
+    #[cfg(feature = "local-runner")]
+    pub runner: Option<LocalRunner>,
 }
 
 impl Context {
     pub async fn new(config: Config) -> Result<Context> {
+        let endpoints = Endpoints::new(&config)?;
         let database = Database::new(&config.database_url).await?;
 
-        // Initialize Gitea client for source control operations
-        let git = GiteaClient::new(
-            config.git_server_endpoint.clone(),
-            config.git_server_username.clone(),
-            config.git_server_password.clone(),
-        );
+        // Initialize Gitea client for source control operations, with a token
+        // on servers where password authentication is disabled
+        let git = GiteaClient::builder(config.git_server_endpoint.clone());
+        let mut git = match &config.git_server_token {
+            Some(token) => git.token(token.clone()),
+            None => git
+                .basic_auth(config.git_server_username.clone(), config.git_server_password.clone()),
+        };
+        if let Some(secs) = config.git_server_timeout_secs {
+            git = git.request_timeout(Duration::from_secs(secs));
+        }
+        if let Some(path) = &config.git_server_ca_cert {
+            git = git.ca_cert_pem(std::fs::read(path).map_err(StorageError::ReadFile)?);
+        }
+        let git = git.build()?;
 
         // Initialize Harbor client for container registry operations
         let harbor = HarborClient::new(
@@ -57,9 +111,46 @@ impl Context {
             config.docker_registry_password.clone(),
         );
 
-        let k8s = kube::Client::try_default().await?;
+        // Standalone deployments grade locally and never reach a cluster
+        let k8s = match config.execution_backend {
+            ExecutionBackend::Tekton => kube::Client::try_default().await?,
+            ExecutionBackend::Local => {
+                let cluster = kube::Config::new(Uri::from_static("http://127.0.0.1:1"));
+                kube::Client::try_from(cluster)?
+            }
+        };
+        // Relies on the rustls crypto provider installed by the clients above
         let http = Client::new();
+        let streams = StreamTracker::new(config.max_streams_per_user, config.max_streams_total);
+
+        let cluster = ClusterHealth::default();
+
+        let jobs = JobRegistry::new(&config.disabled_jobs);
+
+        let settings = SettingsCache::new(Duration::from_secs(config.settings_cache_ttl));
+
+        #[cfg(feature = "local-runner")]
+        let runner = match config.execution_backend {
+            ExecutionBackend::Local => Some(LocalRunner::new(&config)?),
+            ExecutionBackend::Tekton => None,
+        };
 
-        Ok(Context { config, database, git, harbor, k8s, http })
+        Ok(Context {
+            config,
+            endpoints,
+            database,
+            git,
+            harbor,
+            k8s,
+            cluster,
+            http,
+            streams,
+            status: StatusUpdates::default(),
+            jobs,
+            settings,
+            clock: Arc::new(SystemClock),
+            #[cfg(feature = "local-runner")]
+            runner,
+        })
     }
 }
@@ -14,7 +14,7 @@
 
 use axum::{
     Json,
-    http::{self, StatusCode},
+    http::{self, StatusCode, header},
     response::{IntoResponse, Response},
 };
 use serde_json::json;
@@ -22,9 +22,12 @@ use thiserror::Error;
 use tracing::{debug, error};
 
 use crate::{
+    response::{ConflictResponse, RejectionReason},
     schema,
     service::StorageError,
-    utils::{crypto::CryptoError, git::GitError},
+    utils::{
+        crypto::CryptoError, git::GitError, pagination::CursorError, stream::StreamLimitError,
+    },
 };
 
 pub type Result<T, E = ApiError> = std::result::Result<T, E>;
@@ -37,11 +40,29 @@ pub enum ApiError {
     #[error("Unauthorized: {0}")]
     Unauthorized(String),
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("Not Found")]
     NotFound,
 
+    #[error("{0}")]
+    Conflict(String),
+
     #[error("Record already exists")]
-    Conflict,
+    RecordExists,
+
+    #[error("User is already enrolled in this course")]
+    AlreadyEnrolled,
+
+    #[error("User already has a record of this stage")]
+    StageRecordExists,
+
+    #[error("Course already exists")]
+    CourseExists,
+
+    #[error("{0}")]
+    Gone(String),
 
     #[error("Internal Error: {0}")]
     InternalError(String),
@@ -70,6 +91,9 @@ pub enum ApiError {
     #[error("Cannot complete a stage out of order")]
     StageOutOfOrder,
 
+    #[error("Only optional stages can be skipped")]
+    StageNotOptional,
+
     #[error("Gitea client error: {0}")]
     GiteaClientError(#[from] gitea_client::ClientError),
 
@@ -93,25 +117,73 @@ pub enum ApiError {
 
     #[error("Crypto operation failed")]
     CryptoError(#[from] CryptoError),
+
+    #[error("{0}")]
+    TooManyStreams(#[from] StreamLimitError),
+
+    #[error("{0}")]
+    TooManyRequests(String),
+
+    #[error("{0}")]
+    InvalidCursor(#[from] CursorError),
+
+    #[error("Webhook rejected: {0}")]
+    WebhookRejected(#[from] RejectionReason),
+
+    #[error("{message}")]
+    Maintenance { message: String, retry_after: u64 },
+
+    #[cfg(feature = "local-runner")]
+    #[error("Local runner error: {0}")]
+    RunnerError(#[from] crate::service::RunnerError),
 }
 
 impl From<sqlx::Error> for ApiError {
     fn from(e: sqlx::Error) -> Self {
         match e {
-            sqlx::Error::Database(err) if err.is_unique_violation() => ApiError::Conflict,
+            sqlx::Error::Database(err) if err.is_unique_violation() => ApiError::RecordExists,
             sqlx::Error::RowNotFound => ApiError::NotFound,
             _ => ApiError::DatabaseError(e),
         }
     }
 }
 
+impl ApiError {
+    /// Maps database errors like `From` does, except for unique violations
+    /// which become the more specific conflict the caller knows about.
+    pub fn on_duplicate(conflict: ApiError) -> impl FnOnce(sqlx::Error) -> ApiError {
+        move |e| match ApiError::from(e) {
+            ApiError::RecordExists => conflict,
+            e => e,
+        }
+    }
+
+    /// Machine-readable code of a conflict, for clients to tell them apart.
+    pub fn conflict_code(&self) -> Option<&'static str> {
+        match self {
+            ApiError::Conflict(_) => Some("conflict"),
+            ApiError::RecordExists => Some("record_exists"),
+            ApiError::AlreadyEnrolled => Some("already_enrolled"),
+            ApiError::StageRecordExists => Some("stage_record_exists"),
+            ApiError::CourseExists => Some("course_exists"),
+            _ => None,
+        }
+    }
+}
+
 impl From<&ApiError> for StatusCode {
     fn from(val: &ApiError) -> Self {
         match val {
             ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
             ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
             ApiError::NotFound => StatusCode::NOT_FOUND,
-            ApiError::Conflict => StatusCode::CONFLICT,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::RecordExists => StatusCode::CONFLICT,
+            ApiError::AlreadyEnrolled => StatusCode::CONFLICT,
+            ApiError::StageRecordExists => StatusCode::CONFLICT,
+            ApiError::CourseExists => StatusCode::CONFLICT,
+            ApiError::Gone(_) => StatusCode::GONE,
             ApiError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::HTTPError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::StorageError(_) => StatusCode::INTERNAL_SERVER_ERROR,
@@ -121,6 +193,7 @@ impl From<&ApiError> for StatusCode {
             ApiError::StageAlreadyCompleted => StatusCode::BAD_REQUEST,
             ApiError::StageNotInProgress => StatusCode::BAD_REQUEST,
             ApiError::StageOutOfOrder => StatusCode::BAD_REQUEST,
+            ApiError::StageNotOptional => StatusCode::BAD_REQUEST,
             ApiError::GiteaClientError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::GitError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::KubernetesError(_) => StatusCode::INTERNAL_SERVER_ERROR,
@@ -129,12 +202,49 @@ impl From<&ApiError> for StatusCode {
             ApiError::InvalidUuid(_) => StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::HarborClientError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::CryptoError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::TooManyStreams(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::InvalidCursor(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::WebhookRejected(reason) => match reason {
+                RejectionReason::InvalidSignature => StatusCode::UNAUTHORIZED,
+                RejectionReason::UnknownRepo => StatusCode::NOT_FOUND,
+                RejectionReason::UnknownStage => StatusCode::NOT_FOUND,
+                RejectionReason::StalePipeline => StatusCode::CONFLICT,
+            },
+            ApiError::Maintenance { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            #[cfg(feature = "local-runner")]
+            ApiError::RunnerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
+        // Clients restart pagination depending on the kind of cursor error
+        if let ApiError::InvalidCursor(e) = &self {
+            let body = json!({ "code": e.code(), "message": e.to_string() });
+            return (StatusCode::UNPROCESSABLE_ENTITY, Json(body)).into_response();
+        }
+
+        // Clients tell conflicts apart by their code
+        if let Some(code) = self.conflict_code() {
+            let body = ConflictResponse { code: code.to_string(), message: self.to_string() };
+            return (StatusCode::CONFLICT, Json(body)).into_response();
+        }
+
+        // Pipelines log why their completion was dropped
+        if let ApiError::WebhookRejected(reason) = &self {
+            let body = json!({ "rejection_reason": reason, "message": reason.to_string() });
+            return (StatusCode::from(&self), Json(body)).into_response();
+        }
+
+        // Clients retry writes refused during a maintenance once it ends
+        if let ApiError::Maintenance { message, retry_after } = &self {
+            let body = json!({ "message": message });
+            let headers = [(header::RETRY_AFTER, retry_after.to_string())];
+            return (StatusCode::SERVICE_UNAVAILABLE, headers, Json(body)).into_response();
+        }
+
         AutoIntoResponse::into(&self)
     }
 }
@@ -0,0 +1,116 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::convert::Infallible;
+
+use axum::{
+    extract::FromRequestParts,
+    http::{header::ACCEPT, request::Parts},
+};
+
+/// Representations a content endpoint can produce.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MediaType {
+    #[default]
+    Json,
+    Html,
+    Markdown,
+    Text,
+}
+
+impl MediaType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaType::Json => "application/json",
+            MediaType::Html => "text/html; charset=utf-8",
+            MediaType::Markdown => "text/markdown; charset=utf-8",
+            MediaType::Text => "text/plain; charset=utf-8",
+        }
+    }
+
+    fn parse(essence: &str) -> Option<Self> {
+        match essence {
+            "application/json" | "application/*" | "*/*" => Some(MediaType::Json),
+            "text/html" => Some(MediaType::Html),
+            "text/markdown" => Some(MediaType::Markdown),
+            "text/plain" => Some(MediaType::Text),
+            _ => None,
+        }
+    }
+}
+
+/// The preferred media type according to the `Accept` header.
+///
+/// Unknown or missing values fall back to JSON instead of rejecting the
+/// request with 406.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Accept(pub MediaType);
+
+impl Accept {
+    pub fn is_json(&self) -> bool {
+        self.0 == MediaType::Json
+    }
+
+    /// Picks the supported media type with the highest quality value, the
+    /// first one listed wins on ties.
+    pub fn parse(header: &str) -> Self {
+        let mut best: Option<(MediaType, f32)> = None;
+
+        for range in header.split(',') {
+            let mut params = range.split(';').map(str::trim);
+            let essence = params.next().unwrap_or_default().to_ascii_lowercase();
+            let quality = params
+                .filter_map(|p| p.strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            let Some(media) = MediaType::parse(&essence) else { continue };
+            if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+                best = Some((media, quality));
+            }
+        }
+
+        Accept(best.map(|(media, _)| media).unwrap_or_default())
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Accept {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        let header = parts.headers.get(ACCEPT).and_then(|v| v.to_str().ok());
+        Ok(header.map(Accept::parse).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_accept() {
+        assert_eq!(Accept::parse("text/html").0, MediaType::Html);
+        assert_eq!(Accept::parse("text/markdown").0, MediaType::Markdown);
+        assert_eq!(Accept::parse("TEXT/Plain; charset=utf-8").0, MediaType::Text);
+        assert_eq!(Accept::parse("application/json").0, MediaType::Json);
+        assert_eq!(Accept::parse("text/plain;q=0.5, text/html;q=0.9").0, MediaType::Html);
+        assert_eq!(Accept::parse("text/html;q=0, text/plain").0, MediaType::Text);
+        assert_eq!(Accept::parse("text/markdown, text/html").0, MediaType::Markdown);
+
+        // Unknown values fall back to JSON
+        assert_eq!(Accept::parse("image/png").0, MediaType::Json);
+        assert_eq!(Accept::parse("").0, MediaType::Json);
+        assert_eq!(Accept::parse("*/*").0, MediaType::Json);
+    }
+}
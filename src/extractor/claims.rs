@@ -32,6 +32,7 @@ use tracing::debug;
 use crate::{
     context::Context,
     errors::AutoIntoResponse,
+    repository::UserRepository,
     utils::keys::{self, KeysError},
 };
 
@@ -56,6 +57,11 @@ impl FromRequestParts<Arc<Context>> for Claims {
         parts: &mut Parts,
         ctx: &Arc<Context>,
     ) -> Result<Self, Self::Rejection> {
+        // Validated once per request, usually by the route layer already
+        if let Some(claims) = parts.extensions.get::<Claims>() {
+            return Ok(claims.clone());
+        }
+
         // Try to extract the token from the Authorization header first
         let token = match parts.extract::<TypedHeader<Authorization<Bearer>>>().await {
             Ok(TypedHeader(Authorization(bearer))) => bearer.token().to_string(),
@@ -75,18 +81,24 @@ impl FromRequestParts<Arc<Context>> for Claims {
 
         // First attempt with cached keys
         let keys = keys::get_keys().await;
-        if let Some(decoding_key) = keys.read().await.get(&kid) {
-            return validate_token(&token, decoding_key);
-        }
+        let mut claims = keys.read().await.get(&kid).map(|key| validate_token(&token, key));
 
         // If kid not found, refresh keys and try again
-        keys::refresh_keys(ctx.clone()).await?;
+        if claims.is_none() {
+            keys::refresh_keys(ctx.clone()).await?;
+            claims = keys.read().await.get(&kid).map(|key| validate_token(&token, key));
+        }
+
+        let mut claims = claims.ok_or(ClaimsError::KeyNotFound(kid))??;
 
-        if let Some(decoding_key) = keys.read().await.get(&kid) {
-            return validate_token(&token, decoding_key);
+        // Tokens of a merged-away user act on behalf of the user it was
+        // merged into
+        if let Some(id) = UserRepository::merged_into(&ctx.database, &claims.id).await? {
+            claims.id = id;
         }
 
-        Err(ClaimsError::KeyNotFound(kid))
+        parts.extensions.insert(claims.clone());
+        Ok(claims)
     }
 }
 
@@ -124,6 +136,9 @@ pub enum ClaimsError {
 
     #[error("Key operation failed: {0}")]
     KeysError(#[from] KeysError),
+
+    #[error("Failed to look up user: {0}")]
+    DatabaseError(#[from] sqlx::Error),
 }
 
 impl From<&ClaimsError> for StatusCode {
@@ -138,6 +153,7 @@ impl From<&ClaimsError> for StatusCode {
                 KeysError::KeyNotFound(_) => StatusCode::UNAUTHORIZED,
                 _ => StatusCode::INTERNAL_SERVER_ERROR,
             },
+            ClaimsError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
@@ -0,0 +1,143 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    RequestPartsExt,
+    extract::{FromRequestParts, Path},
+    http::{header::AUTHORIZATION, request::Parts},
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    context::Context,
+    errors::ApiError,
+    extractor::{AdminBasic, ApiToken, Claims},
+    request::ApiTokenCapability,
+    service::CourseService,
+};
+
+/// Represents a caller allowed to operate the course in the request path,
+/// either the admin or one of the course maintainers.
+#[derive(Debug)]
+pub enum CourseMaintainer {
+    Admin,
+    Maintainer(Claims),
+}
+
+impl CourseMaintainer {
+    /// Identifier of the caller for the audit trail.
+    pub fn actor(&self) -> &str {
+        match self {
+            CourseMaintainer::Admin => "admin",
+            CourseMaintainer::Maintainer(claims) => &claims.id,
+        }
+    }
+}
+
+/// Accepts admin Basic Auth, or a JWT of a maintainer of the `slug` course.
+impl FromRequestParts<Arc<Context>> for CourseMaintainer {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        ctx: &Arc<Context>,
+    ) -> Result<Self, Self::Rejection> {
+        let basic = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("Basic "));
+        if basic {
+            AdminBasic::from_request_parts(parts, ctx)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            return Ok(CourseMaintainer::Admin);
+        }
+
+        let claims =
+            Claims::from_request_parts(parts, ctx).await.map_err(IntoResponse::into_response)?;
+
+        let slug = course_slug(parts).await?;
+        CourseService::authorize_maintainer(ctx, &slug, &claims.id)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        Ok(CourseMaintainer::Maintainer(claims))
+    }
+}
+
+/// Represents a caller allowed to read the course in the request path,
+/// either a course maintainer or an API token scoped to the course.
+#[derive(Debug)]
+pub enum CourseReader {
+    Maintainer(CourseMaintainer),
+    Token(ApiToken),
+}
+
+impl CourseReader {
+    /// Checks that the caller may read the given data of the course, which
+    /// tokens must have been granted explicitly.
+    pub fn require(&self, capability: ApiTokenCapability) -> Result<(), ApiError> {
+        match self {
+            CourseReader::Token(token) if !token.allows(capability) => Err(ApiError::Forbidden(
+                format!("The API token lacks the {} capability", capability.as_str()),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Accepts an API token covering the `slug` course, or whatever
+/// [`CourseMaintainer`] accepts.
+impl FromRequestParts<Arc<Context>> for CourseReader {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        ctx: &Arc<Context>,
+    ) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("Token "));
+        if !token {
+            return CourseMaintainer::from_request_parts(parts, ctx)
+                .await
+                .map(CourseReader::Maintainer);
+        }
+
+        let token =
+            ApiToken::from_request_parts(parts, ctx).await.map_err(IntoResponse::into_response)?;
+        if !token.covers(&course_slug(parts).await?) {
+            let e = ApiError::Forbidden("The API token does not cover this course".into());
+            return Err(e.into_response());
+        }
+
+        Ok(CourseReader::Token(token))
+    }
+}
+
+/// Extracts the slug of the course in the request path.
+async fn course_slug(parts: &mut Parts) -> Result<String, Response> {
+    let Path(mut params) = parts
+        .extract::<Path<HashMap<String, String>>>()
+        .await
+        .map_err(IntoResponse::into_response)?;
+    params
+        .remove("slug")
+        .ok_or_else(|| ApiError::InternalError("missing course slug".into()).into_response())
+}
@@ -12,9 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod accept;
 mod basic;
 mod claims;
+mod maintainer;
+mod page;
+mod token;
 
 // Re-exports
+pub use accept::*;
 pub use basic::*;
 pub use claims::*;
+pub use maintainer::*;
+pub use token::*;
@@ -0,0 +1,50 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use axum::{
+    RequestPartsExt,
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+};
+
+use crate::{
+    context::Context,
+    errors::ApiError,
+    request::PageQuery,
+    utils::pagination::{self, Cursor, Page},
+};
+
+/// Extracts the page of a listing from `?cursor=&limit=`, clamping the limit
+/// and rejecting tampered or expired cursors with 422.
+impl FromRequestParts<Arc<Context>> for Page {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        ctx: &Arc<Context>,
+    ) -> Result<Self, Self::Rejection> {
+        let Query(query) = parts
+            .extract::<Query<PageQuery>>()
+            .await
+            .map_err(|e| ApiError::BadRequest(e.body_text()))?;
+
+        let after = match query.cursor.as_deref() {
+            None | Some("") => None,
+            Some(token) => Some(Cursor::decode(token, ctx.clock.now(), &ctx.config.auth_secret)?),
+        };
+        Ok(Self { after, limit: pagination::clamp_limit(query.limit) })
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts},
+};
+use uuid::Uuid;
+
+use crate::{
+    context::Context, errors::ApiError, request::ApiTokenCapability, service::ApiTokenService,
+};
+
+/// Represents an integration authenticated by an API token, sent as
+/// `Authorization: Token <secret>`.
+#[derive(Debug, Clone)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub courses: Vec<String>,
+    pub capabilities: Vec<String>,
+}
+
+impl ApiToken {
+    /// Whether the token may read the course.
+    pub fn covers(&self, slug: &str) -> bool {
+        self.courses.iter().any(|course| course == slug)
+    }
+
+    /// Whether the token was granted the capability.
+    pub fn allows(&self, capability: ApiTokenCapability) -> bool {
+        self.capabilities.iter().any(|c| c == capability.as_str())
+    }
+}
+
+/// Extracts and validates the API token of the request
+impl FromRequestParts<Arc<Context>> for ApiToken {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        ctx: &Arc<Context>,
+    ) -> Result<Self, Self::Rejection> {
+        // Authenticated and audited once per request
+        if let Some(token) = parts.extensions.get::<ApiToken>() {
+            return Ok(token.clone());
+        }
+
+        let secret = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Token "))
+            .ok_or_else(|| ApiError::Unauthorized("Missing API token".into()))?;
+
+        let method = parts.method.as_str();
+        let token =
+            ApiTokenService::authenticate(ctx, secret.trim(), method, parts.uri.path()).await?;
+
+        let token =
+            ApiToken { id: token.id, courses: token.courses, capabilities: token.capabilities };
+        parts.extensions.insert(token.clone());
+        Ok(token)
+    }
+}
@@ -0,0 +1,1066 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use axum::{
+    Json,
+    body::Body,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{
+        IntoResponse, Sse,
+        sse::{Event, KeepAlive},
+    },
+};
+use futures::{Stream, StreamExt};
+use std::{convert::Infallible, sync::Arc};
+use tracing::info;
+use utoipa::OpenApi;
+use uuid::Uuid;
+
+use crate::{
+    context::Context,
+    errors::Result,
+    extractor::{AdminBasic, CourseMaintainer, CourseReader},
+    request::{
+        AddMaintainerRequest, AdminAttemptQuery, ApiTokenCapability, AuditLogQuery,
+        CourseSettingsRequest, CreateApiTokenRequest, DismissFlagRequest, ExamWindowRequest,
+        ExtendDeadlineRequest, GrantAttemptsRequest, IntegrityFlagQuery, MaintenanceRequest,
+        MergeUsersRequest, MigrateRepositoriesRequest, PageQuery, PreprovisionRequest,
+        ProgressQuery, RepoMigrationQuery,
+    },
+    response::{
+        AdminSummaryResponse, ApiTokenResponse, AuditLogResponse, ConflictResponse,
+        CourseDetailResponse, CourseSettingsResponse, EffectiveSettingsResponse,
+        GarbageCollectionResponse, GarbageCollectionStatusResponse, IntegrityFlagResponse,
+        JobResponse, MaintainerResponse, MaintenanceResponse, MergeUsersResponse,
+        MigrateRepositoriesResponse, OrphanedRepositoryResponse, Paginated, PipelineRunResponse,
+        PreprovisionResponse, PreviewTokenResponse, ProgressResponse, RebuildProgressResponse,
+        RegistryCredentialResponse, RepoMigrationReportResponse, ResourceProfileResponse,
+        RouteResponse, SnapshotResponse, StageAttemptResponse, StageEngagementResponse,
+        StageLogEnd, StageLogLine, StreamSummary, UserCourseResponse, UserStageResponse,
+    },
+    routes,
+    schema::ResourceProfile,
+    service::{
+        ApiTokenService, AuditService, CourseService, EngagementService, IntegrityService,
+        LogEvent, LogService, MaintenanceService, MetaService, PipelineService, RegistryService,
+        RepoMigrationService, RepoPoolService, RepoService, SettingsService, SnapshotService,
+        StageService, UserService,
+    },
+    swagger::ApiDoc,
+    utils::{pagination::Page, stream::json_event},
+};
+
+// The Admin Service Handlers.
+
+/// Summarize the runtime state of the server.
+#[utoipa::path(
+    operation_id = "get-admin-summary",
+    get, path = "/v1/admin/summary",
+    responses(
+        (status = 200, description = "Summary retrieved successfully", body = AdminSummaryResponse),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn summary(_: AdminBasic, State(ctx): State<Arc<Context>>) -> Result<impl IntoResponse> {
+    let summary = AdminSummaryResponse {
+        streams: StreamSummary::from(&ctx.streams),
+        maintenance: MaintenanceService::current(&ctx).await?.into(),
+    };
+    Ok((StatusCode::OK, Json(summary)))
+}
+
+/// Turn the read-only maintenance mode on or off.
+#[utoipa::path(
+    operation_id = "set-maintenance",
+    put, path = "/v1/admin/maintenance",
+    request_body(
+        content = MaintenanceRequest,
+        description = "Maintenance request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Maintenance mode set successfully", body = MaintenanceResponse),
+        (status = 400, description = "Expiry in the past"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Failed to set maintenance mode")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn set_maintenance(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Json(req): Json<MaintenanceRequest>,
+) -> Result<impl IntoResponse> {
+    let res = MaintenanceService::set(&ctx, &req, "admin").await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Replace the instance defaults of course settings, applied to courses
+/// which leave a setting unset.
+#[utoipa::path(
+    operation_id = "set-course-defaults",
+    put, path = "/v1/admin/course-defaults",
+    request_body(
+        content = CourseSettingsRequest,
+        description = "Course defaults request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Defaults set successfully", body = CourseSettingsResponse),
+        (status = 400, description = "Invalid settings"),
+        (status = 401, description = "Unauthorized"),
+        (status = 422, description = "Unknown setting"),
+        (status = 500, description = "Failed to set defaults")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn set_course_defaults(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Json(req): Json<CourseSettingsRequest>,
+) -> Result<impl IntoResponse> {
+    let res = SettingsService::set_defaults(&ctx, req, "admin").await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Report the state of the background jobs.
+#[utoipa::path(
+    operation_id = "find-admin-jobs",
+    get, path = "/v1/admin/jobs",
+    responses(
+        (status = 200, description = "Jobs retrieved successfully", body = Vec<JobResponse>),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn find_jobs(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+) -> Result<impl IntoResponse> {
+    let jobs: Vec<JobResponse> = ctx.jobs.statuses().into_iter().map(Into::into).collect();
+    Ok((StatusCode::OK, Json(jobs)))
+}
+
+/// List the routes of the API, with the authentication they require.
+#[utoipa::path(
+    operation_id = "find-admin-routes",
+    get, path = "/v1/admin/routes",
+    responses(
+        (status = 200, description = "Routes retrieved successfully", body = Vec<RouteResponse>),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn find_routes(_: AdminBasic) -> Result<impl IntoResponse> {
+    let doc = ApiDoc::openapi();
+    let routes: Vec<RouteResponse> =
+        routes::table().iter().map(|route| RouteResponse::new(route, &doc)).collect();
+    Ok((StatusCode::OK, Json(routes)))
+}
+
+/// List the resource profiles stages may run their tests with.
+#[utoipa::path(
+    operation_id = "find-resource-profiles",
+    get, path = "/v1/admin/resource-profiles",
+    responses(
+        (status = 200, description = "Profiles retrieved successfully", body = Vec<ResourceProfileResponse>),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn find_resource_profiles(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+) -> Result<impl IntoResponse> {
+    let profiles: Vec<ResourceProfileResponse> = ResourceProfile::ALL
+        .into_iter()
+        .map(|profile| (profile, ctx.config.pod_resources(profile)).into())
+        .collect();
+    Ok((StatusCode::OK, Json(profiles)))
+}
+
+/// Find a page of the audit trail.
+#[utoipa::path(
+    operation_id = "find-audit-logs",
+    get, path = "/v1/admin/audit-logs",
+    params(
+        AuditLogQuery,
+        PageQuery,
+    ),
+    responses(
+        (status = 200, description = "Audit logs retrieved successfully", body = Paginated<AuditLogResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 422, description = "Invalid or expired cursor"),
+        (status = 500, description = "Failed to fetch audit logs")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn find_audit_logs(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Query(query): Query<AuditLogQuery>,
+    page: Page,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(AuditService::find(ctx, &query, &page).await?)))
+}
+
+/// Grant extra graded attempts for a stage to a learner.
+#[utoipa::path(
+    operation_id = "grant-stage-attempts",
+    post, path = "/v1/admin/users/{id}/courses/{slug}/stages/{stage_slug}/grant-attempts",
+    params(
+        ("id" = String, description = "The id of the user"),
+        ("slug" = String, description = "The slug of course"),
+        ("stage_slug" = String, description = "The slug of stage"),
+    ),
+    request_body(
+        content = GrantAttemptsRequest,
+        description = "Grant attempts request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Attempts granted successfully", body = UserStageResponse),
+        (status = 400, description = "Invalid attempt count"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "User stage not found"),
+        (status = 500, description = "Failed to grant attempts")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn grant_attempts(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path((id, slug, stage_slug)): Path<(String, String, String)>,
+    Json(req): Json<GrantAttemptsRequest>,
+) -> Result<impl IntoResponse> {
+    let res = StageService::grant_attempts(ctx, &id, &slug, &stage_slug, req.count).await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Download the learner repository as it was when the stage was passed.
+#[utoipa::path(
+    operation_id = "download-stage-snapshot",
+    get, path = "/v1/admin/users/{id}/courses/{slug}/stages/{stage_slug}/snapshot",
+    params(
+        ("id" = String, description = "The id of the user"),
+        ("slug" = String, description = "The slug of course"),
+        ("stage_slug" = String, description = "The slug of stage"),
+    ),
+    responses(
+        (status = 200, description = "Snapshot streamed successfully", content_type = "application/gzip"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a maintainer of the course"),
+        (status = 404, description = "User stage not found or completed without a recorded commit"),
+        (status = 410, description = "Commit or repository no longer available"),
+        (status = 500, description = "Failed to fetch snapshot")
+    ),
+    security(("AdminBasicAuth" = []), ("JWTBearerAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn download_snapshot(
+    caller: CourseMaintainer,
+    State(ctx): State<Arc<Context>>,
+    Path((id, slug, stage_slug)): Path<(String, String, String)>,
+) -> Result<impl IntoResponse> {
+    let (commit, archive) =
+        SnapshotService::download(ctx, &id, &slug, &stage_slug, caller.actor()).await?;
+
+    let filename = format!("{slug}-{stage_slug}-{id}-{commit}.tar.gz");
+    let headers = [
+        (header::CONTENT_TYPE, "application/gzip".to_string()),
+        (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{filename}\"")),
+    ];
+    Ok((StatusCode::OK, headers, Body::from_stream(archive)))
+}
+
+/// List the files of the learner repository as it was when the stage was
+/// passed.
+#[utoipa::path(
+    operation_id = "get-stage-snapshot-tree",
+    get, path = "/v1/admin/users/{id}/courses/{slug}/stages/{stage_slug}/snapshot/tree",
+    params(
+        ("id" = String, description = "The id of the user"),
+        ("slug" = String, description = "The slug of course"),
+        ("stage_slug" = String, description = "The slug of stage"),
+    ),
+    responses(
+        (status = 200, description = "Snapshot retrieved successfully", body = SnapshotResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a maintainer of the course"),
+        (status = 404, description = "User stage not found or completed without a recorded commit"),
+        (status = 410, description = "Commit or repository no longer available"),
+        (status = 500, description = "Failed to fetch snapshot")
+    ),
+    security(("AdminBasicAuth" = []), ("JWTBearerAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn get_snapshot_tree(
+    caller: CourseMaintainer,
+    State(ctx): State<Arc<Context>>,
+    Path((id, slug, stage_slug)): Path<(String, String, String)>,
+) -> Result<impl IntoResponse> {
+    let res = SnapshotService::tree(ctx, &id, &slug, &stage_slug, caller.actor()).await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Stream the tester output of the latest attempt of a stage of a learner
+/// while it is graded.
+#[utoipa::path(
+    operation_id = "stream-learner-stage-logs",
+    get, path = "/v1/admin/users/{id}/courses/{slug}/stages/{stage_slug}/logs/stream",
+    params(
+        ("id" = String, description = "The id of the user"),
+        ("slug" = String, description = "The slug of course"),
+        ("stage_slug" = String, description = "The slug of stage"),
+    ),
+    responses(
+        (status = 200, description = "Successfully started streaming the tester output, one \
+            `line` event per line and an `end` event with the outcome once the run is over. \
+            Without a run in progress only the `end` event is sent",
+            content(
+                (StageLogLine = "text/event-stream"),
+                (StageLogEnd = "text/event-stream"),
+            )
+        ),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a maintainer of the course"),
+        (status = 404, description = "User stage not found"),
+        (status = 429, description = "Too many concurrent streams"),
+        (status = 500, description = "Failed to stream the tester output")
+    ),
+    security(("AdminBasicAuth" = []), ("JWTBearerAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn stream_stage_logs(
+    caller: CourseMaintainer,
+    State(ctx): State<Arc<Context>>,
+    Path((id, slug, stage_slug)): Path<(String, String, String)>,
+) -> Result<Sse<impl Stream<Item = axum::response::Result<Event, Infallible>>>> {
+    info!(
+        "Starting to stream test output of stage {stage_slug} in course {slug} of user {id} for {}...",
+        caller.actor()
+    );
+
+    // Reserve a stream slot for the caller, released once the stream is dropped.
+    let guard = ctx.streams.acquire(caller.actor())?;
+
+    let events = LogService::stream(ctx, &id, &slug, &stage_slug).await?;
+    let stream = events.map(move |event| {
+        let _ = &guard;
+        Ok(match event {
+            LogEvent::Line(line) => json_event(&line),
+            LogEvent::End(end) => json_event(&end),
+        })
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Merge a duplicate account into the account to keep.
+#[utoipa::path(
+    operation_id = "merge-users",
+    post, path = "/v1/admin/users/merge",
+    request_body(
+        content = MergeUsersRequest,
+        description = "Merge users request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Users merged successfully", body = MergeUsersResponse),
+        (status = 400, description = "Same user, or a user already merged into another"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "User not found"),
+        (status = 500, description = "Failed to merge users")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn merge_users(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Json(req): Json<MergeUsersRequest>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(UserService::merge(ctx, &req, "admin").await?)))
+}
+
+/// Grant a learner a deadline beyond the close of the exam window.
+#[utoipa::path(
+    operation_id = "extend-user-course-deadline",
+    post, path = "/v1/admin/users/{id}/courses/{slug}/extend-deadline",
+    params(
+        ("id" = String, description = "The id of the user"),
+        ("slug" = String, description = "The slug of course"),
+    ),
+    request_body(
+        content = ExtendDeadlineRequest,
+        description = "Extend deadline request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Deadline extended successfully", body = UserCourseResponse),
+        (status = 400, description = "No exam window or deadline before its close"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "User course not found"),
+        (status = 500, description = "Failed to extend deadline")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn extend_deadline(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path((id, slug)): Path<(String, String)>,
+    Json(req): Json<ExtendDeadlineRequest>,
+) -> Result<impl IntoResponse> {
+    let res = CourseService::extend_deadline(ctx, &id, &slug, &req, "admin").await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Find the pipeline runs grading the repository of a learner, newest first.
+#[utoipa::path(
+    operation_id = "find-user-pipelines",
+    get, path = "/v1/admin/users/{id}/courses/{slug}/pipelines",
+    params(
+        ("id" = String, description = "The id of the user"),
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 200, description = "Pipeline runs retrieved successfully", body = Vec<PipelineRunResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "User course not found"),
+        (status = 500, description = "Failed to list pipeline runs")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn find_user_pipelines(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path((id, slug)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    let res = PipelineService::new(ctx).find_by_user_course(&id, &slug).await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Set or clear the exam window of a course.
+#[utoipa::path(
+    operation_id = "set-course-exam-window",
+    put, path = "/v1/admin/courses/{slug}/exam-window",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    request_body(
+        content = ExamWindowRequest,
+        description = "Exam window request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Exam window set successfully", body = CourseDetailResponse),
+        (status = 400, description = "Window closes before it opens"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to set exam window")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn set_exam_window(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+    Json(req): Json<ExamWindowRequest>,
+) -> Result<impl IntoResponse> {
+    let res = CourseService::set_exam_window(ctx, &slug, &req, "admin").await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Fetch the progress of all learners of a course.
+#[utoipa::path(
+    operation_id = "find-course-progress",
+    get, path = "/v1/admin/courses/{slug}/progress",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        ProgressQuery,
+    ),
+    responses(
+        (status = 200, description = "Progress retrieved successfully", body = Vec<ProgressResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a maintainer of the course, or a token not granted its progress"),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to fetch progress")
+    ),
+    security(("AdminBasicAuth" = []), ("JWTBearerAuth" = []), ("ApiTokenAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn find_progress(
+    reader: CourseReader,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+    Query(query): Query<ProgressQuery>,
+) -> Result<impl IntoResponse> {
+    reader.require(ApiTokenCapability::Progress)?;
+    Ok((StatusCode::OK, Json(CourseService::find_progress(ctx, &slug, query.fresh).await?)))
+}
+
+/// Rebuild the progress summaries of a course from the user stages.
+#[utoipa::path(
+    operation_id = "rebuild-course-progress",
+    post, path = "/v1/admin/courses/{slug}/progress/rebuild",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 200, description = "Progress rebuilt successfully", body = RebuildProgressResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to rebuild progress")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn rebuild_progress(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse> {
+    let rebuilt = CourseService::rebuild_progress(ctx, &slug).await?;
+    Ok((StatusCode::OK, Json(RebuildProgressResponse { rebuilt })))
+}
+
+/// Find the graded attempts made in a course.
+#[utoipa::path(
+    operation_id = "find-course-stage-attempts",
+    get, path = "/v1/admin/courses/{slug}/attempts",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        AdminAttemptQuery,
+        PageQuery,
+    ),
+    responses(
+        (status = 200, description = "Attempts retrieved successfully", body = Paginated<StageAttemptResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Course not found"),
+        (status = 422, description = "Invalid or expired cursor"),
+        (status = 500, description = "Failed to fetch attempts")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn find_attempts(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+    Query(query): Query<AdminAttemptQuery>,
+    page: Page,
+) -> Result<impl IntoResponse> {
+    let AdminAttemptQuery { user_id, stage_slug, content_hash } = query;
+    let res = StageService::find_attempts(
+        ctx,
+        &slug,
+        user_id.as_deref(),
+        stage_slug.as_deref(),
+        content_hash.as_deref(),
+        &page,
+    )
+    .await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Find the maintainers of a course.
+#[utoipa::path(
+    operation_id = "find-course-maintainers",
+    get, path = "/v1/admin/courses/{slug}/maintainers",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 200, description = "Maintainers retrieved successfully", body = Vec<MaintainerResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to fetch maintainers")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn find_maintainers(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(CourseService::find_maintainers(ctx, &slug).await?)))
+}
+
+/// Grant a user the maintainer role of a course.
+#[utoipa::path(
+    operation_id = "add-course-maintainer",
+    post, path = "/v1/admin/courses/{slug}/maintainers",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    request_body(
+        content = AddMaintainerRequest,
+        description = "Add maintainer request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 201, description = "Maintainer added successfully", body = MaintainerResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Course or user not found"),
+        (status = 409, description = "User already maintains the course", body = ConflictResponse),
+        (status = 500, description = "Failed to add maintainer")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn add_maintainer(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+    Json(req): Json<AddMaintainerRequest>,
+) -> Result<impl IntoResponse> {
+    let res = CourseService::add_maintainer(ctx, &slug, &req.user_id, "admin").await?;
+    Ok((StatusCode::CREATED, Json(res)))
+}
+
+/// Revoke the maintainer role of a user.
+#[utoipa::path(
+    operation_id = "remove-course-maintainer",
+    delete, path = "/v1/admin/courses/{slug}/maintainers/{user_id}",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        ("user_id" = String, description = "The id of the maintainer"),
+    ),
+    responses(
+        (status = 204, description = "Maintainer removed successfully"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Maintainer not found"),
+        (status = 500, description = "Failed to remove maintainer")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn remove_maintainer(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path((slug, user_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    CourseService::remove_maintainer(ctx, &slug, &user_id, "admin").await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Create an API token reading some courses, for integrations such as an
+/// LMS. The secret is only returned in this response.
+#[utoipa::path(
+    operation_id = "create-api-token",
+    post, path = "/v1/admin/api-tokens",
+    request_body(
+        content = CreateApiTokenRequest,
+        description = "Create API token request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 201, description = "API token created successfully", body = ApiTokenResponse),
+        (status = 400, description = "Missing name, courses or capabilities, or unknown course"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Failed to create API token")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn create_api_token(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Json(req): Json<CreateApiTokenRequest>,
+) -> Result<impl IntoResponse> {
+    let res = ApiTokenService::create(ctx, &req, "admin").await?;
+    Ok((StatusCode::CREATED, Json(res)))
+}
+
+/// Find all API tokens, including the revoked ones.
+#[utoipa::path(
+    operation_id = "find-api-tokens",
+    get, path = "/v1/admin/api-tokens",
+    responses(
+        (status = 200, description = "API tokens retrieved successfully", body = Vec<ApiTokenResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Failed to fetch API tokens")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn find_api_tokens(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(ApiTokenService::find(ctx).await?)))
+}
+
+/// Revoke an API token.
+#[utoipa::path(
+    operation_id = "revoke-api-token",
+    delete, path = "/v1/admin/api-tokens/{id}",
+    params(
+        ("id" = Uuid, description = "The id of the API token"),
+    ),
+    responses(
+        (status = 204, description = "API token revoked successfully"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "API token not found or already revoked"),
+        (status = 500, description = "Failed to revoke API token")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn revoke_api_token(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse> {
+    ApiTokenService::revoke(ctx, id, "admin").await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Issue the token granting link previews of an unreleased course.
+#[utoipa::path(
+    operation_id = "get-course-preview-token",
+    get, path = "/v1/admin/courses/{slug}/preview-token",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 200, description = "Token issued successfully", body = PreviewTokenResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to issue token")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn get_preview_token(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse> {
+    CourseService::get(ctx.clone(), &slug, None).await?;
+    let token = MetaService::preview_token(&ctx, &slug)?;
+    Ok((StatusCode::OK, Json(PreviewTokenResponse { token })))
+}
+
+/// Fetch the engagement of learners with every stage of a course.
+#[utoipa::path(
+    operation_id = "find-course-engagement",
+    get, path = "/v1/admin/courses/{slug}/engagement",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 200, description = "Engagement retrieved successfully", body = Vec<StageEngagementResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a maintainer of the course, or a token not granted its stats"),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to fetch engagement")
+    ),
+    security(("AdminBasicAuth" = []), ("JWTBearerAuth" = []), ("ApiTokenAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn find_engagement(
+    reader: CourseReader,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse> {
+    reader.require(ApiTokenCapability::Stats)?;
+    Ok((StatusCode::OK, Json(EngagementService::find_by_course(ctx, &slug).await?)))
+}
+
+/// Find the suspicious completions flagged in a course.
+#[utoipa::path(
+    operation_id = "find-integrity-flags",
+    get, path = "/v1/admin/courses/{slug}/integrity-flags",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        IntegrityFlagQuery,
+    ),
+    responses(
+        (status = 200, description = "Flags retrieved successfully", body = Vec<IntegrityFlagResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a maintainer of the course"),
+        (status = 500, description = "Failed to fetch flags")
+    ),
+    security(("AdminBasicAuth" = []), ("JWTBearerAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn find_integrity_flags(
+    _: CourseMaintainer,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+    Query(query): Query<IntegrityFlagQuery>,
+) -> Result<impl IntoResponse> {
+    let user_id = query.user_id.as_deref();
+    let rule = query.rule.as_deref();
+    let flags = IntegrityService::find_flags(ctx, &slug, user_id, rule, query.dismissed).await?;
+    Ok((StatusCode::OK, Json(flags)))
+}
+
+/// Dismiss a flagged completion with a note.
+#[utoipa::path(
+    operation_id = "dismiss-integrity-flag",
+    post, path = "/v1/admin/courses/{slug}/integrity-flags/{id}/dismiss",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        ("id" = Uuid, description = "The id of the flag"),
+    ),
+    request_body(
+        content = DismissFlagRequest,
+        description = "Dismiss flag request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Flag dismissed successfully", body = IntegrityFlagResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a maintainer of the course"),
+        (status = 404, description = "Flag not found"),
+        (status = 500, description = "Failed to dismiss flag")
+    ),
+    security(("AdminBasicAuth" = []), ("JWTBearerAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn dismiss_integrity_flag(
+    caller: CourseMaintainer,
+    State(ctx): State<Arc<Context>>,
+    Path((slug, id)): Path<(String, Uuid)>,
+    Json(req): Json<DismissFlagRequest>,
+) -> Result<impl IntoResponse> {
+    let flag = IntegrityService::dismiss_flag(ctx, &slug, &id, caller.actor(), &req.note).await?;
+    Ok((StatusCode::OK, Json(flag)))
+}
+
+/// Rotate the registry credentials the pipelines of a course push with.
+#[utoipa::path(
+    operation_id = "rotate-registry-credentials",
+    post, path = "/v1/admin/courses/{slug}/registry-credentials/rotate",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 200, description = "Credentials rotated successfully", body = RegistryCredentialResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to rotate credentials")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn rotate_registry_credentials(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse> {
+    let credential = RegistryService::rotate(&ctx, &slug, "admin").await?;
+    Ok((StatusCode::OK, Json(RegistryCredentialResponse::from(credential))))
+}
+
+/// Trigger a garbage collection of the registry, reclaiming the storage of
+/// deleted images.
+#[utoipa::path(
+    operation_id = "collect-registry-garbage",
+    post, path = "/v1/admin/registry/gc",
+    responses(
+        (status = 202, description = "Garbage collection triggered successfully", body = GarbageCollectionResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 409, description = "A garbage collection is already running", body = ConflictResponse),
+        (status = 500, description = "Failed to trigger garbage collection")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn collect_registry_garbage(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+) -> Result<impl IntoResponse> {
+    let id = RegistryService::collect_garbage(&ctx, "admin").await?;
+    Ok((StatusCode::ACCEPTED, Json(GarbageCollectionResponse { id })))
+}
+
+/// Get the status of a garbage collection of the registry.
+#[utoipa::path(
+    operation_id = "get-registry-garbage-collection",
+    get, path = "/v1/admin/registry/gc/{id}",
+    params(
+        ("id" = i64, description = "The ID of the garbage collection job"),
+    ),
+    responses(
+        (status = 200, description = "Garbage collection retrieved successfully", body = GarbageCollectionStatusResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Garbage collection not found"),
+        (status = 500, description = "Failed to get garbage collection")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn get_registry_garbage_collection(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse> {
+    let res = RegistryService::garbage_collection(&ctx, id).await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Replace the settings overridden on a course, over those of its course.yml.
+#[utoipa::path(
+    operation_id = "set-setting-overrides",
+    put, path = "/v1/admin/courses/{slug}/setting-overrides",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    request_body(
+        content = CourseSettingsRequest,
+        description = "Setting overrides request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Overrides set successfully", body = EffectiveSettingsResponse),
+        (status = 400, description = "Invalid settings"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Course not found"),
+        (status = 422, description = "Unknown setting"),
+        (status = 500, description = "Failed to set overrides")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn set_setting_overrides(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+    Json(req): Json<CourseSettingsRequest>,
+) -> Result<impl IntoResponse> {
+    let res = SettingsService::set_overrides(&ctx, &slug, req, "admin").await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Generate spare learner repositories of a course ahead of its launch.
+#[utoipa::path(
+    operation_id = "preprovision-repositories",
+    post, path = "/v1/admin/courses/{slug}/preprovision",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    request_body(
+        content = PreprovisionRequest,
+        description = "Pre-provision request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Pool filled successfully", body = PreprovisionResponse),
+        (status = 400, description = "Too many repositories requested"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to generate repositories")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn preprovision(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+    Json(req): Json<PreprovisionRequest>,
+) -> Result<impl IntoResponse> {
+    let res = RepoPoolService::preprovision(ctx, &slug, req.count, "admin").await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Queue the import of learner repositories from another Gitea instance.
+#[utoipa::path(
+    operation_id = "migrate-repositories",
+    post, path = "/v1/admin/migrations/repositories",
+    request_body(
+        content = MigrateRepositoriesRequest,
+        description = "Migrate repositories request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 202, description = "Migrations queued successfully", body = MigrateRepositoriesResponse),
+        (status = 400, description = "Invalid source or unknown user course"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Failed to queue migrations")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn migrate_repositories(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Json(req): Json<MigrateRepositoriesRequest>,
+) -> Result<impl IntoResponse> {
+    let res = RepoMigrationService::enqueue(ctx, req, "admin").await?;
+    Ok((StatusCode::ACCEPTED, Json(res)))
+}
+
+/// Report the progress of the repository migrations.
+#[utoipa::path(
+    operation_id = "find-repository-migrations",
+    get, path = "/v1/admin/migrations/repositories",
+    params(
+        RepoMigrationQuery,
+    ),
+    responses(
+        (status = 200, description = "Migrations retrieved successfully", body = RepoMigrationReportResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Failed to fetch migrations")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn find_repository_migrations(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Query(query): Query<RepoMigrationQuery>,
+) -> Result<impl IntoResponse> {
+    let report = RepoMigrationService::report(ctx, query.status.as_deref()).await?;
+    Ok((StatusCode::OK, Json(report)))
+}
+
+/// List the repositories of the organization which belong to neither a
+/// course template nor an existing user course.
+#[utoipa::path(
+    operation_id = "find-orphaned-repositories",
+    get, path = "/v1/admin/repositories/orphans",
+    responses(
+        (status = 200, description = "Orphaned repositories retrieved successfully", body = Vec<OrphanedRepositoryResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Failed to list repositories")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn find_orphaned_repositories(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+) -> Result<impl IntoResponse> {
+    let repos = RepoService::new(ctx).find_orphaned_repositories().await?;
+    let res: Vec<OrphanedRepositoryResponse> = repos.into_iter().map(Into::into).collect();
+    Ok((StatusCode::OK, Json(res)))
+}
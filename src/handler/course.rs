@@ -12,46 +12,90 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{convert::Infallible, sync::Arc};
+use std::{convert::Infallible, sync::Arc, time::Duration};
 
 use axum::{
     Json,
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode, header::RANGE},
     response::{
         IntoResponse, Sse,
         sse::{Event, KeepAlive},
     },
 };
 use futures::{Stream, StreamExt};
-use tokio_stream::wrappers::ReceiverStream;
-use tracing::{error, info};
+use tracing::info;
+use uuid::Uuid;
 
 use crate::{
     context::Context,
     errors::Result,
-    extractor::{AdminBasic, Claims},
-    request::{CreateCourseRequest, CreateUserCourseRequest, UpdateUserCourseRequest},
-    response::{AttemptResponse, CourseDetailResponse, CourseResponse, UserCourseResponse},
-    service::CourseService,
+    extractor::{AdminBasic, Claims, ClaimsError, CourseMaintainer, CourseReader},
+    request::{
+        ApiTokenCapability, CourseAttemptQuery, CourseQuery, CourseSearchQuery,
+        CreateCourseRequest, CreateEngagementEventRequest, CreateUserCourseRequest,
+        DeleteCourseQuery, ResetUserCourseRequest, UpdateCourseRequest, UpdateRegistryRequest,
+        UpdateUserCourseRequest, VerifyGitIdentityRequest,
+    },
+    response::{
+        AttemptResponse, ConflictResponse, CourseDeletionResponse, CourseDetailResponse,
+        CourseExportResponse, CourseJobResponse, CourseResponse, CourseRevisionResponse,
+        CourseSourceResponse, CourseStatsResponse, EffectiveSettingsResponse,
+        GitIdentityVerificationResponse, NumberedPage, OfflineManifestResponse,
+        PipelineRunResponse, ProgressResponse, RegistrySummaryResponse, RepositorySyncResponse,
+        StageSourceResponse, StreamErrorEvent, StreamTimeoutEvent, UserCourseResponse,
+    },
+    service::{
+        CourseService, EngagementService, PipelineService, RegistryService, SettingsService,
+    },
+    utils::stream::{identified_json_event, json_event, last_event_id, with_lifetime},
 };
 
+/// How often the stream of a course creation job checks its status.
+const JOB_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 // The Course Service Handlers.
 
-/// Find all released courses (beta and live status)
+/// Find released courses (beta and live status), a page at a time if a page
+/// size is given.
 #[utoipa::path(
     operation_id = "find-released-courses",
     get, path = "/v1/courses",
+    params(CourseQuery),
     responses(
-        (status = 200, description = "Courses retrieved successfully", body = Vec<CourseResponse>),
+        (status = 200, description = "Courses retrieved successfully", body = NumberedPage<CourseResponse>),
+        (status = 400, description = "Invalid query"),
     ),
     tag = "Course"
 )]
-pub async fn find(State(ctx): State<Arc<Context>>) -> Result<impl IntoResponse> {
-    Ok((StatusCode::OK, Json(CourseService::find_released(ctx).await?)))
+pub async fn find(
+    State(ctx): State<Arc<Context>>,
+    Query(query): Query<CourseQuery>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(CourseService::find(ctx, &query).await?)))
 }
 
-/// Create a course.
+/// Search released courses by keywords in their name, summary and
+/// description, most relevant first.
+#[utoipa::path(
+    operation_id = "search-courses",
+    get, path = "/v1/courses/search",
+    params(CourseSearchQuery),
+    responses(
+        (status = 200, description = "Courses searched successfully", body = Vec<CourseResponse>),
+        (status = 400, description = "Keywords too short or too long"),
+    ),
+    tag = "Course"
+)]
+pub async fn search(
+    State(ctx): State<Arc<Context>>,
+    Query(query): Query<CourseSearchQuery>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(CourseService::search(ctx, &query).await?)))
+}
+
+/// Queue the creation of a course from its git repository, which is
+/// followed through the returned job.
 #[utoipa::path(
     operation_id = "create-course",
     post, path = "/v1/courses",
@@ -61,8 +105,8 @@ pub async fn find(State(ctx): State<Arc<Context>>) -> Result<impl IntoResponse>
         content_type = "application/json"
     ),
     responses(
-        (status = 201, description = "Course created successfully", body = CourseResponse),
-        (status = 500, description = "Failed to create course")
+        (status = 202, description = "Course creation queued successfully", body = CourseJobResponse),
+        (status = 500, description = "Failed to queue course creation")
     ),
     security(("AdminBasicAuth" = [])),
     tag = "Course"
@@ -72,7 +116,79 @@ pub async fn create(
     State(ctx): State<Arc<Context>>,
     Json(req): Json<CreateCourseRequest>,
 ) -> Result<impl IntoResponse> {
-    Ok((StatusCode::CREATED, Json(CourseService::create(ctx, &req.repository).await?)))
+    Ok((StatusCode::ACCEPTED, Json(CourseService::create(ctx, &req).await?)))
+}
+
+/// Get the status of a course creation job.
+#[utoipa::path(
+    operation_id = "get-course-job",
+    get, path = "/v1/courses/jobs/{id}",
+    params(
+        ("id" = Uuid, description = "The ID of the job"),
+    ),
+    responses(
+        (status = 200, description = "Job retrieved successfully", body = CourseJobResponse),
+        (status = 404, description = "Job not found"),
+        (status = 500, description = "Failed to get job")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Course"
+)]
+pub async fn get_job(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(CourseService::get_job(ctx, id).await?)))
+}
+
+/// Stream the status of a course creation job, an event per status change
+/// until the job completes or fails.
+#[utoipa::path(
+    operation_id = "stream-course-job",
+    get, path = "/v1/courses/jobs/{id}/status",
+    params(
+        ("id" = Uuid, description = "The ID of the job"),
+    ),
+    responses(
+        (status = 200, description = "Successfully started streaming job status updates",
+            content_type = "text/event-stream", body = CourseJobResponse),
+        (status = 404, description = "Job not found"),
+        (status = 429, description = "Too many concurrent streams"),
+        (status = 500, description = "Failed to stream job status")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Course"
+)]
+pub async fn stream_job(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path(id): Path<Uuid>,
+) -> Result<Sse<impl Stream<Item = axum::response::Result<Event, Infallible>>>> {
+    let job = CourseService::get_job(ctx.clone(), id).await?;
+
+    // Reserve a stream slot, released once the stream is dropped.
+    let guard = ctx.streams.acquire("admin")?;
+
+    // Poll for status changes until the job finishes.
+    let state = (!job.is_finished()).then(|| (ctx, job.status.clone(), guard));
+    let updates = futures::stream::unfold(state, move |state| async move {
+        let (ctx, last, guard) = state?;
+        let job = loop {
+            tokio::time::sleep(JOB_POLL_INTERVAL).await;
+            if let Ok(job) = CourseService::get_job(ctx.clone(), id).await &&
+                job.status != last
+            {
+                break job;
+            }
+        };
+        let event = json_event(&job);
+        let state = (!job.is_finished()).then_some((ctx, job.status, guard));
+        Some((Ok(event), state))
+    });
+
+    let stream = futures::stream::once(async move { Ok(json_event(&job)) }).chain(updates);
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
 }
 
 /// Get a course.
@@ -87,25 +203,31 @@ pub async fn create(
         (status = 404, description = "Course not found"),
         (status = 500, description = "Failed to get course")
     ),
+    security((), ("JWTBearerAuth" = [])),
     tag = "Course"
 )]
 pub async fn get(
+    claims: Result<Claims, ClaimsError>,
     State(ctx): State<Arc<Context>>,
     Path(slug): Path<String>,
 ) -> Result<impl IntoResponse> {
-    Ok((StatusCode::OK, Json(CourseService::get(ctx, &slug).await?)))
+    let user_id = claims.ok().map(|claims| claims.id);
+    Ok((StatusCode::OK, Json(CourseService::get(ctx, &slug, user_id.as_deref()).await?)))
 }
 
-/// Delete a course.
+/// Delete a course along with its repositories, images and registry
+/// credentials, summarizing what was cleaned up.
 #[utoipa::path(
-     operation_id = "delete-course",
+    operation_id = "delete-course",
     delete, path = "/v1/courses/{slug}",
     params(
         ("slug" = String, description = "The slug of course"),
+        DeleteCourseQuery,
     ),
     responses(
-        (status = 204, description = "Course deleted successfully"),
+        (status = 200, description = "Course deleted successfully", body = CourseDeletionResponse),
         (status = 404, description = "Course not found"),
+        (status = 409, description = "Some repositories or the registry project could not be deleted, the course is kept", body = ConflictResponse),
         (status = 500, description = "Failed to delete course")
     ),
     security(("AdminBasicAuth" = [])),
@@ -115,35 +237,186 @@ pub async fn delete(
     _: AdminBasic,
     State(ctx): State<Arc<Context>>,
     Path(slug): Path<String>,
+    Query(query): Query<DeleteCourseQuery>,
 ) -> Result<impl IntoResponse> {
-    CourseService::delete(ctx, &slug).await?;
-    Ok(StatusCode::NO_CONTENT)
+    Ok((StatusCode::OK, Json(CourseService::delete(ctx, &slug, query.force).await?)))
+}
+
+/// Get the usage and quota of the registry project of a course.
+#[utoipa::path(
+    operation_id = "get-course-registry",
+    get, path = "/v1/courses/{slug}/registry",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 200, description = "Registry summary retrieved successfully", body = RegistrySummaryResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Course or its registry project not found"),
+        (status = 500, description = "Failed to get registry summary")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Course"
+)]
+pub async fn get_registry(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(RegistryService::summary(&ctx, &slug).await?)))
+}
+
+/// Set the storage quota of the registry project of a course.
+#[utoipa::path(
+    operation_id = "update-course-registry",
+    put, path = "/v1/courses/{slug}/registry",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    request_body(
+        content = UpdateRegistryRequest,
+        description = "Registry quota request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Quota set successfully", body = RegistrySummaryResponse),
+        (status = 400, description = "Invalid storage limit"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Course or its registry project not found"),
+        (status = 409, description = "Storage limit below the current usage", body = ConflictResponse),
+        (status = 500, description = "Failed to set quota")
+    ),
+    security(("AdminBasicAuth" = [])),
+    tag = "Course"
+)]
+pub async fn update_registry(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+    Json(req): Json<UpdateRegistryRequest>,
+) -> Result<impl IntoResponse> {
+    let summary = RegistryService::set_quota(&ctx, &slug, req.storage_limit, "admin").await?;
+    Ok((StatusCode::OK, Json(summary)))
 }
 
-/// Update course from git repository
+/// Update course from git repository, optionally pinning it to a branch, tag
+/// or commit SHA
 #[utoipa::path(
     operation_id = "update-course",
     patch, path = "/v1/courses/{slug}",
     params(
         ("slug" = String, description = "The slug of course"),
     ),
+    request_body(
+        content = Option<UpdateCourseRequest>,
+        description = "Update course request",
+        content_type = "application/json"
+    ),
     responses(
         (status = 204, description = "Course updated successfully"),
+        (status = 400, description = "Unknown reference"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a maintainer of the course"),
         (status = 404, description = "Course not found"),
+        (status = 409, description = "A sync of the course is already in progress", body = ConflictResponse),
         (status = 500, description = "Failed to update course")
     ),
-    security(("AdminBasicAuth" = [])),
+    security(("AdminBasicAuth" = []), ("JWTBearerAuth" = [])),
     tag = "Course"
 )]
 pub async fn update(
-    _: AdminBasic,
+    caller: CourseMaintainer,
     State(ctx): State<Arc<Context>>,
     Path(slug): Path<String>,
+    req: Option<Json<UpdateCourseRequest>>,
 ) -> Result<impl IntoResponse> {
-    CourseService::update(ctx, &slug).await?;
+    let req = req.map(|Json(req)| req).unwrap_or_default();
+    CourseService::update(ctx, &slug, &req, caller.actor()).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Merge the latest template of a course into the `template-update` branch
+/// of the repositories of its learners, reporting conflicts rather than
+/// resolving them.
+#[utoipa::path(
+    operation_id = "sync-course-repositories",
+    post, path = "/v1/courses/{slug}/repositories/sync",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 200, description = "Outcome of the sync of each repository", body = Vec<RepositorySyncResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a maintainer of the course"),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to sync repositories")
+    ),
+    security(("AdminBasicAuth" = []), ("JWTBearerAuth" = [])),
+    tag = "Course"
+)]
+pub async fn sync_repositories(
+    caller: CourseMaintainer,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse> {
+    let res = CourseService::sync_repositories(ctx, &slug, caller.actor()).await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Get the progress statistics of a course: enrollments, completions and how
+/// far the learners got with each stage.
+#[utoipa::path(
+    operation_id = "get-course-stats",
+    get, path = "/v1/courses/{slug}/stats",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 200, description = "Stats retrieved successfully", body = CourseStatsResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a maintainer of the course, or a token not granted its stats"),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to get stats")
+    ),
+    security(("AdminBasicAuth" = []), ("JWTBearerAuth" = []), ("ApiTokenAuth" = [])),
+    tag = "Course"
+)]
+pub async fn get_stats(
+    reader: CourseReader,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse> {
+    reader.require(ApiTokenCapability::Stats)?;
+    Ok((StatusCode::OK, Json(CourseService::stats(ctx, &slug).await?)))
+}
+
+/// Fetch the progress of the learners of a course enrolled with a cohort.
+#[utoipa::path(
+    operation_id = "find-cohort-progress",
+    get, path = "/v1/courses/{slug}/cohorts/{cohort}/progress",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        ("cohort" = String, description = "The cohort of the learners"),
+    ),
+    responses(
+        (status = 200, description = "Progress retrieved successfully", body = Vec<ProgressResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a maintainer of the course, or a token not granted its progress"),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to fetch progress")
+    ),
+    security(("AdminBasicAuth" = []), ("JWTBearerAuth" = []), ("ApiTokenAuth" = [])),
+    tag = "Course"
+)]
+pub async fn find_cohort_progress(
+    reader: CourseReader,
+    State(ctx): State<Arc<Context>>,
+    Path((slug, cohort)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    reader.require(ApiTokenCapability::Progress)?;
+    Ok((StatusCode::OK, Json(CourseService::find_cohort_progress(ctx, &slug, &cohort).await?)))
+}
+
 /// Find all courses for the current user.
 #[utoipa::path(
     operation_id = "find-user-courses",
@@ -173,6 +446,7 @@ pub async fn find_user_courses(
     responses(
         (status = 201, description = "User enrolled in course successfully", body = UserCourseResponse),
         (status = 404, description = "Course not found"),
+        (status = 409, description = "User is already enrolled in this course", body = ConflictResponse),
         (status = 500, description = "Failed to enroll user in course")
     ),
     security(("JWTBearerAuth" = [])),
@@ -210,6 +484,31 @@ pub async fn get_user_course(
     Ok((StatusCode::OK, Json(CourseService::get_user_course(ctx, &claims.id, &slug).await?)))
 }
 
+/// Find the pipeline runs grading the repository of the course for the
+/// current user, newest first.
+#[utoipa::path(
+    operation_id = "find-user-course-pipelines",
+    get, path = "/v1/user/courses/{slug}/pipelines",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 200, description = "Pipeline runs retrieved successfully", body = Vec<PipelineRunResponse>),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to list pipeline runs")
+    ),
+    security(("JWTBearerAuth" = [])),
+    tags = ["User", "Course"]
+)]
+pub async fn find_user_pipelines(
+    claims: Claims,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse> {
+    let res = PipelineService::new(ctx).find_by_user_course(&claims.id, &slug).await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
 /// Update this course for the current user.
 #[utoipa::path(
     operation_id = "update-user-course",
@@ -240,16 +539,148 @@ pub async fn update_user_course(
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Unenroll the current user from a course, deleting their repository.
+#[utoipa::path(
+    operation_id = "delete-user-course",
+    delete, path = "/v1/user/courses/{slug}",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 204, description = "User unenrolled successfully"),
+        (status = 404, description = "Course not found or not enrolled"),
+        (status = 500, description = "Failed to unenroll user from course")
+    ),
+    security(("JWTBearerAuth" = [])),
+    tags = ["User", "Course"]
+)]
+pub async fn delete_user_course(
+    claims: Claims,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse> {
+    CourseService::delete_user_course(ctx, &claims.id, &slug).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Reset the progress of the current user in a course, optionally along with
+/// their repository.
+#[utoipa::path(
+    operation_id = "reset-user-course",
+    post, path = "/v1/user/courses/{slug}/reset",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    request_body(
+        content = Option<ResetUserCourseRequest>,
+        description = "Reset user course request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 204, description = "User course reset successfully"),
+        (status = 404, description = "Course not found or not enrolled"),
+        (status = 500, description = "Failed to reset user course")
+    ),
+    security(("JWTBearerAuth" = [])),
+    tags = ["User", "Course"]
+)]
+pub async fn reset_user_course(
+    claims: Claims,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+    req: Option<Json<ResetUserCourseRequest>>,
+) -> Result<impl IntoResponse> {
+    let req = req.map(|Json(req)| req).unwrap_or_default();
+    CourseService::reset_user_course(ctx, &claims.id, &slug, &req).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Maximum size in bytes of an engagement event payload.
+pub const MAX_ENGAGEMENT_EVENT_SIZE: usize = 1024;
+
+/// Record an engagement event of the current user with a stage of a course.
+#[utoipa::path(
+    operation_id = "create-engagement-event",
+    post, path = "/v1/user/courses/{slug}/events",
+    params(
+        ("slug" = String, description = "The slug of course")
+    ),
+    request_body(
+        content = CreateEngagementEventRequest,
+        description = "Engagement event",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 202, description = "Event recorded"),
+        (status = 400, description = "Event time out of range"),
+        (status = 404, description = "User course or stage not found"),
+        (status = 413, description = "Payload too large"),
+        (status = 422, description = "Unknown event type"),
+        (status = 429, description = "Too many events"),
+        (status = 500, description = "Failed to record event")
+    ),
+    security(("JWTBearerAuth" = [])),
+    tag = "User"
+)]
+pub async fn create_engagement_event(
+    claims: Claims,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+    Json(req): Json<CreateEngagementEventRequest>,
+) -> Result<impl IntoResponse> {
+    EngagementService::record(ctx, &claims.id, &slug, &req).await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Start a git identity verification for an enrolled course.
+#[utoipa::path(
+    operation_id = "verify-git-identity",
+    post, path = "/v1/user/verify-git-identity",
+    request_body(
+        content = VerifyGitIdentityRequest,
+        description = "Verify git identity request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Verification token issued", body = GitIdentityVerificationResponse),
+        (status = 404, description = "User course not found"),
+        (status = 409, description = "Git identity already verified", body = ConflictResponse),
+        (status = 500, description = "Failed to issue verification token")
+    ),
+    security(("JWTBearerAuth" = [])),
+    tag = "User"
+)]
+pub async fn verify_git_identity(
+    claims: Claims,
+    State(ctx): State<Arc<Context>>,
+    Json(req): Json<VerifyGitIdentityRequest>,
+) -> Result<impl IntoResponse> {
+    let res = CourseService::verify_git_identity(ctx, &claims.id, &req.course_slug).await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
 /// Stream the status of a specific course for the current user.
 #[utoipa::path(
     operation_id = "stream_user_course_status",
     get, path = "/v1/user/courses/{slug}/status",
     params(
-        ("slug" = String, description = "The slug of course")
+        ("slug" = String, description = "The slug of course"),
+        ("Last-Event-ID" = Option<i64>, Header, description = "Id of the last event received"),
     ),
     responses(
-        (status = 200, description = "Successfully started streaming course status updates"),
+        (status = 200, description = "Successfully started streaming course status updates, \
+            sent as `status` events and an update that cannot be produced as an `error` event \
+            instead. A stream open for too long ends with a `timeout` event. Updates are \
+            numbered by their event id, a client reconnecting with a `Last-Event-ID` is only \
+            sent the current status if it is newer",
+            content(
+                (UserCourseResponse = "text/event-stream"),
+                (StreamErrorEvent = "text/event-stream"),
+                (StreamTimeoutEvent = "text/event-stream"),
+            )
+        ),
         (status = 404, description = "Course or stage not found"),
+        (status = 429, description = "Too many concurrent streams"),
         (status = 500, description = "Failed to stream course status")
     ),
     security(("JWTBearerAuth" = [])),
@@ -259,46 +690,219 @@ pub async fn stream_user_course_status(
     claims: Claims,
     State(ctx): State<Arc<Context>>,
     Path(slug): Path<String>,
-) -> Sse<impl Stream<Item = axum::response::Result<Event, Infallible>>> {
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = axum::response::Result<Event, Infallible>>>> {
     info!("Starting to stream status updates for course {} for user {}...", slug, claims.id);
 
-    // Create a channel for sending status updates.
-    let (sender, receiver) = tokio::sync::mpsc::channel(100);
-
-    // Spawn a background task to fetch and send status updates.
-    tokio::spawn(async move {
-        loop {
-            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
-            let status = CourseService::get_user_course(ctx.clone(), &claims.id, &slug).await;
-            if let Ok(status) = status {
-                let event = Event::default().json_data(status).unwrap_or_else(|e| {
-                    error!("Failed to serialize status update: {}", e);
-                    Event::default().data("status update error")
-                });
-                if sender.send(event).await.is_err() {
-                    break;
-                }
-            }
-        }
-    });
+    // Reserve a stream slot for the user, released once the stream is dropped.
+    let guard = ctx.streams.acquire(&claims.id)?;
 
-    // Convert the receiver into a stream.
-    let stream = ReceiverStream::new(receiver);
-    let stream = stream.map(Ok);
+    // Send the current status, then every change until the stream is open for
+    // too long.
+    let lifetime = Duration::from_secs(ctx.config.max_stream_secs);
+    let last_seen = last_event_id(&headers);
+    let updates =
+        CourseService::stream_user_course_status(ctx, &claims.id, &slug, last_seen).await?;
+    let updates =
+        updates.map(|update| identified_json_event("status", update.version, &update.status));
+    let stream = with_lifetime(updates, lifetime).map(move |event| {
+        let _ = &guard;
+        Ok(event)
+    });
 
     // Return the SSE stream with keep-alive.
-    Sse::new(stream).keep_alive(KeepAlive::default())
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Export a course as imported, with the instructions and solutions of its
+/// stages and its extensions
+#[utoipa::path(
+    operation_id = "export-course",
+    get, path = "/v1/courses/{slug}/export",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 200, description = "Course exported successfully", body = CourseExportResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a maintainer of the course"),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to export course")
+    ),
+    security(("AdminBasicAuth" = []), ("JWTBearerAuth" = [])),
+    tag = "Course"
+)]
+pub async fn export(
+    _: CourseMaintainer,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(CourseService::export(ctx, &slug).await?)))
+}
+
+/// Get the history of the syncs of a course, with the stages each of them
+/// added, removed or changed
+#[utoipa::path(
+    operation_id = "find-course-revisions",
+    get, path = "/v1/courses/{slug}/revisions",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 200, description = "Course revisions retrieved successfully", body = Vec<CourseRevisionResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a maintainer of the course"),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to get course revisions")
+    ),
+    security(("AdminBasicAuth" = []), ("JWTBearerAuth" = [])),
+    tag = "Course"
+)]
+pub async fn find_revisions(
+    _: CourseMaintainer,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(CourseService::find_revisions(ctx, &slug).await?)))
+}
+
+/// Get the course source as parsed from the last imported commit
+#[utoipa::path(
+    operation_id = "get-course-source",
+    get, path = "/v1/courses/{slug}/source",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 200, description = "Course source retrieved successfully", body = CourseSourceResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a maintainer of the course"),
+        (status = 404, description = "Course not found or never imported"),
+        (status = 500, description = "Failed to read course source")
+    ),
+    security(("AdminBasicAuth" = []), ("JWTBearerAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn get_source(
+    _: CourseMaintainer,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(CourseService::get_source(ctx, &slug).await?)))
+}
+
+/// Get the settings the course is graded with, along with the instance
+/// defaults, course.yml values and overrides they are merged from
+#[utoipa::path(
+    operation_id = "get-effective-settings",
+    get, path = "/v1/courses/{slug}/effective-settings",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 200, description = "Settings retrieved successfully", body = EffectiveSettingsResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a maintainer of the course"),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to resolve settings")
+    ),
+    security(("AdminBasicAuth" = []), ("JWTBearerAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn get_effective_settings(
+    _: CourseMaintainer,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(SettingsService::find_effective(&ctx, &slug).await?)))
+}
+
+/// Get the raw files of a stage from the last imported commit
+#[utoipa::path(
+    operation_id = "get-stage-source",
+    get, path = "/v1/courses/{slug}/source/stages/{stage_slug}",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        ("stage_slug" = String, description = "The slug of stage"),
+    ),
+    responses(
+        (status = 200, description = "Stage source retrieved successfully", body = StageSourceResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a maintainer of the course"),
+        (status = 404, description = "Course or stage not found"),
+        (status = 500, description = "Failed to read stage source")
+    ),
+    security(("AdminBasicAuth" = []), ("JWTBearerAuth" = [])),
+    tag = "Admin"
+)]
+pub async fn get_stage_source(
+    _: CourseMaintainer,
+    State(ctx): State<Arc<Context>>,
+    Path((slug, stage_slug)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(CourseService::get_stage_source(ctx, &slug, &stage_slug).await?)))
+}
+
+/// Get the offline manifest of a course, listing every stage and asset a
+/// client needs to prefetch.
+#[utoipa::path(
+    operation_id = "get-offline-manifest",
+    get, path = "/v1/courses/{slug}/offline-manifest",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 200, description = "Manifest retrieved successfully", body = OfflineManifestResponse),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to get manifest")
+    ),
+    tag = "Course"
+)]
+pub async fn get_offline_manifest(
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(CourseService::get_offline_manifest(ctx, &slug).await?)))
+}
+
+/// Get an asset of a course, honoring single `Range` requests.
+#[utoipa::path(
+    operation_id = "get-course-asset",
+    get, path = "/v1/courses/{slug}/assets/{path}",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        ("path" = String, description = "The path of the asset, as listed in the manifest"),
+        ("Range" = Option<String>, Header, description = "A single byte range"),
+    ),
+    responses(
+        (status = 200, description = "Asset retrieved successfully", body = [u8]),
+        (status = 206, description = "Range of the asset retrieved successfully", body = [u8]),
+        (status = 404, description = "Course or asset not found"),
+        (status = 416, description = "Range not satisfiable"),
+        (status = 500, description = "Failed to read asset")
+    ),
+    tag = "Course"
+)]
+pub async fn get_asset(
+    State(ctx): State<Arc<Context>>,
+    Path((slug, path)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse> {
+    let range = headers.get(RANGE).and_then(|value| value.to_str().ok());
+    CourseService::get_asset(ctx, &slug, &path, range).await
 }
 
-/// Find all attempts for a course.
+/// Find a page of the attempts for a course, most recently started first.
 #[utoipa::path(
     operation_id = "find-course-attempts",
     get, path = "/v1/courses/{slug}/attempts",
     params(
         ("slug" = String, description = "The slug of the course"),
+        CourseAttemptQuery,
     ),
     responses(
-        (status = 200, description = "Attempts retrieved successfully", body = Vec<AttemptResponse>),
+        (status = 200, description = "Attempts retrieved successfully", body = NumberedPage<AttemptResponse>),
+        (status = 400, description = "Invalid query"),
         (status = 404, description = "Course not found"),
         (status = 500, description = "Failed to fetch attempts"),
     ),
@@ -307,6 +911,7 @@ pub async fn stream_user_course_status(
 pub async fn find_attempts(
     State(ctx): State<Arc<Context>>,
     Path(slug): Path<String>,
+    Query(query): Query<CourseAttemptQuery>,
 ) -> Result<impl IntoResponse> {
-    Ok((StatusCode::OK, Json(CourseService::find_attempts(ctx, &slug).await?)))
+    Ok((StatusCode::OK, Json(CourseService::find_attempts(ctx, &slug, &query).await?)))
 }
@@ -12,37 +12,67 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{io::Error, sync::Arc};
+use std::{io, sync::Arc};
 
 use axum::{
-    body::Body,
+    body::{Body, Bytes},
     extract::{Path, State},
-    http::{Request, StatusCode, Uri, header},
+    http::{HeaderMap, Request, StatusCode, Uri, header},
     response::{IntoResponse, Response},
 };
-use bytes::Bytes;
-use futures_util::stream::TryStreamExt;
-use tracing::{error, info, trace};
+use futures::{StreamExt, stream, stream::BoxStream};
+use tracing::{error, info, trace, warn};
 use uuid::Uuid;
 
-use crate::{config::Config, context::Context};
+use crate::{
+    context::Context,
+    model::TRIAL_REPO_PREFIX,
+    utils::{git, gzip::GunzipStream, limit::LimitedStream},
+};
+
+/// Content type of the request body sent by `git push`.
+const RECEIVE_PACK_REQUEST: &str = "application/x-git-receive-pack-request";
+
+/// Content type of the response expected by `git push`.
+const RECEIVE_PACK_RESULT: &str = "application/x-git-receive-pack-result";
 
 /// Proxies a Git request to the appropriate repository in the Git server.
 /// This function handles authentication and routing for Git operations.
+///
+/// Pushes are limited in size while their pack is streamed through, and
+/// rejected with a message `git push` prints once the limit is crossed.
+/// Gzip encoded requests, as git sends them, are inflated on the way.
 pub async fn proxy(
     State(ctx): State<Arc<Context>>,
     Path((uuid, _)): Path<(Uuid, String)>,
     req: Request<Body>,
-) -> impl IntoResponse {
-    // Construct the URI for the Git server request to Gitea backend.
-    let trimmed = strip_uuid_prefix(req.uri(), &uuid);
-    let Config { git_server_endpoint, namespace, .. } = &ctx.config;
-    let url = format!("{git_server_endpoint}/{namespace}/{uuid}.git{trimmed}");
+) -> Result<Response, StatusCode> {
+    let trimmed = strip_prefix(req.uri(), &format!("/{uuid}"));
+    forward(&ctx, &ctx.config.namespace, &uuid.to_string(), &trimmed, req).await
+}
 
-    let url = reqwest::Url::parse(&url).map_err(|e| {
-        error!(error = %e, "Failed to parse URI for proxy destination");
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+/// Proxies a Git request to the repository of a trial.
+pub async fn proxy_trial(
+    State(ctx): State<Arc<Context>>,
+    Path((id, _)): Path<(Uuid, String)>,
+    req: Request<Body>,
+) -> Result<Response, StatusCode> {
+    let trimmed = strip_prefix(req.uri(), &format!("/trials/{id}"));
+    let repo = format!("{TRIAL_REPO_PREFIX}{id}");
+    forward(&ctx, &ctx.config.trial_org, &repo, &trimmed, req).await
+}
+
+/// Forwards a Git request to a repository of the Git server, where `trimmed`
+/// is the path and query following the repository.
+async fn forward(
+    ctx: &Context,
+    org: &str,
+    repo: &str,
+    trimmed: &str,
+    req: Request<Body>,
+) -> Result<Response, StatusCode> {
+    // Construct the URI for the Git server request to Gitea backend.
+    let url = ctx.endpoints.git_url(org, repo, trimmed);
     info!(url = %url, "Forwarding to Git server");
 
     // Convert axum Request to reqwest Request with streaming body
@@ -51,11 +81,37 @@ pub async fn proxy(
     // Remove the original host header
     parts.headers.remove(header::HOST);
 
-    // Convert axum Body to a stream of bytes for reqwest
-    let stream = body.into_data_stream().map_ok(Bytes::from).map_err(|e| {
-        error!(error = %e, "Failed to convert body to stream");
-        Error::other("Body conversion error")
-    });
+    let service = GitService::from_path(trimmed);
+    let push_limit = ctx.config.max_receive_pack_size;
+    let git_client = is_git_client(&parts.headers);
+
+    // Refuse announced oversized pushes before contacting the Git server
+    if service == GitService::ReceivePack && content_length(&parts.headers) > Some(push_limit) {
+        warn!(repo = %repo, "Push rejected, announced pack is too large");
+        return Ok(push_too_large(push_limit, git_client, true));
+    }
+
+    // The limits and the capabilities apply to the inflated body, which is
+    // forwarded without a length
+    let mut data: BoxStream<'static, io::Result<Bytes>> = if is_gzip(&parts.headers) {
+        parts.headers.remove(header::CONTENT_ENCODING);
+        parts.headers.remove(header::CONTENT_LENGTH);
+        GunzipStream::new(body.into_data_stream()).boxed()
+    } else {
+        body.into_data_stream().map(|chunk| chunk.map_err(io::Error::other)).boxed()
+    };
+
+    // Peek at the first chunk, it holds the capabilities requested by the client
+    let first = data.next().await;
+    let sideband = match &first {
+        Some(Ok(chunk)) => chunk.windows(9).any(|w| w == b"side-band"),
+        _ => false,
+    };
+
+    // Count the bytes as they are forwarded, without buffering the body
+    let limit = (service == GitService::ReceivePack).then_some(push_limit);
+    let stream = LimitedStream::new(stream::iter(first).chain(data), limit);
+    let sent = stream.counter();
     let body = reqwest::Body::wrap_stream(stream);
 
     let request = ctx
@@ -76,8 +132,14 @@ pub async fn proxy(
         })?;
     trace!(?request, "Built client request for Git server");
 
-    // Execute the request and get streaming response
-    let response = ctx.http.execute(request).await.map_err(|e| {
+    // Execute the request and get streaming response, the upstream request
+    // is aborted as soon as the push crosses its limit
+    let response = ctx.http.execute(request).await;
+    if sent.exceeded() {
+        warn!(repo = %repo, bytes = sent.bytes(), "Push rejected, pack is too large");
+        return Ok(push_too_large(push_limit, git_client, sideband));
+    }
+    let response = response.map_err(|e| {
         error!(error = %e, "Git server request failed");
         StatusCode::BAD_GATEWAY
     })?;
@@ -86,7 +148,18 @@ pub async fn proxy(
     // Convert reqwest Response to axum Response with streaming body
     let status = response.status();
     let headers = response.headers().clone();
-    let body = Body::from_stream(response.bytes_stream());
+    let limit = match (service, ctx.config.max_upload_pack_size) {
+        (GitService::UploadPack, limit) if limit > 0 => Some(limit),
+        _ => None,
+    };
+    let stream = LimitedStream::new(response.bytes_stream().boxed(), limit);
+    let received = stream.counter();
+    let repo = repo.to_string();
+    let body = Body::from_stream(stream.inspect(move |chunk| {
+        if chunk.is_err() && received.exceeded() {
+            warn!(repo = %repo, bytes = received.bytes(), "Fetch aborted, pack is too large");
+        }
+    }));
 
     let mut response_builder = Response::builder().status(status);
     for (key, value) in headers.iter() {
@@ -99,12 +172,70 @@ pub async fn proxy(
     })
 }
 
-/// Strip the leading "/{uuid}" from a request URI and return the remaining path+query.
+/// The git smart HTTP service a proxied request is addressed to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum GitService {
+    /// `git push`
+    ReceivePack,
+
+    /// `git clone` and `git fetch`
+    UploadPack,
+
+    /// Ref advertisement and anything else
+    Other,
+}
+
+impl GitService {
+    fn from_path(path: &str) -> Self {
+        let path = path.split('?').next().unwrap_or_default();
+        if path.ends_with("/git-receive-pack") {
+            GitService::ReceivePack
+        } else if path.ends_with("/git-upload-pack") {
+            GitService::UploadPack
+        } else {
+            GitService::Other
+        }
+    }
+}
+
+/// Whether the request comes from a git client speaking the smart protocol.
+fn is_git_client(headers: &HeaderMap) -> bool {
+    headers.get(header::CONTENT_TYPE).is_some_and(|value| value == RECEIVE_PACK_REQUEST)
+}
+
+/// Whether the request body is gzip encoded.
+fn is_gzip(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case("gzip"))
+}
+
+/// The announced size of the request body, if any.
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers.get(header::CONTENT_LENGTH)?.to_str().ok()?.parse().ok()
+}
+
+/// Rejects a push over the size limit, in the receive-pack result format for
+/// git clients and as a plain 413 for anything else.
+fn push_too_large(limit: u64, git_client: bool, sideband: bool) -> Response {
+    let message = format!("push rejected: the pack exceeds the limit of {limit} bytes");
+    if !git_client {
+        return (StatusCode::PAYLOAD_TOO_LARGE, message).into_response();
+    }
+
+    let body = git::receive_pack_error(&message, sideband);
+    let headers =
+        [(header::CONTENT_TYPE, RECEIVE_PACK_RESULT), (header::CACHE_CONTROL, "no-cache")];
+    (StatusCode::OK, headers, body).into_response()
+}
+
+/// Strip the leading repository prefix, like "/{uuid}", from a request URI
+/// and return the remaining path+query.
 /// For example:
 ///   input:  "/5a0e.../info/refs?service=git-receive-pack"
 ///   output: "/info/refs?service=git-receive-pack"
-fn strip_uuid_prefix(uri: &Uri, uuid: &Uuid) -> String {
+fn strip_prefix(uri: &Uri, prefix: &str) -> String {
     let path_and_query = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("");
-    let prefix = format!("/{}", uuid);
-    path_and_query.strip_prefix(&prefix).unwrap_or(path_and_query).to_string()
+    path_and_query.strip_prefix(prefix).unwrap_or(path_and_query).to_string()
 }
@@ -0,0 +1,49 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use std::sync::Arc;
+use tracing::error;
+
+use crate::{
+    context::Context,
+    response::{MaintenanceResponse, ReadinessResponse},
+    service::MaintenanceService,
+};
+
+/// Report whether the server takes requests.
+#[utoipa::path(
+    operation_id = "get-readiness",
+    get, path = "/readyz",
+    responses(
+        (status = 200, description = "Ready, possibly refusing writes during a maintenance \
+            (status `read_only`)", body = ReadinessResponse),
+        (status = 503, description = "Database unavailable")
+    ),
+    tag = "Health"
+)]
+pub async fn ready(State(ctx): State<Arc<Context>>) -> impl IntoResponse {
+    match MaintenanceService::current(&ctx).await {
+        Ok(mode) => {
+            let status = if mode.is_some() { "read_only" } else { "ready" };
+            let maintenance = MaintenanceResponse::from(mode);
+            let res = ReadinessResponse { status: status.to_string(), maintenance };
+            (StatusCode::OK, Json(res)).into_response()
+        }
+        Err(e) => {
+            error!("Readiness check failed: {e}");
+            StatusCode::SERVICE_UNAVAILABLE.into_response()
+        }
+    }
+}
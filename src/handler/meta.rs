@@ -0,0 +1,91 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{
+        HeaderMap, StatusCode,
+        header::{ETAG, IF_NONE_MATCH},
+    },
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    context::Context, errors::Result, request::MetaQuery, response::MetaPage, service::MetaService,
+};
+
+// The Meta Service Handlers.
+
+/// Render the link preview of a course page.
+#[utoipa::path(
+    operation_id = "get-course-meta",
+    get, path = "/v1/meta/courses/{slug}",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        MetaQuery,
+    ),
+    responses(
+        (status = 200, description = "Preview rendered successfully", body = String, content_type = "text/html"),
+        (status = 304, description = "Preview not modified"),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to render preview")
+    ),
+    tag = "Meta"
+)]
+pub async fn get_course(
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+    Query(query): Query<MetaQuery>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse> {
+    let page = MetaService::course(ctx, &slug, query.preview.as_deref()).await?;
+    Ok(conditional(page, &headers))
+}
+
+/// Render the link preview of a stage page.
+#[utoipa::path(
+    operation_id = "get-stage-meta",
+    get, path = "/v1/meta/courses/{slug}/stages/{stage_slug}",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        ("stage_slug" = String, description = "The slug of stage"),
+        MetaQuery,
+    ),
+    responses(
+        (status = 200, description = "Preview rendered successfully", body = String, content_type = "text/html"),
+        (status = 304, description = "Preview not modified"),
+        (status = 404, description = "Course or stage not found"),
+        (status = 500, description = "Failed to render preview")
+    ),
+    tag = "Meta"
+)]
+pub async fn get_stage(
+    State(ctx): State<Arc<Context>>,
+    Path((slug, stage_slug)): Path<(String, String)>,
+    Query(query): Query<MetaQuery>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse> {
+    let page = MetaService::stage(ctx, &slug, &stage_slug, query.preview.as_deref()).await?;
+    Ok(conditional(page, &headers))
+}
+
+/// Answers with 304 when the client already holds the page.
+fn conditional(page: MetaPage, headers: &HeaderMap) -> Response {
+    if page.matches(headers.get(IF_NONE_MATCH)) {
+        return (StatusCode::NOT_MODIFIED, [(ETAG, page.etag)]).into_response();
+    }
+    page.into_response()
+}
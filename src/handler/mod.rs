@@ -12,8 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod admin;
 pub mod course;
 pub mod extension;
 pub mod git;
+pub mod health;
+pub mod meta;
+pub mod notification;
 pub mod stage;
+pub mod trial;
 pub mod webhook;
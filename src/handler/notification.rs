@@ -0,0 +1,72 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+
+use crate::{
+    context::Context, errors::Result, extractor::Claims,
+    request::UpdateNotificationPreferencesRequest, response::NotificationPreferencesResponse,
+    service::NotificationService,
+};
+
+// The Notification Service Handlers.
+
+/// Get the notification preferences of the current user.
+#[utoipa::path(
+    operation_id = "get-notification-preferences",
+    get, path = "/v1/user/notification-preferences",
+    responses(
+        (status = 200, description = "Preferences retrieved successfully", body = NotificationPreferencesResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Failed to get preferences")
+    ),
+    security(("JWTBearerAuth" = [])),
+    tag = "User"
+)]
+pub async fn get_preferences(
+    claims: Claims,
+    State(ctx): State<Arc<Context>>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(NotificationService::get_preferences(ctx, &claims.id).await?)))
+}
+
+/// Replace the notification preferences of the current user.
+#[utoipa::path(
+    operation_id = "update-notification-preferences",
+    put, path = "/v1/user/notification-preferences",
+    request_body(
+        content = UpdateNotificationPreferencesRequest,
+        description = "Notification preferences",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Preferences saved successfully", body = NotificationPreferencesResponse),
+        (status = 400, description = "Invalid webhook URL or email address"),
+        (status = 401, description = "Unauthorized"),
+        (status = 422, description = "Unknown channel"),
+        (status = 500, description = "Failed to save preferences")
+    ),
+    security(("JWTBearerAuth" = [])),
+    tag = "User"
+)]
+pub async fn update_preferences(
+    claims: Claims,
+    State(ctx): State<Arc<Context>>,
+    Json(req): Json<UpdateNotificationPreferencesRequest>,
+) -> Result<impl IntoResponse> {
+    let prefs = NotificationService::update_preferences(ctx, &claims.id, &req).await?;
+    Ok((StatusCode::OK, Json(prefs)))
+}
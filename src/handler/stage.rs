@@ -14,25 +14,33 @@
 
 use axum::{
     Json,
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
     response::{
         IntoResponse, Sse,
         sse::{Event, KeepAlive},
     },
 };
 use futures::{Stream, StreamExt};
-use std::{convert::Infallible, sync::Arc};
-use tokio_stream::wrappers::ReceiverStream;
-use tracing::{error, info};
+use std::{convert::Infallible, sync::Arc, time::Duration};
+use tracing::info;
 
 use crate::{
     context::Context,
     errors::Result,
-    extractor::Claims,
-    request::CompleteStageRequest,
-    response::{StageDetailResponse, StageResponse, UserStageResponse},
-    service::StageService,
+    extractor::{Accept, Claims},
+    request::{AttemptQuery, CompleteStageRequest, PageQuery},
+    response::{
+        ConflictResponse, Negotiated, Paginated, RetestResponse, RoadmapResponse,
+        StageAttemptResponse, StageDetailResponse, StageHintResponse, StageLogEnd, StageLogLine,
+        StageResponse, StageSolutionResponse, StarterDiffResponse, StreamErrorEvent,
+        StreamTimeoutEvent, UserStageResponse, UserStageStatusResponse,
+    },
+    service::{LogEvent, LogService, RoadmapService, StageService},
+    utils::{
+        pagination::Page,
+        stream::{identified_json_event, json_event, last_event_id, with_lifetime},
+    },
 };
 
 // The Stage Service Handlers.
@@ -109,17 +117,53 @@ pub async fn find_extended_stages(
         ("stage_slug" = String, description = "The slug of stage"),
     ),
     responses(
-        (status = 200, description = "Stage retrieved successfully", body = StageDetailResponse),
+        (status = 200, description = "Stage retrieved successfully, the instruction is returned \
+            directly as sanitized HTML, markdown or plain text if requested through `Accept`",
+            content(
+                (StageDetailResponse = "application/json"),
+                (String = "text/html"),
+                (String = "text/markdown"),
+                (String = "text/plain"),
+            )
+        ),
         (status = 404, description = "Course or stage not found"),
         (status = 500, description = "Failed to get course or stage")
     ),
     tag = "Stage"
 )]
 pub async fn get(
+    accept: Accept,
     State(ctx): State<Arc<Context>>,
     Path((slug, stage_slug)): Path<(String, String)>,
 ) -> Result<impl IntoResponse> {
-    Ok((StatusCode::OK, Json(StageService::get(ctx, &slug, &stage_slug).await?)))
+    let res = StageService::get(ctx, &slug, &stage_slug).await?;
+    let instruction = res.instruction.clone();
+    Ok(Negotiated::new(accept, res, instruction))
+}
+
+/// Get what the starter code of the stage changes compared to the stage
+/// before it.
+#[utoipa::path(
+    operation_id = "get-stage-starter-diff",
+    get, path = "/v1/courses/{slug}/stages/{stage_slug}/starter-diff",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        ("stage_slug" = String, description = "The slug of stage"),
+    ),
+    responses(
+        (status = 200, description = "Starter diff retrieved successfully, empty for stages \
+            without starter code", body = StarterDiffResponse),
+        (status = 404, description = "Course or stage not found"),
+        (status = 500, description = "Failed to get starter diff")
+    ),
+    tag = "Stage"
+)]
+pub async fn get_starter_diff(
+    State(ctx): State<Arc<Context>>,
+    Path((slug, stage_slug)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    let res = StageService::get_starter_diff(ctx, &slug, &stage_slug).await?;
+    Ok((StatusCode::OK, Json(res)))
 }
 
 /// Find all stages for the current user.
@@ -145,6 +189,29 @@ pub async fn find_user_stages(
     Ok((StatusCode::OK, Json(StageService::find_user_stages(ctx, &claims.id, &slug).await?)))
 }
 
+/// Get the path of the current user through the course.
+#[utoipa::path(
+    operation_id = "get-user-course-roadmap",
+    get, path = "/v1/user/courses/{slug}/roadmap",
+    params(
+        ("slug" = String, description = "The slug of course"),
+    ),
+    responses(
+        (status = 200, description = "Roadmap retrieved successfully", body = RoadmapResponse),
+        (status = 404, description = "Course not found"),
+        (status = 500, description = "Failed to get roadmap")
+    ),
+    security(("JWTBearerAuth" = [])),
+    tags = ["User", "Stage"]
+)]
+pub async fn get_roadmap(
+    claims: Claims,
+    State(ctx): State<Arc<Context>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(RoadmapService::get(ctx, &claims.id, &slug).await?)))
+}
+
 /// Get the details of the stage for the current user.
 #[utoipa::path(
     operation_id = "get-user-stage-detail",
@@ -154,7 +221,15 @@ pub async fn find_user_stages(
         ("stage_slug" = String, description = "The slug of stage"),
     ),
     responses(
-        (status = 200, description = "Stage retrieved successfully", body = UserStageResponse),
+        (status = 200, description = "Stage retrieved successfully, the instruction is returned \
+            directly as sanitized HTML, markdown or plain text if requested through `Accept`",
+            content(
+                (UserStageResponse = "application/json"),
+                (String = "text/html"),
+                (String = "text/markdown"),
+                (String = "text/plain"),
+            )
+        ),
         (status = 404, description = "Course or stage not found"),
         (status = 500, description = "Failed to get course or stage")
     ),
@@ -162,11 +237,155 @@ pub async fn find_user_stages(
     tags = ["User", "Stage"]
 )]
 pub async fn get_user_stage(
+    claims: Claims,
+    accept: Accept,
+    State(ctx): State<Arc<Context>>,
+    Path((slug, stage_slug)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    let res = StageService::get_user_stage(ctx.clone(), &claims.id, &slug, &stage_slug).await?;
+
+    // The user stage does not carry the instruction, only fetch it if needed.
+    let instruction = match accept.is_json() {
+        true => String::new(),
+        false => StageService::get(ctx, &slug, &stage_slug).await?.instruction,
+    };
+    Ok(Negotiated::new(accept, res, instruction))
+}
+
+/// Run the tests of the current stage of the current user again.
+#[utoipa::path(
+    operation_id = "retest-stage",
+    post, path = "/v1/user/courses/{slug}/stages/{stage_slug}/retest",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        ("stage_slug" = String, description = "The slug of stage"),
+    ),
+    responses(
+        (status = 200, description = "Tests triggered successfully", body = RetestResponse),
+        (status = 400, description = "Stage not current, never tested or out of attempts"),
+        (status = 404, description = "Course or stage not found"),
+        (status = 409, description = "Tests of the repository are already running"),
+        (status = 429, description = "Too many tests of the user are running"),
+        (status = 500, description = "Failed to trigger tests")
+    ),
+    security(("JWTBearerAuth" = [])),
+    tags = ["User", "Stage"]
+)]
+pub async fn retest_stage(
+    claims: Claims,
+    State(ctx): State<Arc<Context>>,
+    Path((slug, stage_slug)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    let res = StageService::retest(ctx, &claims.id, &slug, &stage_slug).await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Get the solution of a stage for the current user, once unlocked.
+#[utoipa::path(
+    operation_id = "get-user-stage-solution",
+    get, path = "/v1/user/courses/{slug}/stages/{stage_slug}/solution",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        ("stage_slug" = String, description = "The slug of stage"),
+    ),
+    responses(
+        (status = 200, description = "Solution retrieved successfully", body = StageSolutionResponse),
+        (status = 403, description = "Solution not unlocked yet"),
+        (status = 404, description = "Course, stage or solution not found"),
+        (status = 500, description = "Failed to get solution")
+    ),
+    security(("JWTBearerAuth" = [])),
+    tags = ["User", "Stage"]
+)]
+pub async fn get_user_stage_solution(
+    claims: Claims,
+    State(ctx): State<Arc<Context>>,
+    Path((slug, stage_slug)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    let res = StageService::get_solution(ctx, &claims.id, &slug, &stage_slug).await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Find the hints of a stage the current user revealed so far.
+#[utoipa::path(
+    operation_id = "find-user-stage-hints",
+    get, path = "/v1/user/courses/{slug}/stages/{stage_slug}/hints",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        ("stage_slug" = String, description = "The slug of stage"),
+    ),
+    responses(
+        (status = 200, description = "Hints retrieved successfully", body = Vec<StageHintResponse>),
+        (status = 404, description = "Course or stage not found"),
+        (status = 500, description = "Failed to get hints")
+    ),
+    security(("JWTBearerAuth" = [])),
+    tags = ["User", "Stage"]
+)]
+pub async fn find_user_stage_hints(
+    claims: Claims,
+    State(ctx): State<Arc<Context>>,
+    Path((slug, stage_slug)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    let res = StageService::find_hints(ctx, &claims.id, &slug, &stage_slug).await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Reveal the next hint of a stage to the current user.
+#[utoipa::path(
+    operation_id = "unlock-user-stage-hint",
+    post, path = "/v1/user/courses/{slug}/stages/{stage_slug}/hints/unlock",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        ("stage_slug" = String, description = "The slug of stage"),
+    ),
+    responses(
+        (status = 200, description = "Hint unlocked successfully", body = StageHintResponse),
+        (status = 400, description = "All hints of the stage are unlocked"),
+        (status = 404, description = "Course or stage not found"),
+        (status = 500, description = "Failed to unlock hint")
+    ),
+    security(("JWTBearerAuth" = [])),
+    tags = ["User", "Stage"]
+)]
+pub async fn unlock_user_stage_hint(
     claims: Claims,
     State(ctx): State<Arc<Context>>,
     Path((slug, stage_slug)): Path<(String, String)>,
 ) -> Result<impl IntoResponse> {
-    let res = StageService::get_user_stage(ctx, &claims.id, &slug, &stage_slug).await?;
+    let res = StageService::unlock_hint(ctx, &claims.id, &slug, &stage_slug).await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Find the graded attempts of a stage for the current user.
+#[utoipa::path(
+    operation_id = "find-user-stage-attempts",
+    get, path = "/v1/user/courses/{slug}/stages/{stage_slug}/attempts",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        ("stage_slug" = String, description = "The slug of stage"),
+        AttemptQuery,
+        PageQuery,
+    ),
+    responses(
+        (status = 200, description = "Attempts retrieved successfully", body = Paginated<StageAttemptResponse>),
+        (status = 404, description = "Course not found"),
+        (status = 422, description = "Invalid or expired cursor"),
+        (status = 500, description = "Failed to get attempts")
+    ),
+    security(("JWTBearerAuth" = [])),
+    tags = ["User", "Stage"]
+)]
+pub async fn find_user_stage_attempts(
+    claims: Claims,
+    State(ctx): State<Arc<Context>>,
+    Path((slug, stage_slug)): Path<(String, String)>,
+    Query(query): Query<AttemptQuery>,
+    page: Page,
+) -> Result<impl IntoResponse> {
+    let (user, stage, hash) =
+        (Some(claims.id.as_str()), Some(stage_slug.as_str()), query.content_hash);
+    let res = StageService::find_attempts(ctx, &slug, user, stage, hash.as_deref(), &page).await?;
     Ok((StatusCode::OK, Json(res)))
 }
 
@@ -185,6 +404,7 @@ pub async fn get_user_stage(
     responses(
         (status = 200, description = "Stage completed successfully", body = UserStageResponse),
         (status = 404, description = "Course or stage not found"),
+        (status = 409, description = "User already has a record of the next stage", body = ConflictResponse),
         (status = 500, description = "Failed to complete stage")
     ),
     security(("JWTBearerAuth" = [])),
@@ -196,7 +416,34 @@ pub async fn complete_stage(
     Path(slug): Path<String>,
     Json(req): Json<CompleteStageRequest>,
 ) -> Result<impl IntoResponse> {
-    let res = StageService::complete(ctx, &claims.id, &slug, &req.slug).await?;
+    let res = StageService::complete(ctx, &claims.id, &slug, &req.slug, None).await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
+/// Skip an optional stage for the current user, moving on to the next one.
+#[utoipa::path(
+    operation_id = "skip-stage",
+    post, path = "/v1/user/courses/{slug}/stages/{stage_slug}/skip",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        ("stage_slug" = String, description = "The slug of stage"),
+    ),
+    responses(
+        (status = 200, description = "Stage skipped successfully", body = UserStageResponse),
+        (status = 400, description = "Stage is not optional, or not the current stage"),
+        (status = 404, description = "Course or stage not found"),
+        (status = 409, description = "User already has a record of the next stage", body = ConflictResponse),
+        (status = 500, description = "Failed to skip stage")
+    ),
+    security(("JWTBearerAuth" = [])),
+    tags = ["User", "Stage"]
+)]
+pub async fn skip_stage(
+    claims: Claims,
+    State(ctx): State<Arc<Context>>,
+    Path((slug, stage_slug)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    let res = StageService::skip(ctx, &claims.id, &slug, &stage_slug).await?;
     Ok((StatusCode::OK, Json(res)))
 }
 
@@ -207,10 +454,23 @@ pub async fn complete_stage(
     params(
         ("slug" = String, description = "The slug of course"),
         ("stage_slug" = String, description = "The slug of stage"),
+        ("Last-Event-ID" = Option<i64>, Header, description = "Id of the last event received"),
     ),
     responses(
-        (status = 200, description = "Successfully started streaming stage status updates"),
+        (status = 200, description = "Successfully started streaming stage status updates, \
+            sent as `status` events and an update that cannot be produced as an `error` event \
+            instead. The completed status is sent as a `done` event and ends the stream, a \
+            stream open for too long ends with a `timeout` event. Statuses are numbered by \
+            their event id, a client reconnecting with a `Last-Event-ID` is only sent the \
+            current status if it is newer",
+            content(
+                (UserStageStatusResponse = "text/event-stream"),
+                (StreamErrorEvent = "text/event-stream"),
+                (StreamTimeoutEvent = "text/event-stream"),
+            )
+        ),
         (status = 404, description = "Course or stage not found"),
+        (status = 429, description = "Too many concurrent streams"),
         (status = 500, description = "Failed to stream stage status")
     ),
     security(("JWTBearerAuth" = [])),
@@ -220,37 +480,82 @@ pub async fn stream_user_stage_status(
     claims: Claims,
     State(ctx): State<Arc<Context>>,
     Path((slug, stage_slug)): Path<(String, String)>,
-) -> Sse<impl Stream<Item = axum::response::Result<Event, Infallible>>> {
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = axum::response::Result<Event, Infallible>>>> {
     info!(
         "Starting to stream status updates for stage {} in course {} for user {}...",
         stage_slug, slug, claims.id
     );
 
-    // Create a channel for sending status updates.
-    let (sender, receiver) = tokio::sync::mpsc::channel(100);
+    // Reserve a stream slot for the user, released once the stream is dropped.
+    let guard = ctx.streams.acquire(&claims.id)?;
 
-    // Spawn a background task to fetch and send status updates.
-    tokio::spawn(async move {
-        loop {
-            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
-            let status =
-                StageService::get_user_stage_status(&ctx, &claims.id, &slug, &stage_slug).await;
-            if let Ok(status) = status {
-                let event = Event::default().json_data(status).unwrap_or_else(|e| {
-                    error!("Failed to serialize status update: {}", e);
-                    Event::default().data("status update error")
-                });
-                if sender.send(event).await.is_err() {
-                    break;
-                }
-            }
-        }
+    // Send the current status, then every change until the stage is completed
+    // or the stream is open for too long.
+    let lifetime = Duration::from_secs(ctx.config.max_stream_secs);
+    let last_seen = last_event_id(&headers);
+    let updates =
+        StageService::stream_user_stage_status(ctx, &claims.id, &slug, &stage_slug, last_seen)
+            .await?;
+    let updates = updates.map(|update| {
+        let name = if update.status.status == "completed" { "done" } else { "status" };
+        identified_json_event(name, update.version, &update.status)
+    });
+    let stream = with_lifetime(updates, lifetime).map(move |event| {
+        let _ = &guard;
+        Ok(event)
     });
-
-    // Convert the receiver into a stream.
-    let stream = ReceiverStream::new(receiver);
-    let stream = stream.map(Ok);
 
     // Return the SSE stream with keep-alive.
-    Sse::new(stream).keep_alive(KeepAlive::default())
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Stream the tester output of the latest attempt of a stage for the current
+/// user while it is graded.
+#[utoipa::path(
+    operation_id = "stream_user_stage_logs",
+    get, path = "/v1/user/courses/{slug}/stages/{stage_slug}/logs/stream",
+    params(
+        ("slug" = String, description = "The slug of course"),
+        ("stage_slug" = String, description = "The slug of stage"),
+    ),
+    responses(
+        (status = 200, description = "Successfully started streaming the tester output, one \
+            `line` event per line and an `end` event with the outcome once the run is over. \
+            Without a run in progress only the `end` event is sent",
+            content(
+                (StageLogLine = "text/event-stream"),
+                (StageLogEnd = "text/event-stream"),
+            )
+        ),
+        (status = 404, description = "Course or stage not found"),
+        (status = 429, description = "Too many concurrent streams"),
+        (status = 500, description = "Failed to stream the tester output")
+    ),
+    security(("JWTBearerAuth" = [])),
+    tags = ["User", "Stage"]
+)]
+pub async fn stream_user_stage_logs(
+    claims: Claims,
+    State(ctx): State<Arc<Context>>,
+    Path((slug, stage_slug)): Path<(String, String)>,
+) -> Result<Sse<impl Stream<Item = axum::response::Result<Event, Infallible>>>> {
+    info!(
+        "Starting to stream test output of stage {stage_slug} in course {slug} for user {}...",
+        claims.id
+    );
+
+    // Reserve a stream slot for the user, released once the stream is dropped.
+    let guard = ctx.streams.acquire(&claims.id)?;
+
+    let events = LogService::stream(ctx, &claims.id, &slug, &stage_slug).await?;
+    let stream = events.map(move |event| {
+        let _ = &guard;
+        Ok(match event {
+            LogEvent::Line(line) => json_event(&line),
+            LogEvent::End(end) => json_event(&end),
+        })
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
 }
@@ -0,0 +1,152 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::{
+    Json,
+    extract::{ConnectInfo, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use uuid::Uuid;
+
+use crate::{
+    context::Context,
+    errors::Result,
+    extractor::Claims,
+    request::{ConvertTrialRequest, CreateTrialRequest, TrialTokenQuery},
+    response::{ConflictResponse, TrialResponse, TrialStatusResponse, UserCourseResponse},
+    service::TrialService,
+};
+
+// The Trial Service Handlers.
+
+/// Start a trial of the first stage of a course without signing up.
+#[utoipa::path(
+    operation_id = "create-trial",
+    post, path = "/v1/trials",
+    request_body(
+        content = inline(CreateTrialRequest),
+        description = "Course to try and captcha token",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 201, description = "Trial started successfully", body = TrialResponse),
+        (status = 400, description = "Captcha verification failed"),
+        (status = 403, description = "Trials are not enabled"),
+        (status = 404, description = "Course not found"),
+        (status = 429, description = "Too many trials"),
+        (status = 500, description = "Failed to start trial")
+    ),
+    tag = "Trial"
+)]
+pub async fn create(
+    State(ctx): State<Arc<Context>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(req): Json<CreateTrialRequest>,
+) -> Result<impl IntoResponse> {
+    let address = client_address(&ctx.config.trusted_proxies, peer.ip(), &headers);
+    let res = TrialService::create(ctx, &address, &req).await?;
+    Ok((StatusCode::CREATED, Json(res)))
+}
+
+/// Get the status of a trial and the result of its latest push.
+#[utoipa::path(
+    operation_id = "get-trial-status",
+    get, path = "/v1/trials/{id}/status",
+    params(
+        ("id" = Uuid, description = "The id of trial"),
+        TrialTokenQuery,
+    ),
+    responses(
+        (status = 200, description = "Trial status retrieved successfully", body = TrialStatusResponse),
+        (status = 401, description = "Invalid trial token"),
+        (status = 404, description = "Trial not found"),
+        (status = 500, description = "Failed to get trial status")
+    ),
+    tag = "Trial"
+)]
+pub async fn get_status(
+    State(ctx): State<Arc<Context>>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<TrialTokenQuery>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(TrialService::get_status(ctx, &id, &query.token).await?)))
+}
+
+/// Convert a trial into an enrollment of the current user.
+#[utoipa::path(
+    operation_id = "convert-trial",
+    post, path = "/v1/user/trials/{id}/convert",
+    params(
+        ("id" = Uuid, description = "The id of trial"),
+    ),
+    request_body(
+        content = inline(ConvertTrialRequest),
+        description = "Trial token and enrollment preferences",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 201, description = "Trial converted successfully", body = UserCourseResponse),
+        (status = 400, description = "Trial is no longer active"),
+        (status = 401, description = "Invalid trial token"),
+        (status = 404, description = "Trial not found"),
+        (status = 409, description = "Already enrolled in the course", body = ConflictResponse),
+        (status = 500, description = "Failed to convert trial")
+    ),
+    security(("JWTBearerAuth" = [])),
+    tags = ["User", "Trial"]
+)]
+pub async fn convert(
+    claims: Claims,
+    State(ctx): State<Arc<Context>>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<ConvertTrialRequest>,
+) -> Result<impl IntoResponse> {
+    let res = TrialService::convert(ctx, &claims.id, &id, &req).await?;
+    Ok((StatusCode::CREATED, Json(res)))
+}
+
+/// Address of the client of a request from the peer. Only trusted proxies
+/// may name the client: every proxy appends the address it got the request
+/// from to `X-Forwarded-For`, so the rightmost entry not added by one of them
+/// is the client, and whatever the client sent itself is ignored.
+fn client_address(trusted: &[IpAddr], peer: IpAddr, headers: &HeaderMap) -> String {
+    if !trusted.contains(&peer) {
+        return peer.to_string();
+    }
+
+    let forwarded = headers.get_all("x-forwarded-for").iter().filter_map(|v| v.to_str().ok());
+    let mut hops: Vec<&str> = forwarded.flat_map(|value| value.split(',')).map(str::trim).collect();
+    if hops.is_empty() {
+        let real_ip = headers.get("x-real-ip").and_then(|value| value.to_str().ok());
+        hops.extend(real_ip.map(str::trim));
+    }
+
+    let mut client = peer;
+    for hop in hops.into_iter().rev() {
+        match hop.parse::<IpAddr>() {
+            Ok(address) if trusted.contains(&address) => client = address,
+            Ok(address) => return address.to_string(),
+            // A malformed entry cannot be told apart from a forged one
+            Err(_) => break,
+        }
+    }
+    client.to_string()
+}
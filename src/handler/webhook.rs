@@ -16,16 +16,16 @@ use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
 use gitea_client::types::Event;
 use std::sync::Arc;
 use tracing::{debug, error, info};
-use uuid::Uuid;
 
 use crate::{
     context::Context,
-    errors::{ApiError, Result},
+    errors::Result,
     extractor::AdminBasic,
-    repository::CourseRepository,
-    request::event::PipelineEvent,
-    service::{PipelineCleanupGuard, RepoService, StageService},
-    utils::crypto,
+    request::event::{HarborEvent, PipelineEvent},
+    service::{
+        PipelineCleanupGuard, PipelineService, RegistryService, RepoService, TestOutcome,
+        WebhookService,
+    },
 };
 
 /// Handle Gitea Webhook Event.
@@ -37,8 +37,9 @@ pub async fn handle_gitea_webhook(
     let Event { reference, repository, .. } = &event;
     info!("Received push event for repository: {}, ref: {}", repository.full_name, reference);
 
-    // Skip if the event is from the template repository or a non-main branch.
-    if repository.template || reference.ne("refs/heads/main") {
+    // Skip if the event is from the template repository or another branch
+    // than the default one.
+    if repository.template || *reference != ctx.config.default_ref() {
         return Ok(StatusCode::OK);
     }
 
@@ -48,53 +49,88 @@ pub async fn handle_gitea_webhook(
     Ok(StatusCode::OK)
 }
 
+/// Handle Harbor webhook events of pushed images.
+pub async fn handle_harbor_webhook(
+    _: AdminBasic,
+    State(ctx): State<Arc<Context>>,
+    Json(event): Json<HarborEvent>,
+) -> Result<impl IntoResponse> {
+    debug!("Received registry event: {:?}", event);
+    RegistryService::record_push(&ctx, &event).await?;
+    Ok(StatusCode::OK)
+}
+
 /// Handle Tekton pipeline notification webhook events.
 pub async fn handle_tekton_webhook(
     State(ctx): State<Arc<Context>>,
     Json(event): Json<PipelineEvent>,
 ) -> Result<impl IntoResponse> {
     debug!("Received pipeline event: {:?}", event);
-    let PipelineEvent { name, status, repo, course, stage, secret, tasks } = &event;
+    let PipelineEvent {
+        name,
+        status,
+        repo,
+        course,
+        stage,
+        commit,
+        content_hash,
+        repo_commit,
+        tasks,
+        log,
+        results,
+        ..
+    } = &event;
 
     // Create cleanup guard - will delete pipeline when this function exits
     let _cleanup_guard = PipelineCleanupGuard::new(ctx.clone(), name);
 
-    // Verify HMAC signature to prevent request forgery
-    let auth_secret = &ctx.config.auth_secret;
-    let payload = format!("{}{}{}", repo, course, stage);
-
-    if !crypto::hmac_sha256_verify(&payload, auth_secret, secret)? {
-        error!("Received pipeline event with invalid signature");
-        return Err(ApiError::Unauthorized("Invalid signature".into()));
-    }
-
-    // Check overall pipeline status first
-    if status != "Succeeded" {
-        error!("Pipeline run failed, please check it");
-        return Ok(StatusCode::OK);
+    // Verify HMAC signature to prevent request forgery, and that the event
+    // refers to a known repository and stage
+    if let Err(e) = WebhookService::verify(&ctx, &event).await {
+        error!("Rejected pipeline event of {}: {}", name, e);
+        return Err(e);
     }
 
-    // Process the pipeline event based on test task status
-    match tasks.test.status.as_str() {
-        "Succeeded" => {
-            // Look up the course to get the user_id
-            let id = Uuid::parse_str(repo)?;
-            let user_course = CourseRepository::get_user_course_by_id(&ctx.database, &id).await?;
-
-            // Mark the stage as complete
-            StageService::complete(ctx.clone(), &user_course.user_id, course, stage).await?;
-            info!("Stage {} completed successfully for course {}", stage, course);
-        }
-        "Failed" => {
+    // Log why the attempt failed, if it did
+    match (status.as_str(), tasks.test.status.as_str()) {
+        ("Succeeded", "Succeeded") => {}
+        ("Succeeded", "Failed") => {
             info!("Test task failed: reason={}, stage={}", tasks.test.reason, stage);
         }
-        _ => {
+        ("Succeeded", _) => {
             error!(
                 "Test task in non-terminal state: status={}, reason={}",
                 tasks.test.status, tasks.test.reason
             );
         }
+        _ => error!("Pipeline run failed, please check it"),
     }
 
+    // Record the outcome on the graded attempt
+    let passed = status == "Succeeded" && tasks.test.status == "Succeeded";
+    let outcome = TestOutcome {
+        run: name,
+        repo,
+        course,
+        stage,
+        commit,
+        content_hash,
+        repo_commit,
+        passed,
+        reason: &tasks.test.reason,
+        log,
+        results,
+    };
+    PipelineService::new(ctx.clone()).record_outcome(&outcome).await?;
+
     Ok(StatusCode::OK)
 }
+
+/// Validate a Tekton pipeline notification, reporting which of the checks of
+/// the webhook pass without recording anything.
+pub async fn validate_tekton_webhook(
+    State(ctx): State<Arc<Context>>,
+    Json(event): Json<PipelineEvent>,
+) -> Result<impl IntoResponse> {
+    Ok((StatusCode::OK, Json(WebhookService::validate(&ctx, &event).await?)))
+}
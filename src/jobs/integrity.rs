@@ -0,0 +1,65 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    context::Context,
+    errors::Result,
+    jobs::{Job, JobOutcome, Schedule},
+    service::IntegrityService,
+};
+
+/// Number of completions analyzed per transaction
+const BATCH_SIZE: i64 = 100;
+
+/// Analyzes new stage completions, flagging suspicious patterns for
+/// instructors. Runs periodically and right after each completion.
+pub struct AnalyzeCompletions {
+    ctx: Arc<Context>,
+}
+
+impl AnalyzeCompletions {
+    pub const NAME: &'static str = "analyze-completions";
+
+    pub fn new(ctx: Arc<Context>) -> Self {
+        Self { ctx }
+    }
+}
+
+impl Job for AnalyzeCompletions {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::Interval(Duration::from_secs(60))
+    }
+
+    async fn run(&self) -> Result<JobOutcome> {
+        let mut total = 0;
+        loop {
+            let analyzed = IntegrityService::analyze(&self.ctx, BATCH_SIZE).await?;
+            total += analyzed;
+            if analyzed < BATCH_SIZE as usize {
+                break;
+            }
+        }
+
+        match total {
+            0 => Ok(JobOutcome::Idle),
+            n => Ok(JobOutcome::Processed(n)),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    context::Context,
+    errors::Result,
+    jobs::{Job, JobOutcome, Schedule},
+    service::RepoMigrationService,
+};
+
+/// Imports the learner repositories queued for migration from another Gitea
+/// instance. Runs periodically and right after migrations are requested.
+pub struct MigrateRepositories {
+    ctx: Arc<Context>,
+}
+
+impl MigrateRepositories {
+    pub const NAME: &'static str = "migrate-repositories";
+
+    pub fn new(ctx: Arc<Context>) -> Self {
+        Self { ctx }
+    }
+}
+
+impl Job for MigrateRepositories {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::Interval(Duration::from_secs(60))
+    }
+
+    async fn run(&self) -> Result<JobOutcome> {
+        match RepoMigrationService::migrate_pending(self.ctx.clone()).await? {
+            0 => Ok(JobOutcome::Idle),
+            n => Ok(JobOutcome::Processed(n)),
+        }
+    }
+}
@@ -0,0 +1,355 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Background jobs of the server.
+//!
+//! A job implements [`Job`] and is handed to the [`JobRegistry`] on the
+//! context when the server starts, see `app::run`. The registry runs it on its
+//! [`Schedule`], captures panics, logs the duration and outcome of every run,
+//! and exposes the last run through `GET /v1/admin/jobs`. Jobs listed in the
+//! `disabled_jobs` setting are registered but never run.
+//!
+//! On shutdown the registry stops scheduling new runs and waits for the
+//! running ones to finish, up to the drain timeout.
+
+mod course;
+mod engagement;
+mod integrity;
+mod migration;
+mod pipeline;
+mod pool;
+mod registry;
+mod trial;
+mod workspace;
+
+pub use course::*;
+pub use engagement::*;
+pub use integrity::*;
+pub use migration::*;
+pub use pipeline::*;
+pub use pool::*;
+pub use registry::*;
+pub use trial::*;
+pub use workspace::*;
+
+use std::{
+    future::Future,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use futures::FutureExt;
+use tokio::{
+    sync::{Notify, watch},
+    task::JoinSet,
+    time::Interval,
+};
+use tracing::{debug, error, info, warn};
+
+use crate::errors::Result;
+
+/// When a job runs.
+#[derive(Clone, Copy, Debug)]
+pub enum Schedule {
+    /// Periodically, starting right after registration
+    Interval(Duration),
+
+    /// Only when triggered through [`JobRegistry::trigger`]
+    Trigger,
+}
+
+/// The result of a successful run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JobOutcome {
+    /// There was nothing to do
+    Idle,
+
+    /// The given number of items were processed
+    Processed(usize),
+
+    /// The run was skipped for the given reason
+    Skipped(String),
+}
+
+impl std::fmt::Display for JobOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobOutcome::Idle => write!(f, "idle"),
+            JobOutcome::Processed(n) => write!(f, "processed {n}"),
+            JobOutcome::Skipped(reason) => write!(f, "skipped: {reason}"),
+        }
+    }
+}
+
+/// A unit of background work.
+pub trait Job: Send + Sync + 'static {
+    /// Unique name of the job, used in logs and settings.
+    fn name(&self) -> &'static str;
+
+    /// When the job runs.
+    fn schedule(&self) -> Schedule;
+
+    /// Performs a single run.
+    fn run(&self) -> impl Future<Output = Result<JobOutcome>> + Send;
+}
+
+/// Last known state of a registered job.
+#[derive(Clone, Debug, Default)]
+pub struct JobStatus {
+    /// Whether the job is scheduled at all
+    pub enabled: bool,
+
+    /// Whether a run is in progress
+    pub running: bool,
+
+    /// Interval between runs, if periodic
+    pub interval: Option<Duration>,
+
+    /// Start of the latest run
+    pub last_started_at: Option<DateTime<Utc>>,
+
+    /// Duration of the latest finished run
+    pub last_duration: Option<Duration>,
+
+    /// Outcome or error of the latest finished run
+    pub last_outcome: Option<String>,
+
+    /// Whether the latest finished run failed or panicked
+    pub last_failed: bool,
+
+    /// Number of finished runs
+    pub runs: u64,
+
+    /// Number of failed or panicked runs
+    pub failures: u64,
+}
+
+/// Runs the background jobs and keeps track of their state.
+pub struct JobRegistry {
+    disabled: Vec<String>,
+    statuses: Arc<DashMap<&'static str, JobStatus>>,
+    triggers: DashMap<&'static str, Arc<Notify>>,
+    shutdown: watch::Sender<bool>,
+    tasks: Mutex<JoinSet<()>>,
+}
+
+impl JobRegistry {
+    /// Creates a registry that skips the jobs named in `disabled`.
+    pub fn new(disabled: &[String]) -> Self {
+        Self {
+            disabled: disabled.to_vec(),
+            statuses: Arc::default(),
+            triggers: DashMap::new(),
+            shutdown: watch::Sender::new(false),
+            tasks: Mutex::new(JoinSet::new()),
+        }
+    }
+
+    /// Registers a job and starts running it unless it is disabled.
+    pub fn spawn<J: Job>(&self, job: J) {
+        let name = job.name();
+        let schedule = job.schedule();
+        let enabled = !self.disabled.iter().any(|d| d == name);
+        let interval = match schedule {
+            Schedule::Interval(period) => Some(period),
+            Schedule::Trigger => None,
+        };
+        self.statuses.insert(name, JobStatus { enabled, interval, ..Default::default() });
+
+        if !enabled {
+            info!(job = name, "Job is disabled");
+            return;
+        }
+
+        let trigger = self.triggers.entry(name).or_default().clone();
+        let statuses = self.statuses.clone();
+        let mut shutdown = self.shutdown.subscribe();
+
+        let mut tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        tasks.spawn(async move {
+            let mut interval = interval.map(tokio::time::interval);
+            loop {
+                tokio::select! {
+                    _ = shutdown.changed() => break,
+                    _ = trigger.notified() => {}
+                    _ = tick(&mut interval) => {}
+                }
+                execute(&job, &statuses).await;
+            }
+            debug!(job = name, "Job stopped");
+        });
+    }
+
+    /// Requests an immediate run of a job, returns false for unknown or
+    /// disabled jobs.
+    pub fn trigger(&self, name: &str) -> bool {
+        match self.triggers.get(name) {
+            Some(trigger) => {
+                trigger.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The state of all registered jobs, ordered by name.
+    pub fn statuses(&self) -> Vec<(&'static str, JobStatus)> {
+        let mut statuses: Vec<_> =
+            self.statuses.iter().map(|entry| (*entry.key(), entry.value().clone())).collect();
+        statuses.sort_by_key(|(name, _)| *name);
+        statuses
+    }
+
+    /// Stops scheduling runs and waits up to `timeout` for the running ones,
+    /// aborting whatever is left afterwards.
+    pub async fn shutdown(&self, timeout: Duration) {
+        self.shutdown.send_replace(true);
+
+        let mut tasks = std::mem::take(&mut *self.tasks.lock().unwrap_or_else(|e| e.into_inner()));
+        let drained =
+            tokio::time::timeout(timeout, async { while tasks.join_next().await.is_some() {} });
+
+        if drained.await.is_err() {
+            warn!("Jobs did not finish within {:?}, aborting {}", timeout, tasks.len());
+            tasks.shutdown().await;
+        }
+    }
+}
+
+/// Waits for the next tick of the interval, forever if there is none.
+async fn tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Performs a single run of a job, recording its outcome.
+async fn execute<J: Job>(job: &J, statuses: &DashMap<&'static str, JobStatus>) {
+    let name = job.name();
+    if let Some(mut status) = statuses.get_mut(name) {
+        status.running = true;
+        status.last_started_at = Some(Utc::now());
+    }
+
+    let start = Instant::now();
+    let result = AssertUnwindSafe(job.run()).catch_unwind().await;
+    let duration = start.elapsed();
+
+    let (outcome, failed) = match result {
+        Ok(Ok(outcome)) => {
+            match outcome {
+                JobOutcome::Processed(n) if n > 0 => {
+                    info!(job = name, ?duration, %outcome, "Job finished")
+                }
+                _ => debug!(job = name, ?duration, %outcome, "Job finished"),
+            }
+            (outcome.to_string(), false)
+        }
+        Ok(Err(e)) => {
+            error!(job = name, ?duration, error = %e, "Job failed");
+            (format!("error: {e}"), true)
+        }
+        Err(_) => {
+            error!(job = name, ?duration, "Job panicked");
+            ("panicked".to_string(), true)
+        }
+    };
+
+    if let Some(mut status) = statuses.get_mut(name) {
+        status.running = false;
+        status.last_duration = Some(duration);
+        status.last_outcome = Some(outcome);
+        status.last_failed = failed;
+        status.runs += 1;
+        status.failures += u64::from(failed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    struct Flaky {
+        runs: Arc<AtomicUsize>,
+    }
+
+    impl Job for Flaky {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        fn schedule(&self) -> Schedule {
+            Schedule::Trigger
+        }
+
+        async fn run(&self) -> Result<JobOutcome> {
+            if self.runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                panic!("first run fails");
+            }
+            Ok(JobOutcome::Processed(1))
+        }
+    }
+
+    async fn wait_for_runs(registry: &JobRegistry, runs: u64) -> JobStatus {
+        loop {
+            let (_, status) = registry.statuses().remove(0);
+            if status.runs >= runs && !status.running {
+                return status;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_panics_are_captured() {
+        let registry = JobRegistry::new(&[]);
+        let runs = Arc::new(AtomicUsize::new(0));
+        registry.spawn(Flaky { runs: runs.clone() });
+
+        assert!(registry.trigger("flaky"));
+        let status = wait_for_runs(&registry, 1).await;
+        assert!(status.last_failed);
+        assert_eq!(status.last_outcome.as_deref(), Some("panicked"));
+
+        // The job keeps running after a panic
+        assert!(registry.trigger("flaky"));
+        let status = wait_for_runs(&registry, 2).await;
+        assert!(!status.last_failed);
+        assert_eq!(status.failures, 1);
+
+        registry.shutdown(Duration::from_secs(1)).await;
+        assert!(!registry.trigger("unknown"));
+    }
+
+    #[tokio::test]
+    async fn test_disabled_jobs_never_run() {
+        let registry = JobRegistry::new(&["flaky".to_string()]);
+        let runs = Arc::new(AtomicUsize::new(0));
+        registry.spawn(Flaky { runs: runs.clone() });
+
+        assert!(!registry.trigger("flaky"));
+        let (name, status) = registry.statuses().remove(0);
+        assert_eq!(name, "flaky");
+        assert!(!status.enabled);
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+    }
+}
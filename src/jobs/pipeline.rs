@@ -0,0 +1,164 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    context::Context,
+    errors::Result,
+    jobs::{Job, JobOutcome, Schedule},
+    service::{MaintenanceService, PipelineService},
+};
+
+/// Periodically checks the health of the Kubernetes API, dispatching the
+/// attempts queued during an outage or throttled whenever it is available.
+pub struct DispatchQueuedAttempts {
+    ctx: Arc<Context>,
+}
+
+impl DispatchQueuedAttempts {
+    pub const NAME: &'static str = "dispatch-queued-attempts";
+
+    pub fn new(ctx: Arc<Context>) -> Self {
+        Self { ctx }
+    }
+}
+
+impl Job for DispatchQueuedAttempts {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::Interval(Duration::from_secs(self.ctx.config.cluster_health_interval.max(1)))
+    }
+
+    async fn run(&self) -> Result<JobOutcome> {
+        if MaintenanceService::current(&self.ctx).await?.is_some() {
+            return Ok(JobOutcome::Skipped("Maintenance mode is active".into()));
+        }
+
+        let pipeline = PipelineService::new(self.ctx.clone());
+        if !pipeline.check_health().await {
+            return Ok(JobOutcome::Skipped("Kubernetes API is unavailable".into()));
+        }
+
+        match pipeline.dispatch_queued().await? {
+            0 => Ok(JobOutcome::Idle),
+            n => Ok(JobOutcome::Processed(n)),
+        }
+    }
+}
+
+/// Records the outcome of the PipelineRuns whose webhook was lost, starting
+/// with those which finished while the backend was down.
+pub struct ReconcilePipelineRuns {
+    ctx: Arc<Context>,
+}
+
+impl ReconcilePipelineRuns {
+    pub fn new(ctx: Arc<Context>) -> Self {
+        Self { ctx }
+    }
+}
+
+impl Job for ReconcilePipelineRuns {
+    fn name(&self) -> &'static str {
+        "reconcile-pipeline-runs"
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::Interval(Duration::from_secs(300))
+    }
+
+    async fn run(&self) -> Result<JobOutcome> {
+        if !self.ctx.cluster.is_available() {
+            return Ok(JobOutcome::Skipped("Kubernetes API is unavailable".into()));
+        }
+
+        match PipelineService::new(self.ctx.clone()).reconcile().await? {
+            0 => Ok(JobOutcome::Idle),
+            n => Ok(JobOutcome::Processed(n)),
+        }
+    }
+}
+
+/// Deletes the PipelineRuns kept past their retention, along with their
+/// workspace, and those stuck without finishing.
+pub struct RemoveExpiredPipelineRuns {
+    ctx: Arc<Context>,
+}
+
+impl RemoveExpiredPipelineRuns {
+    pub fn new(ctx: Arc<Context>) -> Self {
+        Self { ctx }
+    }
+}
+
+impl Job for RemoveExpiredPipelineRuns {
+    fn name(&self) -> &'static str {
+        "remove-expired-pipeline-runs"
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::Interval(Duration::from_secs(self.ctx.config.pipeline_sweep_interval.max(1)))
+    }
+
+    async fn run(&self) -> Result<JobOutcome> {
+        if !self.ctx.cluster.is_available() {
+            return Ok(JobOutcome::Skipped("Kubernetes API is unavailable".into()));
+        }
+
+        match PipelineService::new(self.ctx.clone()).remove_expired_runs().await? {
+            0 => Ok(JobOutcome::Idle),
+            n => Ok(JobOutcome::Processed(n)),
+        }
+    }
+}
+
+/// Deletes the ConfigMaps with test cases and the Secrets with deploy keys
+/// left behind by PipelineRuns that never became their owner.
+pub struct RemoveOrphanedTestCases {
+    ctx: Arc<Context>,
+}
+
+impl RemoveOrphanedTestCases {
+    pub fn new(ctx: Arc<Context>) -> Self {
+        Self { ctx }
+    }
+}
+
+impl Job for RemoveOrphanedTestCases {
+    fn name(&self) -> &'static str {
+        "remove-orphaned-test-cases"
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::Interval(Duration::from_secs(300))
+    }
+
+    async fn run(&self) -> Result<JobOutcome> {
+        if !self.ctx.cluster.is_available() {
+            return Ok(JobOutcome::Skipped("Kubernetes API is unavailable".into()));
+        }
+
+        let pipeline = PipelineService::new(self.ctx.clone());
+        let removed = pipeline.remove_orphaned_test_cases().await? +
+            pipeline.remove_orphaned_deploy_keys().await?;
+        match removed {
+            0 => Ok(JobOutcome::Idle),
+            n => Ok(JobOutcome::Processed(n)),
+        }
+    }
+}
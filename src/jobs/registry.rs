@@ -0,0 +1,51 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    context::Context,
+    errors::Result,
+    jobs::{Job, JobOutcome, Schedule},
+    service::RegistryService,
+};
+
+/// Deletes the robot accounts and Secrets of rotated registry credentials
+/// once the pipelines started with them had time to finish.
+pub struct RemoveRetiredCredentials {
+    ctx: Arc<Context>,
+}
+
+impl RemoveRetiredCredentials {
+    pub fn new(ctx: Arc<Context>) -> Self {
+        Self { ctx }
+    }
+}
+
+impl Job for RemoveRetiredCredentials {
+    fn name(&self) -> &'static str {
+        "remove-retired-credentials"
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::Interval(Duration::from_secs(300))
+    }
+
+    async fn run(&self) -> Result<JobOutcome> {
+        match RegistryService::remove_retired(&self.ctx).await? {
+            0 => Ok(JobOutcome::Idle),
+            n => Ok(JobOutcome::Processed(n)),
+        }
+    }
+}
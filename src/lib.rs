@@ -19,11 +19,14 @@ pub mod database;
 pub mod errors;
 pub mod extractor;
 pub mod handler;
+pub mod jobs;
 pub mod logger;
+pub mod middleware;
 pub mod model;
 pub mod repository;
 pub mod request;
 pub mod response;
+pub mod router;
 pub mod routes;
 pub mod schema;
 pub mod service;
@@ -0,0 +1,88 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::warn;
+
+use crate::{context::Context, service::MaintenanceService};
+
+/// Route turning the maintenance mode off, which stays writable.
+const MAINTENANCE_ROUTE: &str = "/v1/admin/maintenance";
+
+/// How a request is treated during a maintenance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Access {
+    /// Always served: reads, clones and fetches, and the maintenance route
+    Read,
+
+    /// Refused during a maintenance: mutating API requests and pushes
+    Write,
+
+    /// Webhook deliveries, refused only if the maintenance says so
+    Webhook,
+}
+
+impl Access {
+    fn of(req: &Request) -> Self {
+        let (path, query) = (req.uri().path(), req.uri().query().unwrap_or_default());
+
+        // Git clients push through the proxy, advertising refs first
+        if path.ends_with("/git-receive-pack") || query.contains("service=git-receive-pack") {
+            return Access::Write;
+        }
+        if path.ends_with("/git-upload-pack") {
+            return Access::Read;
+        }
+
+        let mutating =
+            matches!(*req.method(), Method::POST | Method::PUT | Method::PATCH | Method::DELETE);
+        if !mutating || path == MAINTENANCE_ROUTE {
+            Access::Read
+        } else if path.starts_with("/v1/webhooks/") {
+            Access::Webhook
+        } else {
+            Access::Write
+        }
+    }
+}
+
+/// Refuses writes while the read-only maintenance mode is on. The mode is
+/// looked up for writes only, and writes are let through if it can not be.
+pub async fn read_only(State(ctx): State<Arc<Context>>, req: Request, next: Next) -> Response {
+    let access = Access::of(&req);
+    if access == Access::Read {
+        return next.run(req).await;
+    }
+
+    let mode = match MaintenanceService::current(&ctx).await {
+        Ok(Some(mode)) => mode,
+        Ok(None) => return next.run(req).await,
+        Err(e) => {
+            warn!("Failed to look up the maintenance mode: {e}");
+            return next.run(req).await;
+        }
+    };
+
+    if access == Access::Webhook && !mode.reject_webhooks {
+        return next.run(req).await;
+    }
+    MaintenanceService::refusal(&mode, ctx.clock.now()).into_response()
+}
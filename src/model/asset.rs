@@ -0,0 +1,79 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Database model representing an indexed file of a course
+#[derive(Debug, Clone, FromRow)]
+pub struct AssetModel {
+    /// ID of the course the file belongs to
+    pub course_id: Uuid,
+
+    /// ID of the stage the file belongs to, none for course-wide assets
+    pub stage_id: Option<Uuid>,
+
+    /// Path of the file relative to the course repository root
+    pub path: String,
+
+    /// What the file is used for (asset, hint)
+    pub kind: String,
+
+    /// Hex encoded SHA-256 hash of the content
+    pub sha256: String,
+
+    /// Size of the content in bytes
+    pub size: i64,
+}
+
+/// Database model representing a stage as listed in the offline manifest
+#[derive(Debug, FromRow)]
+pub struct StageManifestModel {
+    /// Slug of the stage
+    pub slug: String,
+
+    /// Name of the stage
+    pub name: String,
+
+    /// Slug of the extension of the stage (joined from extensions)
+    pub extension_slug: Option<String>,
+
+    /// Hash of the stage content
+    pub content_hash: String,
+
+    /// Size in bytes of the instruction and the files of the stage
+    pub size: i64,
+
+    /// Number of hints of the stage
+    pub hint_count: i64,
+}
+
+/// Database model representing a file the starter code of a stage changes
+#[derive(Debug, Clone, FromRow)]
+pub struct StarterChangeModel {
+    /// ID of the stage whose starter code changes the file
+    pub stage_id: Uuid,
+
+    /// Path of the file relative to the starter directory of the stage
+    pub path: String,
+
+    /// How the file changed (added, modified, removed)
+    pub kind: String,
+
+    /// Unified diff of the file, none for binary or very large files
+    pub diff: Option<String>,
+
+    /// Whether the diff was cut off at the size limit
+    pub truncated: bool,
+}
@@ -0,0 +1,69 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Database model representing an entry of the audit trail
+#[derive(Debug, FromRow)]
+pub struct AuditLogModel {
+    /// Unique internal identifier
+    pub id: Uuid,
+
+    /// Who performed the action (user ID or "admin")
+    pub actor: String,
+
+    /// The action performed
+    pub action: String,
+
+    /// Path-like identifier of the affected resource
+    pub target: String,
+
+    /// Action specific details
+    pub details: Value,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Position in the order of insertion, assigned by the database
+    pub seq: i64,
+}
+
+impl AuditLogModel {
+    /// Creates a new audit log entry
+    pub fn new(actor: &str, action: &str, target: &str, details: Value) -> Self {
+        Self::new_at(actor, action, target, details, Utc::now())
+    }
+
+    /// Creates a new audit log entry at the given time
+    pub fn new_at(
+        actor: &str,
+        action: &str,
+        target: &str,
+        details: Value,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            actor: actor.to_string(),
+            action: action.to_string(),
+            target: target.to_string(),
+            details,
+            created_at: now,
+            seq: 0,
+        }
+    }
+}
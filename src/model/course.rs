@@ -13,10 +13,15 @@
 // limitations under the License.
 
 use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
 use sqlx::FromRow;
+use tracing::warn;
 use uuid::Uuid;
 
-use crate::schema::Course;
+use crate::schema::{Course, CourseSettings, PipelineSettings};
+
+use super::stage;
 
 /// Database model representing a course entity
 #[derive(Debug, FromRow)]
@@ -51,6 +56,52 @@ pub struct CourseModel {
     /// Number of stages in the course
     pub stage_count: i32,
 
+    /// Whether learners must verify their git identity before activation
+    pub require_verified_identity: bool,
+
+    /// Default maximum number of graded attempts per stage
+    pub max_attempts: Option<i32>,
+
+    /// Default resource profile of the test pods of its stages
+    pub resources: Option<String>,
+
+    /// Tester image pinned for the course
+    pub tester_image: Option<String>,
+
+    /// Programming languages the course is offered in, primary first
+    pub languages: Vec<String>,
+
+    /// Tekton Pipeline grading the course
+    pub pipeline_ref: Option<String>,
+
+    /// Size of the shared workspace of its PipelineRuns
+    pub workspace_size: Option<String>,
+
+    /// Longest its PipelineRuns may take
+    pub pipeline_timeout: Option<String>,
+
+    /// Service account its PipelineRuns run as
+    pub service_account: Option<String>,
+
+    /// Settings overridden by admins at runtime, over those of `course.yml`
+    pub setting_overrides: Value,
+
+    /// Commit SHA of the course repository the course was last synced from
+    pub commit_sha: String,
+
+    /// Branch, tag or commit SHA the course is pinned to, the default branch
+    /// is followed without one
+    pub reference: Option<String>,
+
+    /// Hash over the content of the course, its stages and assets
+    pub content_hash: String,
+
+    /// Start of the exam window, enrollments open at this time
+    pub opens_at: Option<DateTime<Utc>>,
+
+    /// End of the exam window, later attempts do not count
+    pub closes_at: Option<DateTime<Utc>>,
+
     /// Creation timestamp
     pub created_at: DateTime<Utc>,
 
@@ -70,6 +121,68 @@ impl CourseModel {
         self.stage_count = stage_count;
         self
     }
+
+    /// Sets the commit_sha field
+    pub fn with_commit(mut self, commit_sha: &str) -> CourseModel {
+        self.commit_sha = commit_sha.to_string();
+        self
+    }
+
+    /// Sets the reference field
+    pub fn with_reference(mut self, reference: Option<&str>) -> CourseModel {
+        self.reference = reference.map(ToString::to_string);
+        self
+    }
+
+    /// Name of the template repository of a language variant. The primary
+    /// language, like courses offered in a single language, uses the
+    /// template named after the course.
+    pub fn template(&self, language: Option<&str>) -> String {
+        match language {
+            Some(language) if self.languages.first().is_some_and(|l| l != language) => {
+                format!("{}-{language}", self.slug)
+            }
+            _ => self.slug.clone(),
+        }
+    }
+
+    /// Template repositories of the course, along with the directory of the
+    /// course their contents are taken from.
+    pub fn templates(&self) -> Vec<(String, String)> {
+        if self.languages.is_empty() {
+            return vec![(self.slug.clone(), "template".to_string())];
+        }
+
+        let dir = |language: &str| format!("template/{language}");
+        self.languages.iter().map(|l| (self.template(Some(l)), dir(l))).collect()
+    }
+
+    /// Settings declared in `course.yml`.
+    pub fn settings(&self) -> CourseSettings {
+        CourseSettings {
+            max_attempts: self.max_attempts.map(|n| n as u32),
+            resources: stage::parse_profile(&self.slug, self.resources.as_deref()),
+            tester_image: self.tester_image.clone(),
+        }
+    }
+
+    /// How the PipelineRuns grading the course are set up.
+    pub fn pipeline(&self) -> PipelineSettings {
+        PipelineSettings {
+            pipeline_ref: self.pipeline_ref.clone(),
+            workspace_size: self.workspace_size.clone(),
+            timeout: self.pipeline_timeout.clone(),
+            service_account: self.service_account.clone(),
+        }
+    }
+
+    /// Settings overridden by admins, none if the stored ones are malformed.
+    pub fn overrides(&self) -> CourseSettings {
+        serde_json::from_value(self.setting_overrides.clone()).unwrap_or_else(|e| {
+            warn!("Ignoring malformed setting overrides of course {}: {e}", self.slug);
+            CourseSettings::default()
+        })
+    }
 }
 
 impl From<&Course> for CourseModel {
@@ -85,12 +198,51 @@ impl From<&Course> for CourseModel {
             repository: String::new(),
             logo: String::new(),
             stage_count: 0,
+            require_verified_identity: course.require_verified_identity,
+            max_attempts: course.max_attempts.map(|n| n as i32),
+            resources: course.resources.map(|profile| profile.to_string()),
+            tester_image: course.tester_image.clone(),
+            languages: course.languages.clone(),
+            pipeline_ref: course.pipeline.pipeline_ref.clone(),
+            workspace_size: course.pipeline.workspace_size.clone(),
+            pipeline_timeout: course.pipeline.timeout.clone(),
+            service_account: course.pipeline.service_account.clone(),
+            setting_overrides: json!({}),
+            commit_sha: String::new(),
+            reference: None,
+            content_hash: content_hash(course),
+            opens_at: None,
+            closes_at: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
     }
 }
 
+/// Computes a hash over everything a client caching the course downloads:
+/// its description, the content of its stages, and its assets.
+fn content_hash(course: &Course) -> String {
+    let mut hasher = Sha256::new();
+    for part in [&course.slug, &course.name, &course.description, &course.summary] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+
+    let extended = course.extensions.iter().flatten().flat_map(|(_, ext)| ext.stages.values());
+    for stage in course.stages.values().chain(extended) {
+        hasher.update(stage::content_hash(stage).as_bytes());
+        hasher.update([0]);
+    }
+
+    for asset in &course.assets {
+        hasher.update(asset.path.as_bytes());
+        hasher.update([0]);
+        hasher.update(asset.sha256.as_bytes());
+        hasher.update([0]);
+    }
+    hex::encode(hasher.finalize())
+}
+
 /// Database model representing a user's enrollment in a course
 #[derive(Debug, FromRow)]
 pub struct UserCourseModel {
@@ -129,6 +281,42 @@ pub struct UserCourseModel {
 
     /// Whether the first Git push was received
     pub activated: bool,
+
+    /// Whether the course requires a verified git identity (joined from course)
+    pub require_verified_identity: bool,
+
+    /// Whether the learner has verified their git identity
+    pub identity_verified: bool,
+
+    /// Nonce of the pending git identity verification
+    pub identity_nonce: Option<String>,
+
+    /// Repository status (ready/migrating/failed)
+    pub repository_status: String,
+
+    /// Deadline granted to the user beyond the close of the course
+    pub extended_deadline: Option<DateTime<Utc>>,
+
+    /// Instructor-led cohort the user enrolled with
+    pub cohort: Option<String>,
+
+    /// Deadline of the cohort of the user
+    pub cohort_deadline: Option<DateTime<Utc>>,
+
+    /// Programming language the user takes the course in
+    pub language: Option<String>,
+
+    /// Start of the exam window of the course (joined from course)
+    pub opens_at: Option<DateTime<Utc>>,
+
+    /// End of the exam window of the course (joined from course)
+    pub closes_at: Option<DateTime<Utc>>,
+
+    /// Seconds left until the deadline of the user, by database time
+    pub remaining_secs: Option<i64>,
+
+    /// Whether any stage was completed after the course closed
+    pub completed_late: bool,
 }
 
 impl Default for UserCourseModel {
@@ -146,6 +334,18 @@ impl Default for UserCourseModel {
             cadence: "weekly".to_string(),
             accountability: false,
             activated: false,
+            require_verified_identity: false,
+            identity_verified: false,
+            identity_nonce: None,
+            repository_status: "ready".to_string(),
+            extended_deadline: None,
+            cohort: None,
+            cohort_deadline: None,
+            language: None,
+            opens_at: None,
+            closes_at: None,
+            remaining_secs: None,
+            completed_late: false,
         }
     }
 }
@@ -156,6 +356,17 @@ impl UserCourseModel {
         Self { user_id: user_id.to_string(), course_id: *course_id, ..Default::default() }
     }
 
+    /// Creates a new instance started at the given time
+    pub fn new_at(user_id: &str, course_id: &Uuid, now: DateTime<Utc>) -> Self {
+        Self { started_at: now, ..Self::new(user_id, course_id) }
+    }
+
+    /// Deadline of the user: the close of the course, or the extended
+    /// deadline when one was granted
+    pub fn deadline(&self) -> Option<DateTime<Utc>> {
+        self.closes_at.max(self.extended_deadline)
+    }
+
     /// Sets the proficiency field
     pub fn with_proficiency(mut self, proficiency: &str) -> Self {
         self.proficiency = proficiency.to_string();
@@ -173,4 +384,161 @@ impl UserCourseModel {
         self.accountability = accountability;
         self
     }
+
+    /// Sets the cohort and cohort_deadline fields
+    pub fn with_cohort(mut self, cohort: Option<&str>, deadline: Option<DateTime<Utc>>) -> Self {
+        self.cohort = cohort.map(ToString::to_string);
+        self.cohort_deadline = deadline;
+        self
+    }
+
+    /// Sets the language field
+    pub fn with_language(mut self, language: Option<&str>) -> Self {
+        self.language = language.map(ToString::to_string);
+        self
+    }
+
+    /// Whether activation is on hold until the learner verifies their git identity
+    pub fn awaiting_identity_verification(&self) -> bool {
+        self.require_verified_identity && !self.identity_verified
+    }
+}
+
+/// Database model representing a user allowed to operate a single course
+#[derive(Debug, FromRow)]
+pub struct CourseMaintainerModel {
+    /// ID of the maintained course
+    pub course_id: Uuid,
+
+    /// ID of the maintainer
+    pub user_id: String,
+
+    /// Who granted the maintainer role (user ID or "admin")
+    pub added_by: String,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+}
+
+/// Database model representing the creation of a course from its git
+/// repository in the background
+#[derive(Debug, Clone, FromRow)]
+pub struct CourseJobModel {
+    /// Unique internal identifier
+    pub id: Uuid,
+
+    /// The git repository URL the course is created from
+    pub repository: String,
+
+    /// Branch, tag or commit SHA to create the course from
+    pub reference: Option<String>,
+
+    /// Job status (pending/fetching/parsing/pushing_template/completed/failed)
+    pub status: String,
+
+    /// Slug of the course, once the job completed
+    pub course_slug: Option<String>,
+
+    /// Why the job failed
+    pub error: Option<String>,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Last update timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+impl CourseJobModel {
+    /// Creates a pending job at the given time
+    pub fn new_at(repository: &str, reference: Option<&str>, now: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            repository: repository.to_string(),
+            reference: reference.map(ToString::to_string),
+            status: "pending".to_string(),
+            course_slug: None,
+            error: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Database model representing a sync of a course, along with the stages it
+/// changed
+#[derive(Debug, Clone, FromRow)]
+pub struct CourseRevisionModel {
+    /// Unique internal identifier
+    pub id: Uuid,
+
+    /// Reference to the synced course
+    pub course_id: Uuid,
+
+    /// Commit SHA of the course repository the course was synced from
+    pub commit_sha: String,
+
+    /// Who triggered the sync
+    pub actor: String,
+
+    /// Slugs of the stages the sync added
+    pub stages_added: Vec<String>,
+
+    /// Slugs of the stages the sync removed
+    pub stages_removed: Vec<String>,
+
+    /// Slugs of the stages whose content the sync changed
+    pub stages_updated: Vec<String>,
+
+    /// Time of the sync
+    pub synced_at: DateTime<Utc>,
+}
+
+impl CourseRevisionModel {
+    /// Creates a revision without changes at the given time
+    pub fn new_at(course_id: Uuid, commit_sha: &str, actor: &str, now: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            course_id,
+            commit_sha: commit_sha.to_string(),
+            actor: actor.to_string(),
+            stages_added: Vec::new(),
+            stages_removed: Vec::new(),
+            stages_updated: Vec::new(),
+            synced_at: now,
+        }
+    }
+}
+
+/// Database model representing the progress of the learners of a course
+#[derive(Debug, FromRow)]
+pub struct CourseStatsModel {
+    /// Number of learners enrolled in the course
+    pub enrolled_count: i64,
+
+    /// Number of enrollments activated
+    pub activated_count: i64,
+
+    /// Number of learners who completed every base stage
+    pub completed_count: i64,
+
+    /// Average number of stages completed per learner
+    pub average_completed_stages: f64,
+}
+
+/// Database model representing how far the learners of a course got with one
+/// of its stages
+#[derive(Debug, FromRow)]
+pub struct StageStatsModel {
+    /// Slug of the stage
+    pub stage_slug: String,
+
+    /// Slug of the extension of the stage (joined from extensions)
+    pub extension_slug: Option<String>,
+
+    /// Number of learners who reached the stage
+    pub reached_count: i64,
+
+    /// Number of learners who completed the stage
+    pub completed_count: i64,
 }
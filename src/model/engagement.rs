@@ -0,0 +1,98 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Database model representing an engagement event reported by the frontend
+#[derive(Debug, Clone, FromRow)]
+pub struct EngagementEventModel {
+    /// Unique internal identifier
+    pub id: Uuid,
+
+    /// ID of the enrollment of the learner
+    pub user_course_id: Uuid,
+
+    /// ID of the stage the event relates to
+    pub stage_id: Uuid,
+
+    /// Event type (instruction_viewed, hint_opened, solution_viewed, copy_clone_url)
+    pub event_type: String,
+
+    /// Timestamp reported by the client
+    pub occurred_at: DateTime<Utc>,
+
+    /// Timestamp when the server received the event
+    pub received_at: DateTime<Utc>,
+}
+
+impl EngagementEventModel {
+    /// Creates a new event received now
+    pub fn new(
+        user_course_id: Uuid,
+        stage_id: Uuid,
+        event_type: &str,
+        occurred_at: DateTime<Utc>,
+    ) -> Self {
+        Self::new_at(user_course_id, stage_id, event_type, occurred_at, Utc::now())
+    }
+
+    /// Creates a new event received at the given time
+    pub fn new_at(
+        user_course_id: Uuid,
+        stage_id: Uuid,
+        event_type: &str,
+        occurred_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            user_course_id,
+            stage_id,
+            event_type: event_type.to_string(),
+            occurred_at,
+            received_at: now,
+        }
+    }
+}
+
+/// Database model representing the engagement with a stage across learners,
+/// raw events and rollups combined
+#[derive(Debug, FromRow)]
+pub struct StageEngagementModel {
+    /// Slug of the stage
+    pub stage_slug: String,
+
+    /// Slug of the extension of the stage (joined from extensions)
+    pub extension_slug: Option<String>,
+
+    /// Number of times the instructions were viewed
+    pub instruction_viewed: i64,
+
+    /// Number of times a hint was opened
+    pub hint_opened: i64,
+
+    /// Number of times the solution was viewed
+    pub solution_viewed: i64,
+
+    /// Number of times the clone URL was copied
+    pub copy_clone_url: i64,
+
+    /// Number of learners who viewed the instructions
+    pub viewers: i64,
+
+    /// Number of those learners who pushed at least once to the stage
+    pub viewers_pushed: i64,
+}
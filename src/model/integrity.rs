@@ -0,0 +1,94 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Database model representing a completed user stage awaiting analysis
+#[derive(Clone, Debug, FromRow)]
+pub struct CompletionModel {
+    /// ID of the completed user stage
+    pub user_stage_id: Uuid,
+
+    /// ID of the user's course enrollment
+    pub user_course_id: Uuid,
+
+    /// Timestamp when the stage was started
+    pub started_at: DateTime<Utc>,
+
+    /// Timestamp when the stage was completed
+    pub completed_at: DateTime<Utc>,
+
+    /// Number of failed attempts before the stage was passed
+    pub failed_attempts: i64,
+}
+
+/// Database model representing a suspicious completion
+#[derive(Debug, FromRow)]
+pub struct IntegrityFlagModel {
+    /// Unique internal identifier
+    pub id: Uuid,
+
+    /// ID of the flagged user stage
+    pub user_stage_id: Uuid,
+
+    /// ID of the learner (joined from user_courses)
+    pub user_id: String,
+
+    /// Slug of the stage (joined from stages)
+    pub stage_slug: String,
+
+    /// Name of the rule that raised the flag
+    pub rule: String,
+
+    /// Feature values of the completion when it was flagged
+    pub features: Value,
+
+    /// Timestamp when an instructor dismissed the flag
+    pub dismissed_at: Option<DateTime<Utc>>,
+
+    /// Who dismissed the flag
+    pub dismissed_by: Option<String>,
+
+    /// Note left when dismissing the flag
+    pub note: Option<String>,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+}
+
+impl IntegrityFlagModel {
+    /// Creates a new flag of a completion
+    pub fn new(user_stage_id: Uuid, rule: &str, features: Value) -> Self {
+        Self::new_at(user_stage_id, rule, features, Utc::now())
+    }
+
+    /// Creates a new flag of a completion at the given time
+    pub fn new_at(user_stage_id: Uuid, rule: &str, features: Value, now: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            user_stage_id,
+            user_id: String::new(),
+            stage_slug: String::new(),
+            rule: rule.to_string(),
+            features,
+            dismissed_at: None,
+            dismissed_by: None,
+            note: None,
+            created_at: now,
+        }
+    }
+}
@@ -0,0 +1,49 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+/// Database model representing the deploy key pipelines clone a repository
+/// with
+#[derive(Debug, Clone, FromRow)]
+pub struct DeployKeyModel {
+    /// Owner of the repository
+    pub owner: String,
+
+    /// Name of the repository
+    pub repo: String,
+
+    /// ID of the deploy key in the git server
+    pub key_id: i64,
+
+    /// Private key in OpenSSH format, encrypted with the auth secret
+    pub encrypted_private_key: String,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+}
+
+impl DeployKeyModel {
+    /// Creates the record of a deploy key just added to a repository
+    pub fn new(owner: &str, repo: &str, key_id: i64, encrypted_private_key: &str) -> Self {
+        Self {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            key_id,
+            encrypted_private_key: encrypted_private_key.to_string(),
+            created_at: Utc::now(),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Database model representing the import of a learner repository from
+/// another Gitea instance
+#[derive(Debug, Clone, FromRow)]
+pub struct RepoMigrationModel {
+    /// Unique internal identifier
+    pub id: Uuid,
+
+    /// ID of the user course the repository belongs to
+    pub user_course_id: Uuid,
+
+    /// Base URL of the source Gitea instance
+    pub source_url: String,
+
+    /// Full name of the source repository (e.g., "owner/repo")
+    pub source_repo: String,
+
+    /// Username to authenticate against the source
+    pub auth_username: Option<String>,
+
+    /// Password to authenticate against the source, encrypted with the auth
+    /// secret
+    pub encrypted_password: Option<String>,
+
+    /// Access token to authenticate against the source, encrypted with the
+    /// auth secret
+    pub encrypted_token: Option<String>,
+
+    /// Migration status (pending/running/migrated/failed)
+    pub status: String,
+
+    /// Why the latest migration failed
+    pub error: Option<String>,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Last update timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+impl RepoMigrationModel {
+    /// Creates a pending migration of a source repository
+    pub fn new(user_course_id: Uuid, source_url: &str, source_repo: &str) -> Self {
+        Self::new_at(user_course_id, source_url, source_repo, Utc::now())
+    }
+
+    /// Creates a pending migration at the given time
+    pub fn new_at(
+        user_course_id: Uuid,
+        source_url: &str,
+        source_repo: &str,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            user_course_id,
+            source_url: source_url.trim_end_matches('/').to_string(),
+            source_repo: source_repo.to_string(),
+            auth_username: None,
+            encrypted_password: None,
+            encrypted_token: None,
+            status: "pending".to_string(),
+            error: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Sets the encrypted credentials for the source
+    pub fn with_credentials(
+        mut self,
+        username: Option<String>,
+        password: Option<String>,
+        token: Option<String>,
+    ) -> Self {
+        self.auth_username = username;
+        self.encrypted_password = password;
+        self.encrypted_token = token;
+        self
+    }
+
+    /// URL the source repository is cloned from
+    pub fn clone_addr(&self) -> String {
+        format!("{}/{}.git", self.source_url, self.source_repo)
+    }
+}
@@ -12,15 +12,41 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod asset;
 mod attempt;
+mod audit;
 mod course;
+mod engagement;
 mod extension;
+mod integrity;
+mod key;
+mod migration;
+mod notification;
+mod pool;
+mod progress;
+mod registry;
+mod setting;
 mod stage;
+mod token;
+mod trial;
 mod user;
 
 // Re-exports
+pub use asset::*;
 pub use attempt::*;
+pub use audit::*;
 pub use course::*;
+pub use engagement::*;
 pub use extension::*;
+pub use integrity::*;
+pub use key::*;
+pub use migration::*;
+pub use notification::*;
+pub use pool::*;
+pub use progress::*;
+pub use registry::*;
+pub use setting::*;
 pub use stage::*;
+pub use token::*;
+pub use trial::*;
 pub use user::*;
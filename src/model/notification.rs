@@ -0,0 +1,71 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+/// Database model representing the notification preferences of a user
+#[derive(Debug, Clone, FromRow)]
+pub struct NotificationPreferencesModel {
+    /// ID of the user
+    pub user_id: String,
+
+    /// Channel notifications are delivered through (email, webhook)
+    pub channel: String,
+
+    /// Webhook URL, or email address replacing the one of the account
+    pub destination: Option<String>,
+
+    /// Whether course announcements are sent
+    pub announcements: bool,
+
+    /// Whether feedback comments on attempts are sent
+    pub feedback: bool,
+
+    /// Whether reminders of approaching deadlines are sent
+    pub deadline_reminders: bool,
+
+    /// Whether accountability emails are sent for the courses they were
+    /// enabled on
+    pub accountability: bool,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Last update timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+impl NotificationPreferencesModel {
+    /// Creates the preferences users without any start from, matching what
+    /// they received before preferences existed.
+    pub fn new(user_id: &str) -> Self {
+        Self::new_at(user_id, Utc::now())
+    }
+
+    /// Creates the default preferences at the given time
+    pub fn new_at(user_id: &str, now: DateTime<Utc>) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            channel: "email".to_string(),
+            destination: None,
+            announcements: true,
+            feedback: true,
+            deadline_reminders: true,
+            accountability: true,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
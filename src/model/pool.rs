@@ -0,0 +1,72 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Prefix of the names of pooled repositories, telling them apart from the
+/// repositories of user courses, which are named after their UUID.
+pub const POOL_REPO_PREFIX: &str = "pool-";
+
+/// Database model representing a spare learner repository generated ahead
+/// of enrollments
+#[derive(Debug, Clone, FromRow)]
+pub struct PooledRepoModel {
+    /// Unique internal identifier, reserved for the repository name
+    pub id: Uuid,
+
+    /// ID of the course whose template the repository was generated from
+    pub course_id: Uuid,
+
+    /// Commit of the course the template was synced from at generation
+    pub commit_sha: String,
+
+    /// Whether an enrollment took the repository
+    pub claimed: bool,
+
+    /// Name the repository was renamed to when claimed
+    pub claimed_as: Option<String>,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Timestamp the repository was claimed at
+    pub claimed_at: Option<DateTime<Utc>>,
+}
+
+impl PooledRepoModel {
+    /// Creates an unclaimed repository of a course at the given commit
+    pub fn new(course_id: Uuid, commit_sha: &str) -> Self {
+        Self::new_at(course_id, commit_sha, Utc::now())
+    }
+
+    /// Creates an unclaimed repository at the given time
+    pub fn new_at(course_id: Uuid, commit_sha: &str, now: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            course_id,
+            commit_sha: commit_sha.to_string(),
+            claimed: false,
+            claimed_as: None,
+            created_at: now,
+            claimed_at: None,
+        }
+    }
+
+    /// Name of the repository while it is in the pool
+    pub fn repo(&self) -> String {
+        format!("{POOL_REPO_PREFIX}{}", self.id)
+    }
+}
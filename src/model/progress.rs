@@ -0,0 +1,37 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Database model representing the progress summary of an enrollment
+#[derive(Debug, FromRow, PartialEq)]
+pub struct ProgressModel {
+    /// ID of the user's course enrollment
+    pub user_course_id: Uuid,
+
+    /// ID of the enrolled user
+    pub user_id: String,
+
+    /// Number of completed stages
+    pub completed_stage_count: i32,
+
+    /// Timestamp of the latest stage start or completion
+    pub last_activity_at: Option<DateTime<Utc>>,
+
+    /// Number of completed stages per extension slug
+    pub extension_progress: Value,
+}
@@ -0,0 +1,93 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Database model representing the registry robot account of a course
+#[derive(Debug, Clone, FromRow)]
+pub struct RegistryCredentialModel {
+    /// Unique internal identifier
+    pub id: Uuid,
+
+    /// ID of the course the pipelines push for
+    pub course_id: Uuid,
+
+    /// Harbor project the robot account may push to
+    pub project: String,
+
+    /// ID of the robot account in Harbor
+    pub robot_id: i64,
+
+    /// Full name of the robot account, used as registry username
+    pub robot_name: String,
+
+    /// Name of the Kubernetes Secret holding the docker config
+    pub secret_name: String,
+
+    /// Robot account secret, encrypted with the auth secret
+    pub encrypted_secret: String,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Timestamp when the credentials were replaced by a rotation
+    pub retired_at: Option<DateTime<Utc>>,
+}
+
+impl RegistryCredentialModel {
+    /// Creates the credentials of a newly created robot account
+    pub fn new(
+        course_id: Uuid,
+        project: &str,
+        robot_id: i64,
+        robot_name: &str,
+        secret_name: &str,
+        encrypted_secret: &str,
+    ) -> Self {
+        Self::new_at(
+            course_id,
+            project,
+            robot_id,
+            robot_name,
+            secret_name,
+            encrypted_secret,
+            Utc::now(),
+        )
+    }
+
+    /// Creates the credentials at the given time
+    pub fn new_at(
+        course_id: Uuid,
+        project: &str,
+        robot_id: i64,
+        robot_name: &str,
+        secret_name: &str,
+        encrypted_secret: &str,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            course_id,
+            project: project.to_string(),
+            robot_id,
+            robot_name: robot_name.to_string(),
+            secret_name: secret_name.to_string(),
+            encrypted_secret: encrypted_secret.to_string(),
+            created_at: now,
+            retired_at: None,
+        }
+    }
+}
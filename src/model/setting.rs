@@ -0,0 +1,64 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+
+/// Key of the setting holding the maintenance mode.
+pub const MAINTENANCE_SETTING: &str = "maintenance";
+
+/// Key of the setting holding the instance defaults of course settings.
+pub const COURSE_DEFAULTS_SETTING: &str = "course_defaults";
+
+/// Database model representing a setting changed at runtime
+#[derive(Debug, Clone, FromRow)]
+pub struct SettingModel {
+    /// Unique name of the setting
+    pub key: String,
+
+    /// Value of the setting
+    pub value: Value,
+
+    /// Who changed the setting last (user ID or "admin")
+    pub updated_by: String,
+
+    /// Last update timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The read-only maintenance mode, stored as a setting while it is on
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MaintenanceMode {
+    /// Message shown to the clients whose writes are refused
+    pub message: Option<String>,
+
+    /// Whether webhook deliveries are refused too, rather than accepted with
+    /// the attempts of pushes queued until the maintenance ends
+    pub reject_webhooks: bool,
+
+    /// Timestamp the maintenance started at
+    pub started_at: DateTime<Utc>,
+
+    /// Timestamp the maintenance ends at on its own, if any
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl MaintenanceMode {
+    /// Whether the maintenance is still on at the given time
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
@@ -13,10 +13,13 @@
 // limitations under the License.
 
 use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
 use sqlx::FromRow;
+use tracing::warn;
 use uuid::Uuid;
 
-use crate::schema::Stage;
+use crate::schema::{CourseSettings, ResourceProfile, Stage};
 
 /// Represents a learning stage within a course or extension
 #[derive(Debug, FromRow)]
@@ -52,9 +55,30 @@ pub struct StageModel {
     /// Detailed description of the solution approach and logic, if available.
     pub solution: Option<String>,
 
-    /// Sorting weight (default: 0)
+    /// Legacy sorting weight, still written but no longer read
     pub weight: i32,
 
+    /// Position among the base stages of the course, or among the stages
+    /// of the extension
+    pub position: i32,
+
+    /// Maximum number of graded attempts, if limited
+    pub max_attempts: Option<i32>,
+
+    /// Resource profile of the test pod (small, medium, large), the default
+    /// one if not declared
+    pub resources: Option<String>,
+
+    /// Hash of the stage content, used to detect changes on course sync
+    pub content_hash: String,
+
+    /// Whether learners may skip the stage without passing its tests
+    pub optional: bool,
+
+    /// Whether the stage was removed from the course while learners still
+    /// referenced it
+    pub archived: bool,
+
     /// Creation timestamp
     pub created_at: DateTime<Utc>,
 
@@ -80,10 +104,26 @@ impl StageModel {
         self.weight = weight;
         self
     }
+
+    /// Sets the position field
+    pub fn with_position(mut self, position: i32) -> StageModel {
+        self.position = position;
+        self
+    }
+
+    /// Settings declared in `stage.yml`, over those of the course.
+    pub fn settings(&self) -> CourseSettings {
+        CourseSettings {
+            max_attempts: self.max_attempts.map(|n| n as u32),
+            resources: parse_profile(&self.slug, self.resources.as_deref()),
+            tester_image: None,
+        }
+    }
 }
 
 impl From<Stage> for StageModel {
     fn from(stage: Stage) -> Self {
+        let content_hash = content_hash(&stage);
         Self {
             id: Uuid::now_v7(),
             // Will be replaced by actual course_id
@@ -98,12 +138,34 @@ impl From<Stage> for StageModel {
             instruction: stage.instruction,
             solution: stage.solution,
             weight: 0,
+            position: 0,
+            max_attempts: stage.max_attempts.map(|n| n as i32),
+            resources: stage.resources.map(|profile| profile.to_string()),
+            content_hash,
+            optional: stage.optional,
+            archived: false,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
     }
 }
 
+/// Parses a stored resource profile, ignoring one this server does not
+/// define.
+pub(super) fn parse_profile(owner: &str, profile: Option<&str>) -> Option<ResourceProfile> {
+    profile?.parse().inspect_err(|e| warn!("{owner} has {e}, using the default profile")).ok()
+}
+
+/// Computes a hash over the stage content shown to and graded for learners.
+pub(super) fn content_hash(stage: &Stage) -> String {
+    let mut hasher = Sha256::new();
+    for part in [&stage.slug, &stage.description, &stage.instruction] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    hex::encode(hasher.finalize())
+}
+
 /// Database model representing a user's progress in a course stage
 #[derive(Debug, FromRow)]
 pub struct UserStageModel {
@@ -133,11 +195,31 @@ pub struct UserStageModel {
 
     /// Timestamp when the stage was completed
     pub completed_at: Option<DateTime<Utc>>,
+
+    /// Extra attempts granted on top of the stage limit
+    pub granted_attempts: i32,
+
+    /// Commit SHA of the learner's repository which passed the stage
+    pub completed_commit: Option<String>,
+
+    /// Timestamp when the course image was last pushed to the registry
+    pub image_pushed_at: Option<DateTime<Utc>>,
+
+    /// Number of hints the user revealed
+    pub hints_unlocked: i32,
+
+    /// Number of hints of the stage
+    pub hints_total: i64,
 }
 
 impl UserStageModel {
     /// Creates a new instance with default values
     pub fn new(user_course_id: Uuid, stage_id: Uuid) -> Self {
+        Self::new_at(user_course_id, stage_id, Utc::now())
+    }
+
+    /// Creates a new instance started at the given time
+    pub fn new_at(user_course_id: Uuid, stage_id: Uuid, now: DateTime<Utc>) -> Self {
         Self {
             id: Uuid::now_v7(),
             user_course_id,
@@ -146,8 +228,13 @@ impl UserStageModel {
             stage_slug: String::new(),
             status: "in_progress".to_string(),
             test: "failed".to_string(),
-            started_at: Utc::now(),
+            started_at: now,
             completed_at: None,
+            granted_attempts: 0,
+            completed_commit: None,
+            image_pushed_at: None,
+            hints_unlocked: 0,
+            hints_total: 0,
         }
     }
 
@@ -158,9 +245,230 @@ impl UserStageModel {
     }
 
     /// Marks the stage as completed with current timestamp
-    pub fn complete(mut self) -> Self {
+    pub fn complete(self) -> Self {
+        self.complete_at(Utc::now())
+    }
+
+    /// Marks the stage as completed at the given time
+    pub fn complete_at(mut self, now: DateTime<Utc>) -> Self {
         self.status = "completed".to_string();
-        self.completed_at = Some(Utc::now());
+        self.completed_at = Some(now);
+        self
+    }
+
+    /// Marks the stage as skipped, which leaves it without completing it
+    pub fn skip(mut self) -> Self {
+        self.status = "skipped".to_string();
+        self
+    }
+
+    /// Sets the completed_commit field
+    pub fn with_completed_commit(mut self, commit: Option<&str>) -> Self {
+        self.completed_commit = commit.filter(|c| !c.is_empty()).map(str::to_string);
         self
     }
 }
+
+/// Database model representing a graded attempt of a user stage
+#[derive(Debug, FromRow)]
+pub struct StageAttemptModel {
+    /// Unique internal identifier
+    pub id: Uuid,
+
+    /// ID of the user stage
+    pub user_stage_id: Uuid,
+
+    /// ID of the user who made the attempt
+    pub user_id: String,
+
+    /// Slug of the stage
+    pub stage_slug: String,
+
+    /// Name of the PipelineRun, if one was triggered
+    pub pipeline_run: Option<String>,
+
+    /// Attempt status (pending, passed, failed, budget_exhausted, cancelled)
+    pub status: String,
+
+    /// Content hash of the stage at the time of the attempt
+    pub content_hash: String,
+
+    /// Commit SHA of the course repository at the time of the attempt
+    pub course_commit: String,
+
+    /// Commit SHA of the learner's repository that was graded
+    pub repo_commit: String,
+
+    /// Reference of the tester image the attempt was graded with
+    pub tester_image: String,
+
+    /// Whether the stage content changed since the attempt
+    pub stale: bool,
+
+    /// Whether the attempt was submitted after the deadline of the user
+    pub late: bool,
+
+    /// Reason the test run reported for its outcome, once graded
+    pub reason: Option<String>,
+
+    /// Tail of the tester output, once graded
+    pub log: Option<String>,
+
+    /// Results of the test cases the tester ran
+    pub results: Value,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// When the outcome of its PipelineRun was recorded
+    pub finished_at: Option<DateTime<Utc>>,
+
+    /// Position in the order of insertion, assigned by the database
+    pub seq: i64,
+}
+
+/// Output of the test run grading an attempt, stored along with its outcome
+#[derive(Debug, Default)]
+pub struct AttemptOutput<'a> {
+    /// Reason the test run reported for its outcome
+    pub reason: &'a str,
+
+    /// Tail of the tester output
+    pub log: &'a str,
+
+    /// Results of the test cases, as a JSON array
+    pub results: Value,
+}
+
+impl StageAttemptModel {
+    /// Creates a new pending attempt
+    pub fn new(user_stage_id: Uuid, content_hash: &str) -> Self {
+        Self::new_at(user_stage_id, content_hash, Utc::now())
+    }
+
+    /// Creates a new pending attempt at the given time
+    pub fn new_at(user_stage_id: Uuid, content_hash: &str, now: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            user_stage_id,
+            user_id: String::new(),
+            stage_slug: String::new(),
+            pipeline_run: None,
+            status: "pending".to_string(),
+            content_hash: content_hash.to_string(),
+            course_commit: String::new(),
+            repo_commit: String::new(),
+            tester_image: String::new(),
+            stale: false,
+            late: false,
+            reason: None,
+            log: None,
+            results: json!([]),
+            created_at: now,
+            finished_at: None,
+            seq: 0,
+        }
+    }
+
+    /// Sets the course_commit field
+    pub fn with_course_commit(mut self, commit: &str) -> Self {
+        self.course_commit = commit.to_string();
+        self
+    }
+
+    /// Sets the repo_commit field
+    pub fn with_repo_commit(mut self, commit: &str) -> Self {
+        self.repo_commit = commit.to_string();
+        self
+    }
+
+    /// Sets the tester_image field
+    pub fn with_tester_image(mut self, image: &str) -> Self {
+        self.tester_image = image.to_string();
+        self
+    }
+
+    /// Sets the pipeline_run field
+    pub fn with_pipeline_run(mut self, name: &str) -> Self {
+        self.pipeline_run = Some(name.to_string());
+        self
+    }
+
+    /// Sets the status field
+    pub fn with_status(mut self, status: &str) -> Self {
+        self.status = status.to_string();
+        self
+    }
+
+    /// Sets the reason field
+    pub fn with_reason(mut self, reason: &str) -> Self {
+        self.reason = Some(reason.to_string());
+        self
+    }
+
+    /// State of the PipelineRun grading the attempt, none if it was never
+    /// triggered.
+    pub fn pipeline_status(&self) -> Option<&'static str> {
+        self.pipeline_run.as_ref()?;
+        match self.status.as_str() {
+            "pending" => Some("running"),
+            "cancelled" => Some("cancelled"),
+            _ => Some("finished"),
+        }
+    }
+}
+
+/// Database model representing a hint of a stage
+#[derive(Debug, FromRow)]
+pub struct StageHintModel {
+    /// Position of the hint in reveal order, starting at 0
+    pub position: i32,
+
+    /// Markdown content of the hint
+    pub content: String,
+}
+
+/// Database model representing an attempt waiting for its pipeline run
+#[derive(Debug, FromRow)]
+pub struct QueuedAttemptModel {
+    /// ID of the attempt
+    pub id: Uuid,
+
+    /// ID of the user's course enrollment, which names its repository
+    pub user_course_id: Uuid,
+
+    /// Slug of the course
+    pub course_slug: String,
+
+    /// Slug of the stage
+    pub stage_slug: String,
+
+    /// Commit SHA of the course repository at the time of the attempt
+    pub course_commit: String,
+
+    /// Content hash of the stage at the time of the attempt
+    pub content_hash: String,
+
+    /// Commit SHA of the learner's repository to grade
+    pub repo_commit: String,
+}
+
+/// Database model representing a stage of a course along with the state of
+/// an enrolled learner on it
+#[derive(Debug, Clone, FromRow)]
+pub struct RoadmapStageModel {
+    /// Unique human-readable identifier within parent context
+    pub slug: String,
+
+    /// Display name of the stage
+    pub name: String,
+
+    /// Optional slug of the parent extension (null if part of main course)
+    pub extension_slug: Option<String>,
+
+    /// Status of the learner's user stage, if it was started
+    pub status: Option<String>,
+
+    /// Whether this is the current stage of the learner
+    pub current: bool,
+}
@@ -0,0 +1,82 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Database model representing an API token of an integration
+#[derive(Debug, Clone, FromRow)]
+pub struct ApiTokenModel {
+    /// Unique internal identifier
+    pub id: Uuid,
+
+    /// Name telling what the token is used for
+    pub name: String,
+
+    /// SHA-256 hash of the secret, the secret itself is never stored
+    pub token_hash: String,
+
+    /// Slugs of the courses the token may read
+    pub courses: Vec<String>,
+
+    /// What the token may read (progress, stats)
+    pub capabilities: Vec<String>,
+
+    /// Who created the token
+    pub created_by: String,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// When the token was last used to authenticate a request
+    pub last_used_at: Option<DateTime<Utc>>,
+
+    /// When the token was revoked
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiTokenModel {
+    pub fn new(
+        name: &str,
+        token_hash: &str,
+        courses: Vec<String>,
+        capabilities: Vec<String>,
+        created_by: &str,
+    ) -> Self {
+        Self::new_at(name, token_hash, courses, capabilities, created_by, Utc::now())
+    }
+
+    /// Creates a token at the given time
+    pub fn new_at(
+        name: &str,
+        token_hash: &str,
+        courses: Vec<String>,
+        capabilities: Vec<String>,
+        created_by: &str,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            name: name.to_string(),
+            token_hash: token_hash.to_string(),
+            courses,
+            capabilities,
+            created_by: created_by.to_string(),
+            created_at: now,
+            last_used_at: None,
+            revoked_at: None,
+        }
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Prefix of the names of trial repositories, telling them apart from the
+/// repositories of user courses, which are named after their UUID.
+pub const TRIAL_REPO_PREFIX: &str = "trial-";
+
+/// Database model representing a trial of the first stage of a course by a
+/// visitor who has not signed up
+#[derive(Debug, Clone, FromRow)]
+pub struct TrialModel {
+    /// Unique internal identifier
+    pub id: Uuid,
+
+    /// ID of the course tried
+    pub course_id: Uuid,
+
+    /// ID of the stage tried, the first one of the course
+    pub stage_id: Uuid,
+
+    /// Keyed hash of the client address the trial was requested from
+    pub client_key: String,
+
+    /// Trial status (active, converted, expired)
+    pub status: String,
+
+    /// Test result of the latest graded push (pending, passed, failed)
+    pub test: Option<String>,
+
+    /// Name of the PipelineRun grading the latest push
+    pub pipeline_run: Option<String>,
+
+    /// ID of the user course the trial was converted into
+    pub user_course_id: Option<Uuid>,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Last update timestamp
+    pub updated_at: DateTime<Utc>,
+
+    /// Timestamp after which the trial repository is deleted
+    pub expires_at: DateTime<Utc>,
+
+    /// Course slug (joined from courses table)
+    pub course_slug: String,
+
+    /// Stage slug (joined from stages table)
+    pub stage_slug: String,
+}
+
+impl TrialModel {
+    /// Creates a trial of a stage that expires after `ttl`
+    pub fn new(course_id: Uuid, stage_id: Uuid, client_key: &str, ttl: Duration) -> Self {
+        Self::new_at(course_id, stage_id, client_key, ttl, Utc::now())
+    }
+
+    /// Creates a trial started at the given time
+    pub fn new_at(
+        course_id: Uuid,
+        stage_id: Uuid,
+        client_key: &str,
+        ttl: Duration,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            course_id,
+            stage_id,
+            client_key: client_key.to_string(),
+            status: "active".to_string(),
+            test: None,
+            pipeline_run: None,
+            user_course_id: None,
+            created_at: now,
+            updated_at: now,
+            expires_at: now + ttl,
+            course_slug: String::new(),
+            stage_slug: String::new(),
+        }
+    }
+
+    /// Name of the trial repository in the git server
+    pub fn repo(&self) -> String {
+        format!("{TRIAL_REPO_PREFIX}{}", self.id)
+    }
+
+    /// Whether pushes to the trial are still graded at the given time
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.status == "active" && self.expires_at > now
+    }
+}
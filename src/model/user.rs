@@ -14,6 +14,7 @@
 
 use chrono::{DateTime, Utc};
 use sqlx::FromRow;
+use uuid::Uuid;
 
 /// Database model representing a user entity
 #[derive(Debug, FromRow)]
@@ -38,6 +39,12 @@ pub struct UserModel {
 
     /// Last update timestamp
     pub updated_at: DateTime<Utc>,
+
+    /// ID of the user this duplicate account was merged into
+    pub merged_into: Option<String>,
+
+    /// When the account was merged into another
+    pub merged_at: Option<DateTime<Utc>>,
 }
 
 impl UserModel {
@@ -51,6 +58,8 @@ impl UserModel {
             image: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            merged_into: None,
+            merged_at: None,
         }
     }
 
@@ -75,6 +84,25 @@ impl UserModel {
     }
 }
 
+/// An enrollment of one of the users taking part in a merge
+#[derive(Debug, FromRow)]
+pub struct MergeEnrollmentModel {
+    /// ID of the user course
+    pub id: Uuid,
+
+    /// ID of the enrolled user
+    pub user_id: String,
+
+    /// Slug of the course
+    pub course_slug: String,
+
+    /// Number of completed stages, compared to resolve conflicts
+    pub completed_stage_count: i64,
+
+    /// When the enrollment was archived by an earlier merge
+    pub archived_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, FromRow)]
 pub struct JsonWebKey {
     /// Unique identifier for each web key
@@ -0,0 +1,198 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use uuid::Uuid;
+
+use crate::{
+    database::{Database, Transaction},
+    model::{AssetModel, StageManifestModel, StarterChangeModel},
+    repository::Result,
+    schema::{Asset, StarterChange, StarterDiff, StarterFile},
+};
+
+/// Repository for the index of course assets.
+pub struct AssetRepository;
+
+impl AssetRepository {
+    /// Replace the indexed assets of a course, linking them to its stages by
+    /// slug. Must run after the stages were written.
+    pub async fn replace(
+        tx: &mut Transaction<'_>,
+        course_id: Uuid,
+        assets: &[Asset],
+    ) -> Result<()> {
+        sqlx::query("DELETE FROM asset_index WHERE course_id = $1")
+            .bind(course_id)
+            .execute(&mut **tx)
+            .await?;
+
+        let paths: Vec<&str> = assets.iter().map(|a| a.path.as_str()).collect();
+        let stages: Vec<Option<&str>> = assets.iter().map(|a| a.stage.as_deref()).collect();
+        let kinds: Vec<String> = assets.iter().map(|a| a.kind.to_string()).collect();
+        let hashes: Vec<&str> = assets.iter().map(|a| a.sha256.as_str()).collect();
+        let sizes: Vec<i64> = assets.iter().map(|a| a.size as i64).collect();
+
+        sqlx::query(
+            r#"
+            INSERT INTO asset_index (course_id, stage_id, path, kind, sha256, size)
+            SELECT $1, s.id, a.path, a.kind, a.sha256, a.size
+            FROM UNNEST($2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[], $6::BIGINT[])
+                AS a(path, stage, kind, sha256, size)
+            LEFT JOIN stages s ON s.course_id = $1 AND s.slug = a.stage
+            "#,
+        )
+        .bind(course_id)
+        .bind(&paths)
+        .bind(&stages)
+        .bind(&kinds)
+        .bind(&hashes)
+        .bind(&sizes)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Replace the indexed starter code of a course and what it changes,
+    /// linking both to its stages by slug. Must run after the stages were
+    /// written.
+    pub async fn replace_starters(
+        tx: &mut Transaction<'_>,
+        course_id: Uuid,
+        files: &[StarterFile],
+        diffs: &[StarterDiff],
+    ) -> Result<()> {
+        for table in ["starter_files", "starter_changes"] {
+            sqlx::query(&format!("DELETE FROM {table} WHERE course_id = $1"))
+                .bind(course_id)
+                .execute(&mut **tx)
+                .await?;
+        }
+
+        let stages: Vec<&str> = files.iter().map(|f| f.stage.as_str()).collect();
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        let hashes: Vec<&str> = files.iter().map(|f| f.sha256.as_str()).collect();
+        let sizes: Vec<i64> = files.iter().map(|f| f.size as i64).collect();
+
+        sqlx::query(
+            r#"
+            INSERT INTO starter_files (course_id, stage_id, path, sha256, size)
+            SELECT $1, s.id, f.path, f.sha256, f.size
+            FROM UNNEST($2::TEXT[], $3::TEXT[], $4::TEXT[], $5::BIGINT[])
+                AS f(stage, path, sha256, size)
+            JOIN stages s ON s.course_id = $1 AND s.slug = f.stage
+            "#,
+        )
+        .bind(course_id)
+        .bind(&stages)
+        .bind(&paths)
+        .bind(&hashes)
+        .bind(&sizes)
+        .execute(&mut **tx)
+        .await?;
+
+        let (stages, changes): (Vec<&str>, Vec<&StarterChange>) =
+            diffs.iter().flat_map(|d| d.changes.iter().map(|c| (d.stage.as_str(), c))).unzip();
+        let paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+        let kinds: Vec<String> = changes.iter().map(|c| c.kind.to_string()).collect();
+        let contents: Vec<Option<&str>> = changes.iter().map(|c| c.diff.as_deref()).collect();
+        let truncated: Vec<bool> = changes.iter().map(|c| c.truncated).collect();
+
+        sqlx::query(
+            r#"
+            INSERT INTO starter_changes (course_id, stage_id, path, kind, diff, truncated)
+            SELECT $1, s.id, c.path, c.kind, c.diff, c.truncated
+            FROM UNNEST($2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[], $6::BOOLEAN[])
+                AS c(stage, path, kind, diff, truncated)
+            JOIN stages s ON s.course_id = $1 AND s.slug = c.stage
+            "#,
+        )
+        .bind(course_id)
+        .bind(&stages)
+        .bind(&paths)
+        .bind(&kinds)
+        .bind(&contents)
+        .bind(&truncated)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Fetch the files the starter code of a stage changes, ordered by path.
+    pub async fn find_starter_changes(
+        db: &Database,
+        stage_id: Uuid,
+    ) -> Result<Vec<StarterChangeModel>> {
+        let rows = sqlx::query_as::<_, StarterChangeModel>(
+            "SELECT * FROM starter_changes WHERE stage_id = $1 ORDER BY path",
+        )
+        .bind(stage_id)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Fetch the indexed assets of a course, ordered by path.
+    pub async fn find_by_course(db: &Database, course_id: Uuid) -> Result<Vec<AssetModel>> {
+        let rows = sqlx::query_as::<_, AssetModel>(
+            "SELECT * FROM asset_index WHERE course_id = $1 ORDER BY path",
+        )
+        .bind(course_id)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Fetch an indexed asset of a course by its path.
+    pub async fn get(db: &Database, course_id: Uuid, path: &str) -> Result<AssetModel> {
+        let row = sqlx::query_as::<_, AssetModel>(
+            "SELECT * FROM asset_index WHERE course_id = $1 AND path = $2",
+        )
+        .bind(course_id)
+        .bind(path)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Fetch the stages of a course in course order, with the size of their
+    /// instruction and files, and their number of hints.
+    pub async fn find_stage_manifests(
+        db: &Database,
+        course_id: Uuid,
+    ) -> Result<Vec<StageManifestModel>> {
+        let rows = sqlx::query_as::<_, StageManifestModel>(
+            r#"
+            SELECT s.slug, s.name, e.slug AS extension_slug, s.content_hash,
+                   (OCTET_LENGTH(s.instruction) + COALESCE(SUM(a.size), 0))::BIGINT AS size,
+                   COUNT(a.path) FILTER (WHERE a.kind = 'hint') AS hint_count
+            FROM stages s
+            LEFT JOIN extensions e ON s.extension_id = e.id
+            LEFT JOIN asset_index a ON a.stage_id = s.id
+            WHERE s.course_id = $1 AND NOT s.archived
+            GROUP BY s.id, e.slug, e.weight
+            ORDER BY COALESCE(e.weight, -1) ASC, s.position ASC
+            "#,
+        )
+        .bind(course_id)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+}
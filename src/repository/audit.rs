@@ -0,0 +1,100 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    database::{Database, Transaction},
+    model::AuditLogModel,
+    repository::Result,
+    utils::pagination::Page,
+};
+
+/// Repository for the audit trail.
+pub struct AuditRepository;
+
+impl AuditRepository {
+    /// Append an entry to the audit trail.
+    pub async fn create(db: &Database, log: &AuditLogModel) -> Result<AuditLogModel> {
+        let row = sqlx::query_as::<_, AuditLogModel>(
+            r#"
+            INSERT INTO audit_logs (id, actor, action, target, details, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+        )
+        .bind(log.id)
+        .bind(&log.actor)
+        .bind(&log.action)
+        .bind(&log.target)
+        .bind(&log.details)
+        .bind(log.created_at)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Append an entry to the audit trail within a transaction, so that it
+    /// is committed along with the change it records.
+    pub async fn create_in(tx: &mut Transaction<'_>, log: &AuditLogModel) -> Result<AuditLogModel> {
+        let row = sqlx::query_as::<_, AuditLogModel>(
+            r#"
+            INSERT INTO audit_logs (id, actor, action, target, details, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+        )
+        .bind(log.id)
+        .bind(&log.actor)
+        .bind(&log.action)
+        .bind(&log.target)
+        .bind(&log.details)
+        .bind(log.created_at)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Find a page of the audit trail, newest first, optionally restricted
+    /// to an actor, an action and targets starting with a prefix.
+    pub async fn find(
+        db: &Database,
+        actor: Option<&str>,
+        action: Option<&str>,
+        target: Option<&str>,
+        page: &Page,
+    ) -> Result<Vec<AuditLogModel>> {
+        let rows = sqlx::query_as::<_, AuditLogModel>(
+            r#"
+            SELECT * FROM audit_logs
+            WHERE ($1::TEXT IS NULL OR actor = $1)
+                AND ($2::TEXT IS NULL OR action = $2)
+                AND ($3::TEXT IS NULL OR starts_with(target, $3))
+                AND ($4::TIMESTAMPTZ IS NULL OR (created_at, seq) < ($4, $5))
+            ORDER BY created_at DESC, seq DESC
+            LIMIT $6
+            "#,
+        )
+        .bind(actor)
+        .bind(action)
+        .bind(target)
+        .bind(page.after.map(|c| c.created_at))
+        .bind(page.after.map(|c| c.seq))
+        .bind(page.fetch_limit())
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+}
@@ -12,13 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use chrono::{DateTime, Utc};
+use serde_json::Value;
 use tracing::debug;
 use uuid::Uuid;
 
 use crate::{
     database::{Database, Transaction},
-    model::{AttemptModel, CourseModel, UserCourseModel},
+    model::{
+        AttemptModel, CourseJobModel, CourseMaintainerModel, CourseModel, CourseRevisionModel,
+        CourseStatsModel, StageStatsModel, UserCourseModel,
+    },
     repository::Result,
+    utils::status::STATUS_CHANNEL,
 };
 
 /// Repository for managing courses in the database.
@@ -30,8 +36,8 @@ impl CourseRepository {
         let row = sqlx::query_as::<_, CourseModel>(
             r#"
             INSERT INTO courses (
-                id, slug, name, short_name, release_status, description, summary, repository, logo, stage_count, created_at, updated_at
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                id, slug, name, short_name, release_status, description, summary, repository, logo, stage_count, require_verified_identity, max_attempts, resources, tester_image, languages, pipeline_ref, workspace_size, pipeline_timeout, service_account, commit_sha, reference, content_hash, created_at, updated_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24)
             RETURNING *
             "#,
         )
@@ -45,6 +51,18 @@ impl CourseRepository {
         .bind(&course.repository)
         .bind(&course.logo)
         .bind(course.stage_count)
+        .bind(course.require_verified_identity)
+        .bind(course.max_attempts)
+        .bind(&course.resources)
+        .bind(&course.tester_image)
+        .bind(&course.languages)
+        .bind(&course.pipeline_ref)
+        .bind(&course.workspace_size)
+        .bind(&course.pipeline_timeout)
+        .bind(&course.service_account)
+        .bind(&course.commit_sha)
+        .bind(&course.reference)
+        .bind(&course.content_hash)
         .bind(course.created_at)
         .bind(course.updated_at)
         .fetch_one(&mut **tx)
@@ -82,11 +100,66 @@ impl CourseRepository {
         Ok(rows)
     }
 
-    /// Find all released courses (beta and live status)
-    pub(crate) async fn find_released(db: &Database) -> Result<Vec<CourseModel>> {
+    /// Find a page of the released courses (beta and live status), only
+    /// those of a release status if given, along with the number of courses
+    /// of all pages. Without a limit, all courses after the offset are found.
+    pub(crate) async fn find_paginated(
+        db: &Database,
+        release_status: Option<&str>,
+        offset: i64,
+        limit: Option<i64>,
+    ) -> Result<(Vec<CourseModel>, i64)> {
+        let total: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM courses
+            WHERE release_status != 'alpha' AND ($1::TEXT IS NULL OR release_status = $1)
+            "#,
+        )
+        .bind(release_status)
+        .fetch_one(db.pool())
+        .await?;
+
         let rows = sqlx::query_as::<_, CourseModel>(
-            r#"SELECT * FROM courses WHERE release_status != 'alpha'"#,
+            r#"
+            SELECT * FROM courses
+            WHERE release_status != 'alpha' AND ($1::TEXT IS NULL OR release_status = $1)
+            ORDER BY created_at, slug
+            OFFSET $2
+            LIMIT $3
+            "#,
+        )
+        .bind(release_status)
+        .bind(offset)
+        .bind(limit)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok((rows, total))
+    }
+
+    /// Search the released courses whose name, summary or description match
+    /// the keywords, most relevant first. Matches in the name weigh the most,
+    /// those in the description the least.
+    pub(crate) async fn search(db: &Database, query: &str, limit: i64) -> Result<Vec<CourseModel>> {
+        let rows = sqlx::query_as::<_, CourseModel>(
+            r#"
+            WITH matches AS (
+                SELECT
+                    c.*,
+                    setweight(to_tsvector('english', c.name), 'A') ||
+                    setweight(to_tsvector('english', c.summary), 'B') ||
+                    setweight(to_tsvector('english', c.description), 'C') AS document,
+                    websearch_to_tsquery('english', $1) AS query
+                FROM courses c
+            )
+            SELECT * FROM matches
+            WHERE release_status != 'alpha' AND document @@ query
+            ORDER BY ts_rank(document, query) DESC, name
+            LIMIT $2
+            "#,
         )
+        .bind(query)
+        .bind(limit)
         .fetch_all(db.pool())
         .await?;
 
@@ -98,7 +171,7 @@ impl CourseRepository {
         let row = sqlx::query_as::<_, CourseModel>(
             r#"
             UPDATE courses
-            SET name = $2, short_name = $3, release_status = $4, description = $5, summary = $6, stage_count = $7, updated_at = $8
+            SET name = $2, short_name = $3, release_status = $4, description = $5, summary = $6, stage_count = $7, require_verified_identity = $8, max_attempts = $9, resources = $10, tester_image = $11, languages = $12, pipeline_ref = $13, workspace_size = $14, pipeline_timeout = $15, service_account = $16, commit_sha = $17, content_hash = $18, updated_at = $19
             WHERE slug = $1
             RETURNING *
             "#,
@@ -110,6 +183,17 @@ impl CourseRepository {
         .bind(&course.description)
         .bind(&course.summary)
         .bind(course.stage_count)
+        .bind(course.require_verified_identity)
+        .bind(course.max_attempts)
+        .bind(&course.resources)
+        .bind(&course.tester_image)
+        .bind(&course.languages)
+        .bind(&course.pipeline_ref)
+        .bind(&course.workspace_size)
+        .bind(&course.pipeline_timeout)
+        .bind(&course.service_account)
+        .bind(&course.commit_sha)
+        .bind(&course.content_hash)
         .bind(course.updated_at)
         .fetch_one(&mut **tx)
         .await?;
@@ -117,6 +201,100 @@ impl CourseRepository {
         Ok(row)
     }
 
+    /// Pin a course to a branch, tag or commit SHA, or let it follow the
+    /// default branch with `None`.
+    pub async fn set_reference(db: &Database, slug: &str, reference: Option<&str>) -> Result<()> {
+        sqlx::query("UPDATE courses SET reference = $2, updated_at = NOW() WHERE slug = $1")
+            .bind(slug)
+            .bind(reference)
+            .execute(db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Set the exam window of a course, clearing it with `None` bounds.
+    pub async fn set_exam_window(
+        db: &Database,
+        slug: &str,
+        opens_at: Option<DateTime<Utc>>,
+        closes_at: Option<DateTime<Utc>>,
+    ) -> Result<CourseModel> {
+        let row = sqlx::query_as::<_, CourseModel>(
+            r#"
+            UPDATE courses SET opens_at = $2, closes_at = $3, updated_at = NOW()
+            WHERE slug = $1
+            RETURNING *
+            "#,
+        )
+        .bind(slug)
+        .bind(opens_at)
+        .bind(closes_at)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Set the settings of a course overridden by admins at runtime.
+    pub async fn set_setting_overrides(
+        db: &Database,
+        slug: &str,
+        overrides: &Value,
+    ) -> Result<CourseModel> {
+        let row = sqlx::query_as::<_, CourseModel>(
+            r#"
+            UPDATE courses SET setting_overrides = $2, updated_at = NOW()
+            WHERE slug = $1
+            RETURNING *
+            "#,
+        )
+        .bind(slug)
+        .bind(overrides)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Whether the exam window of a course has opened at the given time.
+    /// Courses without a window are always open.
+    pub async fn has_opened(db: &Database, id: &Uuid, now: DateTime<Utc>) -> Result<bool> {
+        let opened = sqlx::query_scalar::<_, bool>(
+            "SELECT opens_at IS NULL OR opens_at <= $2 FROM courses WHERE id = $1",
+        )
+        .bind(id)
+        .bind(now)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(opened)
+    }
+
+    /// Take the advisory lock of a course until the end of the transaction,
+    /// serializing the syncs of its template repository.
+    pub async fn lock(tx: &mut Transaction<'_>, slug: &str) -> Result<()> {
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext('course:' || $1))")
+            .bind(slug)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Take the advisory lock of a course until the end of the transaction
+    /// if no one holds it, returning whether it was taken.
+    pub async fn try_lock(tx: &mut Transaction<'_>, slug: &str) -> Result<bool> {
+        let locked = sqlx::query_scalar::<_, bool>(
+            "SELECT pg_try_advisory_xact_lock(hashtext('course:' || $1))",
+        )
+        .bind(slug)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(locked)
+    }
+
     /// Delete a course by its slug.
     pub async fn delete(db: &Database, slug: &str) -> Result<()> {
         sqlx::query(r#"DELETE FROM courses WHERE slug = $1"#).bind(slug).execute(db.pool()).await?;
@@ -124,6 +302,112 @@ impl CourseRepository {
         Ok(())
     }
 
+    /// Delete an enrollment along with its stages and progress.
+    pub async fn delete_user_course(tx: &mut Transaction<'_>, id: &Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM user_courses WHERE id = $1").bind(id).execute(&mut **tx).await?;
+
+        Ok(())
+    }
+
+    /// Reset an enrollment to before its activation, dropping its stages
+    /// along with their attempts.
+    pub async fn reset_user_course(tx: &mut Transaction<'_>, id: &Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM user_stages WHERE user_course_id = $1")
+            .bind(id)
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query(
+            r#"
+            UPDATE user_courses
+            SET completed_stage_count = 0, current_stage_id = NULL, activated = false
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Aggregate the progress of the learners of a course, overall and per
+    /// stage in course order. A course without enrollments has zeroed stats.
+    pub async fn stats(
+        db: &Database,
+        slug: &str,
+    ) -> Result<(CourseStatsModel, Vec<StageStatsModel>)> {
+        let course = sqlx::query_as::<_, CourseStatsModel>(
+            r#"
+            WITH base AS (
+                SELECT COUNT(*) AS total
+                FROM stages s
+                JOIN courses c ON s.course_id = c.id
+                WHERE c.slug = $1 AND s.extension_id IS NULL AND NOT s.archived
+            ),
+            enrollments AS (
+                SELECT
+                    uc.id,
+                    uc.activated,
+                    COUNT(us.id) FILTER (WHERE us.status = 'completed') AS completed,
+                    COUNT(us.id) FILTER (
+                        WHERE us.status = 'completed' AND s.extension_id IS NULL AND NOT s.archived
+                    ) AS completed_base
+                FROM user_courses uc
+                JOIN courses c ON uc.course_id = c.id
+                LEFT JOIN user_stages us ON us.user_course_id = uc.id
+                LEFT JOIN stages s ON us.stage_id = s.id
+                WHERE c.slug = $1
+                GROUP BY uc.id
+            )
+            SELECT
+                COUNT(e.id) AS enrolled_count,
+                COUNT(e.id) FILTER (WHERE e.activated) AS activated_count,
+                COUNT(e.id) FILTER (WHERE b.total > 0 AND e.completed_base >= b.total)
+                    AS completed_count,
+                COALESCE(AVG(e.completed), 0)::DOUBLE PRECISION AS average_completed_stages
+            FROM base b
+            LEFT JOIN enrollments e ON TRUE
+            GROUP BY b.total
+            "#,
+        )
+        .bind(slug)
+        .fetch_one(db.pool())
+        .await?;
+
+        let stages = sqlx::query_as::<_, StageStatsModel>(
+            r#"
+            SELECT
+                s.slug AS stage_slug,
+                e.slug AS extension_slug,
+                COUNT(us.id) AS reached_count,
+                COUNT(us.id) FILTER (WHERE us.status = 'completed') AS completed_count
+            FROM stages s
+            JOIN courses c ON s.course_id = c.id
+            LEFT JOIN extensions e ON s.extension_id = e.id
+            LEFT JOIN user_stages us ON us.stage_id = s.id
+            WHERE c.slug = $1 AND NOT s.archived
+            GROUP BY s.id, e.slug, e.weight
+            ORDER BY COALESCE(e.weight, -1) ASC, s.position ASC
+            "#,
+        )
+        .bind(slug)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok((course, stages))
+    }
+
+    /// Find the IDs of all enrollments of a course, which name their
+    /// repositories.
+    pub async fn find_user_course_ids(db: &Database, course_id: &Uuid) -> Result<Vec<Uuid>> {
+        let ids = sqlx::query_scalar::<_, Uuid>("SELECT id FROM user_courses WHERE course_id = $1")
+            .bind(course_id)
+            .fetch_all(db.pool())
+            .await?;
+
+        Ok(ids)
+    }
+
     /// Find all courses for the current user.
     pub async fn find_user_courses(db: &Database, user_id: &str) -> Result<Vec<UserCourseModel>> {
         let rows = sqlx::query_as::<_, UserCourseModel>(
@@ -131,7 +415,16 @@ impl CourseRepository {
             SELECT
                 uc.*,
                 c.slug AS course_slug,
-                s.slug AS current_stage_slug
+                c.require_verified_identity,
+                s.slug AS current_stage_slug,
+                c.opens_at,
+                c.closes_at,
+                EXTRACT(EPOCH FROM GREATEST(c.closes_at, uc.extended_deadline) - NOW())::BIGINT AS remaining_secs,
+                EXISTS (
+                    SELECT 1 FROM stage_attempts a
+                    JOIN user_stages us ON a.user_stage_id = us.id
+                    WHERE us.user_course_id = uc.id AND a.status = 'passed' AND NOT a.late AND a.created_at > c.closes_at
+                ) AS completed_late
             FROM user_courses uc
             LEFT JOIN courses c ON uc.course_id = c.id
             LEFT JOIN stages s ON uc.current_stage_id = s.id
@@ -156,7 +449,16 @@ impl CourseRepository {
             SELECT
                 uc.*,
                 c.slug AS course_slug,
-                s.slug AS current_stage_slug
+                c.require_verified_identity,
+                s.slug AS current_stage_slug,
+                c.opens_at,
+                c.closes_at,
+                EXTRACT(EPOCH FROM GREATEST(c.closes_at, uc.extended_deadline) - NOW())::BIGINT AS remaining_secs,
+                EXISTS (
+                    SELECT 1 FROM stage_attempts a
+                    JOIN user_stages us ON a.user_stage_id = us.id
+                    WHERE us.user_course_id = uc.id AND a.status = 'passed' AND NOT a.late AND a.created_at > c.closes_at
+                ) AS completed_late
             FROM user_courses uc
             LEFT JOIN courses c ON uc.course_id = c.id
             LEFT JOIN stages s ON uc.current_stage_id = s.id
@@ -178,7 +480,16 @@ impl CourseRepository {
             SELECT
                 uc.*,
                 c.slug AS course_slug,
-                s.slug AS current_stage_slug
+                c.require_verified_identity,
+                s.slug AS current_stage_slug,
+                c.opens_at,
+                c.closes_at,
+                EXTRACT(EPOCH FROM GREATEST(c.closes_at, uc.extended_deadline) - NOW())::BIGINT AS remaining_secs,
+                EXISTS (
+                    SELECT 1 FROM stage_attempts a
+                    JOIN user_stages us ON a.user_stage_id = us.id
+                    WHERE us.user_course_id = uc.id AND a.status = 'passed' AND NOT a.late AND a.created_at > c.closes_at
+                ) AS completed_late
             FROM user_courses uc
             LEFT JOIN courses c ON uc.course_id = c.id
             LEFT JOIN stages s ON uc.current_stage_id = s.id
@@ -206,14 +517,23 @@ impl CourseRepository {
             r#"
             WITH inserted AS (
                 INSERT INTO user_courses (
-                    id, user_id, course_id, started_at, current_stage_id, completed_stage_count, proficiency, cadence, accountability, activated
-                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                    id, user_id, course_id, started_at, current_stage_id, completed_stage_count, proficiency, cadence, accountability, activated, cohort, cohort_deadline, language
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
                 RETURNING *
             )
             SELECT
                 i.*,
                 c.slug AS course_slug,
-                s.slug AS current_stage_slug
+                c.require_verified_identity,
+                s.slug AS current_stage_slug,
+                c.opens_at,
+                c.closes_at,
+                EXTRACT(EPOCH FROM GREATEST(c.closes_at, i.extended_deadline) - NOW())::BIGINT AS remaining_secs,
+                EXISTS (
+                    SELECT 1 FROM stage_attempts a
+                    JOIN user_stages us ON a.user_stage_id = us.id
+                    WHERE us.user_course_id = i.id AND a.status = 'passed' AND NOT a.late AND a.created_at > c.closes_at
+                ) AS completed_late
             FROM inserted i
             JOIN courses c ON i.course_id = c.id
             LEFT JOIN stages s ON i.current_stage_id = s.id
@@ -229,6 +549,9 @@ impl CourseRepository {
         .bind(&user_course.cadence)
         .bind(user_course.accountability)
         .bind(user_course.activated)
+        .bind(&user_course.cohort)
+        .bind(user_course.cohort_deadline)
+        .bind(&user_course.language)
         .fetch_one(&mut **tx)
         .await?;
 
@@ -250,14 +573,27 @@ impl CourseRepository {
                     proficiency = $4,
                     cadence = $5,
                     accountability = $6,
-                    activated = $7
+                    activated = $7,
+                    identity_verified = $8,
+                    identity_nonce = $9,
+                    cohort = $10,
+                    cohort_deadline = $11
                 WHERE id = $1
                 RETURNING *
             )
             SELECT
                 u.*,
                 c.slug AS course_slug,
-                s.slug AS current_stage_slug
+                c.require_verified_identity,
+                s.slug AS current_stage_slug,
+                c.opens_at,
+                c.closes_at,
+                EXTRACT(EPOCH FROM GREATEST(c.closes_at, u.extended_deadline) - NOW())::BIGINT AS remaining_secs,
+                EXISTS (
+                    SELECT 1 FROM stage_attempts a
+                    JOIN user_stages us ON a.user_stage_id = us.id
+                    WHERE us.user_course_id = u.id AND a.status = 'passed' AND NOT a.late AND a.created_at > c.closes_at
+                ) AS completed_late
             FROM updated u
             LEFT JOIN courses c ON u.course_id = c.id
             LEFT JOIN stages s ON u.current_stage_id = s.id
@@ -270,19 +606,95 @@ impl CourseRepository {
         .bind(&user_course.cadence)
         .bind(user_course.accountability)
         .bind(user_course.activated)
+        .bind(user_course.identity_verified)
+        .bind(&user_course.identity_nonce)
+        .bind(&user_course.cohort)
+        .bind(user_course.cohort_deadline)
         .fetch_one(&mut **tx)
         .await?;
 
+        Self::notify_status(tx, &row.id).await?;
         Ok(row)
     }
 
-    /// Find all attempts for a course.
-    pub async fn find_attempts(db: &Database, slug: &str) -> Result<Vec<AttemptModel>> {
+    /// Notify the status streams of a user course of its update, once the
+    /// transaction commits, bumping the version of its status.
+    pub async fn notify_status(tx: &mut Transaction<'_>, user_course_id: &Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            WITH bumped AS (
+                UPDATE user_courses SET status_version = status_version + 1
+                WHERE id = $2
+                RETURNING id
+            )
+            SELECT pg_notify($1, id::TEXT) FROM bumped
+            "#,
+        )
+        .bind(STATUS_CHANNEL)
+        .bind(user_course_id)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the version of the status of a user course.
+    pub async fn get_status_version(db: &Database, user_course_id: &Uuid) -> Result<i64> {
+        let version = sqlx::query_scalar("SELECT status_version FROM user_courses WHERE id = $1")
+            .bind(user_course_id)
+            .fetch_one(db.pool())
+            .await?;
+
+        Ok(version)
+    }
+
+    /// Grant a user course a deadline beyond the close of the course.
+    pub async fn extend_deadline(
+        db: &Database,
+        id: &Uuid,
+        deadline: DateTime<Utc>,
+    ) -> Result<UserCourseModel> {
+        sqlx::query("UPDATE user_courses SET extended_deadline = $2 WHERE id = $1")
+            .bind(id)
+            .bind(deadline)
+            .execute(db.pool())
+            .await?;
+
+        Self::get_user_course_by_id(db, id).await
+    }
+
+    /// Find a page of the attempts for a course, most recently started first,
+    /// optionally restricted to a user and to those started since a time.
+    /// Returns the total number of matching attempts along with the page.
+    pub async fn find_attempts(
+        db: &Database,
+        slug: &str,
+        user_id: Option<&str>,
+        since: Option<DateTime<Utc>>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<(Vec<AttemptModel>, i64)> {
+        let total: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM user_courses uc
+            JOIN courses c ON uc.course_id = c.id
+            WHERE c.slug = $1
+                AND ($2::TEXT IS NULL OR uc.user_id = $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR uc.started_at >= $3)
+            "#,
+        )
+        .bind(slug)
+        .bind(user_id)
+        .bind(since)
+        .fetch_one(db.pool())
+        .await?;
+
         let rows = sqlx::query_as::<_, AttemptModel>(
             r#"
             SELECT
                 u.id AS user_id,
-                u.image AS avatar,
+                COALESCE(u.image, '') AS avatar,
                 u.name AS username,
                 uc.completed_stage_count AS completed,
                 c.stage_count AS total
@@ -290,10 +702,239 @@ impl CourseRepository {
             JOIN users u ON uc.user_id = u.id
             JOIN courses c ON uc.course_id = c.id
             WHERE c.slug = $1
-            ORDER BY
-                uc.completed_stage_count DESC,
-                uc.started_at DESC
-            LIMIT 10
+                AND ($2::TEXT IS NULL OR uc.user_id = $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR uc.started_at >= $3)
+            ORDER BY uc.started_at DESC, uc.id DESC
+            OFFSET $4
+            LIMIT $5
+            "#,
+        )
+        .bind(slug)
+        .bind(user_id)
+        .bind(since)
+        .bind(offset)
+        .bind(limit)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok((rows, total))
+    }
+
+    /// Grant a user the maintainer role of a course.
+    pub async fn add_maintainer(
+        db: &Database,
+        slug: &str,
+        user_id: &str,
+        added_by: &str,
+    ) -> Result<CourseMaintainerModel> {
+        let row = sqlx::query_as::<_, CourseMaintainerModel>(
+            r#"
+            INSERT INTO course_maintainers (course_id, user_id, added_by, created_at)
+            SELECT id, $2, $3, NOW() FROM courses WHERE slug = $1
+            RETURNING *
+            "#,
+        )
+        .bind(slug)
+        .bind(user_id)
+        .bind(added_by)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Revoke the maintainer role of a user, returns whether it was granted.
+    pub async fn remove_maintainer(db: &Database, slug: &str, user_id: &str) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM course_maintainers m
+            USING courses c
+            WHERE m.course_id = c.id AND c.slug = $1 AND m.user_id = $2
+            "#,
+        )
+        .bind(slug)
+        .bind(user_id)
+        .execute(db.pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Check whether a user maintains a course.
+    pub async fn is_maintainer(db: &Database, slug: &str, user_id: &str) -> Result<bool> {
+        let exists = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM course_maintainers m
+                JOIN courses c ON m.course_id = c.id
+                WHERE c.slug = $1 AND m.user_id = $2
+            )
+            "#,
+        )
+        .bind(slug)
+        .bind(user_id)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(exists)
+    }
+
+    /// Find all maintainers of a course.
+    pub async fn find_maintainers(db: &Database, slug: &str) -> Result<Vec<CourseMaintainerModel>> {
+        let rows = sqlx::query_as::<_, CourseMaintainerModel>(
+            r#"
+            SELECT m.*
+            FROM course_maintainers m
+            JOIN courses c ON m.course_id = c.id
+            WHERE c.slug = $1
+            ORDER BY m.created_at ASC
+            "#,
+        )
+        .bind(slug)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+}
+
+/// Repository for the background creations of courses.
+pub struct CourseJobRepository;
+
+impl CourseJobRepository {
+    /// Queue the creation of a course.
+    pub async fn create(db: &Database, job: &CourseJobModel) -> Result<CourseJobModel> {
+        let row = sqlx::query_as::<_, CourseJobModel>(
+            r#"
+            INSERT INTO course_jobs (id, repository, reference, status, created_at, updated_at)
+            VALUES ($1, $2, $3, 'pending', $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(job.id)
+        .bind(&job.repository)
+        .bind(&job.reference)
+        .bind(job.created_at)
+        .bind(job.updated_at)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Get a job by its ID.
+    pub async fn get(db: &Database, id: Uuid) -> Result<CourseJobModel> {
+        let row = sqlx::query_as::<_, CourseJobModel>("SELECT * FROM course_jobs WHERE id = $1")
+            .bind(id)
+            .fetch_one(db.pool())
+            .await?;
+
+        Ok(row)
+    }
+
+    /// Claim the oldest pending job, marking it as fetching. Concurrent
+    /// callers never claim the same job.
+    pub async fn claim_pending(db: &Database) -> Result<Option<CourseJobModel>> {
+        let row = sqlx::query_as::<_, CourseJobModel>(
+            r#"
+            UPDATE course_jobs SET status = 'fetching', updated_at = NOW()
+            WHERE id IN (
+                SELECT id FROM course_jobs
+                WHERE status = 'pending'
+                ORDER BY created_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .fetch_optional(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Move a running job on to the given step.
+    pub async fn advance(db: &Database, id: Uuid, status: &str) -> Result<()> {
+        sqlx::query("UPDATE course_jobs SET status = $2, updated_at = NOW() WHERE id = $1")
+            .bind(id)
+            .bind(status)
+            .execute(db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Mark a job as completed with the slug of its course.
+    pub async fn complete(db: &Database, id: Uuid, course_slug: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE course_jobs SET status = 'completed', course_slug = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(course_slug)
+        .execute(db.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Mark a job as failed with the reason.
+    pub async fn fail(db: &Database, id: Uuid, error: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE course_jobs SET status = 'failed', error = $2, updated_at = NOW() WHERE id = $1",
+        )
+        .bind(id)
+        .bind(error)
+        .execute(db.pool())
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Repository for the history of course syncs.
+pub struct CourseRevisionRepository;
+
+impl CourseRevisionRepository {
+    /// Record a sync of a course.
+    pub async fn create(
+        tx: &mut Transaction<'_>,
+        revision: &CourseRevisionModel,
+    ) -> Result<CourseRevisionModel> {
+        let row = sqlx::query_as::<_, CourseRevisionModel>(
+            r#"
+            INSERT INTO course_revisions
+                (id, course_id, commit_sha, actor, stages_added, stages_removed, stages_updated, synced_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+            "#,
+        )
+        .bind(revision.id)
+        .bind(revision.course_id)
+        .bind(&revision.commit_sha)
+        .bind(&revision.actor)
+        .bind(&revision.stages_added)
+        .bind(&revision.stages_removed)
+        .bind(&revision.stages_updated)
+        .bind(revision.synced_at)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Find the revisions of a course, newest first.
+    pub async fn find_by_course(db: &Database, slug: &str) -> Result<Vec<CourseRevisionModel>> {
+        let rows = sqlx::query_as::<_, CourseRevisionModel>(
+            r#"
+            SELECT r.* FROM course_revisions r
+            JOIN courses c ON r.course_id = c.id
+            WHERE c.slug = $1
+            ORDER BY r.synced_at DESC, r.id DESC
             "#,
         )
         .bind(slug)
@@ -0,0 +1,152 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    database::Database,
+    model::{EngagementEventModel, StageEngagementModel},
+    repository::Result,
+};
+
+/// Repository for the engagement events of learners and their rollups.
+pub struct EngagementRepository;
+
+impl EngagementRepository {
+    /// Record an engagement event.
+    pub async fn create(db: &Database, event: &EngagementEventModel) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO engagement_events (
+                id, user_course_id, stage_id, event_type, occurred_at, received_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(event.id)
+        .bind(event.user_course_id)
+        .bind(event.stage_id)
+        .bind(&event.event_type)
+        .bind(event.occurred_at)
+        .bind(event.received_at)
+        .execute(db.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Count the events a user reported since the given time, across courses.
+    pub async fn count_by_user(db: &Database, user_id: &str, since: DateTime<Utc>) -> Result<i64> {
+        let count = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*) FROM engagement_events ev
+            JOIN user_courses uc ON ev.user_course_id = uc.id
+            WHERE uc.user_id = $1 AND ev.received_at > $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Aggregate the engagement with every stage of a course, in course order.
+    /// Viewers who pushed are the learners who viewed the instructions of the
+    /// stage and made at least one attempt at it.
+    pub async fn find_by_course(
+        db: &Database,
+        course_slug: &str,
+    ) -> Result<Vec<StageEngagementModel>> {
+        let rows = sqlx::query_as::<_, StageEngagementModel>(
+            r#"
+            WITH counts AS (
+                SELECT user_course_id, stage_id, event_type, SUM(event_count)::BIGINT AS event_count
+                FROM (
+                    SELECT user_course_id, stage_id, event_type, COUNT(*) AS event_count
+                    FROM engagement_events
+                    GROUP BY user_course_id, stage_id, event_type
+                    UNION ALL
+                    SELECT user_course_id, stage_id, event_type, event_count
+                    FROM engagement_rollups
+                ) combined
+                GROUP BY user_course_id, stage_id, event_type
+            )
+            SELECT
+                s.slug AS stage_slug,
+                e.slug AS extension_slug,
+                COALESCE(SUM(n.event_count) FILTER (WHERE n.event_type = 'instruction_viewed'), 0)::BIGINT
+                    AS instruction_viewed,
+                COALESCE(SUM(n.event_count) FILTER (WHERE n.event_type = 'hint_opened'), 0)::BIGINT
+                    AS hint_opened,
+                COALESCE(SUM(n.event_count) FILTER (WHERE n.event_type = 'solution_viewed'), 0)::BIGINT
+                    AS solution_viewed,
+                COALESCE(SUM(n.event_count) FILTER (WHERE n.event_type = 'copy_clone_url'), 0)::BIGINT
+                    AS copy_clone_url,
+                COUNT(n.user_course_id) FILTER (WHERE n.event_type = 'instruction_viewed') AS viewers,
+                COUNT(n.user_course_id) FILTER (
+                    WHERE n.event_type = 'instruction_viewed' AND EXISTS (
+                        SELECT 1 FROM stage_attempts a
+                        JOIN user_stages us ON a.user_stage_id = us.id
+                        WHERE us.user_course_id = n.user_course_id AND us.stage_id = s.id
+                    )
+                ) AS viewers_pushed
+            FROM stages s
+            JOIN courses c ON s.course_id = c.id
+            LEFT JOIN extensions e ON s.extension_id = e.id
+            LEFT JOIN counts n ON n.stage_id = s.id
+            WHERE c.slug = $1 AND NOT s.archived
+            GROUP BY s.id, e.slug, e.weight
+            ORDER BY COALESCE(e.weight, -1) ASC, s.position ASC
+            "#,
+        )
+        .bind(course_slug)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Fold the events received before the given time into the per learner
+    /// rollups and delete them, returning the number of events purged.
+    ///
+    /// Both happen in a single statement, so concurrent runs can not count
+    /// an event twice.
+    pub async fn roll_up(db: &Database, before: DateTime<Utc>) -> Result<u64> {
+        let purged = sqlx::query_scalar::<_, i64>(
+            r#"
+            WITH purged AS (
+                DELETE FROM engagement_events WHERE received_at < $1
+                RETURNING user_course_id, stage_id, event_type
+            ), counts AS (
+                SELECT user_course_id, stage_id, event_type, COUNT(*) AS event_count
+                FROM purged
+                GROUP BY user_course_id, stage_id, event_type
+            ), merged AS (
+                INSERT INTO engagement_rollups (user_course_id, stage_id, event_type, event_count)
+                SELECT user_course_id, stage_id, event_type, event_count FROM counts
+                ON CONFLICT (user_course_id, stage_id, event_type) DO UPDATE
+                SET event_count = engagement_rollups.event_count + EXCLUDED.event_count
+            )
+            SELECT COUNT(*) FROM purged
+            "#,
+        )
+        .bind(before)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(purged as u64)
+    }
+}
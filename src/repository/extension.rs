@@ -18,7 +18,7 @@ use uuid::Uuid;
 use crate::{
     database::{Database, Transaction},
     model::ExtensionModel,
-    repository::Result,
+    repository::{ORDER_SHIFT, Result},
 };
 
 /// Repository for managing extensions in the database.
@@ -114,6 +114,18 @@ impl ExtensionRepository {
         Ok(row)
     }
 
+    /// Moves the weights of the extensions of a course out of the way, so
+    /// that writing a new order never collides with the current one.
+    pub async fn shift_weights(tx: &mut Transaction<'_>, course_id: Uuid) -> Result<()> {
+        sqlx::query(r#"UPDATE extensions SET weight = weight + $2 WHERE course_id = $1"#)
+            .bind(course_id)
+            .bind(ORDER_SHIFT)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(())
+    }
+
     /// Updates an existing record or inserts a new one if it doesn't exist.
     pub async fn upsert(
         tx: &mut Transaction<'_>,
@@ -0,0 +1,201 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use uuid::Uuid;
+
+use crate::{
+    database::{Database, Transaction},
+    model::{CompletionModel, IntegrityFlagModel},
+    repository::Result,
+};
+
+/// Repository for the analysis of completions and the flags it raises.
+pub struct IntegrityRepository;
+
+impl IntegrityRepository {
+    /// Claim up to `limit` completions awaiting analysis, skipping the ones
+    /// claimed by concurrent analyzers.
+    pub async fn claim_pending(tx: &mut Transaction<'_>, limit: i64) -> Result<Vec<Uuid>> {
+        let ids = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT id FROM user_stages
+            WHERE status = 'completed' AND analyzed_at IS NULL
+            ORDER BY completed_at
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        Ok(ids)
+    }
+
+    /// Mark completions as analyzed.
+    pub async fn mark_analyzed(tx: &mut Transaction<'_>, ids: &[Uuid]) -> Result<()> {
+        sqlx::query("UPDATE user_stages SET analyzed_at = NOW() WHERE id = ANY($1)")
+            .bind(ids)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Find all completions of the enrollments of the given user stages,
+    /// ordered by completion time.
+    pub async fn find_completions(
+        tx: &mut Transaction<'_>,
+        user_stage_ids: &[Uuid],
+    ) -> Result<Vec<CompletionModel>> {
+        let rows = sqlx::query_as::<_, CompletionModel>(
+            r#"
+            SELECT
+                us.id AS user_stage_id,
+                us.user_course_id,
+                us.started_at,
+                us.completed_at,
+                (
+                    SELECT COUNT(*) FROM stage_attempts a
+                    WHERE a.user_stage_id = us.id AND a.status = 'failed'
+                ) AS failed_attempts
+            FROM user_stages us
+            WHERE us.status = 'completed'
+              AND us.user_course_id IN (SELECT user_course_id FROM user_stages WHERE id = ANY($1))
+            ORDER BY us.user_course_id, us.completed_at
+            "#,
+        )
+        .bind(user_stage_ids)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Median number of failed attempts per completed stage in the course of
+    /// an enrollment.
+    pub async fn median_failures(tx: &mut Transaction<'_>, user_course_id: &Uuid) -> Result<f64> {
+        let median = sqlx::query_scalar::<_, Option<f64>>(
+            r#"
+            SELECT percentile_cont(0.5) WITHIN GROUP (ORDER BY failures)
+            FROM (
+                SELECT (
+                    SELECT COUNT(*) FROM stage_attempts a
+                    WHERE a.user_stage_id = us.id AND a.status = 'failed'
+                ) AS failures
+                FROM user_stages us
+                JOIN user_courses uc ON us.user_course_id = uc.id
+                WHERE us.status = 'completed'
+                  AND uc.course_id = (SELECT course_id FROM user_courses WHERE id = $1)
+            ) f
+            "#,
+        )
+        .bind(user_course_id)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(median.unwrap_or_default())
+    }
+
+    /// Record a flag, unless the completion was already flagged by the rule.
+    pub async fn create_flag(tx: &mut Transaction<'_>, flag: &IntegrityFlagModel) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO integrity_flags (id, user_stage_id, rule, features, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_stage_id, rule) DO NOTHING
+            "#,
+        )
+        .bind(flag.id)
+        .bind(flag.user_stage_id)
+        .bind(&flag.rule)
+        .bind(&flag.features)
+        .bind(flag.created_at)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Find the flags raised in a course, newest first.
+    pub async fn find_flags(
+        db: &Database,
+        course_slug: &str,
+        user_id: Option<&str>,
+        rule: Option<&str>,
+        dismissed: bool,
+    ) -> Result<Vec<IntegrityFlagModel>> {
+        let rows = sqlx::query_as::<_, IntegrityFlagModel>(
+            r#"
+            SELECT f.*, uc.user_id, s.slug AS stage_slug
+            FROM integrity_flags f
+            JOIN user_stages us ON f.user_stage_id = us.id
+            JOIN user_courses uc ON us.user_course_id = uc.id
+            JOIN courses c ON uc.course_id = c.id
+            JOIN stages s ON us.stage_id = s.id
+            WHERE c.slug = $1
+              AND ($2::TEXT IS NULL OR uc.user_id = $2)
+              AND ($3::TEXT IS NULL OR f.rule = $3)
+              AND ($4 OR f.dismissed_at IS NULL)
+            ORDER BY f.created_at DESC
+            "#,
+        )
+        .bind(course_slug)
+        .bind(user_id)
+        .bind(rule)
+        .bind(dismissed)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Dismiss a flag raised in a course.
+    pub async fn dismiss_flag(
+        db: &Database,
+        course_slug: &str,
+        id: &Uuid,
+        dismissed_by: &str,
+        note: &str,
+    ) -> Result<IntegrityFlagModel> {
+        let row = sqlx::query_as::<_, IntegrityFlagModel>(
+            r#"
+            WITH updated AS (
+                UPDATE integrity_flags f
+                SET dismissed_at = NOW(), dismissed_by = $3, note = $4
+                FROM user_stages us, user_courses uc, courses c
+                WHERE f.id = $2
+                  AND f.user_stage_id = us.id
+                  AND us.user_course_id = uc.id
+                  AND uc.course_id = c.id
+                  AND c.slug = $1
+                RETURNING f.*
+            )
+            SELECT u.*, uc.user_id, s.slug AS stage_slug
+            FROM updated u
+            JOIN user_stages us ON u.user_stage_id = us.id
+            JOIN user_courses uc ON us.user_course_id = uc.id
+            JOIN stages s ON us.stage_id = s.id
+            "#,
+        )
+        .bind(course_slug)
+        .bind(id)
+        .bind(dismissed_by)
+        .bind(note)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+}
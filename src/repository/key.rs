@@ -0,0 +1,80 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{database::Database, model::DeployKeyModel, repository::Result};
+
+/// Repository for the deploy keys of generated repositories.
+pub struct DeployKeyRepository;
+
+impl DeployKeyRepository {
+    /// Find the deploy key of a repository.
+    pub async fn find(db: &Database, owner: &str, repo: &str) -> Result<Option<DeployKeyModel>> {
+        let row = sqlx::query_as::<_, DeployKeyModel>(
+            "SELECT * FROM deploy_keys WHERE owner = $1 AND repo = $2",
+        )
+        .bind(owner)
+        .bind(repo)
+        .fetch_optional(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Record the deploy key of a repository, replacing any previous one.
+    pub async fn upsert(db: &Database, key: &DeployKeyModel) -> Result<DeployKeyModel> {
+        let row = sqlx::query_as::<_, DeployKeyModel>(
+            r#"
+            INSERT INTO deploy_keys (owner, repo, key_id, encrypted_private_key, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (owner, repo) DO UPDATE SET
+                key_id = EXCLUDED.key_id,
+                encrypted_private_key = EXCLUDED.encrypted_private_key,
+                created_at = EXCLUDED.created_at
+            RETURNING *
+            "#,
+        )
+        .bind(&key.owner)
+        .bind(&key.repo)
+        .bind(key.key_id)
+        .bind(&key.encrypted_private_key)
+        .bind(key.created_at)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Move the deploy key of a renamed repository to its new name.
+    pub async fn rename(db: &Database, owner: &str, repo: &str, name: &str) -> Result<()> {
+        sqlx::query("UPDATE deploy_keys SET repo = $3 WHERE owner = $1 AND repo = $2")
+            .bind(owner)
+            .bind(repo)
+            .bind(name)
+            .execute(db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Delete the deploy key of a deleted repository.
+    pub async fn delete(db: &Database, owner: &str, repo: &str) -> Result<()> {
+        sqlx::query("DELETE FROM deploy_keys WHERE owner = $1 AND repo = $2")
+            .bind(owner)
+            .bind(repo)
+            .execute(db.pool())
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use uuid::Uuid;
+
+use crate::{
+    database::{Database, Transaction},
+    model::RepoMigrationModel,
+    repository::Result,
+};
+
+/// Repository for the imports of learner repositories.
+pub struct RepoMigrationRepository;
+
+impl RepoMigrationRepository {
+    /// Find the IDs of the given user courses that exist.
+    pub async fn find_user_course_ids(db: &Database, ids: &[Uuid]) -> Result<Vec<Uuid>> {
+        let rows = sqlx::query_scalar::<_, Uuid>("SELECT id FROM user_courses WHERE id = ANY($1)")
+            .bind(ids)
+            .fetch_all(db.pool())
+            .await?;
+
+        Ok(rows)
+    }
+
+    /// Queue the migration of a repository, replacing an earlier one of the
+    /// same user course unless that one already succeeded. Returns false
+    /// when the repository was already migrated.
+    pub async fn enqueue(tx: &mut Transaction<'_>, migration: &RepoMigrationModel) -> Result<bool> {
+        let queued = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO repo_migrations (id, user_course_id, source_url, source_repo, auth_username, encrypted_password, encrypted_token, status, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, 'pending', $8, $9)
+            ON CONFLICT (user_course_id) DO UPDATE SET
+                source_url = EXCLUDED.source_url,
+                source_repo = EXCLUDED.source_repo,
+                auth_username = EXCLUDED.auth_username,
+                encrypted_password = EXCLUDED.encrypted_password,
+                encrypted_token = EXCLUDED.encrypted_token,
+                status = 'pending',
+                error = NULL,
+                updated_at = NOW()
+            WHERE repo_migrations.status <> 'migrated'
+            RETURNING id
+            "#,
+        )
+        .bind(migration.id)
+        .bind(migration.user_course_id)
+        .bind(&migration.source_url)
+        .bind(&migration.source_repo)
+        .bind(&migration.auth_username)
+        .bind(&migration.encrypted_password)
+        .bind(&migration.encrypted_token)
+        .bind(migration.created_at)
+        .bind(migration.updated_at)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        if queued.is_some() {
+            sqlx::query("UPDATE user_courses SET repository_status = 'migrating' WHERE id = $1")
+                .bind(migration.user_course_id)
+                .execute(&mut **tx)
+                .await?;
+        }
+
+        Ok(queued.is_some())
+    }
+
+    /// Claim up to `limit` pending migrations, marking them as running.
+    /// Concurrent callers never claim the same migration.
+    pub async fn claim_pending(db: &Database, limit: i64) -> Result<Vec<RepoMigrationModel>> {
+        let rows = sqlx::query_as::<_, RepoMigrationModel>(
+            r#"
+            UPDATE repo_migrations SET status = 'running', updated_at = NOW()
+            WHERE id IN (
+                SELECT id FROM repo_migrations
+                WHERE status = 'pending'
+                ORDER BY created_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Record the outcome of a migration and the resulting repository status
+    /// of the user course. The source credentials are dropped either way.
+    pub async fn finish(
+        tx: &mut Transaction<'_>,
+        migration: &RepoMigrationModel,
+        error: Option<&str>,
+    ) -> Result<()> {
+        let (status, repository_status) = match error {
+            None => ("migrated", "ready"),
+            Some(_) => ("failed", "failed"),
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE repo_migrations
+            SET status = $2, error = $3, auth_username = NULL, encrypted_password = NULL, encrypted_token = NULL, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(migration.id)
+        .bind(status)
+        .bind(error)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query("UPDATE user_courses SET repository_status = $2 WHERE id = $1")
+            .bind(migration.user_course_id)
+            .bind(repository_status)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Find the migrations, optionally only those with the given status.
+    pub async fn find(db: &Database, status: Option<&str>) -> Result<Vec<RepoMigrationModel>> {
+        let rows = sqlx::query_as::<_, RepoMigrationModel>(
+            r#"
+            SELECT * FROM repo_migrations
+            WHERE ($1::TEXT IS NULL OR status = $1)
+            ORDER BY created_at
+            "#,
+        )
+        .bind(status)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Count the migrations per status.
+    pub async fn count_by_status(db: &Database) -> Result<Vec<(String, i64)>> {
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            "SELECT status, COUNT(*) FROM repo_migrations GROUP BY status",
+        )
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+}
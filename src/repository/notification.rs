@@ -0,0 +1,70 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{database::Database, model::NotificationPreferencesModel, repository::Result};
+
+/// Repository for the notification preferences of users.
+pub struct NotificationRepository;
+
+impl NotificationRepository {
+    /// Fetch the preferences of a user, if they saved any.
+    pub async fn get(db: &Database, user_id: &str) -> Result<Option<NotificationPreferencesModel>> {
+        let row = sqlx::query_as::<_, NotificationPreferencesModel>(
+            "SELECT * FROM user_notification_preferences WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Create or replace the preferences of a user.
+    pub async fn upsert(
+        db: &Database,
+        prefs: &NotificationPreferencesModel,
+    ) -> Result<NotificationPreferencesModel> {
+        let row = sqlx::query_as::<_, NotificationPreferencesModel>(
+            r#"
+            INSERT INTO user_notification_preferences (
+                user_id, channel, destination, announcements, feedback, deadline_reminders,
+                accountability, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (user_id) DO UPDATE SET
+                channel = EXCLUDED.channel,
+                destination = EXCLUDED.destination,
+                announcements = EXCLUDED.announcements,
+                feedback = EXCLUDED.feedback,
+                deadline_reminders = EXCLUDED.deadline_reminders,
+                accountability = EXCLUDED.accountability,
+                updated_at = EXCLUDED.updated_at
+            RETURNING *
+            "#,
+        )
+        .bind(&prefs.user_id)
+        .bind(&prefs.channel)
+        .bind(&prefs.destination)
+        .bind(prefs.announcements)
+        .bind(prefs.feedback)
+        .bind(prefs.deadline_reminders)
+        .bind(prefs.accountability)
+        .bind(prefs.created_at)
+        .bind(prefs.updated_at)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+}
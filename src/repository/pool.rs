@@ -0,0 +1,162 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::{
+    database::{Database, Transaction},
+    model::PooledRepoModel,
+    repository::Result,
+};
+
+/// Repository for the pool of spare learner repositories.
+///
+/// Only pooled repositories generated from the current commit of their
+/// course and created after the given cutoff are available, the others are
+/// stale and left to the cleanup job.
+pub struct RepoPoolRepository;
+
+impl RepoPoolRepository {
+    /// Add a generated repository to the pool.
+    pub async fn create(db: &Database, repo: &PooledRepoModel) -> Result<PooledRepoModel> {
+        let row = sqlx::query_as::<_, PooledRepoModel>(
+            r#"
+            INSERT INTO repo_pool (id, course_id, commit_sha, claimed, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(repo.id)
+        .bind(repo.course_id)
+        .bind(&repo.commit_sha)
+        .bind(repo.claimed)
+        .bind(repo.created_at)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Count the available repositories of a course.
+    pub async fn count_available(
+        db: &Database,
+        course_id: &Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<i64> {
+        let count = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM repo_pool p
+            JOIN courses c ON p.course_id = c.id
+            WHERE p.course_id = $1 AND NOT p.claimed
+                AND p.commit_sha = c.commit_sha AND p.created_at > $2
+            "#,
+        )
+        .bind(course_id)
+        .bind(since)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Claim the oldest available repository of a course for the repository
+    /// name it is renamed to. Concurrent claims never take the same one.
+    pub async fn claim(
+        db: &Database,
+        course_slug: &str,
+        name: &str,
+        since: DateTime<Utc>,
+    ) -> Result<Option<PooledRepoModel>> {
+        let row = sqlx::query_as::<_, PooledRepoModel>(
+            r#"
+            UPDATE repo_pool SET claimed = true, claimed_as = $2, claimed_at = NOW()
+            WHERE id = (
+                SELECT p.id FROM repo_pool p
+                JOIN courses c ON p.course_id = c.id
+                WHERE c.slug = $1 AND NOT p.claimed
+                    AND p.commit_sha = c.commit_sha AND p.created_at > $3
+                ORDER BY p.created_at
+                LIMIT 1
+                FOR UPDATE OF p SKIP LOCKED
+            ) AND claimed = false
+            RETURNING *
+            "#,
+        )
+        .bind(course_slug)
+        .bind(name)
+        .bind(since)
+        .fetch_optional(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Find unclaimed repositories created before the cutoff or generated
+    /// from an outdated commit, oldest first.
+    pub async fn find_stale(
+        db: &Database,
+        since: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<PooledRepoModel>> {
+        let rows = sqlx::query_as::<_, PooledRepoModel>(
+            r#"
+            SELECT p.* FROM repo_pool p
+            JOIN courses c ON p.course_id = c.id
+            WHERE NOT p.claimed AND (p.created_at <= $1 OR p.commit_sha <> c.commit_sha)
+            ORDER BY p.created_at
+            LIMIT $2
+            "#,
+        )
+        .bind(since)
+        .bind(limit)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Find the unclaimed repositories of a course.
+    pub async fn find_unclaimed(db: &Database, course_id: &Uuid) -> Result<Vec<PooledRepoModel>> {
+        let rows = sqlx::query_as::<_, PooledRepoModel>(
+            "SELECT * FROM repo_pool WHERE course_id = $1 AND NOT claimed ORDER BY created_at",
+        )
+        .bind(course_id)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Remove an unclaimed repository from the pool, keeping it locked until
+    /// the transaction ends. Returns whether it was still unclaimed.
+    pub async fn remove(tx: &mut Transaction<'_>, id: &Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM repo_pool WHERE id = $1 AND claimed = false")
+            .bind(id)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete the records of repositories claimed before the cutoff.
+    pub async fn purge_claimed(db: &Database, before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM repo_pool WHERE claimed AND claimed_at <= $1")
+            .bind(before)
+            .execute(db.pool())
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,160 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use uuid::Uuid;
+
+use crate::{
+    database::{Database, Transaction},
+    model::ProgressModel,
+    repository::Result,
+};
+
+/// Repository for the denormalized progress summary of enrollments.
+///
+/// The summary is derived from the `user_course_progress_live` view, which
+/// aggregates the user stages from scratch. Writers refresh the summary in
+/// the same transaction as the progress change.
+pub struct ProgressRepository;
+
+impl ProgressRepository {
+    /// Recompute the summary of a single enrollment.
+    pub async fn refresh(tx: &mut Transaction<'_>, user_course_id: &Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO user_course_progress (
+                user_course_id, course_id, completed_stage_count, last_activity_at, extension_progress, updated_at
+            )
+            SELECT user_course_id, course_id, completed_stage_count, last_activity_at, extension_progress, NOW()
+            FROM user_course_progress_live
+            WHERE user_course_id = $1
+            ON CONFLICT (user_course_id) DO UPDATE SET
+                completed_stage_count = EXCLUDED.completed_stage_count,
+                last_activity_at = EXCLUDED.last_activity_at,
+                extension_progress = EXCLUDED.extension_progress,
+                updated_at = EXCLUDED.updated_at
+            "#,
+        )
+        .bind(user_course_id)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Recompute the summaries of all enrollments of a course.
+    pub async fn rebuild(tx: &mut Transaction<'_>, course_slug: &str) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO user_course_progress (
+                user_course_id, course_id, completed_stage_count, last_activity_at, extension_progress, updated_at
+            )
+            SELECT p.user_course_id, p.course_id, p.completed_stage_count, p.last_activity_at, p.extension_progress, NOW()
+            FROM user_course_progress_live p
+            JOIN courses c ON p.course_id = c.id
+            WHERE c.slug = $1
+            ON CONFLICT (user_course_id) DO UPDATE SET
+                completed_stage_count = EXCLUDED.completed_stage_count,
+                last_activity_at = EXCLUDED.last_activity_at,
+                extension_progress = EXCLUDED.extension_progress,
+                updated_at = EXCLUDED.updated_at
+            "#,
+        )
+        .bind(course_slug)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Find the summarized progress of all enrollments of a course.
+    pub async fn find_by_course(db: &Database, course_slug: &str) -> Result<Vec<ProgressModel>> {
+        let rows = sqlx::query_as::<_, ProgressModel>(
+            r#"
+            SELECT
+                p.user_course_id,
+                uc.user_id,
+                p.completed_stage_count,
+                p.last_activity_at,
+                p.extension_progress
+            FROM user_course_progress p
+            JOIN user_courses uc ON p.user_course_id = uc.id
+            JOIN courses c ON p.course_id = c.id
+            WHERE c.slug = $1
+            ORDER BY p.completed_stage_count DESC, uc.started_at ASC
+            "#,
+        )
+        .bind(course_slug)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Fetch the progress summary of the enrollments of a course in a
+    /// cohort.
+    pub async fn find_by_cohort(
+        db: &Database,
+        course_slug: &str,
+        cohort: &str,
+    ) -> Result<Vec<ProgressModel>> {
+        let rows = sqlx::query_as::<_, ProgressModel>(
+            r#"
+            SELECT
+                p.user_course_id,
+                uc.user_id,
+                p.completed_stage_count,
+                p.last_activity_at,
+                p.extension_progress
+            FROM user_course_progress p
+            JOIN user_courses uc ON p.user_course_id = uc.id
+            JOIN courses c ON p.course_id = c.id
+            WHERE c.slug = $1 AND uc.cohort = $2
+            ORDER BY p.completed_stage_count DESC, uc.started_at ASC
+            "#,
+        )
+        .bind(course_slug)
+        .bind(cohort)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Aggregate the progress of all enrollments of a course from scratch.
+    pub async fn find_live_by_course(
+        db: &Database,
+        course_slug: &str,
+    ) -> Result<Vec<ProgressModel>> {
+        let rows = sqlx::query_as::<_, ProgressModel>(
+            r#"
+            SELECT
+                p.user_course_id,
+                uc.user_id,
+                p.completed_stage_count,
+                p.last_activity_at,
+                p.extension_progress
+            FROM user_course_progress_live p
+            JOIN user_courses uc ON p.user_course_id = uc.id
+            JOIN courses c ON p.course_id = c.id
+            WHERE c.slug = $1
+            ORDER BY p.completed_stage_count DESC, uc.started_at ASC
+            "#,
+        )
+        .bind(course_slug)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+}
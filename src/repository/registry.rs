@@ -0,0 +1,130 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::{
+    database::{Database, Transaction},
+    model::RegistryCredentialModel,
+    repository::Result,
+};
+
+/// Repository for the registry robot accounts of the courses.
+pub struct RegistryRepository;
+
+impl RegistryRepository {
+    /// Find the credentials handed to new pipelines of a course.
+    pub async fn find_active(
+        db: &Database,
+        course_slug: &str,
+    ) -> Result<Option<RegistryCredentialModel>> {
+        let row = sqlx::query_as::<_, RegistryCredentialModel>(
+            r#"
+            SELECT r.* FROM registry_credentials r
+            JOIN courses c ON r.course_id = c.id
+            WHERE c.slug = $1 AND r.retired_at IS NULL
+            "#,
+        )
+        .bind(course_slug)
+        .fetch_optional(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Replace the active credentials of a course, retiring the previous
+    /// ones as of their creation. The course row is locked so concurrent
+    /// rotations serialize.
+    pub async fn activate(
+        tx: &mut Transaction<'_>,
+        credential: &RegistryCredentialModel,
+    ) -> Result<Option<RegistryCredentialModel>> {
+        sqlx::query("SELECT id FROM courses WHERE id = $1 FOR UPDATE")
+            .bind(credential.course_id)
+            .fetch_one(&mut **tx)
+            .await?;
+
+        let retired = sqlx::query_as::<_, RegistryCredentialModel>(
+            r#"
+            UPDATE registry_credentials SET retired_at = $2
+            WHERE course_id = $1 AND retired_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(credential.course_id)
+        .bind(credential.created_at)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO registry_credentials (id, course_id, project, robot_id, robot_name, secret_name, encrypted_secret, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(credential.id)
+        .bind(credential.course_id)
+        .bind(&credential.project)
+        .bind(credential.robot_id)
+        .bind(&credential.robot_name)
+        .bind(&credential.secret_name)
+        .bind(&credential.encrypted_secret)
+        .bind(credential.created_at)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(retired)
+    }
+
+    /// Find all credentials of a course, the active and the retired ones.
+    pub async fn find_by_course(
+        db: &Database,
+        course_id: &Uuid,
+    ) -> Result<Vec<RegistryCredentialModel>> {
+        let rows = sqlx::query_as::<_, RegistryCredentialModel>(
+            "SELECT * FROM registry_credentials WHERE course_id = $1 ORDER BY created_at, id",
+        )
+        .bind(course_id)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Find the credentials retired before the given time.
+    pub async fn find_retired_before(
+        db: &Database,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<RegistryCredentialModel>> {
+        let rows = sqlx::query_as::<_, RegistryCredentialModel>(
+            "SELECT * FROM registry_credentials WHERE retired_at < $1 ORDER BY retired_at",
+        )
+        .bind(before)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Delete credentials whose robot account and secret were removed.
+    pub async fn delete(db: &Database, id: &Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM registry_credentials WHERE id = $1")
+            .bind(id)
+            .execute(db.pool())
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde_json::Value;
+
+use crate::{database::Database, model::SettingModel, repository::Result};
+
+/// Repository for the settings changed at runtime.
+pub struct SettingRepository;
+
+impl SettingRepository {
+    /// Fetch all settings.
+    pub async fn find_all(db: &Database) -> Result<Vec<SettingModel>> {
+        let rows = sqlx::query_as::<_, SettingModel>("SELECT * FROM system_settings")
+            .fetch_all(db.pool())
+            .await?;
+
+        Ok(rows)
+    }
+
+    /// Create or replace a setting.
+    pub async fn put(db: &Database, key: &str, value: &Value, actor: &str) -> Result<SettingModel> {
+        let row = sqlx::query_as::<_, SettingModel>(
+            r#"
+            INSERT INTO system_settings (key, value, updated_by, updated_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT (key) DO UPDATE
+            SET value = EXCLUDED.value, updated_by = EXCLUDED.updated_by, updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(key)
+        .bind(value)
+        .bind(actor)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Delete a setting, returning whether it was set.
+    pub async fn delete(db: &Database, key: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM system_settings WHERE key = $1")
+            .bind(key)
+            .execute(db.pool())
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
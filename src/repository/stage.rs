@@ -12,17 +12,27 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use chrono::{DateTime, Utc};
 use sqlx::Error;
 use tracing::debug;
 use uuid::Uuid;
 
 use crate::{
     database::{Database, Transaction},
-    model::{StageModel, UserStageModel},
-    repository::Result,
+    model::{
+        AttemptOutput, QueuedAttemptModel, RoadmapStageModel, StageAttemptModel, StageHintModel,
+        StageModel, UserStageModel,
+    },
+    repository::{CourseRepository, ORDER_SHIFT, Result},
+    utils::pagination::Page,
 };
 
 /// Repository for managing stages in the database.
+///
+/// Stages are grouped by extension and ordered by position within their
+/// group: the base stages of a course come first, followed by the stages of
+/// each extension in the order of the extensions. This is the order learners
+/// progress through the course.
 pub struct StageRepository;
 
 impl StageRepository {
@@ -34,8 +44,8 @@ impl StageRepository {
             r#"
             WITH inserted_stage AS (
                 INSERT INTO stages (
-                    id, course_id, extension_id, slug, name, difficulty, description, instruction, solution, weight, created_at, updated_at
-                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                    id, course_id, extension_id, slug, name, difficulty, description, instruction, solution, weight, position, max_attempts, resources, content_hash, optional, created_at, updated_at
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
                 RETURNING *
             )
             SELECT s.*, e.slug as extension_slug
@@ -53,6 +63,11 @@ impl StageRepository {
         .bind(&stage.instruction)
         .bind(&stage.solution)
         .bind(stage.weight)
+        .bind(stage.position)
+        .bind(stage.max_attempts)
+        .bind(&stage.resources)
+        .bind(&stage.content_hash)
+        .bind(stage.optional)
         .bind(stage.created_at)
         .bind(stage.updated_at)
         .fetch_one(&mut **tx)
@@ -107,8 +122,8 @@ impl StageRepository {
             FROM stages s
             JOIN courses c ON s.course_id = c.id
             LEFT JOIN extensions e ON s.extension_id = e.id
-            WHERE c.slug = $1
-            ORDER BY s.weight ASC
+            WHERE c.slug = $1 AND NOT s.archived
+            ORDER BY COALESCE(e.weight, -1) ASC, s.position ASC
             "#,
         )
         .bind(course_slug)
@@ -125,8 +140,8 @@ impl StageRepository {
             SELECT s.*, e.slug as extension_slug FROM stages s
             JOIN courses c ON s.course_id = c.id
             LEFT JOIN extensions e ON s.extension_id = e.id
-            WHERE c.slug = $1 AND s.extension_id IS NULL
-            ORDER BY s.weight ASC
+            WHERE c.slug = $1 AND s.extension_id IS NULL AND NOT s.archived
+            ORDER BY s.position ASC
             "#,
         )
         .bind(course_slug)
@@ -147,8 +162,8 @@ impl StageRepository {
             FROM stages s
             JOIN courses c ON s.course_id = c.id
             JOIN extensions e ON s.extension_id = e.id
-            WHERE c.slug = $1 AND s.extension_id IS NOT NULL
-            ORDER BY s.weight ASC
+            WHERE c.slug = $1 AND s.extension_id IS NOT NULL AND NOT s.archived
+            ORDER BY e.weight ASC, s.position ASC
             "#,
         )
         .bind(course_slug)
@@ -165,8 +180,8 @@ impl StageRepository {
             SELECT s.*, e.slug as extension_slug
             FROM stages s
             JOIN extensions e ON s.extension_id = e.id
-            WHERE e.slug = $1
-            ORDER BY s.weight ASC
+            WHERE e.slug = $1 AND NOT s.archived
+            ORDER BY s.position ASC
             "#,
         )
         .bind(extension_slug)
@@ -176,7 +191,10 @@ impl StageRepository {
         Ok(rows)
     }
 
-    /// Find all stages from the first stage to the specified stage (ordered by weight).
+    /// Find the stages a learner passed to reach the specified stage, in
+    /// order: the base stages up to it, or for an extension stage all base
+    /// stages and the stages of its extension up to it. Stages of other
+    /// extensions are not included.
     pub async fn find_stages_until(
         db: &Database,
         course_slug: &str,
@@ -185,7 +203,7 @@ impl StageRepository {
         let rows = sqlx::query_as::<_, StageModel>(
             r#"
             WITH target_stage AS (
-                SELECT weight FROM stages s
+                SELECT s.extension_id, s.position FROM stages s
                 JOIN courses c ON s.course_id = c.id
                 WHERE c.slug = $1 AND s.slug = $2
             )
@@ -193,8 +211,13 @@ impl StageRepository {
             FROM stages s
             JOIN courses c ON s.course_id = c.id
             LEFT JOIN extensions e ON s.extension_id = e.id
-            WHERE c.slug = $1 AND s.weight <= (SELECT weight FROM target_stage)
-            ORDER BY s.weight ASC
+            CROSS JOIN target_stage t
+            WHERE c.slug = $1 AND NOT s.archived
+              AND (
+                (s.extension_id IS NULL AND (t.extension_id IS NOT NULL OR s.position <= t.position))
+                OR (s.extension_id = t.extension_id AND s.position <= t.position)
+              )
+            ORDER BY COALESCE(e.weight, -1) ASC, s.position ASC
             "#,
         )
         .bind(course_slug)
@@ -205,7 +228,7 @@ impl StageRepository {
         Ok(rows)
     }
 
-    /// Get the first stage (ordered by weight)
+    /// Get the first stage of the course
     pub async fn first(db: &Database, course_slug: &str) -> Result<Option<StageModel>> {
         let stage = sqlx::query_as::<_, StageModel>(
             r#"
@@ -213,8 +236,8 @@ impl StageRepository {
             FROM stages s
             JOIN courses c ON s.course_id = c.id
             LEFT JOIN extensions e ON s.extension_id = e.id
-            WHERE c.slug = $1
-            ORDER BY s.weight ASC
+            WHERE c.slug = $1 AND NOT s.archived
+            ORDER BY COALESCE(e.weight, -1) ASC, s.position ASC
             LIMIT 1
             "#,
         )
@@ -225,7 +248,41 @@ impl StageRepository {
         Ok(stage)
     }
 
-    /// Get the next stage by current stage slug (ordered by weight)
+    /// Find all stages of a course in progression order, along with the
+    /// state of the learner on each of them.
+    pub async fn find_roadmap(
+        db: &Database,
+        user_id: &str,
+        course_slug: &str,
+    ) -> Result<Vec<RoadmapStageModel>> {
+        let rows = sqlx::query_as::<_, RoadmapStageModel>(
+            r#"
+            SELECT
+                s.slug,
+                s.name,
+                e.slug AS extension_slug,
+                us.status,
+                s.id IS NOT DISTINCT FROM uc.current_stage_id AS current
+            FROM user_courses uc
+            JOIN courses c ON uc.course_id = c.id
+            JOIN stages s ON s.course_id = c.id
+            LEFT JOIN extensions e ON s.extension_id = e.id
+            LEFT JOIN user_stages us ON us.user_course_id = uc.id AND us.stage_id = s.id
+            WHERE uc.user_id = $1 AND c.slug = $2 AND NOT s.archived
+            ORDER BY COALESCE(e.weight, -1) ASC, s.position ASC
+            "#,
+        )
+        .bind(user_id)
+        .bind(course_slug)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Get the stage following the current stage by slug, crossing from the
+    /// base stages into the first extension and from one extension into the
+    /// next.
     pub async fn next(
         db: &Database,
         course_slug: &str,
@@ -234,16 +291,18 @@ impl StageRepository {
         let stage = sqlx::query_as::<_, StageModel>(
             r#"
                 WITH current_stage AS (
-                    SELECT weight FROM stages s
+                    SELECT COALESCE(e.weight, -1) AS group_weight, s.position FROM stages s
                     JOIN courses c ON s.course_id = c.id
+                    LEFT JOIN extensions e ON s.extension_id = e.id
                     WHERE c.slug = $1 AND s.slug = $2
                 )
                 SELECT s.*, e.slug as extension_slug
                 FROM stages s
                 JOIN courses c ON s.course_id = c.id
                 LEFT JOIN extensions e ON s.extension_id = e.id
-                WHERE c.slug = $1 AND s.weight > (SELECT weight FROM current_stage)
-                ORDER BY s.weight ASC
+                WHERE c.slug = $1 AND NOT s.archived
+                  AND (COALESCE(e.weight, -1), s.position) > (SELECT group_weight, position FROM current_stage)
+                ORDER BY COALESCE(e.weight, -1) ASC, s.position ASC
                 LIMIT 1
                 "#,
         )
@@ -255,6 +314,37 @@ impl StageRepository {
         Ok(stage)
     }
 
+    /// Get the slugs of the stages before and after the stage by slug, in the
+    /// same order as `next`, so the last base stage leads into the first
+    /// extension.
+    pub async fn find_adjacent(
+        db: &Database,
+        course_slug: &str,
+        stage_slug: &str,
+    ) -> Result<(Option<String>, Option<String>)> {
+        let adjacent = sqlx::query_as::<_, (Option<String>, Option<String>)>(
+            r#"
+                WITH ordered AS (
+                    SELECT s.slug,
+                           LAG(s.slug) OVER w AS previous_slug,
+                           LEAD(s.slug) OVER w AS next_slug
+                    FROM stages s
+                    JOIN courses c ON s.course_id = c.id
+                    LEFT JOIN extensions e ON s.extension_id = e.id
+                    WHERE c.slug = $1 AND NOT s.archived
+                    WINDOW w AS (ORDER BY COALESCE(e.weight, -1) ASC, s.position ASC)
+                )
+                SELECT previous_slug, next_slug FROM ordered WHERE slug = $2
+                "#,
+        )
+        .bind(course_slug)
+        .bind(stage_slug)
+        .fetch_optional(db.pool())
+        .await?;
+
+        Ok(adjacent.unwrap_or_default())
+    }
+
     /// Update a stage in the database.
     pub async fn update(tx: &mut Transaction<'_>, stage: &StageModel) -> Result<StageModel> {
         debug!("Updating stage with slug: {}", stage.slug);
@@ -263,7 +353,7 @@ impl StageRepository {
             r#"
             WITH updated_stage AS (
                 UPDATE stages
-                SET course_id = $2, extension_id = $3, name = $4, difficulty = $5, description = $6, instruction = $7, solution = $8, weight = $9, updated_at = $10
+                SET course_id = $2, extension_id = $3, name = $4, difficulty = $5, description = $6, instruction = $7, solution = $8, weight = $9, position = $10, max_attempts = $11, resources = $12, content_hash = $13, optional = $14, archived = false, updated_at = $15
                 WHERE slug = $1
                 RETURNING *
             )
@@ -281,6 +371,11 @@ impl StageRepository {
         .bind(&stage.instruction)
         .bind(&stage.solution)
         .bind(stage.weight)
+        .bind(stage.position)
+        .bind(stage.max_attempts)
+        .bind(&stage.resources)
+        .bind(&stage.content_hash)
+        .bind(stage.optional)
         .bind(stage.updated_at)
         .fetch_one(&mut **tx)
         .await?;
@@ -288,6 +383,21 @@ impl StageRepository {
         Ok(row)
     }
 
+    /// Moves the positions of the stages of a course out of the way, so that
+    /// writing a new order never collides with the current one. Archived
+    /// stages keep theirs.
+    pub async fn shift_positions(tx: &mut Transaction<'_>, course_id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"UPDATE stages SET position = position + $2 WHERE course_id = $1 AND NOT archived"#,
+        )
+        .bind(course_id)
+        .bind(ORDER_SHIFT)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
     /// Updates an existing record or inserts a new one if it doesn't exist.
     pub async fn upsert(tx: &mut Transaction<'_>, stage: &StageModel) -> Result<StageModel> {
         match Self::update(tx, stage).await {
@@ -297,6 +407,32 @@ impl StageRepository {
         }
     }
 
+    /// Replace the hints of a stage, keeping their order.
+    pub async fn replace_hints(
+        tx: &mut Transaction<'_>,
+        stage_id: Uuid,
+        hints: &[String],
+    ) -> Result<()> {
+        sqlx::query("DELETE FROM stage_hints WHERE stage_id = $1")
+            .bind(stage_id)
+            .execute(&mut **tx)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO stage_hints (stage_id, position, content)
+            SELECT $1, h.ordinality - 1, h.content
+            FROM UNNEST($2::TEXT[]) WITH ORDINALITY AS h(content, ordinality)
+            "#,
+        )
+        .bind(stage_id)
+        .bind(hints)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
     /// Delete a stage by its slug.
     pub async fn delete(tx: &mut Transaction<'_>, slug: &str) -> Result<()> {
         debug!("Deleting stage with slug: {}", slug);
@@ -305,6 +441,70 @@ impl StageRepository {
         Ok(())
     }
 
+    /// Whether any learner reached a stage.
+    pub async fn is_referenced(tx: &mut Transaction<'_>, id: Uuid) -> Result<bool> {
+        let referenced = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM user_stages WHERE stage_id = $1)",
+        )
+        .bind(id)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(referenced)
+    }
+
+    /// Archive a stage removed from its course, at the position it had
+    /// before the order was shifted. Learners keep their progress on it and
+    /// continue with the stage that follows.
+    pub async fn archive(tx: &mut Transaction<'_>, slug: &str) -> Result<()> {
+        debug!("Archiving stage with slug: {}", slug);
+        sqlx::query(
+            r#"
+            UPDATE stages SET archived = true, position = position - $2
+            WHERE slug = $1 AND NOT archived
+            "#,
+        )
+        .bind(slug)
+        .bind(ORDER_SHIFT)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Move the archived stages of an extension about to be deleted to the
+    /// base stages of the course, so they survive it.
+    pub async fn detach_archived(tx: &mut Transaction<'_>, extension_slug: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE stages SET extension_id = NULL
+            WHERE archived AND extension_id IN (SELECT id FROM extensions WHERE slug = $1)
+            "#,
+        )
+        .bind(extension_slug)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Delete the archived stages of a course no learner references anymore,
+    /// returning how many were deleted.
+    pub async fn delete_unreferenced(tx: &mut Transaction<'_>, course_id: Uuid) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM stages s
+            WHERE s.course_id = $1 AND s.archived
+              AND NOT EXISTS (SELECT 1 FROM user_stages us WHERE us.stage_id = s.id)
+            "#,
+        )
+        .bind(course_id)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
     /// Find user stages for the user.
     pub async fn find_user_stages(
         db: &Database,
@@ -316,7 +516,8 @@ impl StageRepository {
             SELECT
                 us.*,
                 c.slug AS course_slug,
-                s.slug AS stage_slug
+                s.slug AS stage_slug,
+                (SELECT COUNT(*) FROM stage_hints h WHERE h.stage_id = s.id) AS hints_total
             FROM user_stages us
             JOIN user_courses uc ON us.user_course_id = uc.id
             JOIN courses c ON uc.course_id = c.id
@@ -344,7 +545,8 @@ impl StageRepository {
             SELECT
                 us.*,
                 c.slug AS course_slug,
-                s.slug AS stage_slug
+                s.slug AS stage_slug,
+                (SELECT COUNT(*) FROM stage_hints h WHERE h.stage_id = s.id) AS hints_total
             FROM user_stages us
             JOIN user_courses uc ON us.user_course_id = uc.id
             JOIN courses c ON uc.course_id = c.id
@@ -379,7 +581,8 @@ impl StageRepository {
             SELECT
                 i.*,
                 c.slug AS course_slug,
-                s.slug AS stage_slug
+                s.slug AS stage_slug,
+                (SELECT COUNT(*) FROM stage_hints h WHERE h.stage_id = s.id) AS hints_total
             FROM inserted i
             JOIN user_courses uc ON i.user_course_id = uc.id
             JOIN courses c ON uc.course_id = c.id
@@ -412,14 +615,17 @@ impl StageRepository {
                 SET
                     status = $2,
                     test = $3,
-                    completed_at = $4
+                    completed_at = $4,
+                    granted_attempts = $5,
+                    completed_commit = $6
                 WHERE id = $1
                 RETURNING *
             )
             SELECT
                 u.*,
                 c.slug AS course_slug,
-                s.slug AS stage_slug
+                s.slug AS stage_slug,
+                (SELECT COUNT(*) FROM stage_hints h WHERE h.stage_id = s.id) AS hints_total
             FROM updated u
             JOIN user_courses uc ON u.user_course_id = uc.id
             JOIN courses c ON uc.course_id = c.id
@@ -430,9 +636,508 @@ impl StageRepository {
         .bind(&user_stage.status)
         .bind(&user_stage.test)
         .bind(user_stage.completed_at)
+        .bind(user_stage.granted_attempts)
+        .bind(&user_stage.completed_commit)
         .fetch_one(&mut **tx)
         .await?;
 
+        CourseRepository::notify_status(tx, &row.user_course_id).await?;
+        Ok(row)
+    }
+
+    /// Lock a user stage until the end of the transaction, returning the
+    /// extra attempts granted so far.
+    pub async fn lock_granted_attempts(tx: &mut Transaction<'_>, id: &Uuid) -> Result<i32> {
+        let granted = sqlx::query_scalar::<_, i32>(
+            "SELECT granted_attempts FROM user_stages WHERE id = $1 FOR UPDATE",
+        )
+        .bind(id)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(granted)
+    }
+
+    /// Set the extra attempts granted for a user stage, leaving the rest of
+    /// the row alone.
+    pub async fn set_granted_attempts(
+        tx: &mut Transaction<'_>,
+        id: &Uuid,
+        granted: i32,
+    ) -> Result<UserStageModel> {
+        let row = sqlx::query_as::<_, UserStageModel>(
+            r#"
+            WITH updated AS (
+                UPDATE user_stages SET granted_attempts = $2
+                WHERE id = $1
+                RETURNING *
+            )
+            SELECT
+                u.*,
+                c.slug AS course_slug,
+                s.slug AS stage_slug,
+                (SELECT COUNT(*) FROM stage_hints h WHERE h.stage_id = s.id) AS hints_total
+            FROM updated u
+            JOIN user_courses uc ON u.user_course_id = uc.id
+            JOIN courses c ON uc.course_id = c.id
+            JOIN stages s ON u.stage_id = s.id
+            "#,
+        )
+        .bind(id)
+        .bind(granted)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        CourseRepository::notify_status(tx, &row.user_course_id).await?;
+        Ok(row)
+    }
+
+    /// Find the hints a user revealed for a user stage, in order.
+    pub async fn find_unlocked_hints(
+        db: &Database,
+        user_stage_id: &Uuid,
+    ) -> Result<Vec<StageHintModel>> {
+        let rows = sqlx::query_as::<_, StageHintModel>(
+            r#"
+            SELECT h.position, h.content
+            FROM stage_hints h
+            JOIN user_stages us ON h.stage_id = us.stage_id
+            WHERE us.id = $1 AND h.position < us.hints_unlocked
+            ORDER BY h.position
+            "#,
+        )
+        .bind(user_stage_id)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Reveal the next hint of a user stage. Returns nothing once every
+    /// hint of the stage is revealed.
+    pub async fn unlock_hint(
+        db: &Database,
+        user_stage_id: &Uuid,
+    ) -> Result<Option<StageHintModel>> {
+        let row = sqlx::query_as::<_, StageHintModel>(
+            r#"
+            WITH unlocked AS (
+                UPDATE user_stages us
+                SET hints_unlocked = us.hints_unlocked + 1
+                WHERE us.id = $1 AND EXISTS (
+                    SELECT 1 FROM stage_hints h
+                    WHERE h.stage_id = us.stage_id AND h.position = us.hints_unlocked
+                )
+                RETURNING us.stage_id, us.hints_unlocked
+            )
+            SELECT h.position, h.content
+            FROM unlocked u
+            JOIN stage_hints h ON h.stage_id = u.stage_id AND h.position = u.hints_unlocked - 1
+            "#,
+        )
+        .bind(user_stage_id)
+        .fetch_optional(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Record when the course image of a user course was pushed, on the
+    /// stage it is at. Returns whether there was such a user stage.
+    pub async fn record_image_push(
+        db: &Database,
+        user_course_id: &Uuid,
+        pushed_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE user_stages us
+            SET image_pushed_at = $2
+            FROM user_courses uc
+            WHERE uc.id = $1 AND us.user_course_id = uc.id AND us.stage_id = uc.current_stage_id
+            "#,
+        )
+        .bind(user_course_id)
+        .bind(pushed_at)
+        .execute(db.pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Record a graded attempt of a user stage. Attempts created after the
+    /// deadline of the user are marked as late.
+    pub async fn create_attempt(
+        db: &Database,
+        attempt: &StageAttemptModel,
+    ) -> Result<StageAttemptModel> {
+        let row = sqlx::query_as::<_, StageAttemptModel>(
+            r#"
+            WITH inserted AS (
+                INSERT INTO stage_attempts (
+                    id, user_stage_id, pipeline_run, status, content_hash, course_commit, repo_commit, tester_image, reason, late, created_at
+                )
+                SELECT $1, $2, $3, $4, $5, $6, $7, $8, $10, COALESCE($9 > GREATEST(c.closes_at, uc.extended_deadline), FALSE), $9
+                FROM user_stages us
+                JOIN user_courses uc ON us.user_course_id = uc.id
+                JOIN courses c ON uc.course_id = c.id
+                WHERE us.id = $2
+                RETURNING *
+            )
+            SELECT
+                i.*,
+                uc.user_id,
+                s.slug AS stage_slug,
+                i.content_hash <> s.content_hash AS stale
+            FROM inserted i
+            JOIN user_stages us ON i.user_stage_id = us.id
+            JOIN user_courses uc ON us.user_course_id = uc.id
+            JOIN stages s ON us.stage_id = s.id
+            "#,
+        )
+        .bind(attempt.id)
+        .bind(attempt.user_stage_id)
+        .bind(&attempt.pipeline_run)
+        .bind(&attempt.status)
+        .bind(&attempt.content_hash)
+        .bind(&attempt.course_commit)
+        .bind(&attempt.repo_commit)
+        .bind(&attempt.tester_image)
+        .bind(attempt.created_at)
+        .bind(&attempt.reason)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Record the outcome of the attempt graded by the given PipelineRun,
+    /// along with the course version and the graded commit echoed back by
+    /// the pipeline, if any, and the output of the tester. Returns nothing
+    /// unless the attempt was still pending.
+    pub async fn complete_attempt(
+        db: &Database,
+        pipeline_run: &str,
+        status: &str,
+        course_commit: &str,
+        content_hash: &str,
+        repo_commit: &str,
+        output: &AttemptOutput<'_>,
+    ) -> Result<Option<StageAttemptModel>> {
+        let row = sqlx::query_as::<_, StageAttemptModel>(
+            r#"
+            WITH updated AS (
+                UPDATE stage_attempts
+                SET status = $2,
+                    course_commit = COALESCE(NULLIF($3, ''), course_commit),
+                    content_hash = COALESCE(NULLIF($4, ''), content_hash),
+                    repo_commit = COALESCE(NULLIF($5, ''), repo_commit),
+                    reason = NULLIF($6, ''),
+                    log = NULLIF($7, ''),
+                    results = $8,
+                    finished_at = NOW()
+                WHERE pipeline_run = $1 AND status = 'pending'
+                RETURNING *
+            )
+            SELECT
+                u.*,
+                uc.user_id,
+                s.slug AS stage_slug,
+                u.content_hash <> s.content_hash AS stale
+            FROM updated u
+            JOIN user_stages us ON u.user_stage_id = us.id
+            JOIN user_courses uc ON us.user_course_id = uc.id
+            JOIN stages s ON us.stage_id = s.id
+            "#,
+        )
+        .bind(pipeline_run)
+        .bind(status)
+        .bind(course_commit)
+        .bind(content_hash)
+        .bind(repo_commit)
+        .bind(output.reason)
+        .bind(output.log)
+        .bind(&output.results)
+        .fetch_optional(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Lock the oldest queued attempt for dispatching. Attempts locked by
+    /// another transaction are skipped, so each is dispatched only once, as
+    /// are those of users with as many running attempts as the given limit,
+    /// unless it is zero.
+    pub async fn claim_queued_attempt(
+        tx: &mut Transaction<'_>,
+        limit: i64,
+    ) -> Result<Option<QueuedAttemptModel>> {
+        let row = sqlx::query_as::<_, QueuedAttemptModel>(
+            r#"
+            SELECT
+                a.id,
+                us.user_course_id,
+                c.slug AS course_slug,
+                s.slug AS stage_slug,
+                a.course_commit,
+                a.content_hash,
+                a.repo_commit
+            FROM stage_attempts a
+            JOIN user_stages us ON a.user_stage_id = us.id
+            JOIN user_courses uc ON us.user_course_id = uc.id
+            JOIN courses c ON uc.course_id = c.id
+            JOIN stages s ON us.stage_id = s.id
+            WHERE a.status = 'queued' AND (
+                $1 = 0 OR (
+                    SELECT COUNT(*)
+                    FROM stage_attempts r
+                    JOIN user_stages rus ON r.user_stage_id = rus.id
+                    JOIN user_courses ruc ON rus.user_course_id = ruc.id
+                    WHERE ruc.user_id = uc.user_id
+                        AND r.status = 'pending' AND r.pipeline_run IS NOT NULL
+                ) < $1
+            )
+            ORDER BY a.created_at ASC, a.seq ASC
+            LIMIT 1
+            FOR UPDATE OF a SKIP LOCKED
+            "#,
+        )
+        .bind(limit)
+        .fetch_optional(&mut **tx)
+        .await?;
+
         Ok(row)
     }
+
+    /// Move a queued attempt to the given status, along with the PipelineRun
+    /// grading it, if one was triggered.
+    pub async fn dispatch_attempt(
+        tx: &mut Transaction<'_>,
+        id: &Uuid,
+        status: &str,
+        pipeline_run: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE stage_attempts SET status = $2, pipeline_run = $3, reason = NULL
+            WHERE id = $1 AND status = 'queued'
+            "#,
+        )
+        .bind(id)
+        .bind(status)
+        .bind(pipeline_run)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the current status of an attempt.
+    pub async fn get_attempt_status(db: &Database, id: &Uuid) -> Result<String> {
+        let status =
+            sqlx::query_scalar::<_, String>("SELECT status FROM stage_attempts WHERE id = $1")
+                .bind(id)
+                .fetch_one(db.pool())
+                .await?;
+
+        Ok(status)
+    }
+
+    /// Find why the latest attempt of the user stage waiting for a pipeline
+    /// run was queued, if one is waiting. Attempts queued during a cluster
+    /// outage or a maintenance carry no reason.
+    pub async fn find_queued_reason(
+        db: &Database,
+        user_stage_id: &Uuid,
+    ) -> Result<Option<Option<String>>> {
+        let reason = sqlx::query_scalar::<_, Option<String>>(
+            r#"
+            SELECT reason FROM stage_attempts
+            WHERE user_stage_id = $1 AND status = 'queued'
+            ORDER BY created_at DESC, seq DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(user_stage_id)
+        .fetch_optional(db.pool())
+        .await?;
+
+        Ok(reason)
+    }
+
+    /// Count the attempts of the user owning the user stage whose
+    /// PipelineRun has not reported its outcome yet, across all courses.
+    pub async fn count_running_attempts(db: &Database, user_stage_id: &Uuid) -> Result<i64> {
+        let count = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*)
+            FROM stage_attempts a
+            JOIN user_stages us ON a.user_stage_id = us.id
+            JOIN user_courses uc ON us.user_course_id = uc.id
+            WHERE a.status = 'pending' AND a.pipeline_run IS NOT NULL AND uc.user_id = (
+                SELECT ouc.user_id
+                FROM user_stages ous
+                JOIN user_courses ouc ON ous.user_course_id = ouc.id
+                WHERE ous.id = $1
+            )
+            "#,
+        )
+        .bind(user_stage_id)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Find the latest attempt of a user stage, if any.
+    pub async fn find_latest_attempt(
+        db: &Database,
+        user_stage_id: &Uuid,
+    ) -> Result<Option<StageAttemptModel>> {
+        let row = sqlx::query_as::<_, StageAttemptModel>(
+            r#"
+            SELECT
+                a.*,
+                uc.user_id,
+                s.slug AS stage_slug,
+                a.content_hash <> s.content_hash AS stale
+            FROM stage_attempts a
+            JOIN user_stages us ON a.user_stage_id = us.id
+            JOIN user_courses uc ON us.user_course_id = uc.id
+            JOIN stages s ON us.stage_id = s.id
+            WHERE a.user_stage_id = $1
+            ORDER BY a.created_at DESC, a.seq DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(user_stage_id)
+        .fetch_optional(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Find the attempts whose PipelineRun was triggered before the given
+    /// time and has not reported its outcome yet, oldest first.
+    pub async fn find_pending_attempts(
+        db: &Database,
+        before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<StageAttemptModel>> {
+        let rows = sqlx::query_as::<_, StageAttemptModel>(
+            r#"
+            SELECT
+                a.*,
+                uc.user_id,
+                s.slug AS stage_slug,
+                a.content_hash <> s.content_hash AS stale
+            FROM stage_attempts a
+            JOIN user_stages us ON a.user_stage_id = us.id
+            JOIN user_courses uc ON us.user_course_id = uc.id
+            JOIN stages s ON us.stage_id = s.id
+            WHERE a.status = 'pending' AND a.pipeline_run IS NOT NULL AND a.created_at < $1
+            ORDER BY a.created_at, a.seq
+            LIMIT $2
+            "#,
+        )
+        .bind(before)
+        .bind(limit)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Find a page of the attempts made in a course, newest first, optionally
+    /// restricted to a user, a stage and the stage content they were graded
+    /// against.
+    pub async fn find_attempts(
+        db: &Database,
+        course_slug: &str,
+        user_id: Option<&str>,
+        stage_slug: Option<&str>,
+        content_hash: Option<&str>,
+        page: &Page,
+    ) -> Result<Vec<StageAttemptModel>> {
+        let rows = sqlx::query_as::<_, StageAttemptModel>(
+            r#"
+            SELECT
+                a.*,
+                uc.user_id,
+                s.slug AS stage_slug,
+                a.content_hash <> s.content_hash AS stale
+            FROM stage_attempts a
+            JOIN user_stages us ON a.user_stage_id = us.id
+            JOIN user_courses uc ON us.user_course_id = uc.id
+            JOIN courses c ON uc.course_id = c.id
+            JOIN stages s ON us.stage_id = s.id
+            WHERE c.slug = $1
+                AND ($2::TEXT IS NULL OR uc.user_id = $2)
+                AND ($3::TEXT IS NULL OR s.slug = $3)
+                AND ($4::TEXT IS NULL OR a.content_hash = $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR (a.created_at, a.seq) < ($5, $6))
+            ORDER BY a.created_at DESC, a.seq DESC
+            LIMIT $7
+            "#,
+        )
+        .bind(course_slug)
+        .bind(user_id)
+        .bind(stage_slug)
+        .bind(content_hash)
+        .bind(page.after.map(|c| c.created_at))
+        .bind(page.after.map(|c| c.seq))
+        .bind(page.fetch_limit())
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Mark the pending attempt graded by the given pipeline run as cancelled.
+    /// Returns whether it was still pending.
+    pub async fn cancel_attempt(db: &Database, pipeline_run: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE stage_attempts SET status = 'cancelled', finished_at = NOW() WHERE pipeline_run = $1 AND status = 'pending'",
+        )
+        .bind(pipeline_run)
+        .execute(db.pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Count the attempts consuming the budget of a user stage, optionally
+    /// restricted to attempts made against the given stage content.
+    pub async fn count_attempts(
+        db: &Database,
+        user_stage_id: &Uuid,
+        content_hash: Option<&str>,
+    ) -> Result<i64> {
+        let count = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*) FROM stage_attempts
+            WHERE user_stage_id = $1
+                AND status NOT IN ('budget_exhausted', 'cancelled')
+                AND ($2::TEXT IS NULL OR content_hash = $2)
+            "#,
+        )
+        .bind(user_stage_id)
+        .bind(content_hash)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Count the failed graded attempts of a user stage.
+    pub async fn count_failed_attempts(db: &Database, user_stage_id: &Uuid) -> Result<i64> {
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM stage_attempts WHERE user_stage_id = $1 AND status = 'failed'",
+        )
+        .bind(user_stage_id)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(count)
+    }
 }
@@ -0,0 +1,114 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use uuid::Uuid;
+
+use crate::{
+    database::{Database, Transaction},
+    model::ApiTokenModel,
+    repository::Result,
+};
+
+/// Selects the tokens along with the slugs of their courses.
+const SELECT_TOKENS: &str = r#"
+    SELECT t.*, ARRAY(
+        SELECT c.slug FROM api_token_courses tc
+        JOIN courses c ON c.id = tc.course_id
+        WHERE tc.token_id = t.id
+        ORDER BY c.slug
+    ) AS courses
+    FROM api_tokens t
+"#;
+
+/// Repository for the API tokens of integrations.
+pub struct ApiTokenRepository;
+
+impl ApiTokenRepository {
+    /// Create a token along with its course scope.
+    pub async fn create(
+        tx: &mut Transaction<'_>,
+        token: &ApiTokenModel,
+        course_ids: &[Uuid],
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO api_tokens (id, name, token_hash, capabilities, created_by, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(token.id)
+        .bind(&token.name)
+        .bind(&token.token_hash)
+        .bind(&token.capabilities)
+        .bind(&token.created_by)
+        .bind(token.created_at)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO api_token_courses (token_id, course_id) SELECT $1, UNNEST($2::UUID[])",
+        )
+        .bind(token.id)
+        .bind(course_ids)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Find all tokens, newest first.
+    pub async fn find(db: &Database) -> Result<Vec<ApiTokenModel>> {
+        let rows = sqlx::query_as::<_, ApiTokenModel>(&format!(
+            "{SELECT_TOKENS} ORDER BY t.created_at DESC"
+        ))
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Fetch the token with the given secret hash, unless it was revoked.
+    pub async fn get_active_by_hash(db: &Database, hash: &str) -> Result<Option<ApiTokenModel>> {
+        let row = sqlx::query_as::<_, ApiTokenModel>(&format!(
+            "{SELECT_TOKENS} WHERE t.token_hash = $1 AND t.revoked_at IS NULL"
+        ))
+        .bind(hash)
+        .fetch_optional(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Record that a token was just used.
+    pub async fn touch(db: &Database, id: Uuid) -> Result<()> {
+        sqlx::query("UPDATE api_tokens SET last_used_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Revoke a token, returning whether it was still active.
+    pub async fn revoke(db: &Database, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE api_tokens SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL",
+        )
+        .bind(id)
+        .execute(db.pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
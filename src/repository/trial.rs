@@ -0,0 +1,216 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::{
+    database::{Database, Transaction},
+    model::TrialModel,
+    repository::Result,
+};
+
+/// Repository for the trials of visitors who have not signed up.
+pub struct TrialRepository;
+
+impl TrialRepository {
+    /// Serializes the admission of new trials until the transaction ends, so
+    /// that concurrent requests can not exceed the caps together.
+    pub async fn lock(tx: &mut Transaction<'_>) -> Result<()> {
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext('trials'))").execute(&mut **tx).await?;
+
+        Ok(())
+    }
+
+    /// Count the trials whose repository is still in use at the given time.
+    pub async fn count_active(tx: &mut Transaction<'_>, now: DateTime<Utc>) -> Result<i64> {
+        let count = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM trials WHERE status = 'active' AND expires_at > $1",
+        )
+        .bind(now)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Count the trials requested by a client since the given time.
+    pub async fn count_by_client(
+        tx: &mut Transaction<'_>,
+        client_key: &str,
+        since: DateTime<Utc>,
+    ) -> Result<i64> {
+        let count = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM trials WHERE client_key = $1 AND created_at > $2",
+        )
+        .bind(client_key)
+        .bind(since)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Create a new trial.
+    pub async fn create(tx: &mut Transaction<'_>, trial: &TrialModel) -> Result<TrialModel> {
+        let row = sqlx::query_as::<_, TrialModel>(
+            r#"
+            WITH inserted AS (
+                INSERT INTO trials (id, course_id, stage_id, client_key, status, created_at, updated_at, expires_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                RETURNING *
+            )
+            SELECT t.*, c.slug AS course_slug, s.slug AS stage_slug
+            FROM inserted t
+            JOIN courses c ON t.course_id = c.id
+            JOIN stages s ON t.stage_id = s.id
+            "#,
+        )
+        .bind(trial.id)
+        .bind(trial.course_id)
+        .bind(trial.stage_id)
+        .bind(&trial.client_key)
+        .bind(&trial.status)
+        .bind(trial.created_at)
+        .bind(trial.updated_at)
+        .bind(trial.expires_at)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Get a trial by its id.
+    pub async fn get_by_id(db: &Database, id: &Uuid) -> Result<TrialModel> {
+        let row = sqlx::query_as::<_, TrialModel>(
+            r#"
+            SELECT t.*, c.slug AS course_slug, s.slug AS stage_slug
+            FROM trials t
+            JOIN courses c ON t.course_id = c.id
+            JOIN stages s ON t.stage_id = s.id
+            WHERE t.id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_one(db.pool())
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Record the PipelineRun grading the latest push to a trial.
+    pub async fn start_attempt(db: &Database, id: &Uuid, pipeline_run: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE trials SET test = 'pending', pipeline_run = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(pipeline_run)
+        .execute(db.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record the test result of a PipelineRun, unless a later push of the
+    /// trial superseded it. Returns whether the result was recorded.
+    pub async fn complete_attempt(
+        db: &Database,
+        id: &Uuid,
+        pipeline_run: &str,
+        test: &str,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE trials SET test = $3, updated_at = NOW()
+            WHERE id = $1 AND pipeline_run = $2
+            "#,
+        )
+        .bind(id)
+        .bind(pipeline_run)
+        .bind(test)
+        .execute(db.pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Mark an active trial as converted into the given user course. Returns
+    /// whether the trial was still active.
+    pub async fn convert(db: &Database, id: &Uuid, user_course_id: &Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE trials SET status = 'converted', user_course_id = $2, updated_at = NOW()
+            WHERE id = $1 AND status = 'active'
+            "#,
+        )
+        .bind(id)
+        .bind(user_course_id)
+        .execute(db.pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// End an active trial right away, leaving its repository to the
+    /// cleanup job.
+    pub async fn cut_short(db: &Database, id: &Uuid, now: DateTime<Utc>) -> Result<()> {
+        sqlx::query("UPDATE trials SET expires_at = $2, updated_at = $2 WHERE id = $1")
+            .bind(id)
+            .bind(now)
+            .execute(db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Find active trials past their expiry at the given time, oldest first.
+    pub async fn find_expired(
+        db: &Database,
+        now: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<TrialModel>> {
+        let rows = sqlx::query_as::<_, TrialModel>(
+            r#"
+            SELECT t.*, c.slug AS course_slug, s.slug AS stage_slug
+            FROM trials t
+            JOIN courses c ON t.course_id = c.id
+            JOIN stages s ON t.stage_id = s.id
+            WHERE t.status = 'active' AND t.expires_at <= $1
+            ORDER BY t.expires_at
+            LIMIT $2
+            "#,
+        )
+        .bind(now)
+        .bind(limit)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Mark a trial whose repository was deleted as expired.
+    pub async fn expire(db: &Database, id: &Uuid) -> Result<()> {
+        sqlx::query(
+            "UPDATE trials SET status = 'expired', updated_at = NOW() WHERE id = $1 AND status = 'active'",
+        )
+        .bind(id)
+        .execute(db.pool())
+        .await?;
+
+        Ok(())
+    }
+}
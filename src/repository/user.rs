@@ -12,9 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
 use crate::{
-    database::Database,
-    model::{JsonWebKey, UserModel},
+    database::{Database, Transaction},
+    model::{JsonWebKey, MergeEnrollmentModel, UserModel},
     repository::Result,
 };
 
@@ -39,6 +42,145 @@ impl UserRepository {
         Ok(row)
     }
 
+    /// Fetch a user by their ID and lock it until the end of the transaction.
+    pub async fn get_for_update(tx: &mut Transaction<'_>, id: &str) -> Result<UserModel> {
+        let row = sqlx::query_as::<_, UserModel>("SELECT * FROM users WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_one(&mut **tx)
+            .await?;
+
+        Ok(row)
+    }
+
+    /// Fetch the ID of the user a user was merged into, if any.
+    pub async fn merged_into(db: &Database, id: &str) -> Result<Option<String>> {
+        let row: Option<Option<String>> =
+            sqlx::query_scalar("SELECT merged_into FROM users WHERE id = $1")
+                .bind(id)
+                .fetch_optional(db.pool())
+                .await?;
+
+        Ok(row.flatten())
+    }
+
+    /// Fetch the enrollments of the given users, with their progress.
+    pub async fn find_merge_enrollments(
+        tx: &mut Transaction<'_>,
+        user_ids: &[&str],
+    ) -> Result<Vec<MergeEnrollmentModel>> {
+        let rows = sqlx::query_as::<_, MergeEnrollmentModel>(
+            r#"
+            SELECT uc.id, uc.user_id, c.slug AS course_slug, uc.archived_at,
+                   COUNT(us.id) FILTER (WHERE us.status = 'completed') AS completed_stage_count
+            FROM user_courses uc
+            JOIN courses c ON uc.course_id = c.id
+            LEFT JOIN user_stages us ON us.user_course_id = uc.id
+            WHERE uc.user_id = ANY($1)
+            GROUP BY uc.id, c.slug
+            ORDER BY c.slug, uc.user_id
+            "#,
+        )
+        .bind(user_ids)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Archive an enrollment, handing it over to the given user. Checking
+    /// the uniqueness of enrollments is deferred to the end of the
+    /// transaction, so that conflicting enrollments may swap their owners.
+    pub async fn archive_enrollment(
+        tx: &mut Transaction<'_>,
+        id: &Uuid,
+        user_id: &str,
+    ) -> Result<()> {
+        sqlx::query("SET CONSTRAINTS unique_user_course DEFERRED").execute(&mut **tx).await?;
+        sqlx::query("UPDATE user_courses SET user_id = $2, archived_at = NOW() WHERE id = $1")
+            .bind(id)
+            .bind(user_id)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Move the active enrollments, the notification preferences and the
+    /// maintainer roles of a user to another.
+    pub async fn reassign(tx: &mut Transaction<'_>, source: &str, target: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE user_courses SET user_id = $2 WHERE user_id = $1 AND archived_at IS NULL",
+        )
+        .bind(source)
+        .bind(target)
+        .execute(&mut **tx)
+        .await?;
+
+        // Preferences the target already chose take precedence
+        sqlx::query(
+            r#"
+            INSERT INTO user_notification_preferences (
+                user_id, channel, destination, announcements, feedback,
+                deadline_reminders, accountability, created_at, updated_at
+            )
+            SELECT $2, channel, destination, announcements, feedback,
+                   deadline_reminders, accountability, created_at, NOW()
+            FROM user_notification_preferences WHERE user_id = $1
+            ON CONFLICT (user_id) DO NOTHING
+            "#,
+        )
+        .bind(source)
+        .bind(target)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO course_maintainers (course_id, user_id, added_by, created_at)
+            SELECT course_id, $2, added_by, created_at FROM course_maintainers WHERE user_id = $1
+            ON CONFLICT (course_id, user_id) DO NOTHING
+            "#,
+        )
+        .bind(source)
+        .bind(target)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query("DELETE FROM user_notification_preferences WHERE user_id = $1")
+            .bind(source)
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query("DELETE FROM course_maintainers WHERE user_id = $1")
+            .bind(source)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Mark a user, and the users merged into it before, as merged into
+    /// another.
+    pub async fn mark_merged(
+        tx: &mut Transaction<'_>,
+        source: &str,
+        target: &str,
+        merged_at: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE users SET merged_into = $2, merged_at = COALESCE(merged_at, $3)
+            WHERE id = $1 OR merged_into = $1
+            "#,
+        )
+        .bind(source)
+        .bind(target)
+        .bind(merged_at)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
     /// Fetch a user by their email.
     pub async fn get_by_email(db: &Database, email: &str) -> Result<UserModel> {
         let row = sqlx::query_as::<_, UserModel>(r#"SELECT * FROM users WHERE email = $1"#)
@@ -0,0 +1,190 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::schema::ResourceProfile;
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct ProgressQuery {
+    /// Aggregate progress from the user stages instead of the summary
+    #[serde(default)]
+    pub fresh: bool,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct AdminAttemptQuery {
+    /// Only return attempts of this user
+    pub user_id: Option<String>,
+
+    /// Only return attempts of this stage
+    pub stage_slug: Option<String>,
+
+    /// Only return attempts graded against this stage content hash
+    pub content_hash: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct IntegrityFlagQuery {
+    /// Only return flags of this user
+    pub user_id: Option<String>,
+
+    /// Only return flags raised by this rule
+    pub rule: Option<String>,
+
+    /// Include flags that were already dismissed
+    #[serde(default)]
+    pub dismissed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DismissFlagRequest {
+    /// Why the flag needs no further action
+    pub note: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MigrateRepositoriesRequest {
+    /// Base URL of the source Gitea instance
+    pub source_url: String,
+
+    /// Username to authenticate against the source
+    pub username: Option<String>,
+
+    /// Password to authenticate against the source
+    pub password: Option<String>,
+
+    /// Access token to authenticate against the source
+    pub token: Option<String>,
+
+    /// Source repositories ("owner/repo") mapped to the ID of the user
+    /// course they belong to
+    pub repositories: BTreeMap<String, Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MaintenanceRequest {
+    /// Whether writes are refused
+    pub enabled: bool,
+
+    /// Message shown to the clients whose writes are refused
+    pub message: Option<String>,
+
+    /// Timestamp the maintenance ends at on its own
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// Refuse webhook deliveries too, instead of accepting them and queueing
+    /// the attempts of pushes until the maintenance ends
+    #[serde(default)]
+    pub reject_webhooks: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PreprovisionRequest {
+    /// Number of spare repositories the pool of the course should hold
+    pub count: u32,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct RepoMigrationQuery {
+    /// Only list migrations with this status (pending/running/migrated/failed)
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct AuditLogQuery {
+    /// Only list entries of this actor (user ID or "admin")
+    pub actor: Option<String>,
+
+    /// Only list entries of this action
+    pub action: Option<String>,
+
+    /// Only list entries whose target starts with this prefix
+    pub target: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExamWindowRequest {
+    /// Start of the window, enrollments open at this time
+    pub opens_at: Option<DateTime<Utc>>,
+
+    /// End of the window, later attempts do not count toward completion
+    pub closes_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExtendDeadlineRequest {
+    /// New deadline of the learner, after the end of the window
+    pub deadline: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MergeUsersRequest {
+    /// ID of the duplicate account to merge away
+    pub source_user_id: String,
+
+    /// ID of the account to keep
+    pub target_user_id: String,
+}
+
+/// What API tokens of integrations may read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiTokenCapability {
+    /// The progress of the learners
+    Progress,
+
+    /// The engagement and progress statistics of the course
+    Stats,
+}
+
+impl ApiTokenCapability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Progress => "progress",
+            Self::Stats => "stats",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateApiTokenRequest {
+    /// Name telling what the token is used for, e.g. the LMS instance
+    pub name: String,
+
+    /// Slugs of the courses the token may read
+    pub courses: Vec<String>,
+
+    /// What the token may read of these courses
+    pub capabilities: Vec<ApiTokenCapability>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CourseSettingsRequest {
+    /// Maximum number of graded attempts per stage
+    pub max_attempts: Option<u32>,
+
+    /// Resource profile of the test pods (small, medium, large)
+    #[schema(value_type = Option<String>)]
+    pub resources: Option<ResourceProfile>,
+
+    /// Tester image grading the course, `{course}` stands for its slug
+    pub tester_image: Option<String>,
+}
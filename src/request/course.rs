@@ -12,13 +12,74 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::schema::Status;
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct CourseQuery {
+    /// Number of the page, starting at 1
+    pub page: Option<i64>,
+
+    /// Number of courses per page, at most 200, all courses when unset
+    pub per_page: Option<i64>,
+
+    /// Only list courses of this release status, beta or live
+    #[param(value_type = Option<String>)]
+    pub release_status: Option<Status>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct CourseAttemptQuery {
+    /// Number of the page, starting at 1
+    pub page: Option<i64>,
+
+    /// Number of attempts per page, 50 by default and at most 200
+    pub per_page: Option<i64>,
+
+    /// Only list the attempts of this user
+    pub user_id: Option<String>,
+
+    /// Only list the attempts started at or after this time
+    pub since: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CourseSearchQuery {
+    /// Keywords to search the name, summary and description for, between 2
+    /// and 100 characters
+    pub q: String,
+
+    /// Maximum number of courses, 50 by default and at most 200
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct DeleteCourseQuery {
+    /// Delete the course even when some of its repositories could not be
+    /// deleted, leaving them behind
+    #[serde(default)]
+    pub force: bool,
+}
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateCourseRequest {
     /// The git repository URL of the course
     pub repository: String,
+
+    /// Branch, tag or commit SHA to pin the course to, the default branch is
+    /// followed without one
+    pub reference: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct UpdateCourseRequest {
+    /// Branch, tag or commit SHA to pin the course to, an empty one lets the
+    /// course follow the default branch again. The current pin is kept
+    /// without one.
+    pub reference: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -34,6 +95,19 @@ pub struct CreateUserCourseRequest {
 
     /// Whether the user wants accountability emails
     pub accountability: bool,
+
+    /// Instructor-led cohort to enroll with
+    #[serde(default)]
+    pub cohort: Option<String>,
+
+    /// Deadline of the cohort, which requires a cohort
+    #[serde(default)]
+    pub cohort_deadline: Option<DateTime<Utc>>,
+
+    /// Programming language to take the course in, the primary language of
+    /// the course without one
+    #[serde(default)]
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -46,4 +120,80 @@ pub struct UpdateUserCourseRequest {
 
     /// Whether the user wants accountability emails
     pub accountability: bool,
+
+    /// Instructor-led cohort to move to, an empty one leaves the cohort
+    /// along with its deadline. The current cohort is kept without one.
+    #[serde(default)]
+    pub cohort: Option<String>,
+
+    /// Deadline of the cohort, the current one is kept without one
+    #[serde(default)]
+    pub cohort_deadline: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct ResetUserCourseRequest {
+    /// Whether to replace the repository with a fresh one from the course
+    /// template as well
+    #[serde(default)]
+    pub reset_repository: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct VerifyGitIdentityRequest {
+    /// The slug of the enrolled course
+    pub course_slug: String,
+}
+
+/// Engagement event types the frontend may report, anything else is rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EngagementEventType {
+    InstructionViewed,
+    HintOpened,
+    SolutionViewed,
+    CopyCloneUrl,
+}
+
+impl EngagementEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::InstructionViewed => "instruction_viewed",
+            Self::HintOpened => "hint_opened",
+            Self::SolutionViewed => "solution_viewed",
+            Self::CopyCloneUrl => "copy_clone_url",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateEngagementEventRequest {
+    /// Type of the event
+    #[serde(rename = "type")]
+    pub kind: EngagementEventType,
+
+    /// The slug of the stage the event relates to
+    pub stage_slug: String,
+
+    /// When the event occurred, as reported by the client
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AddMaintainerRequest {
+    /// The id of the user to grant the maintainer role
+    pub user_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateRegistryRequest {
+    /// Storage quota of the course's registry project in bytes, -1 for
+    /// unlimited
+    pub storage_limit: i64,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct MetaQuery {
+    /// Token granting previews of an unreleased course
+    pub preview: Option<String>,
 }
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PipelineEvent {
@@ -31,11 +32,35 @@ pub struct PipelineEvent {
     /// Current course stage identifier
     pub stage: String,
 
+    /// Commit SHA of the course the run was graded against
+    #[serde(default)]
+    pub commit: String,
+
+    /// Content hash of the stage the run was graded against
+    #[serde(default)]
+    pub content_hash: String,
+
+    /// Commit SHA of the repository the run graded
+    #[serde(default)]
+    pub repo_commit: String,
+
+    /// Random value the run was triggered with, signed into the secret
+    #[serde(default)]
+    pub nonce: String,
+
     /// Secret token for authentication
     pub secret: String,
 
     /// Status of the tasks in the pipeline run
     pub tasks: Tasks,
+
+    /// Output of the tester, truncated to its tail when stored
+    #[serde(default)]
+    pub log: String,
+
+    /// Results of the test cases the tester ran
+    #[serde(default)]
+    pub results: Vec<TestCaseResult>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -51,3 +76,73 @@ pub struct TaskStatus {
     /// Reason for the task status
     pub reason: String,
 }
+
+/// Result of a single test case, as reported by the tester.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct TestCaseResult {
+    /// Slug of the stage the test case belongs to
+    pub slug: String,
+
+    /// Whether the test case passed
+    pub passed: bool,
+
+    /// Why the test case failed, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Event a Harbor webhook policy delivers, in the default payload format.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HarborEvent {
+    /// Type of the event, e.g. "PUSH_ARTIFACT"
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// Time the event occurred at, in seconds since epoch
+    pub occur_at: i64,
+
+    /// Name of the account which caused the event
+    #[serde(default)]
+    pub operator: String,
+
+    /// Artifacts and repository the event is about
+    pub event_data: HarborEventData,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HarborEventData {
+    /// Artifacts the event is about
+    #[serde(default)]
+    pub resources: Vec<HarborResource>,
+
+    /// Repository holding the artifacts
+    pub repository: HarborRepository,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HarborResource {
+    /// Digest of the artifact
+    #[serde(default)]
+    pub digest: String,
+
+    /// Tag of the artifact, if pushed by tag
+    #[serde(default)]
+    pub tag: String,
+
+    /// Full reference of the artifact, e.g. "registry/project/repo:tag"
+    #[serde(default)]
+    pub resource_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HarborRepository {
+    /// Name of the repository, without the project
+    pub name: String,
+
+    /// Project of the repository
+    pub namespace: String,
+
+    /// Name of the repository, with the project
+    #[serde(default)]
+    pub repo_full_name: String,
+}
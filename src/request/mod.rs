@@ -12,10 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod admin;
 mod course;
 pub mod event;
+mod notification;
+mod page;
 mod stage;
+mod trial;
 
 // Re-exports
+pub use admin::*;
 pub use course::*;
+pub use notification::*;
+pub use page::*;
 pub use stage::*;
+pub use trial::*;
@@ -0,0 +1,56 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Channels notifications can be delivered through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationChannel {
+    Email,
+    Webhook,
+}
+
+impl NotificationChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Email => "email",
+            Self::Webhook => "webhook",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateNotificationPreferencesRequest {
+    /// Channel notifications are delivered through
+    pub channel: NotificationChannel,
+
+    /// HTTPS URL of the webhook, required for the webhook channel. For the
+    /// email channel, an address replacing the one of the account
+    pub destination: Option<String>,
+
+    /// Whether course announcements are sent
+    pub announcements: bool,
+
+    /// Whether feedback comments on attempts are sent
+    pub feedback: bool,
+
+    /// Whether reminders of approaching deadlines are sent
+    pub deadline_reminders: bool,
+
+    /// Whether accountability emails are sent for the courses they were
+    /// enabled on
+    pub accountability: bool,
+}
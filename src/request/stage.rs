@@ -13,10 +13,22 @@
 // limitations under the License.
 
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CompleteStageRequest {
     /// The slug of the stage to mark as completed
     pub slug: String,
 }
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GrantAttemptsRequest {
+    /// Number of extra attempts to grant
+    pub count: i32,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct AttemptQuery {
+    /// Only return attempts graded against this stage content hash
+    pub content_hash: Option<String>,
+}
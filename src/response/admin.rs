@@ -0,0 +1,550 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use gitea_client::types::Repository;
+use harbor_client::types::GcJob;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::{ToSchema, openapi::OpenApi};
+use uuid::Uuid;
+
+use crate::{
+    jobs::JobStatus,
+    model::{
+        ApiTokenModel, AuditLogModel, IntegrityFlagModel, MaintenanceMode, RegistryCredentialModel,
+        RepoMigrationModel,
+    },
+    router::Route,
+    schema::ResourceProfile,
+    utils::{resources::PodResources, stream::StreamTracker},
+};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AdminSummaryResponse {
+    /// Open status streams
+    pub streams: StreamSummary,
+
+    /// Read-only maintenance mode
+    pub maintenance: MaintenanceResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MaintenanceResponse {
+    /// Whether writes are refused
+    pub enabled: bool,
+
+    /// Message shown to the clients whose writes are refused
+    pub message: Option<String>,
+
+    /// Whether webhook deliveries are refused too
+    pub reject_webhooks: bool,
+
+    /// Timestamp the maintenance started at
+    pub started_at: Option<DateTime<Utc>>,
+
+    /// Timestamp the maintenance ends at on its own
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<Option<MaintenanceMode>> for MaintenanceResponse {
+    fn from(mode: Option<MaintenanceMode>) -> Self {
+        match mode {
+            Some(mode) => Self {
+                enabled: true,
+                message: mode.message,
+                reject_webhooks: mode.reject_webhooks,
+                started_at: Some(mode.started_at),
+                expires_at: mode.expires_at,
+            },
+            None => Self {
+                enabled: false,
+                message: None,
+                reject_webhooks: false,
+                started_at: None,
+                expires_at: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReadinessResponse {
+    /// Whether the server takes requests (ready, read_only)
+    pub status: String,
+
+    /// Read-only maintenance mode
+    pub maintenance: MaintenanceResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StreamSummary {
+    /// Number of streams currently open
+    pub total: usize,
+
+    /// Number of users with at least one open stream
+    pub users: usize,
+
+    /// Maximum number of concurrent streams per user
+    pub max_per_user: usize,
+
+    /// Maximum number of concurrent streams across all users
+    pub max_total: usize,
+}
+
+impl From<&StreamTracker> for StreamSummary {
+    fn from(tracker: &StreamTracker) -> Self {
+        Self {
+            total: tracker.total(),
+            users: tracker.users(),
+            max_per_user: tracker.max_per_user(),
+            max_total: tracker.max_total(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct JobResponse {
+    /// Unique name of the job
+    pub name: String,
+
+    /// Whether the job is scheduled at all
+    pub enabled: bool,
+
+    /// Whether a run is in progress
+    pub running: bool,
+
+    /// Interval between runs in seconds, if periodic
+    pub interval_secs: Option<u64>,
+
+    /// Start of the latest run
+    pub last_started_at: Option<DateTime<Utc>>,
+
+    /// Duration of the latest finished run in milliseconds
+    pub last_duration_ms: Option<u64>,
+
+    /// Outcome or error of the latest finished run
+    pub last_outcome: Option<String>,
+
+    /// Whether the latest finished run failed
+    pub last_failed: bool,
+
+    /// Number of finished runs
+    pub runs: u64,
+
+    /// Number of failed runs
+    pub failures: u64,
+}
+
+impl From<(&str, JobStatus)> for JobResponse {
+    fn from((name, status): (&str, JobStatus)) -> Self {
+        Self {
+            name: name.to_string(),
+            enabled: status.enabled,
+            running: status.running,
+            interval_secs: status.interval.map(|interval| interval.as_secs()),
+            last_started_at: status.last_started_at,
+            last_duration_ms: status.last_duration.map(|d| d.as_millis() as u64),
+            last_outcome: status.last_outcome,
+            last_failed: status.last_failed,
+            runs: status.runs,
+            failures: status.failures,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct IntegrityFlagResponse {
+    /// Unique identifier of the flag
+    pub id: Uuid,
+
+    /// ID of the flagged learner
+    pub user_id: String,
+
+    /// Slug of the completed stage
+    pub stage_slug: String,
+
+    /// Name of the rule that raised the flag
+    pub rule: String,
+
+    /// Feature values of the completion when it was flagged
+    pub features: Value,
+
+    /// Timestamp when the flag was dismissed
+    pub dismissed_at: Option<DateTime<Utc>>,
+
+    /// Who dismissed the flag
+    pub dismissed_by: Option<String>,
+
+    /// Note left when dismissing the flag
+    pub note: Option<String>,
+
+    /// Timestamp when the flag was raised
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<IntegrityFlagModel> for IntegrityFlagResponse {
+    fn from(model: IntegrityFlagModel) -> Self {
+        Self {
+            id: model.id,
+            user_id: model.user_id,
+            stage_slug: model.stage_slug,
+            rule: model.rule,
+            features: model.features,
+            dismissed_at: model.dismissed_at,
+            dismissed_by: model.dismissed_by,
+            note: model.note,
+            created_at: model.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RegistryCredentialResponse {
+    /// Harbor project the pipelines of the course push to
+    pub project: String,
+
+    /// Name of the robot account
+    pub robot_name: String,
+
+    /// Name of the Kubernetes Secret referenced by new pipelines
+    pub secret_name: String,
+
+    /// Timestamp when the credentials were created
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<RegistryCredentialModel> for RegistryCredentialResponse {
+    fn from(model: RegistryCredentialModel) -> Self {
+        Self {
+            project: model.project,
+            robot_name: model.robot_name,
+            secret_name: model.secret_name,
+            created_at: model.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GarbageCollectionResponse {
+    /// ID of the garbage collection job, to poll its status with
+    pub id: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GarbageCollectionStatusResponse {
+    /// ID of the garbage collection job
+    pub id: i64,
+
+    /// Status of the job, e.g. "Pending", "Running", "Success" or "Error"
+    pub status: Option<String>,
+
+    /// Timestamp when the job was created
+    pub created_at: Option<DateTime<Utc>>,
+
+    /// Timestamp when the job was last updated
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl From<GcJob> for GarbageCollectionStatusResponse {
+    fn from(job: GcJob) -> Self {
+        Self {
+            id: job.id,
+            status: job.job_status,
+            created_at: job.creation_time,
+            updated_at: job.update_time,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MigrateRepositoriesResponse {
+    /// Number of repositories queued for migration
+    pub queued: usize,
+
+    /// Source repositories skipped as they were already migrated
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PreprovisionResponse {
+    /// Number of repositories generated into the pool by this request
+    pub created: usize,
+
+    /// Number of repositories available in the pool now
+    pub available: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RepoMigrationResponse {
+    /// Unique identifier of the migration
+    pub id: Uuid,
+
+    /// ID of the user course the repository belongs to
+    pub user_course_id: Uuid,
+
+    /// Base URL of the source Gitea instance
+    pub source_url: String,
+
+    /// Full name of the source repository
+    pub source_repo: String,
+
+    /// Migration status (pending/running/migrated/failed)
+    pub status: String,
+
+    /// Why the latest migration failed
+    pub error: Option<String>,
+
+    /// Timestamp of the latest status change
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<RepoMigrationModel> for RepoMigrationResponse {
+    fn from(model: RepoMigrationModel) -> Self {
+        Self {
+            id: model.id,
+            user_course_id: model.user_course_id,
+            source_url: model.source_url,
+            source_repo: model.source_repo,
+            status: model.status,
+            error: model.error,
+            updated_at: model.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RepoMigrationReportResponse {
+    /// Number of migrations waiting to run
+    pub pending: usize,
+
+    /// Number of migrations in progress
+    pub running: usize,
+
+    /// Number of repositories migrated
+    pub migrated: usize,
+
+    /// Number of failed migrations
+    pub failed: usize,
+
+    /// The listed migrations
+    pub repositories: Vec<RepoMigrationResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OrphanedRepositoryResponse {
+    /// Name of the repository
+    pub name: String,
+
+    /// Web URL of the repository
+    pub html_url: String,
+
+    /// Whether the repository has no commits
+    pub empty: bool,
+
+    /// Timestamp when the repository was created
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Repository> for OrphanedRepositoryResponse {
+    fn from(repo: Repository) -> Self {
+        Self {
+            name: repo.name,
+            html_url: repo.html_url,
+            empty: repo.empty,
+            created_at: repo.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ResourceProfileResponse {
+    /// Name of the profile stages declare in `resources`
+    pub name: String,
+
+    /// Whether stages without a declared profile get this one
+    pub default: bool,
+
+    /// Resources requested for the test pod
+    pub requests: ResourceQuantities,
+
+    /// Resource limits of the test pod
+    pub limits: ResourceQuantities,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ResourceQuantities {
+    /// CPU quantity, e.g. `500m`
+    pub cpu: String,
+
+    /// Memory quantity, e.g. `512Mi`
+    pub memory: String,
+}
+
+impl From<(ResourceProfile, &PodResources)> for ResourceProfileResponse {
+    fn from((profile, resources): (ResourceProfile, &PodResources)) -> Self {
+        Self {
+            name: profile.to_string(),
+            default: profile == ResourceProfile::default(),
+            requests: ResourceQuantities {
+                cpu: resources.cpu_request.clone(),
+                memory: resources.memory_request.clone(),
+            },
+            limits: ResourceQuantities {
+                cpu: resources.cpu_limit.clone(),
+                memory: resources.memory_limit.clone(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AuditLogResponse {
+    /// Unique identifier of the entry
+    pub id: Uuid,
+
+    /// Who performed the action (user ID or "admin")
+    pub actor: String,
+
+    /// The action performed
+    pub action: String,
+
+    /// Path-like identifier of the affected resource
+    pub target: String,
+
+    /// Action specific details
+    pub details: Value,
+
+    /// When the action was performed
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<AuditLogModel> for AuditLogResponse {
+    fn from(model: AuditLogModel) -> Self {
+        Self {
+            id: model.id,
+            actor: model.actor,
+            action: model.action,
+            target: model.target,
+            details: model.details,
+            created_at: model.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MergeUsersResponse {
+    /// ID of the merged-away account
+    pub source_user_id: String,
+
+    /// ID of the account that was kept
+    pub target_user_id: String,
+
+    /// When the accounts were merged
+    pub merged_at: DateTime<Utc>,
+
+    /// Slugs of the courses whose enrollment was moved by this request,
+    /// empty when the accounts were already merged
+    pub moved: Vec<String>,
+
+    /// Enrollments that lost a conflict and were archived
+    pub archived: Vec<ArchivedEnrollmentResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ArchivedEnrollmentResponse {
+    /// Slug of the course
+    pub course_slug: String,
+
+    /// ID of the archived user course
+    pub user_course_id: Uuid,
+
+    /// New name of the repository of the archived user course
+    pub repository: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiTokenResponse {
+    /// ID of the token, recorded in the audit trail of its requests
+    pub id: Uuid,
+
+    /// Name telling what the token is used for
+    pub name: String,
+
+    /// Slugs of the courses the token may read
+    pub courses: Vec<String>,
+
+    /// What the token may read (progress, stats)
+    pub capabilities: Vec<String>,
+
+    /// Who created the token
+    pub created_by: String,
+
+    /// Timestamp when the token was created
+    pub created_at: DateTime<Utc>,
+
+    /// Timestamp when the token last authenticated a request
+    pub last_used_at: Option<DateTime<Utc>>,
+
+    /// Timestamp when the token was revoked
+    pub revoked_at: Option<DateTime<Utc>>,
+
+    /// The secret to send as `Authorization: Token <secret>`, only returned
+    /// when the token is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl From<ApiTokenModel> for ApiTokenResponse {
+    fn from(model: ApiTokenModel) -> Self {
+        Self {
+            id: model.id,
+            name: model.name,
+            courses: model.courses,
+            capabilities: model.capabilities,
+            created_by: model.created_by,
+            created_at: model.created_at,
+            last_used_at: model.last_used_at,
+            revoked_at: model.revoked_at,
+            token: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RouteResponse {
+    /// HTTP method of the route, `ANY` if it takes any
+    pub method: String,
+
+    /// Path of the route
+    pub path: String,
+
+    /// Authentication the route requires (public, jwt, instructor,
+    /// instructor_or_token, admin_basic)
+    pub auth: String,
+
+    /// Whether the OpenAPI document describes the route
+    pub documented: bool,
+}
+
+impl RouteResponse {
+    pub fn new(route: &Route, doc: &OpenApi) -> Self {
+        Self {
+            method: route.method().to_string(),
+            path: route.path().to_string(),
+            auth: route.auth().to_string(),
+            documented: route.documented(doc),
+        }
+    }
+}
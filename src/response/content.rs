@@ -0,0 +1,106 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use axum::{
+    Json,
+    http::{
+        StatusCode,
+        header::{ACCEPT, ACCEPT_RANGES, CONTENT_RANGE, CONTENT_TYPE, ETAG, VARY},
+    },
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::{
+    extractor::{Accept, MediaType},
+    utils::{markdown, range::ByteRange},
+};
+
+/// A content response negotiated on the `Accept` header.
+///
+/// JSON clients get the serialized `json` value, the other supported media
+/// types get the `markdown` document as-is, rendered to sanitized HTML or
+/// stripped down to plain text.
+pub struct Negotiated<T> {
+    media: MediaType,
+    json: T,
+    markdown: String,
+}
+
+impl<T> Negotiated<T> {
+    pub fn new(Accept(media): Accept, json: T, markdown: impl Into<String>) -> Self {
+        Self { media, json, markdown: markdown.into() }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Negotiated<T> {
+    fn into_response(self) -> Response {
+        let vary = (VARY, ACCEPT.as_str());
+        let body = match self.media {
+            MediaType::Json => return ([vary], Json(self.json)).into_response(),
+            MediaType::Html => markdown::to_html(&self.markdown),
+            MediaType::Markdown => self.markdown,
+            MediaType::Text => markdown::to_text(&self.markdown),
+        };
+        ([(CONTENT_TYPE, self.media.as_str()), vary], body).into_response()
+    }
+}
+
+/// A course asset, or the requested part of it.
+pub struct AssetContent {
+    /// Media type of the asset
+    pub content_type: &'static str,
+
+    /// Hex encoded SHA-256 hash of the whole asset, used as entity tag
+    pub sha256: String,
+
+    /// Size of the whole asset in bytes
+    pub size: u64,
+
+    /// The served content
+    pub body: AssetBody,
+}
+
+pub enum AssetBody {
+    /// The whole asset
+    Full(Vec<u8>),
+
+    /// The requested range of the asset
+    Partial(ByteRange, Vec<u8>),
+
+    /// The requested range lies outside of the asset
+    Unsatisfiable,
+}
+
+impl IntoResponse for AssetContent {
+    fn into_response(self) -> Response {
+        let headers = [
+            (ACCEPT_RANGES, "bytes".to_string()),
+            (ETAG, format!("\"{}\"", self.sha256)),
+            (CONTENT_TYPE, self.content_type.to_string()),
+        ];
+
+        match self.body {
+            AssetBody::Full(body) => (headers, body).into_response(),
+            AssetBody::Partial(range, body) => {
+                let content_range = (CONTENT_RANGE, range.content_range(self.size));
+                (StatusCode::PARTIAL_CONTENT, headers, [content_range], body).into_response()
+            }
+            AssetBody::Unsatisfiable => {
+                let content_range = (CONTENT_RANGE, format!("bytes */{}", self.size));
+                (StatusCode::RANGE_NOT_SATISFIABLE, [content_range]).into_response()
+            }
+        }
+    }
+}
@@ -12,11 +12,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
+use harbor_client::types::ProjectSummary;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use utoipa::ToSchema;
-
-use crate::model::{CourseModel, UserCourseModel};
+use uuid::Uuid;
+
+use crate::{
+    model::{
+        CourseJobModel, CourseMaintainerModel, CourseModel, CourseRevisionModel, CourseStatsModel,
+        ExtensionModel, StageModel, StageStatsModel, UserCourseModel,
+    },
+    schema::{CourseSettings, EffectiveSettings},
+    service::IDENTITY_FILE,
+    utils::endpoints::Endpoints,
+};
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CourseResponse {
@@ -90,11 +103,31 @@ pub struct CourseDetailResponse {
     /// Number of stages in the course
     pub stage_count: i32,
 
+    /// Commit SHA of the course repository the course is live at
+    pub commit: String,
+
+    /// Branch, tag or commit SHA the course is pinned to, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+
+    /// Start of the exam window, if the course runs as an exam
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opens_at: Option<DateTime<Utc>>,
+
+    /// End of the exam window, if the course runs as an exam
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closes_at: Option<DateTime<Utc>>,
+
     /// Creation timestamp
     pub created_at: DateTime<Utc>,
 
     /// Last update timestamp
     pub updated_at: DateTime<Utc>,
+
+    /// Whether the current user maintains the course, only present for
+    /// maintainers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maintainer: Option<bool>,
 }
 
 impl From<CourseModel> for CourseDetailResponse {
@@ -108,12 +141,41 @@ impl From<CourseModel> for CourseDetailResponse {
             summary: model.summary,
             logo: model.logo,
             stage_count: model.stage_count,
+            commit: model.commit_sha,
+            reference: model.reference,
+            opens_at: model.opens_at,
+            closes_at: model.closes_at,
             created_at: model.created_at,
             updated_at: model.updated_at,
+            maintainer: None,
         }
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MaintainerResponse {
+    /// The id of the maintainer
+    pub user_id: String,
+
+    /// Who granted the maintainer role
+    pub added_by: String,
+
+    /// Timestamp when the role was granted
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<CourseMaintainerModel> for MaintainerResponse {
+    fn from(model: CourseMaintainerModel) -> Self {
+        Self { user_id: model.user_id, added_by: model.added_by, created_at: model.created_at }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PreviewTokenResponse {
+    /// Token granting link previews of the unreleased course
+    pub token: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct UserCourseResponse {
     /// Slug of the enrolled course
@@ -142,10 +204,73 @@ pub struct UserCourseResponse {
 
     /// The git repository URL of the user course
     pub repository: String,
+
+    /// Enrollment status (awaiting_first_push/awaiting_identity_verification/active)
+    pub status: String,
+
+    /// Repository status (ready/migrating/failed)
+    pub repository_status: String,
+
+    /// Start of the exam window, if the course runs as an exam
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opens_at: Option<DateTime<Utc>>,
+
+    /// End of the exam window, if the course runs as an exam
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closes_at: Option<DateTime<Utc>>,
+
+    /// Deadline of the learner, later than the end of the exam window when
+    /// an extension was granted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<DateTime<Utc>>,
+
+    /// Seconds left until the deadline, negative once it passed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_seconds: Option<i64>,
+
+    /// Whether any stage was completed after the end of the exam window
+    pub completed_late: bool,
+
+    /// Instructor-led cohort the learner enrolled with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cohort: Option<String>,
+
+    /// Deadline of the cohort of the learner
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cohort_deadline: Option<DateTime<Utc>>,
+
+    /// Programming language the learner takes the course in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+
+    /// Instructions for the learner while the enrollment is on hold
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+
+    /// Timestamp of the latest commit of the repository, unknown when the
+    /// git server could not be asked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_pushed_at: Option<DateTime<Utc>>,
+
+    /// Message of the latest commit of the repository
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_commit_message: Option<String>,
 }
 
-impl<T: ToString> From<(UserCourseModel, T)> for UserCourseResponse {
-    fn from((model, repository): (UserCourseModel, T)) -> Self {
+impl From<(UserCourseModel, &Endpoints, &str)> for UserCourseResponse {
+    fn from((model, endpoints, branch): (UserCourseModel, &Endpoints, &str)) -> Self {
+        let (status, instructions) = if model.awaiting_identity_verification() {
+            let instructions = format!(
+                "Request a token via POST /v1/user/verify-git-identity, commit it to {IDENTITY_FILE} and push to the {branch} branch."
+            );
+            ("awaiting_identity_verification", Some(instructions))
+        } else if model.activated {
+            ("active", None)
+        } else {
+            ("awaiting_first_push", None)
+        };
+        let deadline = model.deadline();
+
         Self {
             course_slug: model.course_slug,
             started_at: model.started_at,
@@ -155,7 +280,506 @@ impl<T: ToString> From<(UserCourseModel, T)> for UserCourseResponse {
             cadence: model.cadence,
             accountability: model.accountability,
             activated: model.activated,
-            repository: repository.to_string(),
+            repository: endpoints.user_repo_url(&model.id),
+            status: status.to_string(),
+            repository_status: model.repository_status,
+            opens_at: model.opens_at,
+            closes_at: model.closes_at,
+            deadline,
+            remaining_seconds: model.remaining_secs,
+            completed_late: model.completed_late,
+            cohort: model.cohort,
+            cohort_deadline: model.cohort_deadline,
+            language: model.language,
+            instructions,
+            last_pushed_at: None,
+            last_commit_message: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GitIdentityVerificationResponse {
+    /// The token to commit to the repository
+    pub token: String,
+
+    /// Path of the file the token must be written to
+    pub path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CourseSourceResponse {
+    /// Commit SHA the course was imported at
+    pub commit: String,
+
+    /// Raw content of course.yml
+    pub course_yml: String,
+
+    /// Front matter of course.yml as parsed by the importer
+    pub course: Value,
+
+    /// Raw content of extensions.yml, if the course has extensions
+    pub extensions_yml: Option<String>,
+
+    /// Stages as parsed by the importer
+    pub stages: Vec<StageSourceSummary>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StageSourceSummary {
+    /// Unique human-readable identifier of the stage
+    pub slug: String,
+
+    /// Slug of the extension the stage belongs to, if any
+    pub extension: Option<String>,
+
+    /// Directory of the stage relative to the repository root
+    pub path: String,
+
+    /// Front matter of stage.yml as parsed by the importer
+    pub front_matter: Value,
+
+    /// Hash of the stage content as computed on import
+    pub content_hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StageSourceResponse {
+    /// Unique human-readable identifier of the stage
+    pub slug: String,
+
+    /// Directory of the stage relative to the repository root
+    pub path: String,
+
+    /// Raw content of stage.yml
+    pub stage_yml: String,
+
+    /// Raw content of instruction.md
+    pub instruction_md: String,
+
+    /// Raw content of solution.md, if available
+    pub solution_md: Option<String>,
+
+    /// Hash of the stage content as computed on import
+    pub content_hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CourseSettingsResponse {
+    /// Maximum number of graded attempts per stage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+
+    /// Resource profile of the test pods
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resources: Option<String>,
+
+    /// Tester image grading the course, `{course}` stands for its slug
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tester_image: Option<String>,
+}
+
+impl From<CourseSettings> for CourseSettingsResponse {
+    fn from(settings: CourseSettings) -> Self {
+        Self {
+            max_attempts: settings.max_attempts,
+            resources: settings.resources.map(|profile| profile.to_string()),
+            tester_image: settings.tester_image,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct EffectiveSettingsResponse {
+    /// Maximum number of graded attempts per stage, null if unlimited
+    pub max_attempts: Option<u32>,
+
+    /// Resource profile of the test pods of stages which declare none
+    pub resources: String,
+
+    /// Reference of the tester image
+    pub tester_image: String,
+
+    /// Instance defaults, applied where the course sets nothing
+    pub defaults: CourseSettingsResponse,
+
+    /// Values declared in course.yml
+    pub course: CourseSettingsResponse,
+
+    /// Values overridden by admins, over those of course.yml
+    pub overrides: CourseSettingsResponse,
+}
+
+impl EffectiveSettingsResponse {
+    pub fn new(
+        effective: EffectiveSettings,
+        defaults: CourseSettings,
+        course: CourseSettings,
+        overrides: CourseSettings,
+    ) -> Self {
+        Self {
+            max_attempts: effective.max_attempts,
+            resources: effective.resources.to_string(),
+            tester_image: effective.tester_image,
+            defaults: defaults.into(),
+            course: course.into(),
+            overrides: overrides.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RegistrySummaryResponse {
+    /// Harbor project holding the images of the course
+    pub project: String,
+
+    /// Number of image repositories in the project
+    pub repo_count: i64,
+
+    /// Storage used by the images in bytes
+    pub storage_used: i64,
+
+    /// Storage quota in bytes, -1 for unlimited
+    pub storage_limit: i64,
+}
+
+impl RegistrySummaryResponse {
+    /// Summarizes a project, whose storage is unlimited without a quota.
+    pub fn new(project: String, summary: ProjectSummary) -> Self {
+        let quota = summary.quota.as_ref();
+        Self {
+            project,
+            repo_count: summary.repo_count,
+            storage_used: quota.map_or(0, |quota| quota.used.storage),
+            storage_limit: quota.map_or(-1, |quota| quota.hard.storage),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CourseJobResponse {
+    /// Unique identifier of the job, to follow its progress with
+    pub job_id: Uuid,
+
+    /// The git repository URL the course is created from
+    pub repository: String,
+
+    /// Branch, tag or commit SHA the course is created from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+
+    /// Job status (pending/fetching/parsing/pushing_template/completed/failed)
+    pub status: String,
+
+    /// Slug of the course, once the job completed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub course_slug: Option<String>,
+
+    /// Why the job failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Timestamp the job was queued at
+    pub created_at: DateTime<Utc>,
+
+    /// Timestamp of the latest status change
+    pub updated_at: DateTime<Utc>,
+}
+
+impl CourseJobResponse {
+    /// Whether the job has finished, successfully or not
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status.as_str(), "completed" | "failed")
+    }
+}
+
+impl From<CourseJobModel> for CourseJobResponse {
+    fn from(model: CourseJobModel) -> Self {
+        Self {
+            job_id: model.id,
+            repository: model.repository,
+            reference: model.reference,
+            status: model.status,
+            course_slug: model.course_slug,
+            error: model.error,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CourseStatsResponse {
+    /// Number of learners enrolled in the course
+    pub enrolled_count: i64,
+
+    /// Number of enrollments activated
+    pub activated_count: i64,
+
+    /// Number of learners who completed every base stage
+    pub completed_count: i64,
+
+    /// Average number of stages completed per learner
+    pub average_completed_stages: f64,
+
+    /// How far the learners got with each stage, in course order
+    pub stages: Vec<StageStatsResponse>,
+}
+
+impl CourseStatsResponse {
+    pub fn new(course: CourseStatsModel, stages: Vec<StageStatsModel>) -> Self {
+        Self {
+            enrolled_count: course.enrolled_count,
+            activated_count: course.activated_count,
+            completed_count: course.completed_count,
+            average_completed_stages: course.average_completed_stages,
+            stages: stages.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StageStatsResponse {
+    /// Slug of the stage
+    pub stage_slug: String,
+
+    /// Slug of the extension of the stage (null if part of main course)
+    pub extension_slug: Option<String>,
+
+    /// Number of learners who reached the stage
+    pub reached_count: i64,
+
+    /// Number of learners who completed the stage
+    pub completed_count: i64,
+}
+
+impl From<StageStatsModel> for StageStatsResponse {
+    fn from(model: StageStatsModel) -> Self {
+        Self {
+            stage_slug: model.stage_slug,
+            extension_slug: model.extension_slug,
+            reached_count: model.reached_count,
+            completed_count: model.completed_count,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CourseExportResponse {
+    /// Unique human-readable identifier
+    pub slug: String,
+
+    /// Full course name
+    pub name: String,
+
+    /// Short display name
+    pub short_name: String,
+
+    /// Release status (alpha/beta/live)
+    pub release_status: String,
+
+    /// Detailed description
+    pub description: String,
+
+    /// Brief summary
+    pub summary: String,
+
+    /// Whether learners must verify their git identity before activation
+    pub require_verified_identity: bool,
+
+    /// Default maximum number of graded attempts per stage
+    pub max_attempts: Option<i32>,
+
+    /// Default resource profile of the test pods of its stages
+    pub resources: Option<String>,
+
+    /// Tester image pinned for the course
+    pub tester_image: Option<String>,
+
+    /// Base stages of the course, in course order
+    pub stages: Vec<StageExport>,
+
+    /// Extensions of the course with their stages, in course order
+    pub extensions: Vec<ExtensionExport>,
+}
+
+impl CourseExportResponse {
+    pub fn new(
+        course: CourseModel,
+        stages: Vec<StageModel>,
+        extensions: Vec<ExtensionModel>,
+    ) -> Self {
+        // Stages arrive in course order, which grouping keeps
+        let mut base = Vec::new();
+        let mut extended: HashMap<Uuid, Vec<StageExport>> = HashMap::new();
+        for stage in stages {
+            match stage.extension_id {
+                Some(id) => extended.entry(id).or_default().push(stage.into()),
+                None => base.push(stage.into()),
+            }
+        }
+
+        let extensions = extensions
+            .into_iter()
+            .map(|extension| ExtensionExport {
+                stages: extended.remove(&extension.id).unwrap_or_default(),
+                slug: extension.slug,
+                name: extension.name,
+                description: extension.description,
+            })
+            .collect();
+
+        Self {
+            slug: course.slug,
+            name: course.name,
+            short_name: course.short_name,
+            release_status: course.release_status,
+            description: course.description,
+            summary: course.summary,
+            require_verified_identity: course.require_verified_identity,
+            max_attempts: course.max_attempts,
+            resources: course.resources,
+            tester_image: course.tester_image,
+            stages: base,
+            extensions,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExtensionExport {
+    /// Unique identifier within course
+    pub slug: String,
+
+    /// Extension name
+    pub name: String,
+
+    /// Extension description
+    pub description: String,
+
+    /// Stages of the extension, in extension order
+    pub stages: Vec<StageExport>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StageExport {
+    /// Unique human-readable identifier within parent context
+    pub slug: String,
+
+    /// Display name of the stage
+    pub name: String,
+
+    /// Difficulty level (very_easy, easy, medium, hard)
+    pub difficulty: String,
+
+    /// A short markdown description of the stage,
+    /// used in the course overview page.
+    pub description: String,
+
+    /// A markdown description for this stage.
+    pub instruction: String,
+
+    /// Detailed description of the solution approach and logic, if available.
+    pub solution: Option<String>,
+
+    /// Maximum number of graded attempts, overriding the course default
+    pub max_attempts: Option<i32>,
+
+    /// Resource profile of the test pod, the default one if not declared
+    pub resources: Option<String>,
+
+    /// Whether learners may skip the stage without passing its tests
+    pub optional: bool,
+}
+
+impl From<StageModel> for StageExport {
+    fn from(model: StageModel) -> Self {
+        Self {
+            slug: model.slug,
+            name: model.name,
+            difficulty: model.difficulty,
+            description: model.description,
+            instruction: model.instruction,
+            solution: model.solution,
+            max_attempts: model.max_attempts,
+            resources: model.resources,
+            optional: model.optional,
         }
     }
 }
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CourseRevisionResponse {
+    /// Commit SHA of the course repository the course was synced from
+    pub commit: String,
+
+    /// Time of the sync
+    pub synced_at: DateTime<Utc>,
+
+    /// Who triggered the sync, `admin` or the ID of a maintainer
+    pub actor: String,
+
+    /// Slugs of the stages the sync added
+    pub stages_added: Vec<String>,
+
+    /// Slugs of the stages the sync removed
+    pub stages_removed: Vec<String>,
+
+    /// Slugs of the stages whose content the sync changed
+    pub stages_updated: Vec<String>,
+}
+
+impl From<CourseRevisionModel> for CourseRevisionResponse {
+    fn from(model: CourseRevisionModel) -> Self {
+        Self {
+            commit: model.commit_sha,
+            synced_at: model.synced_at,
+            actor: model.actor,
+            stages_added: model.stages_added,
+            stages_removed: model.stages_removed,
+            stages_updated: model.stages_updated,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CourseDeletionResponse {
+    /// Slug of the deleted course
+    pub slug: String,
+
+    /// Repositories deleted from the git server, or already gone
+    pub deleted_repositories: Vec<String>,
+
+    /// Repositories left behind on the git server by a forced deletion
+    pub failed_repositories: Vec<String>,
+
+    /// Number of images deleted from the registry
+    pub deleted_images: usize,
+
+    /// Learner repositories whose images could not be deleted
+    pub failed_images: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RepositorySyncResponse {
+    /// ID of the user course the repository belongs to
+    pub user_course_id: Uuid,
+
+    /// ID of the learner owning the repository
+    pub user_id: String,
+
+    /// Outcome of the sync (up_to_date, pushed, conflict, skipped, failed)
+    pub status: String,
+
+    /// Template commit merged into the `template-update` branch, if pushed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+
+    /// Paths where the template changes conflict with those of the learner
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<String>,
+
+    /// Why the repository was skipped or could not be synced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
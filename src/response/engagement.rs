@@ -0,0 +1,69 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::model::StageEngagementModel;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StageEngagementResponse {
+    /// Slug of the stage
+    pub stage_slug: String,
+
+    /// Slug of the extension of the stage (null if part of main course)
+    pub extension_slug: Option<String>,
+
+    /// Number of times the instructions were viewed
+    pub instruction_viewed: i64,
+
+    /// Number of times a hint was opened
+    pub hint_opened: i64,
+
+    /// Number of times the solution was viewed
+    pub solution_viewed: i64,
+
+    /// Number of times the clone URL was copied
+    pub copy_clone_url: i64,
+
+    /// Number of learners who viewed the instructions
+    pub viewers: i64,
+
+    /// Number of those learners who pushed at least once to the stage
+    pub viewers_pushed: i64,
+
+    /// Share of the viewers who pushed, between 0 and 1
+    pub conversion: f64,
+}
+
+impl From<StageEngagementModel> for StageEngagementResponse {
+    fn from(model: StageEngagementModel) -> Self {
+        let conversion = match model.viewers {
+            0 => 0.0,
+            viewers => model.viewers_pushed as f64 / viewers as f64,
+        };
+
+        Self {
+            stage_slug: model.stage_slug,
+            extension_slug: model.extension_slug,
+            instruction_viewed: model.instruction_viewed,
+            hint_opened: model.hint_opened,
+            solution_viewed: model.solution_viewed,
+            copy_clone_url: model.copy_clone_url,
+            viewers: model.viewers,
+            viewers_pushed: model.viewers_pushed,
+            conversion,
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::model::{AssetModel, CourseModel, StageManifestModel};
+
+/// Everything a client needs to cache a course for offline reading.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OfflineManifestResponse {
+    /// Unique human-readable identifier
+    pub slug: String,
+
+    /// Full course name
+    pub name: String,
+
+    /// Short display name
+    pub short_name: String,
+
+    /// Release status (alpha/beta/live)
+    pub release_status: String,
+
+    /// Detailed description
+    pub description: String,
+
+    /// Brief summary
+    pub summary: String,
+
+    /// Commit SHA of the course repository the course was last synced from
+    pub commit: String,
+
+    /// Hash over the content of the course, its stages and assets. Clients
+    /// whose cached copy has the same hash are up to date
+    pub content_hash: String,
+
+    /// Stages in course order
+    pub stages: Vec<ManifestStageResponse>,
+
+    /// Files downloadable from the asset endpoint of the course
+    pub assets: Vec<ManifestAssetResponse>,
+}
+
+impl From<(CourseModel, Vec<StageManifestModel>, Vec<AssetModel>)> for OfflineManifestResponse {
+    fn from(
+        (course, stages, assets): (CourseModel, Vec<StageManifestModel>, Vec<AssetModel>),
+    ) -> Self {
+        Self {
+            slug: course.slug,
+            name: course.name,
+            short_name: course.short_name,
+            release_status: course.release_status,
+            description: course.description,
+            summary: course.summary,
+            commit: course.commit_sha,
+            content_hash: course.content_hash,
+            stages: stages.into_iter().map(Into::into).collect(),
+            assets: assets.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ManifestStageResponse {
+    /// Unique human-readable identifier of the stage
+    pub slug: String,
+
+    /// Name of the stage
+    pub name: String,
+
+    /// Slug of the extension the stage belongs to, if any
+    pub extension: Option<String>,
+
+    /// Hash of the stage content, including its instruction
+    pub content_hash: String,
+
+    /// Estimated download size in bytes of the instruction and the files of
+    /// the stage
+    pub size: i64,
+
+    /// Number of hints of the stage
+    pub hint_count: i64,
+}
+
+impl From<StageManifestModel> for ManifestStageResponse {
+    fn from(model: StageManifestModel) -> Self {
+        Self {
+            slug: model.slug,
+            name: model.name,
+            extension: model.extension_slug,
+            content_hash: model.content_hash,
+            size: model.size,
+            hint_count: model.hint_count,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ManifestAssetResponse {
+    /// Path of the file relative to the course repository root
+    pub path: String,
+
+    /// What the file is used for (asset, hint)
+    pub kind: String,
+
+    /// Hex encoded SHA-256 hash of the content
+    pub sha256: String,
+
+    /// Size of the content in bytes
+    pub size: i64,
+}
+
+impl From<AssetModel> for ManifestAssetResponse {
+    fn from(model: AssetModel) -> Self {
+        Self { path: model.path, kind: model.kind, sha256: model.sha256, size: model.size }
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use axum::{
+    http::{
+        HeaderValue, StatusCode,
+        header::{CACHE_CONTROL, CONTENT_TYPE, ETAG},
+    },
+    response::{IntoResponse, Response},
+};
+
+use crate::utils::html::escape;
+
+/// A minimal HTML document carrying the link preview tags of a frontend
+/// page, redirecting browsers to the page itself.
+///
+/// Every value may come from course-authored text and is escaped.
+#[derive(Debug)]
+pub struct MetaPage {
+    /// Title of the shared page
+    pub title: String,
+
+    /// Short description of the shared page
+    pub description: String,
+
+    /// Absolute URL of the preview image
+    pub image: Option<String>,
+
+    /// Frontend URL of the shared page
+    pub url: String,
+
+    /// Validator of the document, derived from the update timestamps
+    pub etag: String,
+
+    /// Whether the page may be stored by shared caches
+    pub public: bool,
+}
+
+impl MetaPage {
+    /// Renders the HTML document.
+    pub fn render(&self) -> String {
+        let title = escape(&self.title);
+        let description = escape(&self.description);
+        let url = escape(&self.url);
+
+        let card = if self.image.is_some() { "summary_large_image" } else { "summary" };
+        let image = self.image.as_deref().map(escape).map_or_else(String::new, |image| {
+            format!(
+                "<meta property=\"og:image\" content=\"{image}\">\n\
+                 <meta name=\"twitter:image\" content=\"{image}\">\n"
+            )
+        });
+
+        format!(
+            "<!DOCTYPE html>\n\
+             <html>\n\
+             <head>\n\
+             <meta charset=\"utf-8\">\n\
+             <title>{title}</title>\n\
+             <meta name=\"description\" content=\"{description}\">\n\
+             <meta property=\"og:type\" content=\"website\">\n\
+             <meta property=\"og:title\" content=\"{title}\">\n\
+             <meta property=\"og:description\" content=\"{description}\">\n\
+             <meta property=\"og:url\" content=\"{url}\">\n\
+             <meta name=\"twitter:card\" content=\"{card}\">\n\
+             <meta name=\"twitter:title\" content=\"{title}\">\n\
+             <meta name=\"twitter:description\" content=\"{description}\">\n\
+             {image}\
+             <link rel=\"canonical\" href=\"{url}\">\n\
+             <meta http-equiv=\"refresh\" content=\"0; url={url}\">\n\
+             </head>\n\
+             <body><a href=\"{url}\">{title}</a></body>\n\
+             </html>\n"
+        )
+    }
+
+    /// Whether the client already holds this version of the document.
+    pub fn matches(&self, if_none_match: Option<&HeaderValue>) -> bool {
+        let Some(value) = if_none_match.and_then(|v| v.to_str().ok()) else {
+            return false;
+        };
+        value.split(',').map(str::trim).any(|tag| tag == "*" || tag == self.etag)
+    }
+}
+
+impl IntoResponse for MetaPage {
+    fn into_response(self) -> Response {
+        let cache = if self.public { "public, max-age=300" } else { "private, no-cache" };
+        let headers = [(ETAG, self.etag.clone()), (CACHE_CONTROL, cache.to_string())];
+        let html = [(CONTENT_TYPE, "text/html; charset=utf-8")];
+        (StatusCode::OK, headers, html, self.render()).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(title: &str) -> MetaPage {
+        MetaPage {
+            title: title.to_string(),
+            description: "A \"quoted\" <b>summary</b>".to_string(),
+            image: Some("https://stackclass.dev/logo.svg?a=1&b=2".to_string()),
+            url: "https://stackclass.dev/courses/redis".to_string(),
+            etag: "W/\"1\"".to_string(),
+            public: true,
+        }
+    }
+
+    #[test]
+    fn test_course_text_is_escaped() {
+        let html = page("Redis</title><script>alert(1)</script>").render();
+
+        assert!(!html.contains("<script>"));
+        assert!(!html.contains("</title><"));
+        assert!(
+            html.contains(
+                "<title>Redis&lt;/title&gt;&lt;script&gt;alert(1)&lt;/script&gt;</title>"
+            )
+        );
+        assert!(html.contains("content=\"A &quot;quoted&quot; &lt;b&gt;summary&lt;/b&gt;\""));
+        assert!(html.contains("content=\"https://stackclass.dev/logo.svg?a=1&amp;b=2\""));
+        assert_eq!(html.matches("<title>").count(), 1);
+    }
+
+    #[test]
+    fn test_matches_etag() {
+        let page = page("Redis");
+        assert!(page.matches(Some(&HeaderValue::from_static("W/\"0\", W/\"1\""))));
+        assert!(page.matches(Some(&HeaderValue::from_static("*"))));
+        assert!(!page.matches(Some(&HeaderValue::from_static("W/\"2\""))));
+        assert!(!page.matches(None));
+    }
+}
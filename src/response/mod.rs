@@ -12,13 +12,39 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod admin;
 mod attempt;
+mod content;
 mod course;
+mod engagement;
+mod error;
 mod extension;
+mod manifest;
+mod meta;
+mod notification;
+mod page;
+mod pipeline;
+mod progress;
+mod snapshot;
 mod stage;
+mod trial;
+mod webhook;
 
 // Re-exports
+pub use admin::*;
 pub use attempt::*;
+pub use content::*;
 pub use course::*;
+pub use engagement::*;
+pub use error::*;
 pub use extension::*;
+pub use manifest::*;
+pub use meta::*;
+pub use notification::*;
+pub use page::*;
+pub use pipeline::*;
+pub use progress::*;
+pub use snapshot::*;
 pub use stage::*;
+pub use trial::*;
+pub use webhook::*;
@@ -0,0 +1,58 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::model::NotificationPreferencesModel;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NotificationPreferencesResponse {
+    /// Channel notifications are delivered through (email, webhook)
+    pub channel: String,
+
+    /// Webhook URL, or email address replacing the one of the account
+    pub destination: Option<String>,
+
+    /// Whether course announcements are sent
+    pub announcements: bool,
+
+    /// Whether feedback comments on attempts are sent
+    pub feedback: bool,
+
+    /// Whether reminders of approaching deadlines are sent
+    pub deadline_reminders: bool,
+
+    /// Whether accountability emails are sent for the courses they were
+    /// enabled on
+    pub accountability: bool,
+
+    /// When the preferences were last saved, none for the defaults
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl From<(NotificationPreferencesModel, bool)> for NotificationPreferencesResponse {
+    fn from((model, saved): (NotificationPreferencesModel, bool)) -> Self {
+        Self {
+            channel: model.channel,
+            destination: model.destination,
+            announcements: model.announcements,
+            feedback: model.feedback,
+            deadline_reminders: model.deadline_reminders,
+            accountability: model.accountability,
+            updated_at: saved.then_some(model.updated_at),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::utils::{
+    crypto::CryptoError,
+    pagination::{Cursor, Page},
+};
+
+/// A page of a listing, shared by all paginated endpoints.
+///
+/// Pass `next_cursor` back as `?cursor=` to fetch the following page. Cursors
+/// are opaque and signed, and stay valid for 24 hours. Altered or expired
+/// cursors are rejected with 422 and the code `cursor_tampered` or
+/// `cursor_expired`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Paginated<T> {
+    /// Items of the page, newest first
+    pub items: Vec<T>,
+
+    /// Cursor of the next page, null on the last page
+    pub next_cursor: Option<String>,
+
+    /// Whether another page follows
+    pub has_more: bool,
+}
+
+/// A page of a listing by page number, for listings small enough to be
+/// counted, like the courses.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NumberedPage<T> {
+    /// Items of the page
+    pub items: Vec<T>,
+
+    /// Number of items of all pages
+    pub total: i64,
+
+    /// Number of the page, starting at 1
+    pub page: i64,
+
+    /// Maximum number of items per page
+    pub per_page: i64,
+}
+
+impl<T> Paginated<T> {
+    /// Builds a page from rows fetched with [`Page::fetch_limit`], signing
+    /// the cursor of the last item, issued at `now`, if another page follows.
+    pub fn from_rows<M, F>(
+        mut rows: Vec<M>,
+        page: &Page,
+        cursor: F,
+        now: DateTime<Utc>,
+        secret: &str,
+    ) -> Result<Self, CryptoError>
+    where
+        M: Into<T>,
+        F: Fn(&M) -> Cursor,
+    {
+        let has_more = rows.len() as i64 > page.limit;
+        rows.truncate(page.limit as usize);
+
+        let next_cursor = match rows.last() {
+            Some(last) if has_more => Some(cursor(last).encode(now, secret)?),
+            _ => None,
+        };
+        let items = rows.into_iter().map(Into::into).collect();
+        Ok(Self { items, next_cursor, has_more })
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A PipelineRun grading the repository of a user course, as kept by the
+/// cluster.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PipelineRunResponse {
+    /// Name of the PipelineRun
+    pub name: String,
+
+    /// Slug of the graded stage
+    pub stage_slug: Option<String>,
+
+    /// Timestamp when the PipelineRun was created
+    pub created_at: Option<DateTime<Utc>>,
+
+    /// Whether the PipelineRun has not completed yet
+    pub active: bool,
+
+    /// Conditions reported by Tekton, like `Succeeded`
+    pub conditions: Vec<PipelineRunCondition>,
+}
+
+/// A condition of a PipelineRun as reported by Tekton.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PipelineRunCondition {
+    /// Type of the condition
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// Status of the condition (True, False, Unknown)
+    pub status: String,
+
+    /// Machine-readable reason of the status, like `Cancelled`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// Human-readable details of the status
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
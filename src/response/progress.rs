@@ -0,0 +1,54 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::model::ProgressModel;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProgressResponse {
+    /// The unique identifier of the user
+    pub user_id: String,
+
+    /// Number of stages completed by the user
+    pub completed_stage_count: i32,
+
+    /// Timestamp of the latest stage start or completion
+    pub last_activity_at: Option<DateTime<Utc>>,
+
+    /// Number of completed stages per extension slug
+    pub extension_progress: BTreeMap<String, i64>,
+}
+
+impl From<ProgressModel> for ProgressResponse {
+    fn from(model: ProgressModel) -> Self {
+        Self {
+            user_id: model.user_id,
+            completed_stage_count: model.completed_stage_count,
+            last_activity_at: model.last_activity_at,
+            extension_progress: serde_json::from_value(model.extension_progress)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RebuildProgressResponse {
+    /// Number of enrollments whose summary was rebuilt
+    pub rebuilt: u64,
+}
@@ -12,11 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::time::Duration;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use crate::model::{StageModel, UserStageModel};
+use uuid::Uuid;
+
+use crate::{
+    model::{StageAttemptModel, StageHintModel, StageModel, StarterChangeModel, UserStageModel},
+    request::event::TestCaseResult,
+};
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct StageResponse {
@@ -36,6 +43,9 @@ pub struct StageResponse {
     /// used in the course overview page.
     pub description: String,
 
+    /// Whether learners may skip the stage without passing its tests
+    pub optional: bool,
+
     /// Creation timestamp
     pub created_at: DateTime<Utc>,
 
@@ -51,6 +61,7 @@ impl From<StageModel> for StageResponse {
             name: model.name,
             difficulty: model.difficulty,
             description: model.description,
+            optional: model.optional,
             created_at: model.created_at,
             updated_at: model.updated_at,
         }
@@ -78,9 +89,16 @@ pub struct StageDetailResponse {
     /// A markdown description for this stage.
     pub instruction: String,
 
-    /// The solution to this stage, if available.
+    /// Whether learners may skip the stage without passing its tests
+    pub optional: bool,
+
+    /// Slug of the stage before this one, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_slug: Option<String>,
+
+    /// Slug of the stage after this one, if any
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub solution: Option<String>,
+    pub next_slug: Option<String>,
 
     /// Creation timestamp
     pub created_at: DateTime<Utc>,
@@ -98,7 +116,9 @@ impl From<StageModel> for StageDetailResponse {
             difficulty: model.difficulty,
             description: model.description,
             instruction: model.instruction,
-            solution: model.solution,
+            optional: model.optional,
+            previous_slug: None,
+            next_slug: None,
             created_at: model.created_at,
             updated_at: model.updated_at,
         }
@@ -113,7 +133,7 @@ pub struct UserStageResponse {
     /// Slug of the stage
     pub stage_slug: String,
 
-    /// Current progress status (in_progress, completed)
+    /// Current progress status (in_progress, completed, skipped)
     pub status: String,
 
     /// Test result status (passed, failed)
@@ -124,10 +144,40 @@ pub struct UserStageResponse {
 
     /// Timestamp when the stage was completed
     pub completed_at: Option<DateTime<Utc>>,
+
+    /// Number of graded attempts left, if the stage limits them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_attempts: Option<i32>,
+
+    /// Learner commit graded when the stage was completed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_commit: Option<String>,
+
+    /// Timestamp when the course image was last pushed to the registry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_pushed_at: Option<DateTime<Utc>>,
+
+    /// Whether the user may view the solution of the stage
+    pub solution_unlocked: bool,
+
+    /// Number of hints of the stage
+    pub hints_total: i64,
+
+    /// Number of hints the user revealed
+    pub hints_unlocked: i32,
+
+    /// Slug of the stage before this one, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_slug: Option<String>,
+
+    /// Slug of the stage after this one, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_slug: Option<String>,
 }
 
 impl From<UserStageModel> for UserStageResponse {
     fn from(model: UserStageModel) -> Self {
+        let solution_unlocked = model.status == "completed";
         Self {
             course_slug: model.course_slug,
             stage_slug: model.stage_slug,
@@ -135,10 +185,48 @@ impl From<UserStageModel> for UserStageResponse {
             test: model.test,
             started_at: model.started_at,
             completed_at: model.completed_at,
+            remaining_attempts: None,
+            completed_commit: model.completed_commit,
+            image_pushed_at: model.image_pushed_at,
+            solution_unlocked,
+            hints_total: model.hints_total,
+            hints_unlocked: model.hints_unlocked,
+            previous_slug: None,
+            next_slug: None,
         }
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StageSolutionResponse {
+    /// Slug of the stage
+    pub stage_slug: String,
+
+    /// The markdown solution of the stage
+    pub solution: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StageHintResponse {
+    /// Position of the hint in reveal order, starting at 0
+    pub position: i32,
+
+    /// Markdown content of the hint
+    pub content: String,
+}
+
+impl From<StageHintModel> for StageHintResponse {
+    fn from(model: StageHintModel) -> Self {
+        Self { position: model.position, content: model.content }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RetestResponse {
+    /// Name of the PipelineRun running the tests
+    pub pipeline_run: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct UserStageStatusResponse {
     /// Current progress status (in_progress, completed)
@@ -146,4 +234,263 @@ pub struct UserStageStatusResponse {
 
     /// Test result status (passed, failed)
     pub test: String,
+
+    /// Number of graded attempts left, if the stage limits them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_attempts: Option<i32>,
+
+    /// Set to `grading_delayed` while an attempt waits for the cluster, or
+    /// to `grading_throttled` while it waits for other runs of the user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grading: Option<String>,
+
+    /// Deadline of the learner, if the course runs as an exam
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<DateTime<Utc>>,
+
+    /// Seconds left until the deadline, negative once it passed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_seconds: Option<i64>,
+
+    /// Latest attempt of the stage, with the output of its test run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest_attempt: Option<StageAttemptResponse>,
+
+    /// State of the PipelineRun of the latest attempt (running, finished,
+    /// cancelled), if it was graded by one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pipeline_status: Option<String>,
+}
+
+/// Sent as an `error` event on a status stream in place of an update that
+/// could not be produced.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StreamErrorEvent {
+    /// Discriminator of the payload, always `error`
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// Description of what went wrong
+    pub message: String,
+}
+
+impl StreamErrorEvent {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self { kind: "error".to_string(), message: message.into() }
+    }
+}
+
+/// Sent as a `timeout` event on a status stream that reached its maximum
+/// lifetime, right before it is closed. Clients reconnect to keep receiving
+/// updates.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StreamTimeoutEvent {
+    /// Discriminator of the payload, always `timeout`
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// Maximum lifetime of the stream in seconds
+    pub lifetime_secs: u64,
+}
+
+impl StreamTimeoutEvent {
+    pub fn new(lifetime: Duration) -> Self {
+        Self { kind: "timeout".to_string(), lifetime_secs: lifetime.as_secs() }
+    }
+}
+
+/// A line of tester output on a log stream.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StageLogLine {
+    /// Discriminator of the payload, always `line`
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// The line, without ANSI escape sequences
+    pub line: String,
+}
+
+impl StageLogLine {
+    pub fn new(line: impl Into<String>) -> Self {
+        Self { kind: "line".to_string(), line: line.into() }
+    }
+}
+
+/// Sent as the last event of a log stream, once the test run is over.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StageLogEnd {
+    /// Discriminator of the payload, always `end`
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// Status of the latest attempt (passed, failed, ...), none if there is
+    /// no attempt yet
+    pub status: Option<String>,
+
+    /// Number of lines dropped to stay within the rate limit
+    pub dropped_lines: u64,
+}
+
+impl StageLogEnd {
+    pub fn new(status: Option<String>, dropped_lines: u64) -> Self {
+        Self { kind: "end".to_string(), status, dropped_lines }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StageAttemptResponse {
+    /// Unique identifier of the attempt
+    pub id: Uuid,
+
+    /// ID of the user who made the attempt
+    pub user_id: String,
+
+    /// Slug of the stage
+    pub stage_slug: String,
+
+    /// Attempt status (pending, passed, failed, budget_exhausted, cancelled)
+    pub status: String,
+
+    /// Name of the PipelineRun, if one was triggered
+    pub pipeline_run: Option<String>,
+
+    /// Content hash of the stage the attempt was graded against
+    pub content_hash: String,
+
+    /// Commit SHA of the course repository the attempt was graded against
+    pub course_commit: String,
+
+    /// Reference of the tester image the attempt was graded with
+    pub tester_image: String,
+
+    /// Whether the stage content changed since the attempt
+    pub stale: bool,
+
+    /// Whether the attempt was submitted after the deadline, late attempts
+    /// do not count toward completion
+    pub late: bool,
+
+    /// Reason the test run reported for its outcome, once graded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// Tail of the tester output, once graded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log: Option<String>,
+
+    /// Results of the test cases the tester ran
+    pub results: Vec<TestCaseResult>,
+
+    /// Timestamp when the attempt was made
+    pub created_at: DateTime<Utc>,
+
+    /// Timestamp when the outcome of its PipelineRun was recorded
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl From<StageAttemptModel> for StageAttemptResponse {
+    fn from(model: StageAttemptModel) -> Self {
+        Self {
+            id: model.id,
+            user_id: model.user_id,
+            stage_slug: model.stage_slug,
+            status: model.status,
+            pipeline_run: model.pipeline_run,
+            content_hash: model.content_hash,
+            course_commit: model.course_commit,
+            tester_image: model.tester_image,
+            stale: model.stale,
+            late: model.late,
+            reason: model.reason,
+            log: model.log,
+            results: serde_json::from_value(model.results).unwrap_or_default(),
+            created_at: model.created_at,
+            finished_at: model.finished_at,
+        }
+    }
+}
+
+/// State of a stage on the path of a learner through the course
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StageState {
+    /// The stage was passed
+    Completed,
+
+    /// The stage the learner works on
+    Current,
+
+    /// The first stage, which becomes current with the first push
+    Unlocked,
+
+    /// A base stage unlocked once the stages before it are completed
+    LockedPrerequisite,
+
+    /// An extension stage unlocked once the stages before it are completed
+    LockedExtension,
+
+    /// A stage progression will not reach anymore, e.g. one added behind
+    /// the learner's position by a course update
+    Skipped,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RoadmapStageResponse {
+    /// Unique human-readable identifier within parent context
+    pub slug: String,
+
+    /// Optional slug of the parent extension (null if part of main course)
+    pub extension_slug: Option<String>,
+
+    /// Display name of the stage
+    pub name: String,
+
+    /// State of the stage for the learner
+    pub state: StageState,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RoadmapResponse {
+    /// All stages of the course in progression order
+    pub stages: Vec<RoadmapStageResponse>,
+
+    /// Number of stages passed
+    pub completed_stage_count: usize,
+
+    /// Number of stages left on the path, the current one included
+    pub remaining_stage_count: usize,
+
+    /// Share of the path that was passed, from 0 to 100
+    pub percent_complete: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StarterChangeResponse {
+    /// Path of the file relative to the starter directory
+    pub path: String,
+
+    /// How the file changed (added, modified, removed)
+    pub kind: String,
+
+    /// Unified diff of the file, null for binary or very large files
+    pub diff: Option<String>,
+
+    /// Whether the diff was cut off at the size limit
+    pub truncated: bool,
+}
+
+impl From<StarterChangeModel> for StarterChangeResponse {
+    fn from(model: StarterChangeModel) -> Self {
+        Self { path: model.path, kind: model.kind, diff: model.diff, truncated: model.truncated }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StarterDiffResponse {
+    /// Slug of the stage
+    pub stage_slug: String,
+
+    /// Files the starter code of the stage changes compared to the stage
+    /// before it, empty for stages without starter code
+    pub files: Vec<StarterChangeResponse>,
 }
@@ -0,0 +1,74 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::model::TrialModel;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TrialResponse {
+    /// Unique identifier of the trial
+    pub id: Uuid,
+
+    /// Token granting access to this trial only
+    pub token: String,
+
+    /// URL to clone and push the trial repository
+    pub repository: String,
+
+    /// The slug of the course tried
+    pub course_slug: String,
+
+    /// The slug of the stage tried
+    pub stage_slug: String,
+
+    /// Time after which the trial repository is deleted
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TrialStatusResponse {
+    /// Trial status (active, converted, expired)
+    pub status: String,
+
+    /// Test result of the latest push (pending, passed, failed), if any
+    pub test: Option<String>,
+
+    /// The slug of the stage tried
+    pub stage_slug: String,
+
+    /// Time after which the trial repository is deleted
+    pub expires_at: DateTime<Utc>,
+}
+
+impl TrialStatusResponse {
+    /// Status of the trial at the given time, active trials past their
+    /// expiry being reported as expired
+    pub fn new(trial: TrialModel, now: DateTime<Utc>) -> Self {
+        let status = match trial.is_active(now) || trial.status != "active" {
+            true => trial.status,
+            false => "expired".to_string(),
+        };
+
+        Self {
+            status,
+            test: trial.test,
+            stage_slug: trial.stage_slug,
+            expires_at: trial.expires_at,
+        }
+    }
+}
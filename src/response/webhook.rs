@@ -0,0 +1,95 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::Serialize;
+use thiserror::Error;
+
+/// Why a pipeline event was rejected, reported to the pipeline so that its
+/// task logs show why a completion was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectionReason {
+    #[error("Invalid signature")]
+    InvalidSignature,
+
+    #[error("Unknown repository")]
+    UnknownRepo,
+
+    #[error("Unknown stage")]
+    UnknownStage,
+
+    #[error("The pipeline run is not awaiting a result")]
+    StalePipeline,
+}
+
+/// Report of the verification of a pipeline event, without recording it.
+#[derive(Debug, Default, Serialize)]
+pub struct PipelineEventReport {
+    /// Whether the webhook would accept the event
+    pub valid: bool,
+
+    /// Reason the webhook would reject the event with, if any
+    pub rejection_reason: Option<RejectionReason>,
+
+    /// Outcome of each check, in the order they are performed
+    pub checks: Vec<PipelineEventCheck>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PipelineEventCheck {
+    /// Name of the check (signature, repo, user_course, stage)
+    pub name: &'static str,
+
+    /// Outcome of the check
+    pub status: CheckStatus,
+
+    /// Human-readable explanation of the outcome
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+impl PipelineEventReport {
+    /// Records a passed check.
+    pub fn pass(&mut self, name: &'static str, message: impl Into<String>) {
+        self.push(name, CheckStatus::Passed, message.into());
+    }
+
+    /// Records a failed check, the first one deciding the rejection reason.
+    pub fn fail(
+        &mut self,
+        name: &'static str,
+        reason: RejectionReason,
+        message: impl Into<String>,
+    ) {
+        self.rejection_reason.get_or_insert(reason);
+        self.push(name, CheckStatus::Failed, message.into());
+    }
+
+    /// Records a check that could not be performed.
+    pub fn skip(&mut self, name: &'static str, message: impl Into<String>) {
+        self.push(name, CheckStatus::Skipped, message.into());
+    }
+
+    fn push(&mut self, name: &'static str, status: CheckStatus, message: String) {
+        self.checks.push(PipelineEventCheck { name, status, message });
+        self.valid = self.rejection_reason.is_none();
+    }
+}
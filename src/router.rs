@@ -0,0 +1,295 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Declarative route table.
+//!
+//! Every route declares the authentication it requires, which a route layer
+//! checks before the handler runs, whatever extractors the handler itself
+//! takes. In debug builds, a handler whose signature asks for another
+//! authentication than the declared one panics on registration.
+
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+use axum::{
+    Router,
+    extract::{DefaultBodyLimit, FromRequestParts, Request, State},
+    handler::Handler,
+    http::Method,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{MethodFilter, MethodRouter, any, on},
+};
+use utoipa::openapi::{OpenApi, PathItem, path::Operation};
+
+use crate::{
+    context::Context,
+    extractor::{AdminBasic, Claims, CourseMaintainer, CourseReader},
+};
+
+/// Authentication a route requires.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Auth {
+    /// Anyone, the handler may still verify a signature or an optional JWT
+    Public,
+
+    /// A JWT of a user
+    Jwt,
+
+    /// The admin, or a maintainer of the course in the path
+    Instructor,
+
+    /// Whatever [`Auth::Instructor`] accepts, or an API token covering the
+    /// course in the path
+    InstructorOrToken,
+
+    /// The admin, with Basic Auth
+    AdminBasic,
+}
+
+impl Auth {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Auth::Public => "public",
+            Auth::Jwt => "jwt",
+            Auth::Instructor => "instructor",
+            Auth::InstructorOrToken => "instructor_or_token",
+            Auth::AdminBasic => "admin_basic",
+        }
+    }
+}
+
+impl fmt::Display for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A route of the table, served by [`build`].
+pub struct Route {
+    /// Method of the route, none if it takes any
+    method: Option<Method>,
+    path: &'static str,
+    auth: Auth,
+    hidden: bool,
+    handler: MethodRouter<Arc<Context>>,
+}
+
+macro_rules! methods {
+    ($($name:ident => $method:ident),* $(,)?) => {
+        $(
+            pub fn $name<H, T>(path: &'static str, auth: Auth, handler: H) -> Self
+            where
+                H: Handler<T, Arc<Context>>,
+                T: 'static,
+            {
+                Self::new(Some(Method::$method), path, auth, handler)
+            }
+        )*
+    };
+}
+
+impl Route {
+    methods! {
+        get => GET,
+        post => POST,
+        put => PUT,
+        patch => PATCH,
+        delete => DELETE,
+    }
+
+    /// A route taking any method.
+    pub fn any<H, T>(path: &'static str, auth: Auth, handler: H) -> Self
+    where
+        H: Handler<T, Arc<Context>>,
+        T: 'static,
+    {
+        Self::new(None, path, auth, handler)
+    }
+
+    fn new<H, T>(method: Option<Method>, path: &'static str, auth: Auth, handler: H) -> Self
+    where
+        H: Handler<T, Arc<Context>>,
+        T: 'static,
+    {
+        #[cfg(debug_assertions)]
+        check_auth::<T>(method.as_ref(), path, auth);
+
+        let handler = match &method {
+            Some(method) => {
+                let filter = MethodFilter::try_from(method.clone()).expect("supported method");
+                on(filter, handler)
+            }
+            None => any(handler),
+        };
+        Self { method, path, auth, hidden: false, handler }
+    }
+
+    /// Limits the size of request bodies to the given number of bytes.
+    pub fn body_limit(mut self, limit: usize) -> Self {
+        self.handler = self.handler.layer(DefaultBodyLimit::max(limit));
+        self
+    }
+
+    /// Leaves the route out of the OpenAPI document on purpose.
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    pub fn method(&self) -> &str {
+        self.method.as_ref().map_or("ANY", Method::as_str)
+    }
+
+    pub fn path(&self) -> &'static str {
+        self.path
+    }
+
+    pub fn auth(&self) -> Auth {
+        self.auth
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Path of the route as written in the OpenAPI document, where
+    /// wildcards look like any other parameter.
+    pub fn openapi_path(&self) -> String {
+        self.path.replace("{*", "{")
+    }
+
+    /// Whether the OpenAPI document describes the route.
+    pub fn documented(&self, doc: &OpenApi) -> bool {
+        let item = doc.paths.paths.get(&self.openapi_path());
+        match &self.method {
+            Some(method) => item.is_some_and(|item| operation(item, method).is_some()),
+            None => item.is_some(),
+        }
+    }
+}
+
+/// Operation of the path item for the given method.
+pub fn operation<'a>(item: &'a PathItem, method: &Method) -> Option<&'a Operation> {
+    match *method {
+        Method::GET => item.get.as_ref(),
+        Method::POST => item.post.as_ref(),
+        Method::PUT => item.put.as_ref(),
+        Method::PATCH => item.patch.as_ref(),
+        Method::DELETE => item.delete.as_ref(),
+        Method::HEAD => item.head.as_ref(),
+        Method::OPTIONS => item.options.as_ref(),
+        Method::TRACE => item.trace.as_ref(),
+        _ => None,
+    }
+}
+
+/// Builds a router serving the routes, each behind the authentication it
+/// declares. Panics if a method of a path is routed twice.
+pub fn build(ctx: Arc<Context>, routes: Vec<Route>) -> Router<Arc<Context>> {
+    let mut paths: BTreeMap<&str, MethodRouter<Arc<Context>>> = BTreeMap::new();
+
+    for route in routes {
+        let handler = match route.auth {
+            Auth::Public => route.handler,
+            Auth::Jwt => route
+                .handler
+                .route_layer(middleware::from_fn_with_state(ctx.clone(), authenticate::<Claims>)),
+            Auth::Instructor => route.handler.route_layer(middleware::from_fn_with_state(
+                ctx.clone(),
+                authenticate::<CourseMaintainer>,
+            )),
+            Auth::InstructorOrToken => route.handler.route_layer(middleware::from_fn_with_state(
+                ctx.clone(),
+                authenticate::<CourseReader>,
+            )),
+            Auth::AdminBasic => route.handler.route_layer(middleware::from_fn_with_state(
+                ctx.clone(),
+                authenticate::<AdminBasic>,
+            )),
+        };
+
+        let handler = match paths.remove(route.path) {
+            Some(routed) => routed.merge(handler),
+            None => handler,
+        };
+        paths.insert(route.path, handler);
+    }
+
+    paths.into_iter().fold(Router::new(), |router, (path, handler)| router.route(path, handler))
+}
+
+/// Rejects the request unless `A` can be extracted from it.
+async fn authenticate<A>(State(ctx): State<Arc<Context>>, req: Request, next: Next) -> Response
+where
+    A: FromRequestParts<Arc<Context>>,
+    A::Rejection: IntoResponse,
+{
+    let (mut parts, body) = req.into_parts();
+    if let Err(e) = A::from_request_parts(&mut parts, &ctx).await {
+        return e.into_response();
+    }
+    next.run(Request::from_parts(parts, body)).await
+}
+
+/// Panics if the extractors of a handler, `T`, require another
+/// authentication than the declared one. Optional extractors, like
+/// `Option<Claims>`, do not count.
+#[cfg(debug_assertions)]
+fn check_auth<T>(method: Option<&Method>, path: &str, auth: Auth) {
+    use std::any::type_name;
+
+    let required = extractors(type_name::<T>()).find_map(|ty| {
+        if ty == type_name::<AdminBasic>() {
+            Some(Auth::AdminBasic)
+        } else if ty == type_name::<CourseMaintainer>() {
+            Some(Auth::Instructor)
+        } else if ty == type_name::<CourseReader>() {
+            Some(Auth::InstructorOrToken)
+        } else if ty == type_name::<Claims>() {
+            Some(Auth::Jwt)
+        } else {
+            None
+        }
+    });
+
+    if let Some(required) = required &&
+        required != auth
+    {
+        let method = method.map_or("ANY", Method::as_str);
+        panic!("Route {method} {path} is declared {auth}, but its handler requires {required}");
+    }
+}
+
+/// Splits the name of a tuple type into the names of its elements.
+#[cfg(debug_assertions)]
+fn extractors(tuple: &str) -> impl Iterator<Item = &str> {
+    let inner = tuple.strip_prefix('(').and_then(|t| t.strip_suffix(')')).unwrap_or(tuple);
+    let mut depth = 0;
+    let mut start = 0;
+    let mut items = Vec::new();
+
+    for (i, c) in inner.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(inner[start..].trim());
+    items.into_iter().filter(|item| !item.is_empty())
+}
@@ -14,48 +14,235 @@
 
 use std::sync::Arc;
 
-use axum::{
-    Router,
-    routing::{any, delete, get, patch, post},
-};
+use axum::{Router, middleware};
 
 use crate::{
     context::Context,
-    handler::{course, extension, git, stage, webhook},
+    handler::{admin, course, extension, git, health, meta, notification, stage, trial, webhook},
+    middleware::maintenance,
+    router::{self, Auth::*, Route},
 };
 
-pub fn build() -> Router<Arc<Context>> {
-    Router::new()
-        .route("/v1/courses", get(course::find))
-        .route("/v1/courses", post(course::create))
-        .route("/v1/courses/{slug}", get(course::get))
-        .route("/v1/courses/{slug}", delete(course::delete))
-        .route("/v1/courses/{slug}", patch(course::update))
+/// Builds the API routes, refusing writes during a maintenance.
+pub fn build(ctx: Arc<Context>) -> Router {
+    router::build(ctx.clone(), table())
+        .layer(middleware::from_fn_with_state(ctx.clone(), maintenance::read_only))
+        .with_state(ctx)
+}
+
+/// All routes of the API, along with the authentication they require.
+pub fn table() -> Vec<Route> {
+    vec![
+        Route::get("/readyz", Public, health::ready),
+        Route::get("/v1/courses", Public, course::find),
+        Route::post("/v1/courses", AdminBasic, course::create),
+        Route::get("/v1/courses/search", Public, course::search),
+        Route::get("/v1/courses/jobs/{id}", AdminBasic, course::get_job),
+        Route::get("/v1/courses/jobs/{id}/status", AdminBasic, course::stream_job),
+        Route::get("/v1/courses/{slug}", Public, course::get),
+        Route::delete("/v1/courses/{slug}", AdminBasic, course::delete),
+        Route::patch("/v1/courses/{slug}", Instructor, course::update),
         //
-        .route("/v1/courses/{slug}/attempts", get(course::find_attempts))
-        .route("/v1/courses/{slug}/extensions", get(extension::find))
+        Route::get("/v1/courses/{slug}/assets/{*path}", Public, course::get_asset),
+        Route::get("/v1/courses/{slug}/attempts", Public, course::find_attempts),
+        Route::get("/v1/courses/{slug}/extensions", Public, extension::find),
+        Route::get("/v1/courses/{slug}/registry", AdminBasic, course::get_registry),
+        Route::post("/v1/courses/{slug}/repositories/sync", Instructor, course::sync_repositories),
+        Route::put("/v1/courses/{slug}/registry", AdminBasic, course::update_registry),
+        Route::get("/v1/courses/{slug}/offline-manifest", Public, course::get_offline_manifest),
+        // Instructor
+        Route::get("/v1/courses/{slug}/export", Instructor, course::export),
+        Route::get("/v1/courses/{slug}/revisions", Instructor, course::find_revisions),
+        Route::get("/v1/courses/{slug}/source", Instructor, course::get_source),
+        Route::get("/v1/courses/{slug}/stats", InstructorOrToken, course::get_stats),
+        Route::get(
+            "/v1/courses/{slug}/cohorts/{cohort}/progress",
+            InstructorOrToken,
+            course::find_cohort_progress,
+        ),
+        Route::get(
+            "/v1/courses/{slug}/effective-settings",
+            Instructor,
+            course::get_effective_settings,
+        ),
+        Route::get(
+            "/v1/courses/{slug}/source/stages/{stage_slug}",
+            Instructor,
+            course::get_stage_source,
+        ),
         // Stage
-        .route("/v1/courses/{slug}/stages", get(stage::find_all_stages))
-        .route("/v1/courses/{slug}/stages/base", get(stage::find_base_stages))
-        .route("/v1/courses/{slug}/stages/extended", get(stage::find_extended_stages))
-        .route("/v1/courses/{slug}/stages/{stage_slug}", get(stage::get))
+        Route::get("/v1/courses/{slug}/stages", Public, stage::find_all_stages),
+        Route::get("/v1/courses/{slug}/stages/base", Public, stage::find_base_stages),
+        Route::get("/v1/courses/{slug}/stages/extended", Public, stage::find_extended_stages),
+        Route::get("/v1/courses/{slug}/stages/{stage_slug}", Public, stage::get),
+        Route::get(
+            "/v1/courses/{slug}/stages/{stage_slug}/starter-diff",
+            Public,
+            stage::get_starter_diff,
+        ),
         // User course
-        .route("/v1/user/courses", get(course::find_user_courses))
-        .route("/v1/user/courses", post(course::create_user_course))
-        .route("/v1/user/courses/{slug}", get(course::get_user_course))
-        .route("/v1/user/courses/{slug}", patch(course::update_user_course))
-        .route("/v1/user/courses/{slug}/status", get(course::stream_user_course_status))
+        Route::get("/v1/user/courses", Jwt, course::find_user_courses),
+        Route::post("/v1/user/courses", Jwt, course::create_user_course),
+        Route::get("/v1/user/courses/{slug}", Jwt, course::get_user_course),
+        Route::patch("/v1/user/courses/{slug}", Jwt, course::update_user_course),
+        Route::delete("/v1/user/courses/{slug}", Jwt, course::delete_user_course),
+        Route::post("/v1/user/courses/{slug}/reset", Jwt, course::reset_user_course),
+        Route::get("/v1/user/courses/{slug}/pipelines", Jwt, course::find_user_pipelines),
+        Route::get("/v1/user/courses/{slug}/status", Jwt, course::stream_user_course_status),
+        Route::post("/v1/user/courses/{slug}/events", Jwt, course::create_engagement_event)
+            .body_limit(course::MAX_ENGAGEMENT_EVENT_SIZE),
+        Route::post("/v1/user/verify-git-identity", Jwt, course::verify_git_identity),
+        Route::get("/v1/user/notification-preferences", Jwt, notification::get_preferences),
+        Route::put("/v1/user/notification-preferences", Jwt, notification::update_preferences),
         // User stage
-        .route("/v1/user/courses/{slug}/stages", get(stage::find_user_stages))
-        .route("/v1/user/courses/{slug}/stages", post(stage::complete_stage))
-        .route("/v1/user/courses/{slug}/stages/{stage_slug}", get(stage::get_user_stage))
-        .route(
+        Route::get("/v1/user/courses/{slug}/roadmap", Jwt, stage::get_roadmap),
+        Route::get("/v1/user/courses/{slug}/stages", Jwt, stage::find_user_stages),
+        Route::post("/v1/user/courses/{slug}/stages", Jwt, stage::complete_stage),
+        Route::get("/v1/user/courses/{slug}/stages/{stage_slug}", Jwt, stage::get_user_stage),
+        Route::get(
+            "/v1/user/courses/{slug}/stages/{stage_slug}/attempts",
+            Jwt,
+            stage::find_user_stage_attempts,
+        ),
+        Route::post("/v1/user/courses/{slug}/stages/{stage_slug}/skip", Jwt, stage::skip_stage),
+        Route::post("/v1/user/courses/{slug}/stages/{stage_slug}/retest", Jwt, stage::retest_stage),
+        Route::get(
+            "/v1/user/courses/{slug}/stages/{stage_slug}/solution",
+            Jwt,
+            stage::get_user_stage_solution,
+        ),
+        Route::get(
+            "/v1/user/courses/{slug}/stages/{stage_slug}/hints",
+            Jwt,
+            stage::find_user_stage_hints,
+        ),
+        Route::post(
+            "/v1/user/courses/{slug}/stages/{stage_slug}/hints/unlock",
+            Jwt,
+            stage::unlock_user_stage_hint,
+        ),
+        Route::get(
             "/v1/user/courses/{slug}/stages/{stage_slug}/status",
-            get(stage::stream_user_stage_status),
-        )
+            Jwt,
+            stage::stream_user_stage_status,
+        ),
+        Route::get(
+            "/v1/user/courses/{slug}/stages/{stage_slug}/logs/stream",
+            Jwt,
+            stage::stream_user_stage_logs,
+        ),
+        // Trial
+        Route::post("/v1/trials", Public, trial::create),
+        Route::get("/v1/trials/{id}/status", Public, trial::get_status),
+        Route::post("/v1/user/trials/{id}/convert", Jwt, trial::convert),
+        // Admin
+        Route::get("/v1/admin/summary", AdminBasic, admin::summary),
+        Route::get("/v1/admin/api-tokens", AdminBasic, admin::find_api_tokens),
+        Route::post("/v1/admin/api-tokens", AdminBasic, admin::create_api_token),
+        Route::delete("/v1/admin/api-tokens/{id}", AdminBasic, admin::revoke_api_token),
+        Route::get("/v1/admin/audit-logs", AdminBasic, admin::find_audit_logs),
+        Route::put("/v1/admin/course-defaults", AdminBasic, admin::set_course_defaults),
+        Route::get("/v1/admin/jobs", AdminBasic, admin::find_jobs),
+        Route::put("/v1/admin/maintenance", AdminBasic, admin::set_maintenance),
+        Route::get("/v1/admin/routes", AdminBasic, admin::find_routes),
+        Route::get("/v1/admin/resource-profiles", AdminBasic, admin::find_resource_profiles),
+        Route::get(
+            "/v1/admin/migrations/repositories",
+            AdminBasic,
+            admin::find_repository_migrations,
+        ),
+        Route::post("/v1/admin/migrations/repositories", AdminBasic, admin::migrate_repositories),
+        Route::get("/v1/admin/repositories/orphans", AdminBasic, admin::find_orphaned_repositories),
+        Route::post("/v1/admin/registry/gc", AdminBasic, admin::collect_registry_garbage),
+        Route::get(
+            "/v1/admin/registry/gc/{id}",
+            AdminBasic,
+            admin::get_registry_garbage_collection,
+        ),
+        Route::post("/v1/admin/users/merge", AdminBasic, admin::merge_users),
+        Route::post(
+            "/v1/admin/users/{id}/courses/{slug}/stages/{stage_slug}/grant-attempts",
+            AdminBasic,
+            admin::grant_attempts,
+        ),
+        Route::get(
+            "/v1/admin/users/{id}/courses/{slug}/stages/{stage_slug}/snapshot",
+            Instructor,
+            admin::download_snapshot,
+        ),
+        Route::get(
+            "/v1/admin/users/{id}/courses/{slug}/stages/{stage_slug}/snapshot/tree",
+            Instructor,
+            admin::get_snapshot_tree,
+        ),
+        Route::get(
+            "/v1/admin/users/{id}/courses/{slug}/stages/{stage_slug}/logs/stream",
+            Instructor,
+            admin::stream_stage_logs,
+        ),
+        Route::post(
+            "/v1/admin/users/{id}/courses/{slug}/extend-deadline",
+            AdminBasic,
+            admin::extend_deadline,
+        ),
+        Route::get(
+            "/v1/admin/users/{id}/courses/{slug}/pipelines",
+            AdminBasic,
+            admin::find_user_pipelines,
+        ),
+        Route::get("/v1/admin/courses/{slug}/attempts", AdminBasic, admin::find_attempts),
+        Route::get(
+            "/v1/admin/courses/{slug}/engagement",
+            InstructorOrToken,
+            admin::find_engagement,
+        ),
+        Route::put("/v1/admin/courses/{slug}/exam-window", AdminBasic, admin::set_exam_window),
+        Route::get(
+            "/v1/admin/courses/{slug}/integrity-flags",
+            Instructor,
+            admin::find_integrity_flags,
+        ),
+        Route::post(
+            "/v1/admin/courses/{slug}/integrity-flags/{id}/dismiss",
+            Instructor,
+            admin::dismiss_integrity_flag,
+        ),
+        Route::get("/v1/admin/courses/{slug}/maintainers", AdminBasic, admin::find_maintainers),
+        Route::post("/v1/admin/courses/{slug}/maintainers", AdminBasic, admin::add_maintainer),
+        Route::delete(
+            "/v1/admin/courses/{slug}/maintainers/{user_id}",
+            AdminBasic,
+            admin::remove_maintainer,
+        ),
+        Route::post("/v1/admin/courses/{slug}/preprovision", AdminBasic, admin::preprovision),
+        Route::put(
+            "/v1/admin/courses/{slug}/setting-overrides",
+            AdminBasic,
+            admin::set_setting_overrides,
+        ),
+        Route::get("/v1/admin/courses/{slug}/preview-token", AdminBasic, admin::get_preview_token),
+        Route::get("/v1/admin/courses/{slug}/progress", InstructorOrToken, admin::find_progress),
+        Route::post(
+            "/v1/admin/courses/{slug}/progress/rebuild",
+            AdminBasic,
+            admin::rebuild_progress,
+        ),
+        Route::post(
+            "/v1/admin/courses/{slug}/registry-credentials/rotate",
+            AdminBasic,
+            admin::rotate_registry_credentials,
+        ),
+        // Link previews
+        Route::get("/v1/meta/courses/{slug}", Public, meta::get_course),
+        Route::get("/v1/meta/courses/{slug}/stages/{stage_slug}", Public, meta::get_stage),
         // Webhooks
-        .route("/v1/webhooks/gitea", post(webhook::handle_gitea_webhook))
-        .route("/v1/webhooks/tekton", post(webhook::handle_tekton_webhook))
+        Route::post("/v1/webhooks/gitea", AdminBasic, webhook::handle_gitea_webhook).hidden(),
+        Route::post("/v1/webhooks/harbor", AdminBasic, webhook::handle_harbor_webhook).hidden(),
+        Route::post("/v1/webhooks/tekton", Public, webhook::handle_tekton_webhook).hidden(),
+        Route::post("/v1/webhooks/tekton/validate", Public, webhook::validate_tekton_webhook)
+            .hidden(),
         // Git Proxy
-        .route("/{uuid}/{*path}", any(git::proxy))
+        Route::any("/trials/{id}/{*path}", Public, git::proxy_trial).hidden(),
+        Route::any("/{uuid}/{*path}", Public, git::proxy).hidden(),
+    ]
 }
@@ -0,0 +1,57 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// A file shipped with a course besides its definition files, indexed on
+/// import so that clients can download it.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Asset {
+    /// Path of the file relative to the course repository root.
+    pub path: String,
+
+    /// Slug of the stage the file belongs to, none for course-wide assets.
+    pub stage: Option<String>,
+
+    /// What the file is used for.
+    pub kind: AssetKind,
+
+    /// Hex encoded SHA-256 hash of the content.
+    pub sha256: String,
+
+    /// Size of the content in bytes.
+    pub size: u64,
+}
+
+/// What an asset is used for.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetKind {
+    /// A file referenced by instructions, e.g. an image.
+    Asset,
+
+    /// A hint of a stage, kept in its `hints` directory.
+    Hint,
+}
+
+impl fmt::Display for AssetKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetKind::Asset => write!(f, "asset"),
+            AssetKind::Hint => write!(f, "hint"),
+        }
+    }
+}
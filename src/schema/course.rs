@@ -18,7 +18,9 @@ use indexmap::IndexMap;
 
 use serde::{Deserialize, Serialize};
 
-use crate::schema::{ExtensionMap, Stage};
+use crate::schema::{
+    Asset, ExtensionMap, PipelineSettings, ResourceProfile, Stage, StarterDiff, StarterFile,
+};
 
 /// Schema for the course.yml file.
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -41,6 +43,32 @@ pub struct Course {
     /// A short description of course, < 15 words.
     pub summary: String,
 
+    /// Whether learners must verify their git identity before the
+    /// enrollment gets activated.
+    #[serde(default)]
+    pub require_verified_identity: bool,
+
+    /// Default maximum number of graded attempts per stage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+
+    /// Default resource profile of the test pods of its stages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceProfile>,
+
+    /// Tester image pinned for the course, `{course}` stands for its slug.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tester_image: Option<String>,
+
+    /// Programming languages the course is offered in, the first one being
+    /// the primary. Their templates live under `template/{language}`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<String>,
+
+    /// How the PipelineRuns grading the course are set up.
+    #[serde(default, skip_serializing_if = "PipelineSettings::is_empty")]
+    pub pipeline: PipelineSettings,
+
     /// Sequential stages of the course.
     #[serde(skip)]
     pub stages: IndexMap<String, Stage>,
@@ -48,6 +76,18 @@ pub struct Course {
     /// Sets of additional stages.
     #[serde(skip)]
     pub extensions: Option<ExtensionMap>,
+
+    /// Files shipped with the course besides its definition files.
+    #[serde(skip)]
+    pub assets: Vec<Asset>,
+
+    /// Files of the starter code of stages.
+    #[serde(skip)]
+    pub starter_files: Vec<StarterFile>,
+
+    /// What the starter code of each stage with starter code changes.
+    #[serde(skip)]
+    pub starter_diffs: Vec<StarterDiff>,
 }
 
 impl FromStr for Course {
@@ -100,6 +140,82 @@ mod tests {
         assert!(matches!(course.release_status, Status::Beta));
         assert_eq!(course.description, "A comprehensive course on Rust programming language.");
         assert_eq!(course.summary, "Learn Rust programming");
+        assert!(!course.require_verified_identity);
+        assert!(course.languages.is_empty());
+        assert!(course.pipeline.is_empty());
+    }
+
+    #[test]
+    fn test_course_require_verified_identity() {
+        let yaml = r#"
+            slug: rust-course
+            name: Rust Programming
+            short_name: Rust
+            release_status: live
+            description: A comprehensive course on Rust programming language.
+            summary: Learn Rust programming
+            require_verified_identity: true
+        "#;
+
+        let course = Course::from_str(yaml).unwrap();
+        assert!(course.require_verified_identity);
+    }
+
+    #[test]
+    fn test_course_settings() {
+        let yaml = r#"
+            slug: rust-course
+            name: Rust Programming
+            short_name: Rust
+            release_status: live
+            description: A comprehensive course on Rust programming language.
+            summary: Learn Rust programming
+            resources: medium
+            tester_image: ghcr.io/stackclass/rust-course-tester:v2
+        "#;
+
+        let course = Course::from_str(yaml).unwrap();
+        assert_eq!(course.resources, Some(ResourceProfile::Medium));
+        assert_eq!(
+            course.tester_image.as_deref(),
+            Some("ghcr.io/stackclass/rust-course-tester:v2")
+        );
+    }
+
+    #[test]
+    fn test_course_languages() {
+        let yaml = r#"
+            slug: rust-course
+            name: Rust Programming
+            short_name: Rust
+            release_status: live
+            description: A comprehensive course on Rust programming language.
+            summary: Learn Rust programming
+            languages: [rust, go]
+        "#;
+
+        let course = Course::from_str(yaml).unwrap();
+        assert_eq!(course.languages, ["rust", "go"]);
+    }
+
+    #[test]
+    fn test_course_pipeline() {
+        let yaml = r#"
+            slug: rust-course
+            name: Rust Programming
+            short_name: Rust
+            release_status: live
+            description: A comprehensive course on Rust programming language.
+            summary: Learn Rust programming
+            pipeline:
+              pipeline_ref: rust-test-pipeline
+              workspace_size: 20Gi
+        "#;
+
+        let course = Course::from_str(yaml).unwrap();
+        assert_eq!(course.pipeline.pipeline_ref(), "rust-test-pipeline");
+        assert_eq!(course.pipeline.workspace_size(), "20Gi");
+        assert_eq!(course.pipeline.timeout, None);
     }
 
     #[test]
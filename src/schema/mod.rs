@@ -12,14 +12,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod asset;
 mod course;
 mod extension;
 mod manifest;
 mod parser;
+mod pipeline;
+mod settings;
 mod stage;
+mod starter;
 
 // Re-exports
+pub use asset::*;
 pub use course::*;
 pub use extension::*;
 pub use parser::*;
+pub use pipeline::*;
+pub use settings::*;
 pub use stage::*;
+pub use starter::*;
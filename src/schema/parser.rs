@@ -13,10 +13,29 @@
 // limitations under the License.
 
 use indexmap::IndexMap;
-use std::{fs, path::Path, str::FromStr};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 use thiserror::Error;
 
-use crate::schema::{Course, ExtensionMap, ExtensionSet, Stage};
+use crate::{
+    schema::{
+        Asset, AssetKind, ChangeKind, Course, ExtensionMap, ExtensionSet, Stage, StarterChange,
+        StarterDiff, StarterFile,
+    },
+    utils::diff,
+};
+
+/// Files defining a stage, which are not assets.
+const STAGE_FILES: [&str; 3] = ["stage.yml", "instruction.md", "solution.md"];
+
+/// Largest stored diff of a starter file in bytes, longer diffs are cut off
+/// at a line boundary.
+const MAX_DIFF_SIZE: usize = 64 * 1024;
 
 /// Errors that can occur during course parsing
 #[derive(Debug, Error)]
@@ -63,6 +82,8 @@ pub fn parse(path: &Path) -> Result<Course, ParseError> {
     let mut course = parse_course(path)?;
     course.stages = parse_stages(&path.join("stages"))?;
     course.extensions = parse_extensions(path)?;
+    course.assets = parse_assets(path, &course)?;
+    (course.starter_files, course.starter_diffs) = parse_starters(path, &course)?;
 
     Ok(course)
 }
@@ -71,7 +92,10 @@ pub fn parse(path: &Path) -> Result<Course, ParseError> {
 fn parse_course(path: &Path) -> Result<Course, ParseError> {
     let course_yml_path = path.join("course.yml");
     let content = read_to_string(&course_yml_path)?;
-    Course::from_str(&content).map_err(|e| ParseError::yaml(&course_yml_path, e))
+    let course = Course::from_str(&content).map_err(|e| ParseError::yaml(&course_yml_path, e))?;
+    course.pipeline.validate().map_err(ParseError::Validation)?;
+
+    Ok(course)
 }
 
 /// Parse all stages from stages directory
@@ -100,7 +124,7 @@ fn parse_stages(stages_dir: &Path) -> Result<IndexMap<String, Stage>, ParseError
     Ok(stages)
 }
 
-/// Parse single stage including instruction and solution
+/// Parse single stage including instruction, solution and hints
 fn parse_stage(stage_dir: &Path) -> Result<Stage, ParseError> {
     let stage_yml_path = stage_dir.join("stage.yml");
     let meta_content = read_to_string(&stage_yml_path)?;
@@ -115,9 +139,29 @@ fn parse_stage(stage_dir: &Path) -> Result<Stage, ParseError> {
         stage.solution.replace(read_to_string(&sln_path)?);
     }
 
+    stage.hints = parse_hints(&stage_dir.join("hints"))?;
+
     Ok(stage)
 }
 
+/// Read the markdown files of a hints directory, in file name order.
+fn parse_hints(hints_dir: &Path) -> Result<Vec<String>, ParseError> {
+    if !hints_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(hints_dir).map_err(|e| ParseError::io(hints_dir, e))? {
+        let path = entry.map_err(|e| ParseError::io(hints_dir, e))?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "md") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    paths.iter().map(|path| read_to_string(path)).collect()
+}
+
 /// Parse extensions including their stages
 fn parse_extensions(path: &Path) -> Result<Option<ExtensionMap>, ParseError> {
     let extensions_path = path.join("extensions.yml");
@@ -146,6 +190,211 @@ fn parse_extensions(path: &Path) -> Result<Option<ExtensionMap>, ParseError> {
     Ok(Some(extensions))
 }
 
+/// Index the course-wide files in the assets directory, and the files in
+/// stage directories besides the stage definition. Symbolic links are
+/// skipped, they could point outside of the repository.
+fn parse_assets(path: &Path, course: &Course) -> Result<Vec<Asset>, ParseError> {
+    let mut assets = Vec::new();
+    walk_assets(path, Path::new("assets"), None, &mut assets)?;
+
+    let base = course.stages.iter().map(|(dir, stage)| (PathBuf::from("stages").join(dir), stage));
+    let extended = course.extensions.iter().flatten().flat_map(|(slug, ext)| {
+        ext.stages
+            .iter()
+            .map(move |(dir, stage)| (PathBuf::from("extensions").join(slug).join(dir), stage))
+    });
+
+    for (dir, stage) in base.chain(extended) {
+        walk_assets(path, &dir, Some((&stage.slug, &dir)), &mut assets)?;
+    }
+
+    assets.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(assets)
+}
+
+/// Recursively index the files of a directory relative to the course root,
+/// along with the slug and directory of the stage they belong to.
+fn walk_assets(
+    root: &Path,
+    relative: &Path,
+    stage: Option<(&str, &Path)>,
+    assets: &mut Vec<Asset>,
+) -> Result<(), ParseError> {
+    let dir = root.join(relative);
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(&dir).map_err(|e| ParseError::io(&dir, e))? {
+        let entry = entry.map_err(|e| ParseError::io(&dir, e))?;
+        let file_type = entry.file_type().map_err(|e| ParseError::io(&entry.path(), e))?;
+        let name = entry.file_name();
+        let path = relative.join(&name);
+
+        if file_type.is_dir() {
+            walk_assets(root, &path, stage, assets)?;
+            continue;
+        }
+
+        // Definition files directly in a stage directory are no assets
+        let stage_dir = stage.map(|(_, dir)| dir);
+        let definition = stage_dir == Some(relative) && STAGE_FILES.iter().any(|f| name == *f);
+        if !file_type.is_file() || definition {
+            continue;
+        }
+
+        // Hints live in the hints directory of their stage
+        let hint = stage_dir.is_some_and(|dir| dir.join("hints") == relative);
+
+        let path = path
+            .to_str()
+            .ok_or_else(|| ParseError::Structure("Invalid asset file name".into()))?
+            .to_string();
+        let content = fs::read(entry.path()).map_err(|e| ParseError::io(&entry.path(), e))?;
+
+        assets.push(Asset {
+            kind: if hint { AssetKind::Hint } else { AssetKind::Asset },
+            stage: stage.map(|(slug, _)| slug.to_string()),
+            sha256: hex::encode(Sha256::digest(&content)),
+            size: content.len() as u64,
+            path,
+        });
+    }
+
+    Ok(())
+}
+
+/// Index the starter code in the starter directory and diff the starter
+/// code of every stage having some against the stage before it, walking the
+/// stages in course order: base stages first, then the stages of every
+/// extension. The starter code before the first stage is empty.
+fn parse_starters(
+    path: &Path,
+    course: &Course,
+) -> Result<(Vec<StarterFile>, Vec<StarterDiff>), ParseError> {
+    let starter_dir = path.join("starter");
+    if !starter_dir.is_dir() {
+        return Ok(Default::default());
+    }
+
+    let mut trees = HashMap::new();
+    for entry in fs::read_dir(&starter_dir).map_err(|e| ParseError::io(&starter_dir, e))? {
+        let entry = entry.map_err(|e| ParseError::io(&starter_dir, e))?;
+        let file_type = entry.file_type().map_err(|e| ParseError::io(&entry.path(), e))?;
+        if !file_type.is_dir() {
+            continue;
+        }
+
+        let name = entry
+            .file_name()
+            .into_string()
+            .map_err(|_| ParseError::Structure("Invalid starter directory name".into()))?;
+        let mut tree = BTreeMap::new();
+        walk_starter(&entry.path(), Path::new(""), &mut tree)?;
+        trees.insert(name, tree);
+    }
+
+    let extended = course.extensions.iter().flatten().flat_map(|(_, ext)| ext.stages.values());
+    let stages: Vec<&Stage> = course.stages.values().chain(extended).collect();
+    if let Some(name) = trees.keys().find(|name| !stages.iter().any(|s| &&s.slug == name)) {
+        return Err(ParseError::Validation(format!(
+            "Starter directory '{name}' does not match any stage"
+        )));
+    }
+
+    let (mut files, mut diffs) = (Vec::new(), Vec::new());
+    let mut previous = BTreeMap::new();
+    for stage in stages {
+        let Some(tree) = trees.remove(&stage.slug) else {
+            continue;
+        };
+
+        files.extend(tree.iter().map(|(path, content)| StarterFile {
+            stage: stage.slug.clone(),
+            path: path.clone(),
+            sha256: hex::encode(Sha256::digest(content)),
+            size: content.len() as u64,
+        }));
+        diffs
+            .push(StarterDiff { stage: stage.slug.clone(), changes: diff_trees(&previous, &tree) });
+        previous = tree;
+    }
+
+    Ok((files, diffs))
+}
+
+/// Recursively read the files of a starter directory, keyed by their path
+/// relative to it. Symbolic links are skipped like for assets.
+fn walk_starter(
+    root: &Path,
+    relative: &Path,
+    tree: &mut BTreeMap<String, Vec<u8>>,
+) -> Result<(), ParseError> {
+    let dir = root.join(relative);
+    for entry in fs::read_dir(&dir).map_err(|e| ParseError::io(&dir, e))? {
+        let entry = entry.map_err(|e| ParseError::io(&dir, e))?;
+        let file_type = entry.file_type().map_err(|e| ParseError::io(&entry.path(), e))?;
+        let path = relative.join(entry.file_name());
+
+        if file_type.is_dir() {
+            walk_starter(root, &path, tree)?;
+        } else if file_type.is_file() {
+            let key = path
+                .to_str()
+                .ok_or_else(|| ParseError::Structure("Invalid starter file name".into()))?
+                .to_string();
+            let content = fs::read(entry.path()).map_err(|e| ParseError::io(&entry.path(), e))?;
+            tree.insert(key, content);
+        }
+    }
+
+    Ok(())
+}
+
+/// List the files changed between two starter trees, with their diffs.
+fn diff_trees(
+    old: &BTreeMap<String, Vec<u8>>,
+    new: &BTreeMap<String, Vec<u8>>,
+) -> Vec<StarterChange> {
+    let mut paths: Vec<&String> = old.keys().chain(new.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let (before, after) = (old.get(path), new.get(path));
+            let kind = match (before, after) {
+                (None, Some(_)) => ChangeKind::Added,
+                (Some(_), None) => ChangeKind::Removed,
+                (Some(a), Some(b)) if a != b => ChangeKind::Modified,
+                _ => return None,
+            };
+
+            // Binary files are listed without a diff
+            fn text(content: Option<&Vec<u8>>) -> Option<&str> {
+                content.map_or(Some(""), |content| std::str::from_utf8(content).ok())
+            }
+            let old_label = before.map_or("/dev/null".into(), |_| format!("a/{path}"));
+            let new_label = after.map_or("/dev/null".into(), |_| format!("b/{path}"));
+            let diff = text(before)
+                .zip(text(after))
+                .and_then(|(a, b)| diff::unified(a, b, &old_label, &new_label));
+
+            let (diff, truncated) = match diff {
+                Some(diff) if diff.len() > MAX_DIFF_SIZE => {
+                    let head = &diff.as_bytes()[..MAX_DIFF_SIZE];
+                    let end = head.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+                    (Some(diff[..end].to_string()), true)
+                }
+                diff => (diff, false),
+            };
+
+            Some(StarterChange { path: path.clone(), kind, diff, truncated })
+        })
+        .collect()
+}
+
 /// Helper function to read file with path context
 fn read_to_string(path: &Path) -> Result<String, ParseError> {
     fs::read_to_string(path).map_err(|e| ParseError::io(path, e))
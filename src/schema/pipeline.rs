@@ -0,0 +1,179 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::resources;
+
+/// Tekton Pipeline grading courses which do not pick another one.
+pub const DEFAULT_PIPELINE_REF: &str = "course-test-pipeline";
+
+/// Size of the shared workspace of courses which do not pick another one.
+pub const DEFAULT_WORKSPACE_SIZE: &str = "5Gi";
+
+/// Bounds of the shared workspace size, in bytes.
+const WORKSPACE_SIZE_RANGE: std::ops::RangeInclusive<i64> = (1 << 30)..=(1 << 40);
+
+/// Longest timeout of a PipelineRun, in seconds.
+const MAX_TIMEOUT_SECS: u64 = 24 * 60 * 60;
+
+/// How the PipelineRuns grading a course are set up, declared under
+/// `pipeline` in `course.yml`. Knobs left unset keep the defaults of the
+/// instance.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineSettings {
+    /// Name of the Tekton Pipeline the runs refer to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pipeline_ref: Option<String>,
+
+    /// Size of the shared workspace, as a Kubernetes quantity like `20Gi`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace_size: Option<String>,
+
+    /// Longest the runs may take, as a duration like `1h30m`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+
+    /// Service account the tasks of the runs run as.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_account: Option<String>,
+}
+
+impl PipelineSettings {
+    /// Whether no knob is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Name of the Tekton Pipeline the runs refer to.
+    pub fn pipeline_ref(&self) -> &str {
+        self.pipeline_ref.as_deref().unwrap_or(DEFAULT_PIPELINE_REF)
+    }
+
+    /// Size of the shared workspace.
+    pub fn workspace_size(&self) -> &str {
+        self.workspace_size.as_deref().unwrap_or(DEFAULT_WORKSPACE_SIZE)
+    }
+
+    /// Checks the knobs hold values a PipelineRun can be created with,
+    /// describing the first one which does not.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(name) = &self.pipeline_ref &&
+            !is_resource_name(name)
+        {
+            return Err(format!("pipeline_ref `{name}` is not a valid resource name"));
+        }
+        if let Some(size) = &self.workspace_size &&
+            !resources::bytes(size).is_some_and(|bytes| WORKSPACE_SIZE_RANGE.contains(&bytes))
+        {
+            return Err(format!("workspace_size `{size}` must be a quantity from 1Gi to 1Ti"));
+        }
+        if let Some(timeout) = &self.timeout &&
+            !duration_secs(timeout).is_some_and(|secs| (1..=MAX_TIMEOUT_SECS).contains(&secs))
+        {
+            return Err(format!("timeout `{timeout}` must be a duration from 1s to 24h"));
+        }
+        if let Some(name) = &self.service_account &&
+            !is_resource_name(name)
+        {
+            return Err(format!("service_account `{name}` is not a valid resource name"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether the name is a valid name of a Kubernetes resource, a DNS
+/// subdomain.
+fn is_resource_name(name: &str) -> bool {
+    let valid = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit();
+    name.len() <= 253 &&
+        name.starts_with(valid) &&
+        name.ends_with(valid) &&
+        name.chars().all(|c| valid(c) || c == '-' || c == '.')
+}
+
+/// Number of seconds of a duration in the format of Go, like `1h30m`, with
+/// whole hours, minutes and seconds.
+fn duration_secs(duration: &str) -> Option<u64> {
+    let (mut total, mut rest) = (0u64, duration);
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let value: u64 = rest[..digits].parse().ok()?;
+        let unit = match rest[digits..].chars().next()? {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        total = total.checked_add(value.checked_mul(unit)?)?;
+        rest = &rest[digits + 1..];
+    }
+
+    (!duration.is_empty()).then_some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(yaml: &str) -> PipelineSettings {
+        serde_yml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let settings = PipelineSettings::default();
+        assert!(settings.is_empty());
+        assert_eq!(settings.pipeline_ref(), "course-test-pipeline");
+        assert_eq!(settings.workspace_size(), "5Gi");
+        assert_eq!(settings.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate() {
+        let valid = settings(
+            "{ pipeline_ref: rust-pipeline, workspace_size: 20Gi, timeout: 1h30m, \
+             service_account: tester }",
+        );
+        assert_eq!(valid.validate(), Ok(()));
+        assert_eq!(valid.workspace_size(), "20Gi");
+
+        for yaml in [
+            "{ pipeline_ref: Rust_Pipeline }",
+            "{ workspace_size: 100Mi }",
+            "{ workspace_size: 2Ti }",
+            "{ workspace_size: lots }",
+            "{ timeout: 0s }",
+            "{ timeout: 25h }",
+            "{ timeout: '90' }",
+            "{ service_account: -tester }",
+        ] {
+            assert!(settings(yaml).validate().is_err(), "{yaml}");
+        }
+    }
+
+    #[test]
+    fn test_duration_secs() {
+        assert_eq!(duration_secs("1h30m"), Some(5400));
+        assert_eq!(duration_secs("45s"), Some(45));
+        assert_eq!(duration_secs("2h0m10s"), Some(7210));
+        assert_eq!(duration_secs(""), None);
+        assert_eq!(duration_secs("h"), None);
+        assert_eq!(duration_secs("1.5h"), None);
+        assert_eq!(duration_secs("1d"), None);
+        assert_eq!(duration_secs("1é"), None);
+    }
+}
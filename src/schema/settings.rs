@@ -0,0 +1,192 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+
+use crate::schema::ResourceProfile;
+
+/// Placeholder replaced by the course slug in the tester image.
+pub const COURSE_PLACEHOLDER: &str = "{course}";
+
+/// Tester image of courses which do not pin another one.
+pub const DEFAULT_TESTER_IMAGE: &str = "ghcr.io/stackclass/{course}-tester";
+
+/// Knobs of a course which may be left to a broader layer: the instance
+/// defaults, the values declared in `course.yml` and `stage.yml`, and the
+/// overrides set by admins at runtime.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CourseSettings {
+    /// Maximum number of graded attempts per stage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+
+    /// Resource profile of the test pods.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceProfile>,
+
+    /// Tester image grading the course, `{course}` stands for its slug.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tester_image: Option<String>,
+}
+
+impl CourseSettings {
+    /// The values of these settings, completed by those of `fallback` where
+    /// they are not set.
+    pub fn or(self, fallback: CourseSettings) -> CourseSettings {
+        CourseSettings {
+            max_attempts: self.max_attempts.or(fallback.max_attempts),
+            resources: self.resources.or(fallback.resources),
+            tester_image: self.tester_image.or(fallback.tester_image),
+        }
+    }
+}
+
+/// Settings a course is graded with once every layer is merged.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EffectiveSettings {
+    /// Maximum number of graded attempts per stage, none if unlimited.
+    pub max_attempts: Option<u32>,
+
+    /// Resource profile of the test pods.
+    pub resources: ResourceProfile,
+
+    /// Reference of the tester image.
+    pub tester_image: String,
+}
+
+/// Merges the settings of a course, the overrides taking precedence over the
+/// course values, which take precedence over the instance defaults. Knobs
+/// none of them sets get the built-in values.
+pub fn resolve_settings(
+    course: &str,
+    defaults: &CourseSettings,
+    values: &CourseSettings,
+    overrides: &CourseSettings,
+) -> EffectiveSettings {
+    let merged = overrides.clone().or(values.clone()).or(defaults.clone());
+    let tester_image = merged.tester_image.as_deref().unwrap_or(DEFAULT_TESTER_IMAGE);
+
+    EffectiveSettings {
+        max_attempts: merged.max_attempts,
+        resources: merged.resources.unwrap_or_default(),
+        tester_image: tester_image.replace(COURSE_PLACEHOLDER, course),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(max_attempts: Option<u32>, resources: Option<ResourceProfile>) -> CourseSettings {
+        CourseSettings { max_attempts, resources, tester_image: None }
+    }
+
+    fn image(tester_image: &str) -> CourseSettings {
+        CourseSettings { tester_image: Some(tester_image.to_string()), ..Default::default() }
+    }
+
+    #[test]
+    fn test_builtin_values() {
+        let none = CourseSettings::default();
+        let effective = resolve_settings("redis", &none, &none, &none);
+
+        assert_eq!(effective.max_attempts, None);
+        assert_eq!(effective.resources, ResourceProfile::Small);
+        assert_eq!(effective.tester_image, "ghcr.io/stackclass/redis-tester");
+    }
+
+    #[test]
+    fn test_each_layer_alone() {
+        let none = CourseSettings::default();
+        let set = settings(Some(3), Some(ResourceProfile::Large));
+
+        for (defaults, values, overrides) in
+            [(&set, &none, &none), (&none, &set, &none), (&none, &none, &set)]
+        {
+            let effective = resolve_settings("redis", defaults, values, overrides);
+            assert_eq!(effective.max_attempts, Some(3));
+            assert_eq!(effective.resources, ResourceProfile::Large);
+        }
+    }
+
+    #[test]
+    fn test_precedence() {
+        let defaults = settings(Some(1), Some(ResourceProfile::Small));
+        let values = settings(Some(2), Some(ResourceProfile::Medium));
+        let overrides = settings(Some(3), Some(ResourceProfile::Large));
+
+        let effective = resolve_settings("redis", &defaults, &values, &overrides);
+        assert_eq!(
+            (effective.max_attempts, effective.resources),
+            (Some(3), ResourceProfile::Large)
+        );
+
+        let effective = resolve_settings("redis", &defaults, &values, &CourseSettings::default());
+        assert_eq!(
+            (effective.max_attempts, effective.resources),
+            (Some(2), ResourceProfile::Medium)
+        );
+
+        let effective =
+            resolve_settings("redis", &defaults, &CourseSettings::default(), &overrides);
+        assert_eq!(
+            (effective.max_attempts, effective.resources),
+            (Some(3), ResourceProfile::Large)
+        );
+    }
+
+    #[test]
+    fn test_knobs_resolve_independently() {
+        let defaults = settings(Some(1), Some(ResourceProfile::Medium));
+        let values = settings(Some(2), None);
+        let overrides = settings(None, Some(ResourceProfile::Large));
+
+        let effective = resolve_settings("redis", &defaults, &values, &overrides);
+        assert_eq!(effective.max_attempts, Some(2));
+        assert_eq!(effective.resources, ResourceProfile::Large);
+
+        let effective = resolve_settings("redis", &defaults, &values, &CourseSettings::default());
+        assert_eq!(effective.resources, ResourceProfile::Medium);
+    }
+
+    #[test]
+    fn test_tester_image() {
+        let none = CourseSettings::default();
+        let defaults = image("registry.local/{course}-tester");
+        let pinned = image("ghcr.io/stackclass/redis-tester:v2");
+
+        let effective = resolve_settings("redis", &defaults, &none, &none);
+        assert_eq!(effective.tester_image, "registry.local/redis-tester");
+
+        let effective = resolve_settings("redis", &defaults, &pinned, &none);
+        assert_eq!(effective.tester_image, "ghcr.io/stackclass/redis-tester:v2");
+
+        let effective =
+            resolve_settings("redis", &defaults, &pinned, &image("{course}-tester:canary"));
+        assert_eq!(effective.tester_image, "redis-tester:canary");
+    }
+
+    #[test]
+    fn test_or() {
+        let merged = settings(Some(2), None).or(image("tester"));
+        assert_eq!(
+            merged,
+            CourseSettings {
+                max_attempts: Some(2),
+                resources: None,
+                tester_image: Some("tester".to_string())
+            }
+        );
+    }
+}
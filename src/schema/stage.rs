@@ -40,6 +40,22 @@ pub struct Stage {
     /// Detailed description of the solution approach and logic, if available.
     #[serde(skip)]
     pub solution: Option<String>,
+
+    /// Markdown hints revealed to learners one at a time, in order.
+    #[serde(skip)]
+    pub hints: Vec<String>,
+
+    /// Maximum number of graded attempts, overriding the course default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+
+    /// Resource profile of the test pod, the default one if not declared.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceProfile>,
+
+    /// Whether learners may skip the stage without passing its tests.
+    #[serde(default)]
+    pub optional: bool,
 }
 
 impl Hash for Stage {
@@ -78,6 +94,44 @@ impl fmt::Display for Difficulty {
     }
 }
 
+/// A named set of CPU and memory requests and limits for the test pod,
+/// defined in the server configuration.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceProfile {
+    #[default]
+    Small,
+    Medium,
+    Large,
+}
+
+impl ResourceProfile {
+    /// All profiles, from the smallest to the largest.
+    pub const ALL: [ResourceProfile; 3] =
+        [ResourceProfile::Small, ResourceProfile::Medium, ResourceProfile::Large];
+}
+
+impl fmt::Display for ResourceProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResourceProfile::Small => write!(f, "small"),
+            ResourceProfile::Medium => write!(f, "medium"),
+            ResourceProfile::Large => write!(f, "large"),
+        }
+    }
+}
+
+impl FromStr for ResourceProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|profile| profile.to_string() == s)
+            .ok_or_else(|| format!("unknown resource profile: {s}"))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -96,6 +150,55 @@ mod tests {
         assert_eq!(stage.name, "Test Stage");
         assert_eq!(stage.difficulty, Difficulty::Easy);
         assert_eq!(stage.description, "A test stage");
+        assert_eq!(stage.max_attempts, None);
+        assert_eq!(stage.resources, None);
+        assert!(!stage.optional);
+    }
+
+    #[test]
+    fn test_stage_optional() {
+        let yaml = r#"
+            slug: test-stage
+            name: Test Stage
+            difficulty: medium
+            description: A test stage
+            optional: true
+        "#;
+
+        let stage = Stage::from_str(yaml).unwrap();
+        assert!(stage.optional);
+    }
+
+    #[test]
+    fn test_stage_max_attempts() {
+        let yaml = r#"
+            slug: test-stage
+            name: Test Stage
+            difficulty: hard
+            description: A test stage
+            max_attempts: 20
+        "#;
+
+        let stage = Stage::from_str(yaml).unwrap();
+        assert_eq!(stage.max_attempts, Some(20));
+    }
+
+    #[test]
+    fn test_stage_resources() {
+        let yaml = r#"
+            slug: test-stage
+            name: Test Stage
+            difficulty: hard
+            description: A test stage
+            resources: large
+        "#;
+
+        let stage = Stage::from_str(yaml).unwrap();
+        assert_eq!(stage.resources, Some(ResourceProfile::Large));
+
+        // Unknown profiles are rejected when the course is imported
+        let yaml = yaml.replace("large", "huge");
+        assert!(Stage::from_str(&yaml).is_err());
     }
 
     #[test]
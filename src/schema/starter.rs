@@ -0,0 +1,95 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// A file of the starter code of a stage.
+///
+/// Courses may ship the starter code learners work from at each stage in an
+/// optional `starter` directory at the course root. It holds one directory
+/// per stage, named after the stage slug, with the complete starter tree of
+/// that stage:
+///
+/// ```text
+/// starter/
+/// ├── bind-to-port/src/main.rs
+/// └── respond-to-ping/
+///     ├── src/main.rs
+///     └── src/resp.rs
+/// ```
+///
+/// Stages without a directory keep the starter code of the stage before
+/// them, in course order.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StarterFile {
+    /// Slug of the stage the file belongs to.
+    pub stage: String,
+
+    /// Path of the file relative to the starter directory of the stage.
+    pub path: String,
+
+    /// Hex encoded SHA-256 hash of the content.
+    pub sha256: String,
+
+    /// Size of the content in bytes.
+    pub size: u64,
+}
+
+/// What the starter code of a stage changes compared to the previous stage
+/// with starter code, computed on import.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StarterDiff {
+    /// Slug of the stage.
+    pub stage: String,
+
+    /// Changed files, ordered by path.
+    pub changes: Vec<StarterChange>,
+}
+
+/// A file added, modified or removed by the starter code of a stage.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StarterChange {
+    /// Path of the file relative to the starter directory.
+    pub path: String,
+
+    /// How the file changed.
+    pub kind: ChangeKind,
+
+    /// Unified diff of the file, none for binary or very large files.
+    pub diff: Option<String>,
+
+    /// Whether the diff was cut off at the size limit.
+    pub truncated: bool,
+}
+
+/// How a starter file changed.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Removed,
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangeKind::Added => write!(f, "added"),
+            ChangeKind::Modified => write!(f, "modified"),
+            ChangeKind::Removed => write!(f, "removed"),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use crate::{
+    context::Context,
+    errors::Result,
+    model::AuditLogModel,
+    repository::AuditRepository,
+    request::AuditLogQuery,
+    response::{AuditLogResponse, Paginated},
+    utils::pagination::{Cursor, Page},
+};
+
+/// Service for reading the audit trail
+pub struct AuditService;
+
+impl AuditService {
+    /// Find a page of the audit trail, newest first.
+    pub async fn find(
+        ctx: Arc<Context>,
+        query: &AuditLogQuery,
+        page: &Page,
+    ) -> Result<Paginated<AuditLogResponse>> {
+        let AuditLogQuery { actor, action, target } = query;
+        let logs = AuditRepository::find(
+            &ctx.database,
+            actor.as_deref(),
+            action.as_deref(),
+            target.as_deref(),
+            page,
+        )
+        .await?;
+
+        let cursor = |log: &AuditLogModel| Cursor::new(log.created_at, log.seq);
+        Ok(Paginated::from_rows(logs, page, cursor, ctx.clock.now(), &ctx.config.auth_secret)?)
+    }
+}
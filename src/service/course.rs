@@ -12,81 +12,256 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::HashSet, sync::Arc};
-use tracing::{debug, error, info};
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt, stream};
+use gitea_client::{ClientError, types::ListCommitsOptions};
+use serde_json::json;
+use std::{
+    collections::HashSet,
+    io::SeekFrom,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 use crate::{
-    config::Config,
+    config::{Config, ExecutionBackend},
     context::Context,
     database::Transaction,
     errors::{ApiError, Result},
-    model::{CourseModel, ExtensionModel, StageModel, UserCourseModel, UserStageModel},
-    repository::{CourseRepository, ExtensionRepository, StageRepository},
-    request::{CreateUserCourseRequest, UpdateUserCourseRequest},
-    response::{AttemptResponse, CourseDetailResponse, CourseResponse, UserCourseResponse},
+    jobs::CreateCourses,
+    model::{
+        AuditLogModel, CourseJobModel, CourseModel, CourseRevisionModel, ExtensionModel,
+        StageModel, UserCourseModel, UserStageModel,
+    },
+    repository::{
+        AssetRepository, AuditRepository, CourseJobRepository, CourseRepository,
+        CourseRevisionRepository, ExtensionRepository, ProgressRepository, StageRepository,
+        UserRepository,
+    },
+    request::{
+        CourseAttemptQuery, CourseQuery, CourseSearchQuery, CreateCourseRequest,
+        CreateUserCourseRequest, ExamWindowRequest, ExtendDeadlineRequest, ResetUserCourseRequest,
+        UpdateCourseRequest, UpdateUserCourseRequest,
+    },
+    response::{
+        AssetBody, AssetContent, AttemptResponse, CourseDeletionResponse, CourseDetailResponse,
+        CourseExportResponse, CourseJobResponse, CourseResponse, CourseRevisionResponse,
+        CourseSourceResponse, CourseStatsResponse, GitIdentityVerificationResponse,
+        MaintainerResponse, NumberedPage, OfflineManifestResponse, ProgressResponse,
+        RepositorySyncResponse, StageSourceResponse, StageSourceSummary, UserCourseResponse,
+    },
     schema::{self, Course, Stage},
-    service::storage::StorageService,
+    service::storage::{self, StorageError, StorageService},
+    utils::{crypto, pagination, range, status::StatusUpdate},
 };
 
-use super::RepoService;
+/// Path of the file holding the git identity verification token.
+pub const IDENTITY_FILE: &str = ".stackclass/identity";
+
+/// Number of learner repositories synced with their template at once.
+const SYNC_CONCURRENCY: usize = 4;
+
+use super::{PipelineService, RegistryService, RepoService, Teardown, TemplateUpdate};
 
 /// Service for managing courses and related entities
 pub struct CourseService;
 
 impl CourseService {
-    /// Fetch all courses from repository
-    pub async fn find(ctx: Arc<Context>) -> Result<Vec<CourseResponse>> {
-        let courses = CourseRepository::find(&ctx.database).await?;
-        Ok(courses.into_iter().map(Into::into).collect())
+    /// Find a page of the released courses (beta and live status). Without
+    /// a page size all of them are on the first page.
+    pub async fn find(
+        ctx: Arc<Context>,
+        query: &CourseQuery,
+    ) -> Result<NumberedPage<CourseResponse>> {
+        let limit = query.per_page.map(|per_page| pagination::clamp_limit(Some(per_page)));
+        let page = query.page.unwrap_or(1);
+        let offset = page_offset(page, limit.unwrap_or(0))?;
+        let page = if limit.is_some() { page } else { 1 };
+
+        let status = query.release_status.as_ref().map(ToString::to_string);
+        let (courses, total) =
+            CourseRepository::find_paginated(&ctx.database, status.as_deref(), offset, limit)
+                .await?;
+
+        Ok(NumberedPage {
+            items: courses.into_iter().map(Into::into).collect(),
+            total,
+            page,
+            per_page: limit.unwrap_or(total),
+        })
     }
 
-    /// Find all released courses (beta and live status)
-    pub async fn find_released(ctx: Arc<Context>) -> Result<Vec<CourseResponse>> {
-        let courses = CourseRepository::find_released(&ctx.database).await?;
+    /// Search the released courses by keywords, most relevant first.
+    pub async fn search(
+        ctx: Arc<Context>,
+        query: &CourseSearchQuery,
+    ) -> Result<Vec<CourseResponse>> {
+        let keywords = query.q.trim();
+        if !(2..=100).contains(&keywords.chars().count()) {
+            return Err(ApiError::BadRequest("q must be between 2 and 100 characters".into()));
+        }
+
+        let limit = pagination::clamp_limit(query.limit);
+        let courses = CourseRepository::search(&ctx.database, keywords, limit).await?;
         Ok(courses.into_iter().map(Into::into).collect())
     }
 
-    /// Create new course from git repository URL
-    pub async fn create(ctx: Arc<Context>, repository: &str) -> Result<CourseResponse> {
+    /// Queue the creation of a course from its git repository URL, returning
+    /// the job to follow its progress with.
+    pub async fn create(ctx: Arc<Context>, req: &CreateCourseRequest) -> Result<CourseJobResponse> {
+        let reference = req.reference.as_deref().map(str::trim).filter(|r| !r.is_empty());
+        let job = CourseJobModel::new_at(&req.repository, reference, ctx.clock.now());
+        let job = CourseJobRepository::create(&ctx.database, &job).await?;
+
+        ctx.jobs.trigger(CreateCourses::NAME);
+        Ok(job.into())
+    }
+
+    /// Get a course creation job by its ID.
+    pub async fn get_job(ctx: Arc<Context>, id: Uuid) -> Result<CourseJobResponse> {
+        Ok(CourseJobRepository::get(&ctx.database, id).await?.into())
+    }
+
+    /// Create the courses queued for creation one after the other, returning
+    /// how many were processed.
+    pub async fn create_pending(ctx: Arc<Context>) -> Result<usize> {
+        let mut total = 0;
+        while let Some(job) = CourseJobRepository::claim_pending(&ctx.database).await? {
+            Self::run_job(ctx.clone(), job).await?;
+            total += 1;
+        }
+
+        Ok(total)
+    }
+
+    /// Carry out a course creation job and record its outcome. A course the
+    /// job created is deleted again when its template cannot be set up.
+    async fn run_job(ctx: Arc<Context>, job: CourseJobModel) -> Result<()> {
+        match Self::import(ctx.clone(), &job).await {
+            Ok(slug) => {
+                info!("Course creation job {} completed: {slug}", job.id);
+                CourseJobRepository::complete(&ctx.database, job.id, &slug).await?;
+            }
+            Err(e) => {
+                warn!("Course creation job {} failed: {e}", job.id);
+                CourseJobRepository::fail(&ctx.database, job.id, &e.to_string()).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create the course of a job from its git repository, advancing the job
+    /// through each step. Returns the slug of the course.
+    async fn import(ctx: Arc<Context>, job: &CourseJobModel) -> Result<String> {
         let Config { cache_dir, github_token, .. } = &ctx.config;
 
         let storage = StorageService::new(cache_dir, github_token)?;
-        let dir = storage.fetch(repository).await?;
+        let reference = job.reference.as_deref();
+        let (dir, commit) = Self::fetch_repository(&storage, &job.repository, reference).await?;
 
+        CourseJobRepository::advance(&ctx.database, job.id, "parsing").await?;
         let course = schema::parse(&cache_dir.join(dir))?;
         debug!("Parsed course: {:?}", course.name);
 
-        if let Ok(model) = CourseRepository::get_by_slug(&ctx.database, &course.slug).await {
+        if CourseRepository::get_by_slug(&ctx.database, &course.slug).await.is_ok() {
             info!("Course already exists: {:?}", course.name);
-            return Ok(model.into());
+            return Ok(course.slug);
         }
 
-        let model = Self::create_course(ctx.clone(), &course, repository).await?;
+        Self::check_pipeline(&ctx, &course).await?;
+        Self::create_course(ctx.clone(), &course, &job.repository, reference, &commit).await?;
         info!("Successfully created course: {:?}", course.name);
 
+        CourseJobRepository::advance(&ctx.database, job.id, "pushing_template").await?;
+        if let Err(e) = Self::setup_template(&ctx, &course, &job.repository).await {
+            // Leave no half created course behind, a later job may retry
+            if let Err(e) = Self::delete(ctx.clone(), &course.slug, true).await {
+                error!("Failed to roll back course {}: {e}", course.slug);
+            }
+            return Err(e);
+        }
+
+        Ok(course.slug)
+    }
+
+    /// Initialize the template repository of a new course, and its registry
+    /// credentials when images are built.
+    async fn setup_template(ctx: &Arc<Context>, course: &Course, repository: &str) -> Result<()> {
         RepoService::new(ctx.clone()).init(&course.slug, repository).await?;
         info!("Successfully initialized template repository for course: {:?}", course.name);
 
-        Ok(model.into())
+        // Standalone deployments have no registry to push images to
+        if ctx.config.execution_backend == ExecutionBackend::Tekton {
+            RegistryService::provision(ctx, &course.slug, "admin").await?;
+            info!("Successfully provisioned registry credentials for course: {:?}", course.name);
+        }
+
+        Ok(())
+    }
+
+    /// Check the Tekton Pipeline the course picks exists, failing before
+    /// anything is stored rather than on every attempt.
+    async fn check_pipeline(ctx: &Arc<Context>, course: &Course) -> Result<()> {
+        match &course.pipeline.pipeline_ref {
+            Some(name) if ctx.config.execution_backend == ExecutionBackend::Tekton => {
+                PipelineService::new(ctx.clone()).ensure_pipeline(name).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Fetch the course repository at a branch, tag or commit SHA, or at the
+    /// head of its default branch without one.
+    async fn fetch_repository(
+        storage: &StorageService,
+        url: &str,
+        reference: Option<&str>,
+    ) -> Result<(PathBuf, String)> {
+        let fetched = match reference {
+            Some(reference) => storage.fetch_ref(url, reference).await,
+            None => storage.fetch(url).await,
+        };
+
+        fetched.map_err(|e| match e {
+            StorageError::UnknownReference(reference) => {
+                ApiError::BadRequest(format!("Unknown reference: {reference}"))
+            }
+            e => e.into(),
+        })
     }
 
     /// Create course with all related entities in transaction
-    async fn create_course(ctx: Arc<Context>, course: &Course, url: &str) -> Result<CourseModel> {
+    async fn create_course(
+        ctx: Arc<Context>,
+        course: &Course,
+        url: &str,
+        reference: Option<&str>,
+        commit: &str,
+    ) -> Result<CourseModel> {
         let mut tx = ctx.database.pool().begin().await?;
+        Self::lock(&mut tx, &course.slug).await?;
 
         // Persist the course
         let course_model = CourseModel::from(course)
             .with_repository(url)
+            .with_reference(reference)
+            .with_commit(commit)
             .with_stage_count(calculate_total_stages(course));
-        let course_model = CourseRepository::create(&mut tx, &course_model).await?;
+        let course_model = CourseRepository::create(&mut tx, &course_model)
+            .await
+            .map_err(ApiError::on_duplicate(ApiError::CourseExists))?;
 
-        // Persist stages and their solutions with weight
+        // Persist stages and their solutions with position
         for (index, (_, stage)) in course.stages.iter().enumerate() {
-            Self::create_stage(&mut tx, stage, course_model.id, None, index as i32).await?;
+            let position = index as i32;
+            Self::create_stage(&mut tx, stage, course_model.id, None, position, position).await?;
         }
 
-        // Persist extensions and their stages with weight
+        // Persist extensions and their stages with position
         if let Some(extensions) = &course.extensions {
             for (index, (_, ext)) in extensions.iter().enumerate() {
                 let ext_model = ExtensionModel::from(ext.clone())
@@ -97,46 +272,90 @@ impl CourseService {
 
                 for (stage_index, (_, stage)) in ext.stages.iter().enumerate() {
                     let weight = ((index + 1) * 1000 + stage_index) as i32;
-                    Self::create_stage(&mut tx, stage, course_model.id, Some(ext_model.id), weight)
+                    let (ext_id, position) = (Some(ext_model.id), stage_index as i32);
+                    Self::create_stage(&mut tx, stage, course_model.id, ext_id, position, weight)
                         .await?;
                 }
             }
         }
 
+        // Index the assets, now that the stages they belong to exist
+        AssetRepository::replace(&mut tx, course_model.id, &course.assets).await?;
+        AssetRepository::replace_starters(
+            &mut tx,
+            course_model.id,
+            &course.starter_files,
+            &course.starter_diffs,
+        )
+        .await?;
+
         // Commits this transaction
         tx.commit().await?;
 
         Ok(course_model)
     }
 
+    /// Take the lock of a course for the transaction, so that its syncs never
+    /// interleave, failing with a conflict while another one runs.
+    async fn lock(tx: &mut Transaction<'_>, slug: &str) -> Result<()> {
+        if !CourseRepository::try_lock(tx, slug).await? {
+            return Err(ApiError::Conflict(format!(
+                "A sync of course {slug} is already in progress"
+            )));
+        }
+
+        Ok(())
+    }
+
     /// Create stage
     async fn create_stage(
         tx: &mut Transaction<'_>,
         stage: &Stage,
         course_id: Uuid,
         ext_id: Option<Uuid>,
+        position: i32,
         weight: i32,
     ) -> Result<()> {
-        let mut stage_model =
-            StageModel::from(stage.clone()).with_course(course_id).with_weight(weight);
+        let mut stage_model = StageModel::from(stage.clone())
+            .with_course(course_id)
+            .with_position(position)
+            .with_weight(weight);
 
         if let Some(extension_id) = ext_id {
             stage_model = stage_model.with_extension(extension_id);
         }
 
-        let _ = StageRepository::create(tx, &stage_model).await?;
+        let stage_model = StageRepository::create(tx, &stage_model).await?;
+        StageRepository::replace_hints(tx, stage_model.id, &stage.hints).await?;
 
         Ok(())
     }
 
-    /// Get course by slug
-    pub async fn get(ctx: Arc<Context>, slug: &str) -> Result<CourseDetailResponse> {
-        let course = CourseRepository::get_by_slug(&ctx.database, slug).await?;
-        Ok(course.into())
+    /// Get course by slug, flagging whether the given user maintains it
+    pub async fn get(
+        ctx: Arc<Context>,
+        slug: &str,
+        user_id: Option<&str>,
+    ) -> Result<CourseDetailResponse> {
+        let db = &ctx.database;
+        let course = CourseRepository::get_by_slug(db, slug).await?;
+
+        let maintainer = match user_id {
+            Some(user_id) => CourseRepository::is_maintainer(db, slug, user_id).await?,
+            None => false,
+        };
+
+        Ok(CourseDetailResponse { maintainer: maintainer.then_some(true), ..course.into() })
     }
 
-    /// Update course from git repository URL
-    pub async fn update(ctx: Arc<Context>, slug: &str) -> Result<bool> {
+    /// Update course from git repository URL, at the reference it is pinned
+    /// to unless the request pins it to another one.
+    pub async fn update(
+        ctx: Arc<Context>,
+        slug: &str,
+        req: &UpdateCourseRequest,
+        actor: &str,
+    ) -> Result<bool> {
         let Ok(model) = CourseRepository::get_by_slug(&ctx.database, slug).await else {
             error!("Course not found: {:?}", slug);
             return Err(ApiError::NotFound);
@@ -144,46 +363,74 @@ impl CourseService {
 
         let Config { cache_dir, github_token, .. } = &ctx.config;
 
+        // An empty reference unpins the course, none keeps the current pin
+        let reference = match req.reference.as_deref().map(str::trim) {
+            Some("") => None,
+            Some(reference) => Some(reference),
+            None => model.reference.as_deref(),
+        };
+
         let storage = StorageService::new(cache_dir, github_token)?;
-        let dir = storage.fetch(&model.repository).await?;
+        let (dir, commit) = Self::fetch_repository(&storage, &model.repository, reference).await?;
 
         let course = schema::parse(&cache_dir.join(dir))?;
         debug!("Parsed course: {:?}", course.name);
 
-        Self::update_course(ctx.clone(), &course).await?;
-        info!("Successfully updated course: {:?}", model.name);
+        Self::check_pipeline(&ctx, &course).await?;
+        Self::update_course(ctx.clone(), &course, &commit, actor).await?;
+        if reference != model.reference.as_deref() {
+            CourseRepository::set_reference(&ctx.database, slug, reference).await?;
+        }
+        info!("Successfully updated course: {:?} at {}", model.name, commit);
 
-        RepoService::new(ctx).init(&course.slug, &model.repository).await?;
-        info!("Template repository for course {:?} has been synced", course.name);
+        let outcome = RepoService::new(ctx).init(&course.slug, &model.repository).await?;
+        info!("Template repository for course {:?} has been synced: {}", course.name, outcome);
 
         Ok(true)
     }
 
-    /// Update course and related entities with cleanup
-    async fn update_course(ctx: Arc<Context>, course: &Course) -> Result<()> {
+    /// Update course and related entities with cleanup, all or nothing. The
+    /// stages the sync added, removed or changed are recorded as a revision
+    /// of the course.
+    pub async fn update_course(
+        ctx: Arc<Context>,
+        course: &Course,
+        commit: &str,
+        actor: &str,
+    ) -> Result<()> {
+        let slug = &course.slug;
         let mut tx = ctx.database.pool().begin().await?;
+        Self::lock(&mut tx, slug).await?;
 
-        // Fetch existing stages and extensions
-        let slug = &course.slug;
+        // Fetch existing stages and extensions, as left by the previous sync
         let existing_stages = StageRepository::find_by_course(&ctx.database, slug).await?;
         let existing_exts = ExtensionRepository::find_by_course(&ctx.database, slug).await?;
+        let existing_structure = stage_structure(&existing_stages);
+        let (added, removed, updated) = stage_changes(&existing_stages, course);
 
         // Update the course
-        let course_model =
-            CourseModel::from(course).with_stage_count(calculate_total_stages(course));
+        let course_model = CourseModel::from(course)
+            .with_commit(commit)
+            .with_stage_count(calculate_total_stages(course));
         let course_model = CourseRepository::update(&mut tx, &course_model).await?;
 
+        // Reordered extensions and stages take the place of others, so the
+        // current order is moved out of the way before the new one is written
+        ExtensionRepository::shift_weights(&mut tx, course_model.id).await?;
+        StageRepository::shift_positions(&mut tx, course_model.id).await?;
+
         // Track current slugs for cleanup
         let mut current_stage_slugs = HashSet::new();
         let mut current_extension_slugs = HashSet::new();
 
-        // Update and track base stages with weight
+        // Update and track base stages with position
         for (index, (_, stage)) in course.stages.iter().enumerate() {
-            Self::update_stage(&mut tx, stage, course_model.id, None, index as i32).await?;
+            let position = index as i32;
+            Self::update_stage(&mut tx, stage, course_model.id, None, position, position).await?;
             current_stage_slugs.insert(stage.slug.clone());
         }
 
-        // Update and track extension stages with weight
+        // Update and track extension stages with position
         if let Some(extensions) = &course.extensions {
             for (index, (_, ext)) in extensions.iter().enumerate() {
                 let ext_model = ExtensionModel::from(ext.clone())
@@ -195,7 +442,8 @@ impl CourseService {
                 // Upsert extension stages and their solutions
                 for (stage_index, (_, stage)) in ext.stages.iter().enumerate() {
                     let weight = ((index + 1) * 1000 + stage_index) as i32;
-                    Self::update_stage(&mut tx, stage, course_model.id, Some(ext_model.id), weight)
+                    let (ext_id, position) = (Some(ext_model.id), stage_index as i32);
+                    Self::update_stage(&mut tx, stage, course_model.id, ext_id, position, weight)
                         .await?;
                     current_stage_slugs.insert(stage.slug.clone());
                 }
@@ -204,17 +452,25 @@ impl CourseService {
             }
         }
 
-        // Cleanup orphaned stages (both base and extension stages)
+        // Cleanup orphaned stages (both base and extension stages), those
+        // learners reached are archived so their progress is kept
         debug!(
             "Existing stage slugs: {:?}, Current stage slugs: {:?}",
             existing_stages.iter().map(|s| &s.slug).collect::<Vec<_>>(),
             current_stage_slugs
         );
         for existing_stage in existing_stages {
-            if !current_stage_slugs.contains(&existing_stage.slug) {
+            if current_stage_slugs.contains(&existing_stage.slug) {
+                continue;
+            }
+            if StageRepository::is_referenced(&mut tx, existing_stage.id).await? {
+                info!("Archiving stage {:?} still referenced by learners", existing_stage.slug);
+                StageRepository::archive(&mut tx, &existing_stage.slug).await?;
+            } else {
                 StageRepository::delete(&mut tx, &existing_stage.slug).await?;
             }
         }
+        StageRepository::delete_unreferenced(&mut tx, course_model.id).await?;
 
         // Cleanup orphaned extensions
         debug!(
@@ -224,39 +480,374 @@ impl CourseService {
         );
         for existing_extension in existing_exts {
             if !current_extension_slugs.contains(&existing_extension.slug) {
+                StageRepository::detach_archived(&mut tx, &existing_extension.slug).await?;
                 ExtensionRepository::delete(&mut tx, &existing_extension.slug).await?;
             }
         }
 
+        // Re-index the assets against the synced stages
+        AssetRepository::replace(&mut tx, course_model.id, &course.assets).await?;
+        AssetRepository::replace_starters(
+            &mut tx,
+            course_model.id,
+            &course.starter_files,
+            &course.starter_diffs,
+        )
+        .await?;
+
+        // Record what the sync changed
+        let revision = CourseRevisionModel {
+            stages_added: added,
+            stages_removed: removed,
+            stages_updated: updated,
+            ..CourseRevisionModel::new_at(course_model.id, commit, actor, ctx.clock.now())
+        };
+        CourseRevisionRepository::create(&mut tx, &revision).await?;
+
+        // Rebuild progress summaries when stages were added, removed or moved
+        // between extensions, since completed counts per extension may change.
+        if course_structure(course) != existing_structure {
+            let rebuilt = ProgressRepository::rebuild(&mut tx, slug).await?;
+            info!(
+                "Stage structure of course {:?} changed, rebuilt {} progress summaries",
+                slug, rebuilt
+            );
+        }
+
         // Commits this transaction
         tx.commit().await?;
 
         Ok(())
     }
 
-    /// Update stage and handle solution changes
+    /// Update stage and handle solution and hint changes
     async fn update_stage(
         tx: &mut Transaction<'_>,
         stage: &Stage,
         course_id: Uuid,
         ext_id: Option<Uuid>,
+        position: i32,
         weight: i32,
     ) -> Result<()> {
-        let mut stage_model =
-            StageModel::from(stage.clone()).with_course(course_id).with_weight(weight);
+        let mut stage_model = StageModel::from(stage.clone())
+            .with_course(course_id)
+            .with_position(position)
+            .with_weight(weight);
 
         if let Some(extension_id) = ext_id {
             stage_model = stage_model.with_extension(extension_id);
         }
 
-        let _ = StageRepository::upsert(tx, &stage_model).await?;
+        let stage_model = StageRepository::upsert(tx, &stage_model).await?;
+        StageRepository::replace_hints(tx, stage_model.id, &stage.hints).await?;
 
         Ok(())
     }
 
-    /// Delete course by slug
-    pub(crate) async fn delete(ctx: Arc<Context>, slug: &str) -> Result<()> {
-        CourseRepository::delete(&ctx.database, slug).await.map_err(ApiError::DatabaseError)
+    /// Export a course as imported, with the instructions and solutions of
+    /// its stages, base stages and extensions each in course order.
+    pub async fn export(ctx: Arc<Context>, slug: &str) -> Result<CourseExportResponse> {
+        let course = CourseRepository::get_by_slug(&ctx.database, slug).await?;
+        let stages = StageRepository::find_by_course(&ctx.database, slug).await?;
+        let extensions = ExtensionRepository::find_by_course(&ctx.database, slug).await?;
+        Ok(CourseExportResponse::new(course, stages, extensions))
+    }
+
+    /// Fetch the revisions of a course, newest first.
+    pub async fn find_revisions(
+        ctx: Arc<Context>,
+        slug: &str,
+    ) -> Result<Vec<CourseRevisionResponse>> {
+        CourseRepository::get_by_slug(&ctx.database, slug).await?;
+        let revisions = CourseRevisionRepository::find_by_course(&ctx.database, slug).await?;
+        Ok(revisions.into_iter().map(Into::into).collect())
+    }
+
+    /// Get the course source as parsed from the last imported commit
+    pub async fn get_source(ctx: Arc<Context>, slug: &str) -> Result<CourseSourceResponse> {
+        let model = CourseRepository::get_by_slug(&ctx.database, slug).await?;
+        let root = Self::source_dir(&ctx, &model).await?;
+        let course = schema::parse(&root)?;
+
+        let stages = source_stages(&course)
+            .into_iter()
+            .map(|(extension, path, stage)| {
+                Ok(StageSourceSummary {
+                    slug: stage.slug.clone(),
+                    extension: extension.map(str::to_string),
+                    path,
+                    front_matter: serde_json::to_value(stage)
+                        .map_err(ApiError::SerializationError)?,
+                    content_hash: StageModel::from(stage.clone()).content_hash,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let extensions_yml = match course.extensions {
+            Some(_) => Some(read_source(&root, "extensions.yml").await?),
+            None => None,
+        };
+
+        Ok(CourseSourceResponse {
+            commit: model.commit_sha,
+            course_yml: read_source(&root, "course.yml").await?,
+            course: serde_json::to_value(&course).map_err(ApiError::SerializationError)?,
+            extensions_yml,
+            stages,
+        })
+    }
+
+    /// Get the raw files of a stage as stored for the last imported commit
+    pub async fn get_stage_source(
+        ctx: Arc<Context>,
+        slug: &str,
+        stage_slug: &str,
+    ) -> Result<StageSourceResponse> {
+        let model = CourseRepository::get_by_slug(&ctx.database, slug).await?;
+        let root = Self::source_dir(&ctx, &model).await?;
+        let course = schema::parse(&root)?;
+
+        let (_, path, stage) = source_stages(&course)
+            .into_iter()
+            .find(|(_, _, stage)| stage.slug == stage_slug)
+            .ok_or(ApiError::NotFound)?;
+
+        let solution = format!("{path}/solution.md");
+        let solution_md = match root.join(&solution).exists() {
+            true => Some(read_source(&root, &solution).await?),
+            false => None,
+        };
+
+        Ok(StageSourceResponse {
+            slug: stage.slug.clone(),
+            stage_yml: read_source(&root, &format!("{path}/stage.yml")).await?,
+            instruction_md: read_source(&root, &format!("{path}/instruction.md")).await?,
+            solution_md,
+            content_hash: StageModel::from(stage.clone()).content_hash,
+            path,
+        })
+    }
+
+    /// Locate the cached directory of the commit the course was imported at,
+    /// fetching it again when it has been removed from the cache.
+    async fn source_dir(ctx: &Context, model: &CourseModel) -> Result<PathBuf> {
+        if model.commit_sha.is_empty() {
+            return Err(ApiError::NotFound);
+        }
+
+        let Config { cache_dir, github_token, .. } = &ctx.config;
+        let storage = StorageService::new(cache_dir, github_token)?;
+        let dir = storage.fetch_commit(&model.repository, &model.commit_sha).await?;
+
+        Ok(cache_dir.join(dir))
+    }
+
+    /// Get the offline manifest of a course, built from the asset index
+    /// without touching the course repository.
+    pub async fn get_offline_manifest(
+        ctx: Arc<Context>,
+        slug: &str,
+    ) -> Result<OfflineManifestResponse> {
+        let db = &ctx.database;
+        let course = CourseRepository::get_by_slug(db, slug).await?;
+        let stages = AssetRepository::find_stage_manifests(db, course.id).await?;
+        let assets = AssetRepository::find_by_course(db, course.id).await?;
+        Ok((course, stages, assets).into())
+    }
+
+    /// Get an indexed asset of a course, or the part of it requested by the
+    /// given `Range` header.
+    pub async fn get_asset(
+        ctx: Arc<Context>,
+        slug: &str,
+        path: &str,
+        range: Option<&str>,
+    ) -> Result<AssetContent> {
+        let db = &ctx.database;
+        let course = CourseRepository::get_by_slug(db, slug).await?;
+
+        // Only indexed files are served, never the definitions or solutions
+        let asset = AssetRepository::get(db, course.id, path).await?;
+        let size = asset.size as u64;
+        let content = |body| AssetContent {
+            content_type: content_type(&asset.path),
+            sha256: asset.sha256.clone(),
+            size,
+            body,
+        };
+
+        let Ok(range) = range::parse(range, size) else {
+            return Ok(content(AssetBody::Unsatisfiable));
+        };
+
+        let root = Self::source_dir(&ctx, &course).await?;
+        let file = storage::resolve(&root, &asset.path)?;
+        let mut file = tokio::fs::File::open(file).await.map_err(StorageError::ReadFile)?;
+
+        let body = match range {
+            Some(range) => {
+                let mut buf = vec![0; range.len() as usize];
+                file.seek(SeekFrom::Start(range.start)).await.map_err(StorageError::ReadFile)?;
+                file.read_exact(&mut buf).await.map_err(StorageError::ReadFile)?;
+                AssetBody::Partial(range, buf)
+            }
+            None => {
+                let mut buf = Vec::with_capacity(size as usize);
+                file.read_to_end(&mut buf).await.map_err(StorageError::ReadFile)?;
+                AssetBody::Full(buf)
+            }
+        };
+
+        Ok(content(body))
+    }
+
+    /// Delete course by slug, tearing down its repositories first so none
+    /// outlive the records that name them. The images built for its learner
+    /// repositories are deleted on the way, and its robot accounts, their
+    /// Secrets and its Harbor project right before the course.
+    ///
+    /// The course is kept when some of its repositories could not be deleted,
+    /// for the deletion to be retried, unless it is forced.
+    pub(crate) async fn delete(
+        ctx: Arc<Context>,
+        slug: &str,
+        force: bool,
+    ) -> Result<CourseDeletionResponse> {
+        let course = CourseRepository::get_by_slug(&ctx.database, slug).await?;
+        let (mut deleted_images, mut failed_images) = (0, Vec::new());
+
+        // Standalone deployments have no registry holding images, and left
+        // over images must not keep the course around
+        if ctx.config.execution_backend == ExecutionBackend::Tekton {
+            for id in CourseRepository::find_user_course_ids(&ctx.database, &course.id).await? {
+                match RegistryService::cleanup(&ctx, slug, &id).await {
+                    Ok(removed) => deleted_images += removed,
+                    Err(e) => {
+                        warn!("Failed to clean up the images of repository {id}: {e}");
+                        failed_images.push(id.to_string());
+                    }
+                }
+            }
+        }
+
+        let Teardown { deleted, failed } = RepoService::new(ctx.clone()).teardown(slug).await?;
+        if !failed.is_empty() && !force {
+            return Err(ApiError::Conflict(format!(
+                "Failed to delete repositories {} of course {slug}, retry or force the deletion",
+                failed.join(", ")
+            )));
+        }
+
+        // The credentials go with the course, so no robot account may outlive
+        // them
+        if ctx.config.execution_backend == ExecutionBackend::Tekton {
+            RegistryService::teardown(&ctx, &course).await?;
+        }
+
+        CourseRepository::delete(&ctx.database, slug).await.map_err(ApiError::DatabaseError)?;
+        Ok(CourseDeletionResponse {
+            slug: slug.to_string(),
+            deleted_repositories: deleted,
+            failed_repositories: failed,
+            deleted_images,
+            failed_images,
+        })
+    }
+
+    /// Bring the latest template of a course into the repositories of its
+    /// learners, on their template update branch, reporting the outcome for
+    /// each of them. Repositories which are not ready, like those still
+    /// migrating, are skipped.
+    pub async fn sync_repositories(
+        ctx: Arc<Context>,
+        slug: &str,
+        actor: &str,
+    ) -> Result<Vec<RepositorySyncResponse>> {
+        let db = &ctx.database;
+        let course = CourseRepository::get_by_slug(db, slug).await?;
+        let ids = CourseRepository::find_user_course_ids(db, &course.id).await?;
+
+        let repo = RepoService::new(ctx.clone());
+        let mut results: Vec<RepositorySyncResponse> = stream::iter(ids)
+            .map(|id| Self::sync_repository(&ctx, &repo, id))
+            .buffer_unordered(SYNC_CONCURRENCY)
+            .collect()
+            .await;
+        results.sort_by_key(|result| result.user_course_id);
+
+        let count = |status: &str| results.iter().filter(|r| r.status == status).count();
+        let details = json!({
+            "pushed": count("pushed"),
+            "up_to_date": count("up_to_date"),
+            "conflict": count("conflict"),
+            "skipped": count("skipped"),
+            "failed": count("failed"),
+        });
+        info!("Synced the template of course {slug} to its repositories: {details}");
+        let target = format!("courses/{slug}/repositories");
+        let log =
+            AuditLogModel::new_at(actor, "sync_repositories", &target, details, ctx.clock.now());
+        AuditRepository::create(db, &log).await?;
+
+        Ok(results)
+    }
+
+    /// Syncs the repository of a user course with its template, turning
+    /// failures into the outcome reported for it.
+    async fn sync_repository(
+        ctx: &Arc<Context>,
+        repo: &RepoService,
+        id: Uuid,
+    ) -> RepositorySyncResponse {
+        let mut response = RepositorySyncResponse {
+            user_course_id: id,
+            user_id: String::new(),
+            status: "failed".to_string(),
+            commit: None,
+            conflicts: Vec::new(),
+            error: None,
+        };
+
+        let user_course = match CourseRepository::get_user_course_by_id(&ctx.database, &id).await {
+            Ok(user_course) => user_course,
+            Err(e) => {
+                warn!("Failed to find user course {id}: {e}");
+                response.error = Some("User course not found".to_string());
+                return response;
+            }
+        };
+        response.user_id = user_course.user_id;
+        if user_course.repository_status != "ready" {
+            response.status = "skipped".to_string();
+            response.error = Some(format!("Repository is {}", user_course.repository_status));
+            return response;
+        }
+
+        match repo.sync_user_repository(&id).await {
+            Ok(TemplateUpdate::UpToDate) => response.status = "up_to_date".to_string(),
+            Ok(TemplateUpdate::Pushed(commit)) => {
+                response.status = "pushed".to_string();
+                response.commit = Some(commit);
+            }
+            Ok(TemplateUpdate::Conflict(paths)) => {
+                response.status = "conflict".to_string();
+                response.conflicts = paths;
+            }
+            // Git output may quote the authenticated URLs, it is only logged
+            Err(e) => {
+                warn!("Failed to sync the template to repository {id}: {e}");
+                response.error = Some("Failed to merge the template, see the logs".to_string());
+            }
+        }
+        response
+    }
+
+    /// Aggregate the progress of the learners of a course.
+    pub async fn stats(ctx: Arc<Context>, slug: &str) -> Result<CourseStatsResponse> {
+        CourseRepository::get_by_slug(&ctx.database, slug).await?;
+
+        let (course, stages) = CourseRepository::stats(&ctx.database, slug).await?;
+        Ok(CourseStatsResponse::new(course, stages))
     }
 
     /// Fetch all courses for the user.
@@ -276,18 +867,29 @@ impl CourseService {
     ) -> Result<UserCourseResponse> {
         let mut tx = ctx.database.pool().begin().await?;
 
-        // Fetch the course
+        // Fetch the course, exams take enrollments once their window opened
+        let now = ctx.clock.now();
         let course = CourseRepository::get_by_slug(&ctx.database, &req.course_slug).await?;
+        if !CourseRepository::has_opened(&ctx.database, &course.id, now).await? {
+            return Err(ApiError::Forbidden("The course is not open for enrollment yet".into()));
+        }
 
         // Create a new user course enrollment
-        let user_course = UserCourseModel::new(user_id, &course.id)
+        let cohort = cohort(req.cohort.as_deref(), req.cohort_deadline)?;
+        let user_course = UserCourseModel::new_at(user_id, &course.id, now)
             .with_proficiency(&req.proficiency)
             .with_cadence(&req.cadence)
-            .with_accountability(req.accountability);
-        let user_course = CourseRepository::create_user_course(&mut tx, &user_course).await?;
-
-        // Generate Git repository from course template
-        RepoService::new(ctx.clone()).generate(&course.slug, &user_course.id.to_string()).await?;
+            .with_accountability(req.accountability)
+            .with_cohort(cohort, req.cohort_deadline)
+            .with_language(language(&course, req.language.as_deref())?);
+        let user_course = CourseRepository::create_user_course(&mut tx, &user_course)
+            .await
+            .map_err(ApiError::on_duplicate(ApiError::AlreadyEnrolled))?;
+        ProgressRepository::refresh(&mut tx, &user_course.id).await?;
+
+        // Generate Git repository from the template of the chosen language
+        let template = course.template(user_course.language.as_deref());
+        RepoService::new(ctx.clone()).generate(&template, &user_course.id.to_string()).await?;
 
         // Commits this transaction.
         tx.commit().await?;
@@ -295,6 +897,60 @@ impl CourseService {
         Ok(to_response(&ctx, user_course))
     }
 
+    /// Unenroll a user from a course. The enrollment goes away first, its
+    /// repository and images are removed afterwards as far as possible, the
+    /// orphaned ones left behind are cleaned up later on.
+    pub async fn delete_user_course(ctx: Arc<Context>, user_id: &str, slug: &str) -> Result<()> {
+        let user_course = CourseRepository::get_user_course(&ctx.database, user_id, slug).await?;
+
+        let mut tx = ctx.database.pool().begin().await?;
+        CourseRepository::delete_user_course(&mut tx, &user_course.id).await?;
+        tx.commit().await?;
+        info!("Unenrolled user {user_id} from course {slug}");
+
+        let repo = user_course.id.to_string();
+        if let Err(e) = RepoService::new(ctx.clone()).delete(&ctx.config.namespace, &repo).await {
+            warn!("Failed to delete repository {repo} of the unenrolled user: {e}");
+        }
+
+        // Standalone deployments have no registry holding images
+        if ctx.config.execution_backend == ExecutionBackend::Tekton &&
+            let Err(e) = RegistryService::cleanup(&ctx, slug, &user_course.id).await
+        {
+            warn!("Failed to clean up the images of repository {repo}: {e}");
+        }
+
+        Ok(())
+    }
+
+    /// Reset the progress of a user in a course, who starts over from the
+    /// first stage once the course is activated again. The repository is
+    /// regenerated from the course template on request.
+    pub async fn reset_user_course(
+        ctx: Arc<Context>,
+        user_id: &str,
+        slug: &str,
+        req: &ResetUserCourseRequest,
+    ) -> Result<()> {
+        let user_course = CourseRepository::get_user_course(&ctx.database, user_id, slug).await?;
+
+        let mut tx = ctx.database.pool().begin().await?;
+        CourseRepository::reset_user_course(&mut tx, &user_course.id).await?;
+        ProgressRepository::refresh(&mut tx, &user_course.id).await?;
+        tx.commit().await?;
+        info!("Reset the progress of user {user_id} in course {slug}");
+
+        if req.reset_repository {
+            let course = CourseRepository::get_by_slug(&ctx.database, slug).await?;
+            let template = course.template(user_course.language.as_deref());
+            let (service, repo) = (RepoService::new(ctx.clone()), user_course.id.to_string());
+            service.delete(&ctx.config.namespace, &repo).await?;
+            service.generate(&template, &repo).await?;
+        }
+
+        Ok(())
+    }
+
     /// Fetch the course detail for the user.
     pub async fn get_user_course(
         ctx: Arc<Context>,
@@ -302,7 +958,53 @@ impl CourseService {
         slug: &str,
     ) -> Result<UserCourseResponse> {
         let user_course = CourseRepository::get_user_course(&ctx.database, user_id, slug).await?;
-        Ok(to_response(&ctx, user_course))
+        let repo = user_course.id.to_string();
+        let mut res = to_response(&ctx, user_course);
+
+        // The latest commit is a nicety, the course is served without it
+        // while the git server is unavailable
+        let (org, options) = (&ctx.config.namespace, ListCommitsOptions::latest());
+        match ctx.git.list_commits(org, &repo, options).await {
+            Ok(commits) => {
+                if let Some(commit) = commits.first() {
+                    res.last_pushed_at = Some(commit.committed_at());
+                    res.last_commit_message = Some(commit.message().trim_end().to_string());
+                }
+            }
+            // Nothing was pushed to the repository yet
+            Err(ClientError::Conflict(_)) => {}
+            Err(e) => warn!("Failed to fetch the latest commit of repository {repo}: {e}"),
+        }
+
+        Ok(res)
+    }
+
+    /// Stream the course detail for the user, once right away and again on
+    /// every change. Clients which have already seen the version `last_seen`
+    /// are only sent newer details.
+    pub async fn stream_user_course_status(
+        ctx: Arc<Context>,
+        user_id: &str,
+        slug: &str,
+        last_seen: Option<i64>,
+    ) -> Result<impl Stream<Item = StatusUpdate<UserCourseResponse>> + use<>> {
+        let user_course = CourseRepository::get_user_course(&ctx.database, user_id, slug).await?;
+
+        let (id, user_id, slug) = (user_course.id, user_id.to_string(), slug.to_string());
+        let updates = ctx.status.clone();
+        let fetch = move || {
+            let (ctx, user_id, slug) = (ctx.clone(), user_id.clone(), slug.clone());
+            async move {
+                // Read before the status, so it is never newer than the status
+                let version = CourseRepository::get_status_version(&ctx.database, &id).await?;
+                let status = Self::get_user_course(ctx, &user_id, &slug).await?;
+                Ok(StatusUpdate { version, status })
+            }
+        };
+
+        // Courses have no final status, the stream lasts until the client
+        // disconnects
+        Ok(updates.stream(id, last_seen, fetch, |_| false))
     }
 
     /// Update the user course for the user.
@@ -318,6 +1020,18 @@ impl CourseService {
         user_course.cadence = req.cadence.clone();
         user_course.accountability = req.accountability;
 
+        // An empty cohort leaves it, none keeps the current one
+        match req.cohort.as_deref().map(str::trim) {
+            Some("") => user_course.cohort = None,
+            Some(cohort) => user_course.cohort = Some(cohort.to_string()),
+            None => {}
+        }
+        user_course.cohort_deadline = match &user_course.cohort {
+            Some(_) => req.cohort_deadline.or(user_course.cohort_deadline),
+            None => None,
+        };
+        cohort(user_course.cohort.as_deref(), req.cohort_deadline)?;
+
         let mut tx = ctx.database.pool().begin().await?;
         CourseRepository::update_user_course(&mut tx, &user_course).await?;
         tx.commit().await?;
@@ -325,6 +1039,34 @@ impl CourseService {
         Ok(())
     }
 
+    /// Starts a git identity verification for the user course, returning the
+    /// token the learner has to commit and push to their repository.
+    pub async fn verify_git_identity(
+        ctx: Arc<Context>,
+        user_id: &str,
+        course_slug: &str,
+    ) -> Result<GitIdentityVerificationResponse> {
+        let mut user_course =
+            CourseRepository::get_user_course(&ctx.database, user_id, course_slug).await?;
+        if user_course.identity_verified {
+            return Err(ApiError::Conflict("Git identity is already verified".into()));
+        }
+
+        // A fresh nonce invalidates any token issued before
+        let nonce = Uuid::now_v7().to_string();
+        let token = crypto::hmac_sha256_sign(
+            &identity_payload(&user_course.id, &nonce),
+            &ctx.config.auth_secret,
+        )?;
+        user_course.identity_nonce = Some(nonce);
+
+        let mut tx = ctx.database.pool().begin().await?;
+        CourseRepository::update_user_course(&mut tx, &user_course).await?;
+        tx.commit().await?;
+
+        Ok(GitIdentityVerificationResponse { token, path: IDENTITY_FILE.to_string() })
+    }
+
     /// Activates a user course by setting activated flag and creating first stage
     pub async fn activate(
         ctx: Arc<Context>,
@@ -334,29 +1076,266 @@ impl CourseService {
 
         user_course.activated = true;
 
-        // Find first stage by weight
+        // Find the first stage of the course
         if let Some(stage) = StageRepository::first(&ctx.database, &user_course.course_slug).await?
         {
             // Create user stage
-            let user_stage = UserStageModel::new(user_course.id, stage.id);
-            StageRepository::create_user_stage(&mut tx, &user_stage).await?;
+            let user_stage = UserStageModel::new_at(user_course.id, stage.id, ctx.clock.now());
+            StageRepository::create_user_stage(&mut tx, &user_stage)
+                .await
+                .map_err(ApiError::on_duplicate(ApiError::StageRecordExists))?;
 
             user_course.current_stage_id = Some(stage.id);
         }
 
         CourseRepository::update_user_course(&mut tx, user_course).await?;
+        ProgressRepository::refresh(&mut tx, &user_course.id).await?;
         tx.commit().await?;
 
         Ok(())
     }
 
-    /// Fetch all attempts for a course.
-    pub async fn find_attempts(ctx: Arc<Context>, slug: &str) -> Result<Vec<AttemptResponse>> {
-        let attempts = CourseRepository::find_attempts(&ctx.database, slug).await?;
-        Ok(attempts.into_iter().map(Into::into).collect())
+    /// Fetch a page of the attempts for a course, most recently started
+    /// first.
+    pub async fn find_attempts(
+        ctx: Arc<Context>,
+        slug: &str,
+        query: &CourseAttemptQuery,
+    ) -> Result<NumberedPage<AttemptResponse>> {
+        let db = &ctx.database;
+        CourseRepository::get_by_slug(db, slug).await?;
+
+        let per_page = pagination::clamp_limit(query.per_page);
+        let page = query.page.unwrap_or(1);
+        let offset = page_offset(page, per_page)?;
+        let user_id = query.user_id.as_deref();
+        let (attempts, total) =
+            CourseRepository::find_attempts(db, slug, user_id, query.since, offset, per_page)
+                .await?;
+
+        Ok(NumberedPage {
+            items: attempts.into_iter().map(Into::into).collect(),
+            total,
+            page,
+            per_page,
+        })
+    }
+
+    /// Fetch the progress of all learners of a course, either from the
+    /// summary or aggregated from scratch when `fresh` is set.
+    pub async fn find_progress(
+        ctx: Arc<Context>,
+        slug: &str,
+        fresh: bool,
+    ) -> Result<Vec<ProgressResponse>> {
+        let db = &ctx.database;
+        CourseRepository::get_by_slug(db, slug).await?;
+
+        let progress = if fresh {
+            ProgressRepository::find_live_by_course(db, slug).await?
+        } else {
+            ProgressRepository::find_by_course(db, slug).await?
+        };
+        Ok(progress.into_iter().map(Into::into).collect())
+    }
+
+    /// Fetch the progress of the learners of a course enrolled with a cohort.
+    pub async fn find_cohort_progress(
+        ctx: Arc<Context>,
+        slug: &str,
+        cohort: &str,
+    ) -> Result<Vec<ProgressResponse>> {
+        let db = &ctx.database;
+        CourseRepository::get_by_slug(db, slug).await?;
+
+        let progress = ProgressRepository::find_by_cohort(db, slug, cohort).await?;
+        Ok(progress.into_iter().map(Into::into).collect())
+    }
+
+    /// Ensure a user may operate a course on behalf of its author.
+    pub async fn authorize_maintainer(ctx: &Context, slug: &str, user_id: &str) -> Result<()> {
+        if !CourseRepository::is_maintainer(&ctx.database, slug, user_id).await? {
+            debug!("User {} is not a maintainer of course {:?}", user_id, slug);
+            return Err(ApiError::Forbidden("not a maintainer of this course".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Find all maintainers of a course.
+    pub async fn find_maintainers(
+        ctx: Arc<Context>,
+        slug: &str,
+    ) -> Result<Vec<MaintainerResponse>> {
+        let db = &ctx.database;
+        CourseRepository::get_by_slug(db, slug).await?;
+
+        let maintainers = CourseRepository::find_maintainers(db, slug).await?;
+        Ok(maintainers.into_iter().map(Into::into).collect())
+    }
+
+    /// Grant a user the maintainer role of a course.
+    pub async fn add_maintainer(
+        ctx: Arc<Context>,
+        slug: &str,
+        user_id: &str,
+        actor: &str,
+    ) -> Result<MaintainerResponse> {
+        let db = &ctx.database;
+        UserRepository::get_by_id(db, user_id).await?;
+
+        let conflict = ApiError::Conflict("User already maintains the course".into());
+        let maintainer = CourseRepository::add_maintainer(db, slug, user_id, actor)
+            .await
+            .map_err(ApiError::on_duplicate(conflict))?;
+
+        let target = format!("courses/{slug}/maintainers/{user_id}");
+        let log =
+            AuditLogModel::new_at(actor, "add_maintainer", &target, json!({}), ctx.clock.now());
+        AuditRepository::create(db, &log).await?;
+
+        Ok(maintainer.into())
+    }
+
+    /// Set or clear the exam window of a course.
+    pub async fn set_exam_window(
+        ctx: Arc<Context>,
+        slug: &str,
+        req: &ExamWindowRequest,
+        actor: &str,
+    ) -> Result<CourseDetailResponse> {
+        if let (Some(opens_at), Some(closes_at)) = (req.opens_at, req.closes_at) &&
+            closes_at <= opens_at
+        {
+            return Err(ApiError::BadRequest("The window must close after it opens".into()));
+        }
+
+        let db = &ctx.database;
+        let course = CourseRepository::set_exam_window(db, slug, req.opens_at, req.closes_at);
+        let course = course.await?;
+
+        let target = format!("courses/{slug}/exam-window");
+        let details = json!({ "opens_at": req.opens_at, "closes_at": req.closes_at });
+        let log =
+            AuditLogModel::new_at(actor, "set_exam_window", &target, details, ctx.clock.now());
+        AuditRepository::create(db, &log).await?;
+
+        Ok(course.into())
+    }
+
+    /// Grant a learner a deadline beyond the close of the exam window.
+    pub async fn extend_deadline(
+        ctx: Arc<Context>,
+        user_id: &str,
+        slug: &str,
+        req: &ExtendDeadlineRequest,
+        actor: &str,
+    ) -> Result<UserCourseResponse> {
+        let db = &ctx.database;
+        let user_course = CourseRepository::get_user_course(db, user_id, slug).await?;
+        match user_course.closes_at {
+            Some(closes_at) if req.deadline > closes_at => {}
+            Some(_) => {
+                return Err(ApiError::BadRequest("The deadline must be after the close".into()));
+            }
+            None => return Err(ApiError::BadRequest("The course has no exam window".into())),
+        }
+
+        let user_course = CourseRepository::extend_deadline(db, &user_course.id, req.deadline);
+        let user_course = user_course.await?;
+
+        let target = format!("users/{user_id}/courses/{slug}");
+        let details = json!({ "deadline": req.deadline });
+        let log =
+            AuditLogModel::new_at(actor, "extend_deadline", &target, details, ctx.clock.now());
+        AuditRepository::create(db, &log).await?;
+
+        Ok(to_response(&ctx, user_course))
+    }
+
+    /// Revoke the maintainer role of a user.
+    pub async fn remove_maintainer(
+        ctx: Arc<Context>,
+        slug: &str,
+        user_id: &str,
+        actor: &str,
+    ) -> Result<()> {
+        let db = &ctx.database;
+        if !CourseRepository::remove_maintainer(db, slug, user_id).await? {
+            return Err(ApiError::NotFound);
+        }
+
+        let target = format!("courses/{slug}/maintainers/{user_id}");
+        let log =
+            AuditLogModel::new_at(actor, "remove_maintainer", &target, json!({}), ctx.clock.now());
+        AuditRepository::create(db, &log).await?;
+
+        Ok(())
+    }
+
+    /// Rebuild the progress summaries of all enrollments of a course.
+    pub async fn rebuild_progress(ctx: Arc<Context>, slug: &str) -> Result<u64> {
+        CourseRepository::get_by_slug(&ctx.database, slug).await?;
+
+        let mut tx = ctx.database.pool().begin().await?;
+        let rebuilt = ProgressRepository::rebuild(&mut tx, slug).await?;
+        tx.commit().await?;
+
+        info!("Rebuilt {} progress summaries for course {:?}", rebuilt, slug);
+        Ok(rebuilt)
     }
 }
 
+/// The stage layout of a course as stored: every stage slug with the slug of
+/// its extension, if any.
+fn stage_structure(stages: &[StageModel]) -> HashSet<(String, Option<String>)> {
+    stages.iter().map(|s| (s.slug.clone(), s.extension_slug.clone())).collect()
+}
+
+/// The stage layout of a parsed course, comparable with [`stage_structure`].
+fn course_structure(course: &Course) -> HashSet<(String, Option<String>)> {
+    let mut structure: HashSet<_> =
+        course.stages.iter().map(|(_, stage)| (stage.slug.clone(), None)).collect();
+    if let Some(extensions) = &course.extensions {
+        for (_, ext) in extensions.iter() {
+            let stages = ext.stages.iter();
+            structure.extend(stages.map(|(_, stage)| (stage.slug.clone(), Some(ext.slug.clone()))));
+        }
+    }
+    structure
+}
+
+/// The slugs of the stages a parsed course adds, removes and changes the
+/// content of, compared with the stages left by the previous sync.
+fn stage_changes(
+    existing: &[StageModel],
+    course: &Course,
+) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let mut stages: Vec<&Stage> = course.stages.values().collect();
+    if let Some(extensions) = &course.extensions {
+        stages.extend(extensions.values().flat_map(|ext| ext.stages.values()));
+    }
+
+    let (mut added, mut updated) = (Vec::new(), Vec::new());
+    for stage in &stages {
+        match existing.iter().find(|s| s.slug == stage.slug) {
+            None => added.push(stage.slug.clone()),
+            Some(s) if s.content_hash != StageModel::from((*stage).clone()).content_hash => {
+                updated.push(stage.slug.clone())
+            }
+            Some(_) => {}
+        }
+    }
+
+    let removed = existing
+        .iter()
+        .filter(|s| !stages.iter().any(|stage| stage.slug == s.slug))
+        .map(|s| s.slug.clone())
+        .collect();
+
+    (added, removed, updated)
+}
+
 /// Calculates the total number of stages in a course including extensions.
 fn calculate_total_stages(course: &Course) -> i32 {
     let mut total = course.stages.len() as i32;
@@ -366,9 +1345,88 @@ fn calculate_total_stages(course: &Course) -> i32 {
     total
 }
 
+/// Builds the payload signed into a git identity verification token.
+pub(crate) fn identity_payload(user_course_id: &Uuid, nonce: &str) -> String {
+    format!("{user_course_id}:{nonce}")
+}
+
 /// Converts a user course model to a response with repository URL.
 #[inline]
+/// The cohort an enrollment asks for, none when empty. A deadline is only
+/// accepted along with a cohort.
+fn cohort(cohort: Option<&str>, deadline: Option<DateTime<Utc>>) -> Result<Option<&str>> {
+    let cohort = cohort.map(str::trim).filter(|cohort| !cohort.is_empty());
+    if cohort.is_none() && deadline.is_some() {
+        return Err(ApiError::BadRequest("A cohort deadline requires a cohort".into()));
+    }
+    Ok(cohort)
+}
+
+/// Resolves the language a user enrolls with, the primary language of the
+/// course unless another one it is offered in was chosen. Courses offered in
+/// a single language have none.
+fn language<'a>(course: &'a CourseModel, language: Option<&'a str>) -> Result<Option<&'a str>> {
+    let Some(primary) = course.languages.first() else {
+        return match language {
+            Some(_) => Err(ApiError::BadRequest("The course has no language variants".into())),
+            None => Ok(None),
+        };
+    };
+
+    match language {
+        None => Ok(Some(primary)),
+        Some(language) if course.languages.iter().any(|l| l == language) => Ok(Some(language)),
+        Some(language) => {
+            Err(ApiError::BadRequest(format!("The course is not offered in {language}")))
+        }
+    }
+}
+
+/// Number of items ahead of a numbered page, refusing pages below 1 and
+/// pages too far out to be reached.
+fn page_offset(page: i64, per_page: i64) -> Result<i64> {
+    pagination::page_offset(page, per_page)
+        .ok_or_else(|| ApiError::BadRequest("page must be at least 1 and within range".into()))
+}
+
 fn to_response(ctx: &Context, user_course: UserCourseModel) -> UserCourseResponse {
-    let repository = format!("{}/{}", ctx.config.git_proxy_endpoint, user_course.id);
-    UserCourseResponse::from((user_course, repository))
+    UserCourseResponse::from((user_course, &ctx.endpoints, ctx.config.default_branch.as_str()))
+}
+
+/// Lists all stages of a course with their extension and directory relative
+/// to the repository root.
+fn source_stages(course: &Course) -> Vec<(Option<&str>, String, &Stage)> {
+    let mut stages: Vec<_> =
+        course.stages.iter().map(|(dir, stage)| (None, format!("stages/{dir}"), stage)).collect();
+
+    for (slug, extension) in course.extensions.iter().flatten() {
+        for (dir, stage) in &extension.stages {
+            stages.push((Some(slug.as_str()), format!("extensions/{slug}/{dir}"), stage));
+        }
+    }
+
+    stages
+}
+
+/// Reads a file of the cached repository without leaving its directory.
+async fn read_source(root: &Path, relative: &str) -> Result<String> {
+    let path = storage::resolve(root, relative)?;
+    Ok(tokio::fs::read_to_string(path).await.map_err(StorageError::ReadFile)?)
+}
+
+/// Media type of an asset, guessed from its file extension.
+fn content_type(path: &str) -> &'static str {
+    let extension = path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("md") => "text/markdown; charset=utf-8",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("json") => "application/json",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
 }
@@ -0,0 +1,97 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use chrono::Duration;
+use tracing::info;
+
+use crate::{
+    context::Context,
+    errors::{ApiError, Result},
+    model::EngagementEventModel,
+    repository::{CourseRepository, EngagementRepository, StageRepository},
+    request::CreateEngagementEventRequest,
+    response::StageEngagementResponse,
+};
+
+/// How far in the future a client timestamp may be, to allow for clock skew.
+const MAX_CLOCK_SKEW: Duration = Duration::minutes(5);
+
+/// How old an event may be when it is reported, e.g. after being queued
+/// while the learner was offline.
+const MAX_EVENT_AGE: Duration = Duration::days(1);
+
+pub struct EngagementService;
+
+impl EngagementService {
+    /// Record an engagement event of a learner with a stage of a course they
+    /// are enrolled in.
+    pub async fn record(
+        ctx: Arc<Context>,
+        user_id: &str,
+        course_slug: &str,
+        req: &CreateEngagementEventRequest,
+    ) -> Result<()> {
+        let now = ctx.clock.now();
+        if req.occurred_at > now + MAX_CLOCK_SKEW || req.occurred_at < now - MAX_EVENT_AGE {
+            return Err(ApiError::BadRequest("Event time is out of range".into()));
+        }
+
+        let db = &ctx.database;
+        let user_course = CourseRepository::get_user_course(db, user_id, course_slug).await?;
+        let stage = StageRepository::get_by_slug(db, course_slug, &req.stage_slug).await?;
+
+        let config = &ctx.config;
+        let since = now - Duration::seconds(config.engagement_rate_window);
+        if EngagementRepository::count_by_user(db, user_id, since).await? >=
+            config.engagement_rate_limit
+        {
+            return Err(ApiError::TooManyRequests("Too many events, try again later".into()));
+        }
+
+        let event = EngagementEventModel::new_at(
+            user_course.id,
+            stage.id,
+            req.kind.as_str(),
+            req.occurred_at,
+            now,
+        );
+        EngagementRepository::create(db, &event).await?;
+        Ok(())
+    }
+
+    /// Aggregate the engagement with every stage of a course.
+    pub async fn find_by_course(
+        ctx: Arc<Context>,
+        course_slug: &str,
+    ) -> Result<Vec<StageEngagementResponse>> {
+        let db = &ctx.database;
+        CourseRepository::get_by_slug(db, course_slug).await?;
+
+        let rows = EngagementRepository::find_by_course(db, course_slug).await?;
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    /// Roll up the events out of the retention window and purge them,
+    /// returning the number of events purged.
+    pub async fn roll_up(ctx: Arc<Context>) -> Result<u64> {
+        let before = ctx.clock.now() - Duration::seconds(ctx.config.engagement_retention);
+        let purged = EngagementRepository::roll_up(&ctx.database, before).await?;
+        if purged > 0 {
+            info!("Rolled up {} engagement events", purged);
+        }
+        Ok(purged)
+    }
+}
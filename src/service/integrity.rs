@@ -0,0 +1,324 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::HashMap, sync::Arc};
+
+use serde::Serialize;
+use serde_json::json;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    context::Context,
+    errors::{ApiError, Result},
+    model::{AuditLogModel, CompletionModel, IntegrityFlagModel},
+    repository::{AuditRepository, IntegrityRepository},
+    response::IntegrityFlagResponse,
+};
+
+/// Features of a single completion the integrity rules look at
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CompletionFeatures {
+    /// Time between starting and completing the stage
+    pub seconds_since_start: i64,
+
+    /// Failed attempts before the stage was passed
+    pub failed_attempts: i64,
+
+    /// Time since the previous completion of the enrollment, if any
+    pub seconds_since_previous: Option<i64>,
+
+    /// Completions of the enrollment within the rapid window, this one included
+    pub recent_completions: usize,
+
+    /// Median failed attempts per completed stage of the course
+    pub course_median_failures: f64,
+}
+
+impl CompletionFeatures {
+    /// Computes the features of the completion at `index` of the completions
+    /// of an enrollment, ordered by completion time.
+    pub fn compute(
+        completions: &[CompletionModel],
+        index: usize,
+        window: i64,
+        median: f64,
+    ) -> Self {
+        let completion = &completions[index];
+        let completed_at = completion.completed_at;
+
+        let previous = index.checked_sub(1).map(|i| completions[i].completed_at);
+        let recent_completions = completions[..=index]
+            .iter()
+            .filter(|c| (completed_at - c.completed_at).num_seconds() < window)
+            .count();
+
+        Self {
+            seconds_since_start: (completed_at - completion.started_at).num_seconds(),
+            failed_attempts: completion.failed_attempts,
+            seconds_since_previous: previous.map(|p| (completed_at - p).num_seconds()),
+            recent_completions,
+            course_median_failures: median,
+        }
+    }
+}
+
+/// Thresholds of the rules flagging suspicious completions, a zero
+/// threshold disables its rule.
+#[derive(Clone, Debug)]
+pub struct IntegrityRules {
+    /// Completions within the window that are suspicious
+    pub rapid_completions: usize,
+
+    /// Length of the rapid completions window in seconds
+    pub rapid_window: i64,
+
+    /// Minimum plausible time spent on a stage in seconds
+    pub min_stage_seconds: i64,
+
+    /// Course median failures from which a flawless pass is suspicious
+    pub median_failures: f64,
+}
+
+impl From<&Config> for IntegrityRules {
+    fn from(config: &Config) -> Self {
+        Self {
+            rapid_completions: config.integrity_rapid_completions,
+            rapid_window: config.integrity_rapid_window,
+            min_stage_seconds: config.integrity_min_stage_seconds,
+            median_failures: config.integrity_median_failures,
+        }
+    }
+}
+
+impl IntegrityRules {
+    /// Names of the rules the completion violates.
+    pub fn evaluate(&self, features: &CompletionFeatures) -> Vec<&'static str> {
+        let mut rules = Vec::new();
+
+        if self.rapid_completions > 0 && features.recent_completions >= self.rapid_completions {
+            rules.push("rapid_completions");
+        }
+        if self.min_stage_seconds > 0 && features.seconds_since_start < self.min_stage_seconds {
+            rules.push("fast_stage");
+        }
+        if self.median_failures > 0.0 &&
+            features.failed_attempts == 0 &&
+            features.course_median_failures >= self.median_failures
+        {
+            rules.push("flawless_pass");
+        }
+
+        rules
+    }
+}
+
+/// Service surfacing suspicious completion patterns to instructors
+pub struct IntegrityService;
+
+impl IntegrityService {
+    /// Analyze a batch of completions, returning how many were analyzed.
+    pub async fn analyze(ctx: &Context, batch: i64) -> Result<usize> {
+        let rules = IntegrityRules::from(&ctx.config);
+
+        let mut tx = ctx.database.pool().begin().await?;
+        let now = ctx.clock.now();
+        let ids = IntegrityRepository::claim_pending(&mut tx, batch).await?;
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        // Group the completions per enrollment, in completion order
+        let mut enrollments: HashMap<Uuid, Vec<CompletionModel>> = HashMap::new();
+        for completion in IntegrityRepository::find_completions(&mut tx, &ids).await? {
+            enrollments.entry(completion.user_course_id).or_default().push(completion);
+        }
+
+        let mut medians = HashMap::new();
+        for (user_course_id, completions) in &enrollments {
+            for (index, completion) in completions.iter().enumerate() {
+                if !ids.contains(&completion.user_stage_id) {
+                    continue;
+                }
+
+                let median = match medians.get(user_course_id) {
+                    Some(median) => *median,
+                    None => {
+                        let median =
+                            IntegrityRepository::median_failures(&mut tx, user_course_id).await?;
+                        *medians.entry(*user_course_id).or_insert(median)
+                    }
+                };
+
+                let features =
+                    CompletionFeatures::compute(completions, index, rules.rapid_window, median);
+                for rule in rules.evaluate(&features) {
+                    info!("Completion {} flagged by rule {}", completion.user_stage_id, rule);
+                    let features =
+                        serde_json::to_value(&features).map_err(ApiError::SerializationError)?;
+                    let flag =
+                        IntegrityFlagModel::new_at(completion.user_stage_id, rule, features, now);
+                    IntegrityRepository::create_flag(&mut tx, &flag).await?;
+                }
+            }
+        }
+
+        IntegrityRepository::mark_analyzed(&mut tx, &ids).await?;
+        tx.commit().await?;
+
+        Ok(ids.len())
+    }
+
+    /// Find the flags raised in a course.
+    pub async fn find_flags(
+        ctx: Arc<Context>,
+        slug: &str,
+        user_id: Option<&str>,
+        rule: Option<&str>,
+        dismissed: bool,
+    ) -> Result<Vec<IntegrityFlagResponse>> {
+        let flags =
+            IntegrityRepository::find_flags(&ctx.database, slug, user_id, rule, dismissed).await?;
+        Ok(flags.into_iter().map(Into::into).collect())
+    }
+
+    /// Dismiss a flag with a note, without any further action on the learner.
+    pub async fn dismiss_flag(
+        ctx: Arc<Context>,
+        slug: &str,
+        id: &Uuid,
+        actor: &str,
+        note: &str,
+    ) -> Result<IntegrityFlagResponse> {
+        let db = &ctx.database;
+        let flag = IntegrityRepository::dismiss_flag(db, slug, id, actor, note).await?;
+
+        let target = format!("courses/{slug}/integrity-flags/{id}");
+        let log = AuditLogModel::new_at(
+            actor,
+            "dismiss_integrity_flag",
+            &target,
+            json!({ "note": note }),
+            ctx.clock.now(),
+        );
+        AuditRepository::create(db, &log).await?;
+
+        Ok(flag.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, Utc};
+
+    use super::*;
+
+    fn rules() -> IntegrityRules {
+        IntegrityRules {
+            rapid_completions: 5,
+            rapid_window: 600,
+            min_stage_seconds: 30,
+            median_failures: 4.0,
+        }
+    }
+
+    /// Completions of one enrollment, each given as (minutes after the
+    /// first start it was completed, minutes spent on it, failed attempts).
+    fn sequence(steps: &[(i64, i64, i64)]) -> Vec<CompletionModel> {
+        let origin = DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+        let user_course_id = Uuid::now_v7();
+        steps
+            .iter()
+            .map(|&(at, spent, failed_attempts)| {
+                let completed_at = origin + Duration::minutes(at);
+                CompletionModel {
+                    user_stage_id: Uuid::now_v7(),
+                    user_course_id,
+                    started_at: completed_at - Duration::minutes(spent),
+                    completed_at,
+                    failed_attempts,
+                }
+            })
+            .collect()
+    }
+
+    fn flags(completions: &[CompletionModel], median: f64) -> Vec<Vec<&'static str>> {
+        let rules = rules();
+        (0..completions.len())
+            .map(|i| {
+                let features =
+                    CompletionFeatures::compute(completions, i, rules.rapid_window, median);
+                rules.evaluate(&features)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_steady_learner_is_not_flagged() {
+        let completions = sequence(&[(60, 60, 2), (180, 120, 1), (300, 120, 5), (1440, 600, 0)]);
+        assert!(flags(&completions, 1.0).iter().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn test_five_completions_in_ten_minutes() {
+        let completions =
+            sequence(&[(0, 5, 1), (2, 2, 1), (4, 2, 1), (6, 2, 1), (8, 2, 1), (30, 5, 1)]);
+        let flags = flags(&completions, 1.0);
+
+        assert!(flags[..4].iter().all(Vec::is_empty));
+        assert_eq!(flags[4], vec!["rapid_completions"]);
+        assert!(flags[5].is_empty());
+
+        let features = CompletionFeatures::compute(&completions, 4, 600, 1.0);
+        assert_eq!(features.recent_completions, 5);
+        assert_eq!(features.seconds_since_previous, Some(120));
+        assert_eq!(features.seconds_since_start, 120);
+    }
+
+    #[test]
+    fn test_window_is_exclusive() {
+        // The first completion is exactly ten minutes before the fifth one
+        let completions = sequence(&[(0, 5, 1), (3, 3, 1), (5, 2, 1), (7, 2, 1), (10, 3, 1)]);
+        assert!(flags(&completions, 1.0)[4].is_empty());
+    }
+
+    #[test]
+    fn test_flawless_pass_on_hard_course() {
+        let completions = sequence(&[(60, 60, 0), (180, 120, 6)]);
+        assert_eq!(flags(&completions, 4.0), vec![vec!["flawless_pass"], vec![]]);
+        assert!(flags(&completions, 3.5).iter().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn test_instant_completion() {
+        let completions = sequence(&[(60, 0, 2)]);
+        assert_eq!(flags(&completions, 0.0), vec![vec!["fast_stage"]]);
+    }
+
+    #[test]
+    fn test_disabled_rules() {
+        let completions = sequence(&[(0, 0, 0), (1, 0, 0), (2, 0, 0), (3, 0, 0), (4, 0, 0)]);
+        let rules = IntegrityRules {
+            rapid_completions: 0,
+            rapid_window: 600,
+            min_stage_seconds: 0,
+            median_failures: 0.0,
+        };
+        for i in 0..completions.len() {
+            let features = CompletionFeatures::compute(&completions, i, 600, 10.0);
+            assert!(rules.evaluate(&features).is_empty());
+        }
+    }
+}
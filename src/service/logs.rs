@@ -0,0 +1,353 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Streams the tester output of an attempt to the learner while it is graded.
+//!
+//! The test pod of the PipelineRun is looked up by the labels Tekton puts on
+//! it, and the log of its tester container followed until the container
+//! terminates. Pods that are not scheduled yet and log streams cut short, as
+//! happens when the kubelet rotates the log, are retried with a backoff.
+
+use std::{pin::pin, sync::Arc, time::Duration};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures::{AsyncBufReadExt, StreamExt};
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::{Api, ListParams, LogParams};
+use tokio::{
+    sync::mpsc,
+    time::{Instant, sleep},
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::{
+    context::Context,
+    errors::Result,
+    model::StageAttemptModel,
+    repository::StageRepository,
+    response::{StageLogEnd, StageLogLine},
+    service::pipeline::TEST_TASK,
+    utils::{pagination::Page, stream::strip_ansi},
+};
+
+/// Delay before looking for the test pod or its output again, doubled on
+/// every try up to [`MAX_BACKOFF`].
+const MIN_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Time to wait for the outcome of a run once its tester terminated.
+const OUTCOME_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An event of a log stream.
+#[derive(Debug)]
+pub enum LogEvent {
+    Line(StageLogLine),
+    End(StageLogEnd),
+}
+
+/// Service for streaming the tester output of attempts
+pub struct LogService;
+
+impl LogService {
+    /// Stream the tester output of the latest attempt of a user stage until
+    /// its run is over. Without a run in progress, the stream ends right
+    /// away with the status of the latest attempt, as past output is not
+    /// kept.
+    pub async fn stream(
+        ctx: Arc<Context>,
+        user_id: &str,
+        course_slug: &str,
+        stage_slug: &str,
+    ) -> Result<ReceiverStream<LogEvent>> {
+        let db = &ctx.database;
+
+        // Only the user enrolled in the course may follow its attempts
+        StageRepository::get_user_stage(db, user_id, course_slug, stage_slug).await?;
+
+        let page = Page::first(1);
+        let attempts = StageRepository::find_attempts(
+            db,
+            course_slug,
+            Some(user_id),
+            Some(stage_slug),
+            None,
+            &page,
+        )
+        .await?;
+        let attempt = attempts.into_iter().next();
+
+        let live =
+            attempt.as_ref().is_some_and(|a| a.status == "pending" && a.pipeline_run.is_some());
+
+        // The local backend runs tests outside of the cluster
+        #[cfg(feature = "local-runner")]
+        let live = live && ctx.runner.is_none();
+
+        let (tx, rx) = mpsc::channel(64);
+        match attempt {
+            Some(attempt) if live => {
+                tokio::spawn(follow(ctx, attempt, tx));
+            }
+            attempt => {
+                let end = StageLogEnd::new(attempt.map(|attempt| attempt.status), 0);
+                let _ = tx.try_send(LogEvent::End(end));
+            }
+        }
+
+        Ok(ReceiverStream::new(rx))
+    }
+}
+
+/// Forwards the tester output of the attempt, then its outcome.
+async fn follow(ctx: Arc<Context>, attempt: StageAttemptModel, tx: mpsc::Sender<LogEvent>) {
+    let run = attempt.pipeline_run.as_deref().unwrap_or_default();
+    let mut follower = LogFollower::new(&ctx, run, tx);
+
+    if let Err(e) = follower.run(&ctx, &attempt.id).await {
+        warn!("Failed to follow the test output of attempt {}: {e}", attempt.id);
+    }
+    if follower.tx.is_closed() {
+        return;
+    }
+
+    let status = outcome(&ctx, &attempt.id).await;
+    let _ =
+        follower.tx.send(LogEvent::End(StageLogEnd::new(status, follower.limiter.dropped))).await;
+}
+
+/// Waits a while for the outcome of the attempt to be recorded, returning
+/// its status.
+async fn outcome(ctx: &Context, id: &Uuid) -> Option<String> {
+    let deadline = Instant::now() + OUTCOME_TIMEOUT;
+    loop {
+        match StageRepository::get_attempt_status(&ctx.database, id).await {
+            Ok(status) if status != "pending" || Instant::now() >= deadline => return Some(status),
+            Ok(_) => sleep(MIN_BACKOFF).await,
+            Err(e) => {
+                warn!("Failed to get the status of attempt {id}: {e}");
+                return None;
+            }
+        }
+    }
+}
+
+/// State of the tester container of a run.
+#[derive(Debug, PartialEq, Eq)]
+enum TesterState {
+    /// The pod is not scheduled, or the container not started yet
+    Waiting,
+
+    /// The container of the named pod is running
+    Running(String),
+
+    /// The container of the named pod terminated
+    Terminated(String),
+}
+
+struct LogFollower {
+    pods: Api<Pod>,
+    selector: String,
+    container: String,
+    tx: mpsc::Sender<LogEvent>,
+    limiter: RateLimiter,
+
+    /// Time of the last line forwarded, lines up to it are skipped when the
+    /// log is followed again
+    last: Option<DateTime<Utc>>,
+}
+
+impl LogFollower {
+    fn new(ctx: &Context, run: &str, tx: mpsc::Sender<LogEvent>) -> Self {
+        Self {
+            pods: Api::namespaced(ctx.k8s.clone(), &ctx.config.namespace),
+            selector: format!("tekton.dev/pipelineRun={run},tekton.dev/pipelineTask={TEST_TASK}"),
+            container: ctx.config.tester_container.clone(),
+            tx,
+            limiter: RateLimiter::new(ctx.config.log_stream_rate),
+            last: None,
+        }
+    }
+
+    /// Forwards the output of the tester until it terminates, the attempt
+    /// is no longer pending, or the client goes away.
+    async fn run(&mut self, ctx: &Context, attempt: &Uuid) -> Result<()> {
+        let mut backoff = MIN_BACKOFF;
+
+        while !self.tx.is_closed() {
+            match self.state().await {
+                TesterState::Waiting => {
+                    let status =
+                        StageRepository::get_attempt_status(&ctx.database, attempt).await?;
+                    if status != "pending" {
+                        return Ok(());
+                    }
+                }
+                TesterState::Running(pod) => {
+                    // Cut short while the tester still runs, resume right away
+                    if self.forward(&pod).await > 0 {
+                        backoff = MIN_BACKOFF;
+                        continue;
+                    }
+                }
+                TesterState::Terminated(pod) => {
+                    self.forward(&pod).await;
+                    return Ok(());
+                }
+            }
+
+            sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+
+        Ok(())
+    }
+
+    /// Looks up the test pod of the run and the state of its tester.
+    async fn state(&self) -> TesterState {
+        let params = ListParams::default().labels(&self.selector);
+        let pod = match self.pods.list(&params).await {
+            Ok(pods) => pods.items.into_iter().next(),
+            Err(e) => {
+                debug!("Failed to look up the test pod ({}): {e}", self.selector);
+                None
+            }
+        };
+        let Some(pod) = pod else { return TesterState::Waiting };
+
+        let name = pod.metadata.name.unwrap_or_default();
+        let status = pod.status.unwrap_or_default();
+        let container = status.container_statuses.unwrap_or_default().into_iter();
+        let state = container.filter(|c| c.name == self.container).find_map(|c| c.state);
+
+        match state {
+            Some(state) if state.terminated.is_some() => TesterState::Terminated(name),
+            Some(state) if state.running.is_some() => TesterState::Running(name),
+            // Without a container status, a finished pod never ran the tester
+            None if matches!(status.phase.as_deref(), Some("Succeeded" | "Failed")) => {
+                TesterState::Terminated(name)
+            }
+            _ => TesterState::Waiting,
+        }
+    }
+
+    /// Follows the log of the tester from where it was left, returning the
+    /// number of new lines.
+    async fn forward(&mut self, pod: &str) -> usize {
+        let params = LogParams {
+            container: Some(self.container.clone()),
+            follow: true,
+            timestamps: true,
+            since_time: self
+                .last
+                .and_then(|t| t.to_rfc3339_opts(SecondsFormat::Nanos, true).parse().ok()),
+            ..Default::default()
+        };
+        let reader = match self.pods.log_stream(pod, &params).await {
+            Ok(reader) => reader,
+            Err(e) => {
+                debug!("Failed to follow the log of test pod {pod}: {e}");
+                return 0;
+            }
+        };
+
+        let mut lines = pin!(reader.lines());
+        let mut count = 0;
+        while let Some(Ok(line)) = lines.next().await {
+            let (time, text) = split_timestamp(&line);
+            if let Some(time) = time {
+                // Resumed logs start at the second of the last line
+                if self.last.is_some_and(|last| time <= last) {
+                    continue;
+                }
+                self.last = Some(time);
+            }
+
+            count += 1;
+            if !self.limiter.admit() {
+                continue;
+            }
+            let line = StageLogLine::new(strip_ansi(text));
+            if self.tx.send(LogEvent::Line(line)).await.is_err() {
+                break;
+            }
+        }
+
+        count
+    }
+}
+
+/// Splits the timestamp Kubernetes prefixes log lines with off the line.
+fn split_timestamp(line: &str) -> (Option<DateTime<Utc>>, &str) {
+    let Some((time, text)) = line.split_once(' ') else { return (None, line) };
+    match DateTime::parse_from_rfc3339(time) {
+        Ok(time) => (Some(time.to_utc()), text),
+        Err(_) => (None, line),
+    }
+}
+
+/// Admits up to a number of lines per second, counting the dropped ones.
+struct RateLimiter {
+    rate: u32,
+    window: Instant,
+    admitted: u32,
+    dropped: u64,
+}
+
+impl RateLimiter {
+    fn new(rate: u32) -> Self {
+        Self { rate, window: Instant::now(), admitted: 0, dropped: 0 }
+    }
+
+    fn admit(&mut self) -> bool {
+        let now = Instant::now();
+        if now.duration_since(self.window) >= Duration::from_secs(1) {
+            self.window = now;
+            self.admitted = 0;
+        }
+
+        if self.admitted < self.rate {
+            self.admitted += 1;
+            true
+        } else {
+            self.dropped += 1;
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_timestamp() {
+        let (time, text) = split_timestamp("2026-01-01T00:00:00.5Z  indented");
+        assert_eq!(time.unwrap().timestamp_millis(), 1767225600500);
+        assert_eq!(text, " indented");
+        assert_eq!(split_timestamp("no timestamp"), (None, "no timestamp"));
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::new(2);
+        let admitted: Vec<_> = (0..5).map(|_| limiter.admit()).collect();
+        assert_eq!(admitted, [true, true, false, false, false]);
+        assert_eq!(limiter.dropped, 3);
+
+        limiter.window -= Duration::from_secs(1);
+        assert!(limiter.admit());
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde_json::json;
+
+use crate::{
+    context::Context,
+    errors::{ApiError, Result},
+    model::{AuditLogModel, MAINTENANCE_SETTING, MaintenanceMode},
+    repository::{AuditRepository, SettingRepository},
+    request::MaintenanceRequest,
+    response::MaintenanceResponse,
+};
+
+/// Message of refused writes when the operator left none.
+const DEFAULT_MESSAGE: &str = "The service is under maintenance, please try again later";
+
+/// Seconds clients are asked to wait when the maintenance has no end.
+const DEFAULT_RETRY_AFTER: u64 = 300;
+
+/// Service for the read-only maintenance mode.
+///
+/// While the mode is on, the API refuses writes with a 503 and keeps serving
+/// reads, see `middleware::maintenance`. The mode is a setting, so it
+/// survives restarts and reaches every replica within the settings cache
+/// TTL. It ends when it is turned off or once it expired.
+pub struct MaintenanceService;
+
+impl MaintenanceService {
+    /// The maintenance mode in effect, none when writes are allowed.
+    pub async fn current(ctx: &Context) -> Result<Option<MaintenanceMode>> {
+        let mode: Option<MaintenanceMode> =
+            ctx.settings.get(&ctx.database, MAINTENANCE_SETTING).await?;
+        Ok(mode.filter(|mode| mode.is_active(ctx.clock.now())))
+    }
+
+    /// Turn the maintenance mode on or off. A maintenance already on keeps
+    /// its start time.
+    pub async fn set(
+        ctx: &Context,
+        req: &MaintenanceRequest,
+        actor: &str,
+    ) -> Result<MaintenanceResponse> {
+        let now = ctx.clock.now();
+        if req.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err(ApiError::BadRequest("The maintenance must expire in the future".into()));
+        }
+
+        let db = &ctx.database;
+        let mode = match req.enabled {
+            true => {
+                let started_at = Self::current(ctx).await?.map_or(now, |mode| mode.started_at);
+                let mode = MaintenanceMode {
+                    message: req.message.clone(),
+                    reject_webhooks: req.reject_webhooks,
+                    started_at,
+                    expires_at: req.expires_at,
+                };
+                let value = serde_json::to_value(&mode).map_err(ApiError::SerializationError)?;
+                SettingRepository::put(db, MAINTENANCE_SETTING, &value, actor).await?;
+                Some(mode)
+            }
+            false => {
+                SettingRepository::delete(db, MAINTENANCE_SETTING).await?;
+                None
+            }
+        };
+        ctx.settings.invalidate();
+
+        let details = json!({
+            "enabled": req.enabled,
+            "message": req.message,
+            "expires_at": req.expires_at,
+            "reject_webhooks": req.reject_webhooks,
+        });
+        let log =
+            AuditLogModel::new_at(actor, "set_maintenance", "settings/maintenance", details, now);
+        AuditRepository::create(db, &log).await?;
+
+        Ok(mode.into())
+    }
+
+    /// The error refusing a write during the maintenance at the given time,
+    /// asking clients to retry once it is expected to end.
+    pub fn refusal(mode: &MaintenanceMode, now: DateTime<Utc>) -> ApiError {
+        let retry_after = mode.expires_at.map_or(DEFAULT_RETRY_AFTER, |expires_at| {
+            (expires_at - now).num_seconds().max(1) as u64
+        });
+
+        ApiError::Maintenance {
+            message: mode.message.clone().unwrap_or_else(|| DEFAULT_MESSAGE.to_string()),
+            retry_after,
+        }
+    }
+}
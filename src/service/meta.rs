@@ -0,0 +1,104 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use tracing::debug;
+
+use crate::{
+    context::Context,
+    errors::{ApiError, Result},
+    model::CourseModel,
+    repository::{CourseRepository, StageRepository},
+    response::MetaPage,
+    utils::{crypto, html, markdown},
+};
+
+/// Maximum length of the description shown in link previews.
+const DESCRIPTION_LENGTH: usize = 200;
+
+/// Service rendering the link preview documents of shared frontend pages
+pub struct MetaService;
+
+impl MetaService {
+    /// Build the preview document of a course page.
+    pub async fn course(ctx: Arc<Context>, slug: &str, preview: Option<&str>) -> Result<MetaPage> {
+        let course = CourseRepository::get_by_slug(&ctx.database, slug).await?;
+        let public = Self::authorize(&ctx, &course, preview)?;
+
+        Ok(MetaPage {
+            title: course.name,
+            description: html::truncate(&course.summary, DESCRIPTION_LENGTH),
+            image: ctx.endpoints.asset_url(&course.logo),
+            url: ctx.endpoints.course_page(slug),
+            etag: format!("W/\"{}\"", course.updated_at.timestamp_millis()),
+            public,
+        })
+    }
+
+    /// Build the preview document of a stage page.
+    pub async fn stage(
+        ctx: Arc<Context>,
+        slug: &str,
+        stage_slug: &str,
+        preview: Option<&str>,
+    ) -> Result<MetaPage> {
+        let db = &ctx.database;
+        let course = CourseRepository::get_by_slug(db, slug).await?;
+        let public = Self::authorize(&ctx, &course, preview)?;
+        let stage = StageRepository::get_by_slug(db, slug, stage_slug).await?;
+
+        let description = markdown::to_text(&stage.description);
+        Ok(MetaPage {
+            title: format!("{} - {}", stage.name, course.name),
+            description: html::truncate(&description, DESCRIPTION_LENGTH),
+            image: ctx.endpoints.asset_url(&course.logo),
+            url: ctx.endpoints.stage_page(slug, stage_slug),
+            etag: format!(
+                "W/\"{}-{}\"",
+                course.updated_at.timestamp_millis(),
+                stage.updated_at.timestamp_millis()
+            ),
+            public,
+        })
+    }
+
+    /// Token granting previews of an unreleased course.
+    pub fn preview_token(ctx: &Context, slug: &str) -> Result<String> {
+        Ok(crypto::hmac_sha256_sign(&format!("preview:{slug}"), &ctx.config.auth_secret)?)
+    }
+
+    /// Ensures the course may be previewed, returns whether it is public.
+    ///
+    /// Unreleased courses are reported as missing unless a valid preview
+    /// token is supplied.
+    fn authorize(ctx: &Context, course: &CourseModel, preview: Option<&str>) -> Result<bool> {
+        if course.release_status != "alpha" {
+            return Ok(true);
+        }
+
+        let payload = format!("preview:{}", course.slug);
+        match preview {
+            Some(token)
+                if crypto::hmac_sha256_verify(&payload, &ctx.config.auth_secret, token)? =>
+            {
+                Ok(false)
+            }
+            _ => {
+                debug!("Refusing preview of unreleased course {:?}", course.slug);
+                Err(ApiError::NotFound)
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+};
+
+use futures::{StreamExt, stream};
+use gitea_client::{ClientError, types::MigrateRepoRequest};
+use serde_json::json;
+use tracing::{info, warn};
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    context::Context,
+    errors::{ApiError, Result},
+    jobs::MigrateRepositories,
+    model::{AuditLogModel, RepoMigrationModel},
+    repository::{AuditRepository, RepoMigrationRepository},
+    request::MigrateRepositoriesRequest,
+    response::{MigrateRepositoriesResponse, RepoMigrationReportResponse},
+    service::RepoService,
+    utils::crypto,
+};
+
+/// Purpose the source credentials are encrypted for
+const CREDENTIALS_PURPOSE: &str = "repo-migration";
+
+/// Number of migrations claimed per concurrent import.
+const CLAIM_FACTOR: usize = 4;
+
+/// Service importing learner repositories from another Gitea instance.
+///
+/// Imports are queued per user course and carried out in the background by
+/// the `migrate-repositories` job, which asks the Gitea server to pull each
+/// source repository into the organization of the learner repositories.
+/// While queued, the repository status of the enrollment is `migrating`, and
+/// it becomes `ready` or `failed` once the import finished.
+pub struct RepoMigrationService;
+
+impl RepoMigrationService {
+    /// Queue the import of the given source repositories, skipping the ones
+    /// already migrated. Migrations that failed or never finished are queued
+    /// again.
+    pub async fn enqueue(
+        ctx: Arc<Context>,
+        req: MigrateRepositoriesRequest,
+        actor: &str,
+    ) -> Result<MigrateRepositoriesResponse> {
+        let MigrateRepositoriesRequest { source_url, username, password, token, repositories } =
+            req;
+        Self::validate(&ctx, &source_url, &repositories).await?;
+
+        let secret = &ctx.config.auth_secret;
+        let encrypt = |value: Option<String>| {
+            value.map(|value| crypto::encrypt(&value, CREDENTIALS_PURPOSE, secret)).transpose()
+        };
+        let (password, token) = (encrypt(password)?, encrypt(token)?);
+
+        let mut queued = 0;
+        let mut skipped = Vec::new();
+        let mut tx = ctx.database.pool().begin().await?;
+        let now = ctx.clock.now();
+        for (source_repo, user_course_id) in &repositories {
+            let migration =
+                RepoMigrationModel::new_at(*user_course_id, &source_url, source_repo, now)
+                    .with_credentials(username.clone(), password.clone(), token.clone());
+            if RepoMigrationRepository::enqueue(&mut tx, &migration).await? {
+                queued += 1;
+            } else {
+                skipped.push(source_repo.clone());
+            }
+        }
+        tx.commit().await?;
+
+        let details = json!({ "queued": queued, "skipped": skipped });
+        let log = AuditLogModel::new_at(
+            actor,
+            "migrate_repositories",
+            &source_url,
+            details,
+            ctx.clock.now(),
+        );
+        AuditRepository::create(&ctx.database, &log).await?;
+
+        ctx.jobs.trigger(MigrateRepositories::NAME);
+        Ok(MigrateRepositoriesResponse { queued, skipped })
+    }
+
+    /// Import the queued repositories, at most `repo_migration_concurrency`
+    /// at a time, returning how many were processed.
+    pub async fn migrate_pending(ctx: Arc<Context>) -> Result<usize> {
+        let concurrency = ctx.config.repo_migration_concurrency.max(1);
+        let mut total = 0;
+
+        loop {
+            let limit = (concurrency * CLAIM_FACTOR) as i64;
+            let batch = RepoMigrationRepository::claim_pending(&ctx.database, limit).await?;
+            if batch.is_empty() {
+                break;
+            }
+
+            total += batch.len();
+            let results: Vec<Result<()>> = stream::iter(batch)
+                .map(|migration| Self::migrate(ctx.clone(), migration))
+                .buffer_unordered(concurrency)
+                .collect()
+                .await;
+            results.into_iter().collect::<Result<()>>()?;
+        }
+
+        Ok(total)
+    }
+
+    /// Report the progress of the migrations, optionally listing only those
+    /// with the given status.
+    pub async fn report(
+        ctx: Arc<Context>,
+        status: Option<&str>,
+    ) -> Result<RepoMigrationReportResponse> {
+        let counts = RepoMigrationRepository::count_by_status(&ctx.database).await?;
+        let count =
+            |status: &str| counts.iter().find(|(s, _)| s == status).map_or(0, |(_, n)| *n as usize);
+
+        let migrations = RepoMigrationRepository::find(&ctx.database, status).await?;
+        Ok(RepoMigrationReportResponse {
+            pending: count("pending"),
+            running: count("running"),
+            migrated: count("migrated"),
+            failed: count("failed"),
+            repositories: migrations.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    /// Check the source and the mapping of a migration request.
+    async fn validate(
+        ctx: &Context,
+        source_url: &str,
+        repositories: &BTreeMap<String, Uuid>,
+    ) -> Result<()> {
+        let url = Url::parse(source_url)
+            .map_err(|_| ApiError::BadRequest("Invalid source URL".to_string()))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(ApiError::BadRequest("Source URL must use HTTP(S)".to_string()));
+        }
+
+        if repositories.is_empty() {
+            return Err(ApiError::BadRequest("No repositories to migrate".to_string()));
+        }
+
+        let valid = |name: &str| {
+            name.split_once('/').is_some_and(|(owner, repo)| {
+                [owner, repo]
+                    .iter()
+                    .all(|part| !matches!(*part, "" | "." | "..") && !part.contains('/'))
+            })
+        };
+        if let Some(name) = repositories.keys().find(|name| !valid(name)) {
+            return Err(ApiError::BadRequest(format!("Invalid source repository: {name}")));
+        }
+
+        let ids: Vec<Uuid> = repositories.values().copied().collect();
+        if ids.iter().collect::<HashSet<_>>().len() != ids.len() {
+            return Err(ApiError::BadRequest("A user course is mapped more than once".to_string()));
+        }
+
+        let known = RepoMigrationRepository::find_user_course_ids(&ctx.database, &ids).await?;
+        if let Some(id) = ids.iter().find(|id| !known.contains(id)) {
+            return Err(ApiError::BadRequest(format!("Unknown user course: {id}")));
+        }
+
+        Ok(())
+    }
+
+    /// Import a single repository and record the outcome.
+    async fn migrate(ctx: Arc<Context>, migration: RepoMigrationModel) -> Result<()> {
+        let error = match Self::import(&ctx, &migration).await {
+            Ok(()) => {
+                info!(
+                    "Migrated repository {} for {}",
+                    migration.source_repo, migration.user_course_id
+                );
+                None
+            }
+            Err(e) => {
+                warn!("Failed to migrate repository {}: {e}", migration.source_repo);
+                Some(e.to_string())
+            }
+        };
+
+        let mut tx = ctx.database.pool().begin().await?;
+        RepoMigrationRepository::finish(&mut tx, &migration, error.as_deref()).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Ask the Gitea server to pull the source repository into the learner
+    /// repository of the user course.
+    async fn import(ctx: &Arc<Context>, migration: &RepoMigrationModel) -> Result<()> {
+        let secret = &ctx.config.auth_secret;
+        let decrypt = |value: &Option<String>| {
+            value.as_deref().map(|v| crypto::decrypt(v, CREDENTIALS_PURPOSE, secret)).transpose()
+        };
+
+        let owner = &ctx.config.namespace;
+        let name = migration.user_course_id.to_string();
+        let clone_addr = migration.clone_addr();
+        let req = MigrateRepoRequest {
+            auth_username: migration.auth_username.clone(),
+            auth_password: decrypt(&migration.encrypted_password)?,
+            auth_token: decrypt(&migration.encrypted_token)?,
+            clone_addr: clone_addr.clone(),
+            // A one-off copy, mirrors would refuse the pushes of the learner
+            mirror: Some(false),
+            repo_name: name.clone(),
+            repo_owner: Some(owner.clone()),
+            service: Some("gitea".to_string()),
+            ..Default::default()
+        };
+
+        match ctx.git.migrate_repository(req).await {
+            Ok(_) => {}
+            // An earlier run may have imported the repository without
+            // recording it, anything else must not be overwritten
+            Err(ClientError::Conflict(message)) => {
+                let repo = ctx.git.get_repository(owner, &name).await?;
+                if repo.original_url != clone_addr {
+                    return Err(ClientError::Conflict(message).into());
+                }
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        // Pushes to the imported repository reach us through the webhook of
+        // the organization or the repository
+        let repos = RepoService::new(ctx.clone());
+        match ctx.config.repo_webhooks {
+            true => repos.attach_webhook(owner, &name).await,
+            false => repos.setup_webhook(owner).await,
+        }
+    }
+}
@@ -12,19 +12,60 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod audit;
 mod course;
+mod engagement;
 mod extension;
+mod integrity;
+mod logs;
+mod maintenance;
+mod meta;
+mod migration;
+mod notification;
 mod pipeline;
+mod pool;
 mod registry;
 mod repository;
+mod roadmap;
+#[cfg(feature = "local-runner")]
+mod runner;
+mod settings;
+mod snapshot;
 mod stage;
 mod storage;
+mod token;
+mod trial;
+mod user;
+mod webhook;
+mod workspace;
 
 // Re-exports
-pub use course::CourseService;
+pub use audit::AuditService;
+pub use course::{CourseService, IDENTITY_FILE};
+pub use engagement::EngagementService;
 pub use extension::ExtensionService;
-pub use pipeline::{PipelineCleanupGuard, PipelineService};
+pub use integrity::{CompletionFeatures, IntegrityRules, IntegrityService};
+pub use logs::{LogEvent, LogService};
+pub use maintenance::MaintenanceService;
+pub use meta::MetaService;
+pub use migration::RepoMigrationService;
+pub use notification::{Notification, NotificationEvent, NotificationService};
+pub use pipeline::{PipelineCleanupGuard, PipelineService, THROTTLED, TestOutcome};
+pub(crate) use pipeline::{legacy_signing_payload, signing_payload};
+pub use pool::RepoPoolService;
 pub use registry::RegistryService;
-pub use repository::RepoService;
+pub use repository::{RepoService, Teardown, check_submodules};
+pub use roadmap::RoadmapService;
+#[cfg(feature = "local-runner")]
+pub use runner::{LocalRunner, RunnerError, TestRun};
+pub use settings::SettingsService;
+pub use snapshot::SnapshotService;
 pub use stage::StageService;
 pub use storage::{StorageError, StorageService};
+pub use token::ApiTokenService;
+pub use trial::TrialService;
+pub use user::UserService;
+pub use webhook::WebhookService;
+pub use workspace::{
+    IGNORE_FILE, SyncOutcome, TEMPLATE_UPDATE_BRANCH, TemplateUpdate, WorkspaceService,
+};
@@ -0,0 +1,245 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Notifications sent to learners, honoring their preferences.
+//!
+//! Every notification goes through [`NotificationService::dispatch`], which
+//! drops it unless the learner opted in to its event type. Users who never
+//! saved preferences get what they received before preferences existed:
+//! everything by email, accountability emails only for the courses they
+//! enabled them on.
+
+use std::sync::Arc;
+
+use serde::Serialize;
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::{
+    context::Context,
+    errors::{ApiError, Result},
+    model::NotificationPreferencesModel,
+    repository::{CourseRepository, NotificationRepository, UserRepository},
+    request::{NotificationChannel, UpdateNotificationPreferencesRequest},
+    response::NotificationPreferencesResponse,
+};
+
+/// Event types learners are notified about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    Announcement,
+    Feedback,
+    DeadlineReminder,
+    Accountability,
+}
+
+/// A notification for a single learner.
+#[derive(Clone, Debug, Serialize)]
+pub struct Notification {
+    /// ID of the notified user
+    #[serde(skip)]
+    pub user_id: String,
+
+    /// Type of the event
+    pub event: NotificationEvent,
+
+    /// Slug of the course the notification relates to, if any
+    pub course: Option<String>,
+
+    /// Short summary of the notification
+    pub subject: String,
+
+    /// Markdown body of the notification
+    pub body: String,
+}
+
+/// Payload posted to the email relay.
+#[derive(Serialize)]
+struct EmailMessage<'a> {
+    to: &'a str,
+    #[serde(flatten)]
+    notification: &'a Notification,
+}
+
+pub struct NotificationService;
+
+impl NotificationService {
+    /// Get the notification preferences of a user, the defaults if they
+    /// never saved any.
+    pub async fn get_preferences(
+        ctx: Arc<Context>,
+        user_id: &str,
+    ) -> Result<NotificationPreferencesResponse> {
+        let prefs = NotificationRepository::get(&ctx.database, user_id).await?;
+        let saved = prefs.is_some();
+        let prefs =
+            prefs.unwrap_or_else(|| NotificationPreferencesModel::new_at(user_id, ctx.clock.now()));
+        Ok((prefs, saved).into())
+    }
+
+    /// Replace the notification preferences of a user.
+    pub async fn update_preferences(
+        ctx: Arc<Context>,
+        user_id: &str,
+        req: &UpdateNotificationPreferencesRequest,
+    ) -> Result<NotificationPreferencesResponse> {
+        let destination = req.destination.as_deref().map(str::trim).filter(|d| !d.is_empty());
+        match (req.channel, destination) {
+            (NotificationChannel::Webhook, None) => {
+                return Err(ApiError::BadRequest("A webhook URL is required".into()));
+            }
+            (NotificationChannel::Webhook, Some(url)) if !is_https_url(url) => {
+                return Err(ApiError::BadRequest("Webhook URL must use https".into()));
+            }
+            (NotificationChannel::Email, Some(email)) if !is_valid_email(email) => {
+                return Err(ApiError::BadRequest("Invalid email address".into()));
+            }
+            _ => {}
+        }
+
+        let prefs = NotificationPreferencesModel {
+            channel: req.channel.as_str().to_string(),
+            destination: destination.map(str::to_string),
+            announcements: req.announcements,
+            feedback: req.feedback,
+            deadline_reminders: req.deadline_reminders,
+            accountability: req.accountability,
+            ..NotificationPreferencesModel::new_at(user_id, ctx.clock.now())
+        };
+        let prefs = NotificationRepository::upsert(&ctx.database, &prefs).await?;
+        Ok((prefs, true).into())
+    }
+
+    /// Deliver a notification unless the learner opted out of its event
+    /// type, returning whether it was sent.
+    ///
+    /// Delivery failures are logged rather than returned, a notification is
+    /// never worth failing the operation that produced it.
+    pub async fn dispatch(ctx: &Context, notification: &Notification) -> Result<bool> {
+        let db = &ctx.database;
+        let user_id = &notification.user_id;
+        let prefs = NotificationRepository::get(db, user_id).await?;
+        let prefs = prefs.unwrap_or_else(|| NotificationPreferencesModel::new(user_id));
+
+        let mut enabled = match notification.event {
+            NotificationEvent::Announcement => prefs.announcements,
+            NotificationEvent::Feedback => prefs.feedback,
+            NotificationEvent::DeadlineReminder => prefs.deadline_reminders,
+            NotificationEvent::Accountability => prefs.accountability,
+        };
+
+        // Accountability emails are still enabled per course
+        if enabled &&
+            notification.event == NotificationEvent::Accountability &&
+            let Some(course) = &notification.course
+        {
+            enabled = CourseRepository::get_user_course(db, user_id, course).await?.accountability;
+        }
+
+        if !enabled {
+            debug!("User {} opted out of {:?} notifications", user_id, notification.event);
+            return Ok(false);
+        }
+
+        let request = match prefs.channel.as_str() {
+            "webhook" => {
+                let Some(url) = &prefs.destination else { return Ok(false) };
+                ctx.http.post(url).json(notification)
+            }
+            _ => {
+                let Some(relay) = ctx.config.notification_relay_url.as_deref() else {
+                    debug!("No email relay configured, dropping notification for {user_id}");
+                    return Ok(false);
+                };
+                let email = match prefs.destination {
+                    Some(email) => email,
+                    None => UserRepository::get_by_id(db, user_id).await?.email,
+                };
+                ctx.http.post(relay).json(&EmailMessage { to: &email, notification })
+            }
+        };
+
+        match request.send().await.and_then(|response| response.error_for_status()) {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                warn!("Failed to deliver {:?} notification to {user_id}: {e}", notification.event);
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// Whether the URL is an absolute https URL with a host.
+fn is_https_url(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| url.scheme() == "https" && url.host_str().is_some())
+}
+
+/// Whether the address is a syntactically valid email address, in the
+/// pragmatic `local@domain.tld` form rather than everything RFC 5322 allows.
+fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else { return false };
+    let valid_label = |label: &str| {
+        !label.is_empty() &&
+            !label.starts_with('-') &&
+            !label.ends_with('-') &&
+            label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    };
+
+    email.len() <= 254 &&
+        !local.is_empty() &&
+        local.len() <= 64 &&
+        !local.starts_with('.') &&
+        !local.ends_with('.') &&
+        !local.contains("..") &&
+        local.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+/=?^_`{|}~.-".contains(c)) &&
+        domain.contains('.') &&
+        domain.split('.').all(valid_label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_webhook_url_must_be_https() {
+        assert!(is_https_url("https://hooks.example.com/notify"));
+        assert!(!is_https_url("http://hooks.example.com/notify"));
+        assert!(!is_https_url("https://"));
+        assert!(!is_https_url("hooks.example.com/notify"));
+        assert!(!is_https_url("ftp://hooks.example.com"));
+    }
+
+    #[test]
+    fn test_email_syntax() {
+        for email in ["ada@example.com", "ada.lovelace+courses@mail.example.org"] {
+            assert!(is_valid_email(email), "{email}");
+        }
+        for email in [
+            "",
+            "ada",
+            "ada@",
+            "@example.com",
+            "ada@localhost",
+            "ada@@example.com",
+            "ada lovelace@example.com",
+            ".ada@example.com",
+            "ada..l@example.com",
+            "ada@-example.com",
+            "ada@example..com",
+        ] {
+            assert!(!is_valid_email(email), "{email}");
+        }
+    }
+}
@@ -12,23 +12,128 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
+use chrono::DateTime;
+use k8s_openapi::{
+    api::core::v1::{ConfigMap, Secret},
+    apimachinery::pkg::apis::meta::v1::OwnerReference,
+};
 use kube::{
     Api,
-    api::{ApiResource, DeleteParams, DynamicObject, GroupVersionKind, PostParams},
+    api::{
+        ApiResource, DeleteParams, DynamicObject, GroupVersionKind, ListParams, ObjectMeta, Patch,
+        PatchParams, PostParams,
+    },
 };
 use serde_json::{Error as JsonError, Value, json};
-use tracing::{debug, error};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+#[cfg(feature = "local-runner")]
+use crate::service::TestRun;
 use crate::{
+    config::{Config, ExecutionBackend},
     context::Context,
     errors::{ApiError, Result},
-    repository::StageRepository,
-    utils::{crypto, url},
+    jobs::DispatchQueuedAttempts,
+    model::{AttemptOutput, StageAttemptModel},
+    repository::{CourseRepository, StageRepository},
+    request::event::TestCaseResult,
+    response::{PipelineRunResponse, RejectionReason},
+    schema::PipelineSettings,
+    service::{
+        MaintenanceService, RegistryService, RepoService, SettingsService, StageService,
+        TrialService,
+    },
+    utils::{crypto, resources::PodResources},
 };
 
+/// Name of the task running the tester in the course test pipeline.
+pub(crate) const TEST_TASK: &str = "test";
+
+/// Workspace the ConfigMap with the test cases is bound to, holding them in
+/// the [`TEST_CASES_FILE`] file.
+const TEST_CASES_WORKSPACE: &str = "test-cases";
+
+/// Key of the test cases in their ConfigMap.
+const TEST_CASES_FILE: &str = "test-cases.json";
+
+/// Label marking the ConfigMaps holding test cases.
+const TEST_CASES_LABEL: &str = "stackclass.dev/test-cases";
+
+/// Age in seconds after which a ConfigMap with test cases or a Secret with a
+/// deploy key but no PipelineRun is considered orphaned.
+const TEST_CASES_ORPHAN_AGE: i64 = 600;
+
+/// Workspace the Secret with the deploy key the repository is cloned with is
+/// bound to, as the SSH directory of the clone task.
+const DEPLOY_KEY_WORKSPACE: &str = "ssh-directory";
+
+/// Maximum size of the tester output kept with an attempt, in bytes.
+const MAX_LOG_BYTES: usize = 16 * 1024;
+
+/// Label marking the Secrets holding deploy keys.
+const DEPLOY_KEY_LABEL: &str = "stackclass.dev/deploy-key";
+
+/// Seconds the webhook of a finished PipelineRun is given to report its
+/// outcome before the run is reconciled without it.
+const RECONCILE_GRACE_SECS: i64 = 300;
+
+/// Most pending attempts reconciled at once.
+const RECONCILE_BATCH: i64 = 100;
+
+/// Reason recorded on the attempts whose PipelineRun is gone.
+const RUN_MISSING: &str = "PipelineRunMissing";
+
+/// Reason recorded on the attempts queued while their user had as many
+/// PipelineRuns running as allowed.
+pub const THROTTLED: &str = "throttled";
+
+/// Label naming the repository a PipelineRun grades.
+const REPO_LABEL: &str = "stackclass.dev/repo";
+
+/// Label naming the stage a PipelineRun grades.
+const STAGE_LABEL: &str = "stackclass.dev/stage";
+
+/// Outcome of a test run grading an attempt, as reported by a PipelineRun or
+/// the local runner.
+#[derive(Clone, Copy, Debug)]
+pub struct TestOutcome<'a> {
+    /// Name of the run the attempt was recorded with
+    pub run: &'a str,
+
+    /// Name of the graded repository
+    pub repo: &'a str,
+
+    /// Slug of the course
+    pub course: &'a str,
+
+    /// Slug of the graded stage
+    pub stage: &'a str,
+
+    /// Commit of the course repository the attempt was graded against
+    pub commit: &'a str,
+
+    /// Content hash of the stage the attempt was graded against
+    pub content_hash: &'a str,
+
+    /// Commit of the graded repository, empty if the run did not report it
+    pub repo_commit: &'a str,
+
+    /// Whether all tests passed
+    pub passed: bool,
+
+    /// Reason the run reported for its outcome, empty if none
+    pub reason: &'a str,
+
+    /// Output of the tester, empty if the run did not report it
+    pub log: &'a str,
+
+    /// Results of the test cases the tester ran
+    pub results: &'a [TestCaseResult],
+}
+
 /// A service for managing Tekton PipelineRun resources.
 pub struct PipelineService {
     ctx: Arc<Context>,
@@ -40,26 +145,568 @@ impl PipelineService {
         PipelineService { ctx }
     }
 
-    /// Triggers a Tekton PipelineRun for the given repository.
-    pub async fn trigger(&self, repo: &str, course: &str, stage: &str) -> Result<()> {
+    /// Triggers a Tekton PipelineRun for the given repository, returning
+    /// the name of the created PipelineRun.
+    ///
+    /// The course commit, stage content hash and pushed commit are passed to
+    /// the pipeline and echoed back in its completion event, so the outcome
+    /// can be tied to the course version and the code it was graded against.
+    pub async fn trigger(
+        &self,
+        repo: &str,
+        course: &str,
+        stage: &str,
+        commit: &str,
+        content_hash: &str,
+        repo_commit: &str,
+    ) -> Result<String> {
         debug!("Triggering PipelineRun for repository: {course} - {repo}");
 
-        let resource = self.generate(repo, course, stage).await?;
-        self.api().create(&PostParams::default(), &resource).await?;
+        let (resource, cases, key) =
+            self.generate(repo, course, stage, commit, content_hash, repo_commit).await?;
+        let name = resource.metadata.name.clone().unwrap_or_default();
+
+        // The test cases and the deploy key must exist before the pipeline
+        // pod mounts them
+        if let Some(cases) = &cases {
+            self.config_maps().create(&PostParams::default(), cases).await?;
+        }
+        if let Some(key) = &key &&
+            let Err(e) = self.secrets().create(&PostParams::default(), key).await
+        {
+            if cases.is_some() {
+                self.delete_test_cases(&name).await?;
+            }
+            return Err(e.into());
+        }
+
+        let run = match self.api().create(&PostParams::default(), &resource).await {
+            Ok(run) => run,
+            Err(e) => {
+                if cases.is_some() {
+                    self.delete_test_cases(&name).await?;
+                }
+                if key.is_some() {
+                    self.delete_deploy_key(&name).await?;
+                }
+                return Err(e.into());
+            }
+        };
+
+        // Owned by the run, the test cases and the deploy key go away
+        // together with it. Should this fail, the orphan sweep removes them
+        // later.
+        if let Some(owner) = owner_reference(&run) {
+            let patch = json!({ "metadata": { "ownerReferences": [owner] } });
+            let (params, patch) = (PatchParams::default(), Patch::Merge(&patch));
+            if cases.is_some() &&
+                let Err(e) = self.config_maps().patch(&name, &params, &patch).await
+            {
+                warn!("Failed to set the owner of test cases {name}: {e}");
+            }
+            if key.is_some() &&
+                let Err(e) = self.secrets().patch(&name, &params, &patch).await
+            {
+                warn!("Failed to set the owner of deploy key {name}: {e}");
+            }
+        }
+
+        Ok(name)
+    }
+
+    /// Grades an attempt by triggering its PipelineRun and records it.
+    ///
+    /// While the Kubernetes API is unreachable the attempt is queued instead
+    /// of failing, so the push is graded once the cluster is back.
+    pub async fn schedule(
+        &self,
+        repo: &str,
+        course: &str,
+        stage: &str,
+        attempt: StageAttemptModel,
+    ) -> Result<StageAttemptModel> {
+        let db = &self.ctx.database;
+
+        // The local runner needs no cluster, it starts once the attempt is
+        // recorded so that the outcome always finds it
+        #[cfg(feature = "local-runner")]
+        if let Some(runner) = &self.ctx.runner {
+            let name = Uuid::now_v7().to_string();
+            let attempt =
+                StageRepository::create_attempt(db, &attempt.with_pipeline_run(&name)).await?;
+            let (commit, hash) = (&attempt.course_commit, &attempt.content_hash);
+            let run = TestRun::new(&name, repo, course, stage, commit, hash, &attempt.repo_commit);
+            runner.spawn(self.ctx.clone(), run);
+            return Ok(attempt);
+        }
+
+        // Attempts pushed during a maintenance wait for it to end
+        if matches!(MaintenanceService::current(&self.ctx).await, Ok(Some(_))) {
+            info!("Queueing attempt for repository {repo} until the maintenance ends");
+            return Ok(StageRepository::create_attempt(db, &attempt.with_status("queued")).await?);
+        }
+
+        // Attempts of users with too many runs wait for one of them to finish
+        if self.is_throttled(&attempt.user_stage_id).await? {
+            info!("Queueing attempt for repository {repo} until a run of its user finishes");
+            let attempt = attempt.with_status("queued").with_reason(THROTTLED);
+            return Ok(StageRepository::create_attempt(db, &attempt).await?);
+        }
+
+        if self.ctx.cluster.is_available() {
+            let (commit, hash) = (&attempt.course_commit, &attempt.content_hash);
+            match self.trigger(repo, course, stage, commit, hash, &attempt.repo_commit).await {
+                Ok(name) => {
+                    let attempt = attempt.with_pipeline_run(&name);
+                    return Ok(StageRepository::create_attempt(db, &attempt).await?);
+                }
+                Err(ApiError::KubernetesError(e)) if is_unavailable(&e) => {
+                    warn!("Kubernetes API is unavailable, queueing attempt: {e}");
+                    self.ctx.cluster.set_available(false);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        info!("Queueing attempt for repository {repo} until the cluster is available");
+        Ok(StageRepository::create_attempt(db, &attempt.with_status("queued")).await?)
+    }
+
+    /// Whether the user owning the user stage has as many PipelineRuns
+    /// running as allowed, so that another one has to wait.
+    pub async fn is_throttled(&self, user_stage_id: &Uuid) -> Result<bool> {
+        let limit = self.ctx.config.max_concurrent_pipelines_per_user as i64;
+        if limit == 0 {
+            return Ok(false);
+        }
+
+        let running =
+            StageRepository::count_running_attempts(&self.ctx.database, user_stage_id).await?;
+        if running < limit {
+            return Ok(false);
+        }
+
+        info!("Throttling user stage {user_stage_id}: {running} PipelineRuns of its user running");
+        Ok(true)
+    }
+
+    /// Triggers the PipelineRuns of the queued attempts, oldest first, and
+    /// returns how many were dispatched. Attempts of users with as many runs
+    /// as allowed stay queued. Stops at the first connectivity error, leaving
+    /// the remaining attempts queued.
+    pub async fn dispatch_queued(&self) -> Result<usize> {
+        let limit = self.ctx.config.max_concurrent_pipelines_per_user as i64;
+        let mut dispatched = 0;
+
+        loop {
+            let mut tx = self.ctx.database.pool().begin().await?;
+            let Some(queued) = StageRepository::claim_queued_attempt(&mut tx, limit).await? else {
+                break;
+            };
+
+            let repo = queued.user_course_id.to_string();
+            let (course, stage) = (&queued.course_slug, &queued.stage_slug);
+            let (commit, hash) = (&queued.course_commit, &queued.content_hash);
+
+            match self.trigger(&repo, course, stage, commit, hash, &queued.repo_commit).await {
+                Ok(name) => {
+                    StageRepository::dispatch_attempt(&mut tx, &queued.id, "pending", Some(&name))
+                        .await?;
+                    dispatched += 1;
+                }
+                Err(ApiError::KubernetesError(e)) if is_unavailable(&e) => {
+                    warn!("Kubernetes API is unavailable, keeping attempts queued: {e}");
+                    self.ctx.cluster.set_available(false);
+                    break;
+                }
+                Err(e) => {
+                    // Fail the attempt rather than retrying it forever.
+                    error!("Failed to dispatch queued attempt {}: {e}", queued.id);
+                    StageRepository::dispatch_attempt(&mut tx, &queued.id, "failed", None).await?;
+                }
+            }
+
+            tx.commit().await?;
+        }
+
+        Ok(dispatched)
+    }
+
+    /// Records the outcome of a test run on the graded attempt, and completes
+    /// the stage when it passed before the deadline.
+    pub async fn record_outcome(&self, outcome: &TestOutcome<'_>) -> Result<()> {
+        let TestOutcome {
+            run, repo, course, stage, commit, content_hash, repo_commit, passed, ..
+        } = *outcome;
+        let status = if passed { "passed" } else { "failed" };
+
+        // Trials record the outcome on themselves, there is no stage to complete
+        if let Some(id) = TrialService::parse_repo(repo) {
+            return TrialService::record_result(&self.ctx, &id, run, status).await;
+        }
+
+        // Results of unknown runs, or runs whose result was recorded already,
+        // are dropped
+        let db = &self.ctx.database;
+        let output = AttemptOutput {
+            reason: outcome.reason,
+            log: log_tail(outcome.log),
+            results: serde_json::to_value(outcome.results).map_err(ApiError::SerializationError)?,
+        };
+        let Some(attempt) = StageRepository::complete_attempt(
+            db,
+            run,
+            status,
+            commit,
+            content_hash,
+            repo_commit,
+            &output,
+        )
+        .await?
+        else {
+            return Err(RejectionReason::StalePipeline.into());
+        };
+
+        // The finished run lets a throttled attempt of the user be dispatched
+        if self.ctx.config.max_concurrent_pipelines_per_user > 0 {
+            self.ctx.jobs.trigger(DispatchQueuedAttempts::NAME);
+        }
+        if !passed {
+            return Ok(());
+        }
+
+        // Attempts submitted after the deadline are recorded, but do not
+        // count toward completion
+        if attempt.late {
+            info!("Stage {} passed after the deadline for repository {}", stage, repo);
+            return Ok(());
+        }
+
+        // Look up the course to get the user_id
+        let id = Uuid::parse_str(repo)?;
+        let user_course = CourseRepository::get_user_course_by_id(db, &id).await?;
+
+        // Mark the stage as complete, keeping the commit that passed it
+        let graded = Some(attempt.repo_commit.as_str());
+        StageService::complete(self.ctx.clone(), &user_course.user_id, course, stage, graded)
+            .await?;
+        info!("Stage {} completed successfully for course {}", stage, course);
 
         Ok(())
     }
 
-    /// Deletes a Tekton PipelineRun by name.
+    /// Lists the PipelineRuns of the repository still kept by the cluster.
+    pub async fn list_by_repo(&self, repo: &str) -> Result<Vec<DynamicObject>> {
+        let params = ListParams::default().labels(&format!("{REPO_LABEL}={repo}"));
+        Ok(self.api().list(&params).await?.items)
+    }
+
+    /// Finds the PipelineRuns grading the repository of a user course,
+    /// newest first.
+    pub async fn find_by_user_course(
+        &self,
+        user_id: &str,
+        course_slug: &str,
+    ) -> Result<Vec<PipelineRunResponse>> {
+        let user_course =
+            CourseRepository::get_user_course(&self.ctx.database, user_id, course_slug).await?;
+        let mut runs = self.list_by_repo(&user_course.id.to_string()).await?;
+        runs.sort_by(|a, b| b.metadata.creation_timestamp.cmp(&a.metadata.creation_timestamp));
+
+        Ok(runs.into_iter().map(run_response).collect())
+    }
+
+    /// Finds a PipelineRun of the repository which has not completed yet,
+    /// returning its name.
+    pub async fn find_active(&self, repo: &str) -> Result<Option<String>> {
+        let runs = self.list_by_repo(repo).await?;
+        Ok(runs.into_iter().find(is_active).and_then(|run| run.metadata.name))
+    }
+
+    /// Cancels a Tekton PipelineRun by name. Tekton stops its pods and
+    /// reports it as failed.
+    pub async fn cancel(&self, name: &str) -> Result<()> {
+        debug!("Cancelling PipelineRun: {name}");
+
+        let patch = json!({ "spec": { "status": "Cancelled" } });
+        match self.api().patch(name, &PatchParams::default(), &Patch::Merge(&patch)).await {
+            Ok(_) => Ok(()),
+            Err(kube::Error::Api(status)) if status.code == 404 => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Cancels the PipelineRuns of the repository which have not completed
+    /// yet, as a newer push supersedes them, and returns their names. Their
+    /// attempts are recorded as cancelled, so the outcome reported by Tekton
+    /// is dropped.
+    pub async fn cancel_superseded(&self, repo: &str) -> Result<Vec<String>> {
+        // Local runs are short lived and have no cluster to cancel them in
+        if self.ctx.config.execution_backend != ExecutionBackend::Tekton ||
+            !self.ctx.cluster.is_available()
+        {
+            return Ok(Vec::new());
+        }
+
+        let runs = self.list_by_repo(repo).await?;
+        let mut cancelled = Vec::new();
+        for name in runs.into_iter().filter(is_active).filter_map(|run| run.metadata.name) {
+            self.cancel(&name).await?;
+            StageRepository::cancel_attempt(&self.ctx.database, &name).await?;
+            info!("Cancelled PipelineRun {name} superseded by a push to repository {repo}");
+            cancelled.push(name);
+        }
+
+        Ok(cancelled)
+    }
+
+    /// Checks the Tekton Pipeline a course refers to exists, so that courses
+    /// do not fail every attempt on a mistyped `pipeline_ref`.
+    pub async fn ensure_pipeline(&self, name: &str) -> Result<()> {
+        match self.tekton("Pipeline").get_opt(name).await? {
+            Some(_) => Ok(()),
+            None => Err(ApiError::BadRequest(format!(
+                "Unknown pipeline_ref: no Tekton Pipeline {name} in namespace {}",
+                self.ctx.config.namespace
+            ))),
+        }
+    }
+
+    /// Records the outcome of the PipelineRuns which finished without their
+    /// webhook getting through, like while the backend restarted, returning
+    /// how many attempts were settled.
+    ///
+    /// Runs finished for a while are graded from their conditions, and the
+    /// attempts whose run is gone are failed. The tester output and the test
+    /// case results only travel in the webhook, and are not recorded.
+    pub async fn reconcile(&self) -> Result<usize> {
+        let db = &self.ctx.database;
+        let cutoff = self.ctx.clock.now() - chrono::Duration::seconds(RECONCILE_GRACE_SECS);
+        let mut settled = 0;
+
+        for attempt in StageRepository::find_pending_attempts(db, cutoff, RECONCILE_BATCH).await? {
+            let Some(name) = attempt.pipeline_run.as_deref() else {
+                continue;
+            };
+
+            let Some(run) = self.api().get_opt(name).await? else {
+                let output = AttemptOutput { reason: RUN_MISSING, log: "", results: json!([]) };
+                if StageRepository::complete_attempt(db, name, "failed", "", "", "", &output)
+                    .await?
+                    .is_some()
+                {
+                    warn!("Failed attempt {} whose PipelineRun {name} is gone", attempt.id);
+                    settled += 1;
+                }
+                continue;
+            };
+
+            // Runs which just finished may still be reporting their outcome
+            let finished = run.data["status"]["completionTime"].as_str();
+            let finished = finished.and_then(|t| DateTime::parse_from_rfc3339(t).ok());
+            if finished.is_none_or(|t| t >= cutoff) {
+                continue;
+            }
+
+            match self.reconcile_run(&run).await {
+                Ok(()) => {
+                    info!("Recorded the outcome of PipelineRun {name} without its webhook");
+                    settled += 1;
+                }
+                Err(ApiError::WebhookRejected(RejectionReason::StalePipeline)) => {}
+                Err(e) => {
+                    error!("Failed to reconcile PipelineRun {name}: {e}");
+                    continue;
+                }
+            }
+
+            if let Err(e) = self.delete(name).await {
+                warn!("Failed to delete reconciled PipelineRun {name}: {e}");
+            }
+        }
+
+        Ok(settled)
+    }
+
+    /// Records the outcome of a finished PipelineRun from its conditions and
+    /// params, the test task deciding whether the attempt passed.
+    async fn reconcile_run(&self, run: &DynamicObject) -> Result<()> {
+        // Runs which succeeded may still have failed their test task
+        let (status, reason) = succeeded(&run.data);
+        let refs = run.data["status"]["childReferences"].as_array();
+        let task = refs.and_then(|refs| {
+            refs.iter().find(|r| r["pipelineTaskName"] == TEST_TASK)?["name"].as_str()
+        });
+        let task = match task {
+            Some(task) if status == "True" => self.tekton("TaskRun").get_opt(task).await?,
+            _ => None,
+        };
+        let (passed, reason) = match &task {
+            Some(task) => {
+                let (status, reason) = succeeded(&task.data);
+                (status == "True", reason)
+            }
+            None => (false, reason),
+        };
+
+        let name = run.metadata.name.as_deref().unwrap_or_default();
+        let outcome = TestOutcome {
+            run: name,
+            repo: param(run, "REPO"),
+            course: param(run, "COURSE"),
+            stage: param(run, "STAGE"),
+            commit: param(run, "COMMIT"),
+            content_hash: param(run, "CONTENT_HASH"),
+            repo_commit: param(run, "REPO_COMMIT"),
+            passed,
+            reason,
+            log: "",
+            results: &[],
+        };
+        self.record_outcome(&outcome).await
+    }
+
+    /// Probes the Kubernetes API with a minimal list request and records
+    /// whether it is reachable.
+    pub async fn check_health(&self) -> bool {
+        let result = self.api().list(&ListParams::default().limit(1)).await;
+        let available = result.as_ref().map_or_else(|e| !is_unavailable(e), |_| true);
+
+        if self.ctx.cluster.set_available(available) != available {
+            match available {
+                true => info!("Kubernetes API is available again"),
+                false => warn!("Kubernetes API is unavailable: {:?}", result.err()),
+            }
+        }
+        available
+    }
+
+    /// Deletes a Tekton PipelineRun by name, along with its test cases and
+    /// deploy key.
     pub async fn delete(&self, name: &str) -> Result<()> {
         debug!("Deleting PipelineRun: {name}");
         self.api().delete(name, &DeleteParams::default()).await?;
-        Ok(())
+
+        // Usually collected with the run already, unless it never got an owner
+        self.delete_test_cases(name).await?;
+        self.delete_deploy_key(name).await
+    }
+
+    /// Deletes the PipelineRuns grading repositories which finished longer
+    /// ago than the retention window, or are not finished past the hard
+    /// timeout, returning how many were deleted. Finished runs have their
+    /// outcome recorded first, in case their webhook never got through.
+    pub async fn remove_expired_runs(&self) -> Result<usize> {
+        let config = &self.ctx.config;
+        let (retention, max_age) =
+            (config.pipeline_run_retention as i64, config.pipeline_run_max_age as i64);
+        let params = ListParams::default().labels(REPO_LABEL);
+        let now = self.ctx.clock.now().timestamp();
+        let (mut finished, mut stuck) = (0, 0);
+
+        for run in self.api().list(&params).await? {
+            if !is_expired(&run, now, retention, max_age) {
+                continue;
+            }
+
+            let name = run.metadata.name.clone().unwrap_or_default();
+            if is_active(&run) {
+                warn!("Deleting PipelineRun {name}, not finished after {max_age}s");
+                stuck += 1;
+            } else {
+                match self.reconcile_run(&run).await {
+                    Ok(()) | Err(ApiError::WebhookRejected(RejectionReason::StalePipeline)) => {}
+                    Err(e) => {
+                        error!("Failed to record the outcome of PipelineRun {name}: {e}");
+                        continue;
+                    }
+                }
+                finished += 1;
+            }
+            self.delete(&name).await?;
+        }
+
+        if finished + stuck > 0 {
+            info!("Swept {finished} finished and {stuck} stuck PipelineRuns");
+        }
+        Ok(finished + stuck)
+    }
+
+    /// Deletes the ConfigMaps with test cases whose PipelineRun is gone
+    /// without having owned them, returning how many were deleted.
+    pub async fn remove_orphaned_test_cases(&self) -> Result<usize> {
+        let params = ListParams::default().labels(TEST_CASES_LABEL);
+        let now = self.ctx.clock.now().timestamp();
+        let mut removed = 0;
+
+        for cases in self.config_maps().list(&params).await? {
+            if !may_be_orphaned(&cases.metadata, now) {
+                continue;
+            }
+
+            let name = cases.metadata.name.unwrap_or_default();
+            if self.api().get_opt(&name).await?.is_none() {
+                info!("Deleting orphaned test cases {name}");
+                self.delete_test_cases(&name).await?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Deletes the Secrets with deploy keys whose PipelineRun is gone without
+    /// having owned them, returning how many were deleted.
+    pub async fn remove_orphaned_deploy_keys(&self) -> Result<usize> {
+        let params = ListParams::default().labels(DEPLOY_KEY_LABEL);
+        let now = self.ctx.clock.now().timestamp();
+        let mut removed = 0;
+
+        for key in self.secrets().list(&params).await? {
+            if !may_be_orphaned(&key.metadata, now) {
+                continue;
+            }
+
+            let name = key.metadata.name.unwrap_or_default();
+            if self.api().get_opt(&name).await?.is_none() {
+                info!("Deleting orphaned deploy key {name}");
+                self.delete_deploy_key(&name).await?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Deletes the Secret with the deploy key of a PipelineRun, treating a
+    /// missing one as success.
+    async fn delete_deploy_key(&self, name: &str) -> Result<()> {
+        match self.secrets().delete(name, &DeleteParams::default()).await {
+            Ok(_) => Ok(()),
+            Err(kube::Error::Api(status)) if status.code == 404 => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Deletes the ConfigMap with the test cases of a PipelineRun, treating
+    /// a missing one as success.
+    async fn delete_test_cases(&self, name: &str) -> Result<()> {
+        match self.config_maps().delete(name, &DeleteParams::default()).await {
+            Ok(_) => Ok(()),
+            Err(kube::Error::Api(status)) if status.code == 404 => Ok(()),
+            Err(e) => Err(e.into()),
+        }
     }
 
     #[inline]
     fn api(&self) -> Api<DynamicObject> {
-        let gvk = GroupVersionKind::gvk("tekton.dev", "v1", "PipelineRun");
+        self.tekton("PipelineRun")
+    }
+
+    /// API of the Tekton resources of the given kind.
+    #[inline]
+    fn tekton(&self, kind: &str) -> Api<DynamicObject> {
+        let gvk = GroupVersionKind::gvk("tekton.dev", "v1", kind);
         Api::namespaced_with(
             self.ctx.k8s.clone(),
             self.ctx.config.namespace.as_ref(),
@@ -67,58 +714,194 @@ impl PipelineService {
         )
     }
 
-    /// Generates a PipelineRun resource for the given repository.
-    async fn generate(&self, repo: &str, course: &str, stage: &str) -> Result<DynamicObject> {
+    #[inline]
+    fn config_maps(&self) -> Api<ConfigMap> {
+        Api::namespaced(self.ctx.k8s.clone(), self.ctx.config.namespace.as_ref())
+    }
+
+    #[inline]
+    fn secrets(&self) -> Api<Secret> {
+        Api::namespaced(self.ctx.k8s.clone(), self.ctx.config.namespace.as_ref())
+    }
+
+    /// Generates a PipelineRun resource for the given repository, along with
+    /// the ConfigMap holding its test cases when they are too large for a
+    /// param, and the Secret holding the deploy key when cloning over SSH.
+    async fn generate(
+        &self,
+        repo: &str,
+        course: &str,
+        stage: &str,
+        commit: &str,
+        content_hash: &str,
+        repo_commit: &str,
+    ) -> Result<(DynamicObject, Option<ConfigMap>, Option<Secret>)> {
         let name = Uuid::now_v7().to_string();
 
         // Define labels for identification
         let labels = vec![
-            ("stackclass.dev/repo", repo.to_string()),
+            (REPO_LABEL, repo.to_string()),
             ("stackclass.dev/course", course.to_string()),
-            ("stackclass.dev/stage", stage.to_string()),
+            (STAGE_LABEL, stage.to_string()),
         ];
 
         // Build test cases JSON value from all stages up to the current stage
         let stages = StageRepository::find_stages_until(&self.ctx.database, course, stage).await?;
         let slugs: Vec<&str> = stages.iter().map(|stage| stage.slug.as_str()).collect();
-        let cases = build_test_cases_json(&slugs);
+        let cases = test_cases(&name, build_test_cases_json(&slugs), &self.ctx.config);
+
+        // The test pod gets the resources of the stage's profile, and the
+        // tester image the course is pinned to
+        let db = &self.ctx.database;
+        let model = CourseRepository::get_by_slug(db, course).await?;
+        let graded = stages.iter().find(|s| s.slug == stage);
+        let settings = SettingsService::resolve(&self.ctx, &model, graded).await?;
+        let profile = settings.resources;
 
         // Configuration values for the PipelineRun
-        let git_endpoint = &self.ctx.config.git_server_endpoint;
-        let registry = url::hostname(&self.ctx.config.docker_registry_endpoint)?;
-        let org = &self.ctx.config.namespace;
+        let endpoints = &self.ctx.endpoints;
+        let config = &self.ctx.config;
+        let org = repo_org(config, repo);
+
+        // Repositories are cloned with their own read-only deploy key when
+        // the git server is reachable over SSH
+        let (repo_url, key) = match endpoints.ssh_clone_url(org, repo) {
+            Some(url) => {
+                let key = RepoService::new(self.ctx.clone()).deploy_key(org, repo).await?;
+                (url, Some(deploy_key(&name, key, config)))
+            }
+            None => (endpoints.clone_url(org, repo), None),
+        };
+
+        // Courses offered in several languages are tested in the language the
+        // learner enrolled with, trials in the primary one
+        let language = match Uuid::parse_str(repo) {
+            Ok(id) => CourseRepository::get_user_course_by_id(db, &id).await?.language,
+            Err(_) => model.languages.first().cloned(),
+        };
 
-        // Construct the webhook URL for Tekton to send notifications
-        let webhook_endpoint = &self.ctx.config.webhook_endpoint;
-        let webhook_url = format!("{webhook_endpoint}/v1/webhooks/tekton");
+        // Images are pushed with the course's own robot account
+        let (project, credentials) =
+            RegistryService::pipeline_credentials(&self.ctx, course).await?;
 
-        // Generate HMAC signature for webhook authentication
+        // Generate HMAC signature for webhook authentication, bound to this
+        // run so its events cannot be replayed as those of another
         let auth_secret = &self.ctx.config.auth_secret;
-        let payload = format!("{}{}{}", repo, course, stage);
+        let nonce = crypto::random_hex(16)?;
+        let payload =
+            signing_payload(&name, &nonce, repo, course, stage, commit, content_hash, repo_commit);
         let secret = crypto::hmac_sha256_sign(&payload, auth_secret)?;
 
         // Define parameters for the PipelineRun
-        let params = vec![
-            ("REPO_URL", format!("{git_endpoint}/{org}/{repo}.git")),
-            ("COURSE_IMAGE", format!("{registry}/{org}/{repo}:latest")),
-            ("TESTER_IMAGE", format!("ghcr.io/stackclass/{course}-tester")),
-            ("TEST_IMAGE", format!("{registry}/{org}/{repo}-test:latest")),
+        let mut params = vec![
+            ("REPO_URL", repo_url),
+            ("COURSE_IMAGE", endpoints.image_ref(&project, repo, "latest")),
+            ("TESTER_IMAGE", settings.tester_image),
+            ("TEST_IMAGE", endpoints.image_ref(&project, &format!("{repo}-test"), "latest")),
             ("COMMAND", format!("/app/{course}-tester")),
-            ("TEST_CASES_JSON", cases),
-            ("WEBHOOK_URL", webhook_url),
+            ("WEBHOOK_URL", endpoints.webhook_url("tekton")),
             ("REPO", repo.to_string()),
             ("COURSE", course.to_string()),
             ("STAGE", stage.to_string()),
+            ("COMMIT", commit.to_string()),
+            ("CONTENT_HASH", content_hash.to_string()),
+            ("REPO_COMMIT", repo_commit.to_string()),
+            ("RESOURCE_PROFILE", profile.to_string()),
+            ("LANGUAGE", language.unwrap_or_default()),
+            ("NONCE", nonce),
             ("SECRET", secret),
         ];
+        params.extend(cases.params());
 
         // Render a PipelineRun resource with the given name, labels, and params
-        resource(&name, labels, params).map_err(ApiError::SerializationError)
+        let resources = config.pod_resources(profile);
+        let key_name = key.as_ref().and_then(|key| key.metadata.name.as_deref());
+        // Courses may run another pipeline, with a bigger workspace
+        let pipeline = model.pipeline();
+        let (config_map, key_name) = (cases.config_map(), key_name);
+        let run = resource(
+            &name,
+            labels,
+            params,
+            &pipeline,
+            &credentials,
+            resources,
+            config_map,
+            key_name,
+        )
+        .map_err(ApiError::SerializationError)?;
+
+        Ok((run, cases.into_config_map(), key))
+    }
+}
+
+/// Keeps the last [`MAX_LOG_BYTES`] of the tester output, where failures
+/// are reported, cut at a character boundary.
+fn log_tail(log: &str) -> &str {
+    let mut start = log.len().saturating_sub(MAX_LOG_BYTES);
+    while !log.is_char_boundary(start) {
+        start += 1;
     }
+    &log[start..]
+}
+
+/// Whether the error means the API server could not be reached, as opposed
+/// to a request it rejected.
+fn is_unavailable(err: &kube::Error) -> bool {
+    match err {
+        kube::Error::HyperError(_) | kube::Error::Service(_) => true,
+        kube::Error::Api(status) => matches!(status.code, 502..=504),
+        _ => false,
+    }
+}
+
+/// Organization of the given repository, trial repositories live in their
+/// own one.
+pub(crate) fn repo_org<'a>(config: &'a Config, repo: &str) -> &'a str {
+    match TrialService::parse_repo(repo) {
+        Some(_) => &config.trial_org,
+        None => &config.namespace,
+    }
+}
+
+/// Payload signed into the SECRET param and verified on the pipeline event.
+///
+/// The name of the run and the random NONCE param tie the signature to a
+/// single run, so the event of an older run of the same stage cannot be
+/// replayed under the name of a newer one. Each field is prefixed with its
+/// length, so that no two sets of fields share a payload.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn signing_payload(
+    name: &str,
+    nonce: &str,
+    repo: &str,
+    course: &str,
+    stage: &str,
+    commit: &str,
+    content_hash: &str,
+    repo_commit: &str,
+) -> String {
+    [name, nonce, repo, course, stage, commit, content_hash, repo_commit]
+        .iter()
+        .map(|field| format!("{}:{field}", field.len()))
+        .collect()
+}
+
+/// Payload the SECRET param was signed with before runs were bound to their
+/// name, only accepted until `legacy_signatures_until`.
+pub(crate) fn legacy_signing_payload(
+    repo: &str,
+    course: &str,
+    stage: &str,
+    commit: &str,
+    content_hash: &str,
+    repo_commit: &str,
+) -> String {
+    format!("{repo}{course}{stage}{commit}{content_hash}{repo_commit}")
 }
 
 /// Builds a JSON string representing test cases from a list of slugs.
-fn build_test_cases_json(slugs: &[&str]) -> String {
+pub(crate) fn build_test_cases_json(slugs: &[&str]) -> String {
     let mut test_cases = Vec::new();
     for (index, slug) in slugs.iter().enumerate() {
         test_cases.push(json!({
@@ -130,15 +913,180 @@ fn build_test_cases_json(slugs: &[&str]) -> String {
     serde_json::to_string(&test_cases).unwrap()
 }
 
+/// How the test cases are passed to a PipelineRun.
+#[derive(Debug)]
+enum TestCases {
+    /// In the TEST_CASES_JSON param, as pipelines have always received them
+    Param(String),
+
+    /// In a ConfigMap bound to the [`TEST_CASES_WORKSPACE`] workspace and
+    /// named in the TEST_CASES_CONFIGMAP param, TEST_CASES_JSON is empty
+    ConfigMap(Box<ConfigMap>),
+}
+
+impl TestCases {
+    /// Params of the PipelineRun pointing the pipeline at the test cases.
+    fn params(&self) -> Vec<(&'static str, String)> {
+        match self {
+            TestCases::Param(cases) => vec![("TEST_CASES_JSON", cases.clone())],
+            TestCases::ConfigMap(config_map) => vec![
+                ("TEST_CASES_JSON", String::new()),
+                ("TEST_CASES_CONFIGMAP", config_map.metadata.name.clone().unwrap_or_default()),
+            ],
+        }
+    }
+
+    fn config_map(&self) -> Option<&str> {
+        match self {
+            TestCases::Param(_) => None,
+            TestCases::ConfigMap(config_map) => config_map.metadata.name.as_deref(),
+        }
+    }
+
+    fn into_config_map(self) -> Option<ConfigMap> {
+        match self {
+            TestCases::Param(_) => None,
+            TestCases::ConfigMap(config_map) => Some(*config_map),
+        }
+    }
+}
+
+/// Decides how the test cases of the PipelineRun with the given name are
+/// passed, in a param as long as they fit within the configured limit.
+fn test_cases(name: &str, cases: String, config: &Config) -> TestCases {
+    if cases.len() <= config.pipeline_test_cases_limit {
+        return TestCases::Param(cases);
+    }
+
+    TestCases::ConfigMap(Box::new(ConfigMap {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            labels: Some(BTreeMap::from([(TEST_CASES_LABEL.to_string(), "true".to_string())])),
+            ..Default::default()
+        },
+        data: Some(BTreeMap::from([(TEST_CASES_FILE.to_string(), cases)])),
+        ..Default::default()
+    }))
+}
+
+/// Secret holding the deploy key the PipelineRun with the given name clones
+/// its repository with, laid out as the SSH directory of the clone task.
+fn deploy_key(name: &str, private_key: String, config: &Config) -> Secret {
+    let mut files = BTreeMap::from([("id_ed25519".to_string(), private_key)]);
+    match &config.git_server_ssh_known_hosts {
+        Some(known_hosts) => files.insert("known_hosts".to_string(), known_hosts.clone()),
+        None => files.insert("config".to_string(), "StrictHostKeyChecking accept-new\n".into()),
+    };
+
+    Secret {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            labels: Some(BTreeMap::from([(DEPLOY_KEY_LABEL.to_string(), "true".to_string())])),
+            ..Default::default()
+        },
+        type_: Some("Opaque".to_string()),
+        string_data: Some(files),
+        ..Default::default()
+    }
+}
+
+/// Whether an object created for a PipelineRun may have outlived it: owned
+/// ones are collected by Kubernetes, young ones may still be waiting for
+/// their run to be created.
+fn may_be_orphaned(metadata: &ObjectMeta, now: i64) -> bool {
+    let owned = metadata.owner_references.as_ref().is_some_and(|o| !o.is_empty());
+    let created = metadata.creation_timestamp.as_ref().map(|t| t.0.as_second());
+    !owned && created.is_some_and(|created| now - created >= TEST_CASES_ORPHAN_AGE)
+}
+
+/// Whether a PipelineRun is still pending or running, i.e. has no completion
+/// time yet.
+fn is_active(run: &DynamicObject) -> bool {
+    run.data["status"]["completionTime"].is_null()
+}
+
+/// Whether a PipelineRun is past its lifetime: finished runs once they
+/// finished `retention` seconds ago, others once they were created
+/// `max_age` seconds ago.
+fn is_expired(run: &DynamicObject, now: i64, retention: i64, max_age: i64) -> bool {
+    let finished = run.data["status"]["completionTime"].as_str();
+    match finished.map(DateTime::parse_from_rfc3339) {
+        Some(Ok(finished)) => now - finished.timestamp() >= retention,
+        Some(Err(_)) => false,
+        None => {
+            let created = run.metadata.creation_timestamp.as_ref().map(|t| t.0.as_second());
+            created.is_some_and(|created| now - created >= max_age)
+        }
+    }
+}
+
+/// Status and reason of the `Succeeded` condition of a Tekton resource,
+/// empty while it has none.
+fn succeeded(data: &Value) -> (&str, &str) {
+    let conditions = data["status"]["conditions"].as_array();
+    let condition = conditions.and_then(|c| c.iter().find(|c| c["type"] == "Succeeded"));
+    condition.map_or(("", ""), |c| {
+        (c["status"].as_str().unwrap_or_default(), c["reason"].as_str().unwrap_or_default())
+    })
+}
+
+/// Value of a param of a PipelineRun, empty if it was not passed.
+fn param<'a>(run: &'a DynamicObject, name: &str) -> &'a str {
+    let params = run.data["spec"]["params"].as_array();
+    let param = params.and_then(|params| params.iter().find(|p| p["name"] == name));
+    param.and_then(|p| p["value"].as_str()).unwrap_or_default()
+}
+
+/// Describes a PipelineRun by its name, stage and the conditions Tekton
+/// reported on it.
+fn run_response(run: DynamicObject) -> PipelineRunResponse {
+    let conditions = run.data["status"]["conditions"].as_array().cloned().unwrap_or_default();
+    let conditions = conditions.into_iter().filter_map(|c| serde_json::from_value(c).ok());
+
+    let metadata = &run.metadata;
+    let created_at = metadata.creation_timestamp.as_ref();
+
+    PipelineRunResponse {
+        name: metadata.name.clone().unwrap_or_default(),
+        stage_slug: metadata.labels.as_ref().and_then(|labels| labels.get(STAGE_LABEL)).cloned(),
+        created_at: created_at.and_then(|t| DateTime::from_timestamp(t.0.as_second(), 0)),
+        active: is_active(&run),
+        conditions: conditions.collect(),
+    }
+}
+
+/// Reference making the created PipelineRun the owner of another object, so
+/// that it is garbage collected with the run.
+fn owner_reference(run: &DynamicObject) -> Option<OwnerReference> {
+    let types = run.types.as_ref()?;
+    Some(OwnerReference {
+        api_version: types.api_version.clone(),
+        kind: types.kind.clone(),
+        name: run.metadata.name.clone()?,
+        uid: run.metadata.uid.clone()?,
+        ..Default::default()
+    })
+}
+
 /// Creates a new DynamicObject representing a Tekton PipelineRun resource.
-fn resource<T>(name: &str, labels: T, params: T) -> Result<DynamicObject, JsonError>
+#[allow(clippy::too_many_arguments)]
+fn resource<T>(
+    name: &str,
+    labels: T,
+    params: T,
+    pipeline: &PipelineSettings,
+    credentials: &str,
+    resources: &PodResources,
+    test_cases: Option<&str>,
+    deploy_key: Option<&str>,
+) -> Result<DynamicObject, JsonError>
 where
     T: IntoIterator<Item = (&'static str, String)>,
 {
     let labels: Value = labels.into_iter().collect();
     let params: Value = params.into_iter().map(|(k, v)| json!({"name": k, "value": v})).collect();
 
-    let resource = json!({
+    let mut resource = json!({
       "apiVersion": "tekton.dev/v1",
       "kind": "PipelineRun",
       "metadata": {
@@ -147,13 +1095,19 @@ where
       },
       "spec": {
         "pipelineRef": {
-          "name": "course-test-pipeline"
+          "name": pipeline.pipeline_ref()
         },
         "podTemplate": {
             "securityContext": {
               "fsGroup": 65532
             }
         },
+        "taskRunSpecs": [
+          {
+            "pipelineTaskName": TEST_TASK,
+            "computeResources": resources.to_json()
+          }
+        ],
         "params": params,
         "workspaces": [
           {
@@ -165,7 +1119,7 @@ where
                 ],
                 "resources": {
                   "requests": {
-                    "storage": "5Gi"
+                    "storage": pipeline.workspace_size()
                   }
                 }
               }
@@ -174,17 +1128,44 @@ where
           {
             "name": "docker-credentials",
             "secret": {
-              "secretName": "docker-credentials"
+              "secretName": credentials
             }
           }
         ]
       }
     });
 
+    if let Some(timeout) = &pipeline.timeout {
+        resource["spec"]["timeouts"] = json!({ "pipeline": timeout });
+    }
+
+    if let Some(account) = &pipeline.service_account {
+        resource["spec"]["taskRunTemplate"] = json!({ "serviceAccountName": account });
+    }
+
+    if let Some(config_map) = test_cases &&
+        let Some(workspaces) = resource["spec"]["workspaces"].as_array_mut()
+    {
+        workspaces.push(json!({
+            "name": TEST_CASES_WORKSPACE,
+            "configMap": { "name": config_map }
+        }));
+    }
+
+    if let Some(secret) = deploy_key &&
+        let Some(workspaces) = resource["spec"]["workspaces"].as_array_mut()
+    {
+        workspaces.push(json!({
+            "name": DEPLOY_KEY_WORKSPACE,
+            "secret": { "secretName": secret }
+        }));
+    }
+
     serde_json::from_value(resource)
 }
 
-// RAII guard to ensure PipelineRun deletion
+// RAII guard to ensure PipelineRun deletion, its test cases and deploy key
+// included
 pub struct PipelineCleanupGuard<'a> {
     name: &'a str,
     ctx: Arc<Context>,
@@ -210,3 +1191,285 @@ impl<'a> Drop for PipelineCleanupGuard<'a> {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use clap::Parser;
+
+    use super::*;
+    use crate::{
+        config::Config,
+        model::StageModel,
+        schema::{ResourceProfile, Stage},
+    };
+
+    fn config() -> Config {
+        Config::parse_from([
+            "stackclass-server",
+            "--cache-dir=/tmp/stackclass",
+            "--database-url=postgres://localhost/stackclass",
+            "--git-proxy-endpoint=http://git.local",
+            "--git-server-endpoint=http://git.local",
+            "--git-server-username=admin",
+            "--git-server-password=admin",
+            "--webhook-endpoint=http://api.local",
+            "--namespace=stackclass",
+            "--docker-registry-endpoint=http://docker.local",
+            "--docker-registry-username=admin",
+            "--docker-registry-password=admin",
+            "--auth-secret=secret",
+        ])
+    }
+
+    fn stage(resources: Option<&str>) -> StageModel {
+        let mut yaml = "slug: s1\nname: S1\ndifficulty: easy\ndescription: A stage\n".to_string();
+        if let Some(resources) = resources {
+            yaml.push_str(&format!("resources: {resources}\n"));
+        }
+        Stage::from_str(&yaml).unwrap().into()
+    }
+
+    /// Renders the PipelineRun of the stage, with only the profile param.
+    fn render(config: &Config, stage: &StageModel) -> Value {
+        let profile = stage.settings().resources.unwrap_or_default();
+        let params = vec![("RESOURCE_PROFILE", profile.to_string())];
+        let resources = config.pod_resources(profile);
+        let run = resource(
+            "run",
+            vec![],
+            params,
+            &PipelineSettings::default(),
+            "credentials",
+            resources,
+            None,
+            None,
+        );
+        serde_json::to_value(run.unwrap()).unwrap()
+    }
+
+    fn expected(profile: &str, cpu: [&str; 2], memory: [&str; 2]) -> Value {
+        json!({
+            "taskRunSpecs": [{
+                "pipelineTaskName": "test",
+                "computeResources": {
+                    "requests": { "cpu": cpu[0], "memory": memory[0] },
+                    "limits": { "cpu": cpu[1], "memory": memory[1] },
+                }
+            }],
+            "params": [{ "name": "RESOURCE_PROFILE", "value": profile }],
+        })
+    }
+
+    fn rendered(run: &Value) -> Value {
+        json!({ "taskRunSpecs": run["spec"]["taskRunSpecs"], "params": run["spec"]["params"] })
+    }
+
+    #[test]
+    fn test_resources_of_each_profile() {
+        let config = config();
+        let cases = [
+            ("small", ["250m", "500m"], ["256Mi", "512Mi"]),
+            ("medium", ["500m", "1"], ["512Mi", "1Gi"]),
+            ("large", ["1", "2"], ["1Gi", "2Gi"]),
+        ];
+
+        for (profile, cpu, memory) in cases {
+            let run = render(&config, &stage(Some(profile)));
+            assert_eq!(rendered(&run), expected(profile, cpu, memory));
+        }
+    }
+
+    #[test]
+    fn test_default_resources() {
+        let mut config = config();
+        config.pipeline_resources_small = "cpu=100m/200m,memory=64Mi/128Mi".parse().unwrap();
+        let small = expected("small", ["100m", "200m"], ["64Mi", "128Mi"]);
+
+        // Stages without a profile, or with one that is no longer known, get
+        // the small profile
+        assert_eq!(rendered(&render(&config, &stage(None))), small);
+
+        let mut unknown = stage(None);
+        unknown.resources = Some("huge".to_string());
+        assert_eq!(rendered(&render(&config, &unknown)), small);
+    }
+
+    #[test]
+    fn test_large_test_cases_use_config_map() {
+        let mut config = config();
+        config.pipeline_test_cases_limit = 100;
+        let slugs: Vec<String> = (0..70).map(|i| format!("stage-{i}")).collect();
+        let slugs: Vec<&str> = slugs.iter().map(String::as_str).collect();
+
+        // Small payloads keep using the param
+        let small = build_test_cases_json(&slugs[..1]);
+        assert!(small.len() <= 100);
+        let cases = test_cases("run", small.clone(), &config);
+        assert_eq!(cases.params(), vec![("TEST_CASES_JSON", small)]);
+        assert!(cases.into_config_map().is_none());
+
+        // Large ones are moved into a ConfigMap named after the run
+        let large = build_test_cases_json(&slugs);
+        let cases = test_cases("run", large.clone(), &config);
+        assert_eq!(
+            cases.params(),
+            vec![("TEST_CASES_JSON", String::new()), ("TEST_CASES_CONFIGMAP", "run".to_string())]
+        );
+        let config_map = cases.into_config_map().unwrap();
+        assert_eq!(config_map.metadata.name.as_deref(), Some("run"));
+        assert_eq!(config_map.metadata.labels.unwrap()[TEST_CASES_LABEL], "true");
+        assert_eq!(config_map.data.unwrap()[TEST_CASES_FILE], large);
+    }
+
+    #[test]
+    fn test_config_map_is_owned_by_run() {
+        let config = config();
+        let resources = config.pod_resources(ResourceProfile::Small);
+        let mut run = resource(
+            "run",
+            vec![],
+            vec![],
+            &PipelineSettings::default(),
+            "credentials",
+            resources,
+            Some("run"),
+            None,
+        )
+        .unwrap();
+
+        // The workspace is bound to the ConfigMap
+        let spec = serde_json::to_value(&run).unwrap()["spec"].clone();
+        let workspace = spec["workspaces"].as_array().unwrap().last().unwrap().clone();
+        assert_eq!(workspace, json!({ "name": "test-cases", "configMap": { "name": "run" } }));
+
+        // Without a uid, the run has not been created yet and cannot own it
+        assert!(owner_reference(&run).is_none());
+
+        run.metadata.uid = Some("0198c6a4-uid".to_string());
+        let owner = serde_json::to_value(owner_reference(&run).unwrap()).unwrap();
+        assert_eq!(
+            owner,
+            json!({
+                "apiVersion": "tekton.dev/v1",
+                "kind": "PipelineRun",
+                "name": "run",
+                "uid": "0198c6a4-uid"
+            })
+        );
+
+        // Runs passing the test cases in a param bind no such workspace
+        let resources = config.pod_resources(ResourceProfile::Small);
+        let run = resource(
+            "run",
+            vec![],
+            vec![],
+            &PipelineSettings::default(),
+            "credentials",
+            resources,
+            None,
+            None,
+        )
+        .unwrap();
+        let spec = serde_json::to_value(&run).unwrap()["spec"].clone();
+        assert_eq!(spec["workspaces"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_deploy_key_workspace() {
+        let mut config = config();
+        let resources = config.pod_resources(ResourceProfile::Small);
+        let run = resource(
+            "run",
+            vec![],
+            vec![],
+            &PipelineSettings::default(),
+            "credentials",
+            resources,
+            None,
+            Some("run"),
+        );
+
+        // The SSH directory of the clone task is bound to the Secret
+        let spec = serde_json::to_value(run.unwrap()).unwrap()["spec"].clone();
+        let workspace = spec["workspaces"].as_array().unwrap().last().unwrap().clone();
+        assert_eq!(
+            workspace,
+            json!({ "name": "ssh-directory", "secret": { "secretName": "run" } })
+        );
+
+        // Unknown hosts are accepted unless the host keys are configured
+        let key = deploy_key("run", "private".to_string(), &config);
+        assert_eq!(key.metadata.labels.unwrap()[DEPLOY_KEY_LABEL], "true");
+        let files = key.string_data.unwrap();
+        assert_eq!(files["id_ed25519"], "private");
+        assert_eq!(files["config"], "StrictHostKeyChecking accept-new\n");
+        assert!(!files.contains_key("known_hosts"));
+
+        config.git_server_ssh_known_hosts = Some("git.local ssh-ed25519 AAAA".to_string());
+        let files = deploy_key("run", "private".to_string(), &config).string_data.unwrap();
+        assert_eq!(files["known_hosts"], "git.local ssh-ed25519 AAAA");
+        assert!(!files.contains_key("config"));
+    }
+
+    #[test]
+    fn test_log_tail() {
+        assert_eq!(log_tail("short"), "short");
+
+        let log = format!("{}é{}", "a".repeat(10), "b".repeat(MAX_LOG_BYTES - 1));
+        let tail = log_tail(&log);
+        assert_eq!(tail, "b".repeat(MAX_LOG_BYTES - 1));
+    }
+
+    #[test]
+    fn test_signing_payload() {
+        let payload = |repo, course| signing_payload("run", "n", repo, course, "s", "", "", "");
+        assert_eq!(payload("ab", "c"), "3:run1:n2:ab1:c1:s0:0:0:");
+        assert_ne!(payload("ab", "c"), payload("a", "bc"));
+    }
+
+    #[test]
+    fn test_is_active() {
+        let run = |status: Value| {
+            let mut run = DynamicObject::new(
+                "run",
+                &ApiResource::from_gvk(&GroupVersionKind::gvk("tekton.dev", "v1", "PipelineRun")),
+            );
+            run.data = json!({ "status": status });
+            run
+        };
+
+        assert!(is_active(&run(Value::Null)));
+        assert!(is_active(&run(json!({ "startTime": "2025-01-01T00:00:00Z" }))));
+        assert!(!is_active(&run(json!({ "completionTime": "2025-01-01T00:05:00Z" }))));
+    }
+
+    #[test]
+    fn test_is_expired() {
+        let run = |created: &str, status: Value| {
+            let mut run: DynamicObject = serde_json::from_value(json!({
+                "apiVersion": "tekton.dev/v1",
+                "kind": "PipelineRun",
+                "metadata": { "name": "run", "creationTimestamp": created },
+            }))
+            .unwrap();
+            run.data = json!({ "status": status });
+            run
+        };
+        let now = DateTime::parse_from_rfc3339("2025-01-02T00:00:00Z").unwrap().timestamp();
+        let expired = |run: &DynamicObject| is_expired(run, now, 3600, 86400);
+
+        // Finished runs are kept for the retention window
+        let finished = |at: &str| run("2025-01-01T00:00:00Z", json!({ "completionTime": at }));
+        assert!(expired(&finished("2025-01-01T22:59:59Z")));
+        assert!(expired(&finished("2025-01-01T23:00:00Z")));
+        assert!(!expired(&finished("2025-01-01T23:30:00Z")));
+        assert!(!expired(&finished("yesterday")));
+
+        // Others until the hard timeout, even while they report progress
+        assert!(expired(&run("2025-01-01T00:00:00Z", Value::Null)));
+        assert!(expired(&run("2024-12-31T00:00:00Z", json!({ "startTime": "2025-01-01" }))));
+        assert!(!expired(&run("2025-01-01T00:00:01Z", Value::Null)));
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use futures::{StreamExt, stream};
+use serde_json::json;
+
+use crate::{
+    context::Context,
+    errors::{ApiError, Result},
+    model::{AuditLogModel, CourseModel, PooledRepoModel},
+    repository::{AuditRepository, CourseRepository, RepoPoolRepository},
+    response::PreprovisionResponse,
+    service::RepoService,
+};
+
+/// Largest number of spare repositories a course may hold.
+const MAX_POOL_SIZE: u32 = 1000;
+
+/// Number of repositories generated at the same time.
+const PROVISION_CONCURRENCY: usize = 4;
+
+/// Number of stale repositories deleted per batch.
+const REAP_BATCH_SIZE: i64 = 100;
+
+/// Service pre-provisioning learner repositories for announced launches.
+///
+/// Spare repositories are generated from the course template ahead of time,
+/// named after an id reserved in the pool. Enrollments claim one and rename
+/// it after the user course, see `RepoService::generate`, and generate their
+/// repository live only once the pool is exhausted. Repositories generated
+/// from an outdated template or kept longer than `repo_pool_ttl` are deleted
+/// by the `reap-repo-pool` job.
+pub struct RepoPoolService;
+
+impl RepoPoolService {
+    /// Generate spare repositories until the pool of the course holds the
+    /// requested number.
+    pub async fn preprovision(
+        ctx: Arc<Context>,
+        slug: &str,
+        count: u32,
+        actor: &str,
+    ) -> Result<PreprovisionResponse> {
+        if count > MAX_POOL_SIZE {
+            return Err(ApiError::BadRequest(format!(
+                "At most {MAX_POOL_SIZE} repositories can be pooled per course"
+            )));
+        }
+
+        let db = &ctx.database;
+        let course = CourseRepository::get_by_slug(db, slug).await?;
+        let available = RepoPoolRepository::count_available(db, &course.id, Self::cutoff(&ctx));
+        let available = available.await? as usize;
+        let missing = (count as usize).saturating_sub(available);
+
+        let results: Vec<Result<()>> = stream::iter(0..missing)
+            .map(|_| Self::provision(ctx.clone(), &course))
+            .buffer_unordered(PROVISION_CONCURRENCY)
+            .collect()
+            .await;
+        results.into_iter().collect::<Result<()>>()?;
+
+        let target = format!("courses/{slug}/repo-pool");
+        let details = json!({ "count": count, "created": missing });
+        let log =
+            AuditLogModel::new_at(actor, "preprovision_repos", &target, details, ctx.clock.now());
+        AuditRepository::create(db, &log).await?;
+
+        Ok(PreprovisionResponse { created: missing, available: available + missing })
+    }
+
+    /// Delete the unclaimed repositories which are past the TTL or were
+    /// generated from an outdated template, returning how many were deleted.
+    pub async fn reap_stale(ctx: Arc<Context>) -> Result<usize> {
+        let (db, org) = (&ctx.database, &ctx.config.namespace);
+        let repo = RepoService::new(ctx.clone());
+        let cutoff = Self::cutoff(&ctx);
+
+        let mut reaped = 0;
+        loop {
+            let stale = RepoPoolRepository::find_stale(db, cutoff, REAP_BATCH_SIZE).await?;
+            let done = (stale.len() as i64) < REAP_BATCH_SIZE;
+
+            // The entry stays locked until its repository is gone, so that
+            // it can not be claimed in the meantime
+            for pooled in stale {
+                let mut tx = db.pool().begin().await?;
+                if RepoPoolRepository::remove(&mut tx, &pooled.id).await? {
+                    repo.delete(org, &pooled.repo()).await?;
+                    reaped += 1;
+                }
+                tx.commit().await?;
+            }
+
+            if done {
+                break;
+            }
+        }
+
+        RepoPoolRepository::purge_claimed(db, cutoff).await?;
+        Ok(reaped)
+    }
+
+    /// Creation time before which pooled repositories are expired.
+    pub fn cutoff(ctx: &Context) -> DateTime<Utc> {
+        ctx.clock.now() - Duration::seconds(ctx.config.repo_pool_ttl)
+    }
+
+    /// Generate a spare repository of the course into the pool.
+    async fn provision(ctx: Arc<Context>, course: &CourseModel) -> Result<()> {
+        let pooled = PooledRepoModel::new_at(course.id, &course.commit_sha, ctx.clock.now());
+        let repo = RepoService::new(ctx.clone());
+        repo.generate_in(&ctx.config.namespace, &course.slug, &pooled.repo()).await?;
+        RepoPoolRepository::create(&ctx.database, &pooled).await?;
+
+        Ok(())
+    }
+}
@@ -12,10 +12,47 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use harbor_client::{ClientError, types::CreateProjectRequest};
-use tracing::info;
+use std::collections::BTreeMap;
 
-use crate::{context::Context, errors::Result};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Duration};
+use harbor_client::{
+    ClientError,
+    types::{
+        Access, ArtifactQuery, CreateProjectRequest, CreateRobotRequest,
+        CreateWebhookPolicyRequest, Pagination, RobotCreated, UpdateProjectRequest,
+    },
+};
+use k8s_openapi::{ByteString, api::core::v1::Secret};
+use kube::{
+    Api,
+    api::{DeleteParams, ObjectMeta, Patch, PatchParams},
+};
+use serde_json::json;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    context::Context,
+    errors::{ApiError, Result},
+    model::{AuditLogModel, CourseModel, RegistryCredentialModel},
+    repository::{AuditRepository, CourseRepository, RegistryRepository, StageRepository},
+    request::event::HarborEvent,
+    response::{GarbageCollectionStatusResponse, RegistrySummaryResponse},
+    utils::crypto,
+};
+
+/// Purpose the robot account secrets are encrypted for
+const CREDENTIALS_PURPOSE: &str = "registry-credentials";
+
+/// Secret shared by the pipelines of courses without their own robot account
+const SHARED_SECRET: &str = "docker-credentials";
+
+/// Name of the webhook policy notifying us of pushed images
+const WEBHOOK_POLICY: &str = "stackclass";
+
+/// Type of the events of pushed images
+const PUSH_ARTIFACT: &str = "PUSH_ARTIFACT";
 
 /// Service for container registry operations
 pub struct RegistryService;
@@ -25,15 +62,428 @@ impl RegistryService {
     /// Checks if the project exists, and creates it if not
     pub async fn ensure_project(ctx: &Context, name: &str) -> Result<()> {
         match ctx.harbor.head_project(name).await {
-            Ok(_) => Ok(()), // Project exists, nothing to do
+            Ok(_) => {} // Project exists, nothing to do
             Err(ClientError::NotFound) => {
                 let request = CreateProjectRequest::new(name).with_public(true);
                 ctx.harbor.create_project(request).await?;
                 info!("Project '{}' created successfully in Harbor registry", name);
+            }
+            Err(e) => return Err(e.into()), // Propagate other errors
+        }
+
+        match ctx.config.registry_webhooks {
+            true => Self::setup_webhook(ctx, name).await,
+            false => Ok(()),
+        }
+    }
+
+    /// Setup the webhook policy of a project sending us its pushed images,
+    /// replacing one delivering elsewhere, e.g. after the endpoint moved.
+    async fn setup_webhook(ctx: &Context, project: &str) -> Result<()> {
+        let url = ctx.endpoints.webhook_url("harbor");
+        let policies = ctx.harbor.list_webhook_policies(project).await?;
+        let ours = policies.iter().find(|policy| policy.name == WEBHOOK_POLICY);
+        if let Some(policy) = ours {
+            let address = policy.targets.first().map(|target| target.address.as_str());
+            if address == Some(url.as_str()) && policy.event_types == [PUSH_ARTIFACT] {
+                return Ok(());
+            }
+            ctx.harbor.delete_webhook_policy(project, policy.id).await?;
+        }
+
+        // Harbor sends the header as is, authenticating it as the admin
+        let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret)?;
+        let auth_header = format!("Basic {}", STANDARD.encode(format!("admin:{password}")));
+        let request = CreateWebhookPolicyRequest::new(WEBHOOK_POLICY, url, &[PUSH_ARTIFACT])
+            .with_description("Notifies StackClass of pushed course images")
+            .with_auth_header(auth_header);
+        ctx.harbor.create_webhook_policy(project, request).await?;
+
+        debug!("Set up the webhook policy of the project {project}.");
+        Ok(())
+    }
+
+    /// Record when the course image of a user course was pushed, on the
+    /// stage it is at. Test images, and images of trials or of unknown
+    /// repositories are skipped.
+    pub async fn record_push(ctx: &Context, event: &HarborEvent) -> Result<()> {
+        if event.kind != PUSH_ARTIFACT {
+            return Ok(());
+        }
+
+        let repository = &event.event_data.repository;
+        let Ok(user_course_id) = Uuid::parse_str(&repository.name) else {
+            debug!("Skipped pushed image {}", repository.repo_full_name);
+            return Ok(());
+        };
+
+        let pushed_at = DateTime::from_timestamp(event.occur_at, 0).unwrap_or(ctx.clock.now());
+        if !StageRepository::record_image_push(&ctx.database, &user_course_id, pushed_at).await? {
+            debug!("No user stage for pushed image {}", repository.repo_full_name);
+        }
+
+        Ok(())
+    }
+
+    /// Name of the Harbor project holding the images of a course.
+    pub fn course_project(ctx: &Context, slug: &str) -> String {
+        format!("{}-{slug}", ctx.config.namespace)
+    }
+
+    /// Create a robot account with the given access to a project.
+    pub async fn create_robot(
+        ctx: &Context,
+        project: &str,
+        name: &str,
+        permissions: Vec<Access>,
+    ) -> Result<RobotCreated> {
+        let request = CreateRobotRequest::project(project, name, permissions)
+            .with_description("Pushes the images built by the course pipelines");
+        let robot = ctx.harbor.create_robot(request).await?;
+        info!("Robot account '{}' created in project '{}'", robot.name, project);
 
+        Ok(robot)
+    }
+
+    /// Delete a robot account, treating an already deleted one as success.
+    pub async fn delete_robot(ctx: &Context, project: &str, robot_id: i64) -> Result<()> {
+        match ctx.harbor.delete_robot(robot_id).await {
+            Ok(()) | Err(ClientError::NotFound) => {
+                info!("Robot account {} deleted from project '{}'", robot_id, project);
                 Ok(())
             }
-            Err(e) => Err(e.into()), // Propagate other errors
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Provision the registry credentials of a course, unless it already has
+    /// some, in which case their Kubernetes Secret is restored.
+    pub async fn provision(
+        ctx: &Context,
+        slug: &str,
+        actor: &str,
+    ) -> Result<RegistryCredentialModel> {
+        match RegistryRepository::find_active(&ctx.database, slug).await? {
+            Some(credential) => {
+                let secret = crypto::decrypt(
+                    &credential.encrypted_secret,
+                    CREDENTIALS_PURPOSE,
+                    &ctx.config.auth_secret,
+                )?;
+                let name = &credential.secret_name;
+                Self::apply_secret(ctx, name, slug, &credential.robot_name, &secret).await?;
+                Ok(credential)
+            }
+            None => Self::rotate(ctx, slug, actor).await,
+        }
+    }
+
+    /// Replace the registry credentials of a course with a new robot account.
+    ///
+    /// The new robot account gets its own Kubernetes Secret, and only new
+    /// pipelines are pointed at it. The previous robot account and Secret are
+    /// left untouched until the grace period ends, so that running pipelines
+    /// finish with the credentials they started with.
+    pub async fn rotate(ctx: &Context, slug: &str, actor: &str) -> Result<RegistryCredentialModel> {
+        let course = CourseRepository::get_by_slug(&ctx.database, slug).await?;
+        let project = Self::course_project(ctx, slug);
+        Self::ensure_project(ctx, &project).await?;
+
+        let suffix = Uuid::now_v7().simple().to_string()[24..].to_string();
+        let access = vec![Access::new("repository", "push"), Access::new("repository", "pull")];
+        let robot =
+            Self::create_robot(ctx, &project, &format!("pipeline-{suffix}"), access).await?;
+
+        let secret_name = format!("{slug}-registry-{suffix}");
+        let result = async {
+            Self::apply_secret(ctx, &secret_name, slug, &robot.name, &robot.secret).await?;
+
+            let secret =
+                crypto::encrypt(&robot.secret, CREDENTIALS_PURPOSE, &ctx.config.auth_secret)?;
+            let credential = RegistryCredentialModel::new_at(
+                course.id,
+                &project,
+                robot.id,
+                &robot.name,
+                &secret_name,
+                &secret,
+                ctx.clock.now(),
+            );
+
+            let mut tx = ctx.database.pool().begin().await?;
+            let retired = RegistryRepository::activate(&mut tx, &credential).await?;
+            tx.commit().await?;
+
+            Ok::<_, ApiError>((credential, retired))
+        }
+        .await;
+
+        // Leave nothing behind for credentials that never became active
+        let (credential, retired) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                if let Err(e) = Self::delete_secret(ctx, &secret_name).await {
+                    warn!("Failed to clean up secret '{secret_name}': {e}");
+                }
+                if let Err(e) = Self::delete_robot(ctx, &project, robot.id).await {
+                    warn!("Failed to clean up robot account '{}': {e}", robot.name);
+                }
+                return Err(e);
+            }
+        };
+
+        let retired = retired.map(|retired| retired.secret_name);
+        let target = format!("courses/{slug}/registry-credentials");
+        let details = json!({ "secret_name": secret_name, "retired": retired });
+        let log = AuditLogModel::new_at(
+            actor,
+            "rotate_registry_credentials",
+            &target,
+            details,
+            ctx.clock.now(),
+        );
+        AuditRepository::create(&ctx.database, &log).await?;
+
+        Ok(credential)
+    }
+
+    /// Delete the robot accounts and Secrets retired longer than the grace
+    /// period ago, returning how many were deleted.
+    pub async fn remove_retired(ctx: &Context) -> Result<usize> {
+        let grace = Duration::seconds(ctx.config.registry_credentials_grace);
+        let retired =
+            RegistryRepository::find_retired_before(&ctx.database, ctx.clock.now() - grace);
+
+        let mut removed = 0;
+        for credential in retired.await? {
+            Self::delete_robot(ctx, &credential.project, credential.robot_id).await?;
+            Self::delete_secret(ctx, &credential.secret_name).await?;
+            RegistryRepository::delete(&ctx.database, &credential.id).await?;
+            removed += 1;
+        }
+
+        Ok(removed)
+    }
+
+    /// Delete the robot accounts and Secrets of a course, active or retired,
+    /// and then its Harbor project, ahead of the deletion of the course. The
+    /// credentials are forgotten one by one as their robot account and Secret
+    /// are gone, so a failed teardown can be retried.
+    pub async fn teardown(ctx: &Context, course: &CourseModel) -> Result<()> {
+        for credential in RegistryRepository::find_by_course(&ctx.database, &course.id).await? {
+            Self::delete_robot(ctx, &credential.project, credential.robot_id).await?;
+            Self::delete_secret(ctx, &credential.secret_name).await?;
+            RegistryRepository::delete(&ctx.database, &credential.id).await?;
         }
+
+        // Courses which never had a robot account have no project either
+        let project = Self::course_project(ctx, &course.slug);
+        match ctx.harbor.delete_project(&project).await {
+            Ok(()) => info!("Project '{project}' deleted from Harbor registry"),
+            Err(ClientError::NotFound) => {}
+            Err(ClientError::PreconditionFailed(message)) => {
+                return Err(ApiError::Conflict(format!(
+                    "Failed to delete project {project}, it still holds images: {message}"
+                )));
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        Ok(())
+    }
+
+    /// Summarize the Harbor project of a course: its repositories and the
+    /// usage of its quota.
+    pub async fn summary(ctx: &Context, slug: &str) -> Result<RegistrySummaryResponse> {
+        let project = Self::own_project(ctx, slug).await?;
+        let summary = ctx.harbor.get_project_summary(&project).await?;
+        Ok(RegistrySummaryResponse::new(project, summary))
+    }
+
+    /// Change the storage quota of the Harbor project of a course, in bytes
+    /// or -1 for unlimited. A quota below the current usage is a conflict.
+    pub async fn set_quota(
+        ctx: &Context,
+        slug: &str,
+        storage_limit: i64,
+        actor: &str,
+    ) -> Result<RegistrySummaryResponse> {
+        if storage_limit == 0 || storage_limit < -1 {
+            let message = "storage_limit must be positive, or -1 for unlimited";
+            return Err(ApiError::BadRequest(message.into()));
+        }
+
+        let project = Self::own_project(ctx, slug).await?;
+        let request = UpdateProjectRequest::default().with_storage_limit(storage_limit);
+        match ctx.harbor.update_project(&project, request).await {
+            Ok(()) => info!("Storage quota of project '{project}' set to {storage_limit}"),
+            Err(ClientError::Conflict(message)) => return Err(ApiError::Conflict(message)),
+            Err(e) => return Err(e.into()),
+        }
+
+        let target = format!("courses/{slug}/registry");
+        let details = json!({ "project": project, "storage_limit": storage_limit });
+        let log =
+            AuditLogModel::new_at(actor, "set_registry_quota", &target, details, ctx.clock.now());
+        AuditRepository::create(&ctx.database, &log).await?;
+
+        Self::summary(ctx, slug).await
+    }
+
+    /// Trigger a garbage collection of the registry, reclaiming the blobs of
+    /// the deleted artifacts, and return the ID of its job.
+    pub async fn collect_garbage(ctx: &Context, actor: &str) -> Result<i64> {
+        let id = match ctx.harbor.trigger_garbage_collection().await {
+            Ok(id) => id,
+            Err(ClientError::Conflict(message)) => return Err(ApiError::Conflict(message)),
+            Err(e) => return Err(e.into()),
+        };
+        info!("Garbage collection {id} of the registry triggered");
+
+        let details = json!({ "id": id });
+        let log = AuditLogModel::new_at(
+            actor,
+            "trigger_registry_gc",
+            "registry/gc",
+            details,
+            ctx.clock.now(),
+        );
+        AuditRepository::create(&ctx.database, &log).await?;
+
+        Ok(id)
+    }
+
+    /// Status of a garbage collection job of the registry.
+    pub async fn garbage_collection(
+        ctx: &Context,
+        id: i64,
+    ) -> Result<GarbageCollectionStatusResponse> {
+        match ctx.harbor.get_gc_status(id).await {
+            Ok(job) => Ok(job.into()),
+            Err(ClientError::NotFound) => Err(ApiError::NotFound),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Harbor project of a course with its own robot account. Courses
+    /// pushing to the shared namespace project have none to manage.
+    async fn own_project(ctx: &Context, slug: &str) -> Result<String> {
+        CourseRepository::get_by_slug(&ctx.database, slug).await?;
+        match RegistryRepository::find_active(&ctx.database, slug).await? {
+            Some(credential) => Ok(credential.project),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    /// Delete the images the pipelines built for a user course repository,
+    /// returning how many artifacts were deleted. They live in the project of
+    /// the course, or the namespace project for courses without one.
+    pub async fn cleanup(ctx: &Context, slug: &str, repo: &Uuid) -> Result<usize> {
+        let mut projects = vec![Self::course_project(ctx, slug), ctx.config.namespace.clone()];
+        projects.dedup();
+
+        let mut removed = 0;
+        for project in &projects {
+            for image in [repo.to_string(), format!("{repo}-test")] {
+                removed += Self::delete_artifacts(ctx, project, &image).await?;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Delete all artifacts of a repository, treating a missing project or
+    /// repository as empty.
+    async fn delete_artifacts(ctx: &Context, project: &str, repository: &str) -> Result<usize> {
+        let query = ArtifactQuery { pagination: Pagination::new(1, 100), ..Default::default() };
+        let mut removed = 0;
+
+        // Deleting moves the following artifacts onto the first page
+        loop {
+            let artifacts = match ctx.harbor.list_artifacts(project, repository, &query).await {
+                Ok(artifacts) => artifacts,
+                Err(ClientError::NotFound) => break,
+                Err(e) => return Err(e.into()),
+            };
+
+            let last = query.pagination.is_last(artifacts.len());
+            for artifact in artifacts {
+                match ctx.harbor.delete_artifact(project, repository, &artifact.digest).await {
+                    Ok(()) | Err(ClientError::NotFound) => removed += 1,
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            if last {
+                break;
+            }
+        }
+
+        if removed > 0 {
+            info!("Deleted {removed} artifacts of repository '{project}/{repository}'");
+        }
+        Ok(removed)
+    }
+
+    /// Harbor project and Kubernetes Secret new pipelines of a course push
+    /// with. Courses without their own robot account fall back to the
+    /// shared secret and the namespace project.
+    pub async fn pipeline_credentials(ctx: &Context, slug: &str) -> Result<(String, String)> {
+        match RegistryRepository::find_active(&ctx.database, slug).await? {
+            Some(credential) => Ok((credential.project, credential.secret_name)),
+            None => Ok((ctx.config.namespace.clone(), SHARED_SECRET.to_string())),
+        }
+    }
+
+    /// Create or update the Secret holding the docker config of a robot
+    /// account in the namespace of the pipelines.
+    async fn apply_secret(
+        ctx: &Context,
+        name: &str,
+        course: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<()> {
+        let auth = STANDARD.encode(format!("{username}:{password}"));
+        let host = ctx.endpoints.registry_host();
+        let config = json!({
+            "auths": {
+                host: { "username": username, "password": password, "auth": auth }
+            }
+        });
+
+        let secret = Secret {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                labels: Some(BTreeMap::from([(
+                    "stackclass.dev/course".to_string(),
+                    course.to_string(),
+                )])),
+                ..Default::default()
+            },
+            type_: Some("Opaque".to_string()),
+            data: Some(BTreeMap::from([(
+                "config.json".to_string(),
+                ByteString(config.to_string().into_bytes()),
+            )])),
+            ..Default::default()
+        };
+
+        let params = PatchParams::apply("stackclass").force();
+        Self::secrets(ctx).patch(name, &params, &Patch::Apply(&secret)).await?;
+        Ok(())
+    }
+
+    /// Delete a Secret, treating an already deleted one as success.
+    async fn delete_secret(ctx: &Context, name: &str) -> Result<()> {
+        match Self::secrets(ctx).delete(name, &DeleteParams::default()).await {
+            Ok(_) => Ok(()),
+            Err(kube::Error::Api(status)) if status.code == 404 => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[inline]
+    fn secrets(ctx: &Context) -> Api<Secret> {
+        Api::namespaced(ctx.k8s.clone(), ctx.config.namespace.as_ref())
     }
 }
@@ -12,91 +12,204 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
 
 use base64::{Engine, prelude::BASE64_STANDARD as Base64};
-use fs_extra::dir::CopyOptions;
 use gitea_client::{ClientError, types::*};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
 use crate::{
     config::Config,
     context::Context,
     errors::Result,
-    repository::CourseRepository,
-    service::{CourseService, PipelineService, StorageError, StorageService},
-    utils::{crypto, git, url},
+    model::{
+        DeployKeyModel, POOL_REPO_PREFIX, StageAttemptModel, TRIAL_REPO_PREFIX, UserCourseModel,
+    },
+    repository::{
+        AssetRepository, CourseRepository, DeployKeyRepository, RepoMigrationRepository,
+        RepoPoolRepository, StageRepository,
+    },
+    service::{
+        CourseService, IDENTITY_FILE, PipelineService, RepoPoolService, SettingsService,
+        StageService, StorageError, StorageService, SyncOutcome, TemplateUpdate, TrialService,
+        WorkspaceService, course::identity_payload,
+    },
+    utils::{
+        crypto,
+        git::{self, Submodule},
+        url,
+    },
 };
 
+/// Title of the deploy keys the pipelines clone repositories with.
+const DEPLOY_KEY_TITLE: &str = "stackclass-pipeline";
+
+/// Purpose the private keys of deploy keys are encrypted for
+const DEPLOY_KEY_PURPOSE: &str = "deploy-key";
+
 pub struct RepoService {
     ctx: Arc<Context>,
 }
 
+/// Outcome of the teardown of the repositories of a course.
+#[derive(Debug, Default)]
+pub struct Teardown {
+    /// Repositories deleted, or already gone
+    pub deleted: Vec<String>,
+
+    /// Repositories that could not be deleted
+    pub failed: Vec<String>,
+}
+
 impl RepoService {
     pub fn new(ctx: Arc<Context>) -> Self {
         RepoService { ctx }
     }
 
-    /// Initializes a template repository in the Source Code Management system
-    /// for this course. The repository will contain the course's template
-    /// source code.
-    pub async fn init(&self, template: &str, template_url: &str) -> Result<()> {
+    /// Initializes the template repositories in the Source Code Management
+    /// system for this course, one per language it is offered in. The
+    /// repositories will contain the course's template source code.
+    pub async fn init(&self, course_slug: &str, template_url: &str) -> Result<SyncOutcome> {
         let org = &self.ctx.config.namespace;
+        let course = CourseRepository::get_by_slug(&self.ctx.database, course_slug).await?;
 
-        // Fetch or create the template repository in SCM
-        self.fetch_template(org, template).await?;
+        let mut changed = Vec::new();
+        for (template, dir) in course.templates() {
+            // Fetch or create the template repository in SCM
+            self.fetch_template(org, &template).await?;
 
-        // Commits the template source code to the template repository
-        self.commit(template_url, org, template).await?;
+            // Commits the template source code to the template repository
+            let outcome = self.commit(template_url, org, course_slug, &template, &dir).await?;
+            if let SyncOutcome::Pushed(paths) = outcome {
+                changed.extend(paths);
+            }
+        }
 
-        Ok(())
+        Ok(match changed.is_empty() {
+            true => SyncOutcome::Unchanged,
+            false => SyncOutcome::Pushed(changed),
+        })
     }
 
-    /// Commits the template source code to a specified repository.
-    async fn commit(&self, template_url: &str, owner: &str, repo: &str) -> Result<()> {
+    /// Commits the template source code in a directory of the course to a
+    /// specified repository, through its working clone. Nothing is pushed
+    /// unless the template changed.
+    ///
+    /// Templates with submodules are taken from a clone of the course rather
+    /// than from its tarball, which has them as empty directories.
+    async fn commit(
+        &self,
+        template_url: &str,
+        owner: &str,
+        course_slug: &str,
+        repo: &str,
+        template: &str,
+    ) -> Result<SyncOutcome> {
         // Fetch and validate the template directory
         let Config { cache_dir, github_token, .. } = &self.ctx.config;
         let storage = StorageService::new(cache_dir, github_token)?;
-        let dir = storage.fetch(template_url).await?;
-        let template_dir = cache_dir.join(dir).join("template");
+        let db = &self.ctx.database;
+        let course = CourseRepository::get_by_slug(db, course_slug).await?;
+
+        // The template is taken from the commit the course was synced at
+        let commit = course.commit_sha.clone();
+        let dir = storage.fetch_commit(template_url, &commit).await?;
+        let template_dir = cache_dir.join(&dir).join(template);
         if !template_dir.exists() {
             return Err(StorageError::MissingTemplate.into());
         }
 
-        // Create a temporary directory for staging
-        let temp_dir = tempfile::tempdir().map_err(StorageError::CreateDir)?;
-        let workspace = temp_dir.path();
-
-        // Copy template contents to the workspace directory
-        let copy_options = CopyOptions::new().content_only(true).copy_inside(true);
-        fs_extra::dir::copy(&template_dir, workspace, &copy_options)
-            .map_err(|e| StorageError::CopyFiles(e.to_string()))?;
-
-        // Initialize Git repository with the 'main' branch
-        git::init(workspace, "main").await?;
-
-        // Configure Git user information
-        git::config(workspace, "user.name", &self.ctx.config.git_committer_name).await?;
-        git::config(workspace, "user.email", &self.ctx.config.git_committer_email).await?;
+        let prefix = format!("{template}/");
+        let submodules = template_submodules(&cache_dir.join(&dir), template_url, &prefix)?;
+        let upstream = if submodules.is_empty() {
+            None
+        } else {
+            for warning in check_submodules(cache_dir, &submodules).await {
+                warn!("Template of course {}: {}", repo, warning);
+            }
+            Some(storage.clone(template_url, &commit).await?)
+        };
 
-        // Perform Git operations to commit the source code
-        git::stage(workspace).await?;
-        git::commit(workspace, "Initial commit from template").await?;
+        // Template files in the asset index are compared by their hash
+        let hashes = AssetRepository::find_by_course(db, course.id)
+            .await?
+            .into_iter()
+            .filter_map(|asset| Some((asset.path.strip_prefix(&prefix)?.to_string(), asset.sha256)))
+            .collect();
 
-        // ... and push to the remote repository
-        let endpoint = &self.ctx.config.git_server_endpoint;
-        let base_url = format!("{endpoint}/{owner}/{repo}.git");
+        let base_url = self.ctx.endpoints.clone_url(owner, repo);
         let remote_url = url::authenticate(
             &base_url,
             &self.ctx.config.git_server_username,
             &self.ctx.config.git_server_password,
         )?;
-        git::add_remote(workspace, "origin", &remote_url).await?;
-        git::push(workspace, "origin", "main").await?;
 
-        info!("Successfully pushed template contents to repository: {}", base_url);
-        Ok(())
+        // Syncs of the same course take turns, across replicas too
+        let mut tx = db.pool().begin().await?;
+        CourseRepository::lock(&mut tx, course_slug).await?;
+        let workspace = self.workspace();
+        let outcome = match &upstream {
+            Some(upstream) => {
+                let path = upstream.path();
+                workspace.sync_tree(repo, path, template, &submodules, &remote_url).await?
+            }
+            None => workspace.sync(repo, &template_dir, &commit, &remote_url, &hashes).await?,
+        };
+        tx.commit().await?;
+
+        info!("Synced template contents to repository {}: {}", base_url, outcome);
+        Ok(outcome)
+    }
+
+    /// Removes the working clones of deleted courses and of courses not
+    /// synced within the workspace TTL, returning how many were removed.
+    pub async fn prune_workspaces(&self) -> Result<usize> {
+        let db = &self.ctx.database;
+        let courses: HashMap<String, String> = CourseRepository::find(db)
+            .await?
+            .into_iter()
+            .flat_map(|course| {
+                let templates = course.templates().into_iter();
+                templates.map(move |(template, _)| (template, course.slug.clone()))
+            })
+            .collect();
+        let ttl = Duration::from_secs(self.ctx.config.workspace_ttl);
+        let workspace = self.workspace();
+
+        let mut removed = 0;
+        for (name, synced_at) in workspace.list()? {
+            let idle = synced_at.elapsed().unwrap_or_default() > ttl;
+            if courses.contains_key(&name) && !idle {
+                continue;
+            }
+
+            let mut tx = db.pool().begin().await?;
+            CourseRepository::lock(&mut tx, courses.get(&name).unwrap_or(&name)).await?;
+            workspace.remove(&name)?;
+            tx.commit().await?;
+
+            debug!("Removed the working clone of course {}", name);
+            removed += 1;
+        }
+
+        Ok(removed)
+    }
+
+    fn workspace(&self) -> WorkspaceService {
+        let config = &self.ctx.config;
+        WorkspaceService::new(
+            &config.workspace_dir(),
+            &config.git_committer_name,
+            &config.git_committer_email,
+        )
+        .with_branch(&config.default_branch)
     }
 
     /// Processes a repository push event by managing the associated course workflow.
@@ -109,9 +222,23 @@ impl RepoService {
         let repo = &event.repository.name;
         debug!("Handling push event for repository: {}", repo);
 
+        // Trial repositories are graded without a user course
+        if let Some(id) = TrialService::parse_repo(repo) {
+            return TrialService::process(self.ctx.clone(), &id).await;
+        }
+
         let id = Uuid::parse_str(repo)?;
         let mut course = CourseRepository::get_user_course_by_id(&self.ctx.database, &id).await?;
 
+        // Courses requiring a verified identity stay on hold until the pushed
+        // commit carries a valid verification token
+        if course.awaiting_identity_verification() &&
+            !self.verify_identity(&mut course, &event.after).await?
+        {
+            info!("Deferring repository {} until the git identity is verified", repo);
+            return Ok(());
+        }
+
         // If there's no current stage, this is the first setup of the course,
         // so we just need to activate it without running any pipeline stages
         let Some(current_stage_slug) = course.current_stage_slug else {
@@ -119,14 +246,75 @@ impl RepoService {
             return Ok(());
         };
 
-        // Trigger the pipeline run and return immediately
-        // Pipeline completion will be handled asynchronously via Tekton webhook
+        // Refuse to grade once the attempt budget of the stage is exhausted
+        let db = &self.ctx.database;
+        let user_stage = StageRepository::get_user_stage(
+            db,
+            &course.user_id,
+            &course.course_slug,
+            &current_stage_slug,
+        )
+        .await?;
+        let stage = StageRepository::get_by_id(db, user_stage.stage_id).await?;
+        let definition = CourseRepository::get_by_slug(db, &course.course_slug).await?;
+        let settings = SettingsService::resolve(&self.ctx, &definition, Some(&stage)).await?;
+        let attempt =
+            StageAttemptModel::new_at(user_stage.id, &stage.content_hash, self.ctx.clock.now())
+                .with_course_commit(&definition.commit_sha)
+                .with_repo_commit(&event.after)
+                .with_tester_image(&settings.tester_image);
+
+        if StageService::remaining_attempts(&self.ctx, &user_stage, &stage).await? == Some(0) {
+            info!("Attempt budget exhausted for stage {} of repository {}", stage.slug, repo);
+            StageRepository::create_attempt(db, &attempt.with_status("budget_exhausted")).await?;
+            return Ok(());
+        }
+
+        // Runs still grading older pushes would race this one to complete the
+        // stage. Failing to cancel them does not hold the push back.
         let pipeline = PipelineService::new(self.ctx.clone());
-        pipeline.trigger(repo, &course.course_slug, &current_stage_slug).await?;
+        if let Err(e) = pipeline.cancel_superseded(repo).await {
+            warn!("Failed to cancel superseded PipelineRuns of repository {repo}: {e}");
+        }
+
+        // Trigger the pipeline run and return immediately, or queue it while
+        // the cluster is unavailable
+        // Pipeline completion will be handled asynchronously via Tekton webhook
+        pipeline.schedule(repo, &course.course_slug, &current_stage_slug, attempt).await?;
 
         Ok(())
     }
 
+    /// Checks the verification token committed at the given reference and marks
+    /// the identity of the user course as verified when it matches.
+    async fn verify_identity(&self, course: &mut UserCourseModel, reference: &str) -> Result<bool> {
+        let Some(nonce) = &course.identity_nonce else {
+            return Ok(false);
+        };
+
+        let org = &self.ctx.config.namespace;
+        let repo = course.id.to_string();
+        let content = match self.ctx.git.get_raw_file(org, &repo, IDENTITY_FILE, reference).await {
+            Ok(content) => content,
+            Err(ClientError::NotFound) => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+
+        let token = String::from_utf8_lossy(&content);
+        let payload = identity_payload(&course.id, nonce);
+        if !crypto::hmac_sha256_verify(&payload, &self.ctx.config.auth_secret, token.trim())? {
+            return Ok(false);
+        }
+
+        course.identity_verified = true;
+        let mut tx = self.ctx.database.pool().begin().await?;
+        CourseRepository::update_user_course(&mut tx, course).await?;
+        tx.commit().await?;
+
+        info!("Git identity verified for repository {}", repo);
+        Ok(true)
+    }
+
     /// Gets a organization by name,
     /// or creates the organization if it doesn't exist.
     pub async fn fetch_organization(&self, name: &str) -> Result<Organization> {
@@ -154,6 +342,7 @@ impl RepoService {
                 let req = CreateRepositoryRequest {
                     name: repo.to_string(),
                     template: Some(true),
+                    default_branch: Some(self.ctx.config.default_branch.clone()),
                     ..Default::default()
                 };
                 self.ctx.git.create_org_repository(org, req).await?
@@ -165,19 +354,71 @@ impl RepoService {
         Ok(repository)
     }
 
-    /// Generates a new repository from a template if it doesn't exist.
+    /// Generates a new repository from a template if it doesn't exist,
+    /// taking a spare repository of the course from the pool when available.
     pub async fn generate(&self, template: &str, repo: &str) -> Result<Repository> {
         let org = &self.ctx.config.namespace;
-
         let repository = match self.ctx.git.get_repository(org, repo).await {
+            Ok(repository) => repository,
+            Err(ClientError::NotFound) => match self.claim(template, repo).await? {
+                Some(repository) => repository,
+                None => return self.generate_in(org, template, repo).await,
+            },
+            Err(e) => return Err(e.into()),
+        };
+
+        self.protect(org, repo).await?;
+        self.attach_webhook(org, repo).await?;
+        self.attach_deploy_key(org, repo).await?;
+        Ok(repository)
+    }
+
+    /// Claims a pooled repository of the course and renames it. Returns none
+    /// when the pool is empty, or the claimed repository could not be renamed
+    /// and is discarded.
+    async fn claim(&self, template: &str, repo: &str) -> Result<Option<Repository>> {
+        let (db, org) = (&self.ctx.database, &self.ctx.config.namespace);
+        let cutoff = RepoPoolService::cutoff(&self.ctx);
+        let Some(pooled) = RepoPoolRepository::claim(db, template, repo, cutoff).await? else {
+            return Ok(None);
+        };
+
+        // Renaming a missing repository succeeds, fetch it to make sure
+        let renamed = match self.rename(org, &pooled.repo(), repo).await {
+            Ok(()) => self.ctx.git.get_repository(org, repo).await.map_err(Into::into),
+            Err(e) => Err(e),
+        };
+
+        match renamed {
+            Ok(repository) => {
+                info!("Claimed pooled repository {} as {org}/{repo}", pooled.repo());
+                Ok(Some(repository))
+            }
+            Err(e) => {
+                warn!("Failed to claim pooled repository {}: {e}", pooled.repo());
+                if let Err(e) = self.delete(org, &pooled.repo()).await {
+                    warn!("Failed to discard pooled repository {}: {e}", pooled.repo());
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Generates a new repository in the given organization from a template
+    /// if it doesn't exist.
+    pub async fn generate_in(&self, owner: &str, template: &str, repo: &str) -> Result<Repository> {
+        let org = &self.ctx.config.namespace;
+
+        let repository = match self.ctx.git.get_repository(owner, repo).await {
             Ok(repository) => repository,
             Err(ClientError::NotFound) => {
                 let req = GenerateRepositoryRequest {
                     git_content: Some(true),
                     git_hooks: Some(true),
                     name: repo.to_string(),
-                    owner: org.to_string(),
+                    owner: owner.to_string(),
                     webhooks: Some(true),
+                    default_branch: Some(self.ctx.config.default_branch.clone()),
                     ..Default::default()
                 };
                 self.ctx.git.generate_repository(org, template, req).await?
@@ -185,14 +426,346 @@ impl RepoService {
             Err(e) => return Err(e.into()),
         };
 
-        info!("Successfully generated new repository: {org}/{repo}");
+        self.protect(owner, repo).await?;
+        self.attach_webhook(owner, repo).await?;
+        self.attach_deploy_key(owner, repo).await?;
+
+        info!("Successfully generated new repository: {owner}/{repo}");
         Ok(repository)
     }
 
+    /// Protects the default branch of a repository, if not done yet, so that
+    /// learners can push to it but neither delete it nor rewrite its history.
+    /// Only the service account may force push, e.g. to transplant a trial.
+    pub async fn protect(&self, owner: &str, repo: &str) -> Result<()> {
+        let branch = &self.ctx.config.default_branch;
+        match self.ctx.git.get_branch_protection(owner, repo, branch).await {
+            Ok(_) => return Ok(()),
+            Err(ClientError::NotFound) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        let req = CreateBranchProtectionRequest {
+            rule_name: branch.clone(),
+            enable_push: Some(true),
+            enable_force_push: Some(true),
+            enable_force_push_allowlist: Some(true),
+            force_push_allowlist_usernames: Some(vec![self.ctx.config.git_server_username.clone()]),
+            ..Default::default()
+        };
+        self.ctx.git.create_branch_protection(owner, repo, req).await?;
+
+        info!("Successfully protected the {branch} branch of repository: {owner}/{repo}");
+        Ok(())
+    }
+
+    /// Adds a read-only deploy key to a repository for the pipelines to clone
+    /// it with, unless it has one already. Only acts when cloning over SSH is
+    /// configured.
+    pub async fn attach_deploy_key(&self, owner: &str, repo: &str) -> Result<()> {
+        if self.ctx.endpoints.ssh_clone_url(owner, repo).is_none() {
+            return Ok(());
+        }
+
+        if DeployKeyRepository::find(&self.ctx.database, owner, repo).await?.is_none() {
+            self.create_deploy_key(owner, repo).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the private key of the deploy key of a repository, adding one
+    /// first if the repository was generated before deploy keys were.
+    pub async fn deploy_key(&self, owner: &str, repo: &str) -> Result<String> {
+        let key = match DeployKeyRepository::find(&self.ctx.database, owner, repo).await? {
+            Some(key) => key,
+            None => self.create_deploy_key(owner, repo).await?,
+        };
+
+        let secret = &self.ctx.config.auth_secret;
+        Ok(crypto::decrypt(&key.encrypted_private_key, DEPLOY_KEY_PURPOSE, secret)?)
+    }
+
+    /// Generates a key pair, adds its public key to a repository as a
+    /// read-only deploy key and records the encrypted private key. Deploy
+    /// keys added earlier, whose private key is lost, are removed.
+    async fn create_deploy_key(&self, owner: &str, repo: &str) -> Result<DeployKeyModel> {
+        for key in self.ctx.git.list_deploy_keys(owner, repo).await? {
+            if key.title == DEPLOY_KEY_TITLE {
+                self.ctx.git.delete_deploy_key(owner, repo, key.id).await?;
+            }
+        }
+
+        let pair = crypto::ssh_keypair(&format!("{owner}/{repo}"))?;
+        let req = CreateKeyRequest {
+            key: pair.public_key,
+            read_only: true,
+            title: DEPLOY_KEY_TITLE.to_string(),
+        };
+        let key = self.ctx.git.create_deploy_key(owner, repo, req).await?;
+
+        let secret = &self.ctx.config.auth_secret;
+        let encrypted = crypto::encrypt(&pair.private_key, DEPLOY_KEY_PURPOSE, secret)?;
+        let model = DeployKeyModel::new(owner, repo, key.id, &encrypted);
+        let model = DeployKeyRepository::upsert(&self.ctx.database, &model).await?;
+
+        info!("Successfully added a deploy key to repository: {owner}/{repo}");
+        Ok(model)
+    }
+
+    /// Archives a repository, making it read-only.
+    pub async fn archive(&self, owner: &str, repo: &str) -> Result<()> {
+        self.ctx.git.archive_repository(owner, repo).await?;
+
+        info!("Successfully archived repository: {owner}/{repo}");
+        Ok(())
+    }
+
+    /// Deletes a repository, succeeding if it is already gone.
+    pub async fn delete(&self, owner: &str, repo: &str) -> Result<()> {
+        match self.ctx.git.delete_repository(owner, repo).await {
+            Ok(()) | Err(ClientError::NotFound) => {
+                DeployKeyRepository::delete(&self.ctx.database, owner, repo).await?;
+                info!("Successfully deleted repository: {owner}/{repo}");
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Deletes the repositories of a course: those of its enrollments, the
+    /// unclaimed ones of its pool and finally its template. A repository that
+    /// cannot be deleted is reported and the others are deleted regardless.
+    /// Repositories already gone count as deleted, so a teardown can be
+    /// retried.
+    pub async fn teardown(&self, course_slug: &str) -> Result<Teardown> {
+        let db = &self.ctx.database;
+        let org = &self.ctx.config.namespace;
+        let course = CourseRepository::get_by_slug(db, course_slug).await?;
+
+        let mut repos: Vec<String> = CourseRepository::find_user_course_ids(db, &course.id)
+            .await?
+            .iter()
+            .map(Uuid::to_string)
+            .collect();
+        repos.extend(
+            RepoPoolRepository::find_unclaimed(db, &course.id).await?.iter().map(|p| p.repo()),
+        );
+        repos.extend(course.templates().into_iter().map(|(template, _)| template));
+
+        let mut teardown = Teardown::default();
+        for repo in repos {
+            match self.delete(org, &repo).await {
+                Ok(()) => teardown.deleted.push(repo),
+                Err(e) => {
+                    warn!("Failed to delete repository {org}/{repo}: {e}");
+                    teardown.failed.push(repo);
+                }
+            }
+        }
+
+        info!(
+            "Tore down repositories of course {course_slug}: {} deleted, {} failed",
+            teardown.deleted.len(),
+            teardown.failed.len()
+        );
+        Ok(teardown)
+    }
+
+    /// Finds the repositories of the organization which belong to nothing:
+    /// neither the template of a course nor an existing user course. Pooled
+    /// and trial repositories are left to their own jobs.
+    pub async fn find_orphaned_repositories(&self) -> Result<Vec<Repository>> {
+        let db = &self.ctx.database;
+        let org = &self.ctx.config.namespace;
+
+        let mut repos = Vec::new();
+        let mut pagination = Pagination::default();
+        loop {
+            let page = self.ctx.git.list_org_repositories(org, pagination).await?;
+            let last = pagination.is_last(page.len());
+            repos.extend(page);
+            if last {
+                break;
+            }
+            pagination = pagination.next();
+        }
+
+        let templates: HashSet<String> = CourseRepository::find(db)
+            .await?
+            .iter()
+            .flat_map(|course| course.templates().into_iter().map(|(template, _)| template))
+            .collect();
+        repos.retain(|repo| {
+            !templates.contains(&repo.name) &&
+                !repo.name.starts_with(POOL_REPO_PREFIX) &&
+                !repo.name.starts_with(TRIAL_REPO_PREFIX)
+        });
+
+        let ids: Vec<Uuid> =
+            repos.iter().filter_map(|repo| Uuid::parse_str(&repo.name).ok()).collect();
+        let known: HashSet<Uuid> =
+            RepoMigrationRepository::find_user_course_ids(db, &ids).await?.into_iter().collect();
+        repos.retain(|repo| Uuid::parse_str(&repo.name).map_or(true, |id| !known.contains(&id)));
+
+        Ok(repos)
+    }
+
+    /// Renames a repository, succeeding if it is already gone, e.g. renamed
+    /// by an earlier call.
+    pub async fn rename(&self, owner: &str, repo: &str, name: &str) -> Result<()> {
+        let req = EditRepositoryRequest { name: Some(name.to_string()), ..Default::default() };
+        match self.ctx.git.edit_repository(owner, repo, req).await {
+            Ok(_) | Err(ClientError::NotFound) => {
+                DeployKeyRepository::rename(&self.ctx.database, owner, repo, name).await?;
+                info!("Successfully renamed repository {owner}/{repo} to {name}");
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Replaces the default branch of a repository with the one of another,
+    /// carrying over its whole history.
+    pub async fn transplant(&self, from: (&str, &str), to: (&str, &str)) -> Result<()> {
+        let Config {
+            git_server_username: username,
+            git_server_password: password,
+            default_branch: branch,
+            ..
+        } = &self.ctx.config;
+        let source =
+            url::authenticate(&self.ctx.endpoints.clone_url(from.0, from.1), username, password)?;
+        let target =
+            url::authenticate(&self.ctx.endpoints.clone_url(to.0, to.1), username, password)?;
+
+        let temp_dir = tempfile::tempdir().map_err(StorageError::CreateDir)?;
+        let workspace = temp_dir.path();
+
+        git::clone(workspace, &source, branch).await?;
+        git::add_remote(workspace, "target", &target).await?;
+        git::push(workspace, "target", branch).await?;
+
+        info!("Successfully transplanted {}/{} into {}/{}", from.0, from.1, to.0, to.1);
+        Ok(())
+    }
+
+    /// Merges the latest template of the course into the repository of a
+    /// user course, on its template update branch, see
+    /// [`WorkspaceService::update_from_template`].
+    pub async fn sync_user_repository(&self, user_course_id: &Uuid) -> Result<TemplateUpdate> {
+        let db = &self.ctx.database;
+        let user_course = CourseRepository::get_user_course_by_id(db, user_course_id).await?;
+        let course = CourseRepository::get_by_slug(db, &user_course.course_slug).await?;
+
+        let Config { namespace: org, git_server_username, git_server_password, .. } =
+            &self.ctx.config;
+        let authenticate = |repo: &str| {
+            let url = self.ctx.endpoints.clone_url(org, repo);
+            url::authenticate(&url, git_server_username, git_server_password)
+        };
+        let repo_url = authenticate(&user_course_id.to_string())?;
+        let template_url = authenticate(&course.template(user_course.language.as_deref()))?;
+
+        let update = self.workspace().update_from_template(&repo_url, &template_url).await?;
+        info!("Synced template of course {} to repository {user_course_id}: {update}", course.slug);
+        Ok(update)
+    }
+
     /// Setup the webhook for the organization
     pub async fn setup_webhook(&self, org: &str) -> Result<()> {
-        let webhook_endpoint = &self.ctx.config.webhook_endpoint;
-        let url = format!("{webhook_endpoint}/v1/webhooks/gitea");
+        let req = self.webhook_request()?;
+
+        // List all existing hooks
+        let hooks = self.ctx.git.list_org_hooks(org).await?;
+
+        // Skip if a hook with the same configuration already exists, patch
+        // one whose URL or branch filter went stale, or else create it.
+        if hooks.iter().any(|hook| matching(hook, &req)) {
+            return Ok(());
+        }
+        if let Some(hook) = hooks.iter().find(|hook| outdated(hook, &req)) {
+            info!("Updating the stale webhook {} of the organization {org}.", hook.id);
+            self.ctx.git.update_org_hook(org, hook.id, req.into()).await?;
+        } else {
+            info!("Setting up the webhook for the organization {org}.");
+            self.ctx.git.create_org_hook(org, req).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets up the webhook of an organization, or with repository webhooks
+    /// moves its existing repositories over to them first.
+    pub async fn setup_webhooks(&self, org: &str) -> Result<()> {
+        if !self.ctx.config.repo_webhooks {
+            return self.setup_webhook(org).await;
+        }
+
+        // Only an organization still having our webhook has repositories to
+        // move, which are all hooked before it is deleted so that no push
+        // goes unnoticed
+        let req = self.webhook_request()?;
+        let hooks: Vec<Hook> = self
+            .ctx
+            .git
+            .list_org_hooks(org)
+            .await?
+            .into_iter()
+            .filter(|hook| matching(hook, &req) || outdated(hook, &req))
+            .collect();
+        if hooks.is_empty() {
+            return Ok(());
+        }
+
+        let mut pagination = Pagination::default();
+        loop {
+            let page = self.ctx.git.list_org_repositories(org, pagination).await?;
+            for repo in &page {
+                self.setup_repo_webhook(org, &repo.name).await?;
+            }
+            if pagination.is_last(page.len()) {
+                break;
+            }
+            pagination = pagination.next();
+        }
+
+        for hook in hooks {
+            self.ctx.git.delete_org_hook(org, hook.id).await?;
+        }
+
+        info!("Moved the webhook of the organization {org} to its repositories.");
+        Ok(())
+    }
+
+    /// Attaches the webhook to a repository when configured to use
+    /// repository webhooks, the organization webhook covering it otherwise.
+    pub async fn attach_webhook(&self, owner: &str, repo: &str) -> Result<()> {
+        match self.ctx.config.repo_webhooks {
+            true => self.setup_repo_webhook(owner, repo).await,
+            false => Ok(()),
+        }
+    }
+
+    /// Setup the webhook for a repository, replacing stale ones.
+    async fn setup_repo_webhook(&self, owner: &str, repo: &str) -> Result<()> {
+        let req = self.webhook_request()?;
+        let hooks = self.ctx.git.list_repo_hooks(owner, repo).await?;
+        if hooks.iter().any(|hook| matching(hook, &req)) {
+            return Ok(());
+        }
+
+        for hook in hooks.iter().filter(|hook| outdated(hook, &req)) {
+            self.ctx.git.delete_repo_hook(owner, repo, hook.id).await?;
+        }
+        self.ctx.git.create_repo_hook(owner, repo, req).await?;
+
+        debug!("Set up the webhook for the repository {owner}/{repo}.");
+        Ok(())
+    }
+
+    /// Builds the webhook sending push events of the default branch to us.
+    fn webhook_request(&self) -> Result<CreateHookRequest> {
+        let url = self.ctx.endpoints.webhook_url("gitea");
 
         // Generate the HMAC-SHA256 signature for the webhook authorization header
         // using the admin username and the auth_secret from the configuration.
@@ -200,29 +773,58 @@ impl RepoService {
         let password = crypto::hmac_sha256_sign("admin", &self.ctx.config.auth_secret)?;
         let auth_header = format!("Basic {}", Base64.encode(format!("admin:{}", password)));
 
-        // Define the webhook request body to listen for push events on the main branch
+        // Define the webhook request body to listen for push events on the default branch
         // and send them to the specified webhook endpoint in JSON format.
-        let req = CreateHookRequest {
+        Ok(CreateHookRequest {
             active: true,
             authorization_header: Some(auth_header),
-            branch_filter: Some("main".to_string()),
+            branch_filter: Some(self.ctx.config.default_branch.clone()),
             config: HashMap::from([
                 ("content_type".to_string(), "json".to_string()),
-                ("url".to_string(), url.clone()),
+                ("url".to_string(), url),
             ]),
             events: vec!["push".to_string()],
             kind: "gitea".to_string(),
-        };
+        })
+    }
+}
 
-        // List all existing hooks
-        let hooks = self.ctx.git.list_org_hooks(org).await?;
+/// Reads the submodules of a course which lie in its template, with paths
+/// relative to the template and URLs resolved against the course repository.
+fn template_submodules(
+    course_dir: &Path,
+    course_url: &str,
+    prefix: &str,
+) -> Result<Vec<Submodule>> {
+    let content = match std::fs::read_to_string(course_dir.join(".gitmodules")) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(StorageError::ReadFile(e).into()),
+    };
+
+    let mut submodules = Vec::new();
+    for submodule in git::parse_submodules(&content) {
+        let Some(path) = submodule.path.strip_prefix(prefix) else {
+            continue;
+        };
+        let url = url::resolve(course_url, &submodule.url)?;
+        submodules.push(Submodule { name: path.to_string(), path: path.to_string(), url });
+    }
+    Ok(submodules)
+}
 
-        // Check if a hook with the same configuration already exists
-        if !hooks.iter().any(|hook| matching(hook, &req)) {
-            info!("Setting up the webhook for the organization {org}.");
-            self.ctx.git.create_org_hook(org, req).await?;
+/// Checks that learners can fetch the submodules of a template, returning a
+/// warning for each one they can not read anonymously, like a private
+/// repository.
+pub async fn check_submodules(dir: &Path, submodules: &[Submodule]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for submodule in submodules {
+        if !git::is_public(dir, &submodule.url).await {
+            warnings.push(format!(
+                "submodule {} points at {}, which learners will not be able to fetch",
+                submodule.path, submodule.url
+            ));
         }
-
-        Ok(())
     }
+    warnings
 }
@@ -0,0 +1,212 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use crate::{
+    context::Context,
+    errors::Result,
+    model::RoadmapStageModel,
+    repository::{CourseRepository, StageRepository},
+    response::{RoadmapResponse, RoadmapStageResponse, StageState},
+};
+
+/// Service resolving the path of a learner through a course
+pub struct RoadmapService;
+
+impl RoadmapService {
+    /// Get the roadmap of the user through an enrolled course.
+    pub async fn get(
+        ctx: Arc<Context>,
+        user_id: &str,
+        course_slug: &str,
+    ) -> Result<RoadmapResponse> {
+        let db = &ctx.database;
+
+        let stages = StageRepository::find_roadmap(db, user_id, course_slug).await?;
+        if stages.is_empty() {
+            // Tell a course without stages from a missing enrollment
+            CourseRepository::get_user_course(db, user_id, course_slug).await?;
+        }
+
+        let states = classify(&stages);
+        let completed = states.iter().filter(|s| **s == StageState::Completed).count();
+        let remaining = states
+            .iter()
+            .filter(|s| !matches!(s, StageState::Completed | StageState::Skipped))
+            .count();
+
+        let stages = stages
+            .into_iter()
+            .zip(states)
+            .map(|(stage, state)| RoadmapStageResponse {
+                slug: stage.slug,
+                extension_slug: stage.extension_slug,
+                name: stage.name,
+                state,
+            })
+            .collect();
+
+        Ok(RoadmapResponse {
+            stages,
+            completed_stage_count: completed,
+            remaining_stage_count: remaining,
+            percent_complete: percent(completed, completed + remaining),
+        })
+    }
+}
+
+/// Classifies the stages, given in progression order, the way progression
+/// resolves them: the first stage becomes current on activation, and
+/// completing a stage makes the stage following it current.
+fn classify(stages: &[RoadmapStageModel]) -> Vec<StageState> {
+    let completed = |stage: &RoadmapStageModel| stage.status.as_deref() == Some("completed");
+    let mut states: Vec<_> = stages
+        .iter()
+        .map(|stage| if completed(stage) { StageState::Completed } else { StageState::Skipped })
+        .collect();
+
+    // Progression starts from the current stage, or from the first one
+    // before the course is activated.
+    let start = match stages.iter().position(|stage| stage.current) {
+        Some(index) if completed(&stages[index]) => return states,
+        Some(index) => {
+            states[index] = StageState::Current;
+            index
+        }
+        None if stages.is_empty() || completed(&stages[0]) => return states,
+        None => {
+            states[0] = StageState::Unlocked;
+            0
+        }
+    };
+
+    for (index, stage) in stages.iter().enumerate().skip(start + 1) {
+        if !completed(stage) {
+            states[index] = match stage.extension_slug {
+                Some(_) => StageState::LockedExtension,
+                None => StageState::LockedPrerequisite,
+            };
+        }
+    }
+
+    states
+}
+
+/// Percentage of `part` in `total`, rounded to one decimal.
+fn percent(part: usize, total: usize) -> f64 {
+    match total {
+        0 => 0.0,
+        _ => (part as f64 * 1000.0 / total as f64).round() / 10.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use StageState::*;
+
+    /// Builds stages from (extension, status, current) tuples.
+    fn stages(fixture: &[(bool, Option<&str>, bool)]) -> Vec<RoadmapStageModel> {
+        fixture
+            .iter()
+            .enumerate()
+            .map(|(i, &(extension, status, current))| RoadmapStageModel {
+                slug: format!("s{i}"),
+                name: format!("Stage {i}"),
+                extension_slug: extension.then(|| "ext".to_string()),
+                status: status.map(str::to_string),
+                current,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_before_activation() {
+        let stages = stages(&[(false, None, false), (false, None, false), (true, None, false)]);
+        assert_eq!(classify(&stages), vec![Unlocked, LockedPrerequisite, LockedExtension]);
+    }
+
+    #[test]
+    fn test_in_progress() {
+        let stages = stages(&[
+            (false, Some("completed"), false),
+            (false, Some("in_progress"), true),
+            (false, None, false),
+            (true, None, false),
+            (true, None, false),
+        ]);
+        assert_eq!(
+            classify(&stages),
+            vec![Completed, Current, LockedPrerequisite, LockedExtension, LockedExtension]
+        );
+    }
+
+    #[test]
+    fn test_progression_continues_into_extensions() {
+        let stages = stages(&[
+            (false, Some("completed"), false),
+            (true, Some("completed"), false),
+            (true, Some("in_progress"), true),
+        ]);
+        assert_eq!(classify(&stages), vec![Completed, Completed, Current]);
+    }
+
+    #[test]
+    fn test_course_finished() {
+        let stages = stages(&[(false, Some("completed"), false), (false, Some("completed"), true)]);
+        assert_eq!(classify(&stages), vec![Completed, Completed]);
+    }
+
+    #[test]
+    fn test_stage_added_behind_learner() {
+        // A course update inserted a stage before the current one
+        let stages = stages(&[
+            (false, Some("completed"), false),
+            (false, None, false),
+            (false, Some("in_progress"), true),
+            (false, None, false),
+        ]);
+        assert_eq!(classify(&stages), vec![Completed, Skipped, Current, LockedPrerequisite]);
+    }
+
+    #[test]
+    fn test_stage_added_after_finish() {
+        // Completing the last stage ends progression, later additions are
+        // not unlocked
+        let stages = stages(&[(false, Some("completed"), true), (false, None, false)]);
+        assert_eq!(classify(&stages), vec![Completed, Skipped]);
+    }
+
+    #[test]
+    fn test_every_following_stage_is_reachable() {
+        // Progression follows the stage order, the base stages of a course
+        // and its extensions may hold any number of stages
+        let mut fixture = vec![(false, Some("in_progress"), true)];
+        fixture.extend([(false, None, false); 1000]);
+        fixture.extend([(true, None, false); 1001]);
+        let states = classify(&stages(&fixture));
+        assert_eq!(states[0], Current);
+        assert!(states[1..=1000].iter().all(|state| *state == LockedPrerequisite));
+        assert!(states[1001..].iter().all(|state| *state == LockedExtension));
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(percent(0, 0), 0.0);
+        assert_eq!(percent(1, 3), 33.3);
+        assert_eq!(percent(2, 3), 66.7);
+        assert_eq!(percent(3, 3), 100.0);
+    }
+}
@@ -0,0 +1,386 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Grading of attempts in containers of the local Docker or Podman daemon,
+//! for standalone deployments without Kubernetes, Tekton and Harbor.
+//!
+//! The learner repository is cloned into a temporary workspace and mounted
+//! into a container of the course tester. The container has no network and
+//! gets the limits of the stage's resource profile. The tester writes its
+//! results into the mounted results directory, and together with its exit
+//! code they decide the outcome, which is then recorded like the one of a
+//! PipelineRun.
+
+use std::{path::Path, sync::Arc, time::Duration};
+
+use bollard::{
+    API_DEFAULT_VERSION, Docker,
+    errors::Error as DockerError,
+    models::{ContainerCreateBody, HostConfig},
+    query_parameters::{
+        CreateContainerOptions, CreateImageOptions, KillContainerOptions, LogsOptions,
+        RemoveContainerOptions, StartContainerOptions, WaitContainerOptions,
+    },
+};
+use futures::{StreamExt, TryStreamExt};
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::{fs, sync::Semaphore, time};
+use tracing::{debug, error, info, warn};
+
+use super::pipeline::{build_test_cases_json, repo_org};
+use crate::{
+    config::Config,
+    context::Context,
+    errors::Result,
+    repository::StageRepository,
+    schema::ResourceProfile,
+    service::{PipelineService, SettingsService, TestOutcome},
+    utils::{git, url},
+};
+
+/// Timeout in seconds of requests to the container daemon.
+const DAEMON_TIMEOUT: u64 = 120;
+
+/// Where the learner repository is mounted in the tester container.
+const REPOSITORY_DIR: &str = "/workspace";
+
+/// Where the tester container writes its results.
+const RESULTS_DIR: &str = "/results";
+
+/// Name of the results file the tester writes into [`RESULTS_DIR`].
+const RESULTS_FILE: &str = "results.json";
+
+/// Maximum number of processes in the tester container.
+const PIDS_LIMIT: i64 = 512;
+
+/// Error type for the local runner
+#[derive(Debug, Error)]
+pub enum RunnerError {
+    #[error("Container daemon error: {0}")]
+    Daemon(#[from] DockerError),
+
+    #[error("Failed to prepare the workspace: {0}")]
+    Workspace(#[from] std::io::Error),
+
+    #[error("Resources of the {0} profile are no valid container limits")]
+    Resources(ResourceProfile),
+}
+
+/// A test run grading an attempt, which takes the place of a PipelineRun.
+#[derive(Clone, Debug)]
+pub struct TestRun {
+    /// Name the attempt was recorded with
+    pub name: String,
+
+    /// Name of the graded repository
+    pub repo: String,
+
+    /// Slug of the course
+    pub course: String,
+
+    /// Slug of the graded stage
+    pub stage: String,
+
+    /// Commit of the course repository the attempt is graded against
+    pub commit: String,
+
+    /// Content hash of the stage the attempt is graded against
+    pub content_hash: String,
+
+    /// Commit of the repository to grade, its main branch if empty
+    pub repo_commit: String,
+}
+
+impl TestRun {
+    pub fn new(
+        name: &str,
+        repo: &str,
+        course: &str,
+        stage: &str,
+        commit: &str,
+        content_hash: &str,
+        repo_commit: &str,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            repo: repo.to_string(),
+            course: course.to_string(),
+            stage: stage.to_string(),
+            commit: commit.to_string(),
+            content_hash: content_hash.to_string(),
+            repo_commit: repo_commit.to_string(),
+        }
+    }
+
+    fn outcome(&self, passed: bool) -> TestOutcome<'_> {
+        TestOutcome {
+            run: &self.name,
+            repo: &self.repo,
+            course: &self.course,
+            stage: &self.stage,
+            commit: &self.commit,
+            content_hash: &self.content_hash,
+            repo_commit: &self.repo_commit,
+            passed,
+            reason: "",
+            log: "",
+            results: &[],
+        }
+    }
+}
+
+/// Results the tester writes to [`RESULTS_FILE`].
+#[derive(Debug, Deserialize)]
+struct TestResults {
+    /// Whether all test cases passed
+    passed: bool,
+}
+
+/// Runs course testers in containers of the local daemon, a bounded number
+/// at a time.
+#[derive(Clone)]
+pub struct LocalRunner {
+    docker: Docker,
+    permits: Arc<Semaphore>,
+    timeout: Duration,
+}
+
+impl LocalRunner {
+    /// Connects to the daemon at the configured socket, or the default one.
+    pub fn new(config: &Config) -> Result<Self, RunnerError> {
+        let docker = match &config.local_runner_socket {
+            Some(socket) => {
+                Docker::connect_with_local(socket, DAEMON_TIMEOUT, API_DEFAULT_VERSION)?
+            }
+            None => Docker::connect_with_local_defaults()?,
+        };
+
+        Ok(Self {
+            docker,
+            permits: Arc::new(Semaphore::new(config.local_runner_concurrency)),
+            timeout: Duration::from_secs(config.local_runner_timeout),
+        })
+    }
+
+    /// Grades the run in the background as soon as a slot is free, then
+    /// records its outcome. The attempt must already be recorded under the
+    /// name of the run.
+    pub fn spawn(&self, ctx: Arc<Context>, run: TestRun) {
+        let runner = self.clone();
+        tokio::spawn(async move {
+            // The semaphore is never closed
+            let Ok(_permit) = runner.permits.clone().acquire_owned().await else { return };
+
+            // Runs that could not be carried out count as failed, just like
+            // PipelineRuns that failed
+            let passed = runner.execute(&ctx, &run).await.unwrap_or_else(|e| {
+                error!("Failed to run the tests of {}: {}", run.name, e);
+                false
+            });
+
+            let pipeline = PipelineService::new(ctx.clone());
+            if let Err(e) = pipeline.record_outcome(&run.outcome(passed)).await {
+                error!("Failed to record the outcome of {}: {}", run.name, e);
+            }
+        });
+    }
+
+    /// Runs the tester against the repository, returning whether it passed.
+    async fn execute(&self, ctx: &Context, run: &TestRun) -> Result<bool> {
+        let (config, db) = (&ctx.config, &ctx.database);
+        info!("Running tests of {} for repository: {} - {}", run.name, run.course, run.repo);
+
+        // Clone the repository into a fresh workspace
+        let workspace = tempfile::tempdir().map_err(RunnerError::Workspace)?;
+        let repo_dir = workspace.path().join("repo");
+        let results_dir = workspace.path().join("results");
+        for dir in [&repo_dir, &results_dir] {
+            fs::create_dir(dir).await.map_err(RunnerError::Workspace)?;
+        }
+
+        let url = ctx.endpoints.clone_url(repo_org(config, &run.repo), &run.repo);
+        let url =
+            url::authenticate(&url, &config.git_server_username, &config.git_server_password)?;
+        git::clone(&repo_dir, &url, &config.default_branch).await?;
+        if !run.repo_commit.is_empty() {
+            git::reset(&repo_dir, &run.repo_commit).await?;
+        }
+
+        // Test cases of all stages up to the graded one, with the limits of
+        // its resource profile
+        let stages = StageRepository::find_stages_until(db, &run.course, &run.stage).await?;
+        let slugs: Vec<&str> = stages.iter().map(|stage| stage.slug.as_str()).collect();
+        let graded = stages.iter().find(|s| s.slug == run.stage);
+        let settings = SettingsService::resolve_by_slug(ctx, &run.course, graded).await?;
+        let profile = settings.resources;
+
+        let resources = config.pod_resources(profile);
+        let (Some(nano_cpus), Some(memory)) = (resources.nano_cpus(), resources.memory_bytes())
+        else {
+            return Err(RunnerError::Resources(profile).into());
+        };
+
+        let image = with_default_tag(&settings.tester_image);
+        self.pull(&image).await?;
+
+        let body = ContainerCreateBody {
+            image: Some(image),
+            cmd: Some(vec![format!("/app/{}-tester", run.course)]),
+            env: Some(vec![
+                format!("STACKCLASS_REPOSITORY_DIR={REPOSITORY_DIR}"),
+                format!("STACKCLASS_TEST_CASES_JSON={}", build_test_cases_json(&slugs)),
+                format!("STACKCLASS_RESULTS_PATH={RESULTS_DIR}/{RESULTS_FILE}"),
+            ]),
+            working_dir: Some(REPOSITORY_DIR.to_string()),
+            network_disabled: Some(true),
+            host_config: Some(HostConfig {
+                binds: Some(vec![
+                    format!("{}:{REPOSITORY_DIR}", repo_dir.display()),
+                    format!("{}:{RESULTS_DIR}", results_dir.display()),
+                ]),
+                network_mode: Some("none".to_string()),
+                nano_cpus: Some(nano_cpus),
+                memory: Some(memory),
+                memory_swap: Some(memory),
+                pids_limit: Some(PIDS_LIMIT),
+                cap_drop: Some(vec!["ALL".to_string()]),
+                security_opt: Some(vec!["no-new-privileges".to_string()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        // Make sure the container is removed, whatever the outcome
+        let name = format!("stackclass-{}", run.name);
+        let options = CreateContainerOptions { name: Some(name.clone()), ..Default::default() };
+        self.docker.create_container(Some(options), body).await.map_err(RunnerError::from)?;
+        let exit_code = self.run(&name).await;
+
+        let options = RemoveContainerOptions { force: true, ..Default::default() };
+        if let Err(e) = self.docker.remove_container(&name, Some(options)).await {
+            warn!("Failed to remove container {}: {}", name, e);
+        }
+
+        match exit_code? {
+            Some(0) => Ok(read_results(&results_dir).await),
+            Some(code) => {
+                info!("Tester of {} exited with code {}", run.name, code);
+                Ok(false)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Pulls the image, unless it is present already.
+    async fn pull(&self, image: &str) -> Result<(), RunnerError> {
+        if self.docker.inspect_image(image).await.is_ok() {
+            return Ok(());
+        }
+
+        debug!("Pulling tester image: {}", image);
+        let options =
+            CreateImageOptions { from_image: Some(image.to_string()), ..Default::default() };
+        self.docker.create_image(Some(options), None, None).try_collect::<Vec<_>>().await?;
+        Ok(())
+    }
+
+    /// Starts the container and waits for it to exit, returning its exit
+    /// code, or `None` if it was stopped for running too long.
+    async fn run(&self, name: &str) -> Result<Option<i64>, RunnerError> {
+        self.docker.start_container(name, None::<StartContainerOptions>).await?;
+
+        let mut wait = self.docker.wait_container(name, None::<WaitContainerOptions>);
+        let exit_code = match time::timeout(self.timeout, wait.next()).await {
+            Ok(Some(Ok(response))) => Some(response.status_code),
+            Ok(Some(Err(DockerError::DockerContainerWaitError { code, .. }))) => Some(code),
+            Ok(Some(Err(e))) => return Err(e.into()),
+            Ok(None) => None,
+            Err(_) => {
+                warn!("Container {} ran longer than {:?}, stopping it", name, self.timeout);
+                self.docker.kill_container(name, None::<KillContainerOptions>).await?;
+                None
+            }
+        };
+
+        // Keep the output of the tester around for troubleshooting
+        let options = LogsOptions { stdout: true, stderr: true, ..Default::default() };
+        let mut logs = self.docker.logs(name, Some(options));
+        while let Some(Ok(output)) = logs.next().await {
+            debug!("[{}] {}", name, output.to_string().trim_end());
+        }
+
+        Ok(exit_code)
+    }
+}
+
+/// Whether the results written by the tester report a pass, missing or
+/// unreadable results count as a failure.
+async fn read_results(dir: &Path) -> bool {
+    let results = match fs::read(dir.join(RESULTS_FILE)).await {
+        Ok(results) => results,
+        Err(e) => {
+            warn!("Tester wrote no results: {}", e);
+            return false;
+        }
+    };
+
+    match serde_json::from_slice::<TestResults>(&results) {
+        Ok(results) => results.passed,
+        Err(e) => {
+            warn!("Tester wrote invalid results: {}", e);
+            false
+        }
+    }
+}
+
+/// The image reference, pulled at its latest tag unless it is pinned to a
+/// tag or a digest.
+fn with_default_tag(image: &str) -> String {
+    let name = image.rsplit('/').next().unwrap_or(image);
+    match name.contains(':') || name.contains('@') {
+        true => image.to_string(),
+        false => format!("{image}:latest"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_default_tag() {
+        let cases = [
+            ("ghcr.io/stackclass/redis-tester", "ghcr.io/stackclass/redis-tester:latest"),
+            ("ghcr.io/stackclass/redis-tester:v2", "ghcr.io/stackclass/redis-tester:v2"),
+            ("registry.local:5000/redis-tester", "registry.local:5000/redis-tester:latest"),
+            ("redis-tester@sha256:abc", "redis-tester@sha256:abc"),
+        ];
+        for (image, expected) in cases {
+            assert_eq!(with_default_tag(image), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_results() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!read_results(dir.path()).await);
+
+        let cases = [(r#"{"passed":true}"#, true), (r#"{"passed":false}"#, false), ("{", false)];
+        for (results, passed) in cases {
+            std::fs::write(dir.path().join(RESULTS_FILE), results).unwrap();
+            assert_eq!(read_results(dir.path()).await, passed);
+        }
+    }
+}
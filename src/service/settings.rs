@@ -0,0 +1,141 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    context::Context,
+    errors::{ApiError, Result},
+    model::{AuditLogModel, COURSE_DEFAULTS_SETTING, CourseModel, StageModel},
+    repository::{AuditRepository, CourseRepository, SettingRepository},
+    request::CourseSettingsRequest,
+    response::{CourseSettingsResponse, EffectiveSettingsResponse},
+    schema::{CourseSettings, EffectiveSettings, resolve_settings},
+};
+
+/// Service resolving the settings courses are graded with.
+///
+/// A setting is taken from the overrides admins set on the course, then from
+/// what `stage.yml` and `course.yml` declare, then from the instance
+/// defaults. Resolution happens on every use, so a change of the defaults
+/// applies without syncing courses, within the settings cache TTL.
+pub struct SettingsService;
+
+impl SettingsService {
+    /// The instance defaults of course settings.
+    pub async fn defaults(ctx: &Context) -> Result<CourseSettings> {
+        let defaults = ctx.settings.get(&ctx.database, COURSE_DEFAULTS_SETTING).await?;
+        Ok(defaults.unwrap_or_default())
+    }
+
+    /// The settings the course, or one of its stages, is graded with.
+    pub async fn resolve(
+        ctx: &Context,
+        course: &CourseModel,
+        stage: Option<&StageModel>,
+    ) -> Result<EffectiveSettings> {
+        let defaults = Self::defaults(ctx).await?;
+        let values = match stage {
+            Some(stage) => stage.settings().or(course.settings()),
+            None => course.settings(),
+        };
+
+        Ok(resolve_settings(&course.slug, &defaults, &values, &course.overrides()))
+    }
+
+    /// The settings the course with the given slug, or one of its stages, is
+    /// graded with.
+    pub async fn resolve_by_slug(
+        ctx: &Context,
+        slug: &str,
+        stage: Option<&StageModel>,
+    ) -> Result<EffectiveSettings> {
+        let course = CourseRepository::get_by_slug(&ctx.database, slug).await?;
+        Self::resolve(ctx, &course, stage).await
+    }
+
+    /// The settings of the course along with the layers they are merged from.
+    pub async fn find_effective(ctx: &Context, slug: &str) -> Result<EffectiveSettingsResponse> {
+        let course = CourseRepository::get_by_slug(&ctx.database, slug).await?;
+        Self::effective(ctx, &course).await
+    }
+
+    /// Replace the instance defaults of course settings.
+    pub async fn set_defaults(
+        ctx: &Context,
+        req: CourseSettingsRequest,
+        actor: &str,
+    ) -> Result<CourseSettingsResponse> {
+        let defaults = validate(req)?;
+        let value = serde_json::to_value(&defaults).map_err(ApiError::SerializationError)?;
+
+        let db = &ctx.database;
+        SettingRepository::put(db, COURSE_DEFAULTS_SETTING, &value, actor).await?;
+        ctx.settings.invalidate();
+
+        let log = AuditLogModel::new_at(
+            actor,
+            "set_course_defaults",
+            "settings/course_defaults",
+            value,
+            ctx.clock.now(),
+        );
+        AuditRepository::create(db, &log).await?;
+
+        Ok(defaults.into())
+    }
+
+    /// Replace the settings admins override on the course.
+    pub async fn set_overrides(
+        ctx: &Context,
+        slug: &str,
+        req: CourseSettingsRequest,
+        actor: &str,
+    ) -> Result<EffectiveSettingsResponse> {
+        let overrides = validate(req)?;
+        let value = serde_json::to_value(&overrides).map_err(ApiError::SerializationError)?;
+
+        let db = &ctx.database;
+        let course = CourseRepository::set_setting_overrides(db, slug, &value).await?;
+
+        let target = format!("courses/{slug}/setting-overrides");
+        let log =
+            AuditLogModel::new_at(actor, "set_setting_overrides", &target, value, ctx.clock.now());
+        AuditRepository::create(db, &log).await?;
+
+        Self::effective(ctx, &course).await
+    }
+
+    async fn effective(ctx: &Context, course: &CourseModel) -> Result<EffectiveSettingsResponse> {
+        let (values, overrides) = (course.settings(), course.overrides());
+        let defaults = Self::defaults(ctx).await?;
+        let effective = resolve_settings(&course.slug, &defaults, &values, &overrides);
+
+        Ok(EffectiveSettingsResponse::new(effective, defaults, values, overrides))
+    }
+}
+
+/// Checks the requested settings hold values a course could declare.
+fn validate(req: CourseSettingsRequest) -> Result<CourseSettings> {
+    if req.max_attempts == Some(0) {
+        return Err(ApiError::BadRequest("max_attempts must be positive".into()));
+    }
+    if req.tester_image.as_deref().is_some_and(|image| image.trim().is_empty()) {
+        return Err(ApiError::BadRequest("tester_image must not be empty".into()));
+    }
+
+    Ok(CourseSettings {
+        max_attempts: req.max_attempts,
+        resources: req.resources,
+        tester_image: req.tester_image,
+    })
+}
@@ -0,0 +1,184 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{path::PathBuf, sync::Arc};
+
+use bytes::Bytes;
+use flate2::read::GzDecoder;
+use futures::{Stream, TryStreamExt};
+use gitea_client::ClientError;
+use serde_json::json;
+use tar::{Archive, EntryType};
+
+use crate::{
+    context::Context,
+    errors::{ApiError, Result},
+    model::AuditLogModel,
+    repository::{AuditRepository, CourseRepository, StageRepository},
+    response::{SnapshotFileResponse, SnapshotResponse},
+};
+
+/// Service serving the learner repository as it was when a stage was passed
+pub struct SnapshotService;
+
+impl SnapshotService {
+    /// Stream the tarball of the learner repository at the commit which
+    /// completed the stage, returned along with that commit.
+    pub async fn download(
+        ctx: Arc<Context>,
+        user_id: &str,
+        course_slug: &str,
+        stage_slug: &str,
+        actor: &str,
+    ) -> Result<(String, impl Stream<Item = Result<Bytes, ClientError>> + use<>)> {
+        let commit = Self::completed_commit(&ctx, user_id, course_slug, stage_slug).await?;
+        let archive = Self::archive(&ctx, user_id, course_slug, &commit).await?;
+        Self::audit(&ctx, user_id, course_slug, stage_slug, actor, &commit, "download").await?;
+
+        Ok((commit, archive))
+    }
+
+    /// List the files of the learner repository at the commit which
+    /// completed the stage.
+    pub async fn tree(
+        ctx: Arc<Context>,
+        user_id: &str,
+        course_slug: &str,
+        stage_slug: &str,
+        actor: &str,
+    ) -> Result<SnapshotResponse> {
+        let commit = Self::completed_commit(&ctx, user_id, course_slug, stage_slug).await?;
+        let archive = Self::archive(&ctx, user_id, course_slug, &commit).await?;
+        let bytes: Vec<u8> = archive
+            .map_ok(|chunk| chunk.to_vec())
+            .try_concat()
+            .await
+            .map_err(ApiError::GiteaClientError)?;
+        let files = list_files(&bytes)?;
+        Self::audit(&ctx, user_id, course_slug, stage_slug, actor, &commit, "tree").await?;
+
+        Ok(SnapshotResponse { commit, files })
+    }
+
+    /// Commit recorded when the stage was completed, a stage completed before
+    /// commits were recorded has no snapshot.
+    async fn completed_commit(
+        ctx: &Context,
+        user_id: &str,
+        course_slug: &str,
+        stage_slug: &str,
+    ) -> Result<String> {
+        let user_stage =
+            StageRepository::get_user_stage(&ctx.database, user_id, course_slug, stage_slug)
+                .await?;
+        user_stage.completed_commit.ok_or(ApiError::NotFound)
+    }
+
+    /// Request the archive of the repository at the commit, which Gitea no
+    /// longer has once the repository was deleted or the commit collected.
+    async fn archive(
+        ctx: &Context,
+        user_id: &str,
+        course_slug: &str,
+        commit: &str,
+    ) -> Result<impl Stream<Item = Result<Bytes, ClientError>> + use<>> {
+        let user_course =
+            CourseRepository::get_user_course(&ctx.database, user_id, course_slug).await?;
+        let repo = user_course.id.to_string();
+
+        match ctx.git.get_archive(&ctx.config.namespace, &repo, commit).await {
+            Ok(archive) => Ok(archive),
+            Err(ClientError::NotFound) => Err(ApiError::Gone(format!(
+                "Commit {commit} is no longer available in the repository"
+            ))),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn audit(
+        ctx: &Context,
+        user_id: &str,
+        course_slug: &str,
+        stage_slug: &str,
+        actor: &str,
+        commit: &str,
+        format: &str,
+    ) -> Result<()> {
+        let target = format!("users/{user_id}/courses/{course_slug}/stages/{stage_slug}");
+        let details = json!({ "commit": commit, "format": format });
+        let log = AuditLogModel::new_at(actor, "read_snapshot", &target, details, ctx.clock.now());
+        AuditRepository::create(&ctx.database, &log).await?;
+        Ok(())
+    }
+}
+
+/// Lists the regular files of a gzipped tarball as Gitea builds it, with
+/// the directory named after the repository stripped from their path.
+fn list_files(bytes: &[u8]) -> Result<Vec<SnapshotFileResponse>> {
+    let invalid = |e: std::io::Error| ApiError::InternalError(format!("Invalid archive: {e}"));
+
+    let mut archive = Archive::new(GzDecoder::new(bytes));
+    let mut files = Vec::new();
+    for entry in archive.entries().map_err(invalid)? {
+        let entry = entry.map_err(invalid)?;
+        if entry.header().entry_type() != EntryType::Regular {
+            continue;
+        }
+
+        let path: PathBuf = entry.path().map_err(invalid)?.components().skip(1).collect();
+        files.push(SnapshotFileResponse {
+            path: path.to_string_lossy().into_owned(),
+            size: entry.size(),
+        });
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::{Compression, write::GzEncoder};
+    use tar::{Builder, Header};
+
+    use super::*;
+
+    fn tarball(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        builder.append_dir("repo", ".").unwrap();
+        for (path, data) in entries {
+            let mut header = Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, *data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn list_files_strips_the_repository_directory() {
+        let bytes = tarball(&[("repo/src/main.rs", b"fn main() {}"), ("repo/README.md", b"#")]);
+
+        let files = list_files(&bytes).unwrap();
+
+        let files: Vec<_> = files.iter().map(|f| (f.path.as_str(), f.size)).collect();
+        assert_eq!(files, vec![("README.md", 1), ("src/main.rs", 12)]);
+    }
+
+    #[test]
+    fn list_files_rejects_garbage() {
+        assert!(list_files(b"not a tarball").is_err());
+    }
+}
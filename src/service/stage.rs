@@ -14,13 +14,30 @@
 
 use std::sync::Arc;
 
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use serde_json::json;
+use tracing::warn;
+
 use crate::{
     context::Context,
     database::{Database, Transaction},
     errors::{ApiError, Result},
-    model::{UserCourseModel, UserStageModel},
-    repository::{CourseRepository, StageRepository},
-    response::{StageDetailResponse, StageResponse, UserStageResponse, UserStageStatusResponse},
+    jobs::AnalyzeCompletions,
+    model::{AuditLogModel, StageAttemptModel, StageModel, UserCourseModel, UserStageModel},
+    repository::{
+        AssetRepository, AuditRepository, CourseRepository, ProgressRepository, StageRepository,
+    },
+    response::{
+        Paginated, RetestResponse, StageAttemptResponse, StageDetailResponse, StageHintResponse,
+        StageResponse, StageSolutionResponse, StarterDiffResponse, UserStageResponse,
+        UserStageStatusResponse,
+    },
+    service::{PipelineService, RepoService, SettingsService, THROTTLED},
+    utils::{
+        pagination::{Cursor, Page},
+        status::StatusUpdate,
+    },
 };
 
 /// Service for managing stages
@@ -52,7 +69,26 @@ impl StageService {
         stage_slug: &str,
     ) -> Result<StageDetailResponse> {
         let stage = StageRepository::get_by_slug(&ctx.database, course_slug, stage_slug).await?;
-        Ok(stage.into())
+        let (previous_slug, next_slug) =
+            StageRepository::find_adjacent(&ctx.database, course_slug, stage_slug).await?;
+
+        Ok(StageDetailResponse { previous_slug, next_slug, ..stage.into() })
+    }
+
+    /// Get what the starter code of the stage changes compared to the stage
+    /// before it, as computed when the course was synced.
+    pub async fn get_starter_diff(
+        ctx: Arc<Context>,
+        course_slug: &str,
+        stage_slug: &str,
+    ) -> Result<StarterDiffResponse> {
+        let stage = StageRepository::get_by_slug(&ctx.database, course_slug, stage_slug).await?;
+        let changes = AssetRepository::find_starter_changes(&ctx.database, stage.id).await?;
+
+        Ok(StarterDiffResponse {
+            stage_slug: stage.slug,
+            files: changes.into_iter().map(Into::into).collect(),
+        })
     }
 
     /// Fetch user stages for the user.
@@ -72,23 +108,104 @@ impl StageService {
         course_slug: &str,
         stage_slug: &str,
     ) -> Result<UserStageResponse> {
-        let stage =
+        let user_stage =
+            StageRepository::get_user_stage(&ctx.database, user_id, course_slug, stage_slug)
+                .await?;
+        let stage = StageRepository::get_by_id(&ctx.database, user_stage.stage_id).await?;
+        let remaining_attempts = Self::remaining_attempts(&ctx, &user_stage, &stage).await?;
+        let solution_unlocked = Self::solution_unlocked(&ctx, &user_stage).await?;
+        let (previous_slug, next_slug) =
+            StageRepository::find_adjacent(&ctx.database, course_slug, stage_slug).await?;
+
+        Ok(UserStageResponse {
+            remaining_attempts,
+            solution_unlocked,
+            previous_slug,
+            next_slug,
+            ..user_stage.into()
+        })
+    }
+
+    /// Get the solution of the stage, once the user completed it or failed it
+    /// often enough.
+    pub async fn get_solution(
+        ctx: Arc<Context>,
+        user_id: &str,
+        course_slug: &str,
+        stage_slug: &str,
+    ) -> Result<StageSolutionResponse> {
+        let user_stage =
+            StageRepository::get_user_stage(&ctx.database, user_id, course_slug, stage_slug)
+                .await?;
+        if !Self::solution_unlocked(&ctx, &user_stage).await? {
+            return Err(ApiError::Forbidden("The solution of the stage is not unlocked yet".into()));
+        }
+
+        let stage = StageRepository::get_by_id(&ctx.database, user_stage.stage_id).await?;
+        let solution = stage.solution.ok_or(ApiError::NotFound)?;
+
+        Ok(StageSolutionResponse { stage_slug: stage.slug, solution })
+    }
+
+    /// Find the hints of the stage the user revealed so far.
+    pub async fn find_hints(
+        ctx: Arc<Context>,
+        user_id: &str,
+        course_slug: &str,
+        stage_slug: &str,
+    ) -> Result<Vec<StageHintResponse>> {
+        let user_stage =
+            StageRepository::get_user_stage(&ctx.database, user_id, course_slug, stage_slug)
+                .await?;
+        let hints = StageRepository::find_unlocked_hints(&ctx.database, &user_stage.id).await?;
+
+        Ok(hints.into_iter().map(Into::into).collect())
+    }
+
+    /// Reveal the next hint of the stage to the user.
+    pub async fn unlock_hint(
+        ctx: Arc<Context>,
+        user_id: &str,
+        course_slug: &str,
+        stage_slug: &str,
+    ) -> Result<StageHintResponse> {
+        let user_stage =
             StageRepository::get_user_stage(&ctx.database, user_id, course_slug, stage_slug)
                 .await?;
-        Ok(stage.into())
+
+        match StageRepository::unlock_hint(&ctx.database, &user_stage.id).await? {
+            Some(hint) => Ok(hint.into()),
+            None => Err(ApiError::BadRequest("All hints of the stage are unlocked".into())),
+        }
+    }
+
+    /// Whether the user may view the solution of the stage: once completed,
+    /// or after the configured number of failed attempts.
+    async fn solution_unlocked(ctx: &Context, user_stage: &UserStageModel) -> Result<bool> {
+        if user_stage.status == "completed" {
+            return Ok(true);
+        }
+        let Some(threshold) = ctx.config.solution_unlock_failures else {
+            return Ok(false);
+        };
+
+        let failed = StageRepository::count_failed_attempts(&ctx.database, &user_stage.id).await?;
+        Ok(failed >= i64::from(threshold))
     }
 
-    /// Mark a stage as completed for a user.
+    /// Mark a stage as completed for a user, along with the commit of their
+    /// repository which passed it, if known.
     pub async fn complete(
         ctx: Arc<Context>,
         user_id: &str,
         course_slug: &str,
         stage_slug: &str,
+        commit: Option<&str>,
     ) -> Result<UserStageResponse> {
         let db = &ctx.database;
 
         //  Fetch the user's course enrollment and current user stage.
-        let user_course = CourseRepository::get_user_course(db, user_id, course_slug).await?;
+        let mut user_course = CourseRepository::get_user_course(db, user_id, course_slug).await?;
         let mut user_stage =
             StageRepository::get_user_stage(db, user_id, course_slug, stage_slug).await?;
 
@@ -103,30 +220,150 @@ impl StageService {
             return Err(ApiError::StageOutOfOrder);
         }
 
-        // Begins a new transaction.
+        // Begins a new transaction, whose rows all share the same timestamp.
         let mut tx = ctx.database.pool().begin().await?;
+        let now = ctx.clock.now();
 
         // Mark the stage as completed.
-        user_stage = user_stage.passed().complete();
+        user_stage = user_stage.passed().complete_at(now).with_completed_commit(commit);
         let completed_stage = StageRepository::update_user_stage(&mut tx, &user_stage).await?;
 
         // Update user course and create next stage if needed.
-        Self::start_next_stage(&mut tx, db, user_course, course_slug, stage_slug).await?;
+        let user_course_id = user_course.id;
+        user_course.completed_stage_count += 1;
+        let user_course =
+            Self::start_next_stage(&mut tx, db, user_course, course_slug, stage_slug, now).await?;
+
+        // Keep the progress summary in step with the user stages.
+        ProgressRepository::refresh(&mut tx, &user_course_id).await?;
 
         // Commits this transaction.
         tx.commit().await?;
 
+        // Look for suspicious patterns while the completion is fresh.
+        ctx.jobs.trigger(AnalyzeCompletions::NAME);
+
+        // Archive the repository of a finished course when configured. The
+        // completion stands even if the git server refuses.
+        if ctx.config.archive_completed_repositories {
+            let course = CourseRepository::get_by_slug(db, course_slug).await?;
+            if user_course.completed_stage_count >= course.stage_count {
+                let (org, repo) = (&ctx.config.namespace, user_course_id.to_string());
+                if let Err(e) = RepoService::new(ctx.clone()).archive(org, &repo).await {
+                    warn!("Failed to archive the repository {repo} of a finished course: {e}");
+                }
+            }
+        }
+
         Ok(completed_stage.into())
     }
 
-    /// Update user course and create next stage if needed.
+    /// Skip the current stage of a user, if optional, and move on to the
+    /// next one. Skipped stages do not count as completed.
+    pub async fn skip(
+        ctx: Arc<Context>,
+        user_id: &str,
+        course_slug: &str,
+        stage_slug: &str,
+    ) -> Result<UserStageResponse> {
+        let db = &ctx.database;
+        let user_course = CourseRepository::get_user_course(db, user_id, course_slug).await?;
+        let user_stage =
+            StageRepository::get_user_stage(db, user_id, course_slug, stage_slug).await?;
+
+        // Only the current stage can be skipped, if its authors allow it.
+        if user_stage.status == "completed" {
+            return Err(ApiError::StageAlreadyCompleted);
+        }
+        if user_stage.status != "in_progress" {
+            return Err(ApiError::StageNotInProgress);
+        }
+        if user_course.current_stage_id != Some(user_stage.stage_id) {
+            return Err(ApiError::StageOutOfOrder);
+        }
+        if !StageRepository::get_by_id(db, user_stage.stage_id).await?.optional {
+            return Err(ApiError::StageNotOptional);
+        }
+
+        let mut tx = ctx.database.pool().begin().await?;
+        let now = ctx.clock.now();
+
+        let skipped_stage = StageRepository::update_user_stage(&mut tx, &user_stage.skip()).await?;
+        let user_course_id = user_course.id;
+        Self::start_next_stage(&mut tx, db, user_course, course_slug, stage_slug, now).await?;
+        ProgressRepository::refresh(&mut tx, &user_course_id).await?;
+
+        tx.commit().await?;
+        Ok(skipped_stage.into())
+    }
+
+    /// Run the tests of the current stage of a user again, against the
+    /// commit graded last, returning the name of the triggered PipelineRun.
+    pub async fn retest(
+        ctx: Arc<Context>,
+        user_id: &str,
+        course_slug: &str,
+        stage_slug: &str,
+    ) -> Result<RetestResponse> {
+        let db = &ctx.database;
+        let user_course = CourseRepository::get_user_course(db, user_id, course_slug).await?;
+        let user_stage =
+            StageRepository::get_user_stage(db, user_id, course_slug, stage_slug).await?;
+
+        // Only the current stage is tested, once a push was graded already
+        if user_stage.status != "in_progress" {
+            return Err(ApiError::StageNotInProgress);
+        }
+        if user_course.current_stage_id != Some(user_stage.stage_id) {
+            return Err(ApiError::StageOutOfOrder);
+        }
+        let Some(last) = StageRepository::find_latest_attempt(db, &user_stage.id).await? else {
+            return Err(ApiError::BadRequest("The stage was not tested yet".into()));
+        };
+
+        let stage = StageRepository::get_by_id(db, user_stage.stage_id).await?;
+        if Self::remaining_attempts(&ctx, &user_stage, &stage).await? == Some(0) {
+            return Err(ApiError::BadRequest("The attempt budget of the stage is exhausted".into()));
+        }
+
+        // Never run the tests of a repository twice at the same time, nor
+        // more runs of a user than allowed
+        let repo = user_course.id.to_string();
+        let pipeline = PipelineService::new(ctx.clone());
+        if let Some(run) = pipeline.find_active(&repo).await? {
+            return Err(ApiError::Conflict(format!("Tests are already running in {run}")));
+        }
+        if pipeline.is_throttled(&user_stage.id).await? {
+            return Err(ApiError::TooManyRequests("Too many tests running, try again later".into()));
+        }
+
+        let definition = CourseRepository::get_by_slug(db, course_slug).await?;
+        let settings = SettingsService::resolve(&ctx, &definition, Some(&stage)).await?;
+        let (commit, hash) = (&definition.commit_sha, &stage.content_hash);
+        let name = pipeline
+            .trigger(&repo, course_slug, stage_slug, commit, hash, &last.repo_commit)
+            .await?;
+
+        let attempt = StageAttemptModel::new_at(user_stage.id, hash, ctx.clock.now())
+            .with_course_commit(commit)
+            .with_repo_commit(&last.repo_commit)
+            .with_tester_image(&settings.tester_image)
+            .with_pipeline_run(&name);
+        StageRepository::create_attempt(db, &attempt).await?;
+
+        Ok(RetestResponse { pipeline_run: name })
+    }
+
+    /// Update user course and create next stage if needed, returning the
+    /// updated user course.
     async fn start_next_stage(
         tx: &mut Transaction<'_>,
         db: &Database,
         user_course: UserCourseModel,
         course_slug: &str,
         stage_slug: &str,
-    ) -> Result<()> {
+        now: DateTime<Utc>,
+    ) -> Result<UserCourseModel> {
         let mut updated_user_course = user_course;
 
         // Find the next stage (if any) by current stage slug.
@@ -134,15 +371,16 @@ impl StageService {
 
         // If there is a next stage, create a new instance for it
         if let Some(next_stage) = next_stage {
-            let user_stage = UserStageModel::new(updated_user_course.id, next_stage.id);
-            StageRepository::create_user_stage(tx, &user_stage).await?;
+            let user_stage = UserStageModel::new_at(updated_user_course.id, next_stage.id, now);
+            StageRepository::create_user_stage(tx, &user_stage)
+                .await
+                .map_err(ApiError::on_duplicate(ApiError::StageRecordExists))?;
             updated_user_course.current_stage_id = Some(next_stage.id);
         }
 
-        updated_user_course.completed_stage_count += 1;
         CourseRepository::update_user_course(tx, &updated_user_course).await?;
 
-        Ok(())
+        Ok(updated_user_course)
     }
 
     /// Get the current status of a stage for the user.
@@ -157,6 +395,184 @@ impl StageService {
             StageRepository::get_user_stage(&ctx.database, user_id, course_slug, stage_slug)
                 .await?;
 
-        Ok(UserStageStatusResponse { status: user_stage.status, test: user_stage.test })
+        let stage = StageRepository::get_by_id(&ctx.database, user_stage.stage_id).await?;
+        let remaining_attempts = Self::remaining_attempts(ctx, &user_stage, &stage).await?;
+
+        // Attempts queued during a cluster outage, or while the user had too
+        // many runs, are not graded yet
+        let queued = StageRepository::find_queued_reason(&ctx.database, &user_stage.id).await?;
+        let grading = queued.map(|reason| match reason.as_deref() {
+            Some(THROTTLED) => "grading_throttled".to_string(),
+            _ => "grading_delayed".to_string(),
+        });
+
+        // Exams count down to the deadline of the user
+        let user_course =
+            CourseRepository::get_user_course(&ctx.database, user_id, course_slug).await?;
+
+        // The outcome of the latest attempt explains a failing test
+        let latest = StageRepository::find_latest_attempt(&ctx.database, &user_stage.id).await?;
+
+        Ok(UserStageStatusResponse {
+            status: user_stage.status,
+            test: user_stage.test,
+            remaining_attempts,
+            grading,
+            deadline: user_course.deadline(),
+            remaining_seconds: user_course.remaining_secs,
+            pipeline_status: latest.as_ref().and_then(|a| a.pipeline_status()).map(Into::into),
+            latest_attempt: latest.map(Into::into),
+        })
+    }
+
+    /// Stream the status of a stage for the user, once right away and again
+    /// on every change until the stage is completed. Clients which have
+    /// already seen the version `last_seen` are only sent newer statuses.
+    pub async fn stream_user_stage_status(
+        ctx: Arc<Context>,
+        user_id: &str,
+        course_slug: &str,
+        stage_slug: &str,
+        last_seen: Option<i64>,
+    ) -> Result<impl Stream<Item = StatusUpdate<UserStageStatusResponse>> + use<>> {
+        let user_stage =
+            StageRepository::get_user_stage(&ctx.database, user_id, course_slug, stage_slug)
+                .await?;
+
+        let (id, user_id, course_slug, stage_slug) = (
+            user_stage.user_course_id,
+            user_id.to_string(),
+            course_slug.to_string(),
+            stage_slug.to_string(),
+        );
+        let updates = ctx.status.clone();
+        let fetch = move || {
+            let (ctx, user_id) = (ctx.clone(), user_id.clone());
+            let (course_slug, stage_slug) = (course_slug.clone(), stage_slug.clone());
+            async move {
+                // Read before the status, so it is never newer than the status
+                let version = CourseRepository::get_status_version(&ctx.database, &id).await?;
+                let status =
+                    Self::get_user_stage_status(&ctx, &user_id, &course_slug, &stage_slug).await?;
+                Ok(StatusUpdate { version, status })
+            }
+        };
+
+        let done = |status: &UserStageStatusResponse| status.status == "completed";
+        Ok(updates.stream(id, last_seen, fetch, done))
+    }
+
+    /// Find a page of the graded attempts made in a course, newest first,
+    /// optionally filtered by user, stage and stage content hash.
+    pub async fn find_attempts(
+        ctx: Arc<Context>,
+        course_slug: &str,
+        user_id: Option<&str>,
+        stage_slug: Option<&str>,
+        content_hash: Option<&str>,
+        page: &Page,
+    ) -> Result<Paginated<StageAttemptResponse>> {
+        let db = &ctx.database;
+        CourseRepository::get_by_slug(db, course_slug).await?;
+
+        let attempts = StageRepository::find_attempts(
+            db,
+            course_slug,
+            user_id,
+            stage_slug,
+            content_hash,
+            page,
+        )
+        .await?;
+
+        let cursor = |attempt: &StageAttemptModel| Cursor::new(attempt.created_at, attempt.seq);
+        Ok(Paginated::from_rows(attempts, page, cursor, ctx.clock.now(), &ctx.config.auth_secret)?)
+    }
+
+    /// Number of graded attempts left for the user stage, or `None` if the
+    /// stage does not limit them.
+    pub async fn remaining_attempts(
+        ctx: &Context,
+        user_stage: &UserStageModel,
+        stage: &StageModel,
+    ) -> Result<Option<i32>> {
+        let course = CourseRepository::get_by_id(&ctx.database, stage.course_id).await?;
+        let settings = SettingsService::resolve(ctx, &course, Some(stage)).await?;
+        let Some(limit) = settings.max_attempts.map(|n| n as i32) else {
+            return Ok(None);
+        };
+
+        // Only attempts against the current stage content count, unless
+        // budgets are configured to survive course syncs
+        let content_hash =
+            ctx.config.reset_attempts_on_change.then_some(stage.content_hash.as_str());
+        let used =
+            StageRepository::count_attempts(&ctx.database, &user_stage.id, content_hash).await?;
+
+        Ok(Some(remaining_attempts(limit, user_stage.granted_attempts, used)))
+    }
+
+    /// Grant extra graded attempts for a stage to a user.
+    pub async fn grant_attempts(
+        ctx: Arc<Context>,
+        user_id: &str,
+        course_slug: &str,
+        stage_slug: &str,
+        count: i32,
+    ) -> Result<UserStageResponse> {
+        if count <= 0 {
+            return Err(ApiError::BadRequest("count must be positive".into()));
+        }
+
+        let db = &ctx.database;
+        let user_stage =
+            StageRepository::get_user_stage(db, user_id, course_slug, stage_slug).await?;
+
+        // Only the granted attempts change, under a lock, so that concurrent
+        // grants and gradings are not lost
+        let mut tx = db.pool().begin().await?;
+        let granted = StageRepository::lock_granted_attempts(&mut tx, &user_stage.id).await?;
+        let granted = granted
+            .checked_add(count)
+            .ok_or_else(|| ApiError::BadRequest("too many attempts granted".into()))?;
+        let user_stage =
+            StageRepository::set_granted_attempts(&mut tx, &user_stage.id, granted).await?;
+
+        let target = format!("users/{user_id}/courses/{course_slug}/stages/{stage_slug}");
+        let log = AuditLogModel::new_at(
+            "admin",
+            "grant_attempts",
+            &target,
+            json!({ "count": count }),
+            ctx.clock.now(),
+        );
+        AuditRepository::create_in(&mut tx, &log).await?;
+        tx.commit().await?;
+
+        let stage = StageRepository::get_by_id(db, user_stage.stage_id).await?;
+        let remaining_attempts = Self::remaining_attempts(&ctx, &user_stage, &stage).await?;
+
+        Ok(UserStageResponse { remaining_attempts, ..user_stage.into() })
+    }
+}
+
+/// Computes the attempts left from the stage limit, the extra attempts
+/// granted and the attempts already used.
+fn remaining_attempts(limit: i32, granted: i32, used: i64) -> i32 {
+    (i64::from(limit) + i64::from(granted) - used).clamp(0, i64::from(i32::MAX)) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remaining_attempts() {
+        assert_eq!(remaining_attempts(20, 0, 0), 20);
+        assert_eq!(remaining_attempts(20, 0, 19), 1);
+        assert_eq!(remaining_attempts(20, 0, 20), 0);
+        assert_eq!(remaining_attempts(20, 0, 25), 0);
+        assert_eq!(remaining_attempts(20, 5, 20), 5);
+        assert_eq!(remaining_attempts(0, 0, 0), 0);
     }
 }
@@ -22,14 +22,23 @@ use octocrab::{
     params::repos::{Commitish, Reference},
 };
 use std::{
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
     sync::Arc,
 };
 use tar::Archive;
+use tempfile::TempDir;
 use thiserror::Error;
 use tokio::fs;
 use tracing::{debug, info};
 
+use crate::utils::{
+    git::{self, GitError},
+    url,
+};
+
+/// Base URL of the repositories on GitHub.
+const GITHUB_URL: &str = "https://github.com/";
+
 type Result<T, E = StorageError> = std::result::Result<T, E>;
 
 #[derive(Error, Debug)]
@@ -46,6 +55,9 @@ pub enum StorageError {
     #[error("Invalid reference type")]
     InvalidReferenceType,
 
+    #[error("Unknown reference: {0}")]
+    UnknownReference(String),
+
     #[error("No default branch found")]
     NoDefaultBranch,
 
@@ -61,17 +73,33 @@ pub enum StorageError {
     #[error("Failed to unpack tarball")]
     UnpackTarball(#[source] std::io::Error),
 
-    #[error("Failed to copy files: {0}")]
-    CopyFiles(String),
-
     #[error("Template directory is missing")]
     MissingTemplate,
+
+    #[error("Invalid path in repository: {0}")]
+    InvalidPath(String),
+
+    #[error("Failed to read file")]
+    ReadFile(#[source] std::io::Error),
+
+    #[error("Failed to sync workspace")]
+    SyncWorkspace(#[source] std::io::Error),
+
+    #[error("Invalid ignore file in template")]
+    InvalidIgnoreFile(#[source] ignore::Error),
+
+    #[error("Invalid clone URL")]
+    CloneUrl(#[source] ::url::ParseError),
+
+    #[error("Failed to clone repository")]
+    CloneRepo(#[source] GitError),
 }
 
 // Service for downloading and caching GitHub repositories
 pub struct StorageService {
     cache_dir: PathBuf,      // Base directory for storing cached repositories
     octocrab: Arc<Octocrab>, // GitHub API client
+    token: Option<String>,   // GitHub token, also used by clones
 }
 
 impl StorageService {
@@ -87,12 +115,12 @@ impl StorageService {
             None => octocrab::instance(),
         };
 
-        Ok(Self { cache_dir: cache_dir.to_path_buf(), octocrab })
+        Ok(Self { cache_dir: cache_dir.to_path_buf(), octocrab, token: github_token.clone() })
     }
 
-    /// Download and store GitHub repository,
-    /// and return the path of the cached directory.
-    pub async fn fetch(&self, url: &str) -> Result<PathBuf> {
+    /// Download and store GitHub repository, and return the path of the
+    /// cached directory along with the commit SHA it was taken at.
+    pub async fn fetch(&self, url: &str) -> Result<(PathBuf, String)> {
         let repo = GHRepo::from_url(url).map_err(StorageError::InvalidRepoUrl)?;
         let api = self.octocrab.repos(repo.owner(), repo.name());
 
@@ -116,6 +144,69 @@ impl StorageService {
         info!("Downloading repository {}", repo);
         let dir = self.download(repo.owner(), repo.name(), &reference).await?;
 
+        Ok((dir, reference))
+    }
+
+    /// Download and store GitHub repository at a branch, tag or commit SHA,
+    /// and return the path of the cached directory along with the commit SHA
+    /// the reference resolves to.
+    pub async fn fetch_ref(&self, url: &str, reference: &str) -> Result<(PathBuf, String)> {
+        let repo = GHRepo::from_url(url).map_err(StorageError::InvalidRepoUrl)?;
+
+        // The commits endpoint resolves branches, tags and SHAs alike
+        info!("Resolving reference {} of repository {}", reference, repo);
+        let commit =
+            self.octocrab.commits(repo.owner(), repo.name()).get(reference).await.map_err(|e| {
+                match e {
+                    octocrab::Error::GitHub { source, .. }
+                        if matches!(source.status_code.as_u16(), 404 | 422) =>
+                    {
+                        StorageError::UnknownReference(reference.to_string())
+                    }
+                    e => StorageError::FetchRepoInfo(e),
+                }
+            })?;
+
+        info!("Downloading repository {} at {}", repo, commit.sha);
+        let dir = self.download(repo.owner(), repo.name(), &commit.sha).await?;
+
+        Ok((dir, commit.sha))
+    }
+
+    /// Return the cached directory of the repository at the given commit,
+    /// downloading it again if it has been removed from the cache.
+    pub async fn fetch_commit(&self, url: &str, sha: &str) -> Result<PathBuf> {
+        if sha.len() < 7 || !sha.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(StorageError::InvalidReferenceType);
+        }
+
+        let repo = GHRepo::from_url(url).map_err(StorageError::InvalidRepoUrl)?;
+        self.download(repo.owner(), repo.name(), sha).await
+    }
+
+    /// Clone the repository along with its submodules at the given commit
+    /// into a temporary directory of the cache, and drop its origin so the
+    /// clone keeps no credentials. Submodules on GitHub are fetched with
+    /// the token too.
+    pub async fn clone(&self, url: &str, sha: &str) -> Result<TempDir> {
+        fs::create_dir_all(&self.cache_dir).await.map_err(StorageError::CreateDir)?;
+        let dir = tempfile::tempdir_in(&self.cache_dir).map_err(StorageError::CreateDir)?;
+
+        let rewrites = match &self.token {
+            Some(token) => {
+                let authenticated = url::authenticate(GITHUB_URL, "x-access-token", token)
+                    .map_err(StorageError::CloneUrl)?;
+                vec![(GITHUB_URL.to_string(), authenticated)]
+            }
+            None => Vec::new(),
+        };
+
+        info!("Cloning repository {} with its submodules", url);
+        git::clone_recursive(dir.path(), url, sha, &rewrites)
+            .await
+            .map_err(StorageError::CloneRepo)?;
+        git::remove_remote(dir.path(), "origin").await.map_err(StorageError::CloneRepo)?;
+
         Ok(dir)
     }
 
@@ -165,3 +256,71 @@ impl StorageService {
         Ok(())
     }
 }
+
+/// Resolve a path relative to a cached repository, refusing anything that
+/// would end up outside of it, be it through `..` or a symbolic link.
+pub fn resolve(root: &Path, relative: &str) -> Result<PathBuf> {
+    let invalid = || StorageError::InvalidPath(relative.to_string());
+
+    let relative = Path::new(relative);
+    if relative.as_os_str().is_empty() ||
+        !relative.components().all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Err(invalid());
+    }
+
+    let root = root.canonicalize().map_err(StorageError::ReadFile)?;
+    let path = root.join(relative).canonicalize().map_err(StorageError::ReadFile)?;
+    if !path.starts_with(&root) {
+        return Err(invalid());
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("course/stages/01-ping")).unwrap();
+        std::fs::write(dir.path().join("course/stages/01-ping/stage.yml"), "slug: ping").unwrap();
+        std::fs::write(dir.path().join("secret"), "secret").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_resolve_file_inside_root() {
+        let dir = fixture();
+        let root = dir.path().join("course");
+
+        let path = resolve(&root, "stages/01-ping/stage.yml").unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "slug: ping");
+    }
+
+    #[test]
+    fn test_resolve_rejects_traversal() {
+        let dir = fixture();
+        let root = dir.path().join("course");
+
+        for relative in ["../secret", "stages/../../secret", "/etc/passwd", "./course.yml", ""] {
+            assert!(
+                matches!(resolve(&root, relative), Err(StorageError::InvalidPath(_))),
+                "{relative}"
+            );
+        }
+        assert!(matches!(resolve(&root, "course.yml"), Err(StorageError::ReadFile(_))));
+    }
+
+    #[test]
+    fn test_resolve_rejects_symlink_escape() {
+        let dir = fixture();
+        let root = dir.path().join("course");
+        std::os::unix::fs::symlink(dir.path().join("secret"), root.join("stages/leak")).unwrap();
+        std::os::unix::fs::symlink("01-ping", root.join("stages/alias")).unwrap();
+
+        assert!(matches!(resolve(&root, "stages/leak"), Err(StorageError::InvalidPath(_))));
+        assert!(resolve(&root, "stages/alias/stage.yml").is_ok());
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use serde_json::json;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    context::Context,
+    errors::{ApiError, Result},
+    model::{ApiTokenModel, AuditLogModel},
+    repository::{ApiTokenRepository, AuditRepository, CourseRepository},
+    request::CreateApiTokenRequest,
+    response::ApiTokenResponse,
+    utils::crypto,
+};
+
+/// Prefix of the secrets of API tokens, telling them apart in leaked logs.
+const TOKEN_PREFIX: &str = "sct_";
+
+/// Service for the API tokens of integrations
+pub struct ApiTokenService;
+
+impl ApiTokenService {
+    /// Create a token reading the given courses. The secret is part of the
+    /// response only here, just its hash is stored.
+    pub async fn create(
+        ctx: Arc<Context>,
+        req: &CreateApiTokenRequest,
+        actor: &str,
+    ) -> Result<ApiTokenResponse> {
+        let name = req.name.trim();
+        if name.is_empty() {
+            return Err(ApiError::BadRequest("The token needs a name".into()));
+        }
+        if req.courses.is_empty() || req.capabilities.is_empty() {
+            return Err(ApiError::BadRequest("The token needs courses and capabilities".into()));
+        }
+
+        let mut courses = req.courses.clone();
+        courses.sort();
+        courses.dedup();
+
+        let db = &ctx.database;
+        let mut course_ids = Vec::with_capacity(courses.len());
+        for slug in &courses {
+            match CourseRepository::get_by_slug(db, slug).await {
+                Ok(course) => course_ids.push(course.id),
+                Err(sqlx::Error::RowNotFound) => {
+                    return Err(ApiError::BadRequest(format!("Unknown course: {slug}")));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut capabilities = req.capabilities.clone();
+        capabilities.sort();
+        capabilities.dedup();
+        let capabilities = capabilities.iter().map(|c| c.as_str().to_string()).collect();
+
+        let secret = format!("{TOKEN_PREFIX}{}", crypto::random_hex(32)?);
+        let token = ApiTokenModel::new_at(
+            name,
+            &crypto::sha256_hex(&secret),
+            courses,
+            capabilities,
+            actor,
+            ctx.clock.now(),
+        );
+
+        let mut tx = db.pool().begin().await?;
+        ApiTokenRepository::create(&mut tx, &token, &course_ids).await?;
+        tx.commit().await?;
+
+        let details = json!({ "courses": token.courses, "capabilities": token.capabilities });
+        let log = AuditLogModel::new_at(
+            actor,
+            "create_api_token",
+            &target(token.id),
+            details,
+            ctx.clock.now(),
+        );
+        AuditRepository::create(db, &log).await?;
+
+        info!("Created API token {} for courses {:?}", token.id, token.courses);
+        Ok(ApiTokenResponse { token: Some(secret), ..token.into() })
+    }
+
+    /// Find all tokens, newest first.
+    pub async fn find(ctx: Arc<Context>) -> Result<Vec<ApiTokenResponse>> {
+        let tokens = ApiTokenRepository::find(&ctx.database).await?;
+        Ok(tokens.into_iter().map(Into::into).collect())
+    }
+
+    /// Revoke a token, its next request is rejected.
+    pub async fn revoke(ctx: Arc<Context>, id: Uuid, actor: &str) -> Result<()> {
+        let db = &ctx.database;
+        if !ApiTokenRepository::revoke(db, id).await? {
+            return Err(ApiError::NotFound);
+        }
+
+        let log = AuditLogModel::new_at(
+            actor,
+            "revoke_api_token",
+            &target(id),
+            json!({}),
+            ctx.clock.now(),
+        );
+        AuditRepository::create(db, &log).await?;
+        Ok(())
+    }
+
+    /// Authenticate a request by the secret of an active token, recording the
+    /// use and auditing the request under the token id.
+    ///
+    /// Tokens are looked up on every request, so revocations apply at once.
+    pub async fn authenticate(
+        ctx: &Context,
+        secret: &str,
+        method: &str,
+        path: &str,
+    ) -> Result<ApiTokenModel> {
+        let db = &ctx.database;
+        let Some(token) =
+            ApiTokenRepository::get_active_by_hash(db, &crypto::sha256_hex(secret)).await?
+        else {
+            return Err(ApiError::Unauthorized("Invalid API token".into()));
+        };
+        ApiTokenRepository::touch(db, token.id).await?;
+
+        let actor = actor(token.id);
+        let log = AuditLogModel::new_at(
+            &actor,
+            "api_token_request",
+            path,
+            json!({ "method": method }),
+            ctx.clock.now(),
+        );
+        AuditRepository::create(db, &log).await?;
+
+        Ok(token)
+    }
+}
+
+/// Audit trail identifier of requests made with a token.
+fn actor(id: Uuid) -> String {
+    format!("api_token:{id}")
+}
+
+/// Audit trail target of a token.
+fn target(id: Uuid) -> String {
+    format!("api-tokens/{id}")
+}
@@ -0,0 +1,299 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use chrono::Duration;
+use serde::Deserialize;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+#[cfg(feature = "local-runner")]
+use crate::service::TestRun;
+use crate::{
+    context::Context,
+    errors::{ApiError, Result},
+    model::{TRIAL_REPO_PREFIX, TrialModel},
+    repository::{CourseRepository, StageRepository, TrialRepository},
+    request::{ConvertTrialRequest, CreateTrialRequest, CreateUserCourseRequest},
+    response::{TrialResponse, TrialStatusResponse, UserCourseResponse},
+    service::{CourseService, PipelineService, RepoService},
+    utils::crypto,
+};
+
+/// Number of expired trials reaped per batch.
+const REAP_BATCH_SIZE: i64 = 100;
+
+/// Response of a captcha verification URL.
+#[derive(Debug, Deserialize)]
+struct CaptchaVerification {
+    success: bool,
+}
+
+/// Service for trials of the first stage of a course, which visitors can
+/// start without signing up.
+///
+/// A trial gets its own repository in the trial organization, generated from
+/// the course template. Pushes to it are graded like those of learners, but
+/// no user course is involved: results are recorded on the trial itself.
+/// Trial repositories are deleted once they expire, unless the visitor signs
+/// up and converts the trial into an enrollment first.
+pub struct TrialService;
+
+impl TrialService {
+    /// Start a trial of the first stage of a course for the given client
+    /// address.
+    pub async fn create(
+        ctx: Arc<Context>,
+        address: &str,
+        req: &CreateTrialRequest,
+    ) -> Result<TrialResponse> {
+        Self::verify_captcha(&ctx, &req.captcha_token, address).await?;
+
+        // Unreleased courses can not be tried
+        let db = &ctx.database;
+        let course = CourseRepository::get_by_slug(db, &req.course_slug).await?;
+        if course.release_status == "alpha" {
+            return Err(ApiError::NotFound);
+        }
+        let stage = StageRepository::first(db, &course.slug).await?.ok_or(ApiError::NotFound)?;
+
+        // Admit the trial while holding the lock, so that concurrent requests
+        // can not exceed the limits together
+        let config = &ctx.config;
+        let client_key = Self::client_key(&ctx, address)?;
+        let mut tx = db.pool().begin().await?;
+        TrialRepository::lock(&mut tx).await?;
+
+        let now = ctx.clock.now();
+        let since = now - Duration::seconds(config.trial_rate_window);
+        if TrialRepository::count_by_client(&mut tx, &client_key, since).await? >=
+            config.trial_rate_limit
+        {
+            return Err(ApiError::TooManyRequests("Too many trials, try again later".into()));
+        }
+        if TrialRepository::count_active(&mut tx, now).await? >= config.max_active_trials {
+            return Err(ApiError::TooManyRequests("No trials available, try again later".into()));
+        }
+
+        let ttl = Duration::seconds(config.trial_ttl);
+        let trial = TrialModel::new_at(course.id, stage.id, &client_key, ttl, now);
+        let trial = TrialRepository::create(&mut tx, &trial).await?;
+        tx.commit().await?;
+
+        // Generate the trial repository from the course template, a failed
+        // trial is left to the cleanup job
+        let repo = RepoService::new(ctx.clone());
+        if let Err(e) = repo.generate_in(&config.trial_org, &course.slug, &trial.repo()).await {
+            TrialRepository::cut_short(db, &trial.id, ctx.clock.now()).await?;
+            return Err(e);
+        }
+        info!("Started trial {} of course {}", trial.id, course.slug);
+
+        Ok(TrialResponse {
+            id: trial.id,
+            token: Self::token(&ctx, &trial.id)?,
+            repository: ctx.endpoints.trial_repo_url(&trial.id),
+            course_slug: trial.course_slug,
+            stage_slug: trial.stage_slug,
+            expires_at: trial.expires_at,
+        })
+    }
+
+    /// Get the status of a trial and the result of its latest push.
+    pub async fn get_status(
+        ctx: Arc<Context>,
+        id: &Uuid,
+        token: &str,
+    ) -> Result<TrialStatusResponse> {
+        Self::authorize(&ctx, id, token)?;
+        let trial = TrialRepository::get_by_id(&ctx.database, id).await?;
+        Ok(TrialStatusResponse::new(trial, ctx.clock.now()))
+    }
+
+    /// Convert an active trial into an enrollment of the user, carrying the
+    /// trial repository over into the new repository of the learner.
+    pub async fn convert(
+        ctx: Arc<Context>,
+        user_id: &str,
+        id: &Uuid,
+        req: &ConvertTrialRequest,
+    ) -> Result<UserCourseResponse> {
+        Self::authorize(&ctx, id, &req.token)?;
+        let db = &ctx.database;
+        let trial = TrialRepository::get_by_id(db, id).await?;
+        if !trial.is_active(ctx.clock.now()) {
+            return Err(ApiError::BadRequest("Trial is no longer active".into()));
+        }
+
+        let enrollment = CreateUserCourseRequest {
+            course_slug: trial.course_slug.clone(),
+            proficiency: req.proficiency.clone(),
+            cadence: req.cadence.clone(),
+            accountability: req.accountability,
+            cohort: None,
+            cohort_deadline: None,
+            language: None,
+        };
+        CourseService::create_user_course(ctx.clone(), user_id, &enrollment).await?;
+        let user_course =
+            CourseRepository::get_user_course(db, user_id, &trial.course_slug).await?;
+
+        // The push into the new repository activates the course, the same
+        // way the first push of a learner does
+        let (config, repo) = (&ctx.config, RepoService::new(ctx.clone()));
+        let (trial_repo, user_repo) = (trial.repo(), user_course.id.to_string());
+        repo.transplant((&config.trial_org, &trial_repo), (&config.namespace, &user_repo)).await?;
+
+        TrialRepository::convert(db, id, &user_course.id).await?;
+        repo.delete(&config.trial_org, &trial_repo).await?;
+        info!("Converted trial {} into user course {}", id, user_course.id);
+
+        CourseService::get_user_course(ctx, user_id, &trial.course_slug).await
+    }
+
+    /// Grades a push to the repository of a trial.
+    pub(crate) async fn process(ctx: Arc<Context>, id: &Uuid) -> Result<()> {
+        let db = &ctx.database;
+        let trial = TrialRepository::get_by_id(db, id).await?;
+        if !trial.is_active(ctx.clock.now()) {
+            info!("Ignoring push to trial {} which is no longer active", id);
+            return Ok(());
+        }
+
+        let commit = CourseRepository::get_by_slug(db, &trial.course_slug).await?.commit_sha;
+        let stage = StageRepository::get_by_id(db, trial.stage_id).await?;
+
+        let (course, repo) = (&trial.course_slug, trial.repo());
+
+        // The local runner starts once the attempt is recorded
+        #[cfg(feature = "local-runner")]
+        if let Some(runner) = &ctx.runner {
+            let name = Uuid::now_v7().to_string();
+            TrialRepository::start_attempt(db, id, &name).await?;
+            let hash = &stage.content_hash;
+            let run = TestRun::new(&name, &repo, course, &stage.slug, &commit, hash, "");
+            runner.spawn(ctx.clone(), run);
+            return Ok(());
+        }
+
+        // Trials are not queued while the cluster is unavailable, visitors
+        // simply push again
+        let pipeline = PipelineService::new(ctx.clone());
+        let name =
+            pipeline.trigger(&repo, course, &stage.slug, &commit, &stage.content_hash, "").await?;
+        TrialRepository::start_attempt(db, id, &name).await?;
+
+        Ok(())
+    }
+
+    /// Records the outcome (passed, failed) of a PipelineRun grading a trial.
+    pub async fn record_result(
+        ctx: &Context,
+        id: &Uuid,
+        pipeline_run: &str,
+        outcome: &str,
+    ) -> Result<()> {
+        if !TrialRepository::complete_attempt(&ctx.database, id, pipeline_run, outcome).await? {
+            info!("Ignoring outdated result of PipelineRun {} for trial {}", pipeline_run, id);
+        }
+        Ok(())
+    }
+
+    /// Deletes the repositories of expired trials, returns how many were
+    /// reaped.
+    pub async fn reap_expired(ctx: Arc<Context>) -> Result<usize> {
+        let repo = RepoService::new(ctx.clone());
+
+        let mut reaped = 0;
+        loop {
+            let now = ctx.clock.now();
+            let expired =
+                TrialRepository::find_expired(&ctx.database, now, REAP_BATCH_SIZE).await?;
+            let done = (expired.len() as i64) < REAP_BATCH_SIZE;
+
+            for trial in expired {
+                repo.delete(&ctx.config.trial_org, &trial.repo()).await?;
+                TrialRepository::expire(&ctx.database, &trial.id).await?;
+                reaped += 1;
+            }
+
+            if done {
+                return Ok(reaped);
+            }
+        }
+    }
+
+    /// Token granting access to a single trial.
+    pub fn token(ctx: &Context, id: &Uuid) -> Result<String> {
+        Ok(crypto::hmac_sha256_sign(&format!("trial:{id}"), &ctx.config.auth_secret)?)
+    }
+
+    /// Keyed hash of a client address, so that trials can be rate limited
+    /// per client without storing addresses.
+    pub fn client_key(ctx: &Context, address: &str) -> Result<String> {
+        Ok(crypto::hmac_sha256_sign(&format!("trial-client:{address}"), &ctx.config.auth_secret)?)
+    }
+
+    /// ID of the trial a repository belongs to, if it is a trial repository.
+    pub fn parse_repo(repo: &str) -> Option<Uuid> {
+        Uuid::parse_str(repo.strip_prefix(TRIAL_REPO_PREFIX)?).ok()
+    }
+
+    /// Ensures the token grants access to the trial.
+    fn authorize(ctx: &Context, id: &Uuid, token: &str) -> Result<()> {
+        let payload = format!("trial:{id}");
+        match crypto::hmac_sha256_verify(&payload, &ctx.config.auth_secret, token)? {
+            true => Ok(()),
+            false => Err(ApiError::Unauthorized("Invalid trial token".into())),
+        }
+    }
+
+    /// Checks a captcha token against the configured verification URL.
+    async fn verify_captcha(ctx: &Context, token: &str, address: &str) -> Result<()> {
+        let Some(url) = ctx.config.captcha_verify_url.as_deref().filter(|url| !url.is_empty())
+        else {
+            return Err(ApiError::Forbidden("Trials are not enabled".into()));
+        };
+
+        let secret = ctx.config.captcha_secret.as_deref().unwrap_or_default();
+        let form = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("secret", secret)
+            .append_pair("response", token)
+            .append_pair("remoteip", address)
+            .finish();
+
+        let response = ctx
+            .http
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(form)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        let verification = match response {
+            Ok(response) => response.json::<CaptchaVerification>().await,
+            Err(e) => Err(e),
+        };
+
+        match verification {
+            Ok(CaptchaVerification { success: true }) => Ok(()),
+            Ok(_) => Err(ApiError::BadRequest("Captcha verification failed".into())),
+            Err(e) => {
+                warn!("Failed to verify captcha token: {e}");
+                Err(ApiError::InternalError("Captcha verification is unavailable".into()))
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::HashMap, sync::Arc};
+
+use serde_json::json;
+use tracing::info;
+
+use crate::{
+    context::Context,
+    errors::{ApiError, Result},
+    model::{AuditLogModel, MergeEnrollmentModel},
+    repository::{AuditRepository, UserRepository},
+    request::MergeUsersRequest,
+    response::{ArchivedEnrollmentResponse, MergeUsersResponse},
+    service::RepoService,
+};
+
+/// Suffix appended to the repository name of archived enrollments.
+const MERGED_SUFFIX: &str = "-merged";
+
+/// Service for managing user accounts
+pub struct UserService;
+
+impl UserService {
+    /// Merge a duplicate account into the account to keep.
+    ///
+    /// The enrollments of the source move to the target. Where both are
+    /// enrolled in the same course, the enrollment with more completed
+    /// stages is kept, the target's one on a tie, and the other one is
+    /// archived under the source with its repository renamed. Re-running a
+    /// completed merge only retries the renames.
+    pub async fn merge(
+        ctx: Arc<Context>,
+        req: &MergeUsersRequest,
+        actor: &str,
+    ) -> Result<MergeUsersResponse> {
+        let (source, target) = (req.source_user_id.as_str(), req.target_user_id.as_str());
+        if source == target {
+            return Err(ApiError::BadRequest("Cannot merge a user into itself".into()));
+        }
+
+        // Lock both users in a stable order, so that concurrent merges of the
+        // same users cannot deadlock
+        let mut tx = ctx.database.pool().begin().await?;
+        let mut users = HashMap::new();
+        for id in if source < target { [source, target] } else { [target, source] } {
+            users.insert(id, UserRepository::get_for_update(&mut tx, id).await?);
+        }
+        let (source_user, target_user) = (&users[source], &users[target]);
+
+        if target_user.merged_into.is_some() {
+            return Err(ApiError::BadRequest("The target user was merged into another".into()));
+        }
+        let merged = match (&source_user.merged_into, source_user.merged_at) {
+            (Some(into), Some(merged_at)) if into == target => Some(merged_at),
+            (Some(_), _) => {
+                return Err(ApiError::BadRequest("The source user was merged into another".into()));
+            }
+            (None, _) => None,
+        };
+
+        let enrollments =
+            UserRepository::find_merge_enrollments(&mut tx, &[source, target]).await?;
+        let conflicts = match merged {
+            Some(_) => Vec::new(),
+            None => conflicts(&enrollments, source),
+        };
+
+        // Enrollments archived by an earlier run of the same merge
+        let mut archived: Vec<_> = enrollments
+            .iter()
+            .filter(|e| e.user_id == source && e.archived_at.is_some())
+            .map(|e| (e, None))
+            .collect();
+        let mut moved = Vec::new();
+
+        let merged_at = match merged {
+            Some(merged_at) => merged_at,
+            None => {
+                // The loser of a conflict stays with, or is handed over to,
+                // the source before its other enrollments move to the target
+                for (kept, loser) in &conflicts {
+                    UserRepository::archive_enrollment(&mut tx, &loser.id, source).await?;
+                    archived.push((loser, Some(kept)));
+                }
+                moved = enrollments
+                    .iter()
+                    .filter(|e| e.user_id == source && e.archived_at.is_none())
+                    .filter(|e| !conflicts.iter().any(|(_, loser)| loser.id == e.id))
+                    .map(|e| e.course_slug.clone())
+                    .collect();
+
+                let merged_at = ctx.clock.now();
+                UserRepository::reassign(&mut tx, source, target).await?;
+                UserRepository::mark_merged(&mut tx, source, target, merged_at).await?;
+                merged_at
+            }
+        };
+        tx.commit().await?;
+
+        if merged.is_none() {
+            let details = json!({
+                "target": target,
+                "moved": moved,
+                "archived": archived.iter().map(|(loser, kept)| json!({
+                    "course": loser.course_slug,
+                    "user_course_id": loser.id,
+                    "kept_user_course_id": kept.map(|kept| kept.id),
+                })).collect::<Vec<_>>(),
+            });
+            let log = AuditLogModel::new_at(
+                actor,
+                "merge_users",
+                &format!("users/{source}"),
+                details,
+                ctx.clock.now(),
+            );
+            AuditRepository::create(&ctx.database, &log).await?;
+            info!("Merged user {source} into {target}");
+        }
+
+        // Renaming is idempotent, failed renames are retried by re-running
+        // the merge
+        let org = &ctx.config.namespace;
+        let repos = RepoService::new(ctx.clone());
+        let mut res = Vec::with_capacity(archived.len());
+        for (enrollment, _) in archived {
+            let repository = format!("{}{MERGED_SUFFIX}", enrollment.id);
+            repos.rename(org, &enrollment.id.to_string(), &repository).await?;
+            res.push(ArchivedEnrollmentResponse {
+                course_slug: enrollment.course_slug.clone(),
+                user_course_id: enrollment.id,
+                repository,
+            });
+        }
+
+        Ok(MergeUsersResponse {
+            source_user_id: source.to_string(),
+            target_user_id: target.to_string(),
+            merged_at,
+            moved,
+            archived: res,
+        })
+    }
+}
+
+/// Pair the active enrollments of both users in the same course, the one to
+/// keep first.
+fn conflicts<'a>(
+    enrollments: &'a [MergeEnrollmentModel],
+    source: &str,
+) -> Vec<(&'a MergeEnrollmentModel, &'a MergeEnrollmentModel)> {
+    let active = enrollments.iter().filter(|e| e.archived_at.is_none());
+    let (from_source, from_target): (Vec<_>, Vec<_>) = active.partition(|e| e.user_id == source);
+
+    from_source
+        .into_iter()
+        .filter_map(|s| {
+            let t = from_target.iter().find(|t| t.course_slug == s.course_slug)?;
+            Some(if s.completed_stage_count > t.completed_stage_count { (s, *t) } else { (*t, s) })
+        })
+        .collect()
+}
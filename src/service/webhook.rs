@@ -0,0 +1,133 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    context::Context,
+    errors::Result,
+    repository::{CourseRepository, StageRepository},
+    request::event::PipelineEvent,
+    response::{PipelineEventReport, RejectionReason},
+    service::{TrialService, legacy_signing_payload, signing_payload},
+    utils::crypto,
+};
+
+/// Service for verifying the events reported by pipelines
+pub struct WebhookService;
+
+impl WebhookService {
+    /// Verify that a pipeline event is signed and refers to a known
+    /// repository and stage, rejecting it with the reason of the first
+    /// failed check.
+    pub async fn verify(ctx: &Context, event: &PipelineEvent) -> Result<()> {
+        match Self::validate(ctx, event).await?.rejection_reason {
+            Some(reason) => Err(reason.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Run the checks the webhook performs on a pipeline event, without
+    /// recording anything, and report the outcome of each.
+    pub async fn validate(ctx: &Context, event: &PipelineEvent) -> Result<PipelineEventReport> {
+        let PipelineEvent {
+            name,
+            nonce,
+            repo,
+            course,
+            stage,
+            commit,
+            content_hash,
+            repo_commit,
+            secret,
+            ..
+        } = event;
+        let mut report = PipelineEventReport::default();
+
+        // Nothing is looked up for unsigned events, so that they cannot be
+        // used to probe for user courses
+        let payload =
+            signing_payload(name, nonce, repo, course, stage, commit, content_hash, repo_commit);
+        let auth_secret = &ctx.config.auth_secret;
+        let signed = crypto::hmac_sha256_verify(&payload, auth_secret, secret)?;
+
+        // Runs triggered before the upgrade carry no nonce and are signed in
+        // the legacy format, accepted for a grace period only
+        let legacy = !signed &&
+            nonce.is_empty() &&
+            ctx.config.legacy_signatures_until.is_some_and(|until| Utc::now() < until) &&
+            crypto::hmac_sha256_verify(
+                &legacy_signing_payload(repo, course, stage, commit, content_hash, repo_commit),
+                auth_secret,
+                secret,
+            )?;
+        if !signed && !legacy {
+            report.fail(
+                "signature",
+                RejectionReason::InvalidSignature,
+                "The secret does not match the signature of name, nonce, repo, course, stage, \
+                 commit, content_hash and repo_commit",
+            );
+            for name in ["repo", "user_course", "stage"] {
+                report.skip(name, "Requires a valid signature");
+            }
+            return Ok(report);
+        }
+        if legacy {
+            report.pass("signature", "The secret matches the legacy signature");
+        } else {
+            report.pass("signature", "The secret matches");
+        }
+
+        let db = &ctx.database;
+        if TrialService::parse_repo(repo).is_some() {
+            report.pass("repo", "Trial repository");
+            report.skip("user_course", "Trials are graded without a user course");
+        } else if let Ok(id) = Uuid::parse_str(repo) {
+            report.pass("repo", "User course repository");
+            match CourseRepository::get_user_course_by_id(db, &id).await {
+                Ok(user_course) if user_course.course_slug == *course => {
+                    report.pass("user_course", format!("Enrolled in course {course}"));
+                }
+                Ok(user_course) => report.fail(
+                    "user_course",
+                    RejectionReason::UnknownRepo,
+                    format!("The user course belongs to course {}", user_course.course_slug),
+                ),
+                Err(sqlx::Error::RowNotFound) => report.fail(
+                    "user_course",
+                    RejectionReason::UnknownRepo,
+                    format!("No user course with ID {id}"),
+                ),
+                Err(e) => return Err(e.into()),
+            }
+        } else {
+            report.fail("repo", RejectionReason::UnknownRepo, "Not a user course or trial ID");
+            report.skip("user_course", "Requires a valid repo");
+        }
+
+        match StageRepository::get_by_slug(db, course, stage).await {
+            Ok(_) => report.pass("stage", format!("Stage {stage} exists in course {course}")),
+            Err(sqlx::Error::RowNotFound) => report.fail(
+                "stage",
+                RejectionReason::UnknownStage,
+                format!("No stage {stage} in course {course}"),
+            ),
+            Err(e) => return Err(e.into()),
+        }
+
+        Ok(report)
+    }
+}
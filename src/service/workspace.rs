@@ -0,0 +1,445 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use sha2::{Digest, Sha256};
+use tracing::{debug, info};
+
+use crate::{
+    errors::Result,
+    service::StorageError,
+    utils::git::{self, GitError, Submodule},
+};
+
+/// File at the root of a template listing, in the gitignore format, the
+/// files course maintainers keep for themselves, e.g. CI configurations or
+/// editor settings. Neither they nor the file itself reach learners.
+pub const IGNORE_FILE: &str = ".stackclassignore";
+
+/// File written by every sync, its modification time tells when a working
+/// clone was last used.
+const SYNC_MARKER: &str = ".git/stackclass-synced";
+
+/// Outcome of syncing a working clone with its template.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SyncOutcome {
+    /// The remote already matched the template, nothing was pushed
+    Unchanged,
+
+    /// A commit changing the given paths was pushed
+    Pushed(Vec<String>),
+}
+
+impl fmt::Display for SyncOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncOutcome::Unchanged => write!(f, "unchanged"),
+            SyncOutcome::Pushed(paths) => write!(f, "pushed {} changed files", paths.len()),
+        }
+    }
+}
+
+/// Branch of learner repositories the updates of their template are pushed
+/// to, for learners to merge.
+pub const TEMPLATE_UPDATE_BRANCH: &str = "template-update";
+
+/// Outcome of bringing the updates of a template into a learner repository.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TemplateUpdate {
+    /// The repository has the latest template commit already
+    UpToDate,
+
+    /// A merge of the given template commit was pushed
+    Pushed(String),
+
+    /// The template changes conflict with the learner's in the given paths,
+    /// nothing was pushed
+    Conflict(Vec<String>),
+}
+
+impl fmt::Display for TemplateUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateUpdate::UpToDate => write!(f, "up to date"),
+            TemplateUpdate::Pushed(commit) => write!(f, "pushed a merge of {commit}"),
+            TemplateUpdate::Conflict(paths) => write!(f, "{} conflicting files", paths.len()),
+        }
+    }
+}
+
+/// Keeps a persistent working clone per template repository, so that syncs
+/// only write the files that changed and push only when the tree differs.
+pub struct WorkspaceService {
+    work_dir: PathBuf,
+    committer_name: String,
+    committer_email: String,
+    branch: String,
+}
+
+impl WorkspaceService {
+    pub fn new(work_dir: &Path, committer_name: &str, committer_email: &str) -> Self {
+        Self {
+            work_dir: work_dir.to_path_buf(),
+            committer_name: committer_name.to_string(),
+            committer_email: committer_email.to_string(),
+            branch: "main".to_string(),
+        }
+    }
+
+    /// Uses another default branch than `main` for the repositories.
+    pub fn with_branch(mut self, branch: &str) -> Self {
+        self.branch = branch.to_string();
+        self
+    }
+
+    /// Brings the working clone `name` in line with the template directory,
+    /// but for the files matched by its [`IGNORE_FILE`], then commits and
+    /// pushes it to the default branch of the remote if the tree changed.
+    ///
+    /// Files are compared by the given SHA-256 hashes, keyed by their path
+    /// relative to the template, or else by size and modification time. The
+    /// commit names the `source` commit of the course the template was taken
+    /// from. Callers must keep other syncs of the same clone from running at
+    /// the same time.
+    pub async fn sync(
+        &self,
+        name: &str,
+        template_dir: &Path,
+        source: &str,
+        remote_url: &str,
+        hashes: &HashMap<String, String>,
+    ) -> Result<SyncOutcome> {
+        let (dir, initial) = self.checkout(name, remote_url).await?;
+
+        let ignore = load_ignore(template_dir)?;
+        sync_files(template_dir, &dir, hashes, &ignore).map_err(StorageError::SyncWorkspace)?;
+        git::stage(&dir).await?;
+
+        self.publish(name, &dir, initial, source, remote_url).await
+    }
+
+    /// Brings the working clone `name` in line with the `prefix` directory
+    /// of the `upstream` repository, keeping submodule pointers and leaving
+    /// out what the attributes mark `export-ignore` or the [`IGNORE_FILE`]
+    /// matches, then commits and pushes it like [`WorkspaceService::sync`].
+    ///
+    /// The tree is staged by git itself, so line endings follow the
+    /// attributes, and the given submodules, with paths relative to the
+    /// prefix, are declared in the `.gitmodules` file of the clone.
+    pub async fn sync_tree(
+        &self,
+        name: &str,
+        upstream: &Path,
+        prefix: &str,
+        submodules: &[Submodule],
+        remote_url: &str,
+    ) -> Result<SyncOutcome> {
+        let (dir, initial) = self.checkout(name, remote_url).await?;
+
+        let source = upstream.to_str().ok_or(StorageError::InvalidPath(prefix.to_string()))?;
+        git::fetch(&dir, source, "HEAD").await?;
+        let commit = git::rev_parse(&dir, "FETCH_HEAD").await?;
+        git::read_tree(&dir, &format!("{commit}:{prefix}")).await?;
+
+        let mut ignored: Vec<String> = git::export_ignored(upstream, prefix)
+            .await?
+            .iter()
+            .filter_map(|path| path.strip_prefix(&format!("{prefix}/")).map(str::to_string))
+            .collect();
+        let ignore = load_ignore(&upstream.join(prefix))?;
+        ignored.extend(
+            git::tracked_files(&dir)
+                .await?
+                .into_iter()
+                .filter(|path| ignore.matched_path_or_any_parents(path, false).is_ignore()),
+        );
+        if !ignored.is_empty() {
+            git::remove(&dir, &ignored).await?;
+        }
+
+        if !submodules.is_empty() {
+            fs::write(dir.join(".gitmodules"), Submodule::render(submodules))
+                .map_err(StorageError::SyncWorkspace)?;
+            git::stage_file(&dir, ".gitmodules").await?;
+        }
+
+        self.publish(name, &dir, initial, &commit, remote_url).await
+    }
+
+    /// Merges the latest commit of the default branch of a template
+    /// repository into the [`TEMPLATE_UPDATE_BRANCH`] of a learner repository,
+    /// starting from its default branch, in a temporary clone. The default
+    /// branch itself is never pushed, and conflicts are reported rather than
+    /// resolved.
+    ///
+    /// Repositories generated from a template share no history with it, so
+    /// their first commit is grafted onto the template commit with the same
+    /// tree. Once an update was merged, the merge commit ties the histories.
+    /// Without a matching commit, only the changes of the latest template
+    /// commit are brought in, like a cherry-pick would.
+    pub async fn update_from_template(
+        &self,
+        repo_url: &str,
+        template_url: &str,
+    ) -> Result<TemplateUpdate> {
+        let temp_dir = tempfile::tempdir().map_err(StorageError::CreateDir)?;
+        let dir = temp_dir.path();
+
+        git::clone(dir, repo_url, &self.branch).await?;
+        git::config(dir, "user.name", &self.committer_name).await?;
+        git::config(dir, "user.email", &self.committer_email).await?;
+        git::fetch(dir, template_url, &self.branch).await?;
+        let template = git::rev_parse(dir, "FETCH_HEAD").await?;
+
+        if git::merge_base(dir, "HEAD", &template).await?.is_none() {
+            let root = git::root_commit(dir, "HEAD").await?;
+            let tree = git::rev_parse(dir, &format!("{root}^{{tree}}")).await?;
+            let base = match git::find_by_tree(dir, &template, &tree).await? {
+                Some(base) => base,
+                None => {
+                    git::parents(dir, &template).await?.into_iter().next().ok_or_else(|| {
+                        GitError::Merge("no template commit to merge the repository with".into())
+                    })?
+                }
+            };
+            git::graft(dir, &root, &base).await?;
+        }
+        if git::is_ancestor(dir, &template, "HEAD").await? {
+            return Ok(TemplateUpdate::UpToDate);
+        }
+
+        git::checkout_branch(dir, TEMPLATE_UPDATE_BRANCH).await?;
+        if !git::merge(dir, &template, "Merge template updates").await? {
+            return Ok(TemplateUpdate::Conflict(git::conflicted_files(dir).await?));
+        }
+
+        // The branch is recreated from the default branch on every update
+        git::push(dir, repo_url, TEMPLATE_UPDATE_BRANCH).await?;
+        Ok(TemplateUpdate::Pushed(template))
+    }
+
+    /// Prepares the working clone `name` from the remote default branch, or
+    /// from scratch while it has none, returning its directory and whether
+    /// the remote is empty.
+    async fn checkout(&self, name: &str, remote_url: &str) -> Result<(PathBuf, bool)> {
+        let dir = self.work_dir.join(name);
+        fs::create_dir_all(&self.work_dir).map_err(StorageError::CreateDir)?;
+
+        let initial = git::ls_remote(&self.work_dir, remote_url, &self.branch).await?.is_none();
+        if initial || !dir.join(".git").is_dir() {
+            self.create(&dir).await?;
+        }
+        if !initial {
+            git::fetch(&dir, remote_url, &self.branch).await?;
+            git::reset(&dir, "FETCH_HEAD").await?;
+        }
+
+        Ok((dir, initial))
+    }
+
+    /// Commits and pushes the staged changes of a working clone, if any, with
+    /// the commit of the course they come from in the message.
+    async fn publish(
+        &self,
+        name: &str,
+        dir: &Path,
+        initial: bool,
+        source: &str,
+        remote_url: &str,
+    ) -> Result<SyncOutcome> {
+        fs::write(dir.join(SYNC_MARKER), b"").map_err(StorageError::SyncWorkspace)?;
+
+        let changes = git::staged_files(dir).await?;
+        if changes.is_empty() {
+            debug!("Working clone {} already matches its template", name);
+            return Ok(SyncOutcome::Unchanged);
+        }
+
+        let title = if initial { "Initial commit from template" } else { "Sync with template" };
+        git::commit(dir, &format!("{title}\n\nCourse commit: {source}")).await?;
+        git::push(dir, remote_url, &self.branch).await?;
+
+        info!("Pushed {} changed files of working clone {}", changes.len(), name);
+        Ok(SyncOutcome::Pushed(changes))
+    }
+
+    /// Lists the working clones along with when they were last synced.
+    pub fn list(&self) -> Result<Vec<(String, SystemTime)>> {
+        let entries = match fs::read_dir(&self.work_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(StorageError::ReadFile(e).into()),
+        };
+
+        let mut clones = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(StorageError::ReadFile)?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if path.is_dir() {
+                let synced_at = fs::metadata(path.join(SYNC_MARKER)).and_then(|m| m.modified());
+                clones.push((name.to_string(), synced_at.unwrap_or(UNIX_EPOCH)));
+            }
+        }
+
+        Ok(clones)
+    }
+
+    /// Removes a working clone, succeeding if it is already gone.
+    pub fn remove(&self, name: &str) -> Result<()> {
+        remove_path(&self.work_dir.join(name)).map_err(StorageError::SyncWorkspace)?;
+        Ok(())
+    }
+
+    /// Replaces the directory with an empty repository on the default branch.
+    async fn create(&self, dir: &Path) -> Result<()> {
+        remove_path(dir).map_err(StorageError::SyncWorkspace)?;
+        fs::create_dir_all(dir).map_err(StorageError::CreateDir)?;
+
+        git::init(dir, &self.branch).await?;
+        git::config(dir, "user.name", &self.committer_name).await?;
+        git::config(dir, "user.email", &self.committer_email).await?;
+        Ok(())
+    }
+}
+
+/// Loads the [`IGNORE_FILE`] of a template directory, if any, which always
+/// ignores itself.
+fn load_ignore(template_dir: &Path) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(template_dir);
+    let path = template_dir.join(IGNORE_FILE);
+    if let Some(e) = path.is_file().then(|| builder.add(&path)).flatten() {
+        return Err(StorageError::InvalidIgnoreFile(e).into());
+    }
+
+    builder.add_line(None, &format!("/{IGNORE_FILE}")).map_err(StorageError::InvalidIgnoreFile)?;
+    Ok(builder.build().map_err(StorageError::InvalidIgnoreFile)?)
+}
+
+/// Mirrors the files of `source` which are not ignored into `target`,
+/// leaving its `.git` directory alone. Changed files keep their modification
+/// time, so that the next sync can tell they are up to date.
+fn sync_files(
+    source: &Path,
+    target: &Path,
+    hashes: &HashMap<String, String>,
+    ignore: &Gitignore,
+) -> io::Result<()> {
+    let mut kept = HashSet::new();
+    copy_changed(source, target, Path::new(""), hashes, ignore, &mut kept)?;
+    remove_stale(target, Path::new(""), &kept)
+}
+
+/// Recursively copies the files of a directory relative to the roots that
+/// differ in the target, collecting the paths of all entries not ignored.
+fn copy_changed(
+    source: &Path,
+    target: &Path,
+    relative: &Path,
+    hashes: &HashMap<String, String>,
+    ignore: &Gitignore,
+    kept: &mut HashSet<PathBuf>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(source.join(relative))? {
+        let entry = entry?;
+        let path = relative.join(entry.file_name());
+        if path == Path::new(".git") {
+            continue;
+        }
+
+        let metadata = fs::metadata(entry.path())?;
+        if ignore.matched(&path, metadata.is_dir()).is_ignore() {
+            continue;
+        }
+
+        let to = target.join(&path);
+        kept.insert(path.clone());
+
+        if metadata.is_dir() {
+            if !to.is_dir() {
+                remove_path(&to)?;
+                fs::create_dir_all(&to)?;
+            }
+            copy_changed(source, target, &path, hashes, ignore, kept)?;
+            continue;
+        }
+
+        let hash = path.to_str().and_then(|path| hashes.get(path));
+        if up_to_date(&metadata, &to, hash)? {
+            continue;
+        }
+
+        remove_path(&to)?;
+        let mut file = fs::File::create(&to)?;
+        io::copy(&mut fs::File::open(entry.path())?, &mut file)?;
+        file.set_modified(metadata.modified()?)?;
+        file.set_permissions(metadata.permissions())?;
+    }
+
+    Ok(())
+}
+
+/// Whether the copy of a file is up to date, judged by its hash when known,
+/// or else by its size and modification time.
+fn up_to_date(source: &fs::Metadata, copy: &Path, hash: Option<&String>) -> io::Result<bool> {
+    let Ok(metadata) = fs::symlink_metadata(copy) else {
+        return Ok(false);
+    };
+    if !metadata.is_file() || metadata.len() != source.len() {
+        return Ok(false);
+    }
+
+    match hash {
+        Some(hash) => Ok(hex::encode(Sha256::digest(fs::read(copy)?)) == *hash),
+        None => Ok(metadata.modified()? == source.modified()?),
+    }
+}
+
+/// Recursively removes the entries of a directory relative to the target
+/// root which are not kept.
+fn remove_stale(target: &Path, relative: &Path, kept: &HashSet<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(target.join(relative))? {
+        let entry = entry?;
+        let path = relative.join(entry.file_name());
+        if path == Path::new(".git") {
+            continue;
+        }
+
+        if !kept.contains(&path) {
+            remove_path(&entry.path())?;
+        } else if entry.file_type()?.is_dir() {
+            remove_stale(target, &path, kept)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes a file or directory, succeeding if it does not exist.
+fn remove_path(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
@@ -14,7 +14,7 @@
 
 use utoipa::{
     Modify, OpenApi,
-    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
 };
 use utoipa_swagger_ui::SwaggerUi;
 
@@ -27,53 +27,229 @@ use crate::{handler, request, response};
     ),
     paths(
         handler::course::find,
+        handler::course::search,
         handler::course::create,
+        handler::course::get_job,
+        handler::course::stream_job,
         handler::course::get,
         handler::course::delete,
+        handler::course::sync_repositories,
+        handler::course::get_registry,
+        handler::course::update_registry,
         handler::course::update,
 
         handler::course::find_attempts,
+        handler::course::export,
+        handler::course::find_revisions,
+        handler::course::get_source,
+        handler::course::get_stats,
+        handler::course::find_cohort_progress,
+        handler::course::get_effective_settings,
+        handler::course::get_stage_source,
+        handler::course::get_offline_manifest,
+        handler::course::get_asset,
         handler::extension::find,
 
         handler::stage::find_all_stages,
         handler::stage::find_base_stages,
         handler::stage::find_extended_stages,
         handler::stage::get,
+        handler::stage::get_starter_diff,
 
         handler::course::find_user_courses,
         handler::course::create_user_course,
         handler::course::get_user_course,
         handler::course::update_user_course,
+        handler::course::delete_user_course,
+        handler::course::reset_user_course,
+        handler::course::find_user_pipelines,
         handler::course::stream_user_course_status,
+        handler::course::verify_git_identity,
+        handler::notification::get_preferences,
+        handler::notification::update_preferences,
+        handler::course::create_engagement_event,
 
         handler::stage::find_user_stages,
+        handler::stage::get_roadmap,
         handler::stage::complete_stage,
+        handler::stage::skip_stage,
+        handler::stage::retest_stage,
         handler::stage::get_user_stage,
-        handler::stage::stream_user_stage_status
+        handler::stage::get_user_stage_solution,
+        handler::stage::find_user_stage_hints,
+        handler::stage::unlock_user_stage_hint,
+        handler::stage::find_user_stage_attempts,
+        handler::stage::stream_user_stage_status,
+        handler::stage::stream_user_stage_logs,
+
+        handler::admin::summary,
+        handler::admin::find_jobs,
+        handler::admin::find_routes,
+        handler::admin::find_audit_logs,
+        handler::admin::find_resource_profiles,
+        handler::admin::grant_attempts,
+        handler::admin::download_snapshot,
+        handler::admin::get_snapshot_tree,
+        handler::admin::stream_stage_logs,
+        handler::admin::merge_users,
+        handler::admin::extend_deadline,
+        handler::admin::find_user_pipelines,
+        handler::admin::set_exam_window,
+        handler::admin::find_progress,
+        handler::admin::rebuild_progress,
+        handler::admin::find_attempts,
+        handler::admin::find_engagement,
+        handler::admin::find_integrity_flags,
+        handler::admin::dismiss_integrity_flag,
+        handler::admin::find_maintainers,
+        handler::admin::add_maintainer,
+        handler::admin::remove_maintainer,
+        handler::admin::get_preview_token,
+        handler::admin::create_api_token,
+        handler::admin::find_api_tokens,
+        handler::admin::revoke_api_token,
+        handler::admin::rotate_registry_credentials,
+        handler::admin::collect_registry_garbage,
+        handler::admin::get_registry_garbage_collection,
+        handler::admin::preprovision,
+        handler::admin::set_setting_overrides,
+        handler::admin::migrate_repositories,
+        handler::admin::find_repository_migrations,
+        handler::admin::find_orphaned_repositories,
+        handler::admin::set_maintenance,
+        handler::admin::set_course_defaults,
+
+        handler::health::ready,
+
+        handler::meta::get_course,
+        handler::meta::get_stage,
+
+        handler::trial::create,
+        handler::trial::get_status,
+        handler::trial::convert
     ),
     components(
         schemas(
             request::CreateCourseRequest,
+            request::UpdateCourseRequest,
             response::CourseResponse,
+            response::CourseJobResponse,
+            response::CourseDeletionResponse,
+            response::RepositorySyncResponse,
+            response::CourseStatsResponse,
+            response::StageStatsResponse,
             response::CourseDetailResponse,
+            response::CourseExportResponse,
+            response::CourseRevisionResponse,
+            response::ExtensionExport,
+            response::StageExport,
+            response::CourseSourceResponse,
+            response::StageSourceSummary,
+            response::StageSourceResponse,
+            response::OfflineManifestResponse,
+            response::ManifestStageResponse,
+            response::ManifestAssetResponse,
 
             response::AttemptResponse,
             response::ExtensionResponse,
 
             response::StageResponse,
             response::StageDetailResponse,
+            response::StarterChangeResponse,
+            response::StarterDiffResponse,
+            response::StageSolutionResponse,
+            response::StageHintResponse,
+            response::RetestResponse,
 
             request::CreateUserCourseRequest,
+            request::ResetUserCourseRequest,
             request::UpdateUserCourseRequest,
             response::UserCourseResponse,
+            request::VerifyGitIdentityRequest,
+            request::NotificationChannel,
+            request::UpdateNotificationPreferencesRequest,
+            response::NotificationPreferencesResponse,
+            request::CreateEngagementEventRequest,
+            request::EngagementEventType,
+            response::GitIdentityVerificationResponse,
             response::UserStageResponse,
             response::UserStageStatusResponse,
+            response::StreamErrorEvent,
+            response::StreamTimeoutEvent,
+            response::PipelineRunResponse,
+            response::PipelineRunCondition,
+            response::ConflictResponse,
+            response::StageLogLine,
+            response::StageLogEnd,
+            response::RoadmapResponse,
+            response::RoadmapStageResponse,
+            response::StageState,
+
+            response::AdminSummaryResponse,
+            response::StreamSummary,
+            response::JobResponse,
+            response::RouteResponse,
+            response::ProgressResponse,
+            response::RebuildProgressResponse,
+            response::StageAttemptResponse,
+            request::event::TestCaseResult,
+            response::Paginated<response::StageAttemptResponse>,
+            response::AuditLogResponse,
+            response::Paginated<response::AuditLogResponse>,
+            response::NumberedPage<response::CourseResponse>,
+            response::NumberedPage<response::AttemptResponse>,
+            request::GrantAttemptsRequest,
+            response::SnapshotResponse,
+            response::SnapshotFileResponse,
+            request::MergeUsersRequest,
+            response::MergeUsersResponse,
+            response::ArchivedEnrollmentResponse,
+            request::ExtendDeadlineRequest,
+            request::ExamWindowRequest,
+            request::AddMaintainerRequest,
+            response::MaintainerResponse,
+            response::PreviewTokenResponse,
+            request::ApiTokenCapability,
+            request::CreateApiTokenRequest,
+            response::ApiTokenResponse,
+            request::DismissFlagRequest,
+            response::IntegrityFlagResponse,
+            response::StageEngagementResponse,
+            response::ResourceProfileResponse,
+            response::ResourceQuantities,
+            response::RegistryCredentialResponse,
+            response::GarbageCollectionResponse,
+            response::GarbageCollectionStatusResponse,
+            request::UpdateRegistryRequest,
+            response::RegistrySummaryResponse,
+            request::MigrateRepositoriesRequest,
+            response::MigrateRepositoriesResponse,
+            request::PreprovisionRequest,
+            response::PreprovisionResponse,
+            response::RepoMigrationResponse,
+            response::RepoMigrationReportResponse,
+            response::OrphanedRepositoryResponse,
+            request::MaintenanceRequest,
+            response::MaintenanceResponse,
+            request::CourseSettingsRequest,
+            response::CourseSettingsResponse,
+            response::EffectiveSettingsResponse,
+            response::ReadinessResponse,
+
+            request::CreateTrialRequest,
+            response::TrialResponse,
+            response::TrialStatusResponse,
+            request::ConvertTrialRequest,
         )
     ),
     tags(
+        (name = "Admin", description = "The Admin Service Handlers"),
         (name = "Course", description = "The Course Service Handlers"),
         (name = "Extension", description = "The Extension Service Handlers"),
+        (name = "Health", description = "The Health Service Handlers"),
+        (name = "Meta", description = "The Meta Service Handlers"),
         (name = "Stage", description = "The Stage Service Handlers"),
+        (name = "Trial", description = "The Trial Service Handlers"),
         (name = "User", description = "The User Service Handlers"),
     ),
     modifiers(&SecurityAddon),
@@ -95,6 +271,14 @@ impl Modify for SecurityAddon {
             components.add_security_scheme(
                 "AdminBasicAuth",
                 SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Basic).build()),
+            );
+
+            components.add_security_scheme(
+                "ApiTokenAuth",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                    "Authorization",
+                    "An API token, sent as `Token <secret>`",
+                ))),
             )
         }
     }
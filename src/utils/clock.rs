@@ -0,0 +1,102 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Source of the current time.
+//!
+//! Services read the time from the clock of the context rather than from
+//! `Utc::now()`, so that tests of time-dependent logic can move it by hand.
+//! A flow writing several rows reads the clock once, so the rows it writes
+//! share the same timestamp.
+
+use std::{fmt::Debug, sync::Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+
+/// A source of the current time.
+pub trait Clock: Debug + Send + Sync {
+    /// The current time.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The clock of the system, used in production.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock which only moves when told to, for tests.
+///
+/// Its time is kept to the microsecond, the precision of the database, so
+/// that timestamps read back compare equal to the ones written.
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl ManualClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self { now: Mutex::new(truncate(now)) }
+    }
+
+    /// Moves the clock forward by the given duration.
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.now.lock().unwrap();
+        *now = truncate(*now + by);
+    }
+
+    /// Moves the clock to the given time.
+    pub fn set(&self, to: DateTime<Utc>) {
+        *self.now.lock().unwrap() = truncate(to);
+    }
+}
+
+impl Default for ManualClock {
+    /// A clock stopped at the current time of the system.
+    fn default() -> Self {
+        Self::new(Utc::now())
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+fn truncate(time: DateTime<Utc>) -> DateTime<Utc> {
+    DateTime::from_timestamp_micros(time.timestamp_micros()).unwrap_or(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock() {
+        let start = DateTime::from_timestamp(1_735_689_600, 123_456_789).unwrap();
+        let clock = ManualClock::new(start);
+        assert_eq!(clock.now().timestamp_subsec_nanos(), 123_456_000);
+        assert_eq!(clock.now(), clock.now());
+
+        clock.advance(Duration::days(31));
+        assert_eq!(clock.now() - truncate(start), Duration::days(31));
+
+        clock.set(start);
+        assert_eq!(clock.now(), truncate(start));
+    }
+}
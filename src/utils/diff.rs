@@ -0,0 +1,179 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Line based unified diffs of text files.
+
+/// Largest number of cells of the table comparing the lines of two files,
+/// larger files are not diffed.
+const MAX_CELLS: usize = 4_000_000;
+
+/// Lines of context shown around changes.
+const CONTEXT: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Edit {
+    Keep,
+    Delete,
+    Insert,
+}
+
+/// Builds the unified diff turning `old` into `new`, with the given labels
+/// in its header. Returns none when the files are too large to compare.
+pub fn unified(old: &str, new: &str, old_label: &str, new_label: &str) -> Option<String> {
+    let (old, new): (Vec<&str>, Vec<&str>) = (old.lines().collect(), new.lines().collect());
+    let edits = edits(&old, &new)?;
+
+    // Line numbers of both files before every edit
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut o, mut n) = (0, 0);
+    for (edit, _) in &edits {
+        positions.push((o, n));
+        match edit {
+            Edit::Keep => (o, n) = (o + 1, n + 1),
+            Edit::Delete => o += 1,
+            Edit::Insert => n += 1,
+        }
+    }
+    positions.push((o, n));
+
+    let mut out = format!("--- {old_label}\n+++ {new_label}\n");
+    let changed = |k: usize| edits[k].0 != Edit::Keep;
+    let mut next = 0;
+    while let Some(first) = (next..edits.len()).find(|&k| changed(k)) {
+        // Changes closer than twice the context share a hunk
+        let mut last = first;
+        while let Some(k) = (last + 1..edits.len()).find(|&k| changed(k)) {
+            if k - last > 2 * CONTEXT {
+                break;
+            }
+            last = k;
+        }
+
+        let start = first.saturating_sub(CONTEXT).max(next);
+        let stop = (last + CONTEXT + 1).min(edits.len());
+        let (old_start, new_start) = positions[start];
+        let (old_stop, new_stop) = positions[stop];
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, old_stop - old_start),
+            range(new_start, new_stop - new_start)
+        ));
+
+        for (edit, line) in &edits[start..stop] {
+            let prefix = match edit {
+                Edit::Keep => ' ',
+                Edit::Delete => '-',
+                Edit::Insert => '+',
+            };
+            out.push(prefix);
+            out.push_str(line);
+            out.push('\n');
+        }
+        next = stop;
+    }
+
+    Some(out)
+}
+
+/// Formats the range of a hunk header, which starts at the line before an
+/// empty range.
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{start},0"),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+/// Finds the shortest edit script by the longest common subsequence of the
+/// lines, leaving out the common prefix and suffix.
+fn edits<'a>(old: &[&'a str], new: &[&'a str]) -> Option<Vec<(Edit, &'a str)>> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let (n, m) = (a.len(), b.len());
+    if (n + 1).saturating_mul(m + 1) > MAX_CELLS {
+        return None;
+    }
+
+    // Length of the common subsequence of the suffixes a[i..] and b[j..]
+    let width = m + 1;
+    let mut table = vec![0u32; (n + 1) * width];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            table[i * width + j] = if a[i] == b[j] {
+                table[(i + 1) * width + j + 1] + 1
+            } else {
+                table[(i + 1) * width + j].max(table[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut edits: Vec<_> = old[..prefix].iter().map(|line| (Edit::Keep, *line)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && a[i] == b[j] {
+            edits.push((Edit::Keep, a[i]));
+            (i, j) = (i + 1, j + 1);
+        } else if i < n && (j == m || table[(i + 1) * width + j] >= table[i * width + j + 1]) {
+            edits.push((Edit::Delete, a[i]));
+            i += 1;
+        } else {
+            edits.push((Edit::Insert, b[j]));
+            j += 1;
+        }
+    }
+    edits.extend(old[old.len() - suffix..].iter().map(|line| (Edit::Keep, *line)));
+
+    Some(edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_hunks() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn\n";
+        let diff = unified(old, new, "a/x", "b/x").unwrap();
+        assert_eq!(
+            diff,
+            "--- a/x\n+++ b/x\n@@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n\
+             @@ -11,3 +11,4 @@\n k\n l\n m\n+n\n"
+        );
+    }
+
+    #[test]
+    fn test_unified_added_and_removed_files() {
+        let diff = unified("", "fn main() {}\n", "/dev/null", "b/main.rs").unwrap();
+        assert_eq!(diff, "--- /dev/null\n+++ b/main.rs\n@@ -0,0 +1 @@\n+fn main() {}\n");
+
+        let diff = unified("x\ny\n", "", "a/lib.rs", "/dev/null").unwrap();
+        assert_eq!(diff, "--- a/lib.rs\n+++ /dev/null\n@@ -1,2 +0,0 @@\n-x\n-y\n");
+    }
+
+    #[test]
+    fn test_unified_gives_up_on_large_files() {
+        let old = "x\n".repeat(3000);
+        let new = "y\n".repeat(3000);
+        assert_eq!(unified(&old, &new, "a", "b"), None);
+    }
+}
@@ -0,0 +1,266 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use url::{ParseError, Url};
+use uuid::Uuid;
+
+use crate::config::Config;
+
+/// The external endpoints derived from the configuration.
+///
+/// Every URL handed out to Gitea, Tekton or the frontend is built here, so
+/// that trailing slashes or path prefixes in the configured values can not
+/// produce malformed URLs.
+#[derive(Clone, Debug)]
+pub struct Endpoints {
+    git_server: Url,
+    git_ssh: Option<Url>,
+    git_proxy: Url,
+    webhook: Url,
+    registry: String,
+    frontend: Url,
+}
+
+impl Endpoints {
+    /// Validates the configured endpoints.
+    pub fn new(config: &Config) -> Result<Self, ParseError> {
+        let mut endpoints = Self::parse(
+            &config.git_server_endpoint,
+            &config.git_proxy_endpoint,
+            &config.webhook_endpoint,
+            &config.docker_registry_endpoint,
+            &config.frontend_base_url,
+        )?;
+        endpoints.git_ssh = config.git_server_ssh_endpoint.as_deref().map(base).transpose()?;
+        Ok(endpoints)
+    }
+
+    fn parse(
+        git_server: &str,
+        git_proxy: &str,
+        webhook: &str,
+        registry: &str,
+        frontend: &str,
+    ) -> Result<Self, ParseError> {
+        // Standalone deployments grade locally and have no registry
+        let registry = match registry {
+            "" => String::new(),
+            registry => {
+                let registry = base(registry)?;
+                let host = registry.host_str().ok_or(ParseError::EmptyHost)?;
+                match registry.port() {
+                    Some(port) => format!("{host}:{port}"),
+                    None => host.to_string(),
+                }
+            }
+        };
+
+        Ok(Self {
+            git_server: base(git_server)?,
+            git_ssh: None,
+            git_proxy: base(git_proxy)?,
+            webhook: base(webhook)?,
+            registry,
+            frontend: base(frontend)?,
+        })
+    }
+
+    /// URL to clone a repository from the git server.
+    pub fn clone_url(&self, org: &str, repo: &str) -> String {
+        join(&self.git_server, &[org, &format!("{repo}.git")]).into()
+    }
+
+    /// URL to clone a repository from the git server over SSH, if enabled.
+    pub fn ssh_clone_url(&self, org: &str, repo: &str) -> Option<String> {
+        let base = self.git_ssh.as_ref()?;
+        Some(join(base, &[org, &format!("{repo}.git")]).into())
+    }
+
+    /// URL of a git smart HTTP resource of a repository, where `rest` is the
+    /// already encoded path and query following the `.git` suffix.
+    pub fn git_url(&self, org: &str, repo: &str, rest: &str) -> Url {
+        let mut url = join(&self.git_server, &[org, &format!("{repo}.git")]);
+        let (path, query) = rest.split_once('?').map_or((rest, None), |(p, q)| (p, Some(q)));
+        url.set_path(&format!("{}{}", url.path(), path));
+        url.set_query(query);
+        url
+    }
+
+    /// URL of a learner repository as exposed through the git proxy.
+    pub fn user_repo_url(&self, user_course_id: &Uuid) -> String {
+        join(&self.git_proxy, &[&user_course_id.to_string()]).into()
+    }
+
+    /// URL of a trial repository as exposed through the git proxy.
+    pub fn trial_repo_url(&self, trial_id: &Uuid) -> String {
+        join(&self.git_proxy, &["trials", &trial_id.to_string()]).into()
+    }
+
+    /// URL of the webhook handler for the given kind (gitea, tekton).
+    pub fn webhook_url(&self, kind: &str) -> String {
+        join(&self.webhook, &["v1", "webhooks", kind]).into()
+    }
+
+    /// URL of a course page in the frontend.
+    pub fn course_page(&self, slug: &str) -> String {
+        join(&self.frontend, &["courses", slug]).into()
+    }
+
+    /// URL of a stage page in the frontend.
+    pub fn stage_page(&self, slug: &str, stage_slug: &str) -> String {
+        join(&self.frontend, &["courses", slug, "stages", stage_slug]).into()
+    }
+
+    /// Absolute URL of a course asset, resolving paths against the frontend.
+    pub fn asset_url(&self, path: &str) -> Option<String> {
+        if path.is_empty() {
+            return None;
+        }
+        Url::parse(path).or_else(|_| self.frontend.join(path)).ok().map(Into::into)
+    }
+
+    /// Host, and port if any, of the docker registry.
+    pub fn registry_host(&self) -> &str {
+        &self.registry
+    }
+
+    /// Reference of an image in the docker registry.
+    pub fn image_ref(&self, org: &str, repo: &str, tag: &str) -> String {
+        format!("{}/{org}/{repo}:{tag}", self.registry)
+    }
+}
+
+/// Parses a base URL, rejecting values that can not carry a path.
+fn base(value: &str) -> Result<Url, ParseError> {
+    let url = Url::parse(value.trim())?;
+    if url.cannot_be_a_base() {
+        return Err(ParseError::RelativeUrlWithCannotBeABaseBase);
+    }
+    Ok(url)
+}
+
+/// Appends percent-encoded path segments to a base URL.
+fn join(base: &Url, segments: &[&str]) -> Url {
+    let mut url = base.clone();
+    url.set_query(None);
+    if let Ok(mut path) = url.path_segments_mut() {
+        path.pop_if_empty().extend(segments);
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoints(git: &str, proxy: &str, webhook: &str, registry: &str) -> Endpoints {
+        Endpoints::parse(git, proxy, webhook, registry, "https://stackclass.dev/").unwrap()
+    }
+
+    #[test]
+    fn test_trailing_slashes_are_normalized() {
+        let id = Uuid::now_v7();
+        let hosts = ["http://git.local", "http://git.local:3000", "https://git.local/gitea"];
+
+        for host in hosts {
+            let plain = endpoints(host, host, host, "http://docker.local");
+            let slashed = endpoints(
+                &format!("{host}/"),
+                &format!("{host}/"),
+                &format!("{host}/"),
+                "http://docker.local/",
+            );
+
+            assert_eq!(plain.clone_url("org", "repo"), slashed.clone_url("org", "repo"));
+            assert_eq!(plain.user_repo_url(&id), slashed.user_repo_url(&id));
+            assert_eq!(plain.webhook_url("gitea"), slashed.webhook_url("gitea"));
+            assert_eq!(
+                plain.image_ref("org", "r", "latest"),
+                slashed.image_ref("org", "r", "latest")
+            );
+
+            for url in [plain.clone_url("org", "repo"), plain.webhook_url("tekton")] {
+                assert!(Url::parse(&url).is_ok());
+                assert!(!url.replacen("://", "", 1).contains("//"), "{url}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_urls() {
+        let id = Uuid::parse_str("0198b0f4-6c3a-7a2e-9d1b-3f7c8e2a1b4d").unwrap();
+        let e = endpoints(
+            "http://git.local:3000/",
+            "https://git.stackclass.dev",
+            "http://api.local/prefix/",
+            "https://docker.local:5000/",
+        );
+
+        assert_eq!(e.clone_url("org", "repo"), "http://git.local:3000/org/repo.git");
+        assert_eq!(e.user_repo_url(&id), format!("https://git.stackclass.dev/{id}"));
+        assert_eq!(e.trial_repo_url(&id), format!("https://git.stackclass.dev/trials/{id}"));
+        assert_eq!(e.webhook_url("gitea"), "http://api.local/prefix/v1/webhooks/gitea");
+        assert_eq!(e.image_ref("org", "repo", "latest"), "docker.local:5000/org/repo:latest");
+        assert_eq!(
+            e.git_url("org", "repo", "/info/refs?service=git-upload-pack").as_str(),
+            "http://git.local:3000/org/repo.git/info/refs?service=git-upload-pack"
+        );
+    }
+
+    #[test]
+    fn test_ssh_clone_url() {
+        let mut e =
+            endpoints("http://git.local", "http://git.local", "http://api.local", "http://d");
+        assert_eq!(e.ssh_clone_url("org", "repo"), None);
+
+        e.git_ssh = Some(base("ssh://git@git.local:2222/").unwrap());
+        assert_eq!(
+            e.ssh_clone_url("org", "repo").unwrap(),
+            "ssh://git@git.local:2222/org/repo.git"
+        );
+    }
+
+    #[test]
+    fn test_frontend_urls() {
+        let e = endpoints("http://git.local", "http://git.local", "http://api.local", "http://d");
+        assert_eq!(e.course_page("redis"), "https://stackclass.dev/courses/redis");
+        assert_eq!(
+            e.stage_page("redis", "ping"),
+            "https://stackclass.dev/courses/redis/stages/ping"
+        );
+        assert_eq!(
+            e.asset_url("/logos/redis.svg").unwrap(),
+            "https://stackclass.dev/logos/redis.svg"
+        );
+        assert_eq!(e.asset_url("https://cdn.local/a.png").unwrap(), "https://cdn.local/a.png");
+        assert_eq!(e.asset_url(""), None);
+    }
+
+    #[test]
+    fn test_segments_are_encoded() {
+        let e = endpoints("http://git.local", "http://git.local", "http://api.local", "http://d");
+        assert_eq!(e.clone_url("my org", "a/b"), "http://git.local/my%20org/a%2Fb.git");
+    }
+
+    #[test]
+    fn test_invalid_endpoints() {
+        assert!(
+            Endpoints::parse("git.local", "http://a", "http://a", "http://a", "http://a").is_err()
+        );
+        assert!(
+            Endpoints::parse("http://a", "http://a", "http://a", "mailto:x", "http://a").is_err()
+        );
+        assert!(Endpoints::parse("http://a", "http://a", "http://a", "http://a", "a").is_err());
+    }
+}
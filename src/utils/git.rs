@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::path::Path;
+use std::{collections::BTreeSet, path::Path};
 use thiserror::Error;
 use tokio::process::Command;
 
@@ -35,6 +35,24 @@ pub enum GitError {
 
     #[error("Failed to configure Git: {0}")]
     ConfigError(String),
+
+    #[error("Failed to clone repository: {0}")]
+    CloneRepo(String),
+
+    #[error("Failed to fetch changes: {0}")]
+    FetchChanges(String),
+
+    #[error("Failed to reset working tree: {0}")]
+    ResetTree(String),
+
+    #[error("Failed to inspect repository: {0}")]
+    Inspect(String),
+
+    #[error("Failed to check out branch: {0}")]
+    Checkout(String),
+
+    #[error("Failed to merge changes: {0}")]
+    Merge(String),
 }
 
 /// Initializes a new Git repository in the specified directory
@@ -44,6 +62,138 @@ pub async fn init(dir: &Path, branch: &str) -> Result<(), GitError> {
     git(dir, &["init", "-b", branch]).await.map_err(GitError::InitRepo)
 }
 
+/// Clones the given branch of a remote repository into the directory,
+/// which must be empty.
+#[inline]
+pub async fn clone(dir: &Path, url: &str, branch: &str) -> Result<(), GitError> {
+    git(dir, &["clone", "--branch", branch, url, "."]).await.map_err(GitError::CloneRepo)
+}
+
+/// Clones a remote repository along with its submodules into the directory,
+/// which must be empty, and checks out the given commit.
+///
+/// URLs starting with the first of a pair of `rewrites` are fetched from the
+/// second instead, so that credentials reach the submodules too without
+/// ending up in the configuration of the clone.
+pub async fn clone_recursive(
+    dir: &Path,
+    url: &str,
+    reference: &str,
+    rewrites: &[(String, String)],
+) -> Result<(), GitError> {
+    let options: Vec<String> = rewrites
+        .iter()
+        .flat_map(|(from, to)| ["-c".to_string(), format!("url.{to}.insteadOf={from}")])
+        .collect();
+    let with = |args: &[&str]| -> Vec<String> {
+        options.iter().cloned().chain(args.iter().map(|arg| arg.to_string())).collect()
+    };
+
+    for args in [
+        with(&["clone", "--no-checkout", url, "."]),
+        with(&["checkout", "--detach", reference]),
+        with(&["submodule", "update", "--init", "--recursive"]),
+    ] {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        git(dir, &args).await.map_err(GitError::CloneRepo)?;
+    }
+    Ok(())
+}
+
+/// Whether a remote repository can be read anonymously.
+pub async fn is_public(dir: &Path, url: &str) -> bool {
+    output(dir, &["-c", "credential.helper=", "ls-remote", "--heads", url]).await.is_ok()
+}
+
+/// Returns the commit a branch of a remote repository points to, if the
+/// branch exists.
+pub async fn ls_remote(dir: &Path, url: &str, branch: &str) -> Result<Option<String>, GitError> {
+    let refs =
+        output(dir, &["ls-remote", "--heads", url, branch]).await.map_err(GitError::Inspect)?;
+    Ok(refs.split_whitespace().next().map(str::to_string))
+}
+
+/// Fetches a branch of a remote repository into `FETCH_HEAD`.
+#[inline]
+pub async fn fetch(dir: &Path, url: &str, branch: &str) -> Result<(), GitError> {
+    git(dir, &["fetch", "--no-tags", url, branch]).await.map_err(GitError::FetchChanges)
+}
+
+/// Resets the index and working tree to the given reference, discarding any
+/// local changes.
+#[inline]
+pub async fn reset(dir: &Path, reference: &str) -> Result<(), GitError> {
+    git(dir, &["reset", "--hard", reference]).await.map_err(GitError::ResetTree)
+}
+
+/// Lists the paths of the staged changes.
+pub async fn staged_files(dir: &Path) -> Result<Vec<String>, GitError> {
+    let names = output(dir, &["diff", "--cached", "--name-only", "--no-renames", "-z"])
+        .await
+        .map_err(GitError::Inspect)?;
+    Ok(names.split('\0').filter(|name| !name.is_empty()).map(str::to_string).collect())
+}
+
+/// Lists the paths of the files in the index.
+pub async fn tracked_files(dir: &Path) -> Result<Vec<String>, GitError> {
+    let names = output(dir, &["ls-files", "-z"]).await.map_err(GitError::Inspect)?;
+    Ok(names.split('\0').filter(|name| !name.is_empty()).map(str::to_string).collect())
+}
+
+/// Replaces the index and working tree with the given tree, removing the
+/// files it does not have.
+#[inline]
+pub async fn read_tree(dir: &Path, tree: &str) -> Result<(), GitError> {
+    git(dir, &["read-tree", "--reset", "-u", tree]).await.map_err(GitError::ResetTree)
+}
+
+/// Lists the tracked paths under `prefix`, and the directories leading to
+/// them, which are marked `export-ignore` by the attributes of the
+/// repository.
+pub async fn export_ignored(dir: &Path, prefix: &str) -> Result<Vec<String>, GitError> {
+    let files = output(dir, &["ls-files", "-z", "--", prefix]).await.map_err(GitError::Inspect)?;
+
+    let mut paths = BTreeSet::new();
+    for file in files.split('\0').filter(|file| !file.is_empty()) {
+        let mut path = Path::new(file);
+        while path.starts_with(prefix) && path != Path::new(prefix) {
+            if let Some(path) = path.to_str() {
+                paths.insert(path.to_string());
+            }
+            path = path.parent().unwrap_or(Path::new(""));
+        }
+    }
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut args = vec!["check-attr", "-z", "export-ignore", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    let attributes = output(dir, &args).await.map_err(GitError::Inspect)?;
+
+    // Entries come as path, attribute and value, each ended by a NUL
+    let fields: Vec<&str> = attributes.split('\0').collect();
+    Ok(fields
+        .chunks_exact(3)
+        .filter(|entry| entry[2] == "set")
+        .map(|entry| entry[0].to_string())
+        .collect())
+}
+
+/// Removes paths from the index and the working tree, ignoring those which
+/// are not tracked.
+pub async fn remove(dir: &Path, paths: &[String]) -> Result<(), GitError> {
+    let mut args = vec!["rm", "-r", "-q", "-f", "--ignore-unmatch", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    git(dir, &args).await.map_err(GitError::StageFiles)
+}
+
+/// Stages a single file of the working directory.
+#[inline]
+pub async fn stage_file(dir: &Path, path: &str) -> Result<(), GitError> {
+    git(dir, &["add", "--", path]).await.map_err(GitError::StageFiles)
+}
+
 /// Stages all files in the working directory.
 #[inline]
 pub async fn stage(dir: &Path) -> Result<(), GitError> {
@@ -56,6 +206,12 @@ pub async fn commit(dir: &Path, message: &str) -> Result<(), GitError> {
     git(dir, &["commit", "-m", message]).await.map_err(GitError::CommitChanges)
 }
 
+/// Removes a remote repository along with its credentials.
+#[inline]
+pub async fn remove_remote(dir: &Path, remote_name: &str) -> Result<(), GitError> {
+    git(dir, &["remote", "remove", remote_name]).await.map_err(GitError::ConfigError)
+}
+
 /// Adds a remote repository.
 #[inline]
 pub async fn add_remote(dir: &Path, remote_name: &str, remote_url: &str) -> Result<(), GitError> {
@@ -68,24 +224,245 @@ pub async fn push(dir: &Path, remote_name: &str, branch: &str) -> Result<(), Git
     git(dir, &["push", "--force", remote_name, branch]).await.map_err(GitError::PushChanges)
 }
 
+/// Returns the commit a reference points to.
+pub async fn rev_parse(dir: &Path, reference: &str) -> Result<String, GitError> {
+    let commit = output(dir, &["rev-parse", "--verify", "--end-of-options", reference])
+        .await
+        .map_err(GitError::Inspect)?;
+    Ok(commit.trim().to_string())
+}
+
+/// Returns the first commit of the history of a reference, the oldest one
+/// when it merged unrelated histories.
+pub async fn root_commit(dir: &Path, reference: &str) -> Result<String, GitError> {
+    let roots = output(dir, &["rev-list", "--max-parents=0", reference])
+        .await
+        .map_err(GitError::Inspect)?;
+    roots
+        .lines()
+        .last()
+        .map(str::to_string)
+        .ok_or_else(|| GitError::Inspect(format!("{reference} has no commits")))
+}
+
+/// Returns the parents of a commit.
+pub async fn parents(dir: &Path, commit: &str) -> Result<Vec<String>, GitError> {
+    let line = output(dir, &["rev-list", "--parents", "-n", "1", commit])
+        .await
+        .map_err(GitError::Inspect)?;
+    Ok(line.split_whitespace().skip(1).map(str::to_string).collect())
+}
+
+/// Finds the newest commit in the history of a reference with the given
+/// tree.
+pub async fn find_by_tree(
+    dir: &Path,
+    reference: &str,
+    tree: &str,
+) -> Result<Option<String>, GitError> {
+    let log =
+        output(dir, &["log", "--format=%H %T", reference]).await.map_err(GitError::Inspect)?;
+    Ok(log.lines().find_map(|line| {
+        let (commit, commit_tree) = line.split_once(' ')?;
+        (commit_tree == tree).then(|| commit.to_string())
+    }))
+}
+
+/// Returns the best common ancestor of two commits, if they have one.
+pub async fn merge_base(dir: &Path, a: &str, b: &str) -> Result<Option<String>, GitError> {
+    match run(dir, &["merge-base", a, b]).await.map_err(GitError::Inspect)? {
+        Some(base) => Ok(Some(base.trim().to_string())),
+        None => Ok(None),
+    }
+}
+
+/// Whether the commit is an ancestor of, or the same as, the other one.
+pub async fn is_ancestor(dir: &Path, ancestor: &str, commit: &str) -> Result<bool, GitError> {
+    let result = run(dir, &["merge-base", "--is-ancestor", ancestor, commit]).await;
+    Ok(result.map_err(GitError::Inspect)?.is_some())
+}
+
+/// Makes the commit appear to have the given parent in the local history,
+/// without rewriting it.
+#[inline]
+pub async fn graft(dir: &Path, commit: &str, parent: &str) -> Result<(), GitError> {
+    git(dir, &["replace", "--graft", commit, parent]).await.map_err(GitError::Merge)
+}
+
+/// Creates or resets a branch at the current commit and checks it out.
+#[inline]
+pub async fn checkout_branch(dir: &Path, branch: &str) -> Result<(), GitError> {
+    git(dir, &["checkout", "-B", branch]).await.map_err(GitError::Checkout)
+}
+
+/// Merges a commit into the current branch with a merge commit, returning
+/// false and leaving the conflicts in the working tree if it does not merge
+/// cleanly.
+pub async fn merge(dir: &Path, commit: &str, message: &str) -> Result<bool, GitError> {
+    let result = run(dir, &["merge", "--no-ff", "--no-edit", "-m", message, commit]).await;
+    Ok(result.map_err(GitError::Merge)?.is_some())
+}
+
+/// Lists the paths left conflicted by a merge.
+pub async fn conflicted_files(dir: &Path) -> Result<Vec<String>, GitError> {
+    let names = output(dir, &["diff", "--name-only", "--diff-filter=U", "-z"])
+        .await
+        .map_err(GitError::Inspect)?;
+    Ok(names.split('\0').filter(|name| !name.is_empty()).map(str::to_string).collect())
+}
+
 /// Configures Git settings for the repository.
 #[inline]
 pub async fn config(dir: &Path, key: &str, value: &str) -> Result<(), GitError> {
     git(dir, &["config", key, value]).await.map_err(GitError::ConfigError)
 }
 
+/// Executes a Git command which exits with status 1 to answer no, returning
+/// its standard output on success and none on a no, or a raw error message
+/// if failed otherwise.
+async fn run(dir: &Path, args: &[&str]) -> Result<Option<String>, String> {
+    let output = command(dir, args).output().await.map_err(|e| e.to_string())?;
+    match output.status.code() {
+        Some(0) => Ok(Some(String::from_utf8_lossy(&output.stdout).to_string())),
+        Some(1) => Ok(None),
+        _ => Err(String::from_utf8_lossy(&output.stderr).to_string()),
+    }
+}
+
 /// Executes a Git command and returns a raw error message if failed.
 async fn git(dir: &Path, args: &[&str]) -> Result<(), String> {
-    let output = Command::new("git")
-        .args(args)
-        .current_dir(dir)
-        .output()
-        .await
-        .map_err(|e| e.to_string())?;
+    output(dir, args).await.map(drop)
+}
+
+/// Executes a Git command and returns its standard output, or a raw error
+/// message if failed.
+async fn output(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = command(dir, args).output().await.map_err(|e| e.to_string())?;
 
     if !output.status.success() {
         return Err(String::from_utf8_lossy(&output.stderr).to_string());
     }
 
-    Ok(())
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Builds a Git command running in the directory, never prompting.
+fn command(dir: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new("git");
+    command.args(args).current_dir(dir).env("GIT_TERMINAL_PROMPT", "0");
+    command
+}
+
+/// A submodule declared in a `.gitmodules` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Submodule {
+    pub name: String,
+    pub path: String,
+    pub url: String,
+}
+
+impl Submodule {
+    /// Renders submodules as the content of a `.gitmodules` file.
+    pub fn render(submodules: &[Submodule]) -> String {
+        submodules
+            .iter()
+            .map(|sm| {
+                format!("[submodule \"{}\"]\n\tpath = {}\n\turl = {}\n", sm.name, sm.path, sm.url)
+            })
+            .collect()
+    }
+}
+
+/// Parses the submodules declared in a `.gitmodules` file, skipping those
+/// missing a path or a URL.
+pub fn parse_submodules(content: &str) -> Vec<Submodule> {
+    // Sections as their name, path and URL, other sections have no name
+    let mut sections: Vec<(Option<String>, Option<String>, Option<String>)> = Vec::new();
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            let name = section.strip_prefix("submodule").map(|name| name.trim().trim_matches('"'));
+            sections.push((name.map(str::to_string), None, None));
+            continue;
+        }
+
+        let (Some((Some(_), path, url)), Some((key, value))) =
+            (sections.last_mut(), line.split_once('='))
+        else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"').to_string();
+        match key.trim() {
+            "path" => *path = Some(value),
+            "url" => *url = Some(value),
+            _ => {}
+        }
+    }
+
+    sections
+        .into_iter()
+        .filter_map(|section| match section {
+            (Some(name), Some(path), Some(url)) => Some(Submodule { name, path, url }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Encodes a payload as a pkt-line of the git wire protocol.
+pub fn pkt_line(payload: &[u8]) -> Vec<u8> {
+    let mut line = format!("{:04x}", payload.len() + 4).into_bytes();
+    line.extend_from_slice(payload);
+    line
+}
+
+/// Builds a receive-pack result that makes `git push` fail with `message`.
+///
+/// Clients that negotiated a side-band get the message on the error band,
+/// the others get it as the unpack status of the report.
+pub fn receive_pack_error(message: &str, sideband: bool) -> Vec<u8> {
+    let mut body = if sideband {
+        pkt_line(format!("\x03{message}\n").as_bytes())
+    } else {
+        pkt_line(format!("unpack {message}\n").as_bytes())
+    };
+    body.extend_from_slice(b"0000");
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receive_pack_error() {
+        assert_eq!(pkt_line(b"a\n"), b"0006a\n");
+        assert_eq!(receive_pack_error("too large", false), b"0015unpack too large\n0000");
+        assert_eq!(receive_pack_error("too large", true), b"000f\x03too large\n0000");
+    }
+
+    #[test]
+    fn test_parse_submodules() {
+        let content = "# Vendored\n\
+            [submodule \"template/harness\"]\n\
+            \tpath = template/harness\n\
+            \turl = https://github.com/org/harness.git\n\
+            [core]\n\
+            \tpath = ignored\n\
+            [submodule \"broken\"]\n\
+            \tpath = broken\n";
+
+        let submodules = parse_submodules(content);
+        assert_eq!(
+            submodules,
+            [Submodule {
+                name: "template/harness".into(),
+                path: "template/harness".into(),
+                url: "https://github.com/org/harness.git".into(),
+            }]
+        );
+        assert_eq!(parse_submodules(&Submodule::render(&submodules)), submodules);
+    }
 }
@@ -0,0 +1,161 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    io::{self, Write},
+    mem,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Buf, Bytes};
+use flate2::write::GzDecoder;
+use futures::Stream;
+
+/// Most decompressed bytes held before they are passed on.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A byte stream inflating the gzip stream it wraps as it is read, e.g. a
+/// request body sent with `Content-Encoding: gzip`.
+///
+/// Input is fed to the decoder only while less than 64 KiB of its output is
+/// waiting, so a highly compressed chunk does not inflate all at once.
+pub struct GunzipStream<S> {
+    inner: S,
+    decoder: GzDecoder<Vec<u8>>,
+    pending: Bytes,
+    done: bool,
+}
+
+impl<S> GunzipStream<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner, decoder: GzDecoder::new(Vec::new()), pending: Bytes::new(), done: false }
+    }
+
+    /// Fails the stream, which is not polled again.
+    fn fail(&mut self, e: io::Error) -> Poll<Option<io::Result<Bytes>>> {
+        self.done = true;
+        Poll::Ready(Some(Err(e)))
+    }
+
+    /// Takes the output of the decoder so far.
+    fn take(&mut self) -> Bytes {
+        Bytes::from(mem::take(self.decoder.get_mut()))
+    }
+}
+
+impl<S, E> Stream for GunzipStream<S>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            // Each write inflates at most one buffer of the decoder
+            while !this.pending.is_empty() && this.decoder.get_ref().len() < CHUNK_SIZE {
+                match this.decoder.write(&this.pending) {
+                    // Anything after the end of the gzip stream is dropped
+                    Ok(0) => this.pending.clear(),
+                    Ok(n) => this.pending.advance(n),
+                    Err(e) => return this.fail(e),
+                }
+            }
+            if this.decoder.get_ref().len() >= CHUNK_SIZE ||
+                (this.pending.is_empty() && !this.decoder.get_ref().is_empty())
+            {
+                return Poll::Ready(Some(Ok(this.take())));
+            }
+
+            match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => this.pending = chunk,
+                Poll::Ready(Some(Err(e))) => return this.fail(io::Error::other(e)),
+                Poll::Ready(None) => {
+                    if let Err(e) = this.decoder.try_finish() {
+                        return this.fail(e);
+                    }
+                    this.done = true;
+                    let rest = this.take();
+                    return Poll::Ready((!rest.is_empty()).then_some(Ok(rest)));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::{Compression, write::GzEncoder};
+    use futures::{StreamExt, stream};
+
+    use super::*;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// The data split in chunks of the given size.
+    fn chunked(data: Vec<u8>, size: usize) -> impl Stream<Item = io::Result<Bytes>> + Unpin {
+        let chunks: Vec<_> = data.chunks(size).map(|c| Ok(Bytes::copy_from_slice(c))).collect();
+        stream::iter(chunks)
+    }
+
+    #[tokio::test]
+    async fn test_inflates_across_chunks() {
+        let data: Vec<u8> = (0..200_000u32).flat_map(|n| n.to_le_bytes()).collect();
+        let stream = GunzipStream::new(chunked(gzip(&data), 7));
+
+        let chunks: Vec<_> = stream.collect().await;
+        let inflated: Vec<u8> = chunks.into_iter().flat_map(|c| c.unwrap()).collect();
+        assert_eq!(inflated, data);
+    }
+
+    #[tokio::test]
+    async fn test_output_is_bounded() {
+        // 64 MiB of zeros compress to a single small chunk
+        let compressed = gzip(&vec![0u8; 64 * 1024 * 1024]);
+        assert!(compressed.len() < CHUNK_SIZE);
+        let mut stream = GunzipStream::new(chunked(compressed, CHUNK_SIZE));
+
+        let mut total = 0;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.unwrap();
+            assert!(chunk.len() < 2 * CHUNK_SIZE, "{}", chunk.len());
+            total += chunk.len();
+        }
+        assert_eq!(total, 64 * 1024 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_data_fails() {
+        let mut stream = GunzipStream::new(chunked(b"not gzip at all".to_vec(), 4));
+        assert!(stream.next().await.unwrap().is_err());
+        assert!(stream.next().await.is_none());
+
+        // A stream cut short fails too
+        let mut truncated = gzip(b"refs/heads/main");
+        truncated.truncate(truncated.len() - 4);
+        let chunks: Vec<_> = GunzipStream::new(chunked(truncated, 4)).collect().await;
+        assert!(chunks.last().unwrap().is_err());
+    }
+}
@@ -0,0 +1,44 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// Last known availability of the Kubernetes API server.
+///
+/// Updated by the periodic health check and by failed API calls, so that
+/// callers can skip the cluster altogether during an outage.
+#[derive(Clone, Debug)]
+pub struct ClusterHealth {
+    available: Arc<AtomicBool>,
+}
+
+impl Default for ClusterHealth {
+    fn default() -> Self {
+        Self { available: Arc::new(AtomicBool::new(true)) }
+    }
+}
+
+impl ClusterHealth {
+    pub fn is_available(&self) -> bool {
+        self.available.load(Ordering::Acquire)
+    }
+
+    /// Records the outcome of a check, returning the previous availability.
+    pub fn set_available(&self, available: bool) -> bool {
+        self.available.swap(available, Ordering::AcqRel)
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    io,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::Stream;
+use thiserror::Error;
+
+/// Error raised once a stream carried more bytes than allowed
+#[derive(Debug, Error)]
+#[error("Stream exceeded the limit of {0} bytes")]
+pub struct SizeLimitExceeded(pub u64);
+
+/// Shared tally of the bytes forwarded by a [`LimitedStream`], readable after
+/// the stream has been handed over to a client or server.
+#[derive(Clone, Debug, Default)]
+pub struct ByteCounter {
+    inner: Arc<Tally>,
+}
+
+#[derive(Debug, Default)]
+struct Tally {
+    bytes: AtomicU64,
+    exceeded: AtomicBool,
+}
+
+impl ByteCounter {
+    /// Number of bytes forwarded so far.
+    pub fn bytes(&self) -> u64 {
+        self.inner.bytes.load(Ordering::Acquire)
+    }
+
+    /// Whether the stream was cut off because of its limit.
+    pub fn exceeded(&self) -> bool {
+        self.inner.exceeded.load(Ordering::Acquire)
+    }
+}
+
+/// A byte stream that is forwarded chunk by chunk while counting, and ends
+/// with a [`SizeLimitExceeded`] error as soon as the limit is crossed.
+///
+/// Nothing is buffered: the offending chunk is dropped and the inner stream
+/// is never polled again, so the sender stops being read from.
+pub struct LimitedStream<S> {
+    inner: S,
+    limit: Option<u64>,
+    counter: ByteCounter,
+    done: bool,
+}
+
+impl<S> LimitedStream<S> {
+    /// Wraps a stream, where a `limit` of `None` only counts the bytes.
+    pub fn new(inner: S, limit: Option<u64>) -> Self {
+        Self { inner, limit, counter: ByteCounter::default(), done: false }
+    }
+
+    /// Handle on the tally of this stream.
+    pub fn counter(&self) -> ByteCounter {
+        self.counter.clone()
+    }
+}
+
+impl<S, E> Stream for LimitedStream<S>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        let chunk = match Pin::new(&mut self.inner).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => chunk,
+            Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(io::Error::other(e)))),
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        let tally = &self.counter.inner;
+        let total =
+            tally.bytes.fetch_add(chunk.len() as u64, Ordering::AcqRel) + chunk.len() as u64;
+        if let Some(limit) = self.limit.filter(|limit| total > *limit) {
+            tally.exceeded.store(true, Ordering::Release);
+            self.done = true;
+            return Poll::Ready(Some(Err(io::Error::other(SizeLimitExceeded(limit)))));
+        }
+
+        Poll::Ready(Some(Ok(chunk)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use futures::{StreamExt, stream};
+
+    use super::*;
+
+    /// An endless stream of 64 KiB chunks, counting how often it is polled.
+    fn endless(polls: Arc<AtomicUsize>) -> impl Stream<Item = io::Result<Bytes>> + Unpin {
+        stream::repeat_with(move || {
+            polls.fetch_add(1, Ordering::SeqCst);
+            Ok(Bytes::from(vec![0u8; 64 * 1024]))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_stops_reading_once_exceeded() {
+        let polls = Arc::new(AtomicUsize::new(0));
+        let mut stream = LimitedStream::new(endless(polls.clone()), Some(1024 * 1024));
+        let counter = stream.counter();
+
+        let mut forwarded = 0;
+        while let Some(Ok(chunk)) = stream.next().await {
+            forwarded += chunk.len();
+        }
+
+        // Exactly the limit passes, the next chunk is refused
+        assert_eq!(forwarded, 1024 * 1024);
+        assert!(counter.exceeded());
+        assert_eq!(polls.load(Ordering::SeqCst), 17);
+
+        // The source is not read from anymore
+        assert!(stream.next().await.is_none());
+        assert_eq!(polls.load(Ordering::SeqCst), 17);
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_only_counts() {
+        let chunks = stream::iter((0..3).map(|_| Ok::<_, io::Error>(Bytes::from_static(b"abc"))));
+        let stream = LimitedStream::new(chunks, None);
+        let counter = stream.counter();
+
+        let chunks: Vec<_> = stream.collect().await;
+        assert!(chunks.iter().all(Result::is_ok));
+        assert_eq!(counter.bytes(), 9);
+        assert!(!counter.exceeded());
+    }
+}
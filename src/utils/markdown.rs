@@ -0,0 +1,132 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd, html};
+
+fn parser(markdown: &str) -> Parser<'_> {
+    Parser::new_ext(markdown, Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH)
+}
+
+/// Renders markdown to HTML, removing scripts, event handlers and any other
+/// markup that is unsafe to embed in a page.
+pub fn to_html(markdown: &str) -> String {
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, parser(markdown));
+    ammonia::clean(&unsafe_html)
+}
+
+/// Renders markdown to plain text, dropping the formatting and raw HTML.
+///
+/// Links and images keep their target as `text (url)`, and control
+/// characters are removed so the output is safe to print on a terminal.
+pub fn to_text(markdown: &str) -> String {
+    let mut out = String::new();
+    let mut links: Vec<String> = Vec::new();
+    let mut lists: Vec<Option<u64>> = Vec::new();
+
+    for event in parser(markdown) {
+        match event {
+            Event::Text(text) | Event::Code(text) => out.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => out.push('\n'),
+            Event::Rule => end_block(&mut out),
+            Event::Start(Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. }) => {
+                links.push(dest_url.to_string());
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                if let Some(url) = links.pop().filter(|url| !out.ends_with(url.as_str())) {
+                    out.push_str(&format!(" ({url})"));
+                }
+            }
+            Event::Start(Tag::List(start)) => lists.push(start),
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+                if lists.is_empty() {
+                    end_block(&mut out);
+                }
+            }
+            Event::Start(Tag::Item) => {
+                end_line(&mut out);
+                out.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(n)) => {
+                        out.push_str(&format!("{n}. "));
+                        *n += 1;
+                    }
+                    _ => out.push_str("- "),
+                }
+            }
+            Event::End(TagEnd::Item) => end_line(&mut out),
+            Event::End(TagEnd::TableCell) => out.push('\t'),
+            Event::End(TagEnd::TableHead | TagEnd::TableRow) => end_line(&mut out),
+            Event::End(TagEnd::Paragraph) if !lists.is_empty() => end_line(&mut out),
+            Event::End(
+                TagEnd::Paragraph |
+                TagEnd::Heading(_) |
+                TagEnd::CodeBlock |
+                TagEnd::BlockQuote(_) |
+                TagEnd::Table,
+            ) => end_block(&mut out),
+            _ => {}
+        }
+    }
+
+    out.retain(|c| !c.is_control() || c == '\n' || c == '\t');
+    out.trim().to_string()
+}
+
+/// Terminates the current line, if any.
+fn end_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// Separates blocks by exactly one blank line.
+fn end_block(out: &mut String) {
+    let len = out.trim_end_matches('\n').len();
+    out.truncate(len);
+    if !out.is_empty() {
+        out.push_str("\n\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_html_is_sanitized() {
+        let html = to_html("# Title\n\n<script>alert(1)</script>\n\n[x](javascript:alert(1))");
+        assert!(html.contains("<h1>Title</h1>"));
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("javascript:"));
+    }
+
+    #[test]
+    fn test_to_text() {
+        let markdown = "# Scanning\n\nRead the [docs](https://docs.rs) and run `make`.\n\n\
+                        - one\n- **two**\n\n1. first\n2. second\n\n\
+                        ```sh\necho hi\n```\n\nSome <b>raw</b> \x1b[31mred";
+        assert_eq!(
+            to_text(markdown),
+            "Scanning\n\nRead the docs (https://docs.rs) and run make.\n\n\
+             - one\n- two\n\n1. first\n2. second\n\necho hi\n\nSome raw [31mred"
+        );
+    }
+
+    #[test]
+    fn test_to_text_autolink() {
+        assert_eq!(to_text("<https://stackclass.dev>"), "https://stackclass.dev");
+    }
+}
@@ -12,7 +12,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod clock;
 pub mod crypto;
+pub mod diff;
+pub mod endpoints;
 pub mod git;
+pub mod gzip;
+pub mod health;
+pub mod html;
 pub mod keys;
+pub mod limit;
+pub mod markdown;
+pub mod pagination;
+pub mod range;
+pub mod resources;
+pub mod settings;
+pub mod status;
+pub mod stream;
 pub mod url;
@@ -0,0 +1,220 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Keyset pagination over `(created_at, seq)`, newest first.
+//!
+//! `seq` is the insertion sequence of the table, which breaks ties between
+//! rows created within the same instant.
+//!
+//! A page is requested with `?cursor=&limit=`. The cursor is the opaque,
+//! signed position of the last item of the previous page, so clients can
+//! only pass back the cursors they were handed. Repositories fetch one row
+//! more than the limit to learn whether another page follows:
+//!
+//! ```sql
+//! WHERE ($1::TIMESTAMPTZ IS NULL OR (created_at, seq) < ($1, $2))
+//! ORDER BY created_at DESC, seq DESC
+//! LIMIT $3
+//! ```
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Duration, Utc};
+use thiserror::Error;
+
+use crate::utils::crypto::{self, CryptoError};
+
+/// Number of items of a page when the request does not ask for a limit.
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+
+/// Maximum number of items of a page, larger limits are clamped.
+pub const MAX_PAGE_SIZE: i64 = 200;
+
+/// Time a cursor stays valid after it was issued.
+pub const CURSOR_TTL: Duration = Duration::hours(24);
+
+/// Error type for cursors passed back by clients
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CursorError {
+    #[error("Malformed cursor")]
+    Malformed,
+
+    #[error("Cursor signature does not match")]
+    Tampered,
+
+    #[error("Cursor expired, restart from the first page")]
+    Expired,
+}
+
+impl CursorError {
+    /// Machine readable code of the error, returned alongside the message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CursorError::Malformed => "cursor_malformed",
+            CursorError::Tampered => "cursor_tampered",
+            CursorError::Expired => "cursor_expired",
+        }
+    }
+}
+
+/// Position of an item in a listing ordered by `(created_at, seq)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub seq: i64,
+}
+
+impl Cursor {
+    pub fn new(created_at: DateTime<Utc>, seq: i64) -> Self {
+        Self { created_at, seq }
+    }
+
+    /// Encodes the cursor into an opaque token issued at `now`, signed with
+    /// the secret.
+    pub fn encode(&self, now: DateTime<Utc>, secret: &str) -> Result<String, CryptoError> {
+        let expires_at = (now + CURSOR_TTL).timestamp();
+        let payload = format!("{}.{}.{}", self.created_at.timestamp_micros(), self.seq, expires_at);
+        let signature = crypto::hmac_sha256_sign(&payload, secret)?;
+        Ok(URL_SAFE_NO_PAD.encode(format!("{payload}.{signature}")))
+    }
+
+    /// Decodes a token issued by [`Cursor::encode`], rejecting tokens that
+    /// were altered or have expired at `now`.
+    pub fn decode(token: &str, now: DateTime<Utc>, secret: &str) -> Result<Self, CursorError> {
+        let decoded = URL_SAFE_NO_PAD.decode(token).map_err(|_| CursorError::Malformed)?;
+        let decoded = String::from_utf8(decoded).map_err(|_| CursorError::Malformed)?;
+        let (payload, signature) = decoded.rsplit_once('.').ok_or(CursorError::Malformed)?;
+
+        let verified = crypto::hmac_sha256_verify(payload, secret, signature);
+        if !verified.map_err(|_| CursorError::Tampered)? {
+            return Err(CursorError::Tampered);
+        }
+
+        let mut parts = payload.split('.');
+        let (Some(micros), Some(seq), Some(expires_at), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(CursorError::Malformed);
+        };
+
+        let expires_at: i64 = expires_at.parse().map_err(|_| CursorError::Malformed)?;
+        if now.timestamp() > expires_at {
+            return Err(CursorError::Expired);
+        }
+
+        let micros = micros.parse().map_err(|_| CursorError::Malformed)?;
+        let created_at = DateTime::from_timestamp_micros(micros).ok_or(CursorError::Malformed)?;
+        let seq = seq.parse().map_err(|_| CursorError::Malformed)?;
+        Ok(Self { created_at, seq })
+    }
+}
+
+/// The page of a listing requested through `?cursor=&limit=`, with the
+/// cursor verified and the limit clamped.
+#[derive(Debug, Clone, Copy)]
+pub struct Page {
+    /// Position after which the page starts, `None` for the first page
+    pub after: Option<Cursor>,
+
+    /// Maximum number of items of the page
+    pub limit: i64,
+}
+
+impl Page {
+    /// The first page with the given limit.
+    pub fn first(limit: i64) -> Self {
+        Self { after: None, limit: clamp_limit(Some(limit)) }
+    }
+
+    /// Number of rows to fetch, one more than the limit to tell whether
+    /// another page follows.
+    pub fn fetch_limit(&self) -> i64 {
+        self.limit + 1
+    }
+}
+
+/// Clamps the requested page size to `1..=MAX_PAGE_SIZE`.
+pub fn clamp_limit(limit: Option<i64>) -> i64 {
+    limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+}
+
+/// Number of items ahead of a numbered page, `None` when the page is below 1
+/// or so far out that the offset overflows.
+pub fn page_offset(page: i64, per_page: i64) -> Option<i64> {
+    match page {
+        1.. => (page - 1).checked_mul(per_page),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "secret";
+
+    fn cursor() -> Cursor {
+        let created_at = DateTime::from_timestamp_micros(1_735_689_600_123_456).unwrap();
+        Cursor::new(created_at, 42)
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let now = Utc::now();
+        let cursor = cursor();
+        let token = cursor.encode(now, SECRET).unwrap();
+        assert_eq!(Cursor::decode(&token, now, SECRET), Ok(cursor));
+        assert_eq!(Cursor::decode(&token, now + CURSOR_TTL, SECRET), Ok(cursor));
+    }
+
+    #[test]
+    fn test_tampered() {
+        let now = Utc::now();
+        let token = cursor().encode(now, SECRET).unwrap();
+        assert_eq!(Cursor::decode(&token, now, "other"), Err(CursorError::Tampered));
+
+        // Moving the position keeps the old signature
+        let decoded = String::from_utf8(URL_SAFE_NO_PAD.decode(&token).unwrap()).unwrap();
+        let forged = URL_SAFE_NO_PAD.encode(decoded.replacen("1735689600123456", "1", 1));
+        assert_eq!(Cursor::decode(&forged, now, SECRET), Err(CursorError::Tampered));
+
+        assert_eq!(Cursor::decode("not a cursor", now, SECRET), Err(CursorError::Malformed));
+        assert_eq!(Cursor::decode("", now, SECRET), Err(CursorError::Malformed));
+    }
+
+    #[test]
+    fn test_expired() {
+        let now = Utc::now();
+        let token = cursor().encode(now, SECRET).unwrap();
+        let later = now + CURSOR_TTL + Duration::seconds(1);
+        assert_eq!(Cursor::decode(&token, later, SECRET), Err(CursorError::Expired));
+    }
+
+    #[test]
+    fn test_clamp_limit() {
+        assert_eq!(clamp_limit(None), DEFAULT_PAGE_SIZE);
+        assert_eq!(clamp_limit(Some(0)), 1);
+        assert_eq!(clamp_limit(Some(10)), 10);
+        assert_eq!(clamp_limit(Some(10_000)), MAX_PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_page_offset() {
+        assert_eq!(page_offset(1, 50), Some(0));
+        assert_eq!(page_offset(3, 50), Some(100));
+        assert_eq!(page_offset(0, 50), None);
+        assert_eq!(page_offset(-1, 50), None);
+        assert_eq!(page_offset(i64::MAX, 50), None);
+        assert_eq!(page_offset(i64::MAX, 1), Some(i64::MAX - 1));
+    }
+}
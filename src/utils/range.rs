@@ -0,0 +1,122 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Single byte ranges of the `Range` header, see RFC 9110 section 14.
+
+/// A byte range of a resource, both bounds inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    /// Number of bytes in the range.
+    pub fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// Whether the range is empty, which a parsed range never is.
+    pub fn is_empty(&self) -> bool {
+        self.end < self.start
+    }
+
+    /// Value of the `Content-Range` header serving this range.
+    pub fn content_range(&self, size: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, size)
+    }
+}
+
+/// The requested range lies outside of the resource.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Unsatisfiable;
+
+/// Parses the `Range` header against a resource of the given size.
+///
+/// Returns `None` when the whole resource is to be served: without header,
+/// and for other units, malformed values and multiple ranges, all of which
+/// may be ignored.
+pub fn parse(header: Option<&str>, size: u64) -> Result<Option<ByteRange>, Unsatisfiable> {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else { return Ok(None) };
+
+    let range = match (start.parse::<u64>().ok(), end.parse::<u64>().ok()) {
+        // The last bytes of the resource
+        (None, Some(suffix)) if start.is_empty() => {
+            if suffix == 0 || size == 0 {
+                return Err(Unsatisfiable);
+            }
+            ByteRange { start: size.saturating_sub(suffix), end: size - 1 }
+        }
+        // From an offset to the end of the resource
+        (Some(start), None) if end.is_empty() => ByteRange { start, end: size.saturating_sub(1) },
+        (Some(start), Some(end)) if start <= end => {
+            ByteRange { start, end: end.min(size.saturating_sub(1)) }
+        }
+        _ => return Ok(None),
+    };
+
+    match range.start < size {
+        true => Ok(Some(range)),
+        false => Err(Unsatisfiable),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: u64, end: u64) -> Result<Option<ByteRange>, Unsatisfiable> {
+        Ok(Some(ByteRange { start, end }))
+    }
+
+    #[test]
+    fn test_parse_ranges() {
+        assert_eq!(parse(Some("bytes=0-99"), 1000), range(0, 99));
+        assert_eq!(parse(Some("bytes=500-"), 1000), range(500, 999));
+        assert_eq!(parse(Some("bytes=-100"), 1000), range(900, 999));
+        assert_eq!(parse(Some("bytes=-5000"), 1000), range(0, 999));
+        assert_eq!(parse(Some("bytes=900-5000"), 1000), range(900, 999));
+        assert_eq!(parse(Some("bytes=999-999"), 1000), range(999, 999));
+    }
+
+    #[test]
+    fn test_whole_resource_is_served() {
+        for header in [None, Some("items=0-1"), Some("bytes=0-1,5-6"), Some("bytes=a-b")] {
+            assert_eq!(parse(header, 1000), Ok(None), "{header:?}");
+        }
+        assert_eq!(parse(Some("bytes=9-1"), 1000), Ok(None));
+    }
+
+    #[test]
+    fn test_unsatisfiable_ranges() {
+        assert_eq!(parse(Some("bytes=1000-"), 1000), Err(Unsatisfiable));
+        assert_eq!(parse(Some("bytes=1000-2000"), 1000), Err(Unsatisfiable));
+        assert_eq!(parse(Some("bytes=-0"), 1000), Err(Unsatisfiable));
+        assert_eq!(parse(Some("bytes=0-"), 0), Err(Unsatisfiable));
+        assert_eq!(parse(Some("bytes=-1"), 0), Err(Unsatisfiable));
+    }
+
+    #[test]
+    fn test_content_range() {
+        let range = ByteRange { start: 900, end: 999 };
+        assert_eq!(range.len(), 100);
+        assert_eq!(range.content_range(1000), "bytes 900-999/1000");
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+
+use serde_json::{Value, json};
+use thiserror::Error;
+
+/// Error type for parsing pod resources from the configuration
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ResourcesError {
+    #[error("Expected `<resource>=<request>/<limit>`, got `{0}`")]
+    Malformed(String),
+
+    #[error("Unknown resource `{0}`, expected `cpu` or `memory`")]
+    UnknownResource(String),
+
+    #[error("Missing the `{0}` resource")]
+    Missing(&'static str),
+}
+
+/// CPU and memory requests and limits of a pod, in Kubernetes quantities.
+///
+/// Parsed from the configuration in the `cpu=<request>/<limit>,memory=<request>/<limit>`
+/// format, e.g. `cpu=500m/1,memory=512Mi/1Gi`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PodResources {
+    pub cpu_request: String,
+    pub cpu_limit: String,
+    pub memory_request: String,
+    pub memory_limit: String,
+}
+
+impl PodResources {
+    /// Renders the resources as a Kubernetes `ResourceRequirements` object.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "requests": { "cpu": self.cpu_request, "memory": self.memory_request },
+            "limits": { "cpu": self.cpu_limit, "memory": self.memory_limit },
+        })
+    }
+
+    /// CPU limit in units of 10^-9 CPUs, as taken by container runtimes.
+    pub fn nano_cpus(&self) -> Option<i64> {
+        let (value, scale) = match self.cpu_limit.strip_suffix('m') {
+            Some(millis) => (millis, 1e6),
+            None => (self.cpu_limit.as_str(), 1e9),
+        };
+        quantity(value, scale)
+    }
+
+    /// Memory limit in bytes.
+    pub fn memory_bytes(&self) -> Option<i64> {
+        bytes(&self.memory_limit)
+    }
+}
+
+/// Number of bytes of a Kubernetes quantity like `512Mi` or `1G`, `None`
+/// unless it is positive.
+pub fn bytes(amount: &str) -> Option<i64> {
+    const SUFFIXES: [(&str, f64); 8] = [
+        ("Ki", 1024.0),
+        ("Mi", 1048576.0),
+        ("Gi", 1073741824.0),
+        ("Ti", 1099511627776.0),
+        ("k", 1e3),
+        ("M", 1e6),
+        ("G", 1e9),
+        ("T", 1e12),
+    ];
+
+    let suffix = SUFFIXES.iter().find_map(|(suffix, scale)| {
+        amount.strip_suffix(suffix).map(|value| quantity(value, *scale))
+    });
+    suffix.unwrap_or_else(|| quantity(amount, 1.0))
+}
+
+/// Scales a plain decimal quantity, `None` unless it is a positive number.
+fn quantity(value: &str, scale: f64) -> Option<i64> {
+    let value: f64 = value.parse().ok()?;
+    (value.is_finite() && value > 0.0).then(|| (value * scale).round() as i64)
+}
+
+impl FromStr for PodResources {
+    type Err = ResourcesError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mut cpu, mut memory) = (None, None);
+        for part in s.split(',').map(str::trim) {
+            let malformed = || ResourcesError::Malformed(part.to_string());
+            let (name, quantities) = part.split_once('=').ok_or_else(malformed)?;
+            let (request, limit) = quantities.split_once('/').ok_or_else(malformed)?;
+            if request.is_empty() || limit.is_empty() {
+                return Err(malformed());
+            }
+
+            let quantities = Some((request.to_string(), limit.to_string()));
+            match name.trim() {
+                "cpu" => cpu = quantities,
+                "memory" => memory = quantities,
+                other => return Err(ResourcesError::UnknownResource(other.to_string())),
+            }
+        }
+
+        let (cpu_request, cpu_limit) = cpu.ok_or(ResourcesError::Missing("cpu"))?;
+        let (memory_request, memory_limit) = memory.ok_or(ResourcesError::Missing("memory"))?;
+        Ok(Self { cpu_request, cpu_limit, memory_request, memory_limit })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let resources: PodResources = "cpu=500m/1, memory=512Mi/1Gi".parse().unwrap();
+        assert_eq!(
+            resources.to_json(),
+            json!({
+                "requests": { "cpu": "500m", "memory": "512Mi" },
+                "limits": { "cpu": "1", "memory": "1Gi" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let resources: PodResources = "cpu=250m/500m,memory=256Mi/512Mi".parse().unwrap();
+        assert_eq!(resources.nano_cpus(), Some(500_000_000));
+        assert_eq!(resources.memory_bytes(), Some(512 * 1024 * 1024));
+
+        let resources: PodResources = "cpu=1/1.5,memory=1G/2000000".parse().unwrap();
+        assert_eq!(resources.nano_cpus(), Some(1_500_000_000));
+        assert_eq!(resources.memory_bytes(), Some(2_000_000));
+
+        let resources: PodResources = "cpu=1/lots,memory=1Gi/-1Gi".parse().unwrap();
+        assert_eq!(resources.nano_cpus(), None);
+        assert_eq!(resources.memory_bytes(), None);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(
+            "cpu=500m".parse::<PodResources>(),
+            Err(ResourcesError::Malformed("cpu=500m".into()))
+        );
+        assert_eq!(
+            "cpu=1/2,gpu=1/1".parse::<PodResources>(),
+            Err(ResourcesError::UnknownResource("gpu".into()))
+        );
+        assert_eq!("cpu=1/2".parse::<PodResources>(), Err(ResourcesError::Missing("memory")));
+    }
+}
@@ -0,0 +1,84 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cache of the settings changed at runtime.
+//!
+//! Settings are stored in the database so that they survive restarts and
+//! apply to all replicas. Every replica reads them through its own cache,
+//! which is reloaded at most once per TTL, so a change made on one replica
+//! is effective on all others within the TTL.
+
+use std::{
+    collections::HashMap,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tracing::warn;
+
+use crate::{database::Database, repository::SettingRepository};
+
+/// Settings of a replica, reloaded from the database once stale.
+#[derive(Debug)]
+pub struct SettingsCache {
+    ttl: Duration,
+    entries: RwLock<Option<(Instant, HashMap<String, Value>)>>,
+}
+
+impl SettingsCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl, entries: RwLock::new(None) }
+    }
+
+    /// Gets the value of a setting, none if it is not set or does not have
+    /// the expected shape.
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        db: &Database,
+        key: &str,
+    ) -> sqlx::Result<Option<T>> {
+        let cached = self.entries.read().unwrap().as_ref().and_then(|(loaded_at, entries)| {
+            (loaded_at.elapsed() < self.ttl).then(|| entries.get(key).cloned())
+        });
+
+        let value = match cached {
+            Some(value) => value,
+            None => {
+                let entries: HashMap<String, Value> = SettingRepository::find_all(db)
+                    .await?
+                    .into_iter()
+                    .map(|setting| (setting.key, setting.value))
+                    .collect();
+                let value = entries.get(key).cloned();
+                *self.entries.write().unwrap() = Some((Instant::now(), entries));
+                value
+            }
+        };
+
+        Ok(value.and_then(|value| match serde_json::from_value(value) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!("Ignoring malformed setting {key}: {e}");
+                None
+            }
+        }))
+    }
+
+    /// Drops the cached settings, so that the next read reloads them.
+    pub fn invalidate(&self) {
+        *self.entries.write().unwrap() = None;
+    }
+}
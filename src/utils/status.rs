@@ -0,0 +1,178 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Push updates of user courses to the open status streams.
+//!
+//! Updates of a user course, its stages or their attempts notify the
+//! [`STATUS_CHANNEL`] of PostgreSQL with the id of the user course, within
+//! the transaction making them. Every replica listens to the channel and
+//! hands the notifications to the streams it serves, so an update made on
+//! one replica, like the outcome of a pipeline, reaches the streams open on
+//! all others.
+//!
+//! Each notification also bumps the status version of the user course,
+//! which numbers the statuses sent so reconnecting clients are only sent the
+//! current one when they missed an update.
+
+use std::future::Future;
+
+use futures::{Stream, stream};
+use serde::Serialize;
+use sqlx::{PgPool, postgres::PgListener};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    task::JoinHandle,
+};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::errors::Result;
+
+/// PostgreSQL channel notified with the id of updated user courses.
+pub const STATUS_CHANNEL: &str = "user_course_status";
+
+/// Number of notifications buffered for a slow stream, which refreshes its
+/// status once it fell behind.
+const CAPACITY: usize = 1024;
+
+/// Fans the notifications of the database out to the status streams of the
+/// replica.
+#[derive(Clone)]
+pub struct StatusUpdates {
+    sender: broadcast::Sender<Uuid>,
+}
+
+impl Default for StatusUpdates {
+    fn default() -> Self {
+        Self { sender: broadcast::channel(CAPACITY).0 }
+    }
+}
+
+impl StatusUpdates {
+    /// Starts listening to the notifications of the database, returning once
+    /// the channel is listened to.
+    pub async fn start(&self, pool: &PgPool) -> sqlx::Result<JoinHandle<()>> {
+        let mut listener = PgListener::connect_with(pool).await?;
+        listener.listen(STATUS_CHANNEL).await?;
+
+        let sender = self.sender.clone();
+        Ok(tokio::spawn(async move {
+            loop {
+                // Notifications sent while the connection is re-established
+                // are lost, streams catch up with the next one
+                let notification = match listener.try_recv().await {
+                    Ok(Some(notification)) => notification,
+                    Ok(None) => {
+                        warn!("Lost the connection listening to status updates, reconnecting");
+                        continue;
+                    }
+                    Err(e) => {
+                        warn!("Failed to receive status updates: {e}");
+                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+
+                match notification.payload().parse() {
+                    Ok(id) => {
+                        let _ = sender.send(id);
+                    }
+                    Err(e) => debug!("Ignoring malformed status update: {e}"),
+                }
+            }
+        }))
+    }
+
+    /// Streams the status of a user course as produced by `fetch`, once right
+    /// away and again after every update of the user course which changed
+    /// it, until `done` tells the status is final.
+    ///
+    /// A client which has already seen the version `last_seen` is not sent
+    /// the current status again unless it is newer or final.
+    pub fn stream<T, F, Fut>(
+        &self,
+        user_course_id: Uuid,
+        last_seen: Option<i64>,
+        fetch: F,
+        done: fn(&T) -> bool,
+    ) -> impl Stream<Item = StatusUpdate<T>> + use<T, F, Fut>
+    where
+        T: Serialize,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<StatusUpdate<T>>>,
+    {
+        // Subscribe before the first fetch, so no update falls in between
+        let updates = self.sender.subscribe();
+        let state =
+            StreamState { updates, fetch, last_seen, last: None, started: false, finished: false };
+
+        stream::unfold(state, move |mut state| async move {
+            if state.finished {
+                return None;
+            }
+
+            loop {
+                if state.started {
+                    match state.updates.recv().await {
+                        Ok(id) if id != user_course_id => continue,
+                        // Updates might have been missed, refresh anyway
+                        Ok(_) | Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+                let first = !state.started;
+                state.started = true;
+
+                // A status that cannot be produced now is retried on the next
+                // update, and one that did not change is not sent again
+                let Ok(update) = (state.fetch)().await else {
+                    continue;
+                };
+                let json = serde_json::to_string(&update.status).ok();
+                if json.is_some() && json == state.last {
+                    continue;
+                }
+
+                // The client reconnected without missing anything. The status
+                // is not remembered, as it may have been read after an update
+                // newer than its version
+                let finished = done(&update.status);
+                let seen = state.last_seen.is_some_and(|seen| update.version <= seen);
+                if first && seen && !finished {
+                    continue;
+                }
+
+                state.last = json;
+                state.finished = finished;
+                return Some((update, state));
+            }
+        })
+    }
+}
+
+/// A status along with the version of the user course it was produced at.
+pub struct StatusUpdate<T> {
+    pub version: i64,
+    pub status: T,
+}
+
+/// Progress of a status stream.
+struct StreamState<F> {
+    updates: broadcast::Receiver<Uuid>,
+    fetch: F,
+    last_seen: Option<i64>,
+    last: Option<String>,
+    started: bool,
+    finished: bool,
+}
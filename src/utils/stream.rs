@@ -0,0 +1,374 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{http::HeaderMap, response::sse::Event};
+use dashmap::{DashMap, mapref::entry::Entry};
+use futures::{Stream, StreamExt, stream};
+use serde::Serialize;
+use thiserror::Error;
+use tokio::time::Instant;
+use tracing::error;
+
+use crate::response::{StreamErrorEvent, StreamTimeoutEvent};
+
+/// Header with the id of the last event a reconnecting client received.
+pub const LAST_EVENT_ID: &str = "last-event-id";
+
+/// Error type for stream admission
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum StreamLimitError {
+    #[error("Too many concurrent streams for this user (limit: {0})")]
+    UserLimitReached(usize),
+
+    #[error("Too many concurrent streams on this server (limit: {0})")]
+    TotalLimitReached(usize),
+}
+
+/// Keeps track of the open streaming connections, per user and in total.
+#[derive(Clone)]
+pub struct StreamTracker {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    per_user: DashMap<String, usize>,
+    total: AtomicUsize,
+    max_per_user: usize,
+    max_total: usize,
+}
+
+impl StreamTracker {
+    pub fn new(max_per_user: usize, max_total: usize) -> Self {
+        let inner =
+            Inner { per_user: DashMap::new(), total: AtomicUsize::new(0), max_per_user, max_total };
+        Self { inner: Arc::new(inner) }
+    }
+
+    /// Reserves a stream slot for the given user. The slot is released when
+    /// the returned guard is dropped.
+    pub fn acquire(&self, user_id: &str) -> Result<StreamGuard, StreamLimitError> {
+        let inner = &self.inner;
+
+        // Reserve a global slot first, so the per-user counter is never
+        // incremented for a stream that is going to be rejected anyway.
+        inner
+            .total
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < inner.max_total).then_some(n + 1)
+            })
+            .map_err(|_| StreamLimitError::TotalLimitReached(inner.max_total))?;
+
+        let admitted = match inner.per_user.entry(user_id.to_string()) {
+            Entry::Occupied(mut entry) if *entry.get() < inner.max_per_user => {
+                *entry.get_mut() += 1;
+                true
+            }
+            Entry::Vacant(entry) if inner.max_per_user > 0 => {
+                entry.insert(1);
+                true
+            }
+            _ => false,
+        };
+
+        if !admitted {
+            inner.total.fetch_sub(1, Ordering::AcqRel);
+            return Err(StreamLimitError::UserLimitReached(inner.max_per_user));
+        }
+
+        Ok(StreamGuard { inner: self.inner.clone(), user_id: user_id.to_string() })
+    }
+
+    /// Number of streams currently open across all users.
+    pub fn total(&self) -> usize {
+        self.inner.total.load(Ordering::Acquire)
+    }
+
+    /// Number of users with at least one stream open.
+    pub fn users(&self) -> usize {
+        self.inner.per_user.len()
+    }
+
+    /// Number of streams currently open for the given user.
+    pub fn count(&self, user_id: &str) -> usize {
+        self.inner.per_user.get(user_id).map(|n| *n).unwrap_or(0)
+    }
+
+    pub fn max_per_user(&self) -> usize {
+        self.inner.max_per_user
+    }
+
+    pub fn max_total(&self) -> usize {
+        self.inner.max_total
+    }
+}
+
+/// A reserved stream slot, released on drop.
+///
+/// The guard must be owned by the response stream itself, so that every way
+/// the stream can end (client disconnect, normal termination, shutdown)
+/// gives the slot back.
+pub struct StreamGuard {
+    inner: Arc<Inner>,
+    user_id: String,
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        self.inner.per_user.remove_if_mut(&self.user_id, |_, n| {
+            *n -= 1;
+            *n == 0
+        });
+        self.inner.total.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Builds a server-sent event carrying `data` as JSON. An update that cannot
+/// be serialized is replaced by an `error` event with a [`StreamErrorEvent`],
+/// so clients always receive a parseable payload.
+pub fn json_event<T: Serialize>(data: &T) -> Event {
+    match serde_json::to_string(data) {
+        Ok(json) => Event::default().data(json),
+        Err(e) => error_event(e),
+    }
+}
+
+/// Builds a server-sent event named `name` carrying `data` as JSON, with the
+/// same fallback as [`json_event`].
+pub fn named_json_event<T: Serialize>(name: &str, data: &T) -> Event {
+    match serde_json::to_string(data) {
+        Ok(json) => Event::default().event(name).data(json),
+        Err(e) => error_event(e),
+    }
+}
+
+/// Builds a server-sent event like [`named_json_event`], with `id` as the id
+/// clients resume from. An update replaced by an `error` event has no id, so
+/// it is sent again on reconnection.
+pub fn identified_json_event<T: Serialize>(name: &str, id: i64, data: &T) -> Event {
+    match serde_json::to_string(data) {
+        Ok(json) => Event::default().event(name).id(id.to_string()).data(json),
+        Err(e) => error_event(e),
+    }
+}
+
+/// Id of the last event received by a reconnecting client, sent in the
+/// `Last-Event-ID` header. Ids that are not ours are ignored.
+pub fn last_event_id(headers: &HeaderMap) -> Option<i64> {
+    headers.get(LAST_EVENT_ID)?.to_str().ok()?.parse().ok()
+}
+
+fn error_event(e: serde_json::Error) -> Event {
+    error!("Failed to serialize status update: {}", e);
+    let payload = StreamErrorEvent::new(format!("Failed to serialize status update: {e}"));
+    let json = serde_json::to_string(&payload).unwrap_or_default();
+    Event::default().event("error").data(json)
+}
+
+/// Ends `stream` once it has been open for `lifetime`, with a `timeout` event
+/// carrying a [`StreamTimeoutEvent`] so clients know to reconnect.
+pub fn with_lifetime<S>(stream: S, lifetime: Duration) -> impl Stream<Item = Event>
+where
+    S: Stream<Item = Event>,
+{
+    let deadline = Instant::now() + lifetime;
+    stream::unfold(Some(Box::pin(stream)), move |stream| async move {
+        let mut stream = stream?;
+        tokio::select! {
+            event = stream.next() => event.map(|event| (event, Some(stream))),
+            _ = tokio::time::sleep_until(deadline) => {
+                Some((named_json_event("timeout", &StreamTimeoutEvent::new(lifetime)), None))
+            }
+        }
+    })
+}
+
+/// Removes ANSI escape sequences, like colors and cursor movements, and
+/// other control characters but tabs from a line of terminal output.
+pub fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            if !c.is_control() || c == '\t' {
+                out.push(c);
+            }
+            continue;
+        }
+
+        match chars.next() {
+            // CSI, up to the final byte
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC, up to the BEL or ST terminator
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            // nF sequences, like character set designations, up to the final byte
+            Some(c) if (' '..='/').contains(&c) => {
+                for c in chars.by_ref() {
+                    if !(' '..='/').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // Two-character sequences
+            _ => {}
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use axum::response::{IntoResponse, Sse};
+    use serde::Serializer;
+    use serde_json::Value;
+
+    use super::*;
+
+    struct Unserializable;
+
+    impl Serialize for Unserializable {
+        fn serialize<S: Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("boom"))
+        }
+    }
+
+    /// Renders a single event the way it is sent to the client.
+    async fn render(event: Event) -> String {
+        let stream = futures::stream::once(async { Ok::<_, Infallible>(event) });
+        let body = Sse::new(stream).into_response().into_body();
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_json_event() {
+        let text = render(json_event(&serde_json::json!({ "status": "completed" }))).await;
+        assert_eq!(text, "data: {\"status\":\"completed\"}\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_unserializable_data_becomes_error_event() {
+        let text = render(json_event(&Unserializable)).await;
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("event: error"));
+
+        let data = lines.next().unwrap().strip_prefix("data: ").unwrap();
+        let payload: Value = serde_json::from_str(data).unwrap();
+        assert_eq!(payload["type"], "error");
+        assert!(payload["message"].as_str().unwrap().contains("boom"));
+    }
+
+    #[tokio::test]
+    async fn test_named_json_event() {
+        let text = render(named_json_event("done", &serde_json::json!({ "n": 1 }))).await;
+        assert_eq!(text, "event: done\ndata: {\"n\":1}\n\n");
+
+        // The name of the event is replaced along with its data
+        let text = render(named_json_event("done", &Unserializable)).await;
+        assert!(text.starts_with("event: error\n"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_ends_with_timeout_event() {
+        let stream = with_lifetime(stream::pending(), Duration::from_millis(50));
+        let events: Vec<_> = stream.collect().await;
+        assert_eq!(events.len(), 1);
+
+        let text = render(events.into_iter().next().unwrap()).await;
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("event: timeout"));
+        let data = lines.next().unwrap().strip_prefix("data: ").unwrap();
+        let payload: Value = serde_json::from_str(data).unwrap();
+        assert_eq!(payload["type"], "timeout");
+    }
+
+    #[tokio::test]
+    async fn test_stream_ending_in_time_has_no_timeout_event() {
+        let events = stream::iter([json_event(&1), json_event(&2)]);
+        let events: Vec<_> = with_lifetime(events, Duration::from_secs(60)).collect().await;
+
+        let mut texts = Vec::new();
+        for event in events {
+            texts.push(render(event).await);
+        }
+        assert_eq!(texts, ["data: 1\n\n", "data: 2\n\n"]);
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("\x1b[1;32mPASS\x1b[0m stage #1"), "PASS stage #1");
+        assert_eq!(strip_ansi("\x1b]0;title\x07ok\x1b]8;;url\x1b\\link"), "oklink");
+        assert_eq!(strip_ansi("a\tb\r\x1b(Bc\x1b"), "a\tbc");
+        assert_eq!(strip_ansi("ünïcode ✓"), "ünïcode ✓");
+    }
+
+    #[test]
+    fn test_per_user_limit() {
+        let tracker = StreamTracker::new(3, 100);
+
+        let mut guards: Vec<_> = (0..3).map(|_| tracker.acquire("alice").unwrap()).collect();
+        assert_eq!(tracker.count("alice"), 3);
+
+        // The N+1th stream is rejected, other users are not affected
+        assert_eq!(tracker.acquire("alice").err(), Some(StreamLimitError::UserLimitReached(3)));
+        let bob = tracker.acquire("bob").unwrap();
+        assert_eq!(tracker.total(), 4);
+
+        // Closing one stream frees a slot
+        guards.pop();
+        assert_eq!(tracker.count("alice"), 2);
+        guards.push(tracker.acquire("alice").unwrap());
+
+        drop(guards);
+        drop(bob);
+        assert_eq!(tracker.total(), 0);
+        assert_eq!(tracker.users(), 0);
+    }
+
+    #[test]
+    fn test_total_limit() {
+        let tracker = StreamTracker::new(10, 2);
+
+        let first = tracker.acquire("alice").unwrap();
+        let _second = tracker.acquire("bob").unwrap();
+        assert_eq!(tracker.acquire("carol").err(), Some(StreamLimitError::TotalLimitReached(2)));
+        assert_eq!(tracker.count("carol"), 0);
+
+        drop(first);
+        assert!(tracker.acquire("carol").is_ok());
+    }
+}
@@ -24,8 +24,26 @@ pub fn authenticate(url: &str, username: &str, password: &str) -> Result<String,
     Ok(parsed_url.to_string())
 }
 
-/// Extracts the host part from a URL, removing protocol and port.
-pub fn hostname(url: &str) -> Result<String, ParseError> {
-    let parsed_url = Url::parse(url)?;
-    parsed_url.host_str().map(|host| host.to_string()).ok_or(ParseError::EmptyHost)
+/// Resolves a submodule URL the way git does, where relative URLs are taken
+/// relative to the repository declaring them.
+pub fn resolve(base: &str, url: &str) -> Result<String, ParseError> {
+    if !url.starts_with("./") && !url.starts_with("../") {
+        return Ok(url.to_string());
+    }
+
+    let base = Url::parse(&format!("{}/", base.trim_end_matches('/')))?;
+    Ok(base.join(url)?.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let base = "https://github.com/org/course";
+        assert_eq!(resolve(base, "../harness.git").unwrap(), "https://github.com/org/harness.git");
+        assert_eq!(resolve(base, "./vendor").unwrap(), "https://github.com/org/course/vendor");
+        assert_eq!(resolve(base, "git@github.com:org/x.git").unwrap(), "git@github.com:org/x.git");
+    }
 }
@@ -0,0 +1,153 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Integrations read courses with scoped API tokens. These tests need a
+//! disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test api-token-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+use stackclass::{context::Context, routes, utils::crypto};
+use tower::ServiceExt;
+
+use common::{create_course, setup, unreachable_cluster};
+
+/// Sends a request with the given `Authorization` header value and body.
+async fn send(
+    ctx: &Arc<Context>,
+    method: Method,
+    uri: &str,
+    auth: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, auth)
+        .header(header::CONTENT_TYPE, "application/json");
+    let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+
+    let app = routes::build(ctx.clone());
+    let res = app.oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn admin(ctx: &Context) -> String {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    format!("Basic {}", STANDARD.encode(format!("admin:{password}")))
+}
+
+/// Creates a token for the courses and returns its id and secret.
+async fn create_token(
+    ctx: &Arc<Context>,
+    courses: &[&str],
+    capabilities: &[&str],
+) -> (String, String) {
+    let req = json!({ "name": "Moodle", "courses": courses, "capabilities": capabilities });
+    let (status, body) =
+        send(ctx, Method::POST, "/v1/admin/api-tokens", &admin(ctx), Some(req)).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    (body["id"].as_str().unwrap().to_string(), body["token"].as_str().unwrap().to_string())
+}
+
+async fn audit_actions(ctx: &Context, id: &str) -> Vec<(String, String)> {
+    sqlx::query_as("SELECT action, target || ' ' || details::TEXT FROM audit_logs WHERE actor = $1")
+        .bind(format!("api_token:{id}"))
+        .fetch_all(ctx.database.pool())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_token_is_scoped_to_courses_and_capabilities() {
+    let ctx = setup(unreachable_cluster()).await;
+    let (course_a, course_b) = (create_course(&ctx).await, create_course(&ctx).await);
+    let (id, secret) = create_token(&ctx, &[&course_a], &["progress"]).await;
+    assert!(secret.starts_with("sct_"));
+    let auth = format!("Token {secret}");
+
+    let uri = format!("/v1/admin/courses/{course_a}/progress");
+    assert_eq!(send(&ctx, Method::GET, &uri, &auth, None).await.0, StatusCode::OK);
+
+    // Other courses and capabilities are off limits
+    let uri = format!("/v1/admin/courses/{course_b}/progress");
+    assert_eq!(send(&ctx, Method::GET, &uri, &auth, None).await.0, StatusCode::FORBIDDEN);
+    let uri = format!("/v1/admin/courses/{course_a}/engagement");
+    assert_eq!(send(&ctx, Method::GET, &uri, &auth, None).await.0, StatusCode::FORBIDDEN);
+
+    // So are operational and admin endpoints
+    let uri = format!("/v1/admin/courses/{course_a}/integrity-flags");
+    assert_eq!(send(&ctx, Method::GET, &uri, &auth, None).await.0, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(&ctx, Method::GET, "/v1/admin/api-tokens", &auth, None).await;
+    assert_ne!(status, StatusCode::OK);
+
+    let uri = format!("/v1/admin/courses/{course_a}/progress");
+    let (status, _) = send(&ctx, Method::GET, &uri, "Token sct_unknown", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Every authenticated request is audited under the token id, never the secret
+    let logs = audit_actions(&ctx, &id).await;
+    assert_eq!(logs.len(), 3);
+    assert!(logs.iter().all(|(action, _)| action == "api_token_request"));
+    assert!(logs.iter().all(|(_, entry)| !entry.contains(&secret)));
+
+    // Unknown courses are refused upfront
+    let req = json!({ "name": "Canvas", "courses": ["unknown"], "capabilities": ["stats"] });
+    let (status, _) =
+        send(&ctx, Method::POST, "/v1/admin/api-tokens", &admin(&ctx), Some(req)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_secret_is_shown_once_and_revocation_applies_at_once() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let (id, secret) = create_token(&ctx, &[&slug], &["progress", "stats"]).await;
+    let auth = format!("Token {secret}");
+
+    let uri = format!("/v1/admin/courses/{slug}/engagement");
+    assert_eq!(send(&ctx, Method::GET, &uri, &auth, None).await.0, StatusCode::OK);
+
+    // The listing never carries the secret or its hash
+    let (status, body) = send(&ctx, Method::GET, "/v1/admin/api-tokens", &admin(&ctx), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let token = body.as_array().unwrap().iter().find(|t| t["id"] == id.as_str()).unwrap();
+    assert_eq!(token["courses"], json!([slug]));
+    assert_eq!(token["capabilities"], json!(["progress", "stats"]));
+    assert!(token["last_used_at"].is_string());
+    assert!(token.get("token").is_none());
+    assert!(!body.to_string().contains(&secret));
+    assert!(!body.to_string().contains(&crypto::sha256_hex(&secret)));
+
+    let revoke = format!("/v1/admin/api-tokens/{id}");
+    let (status, _) = send(&ctx, Method::DELETE, &revoke, &admin(&ctx), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(send(&ctx, Method::GET, &uri, &auth, None).await.0, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(&ctx, Method::DELETE, &revoke, &admin(&ctx), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
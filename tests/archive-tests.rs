@@ -0,0 +1,96 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Repositories of finished courses are archived when configured. These
+//! tests need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test archive-tests -- --ignored
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::{Json, Router, extract::Path, http::StatusCode, routing::patch};
+use serde_json::Value;
+use stackclass::{
+    context::Context,
+    repository::CourseRepository,
+    service::{CourseService, StageService},
+};
+
+use common::{create_course, enroll, mock_context};
+
+/// Repositories archived through the fake Gitea server.
+type Archived = Arc<Mutex<Vec<String>>>;
+
+/// A Gitea server recording the repositories archived on it, but failing
+/// to answer with the repository, which must not fail the completion.
+async fn context(archive_completed_repositories: bool) -> (Arc<Context>, Archived) {
+    let archived = Archived::default();
+    let recorded = archived.clone();
+    let app = Router::new().route(
+        "/api/v1/repos/{owner}/{repo}",
+        patch(move |Path((_, repo)): Path<(String, String)>, Json(body): Json<Value>| {
+            let recorded = recorded.clone();
+            async move {
+                if body["archived"] == true {
+                    recorded.lock().unwrap().push(repo);
+                }
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }),
+    );
+
+    let mut ctx = mock_context(app).await;
+    ctx.config.archive_completed_repositories = archive_completed_repositories;
+    (Arc::new(ctx), archived)
+}
+
+/// Completes the stages of the course in order, returning the repository
+/// of the learner.
+async fn complete(ctx: &Arc<Context>, slug: &str, stages: &[&str]) -> String {
+    let user_id = enroll(ctx, slug).await;
+    let mut user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+
+    for stage in stages {
+        let stage_slug = format!("{slug}-{stage}");
+        StageService::complete(ctx.clone(), &user_id, slug, &stage_slug, None).await.unwrap();
+    }
+    user_course.id.to_string()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_finished_course_archives_repository() {
+    let (ctx, archived) = context(true).await;
+    let slug = create_course(&ctx).await;
+
+    complete(&ctx, &slug, &["s1", "s2"]).await;
+    assert!(archived.lock().unwrap().is_empty());
+
+    let repo = complete(&ctx, &slug, &["s1", "s2", "e1"]).await;
+    assert_eq!(*archived.lock().unwrap(), [repo]);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_repositories_are_kept_by_default() {
+    let (ctx, archived) = context(false).await;
+    let slug = create_course(&ctx).await;
+
+    complete(&ctx, &slug, &["s1", "s2", "e1"]).await;
+    assert!(archived.lock().unwrap().is_empty());
+}
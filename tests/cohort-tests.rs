@@ -0,0 +1,192 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Learners enroll with an instructor-led cohort, whose progress instructors
+//! follow. These tests need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test cohort-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    errors::ApiError,
+    model::UserCourseModel,
+    repository::{CourseRepository, ProgressRepository},
+    request::CreateUserCourseRequest,
+    routes,
+    service::CourseService,
+    utils::crypto,
+};
+use tower::ServiceExt;
+
+use common::{create_course, create_user, enroll_user, setup, token, unreachable_cluster};
+
+const DEADLINE: &str = "2030-06-30T00:00:00Z";
+
+async fn send(
+    ctx: &Arc<Context>,
+    method: Method,
+    uri: &str,
+    authorization: String,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, authorization)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+        .unwrap();
+
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn admin(ctx: &Context) -> String {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    format!("Basic {}", STANDARD.encode(format!("admin:{password}")))
+}
+
+/// Enrolls a new learner with the cohort, and returns the user id.
+async fn learner(ctx: &Context, slug: &str, cohort: Option<&str>) -> String {
+    let user_id = create_user(ctx).await;
+    let course = CourseRepository::get_by_slug(&ctx.database, slug).await.unwrap();
+    let deadline = cohort.map(|_| DEADLINE.parse::<DateTime<Utc>>().unwrap());
+    let user_course = UserCourseModel::new(&user_id, &course.id).with_cohort(cohort, deadline);
+
+    let mut tx = ctx.database.pool().begin().await.unwrap();
+    let user_course = CourseRepository::create_user_course(&mut tx, &user_course).await.unwrap();
+    ProgressRepository::refresh(&mut tx, &user_course.id).await.unwrap();
+    tx.commit().await.unwrap();
+
+    user_id
+}
+
+/// The cohort and its deadline of the enrollment of the user.
+async fn cohort(ctx: &Arc<Context>, user_id: &str, slug: &str) -> (Value, Value) {
+    let bearer = format!("Bearer {}", token(ctx, user_id).await);
+    let (status, body) = send(ctx, Method::GET, "/v1/user/courses", bearer, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let course = body.as_array().unwrap().iter().find(|c| c["course_slug"] == json!(slug));
+    let course = course.unwrap();
+    (course["cohort"].clone(), course["cohort_deadline"].clone())
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_cohort_progress() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let spring =
+        [learner(&ctx, &slug, Some("spring")).await, learner(&ctx, &slug, Some("spring")).await];
+    learner(&ctx, &slug, Some("fall")).await;
+    learner(&ctx, &slug, None).await;
+
+    let uri = format!("/v1/courses/{slug}/cohorts/spring/progress");
+    let (status, body) = send(&ctx, Method::GET, &uri, admin(&ctx), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let mut users: Vec<&str> =
+        body.as_array().unwrap().iter().map(|p| p["user_id"].as_str().unwrap()).collect();
+    users.sort();
+    let mut expected: Vec<&str> = spring.iter().map(String::as_str).collect();
+    expected.sort();
+    assert_eq!(users, expected);
+    assert_eq!(body[0]["completed_stage_count"], json!(0));
+
+    let uri = format!("/v1/courses/{slug}/cohorts/winter/progress");
+    let (status, body) = send(&ctx, Method::GET, &uri, admin(&ctx), None).await;
+    assert_eq!((status, body), (StatusCode::OK, json!([])));
+
+    // Learners see their cohort, and are not instructors of it
+    let (cohort_name, deadline) = cohort(&ctx, &spring[0], &slug).await;
+    assert_eq!(cohort_name, json!("spring"));
+    let deadline: DateTime<Utc> = deadline.as_str().unwrap().parse().unwrap();
+    assert_eq!(deadline, DEADLINE.parse::<DateTime<Utc>>().unwrap());
+    let bearer = format!("Bearer {}", token(&ctx, &spring[0]).await);
+    let uri = format!("/v1/courses/{slug}/cohorts/spring/progress");
+    assert_eq!(send(&ctx, Method::GET, &uri, bearer, None).await.0, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_update_cohort() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let user_id = create_user(&ctx).await;
+    enroll_user(&ctx, &user_id, &slug).await;
+
+    let bearer = format!("Bearer {}", token(&ctx, &user_id).await);
+    let uri = format!("/v1/user/courses/{slug}");
+    let update = |extra: Value| {
+        let mut body =
+            json!({ "proficiency": "beginner", "cadence": "weekly", "accountability": false });
+        body.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        body
+    };
+
+    // A deadline needs a cohort
+    let body = update(json!({ "cohort_deadline": DEADLINE }));
+    let (status, _) = send(&ctx, Method::PATCH, &uri, bearer.clone(), Some(body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let body = update(json!({ "cohort": "spring", "cohort_deadline": DEADLINE }));
+    let (status, _) = send(&ctx, Method::PATCH, &uri, bearer.clone(), Some(body)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (cohort_name, deadline) = cohort(&ctx, &user_id, &slug).await;
+    assert_eq!(cohort_name, json!("spring"));
+    assert!(deadline.is_string());
+
+    // Without a cohort the current one is kept, an empty one leaves it
+    let (status, _) =
+        send(&ctx, Method::PATCH, &uri, bearer.clone(), Some(update(json!({})))).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(cohort(&ctx, &user_id, &slug).await.0, json!("spring"));
+
+    let body = update(json!({ "cohort": "" }));
+    let (status, _) = send(&ctx, Method::PATCH, &uri, bearer, Some(body)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(cohort(&ctx, &user_id, &slug).await, (Value::Null, Value::Null));
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_enroll_with_deadline_requires_cohort() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let user_id = create_user(&ctx).await;
+
+    let req = CreateUserCourseRequest {
+        course_slug: slug.clone(),
+        proficiency: "beginner".to_string(),
+        cadence: "weekly".to_string(),
+        accountability: false,
+        cohort: Some(" ".to_string()),
+        cohort_deadline: Some(DEADLINE.parse().unwrap()),
+        language: None,
+    };
+    let res = CourseService::create_user_course(ctx.clone(), &user_id, &req).await;
+    assert!(matches!(res, Err(ApiError::BadRequest(_))), "{res:?}");
+}
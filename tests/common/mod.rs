@@ -0,0 +1,324 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers shared by the database-backed integration tests.
+
+#![allow(dead_code)]
+
+use std::{sync::Arc, time::Duration};
+
+use axum::Router;
+use chrono::Utc;
+use clap::Parser;
+use gitea_client::GiteaClient;
+use harbor_client::HarborClient;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde_json::{Value, json};
+use stackclass::{
+    config::Config,
+    context::Context,
+    database::Database,
+    jobs::JobRegistry,
+    model::UserCourseModel,
+    repository::{CourseRepository, ProgressRepository},
+    utils::{
+        clock::{ManualClock, SystemClock},
+        crypto,
+        endpoints::Endpoints,
+        health::ClusterHealth,
+        settings::SettingsCache,
+        status::StatusUpdates,
+        stream::StreamTracker,
+    },
+};
+use uuid::Uuid;
+
+/// Time of all records of the mocked Gitea server.
+pub const TIMESTAMP: &str = "2025-01-01T00:00:00Z";
+
+/// Key id of the signing key registered by [`token`].
+const KEY_ID: &str = "stackclass-tests";
+
+/// RSA modulus of the signing key, base64url encoded.
+const KEY_MODULUS: &str = "xcs2A9UMNlbkIg5Y2yPzDd2LMY4tFmkStNaggeeULkPEidSr86_e9eZ1itcNNTAUnpqKu9zff_TY_9gpyaM_O0x8U_BNFPPFjczZnMNs0bsPW8jCNXOm96fDRui0NoGczeTUf-iTMWnlsCXONhUFuI1UiTB5x7IvN8rnYEFjjDalzOzekLjlsgOMgOfwfKXF2FtRiE3gFXUn5XkzoTu-mNgzCjyJxA6iI8hSF8VRgv-L1d_Aelg1JOvpjxAkprLocycO4cOk_JfW41xLOM0zcv3wGI0m1psk6m1_HGJY9WjJZ73zrYGItP1lPrrHFuo-h9bdkDVNaDGXs79Te8HPyQ";
+
+/// A Kubernetes client for tests that never reach the cluster.
+pub fn unreachable_cluster() -> kube::Client {
+    let config = kube::Config::new("http://127.0.0.1:1".parse().unwrap());
+    kube::Client::try_from(config).unwrap()
+}
+
+/// Builds a context backed by the database in `TEST_DATABASE_URL` and the
+/// given Kubernetes client.
+pub async fn setup(k8s: kube::Client) -> Arc<Context> {
+    let database_url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL is not set");
+    let config = Config::parse_from([
+        "stackclass-server",
+        "--cache-dir=/tmp/stackclass",
+        &format!("--database-url={database_url}"),
+        "--git-proxy-endpoint=http://git.local",
+        "--git-server-endpoint=http://git.local",
+        "--git-server-username=admin",
+        "--git-server-password=admin",
+        "--webhook-endpoint=http://api.local",
+        "--namespace=stackclass",
+        "--docker-registry-endpoint=http://docker.local",
+        "--docker-registry-username=admin",
+        "--docker-registry-password=admin",
+        "--auth-secret=secret",
+    ]);
+
+    let database = Database::new(&config.database_url).await.unwrap();
+    database.migrate().await.unwrap();
+
+    Arc::new(Context {
+        endpoints: Endpoints::new(&config).unwrap(),
+        git: GiteaClient::new(config.git_server_endpoint.clone(), "admin".into(), "admin".into())
+            .unwrap(),
+        harbor: HarborClient::new(config.docker_registry_endpoint.clone(), "a".into(), "a".into()),
+        k8s,
+        cluster: ClusterHealth::default(),
+        http: reqwest::Client::new(),
+        streams: StreamTracker::new(1, 1),
+        status: StatusUpdates::default(),
+        jobs: JobRegistry::new(&[]),
+        settings: SettingsCache::new(Duration::from_secs(config.settings_cache_ttl)),
+        clock: Arc::new(SystemClock),
+        #[cfg(feature = "local-runner")]
+        runner: None,
+        database,
+        config,
+    })
+}
+
+/// Serves the mocked API on a free local port, returning its address.
+pub async fn serve(app: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("http://{addr}")
+}
+
+/// Serves the mocked Gitea and Harbor API of the router, and points the
+/// clients of the context at it without retrying. Returns its address.
+pub async fn mock_api(ctx: &mut Context, app: Router) -> String {
+    let url = serve(app).await;
+    ctx.git = GiteaClient::new(url.clone(), "admin".into(), "admin".into())
+        .unwrap()
+        .with_retry_policy(gitea_client::RetryPolicy::none());
+    ctx.harbor = HarborClient::new(url.clone(), "admin".into(), "admin".into())
+        .with_retry_policy(harbor_client::RetryPolicy::none());
+    url
+}
+
+/// Builds a context backed by the mocked Gitea and Harbor API of the router,
+/// for the test to adjust before sharing it.
+pub async fn mock_context(app: Router) -> Context {
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    mock_api(&mut ctx, app).await;
+    ctx
+}
+
+/// Replaces the clock of the context with one stopped at the current time,
+/// which the test then moves by hand.
+pub fn stop_clock(ctx: &mut Context) -> Arc<ManualClock> {
+    let clock = Arc::new(ManualClock::default());
+    ctx.clock = clock.clone();
+    clock
+}
+
+/// Inserts a course with two base stages and one extension stage, and
+/// returns its slug.
+pub async fn create_course(ctx: &Context) -> String {
+    let slug = format!("course-{}", Uuid::now_v7().simple());
+    let pool = ctx.database.pool();
+
+    let course_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO courses (id, slug, name, short_name, release_status, description, summary, repository, stage_count)
+        VALUES ($1, $2, $2, $2, 'beta', '', '', '', 3)
+        RETURNING id
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(&slug)
+    .fetch_one(pool)
+    .await
+    .unwrap();
+
+    let ext_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO extensions (id, course_id, slug, name, description, stage_count)
+        VALUES ($1, $2, 'ext', 'Extension', '', 1)
+        RETURNING id
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(course_id)
+    .fetch_one(pool)
+    .await
+    .unwrap();
+
+    for (stage, ext, position) in [("s1", None, 0), ("s2", None, 1), ("e1", Some(ext_id), 0)] {
+        sqlx::query(
+            r#"
+            INSERT INTO stages (id, course_id, extension_id, slug, name, difficulty, description, instruction, position)
+            VALUES ($1, $2, $3, $4, $4, 'easy', '', '', $5)
+            "#,
+        )
+        .bind(Uuid::now_v7())
+        .bind(course_id)
+        .bind(ext)
+        .bind(format!("{slug}-{stage}"))
+        .bind(position)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    slug
+}
+
+/// Inserts a new user and returns its id.
+pub async fn create_user(ctx: &Context) -> String {
+    let user_id = Uuid::now_v7().to_string();
+    sqlx::query(
+        r#"
+        INSERT INTO users (id, name, email, email_verified, created_at, updated_at)
+        VALUES ($1, $1, $1, true, NOW(), NOW())
+        "#,
+    )
+    .bind(&user_id)
+    .execute(ctx.database.pool())
+    .await
+    .unwrap();
+
+    user_id
+}
+
+/// Enrolls a new user in the course and returns the user id.
+pub async fn enroll(ctx: &Arc<Context>, slug: &str) -> String {
+    let user_id = create_user(ctx).await;
+    enroll_user(ctx, &user_id, slug).await;
+    user_id
+}
+
+/// Enrolls an existing user in the course and returns the user course id.
+pub async fn enroll_user(ctx: &Context, user_id: &str, slug: &str) -> Uuid {
+    let course = CourseRepository::get_by_slug(&ctx.database, slug).await.unwrap();
+    let user_course = UserCourseModel::new(user_id, &course.id)
+        .with_proficiency("beginner")
+        .with_cadence("weekly")
+        .with_accountability(false);
+
+    let mut tx = ctx.database.pool().begin().await.unwrap();
+    let user_course = CourseRepository::create_user_course(&mut tx, &user_course).await.unwrap();
+    ProgressRepository::refresh(&mut tx, &user_course.id).await.unwrap();
+    tx.commit().await.unwrap();
+
+    user_course.id
+}
+
+/// Issues a JWT for the user, registering the test signing key in the
+/// `jwks` table on first use.
+pub async fn token(ctx: &Context, user_id: &str) -> String {
+    let jwk = json!({ "kty": "RSA", "alg": "RS256", "kid": KEY_ID, "n": KEY_MODULUS, "e": "AQAB" });
+    sqlx::query(
+        "INSERT INTO jwks (id, public_key, private_key) VALUES ($1, $2, '') ON CONFLICT DO NOTHING",
+    )
+    .bind(KEY_ID)
+    .bind(jwk.to_string())
+    .execute(ctx.database.pool())
+    .await
+    .unwrap();
+
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some(KEY_ID.to_string());
+    let claims = json!({
+        "id": user_id,
+        "iss": "StackClass",
+        "aud": "StackClass",
+        "exp": Utc::now().timestamp() + 3600,
+    });
+    let key = EncodingKey::from_rsa_pem(include_bytes!("jwt-test-key.pem")).unwrap();
+    jsonwebtoken::encode(&header, &claims, &key).unwrap()
+}
+
+/// Secret of a pipeline event of the given run, without commit, content
+/// hash or repository commit, signed with the auth secret.
+pub fn pipeline_secret(
+    ctx: &Context,
+    name: &str,
+    nonce: &str,
+    repo: &str,
+    course: &str,
+    stage: &str,
+) -> String {
+    let payload: String = [name, nonce, repo, course, stage, "", "", ""]
+        .iter()
+        .map(|field| format!("{}:{field}", field.len()))
+        .collect();
+    crypto::hmac_sha256_sign(&payload, &ctx.config.auth_secret).unwrap()
+}
+
+/// A user as the Gitea API returns it.
+pub fn user(login: &str) -> Value {
+    json!({
+        "active": true,
+        "avatar_url": "",
+        "created": TIMESTAMP,
+        "description": "",
+        "email": "",
+        "followers_count": 0,
+        "following_count": 0,
+        "full_name": "",
+        "html_url": "",
+        "id": 1,
+        "is_admin": false,
+        "language": "",
+        "last_login": TIMESTAMP,
+        "location": "",
+        "login": login,
+        "login_name": "",
+        "prohibit_login": false,
+        "restricted": false,
+        "source_id": 0,
+        "starred_repos_count": 0,
+        "username": login,
+        "visibility": "public",
+        "website": ""
+    })
+}
+
+/// A repository as the Gitea API returns it.
+pub fn repository(owner: &str, name: &str) -> Value {
+    let mut repository: Value =
+        serde_json::from_str(include_str!("gitea-repository.json")).unwrap();
+    repository["name"] = json!(name);
+    repository["full_name"] = json!(format!("{owner}/{name}"));
+    repository["owner"] = user(owner);
+    repository
+}
+
+/// An image pushed by a pipeline, named after its repository.
+pub fn artifact(repo: &str) -> Value {
+    json!({
+        "id": 1,
+        "type": "IMAGE",
+        "media_type": "application/vnd.oci.image.config.v1+json",
+        "project_id": 1,
+        "repository_id": 1,
+        "digest": format!("sha256:{repo}"),
+    })
+}
@@ -0,0 +1,93 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conflicts are reported with a code and a message telling them apart. These
+//! tests need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test conflict-tests -- --ignored
+
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde_json::{Value, json};
+use stackclass::{errors::ApiError, repository::CourseRepository, routes};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{create_course, enroll, setup, token, unreachable_cluster};
+
+/// Reads the status and the JSON body of a response.
+async fn read(res: Response) -> (StatusCode, Value) {
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_duplicate_enrollment() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+
+    let body = json!({
+        "course_slug": slug,
+        "proficiency": "beginner",
+        "cadence": "weekly",
+        "accountability": false
+    });
+    let req = Request::post("/v1/user/courses")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token(&ctx, &user_id).await))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let (status, body) = read(routes::build(ctx.clone()).oneshot(req).await.unwrap()).await;
+
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(
+        body,
+        json!({ "code": "already_enrolled", "message": "User is already enrolled in this course" })
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_duplicate_course() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+
+    // Courses are fetched from GitHub on creation, so the duplicate is
+    // inserted the way the course service does
+    let mut course = CourseRepository::get_by_slug(&ctx.database, &slug).await.unwrap();
+    course.id = Uuid::now_v7();
+    let mut tx = ctx.database.pool().begin().await.unwrap();
+    let err = CourseRepository::create(&mut tx, &course)
+        .await
+        .map_err(ApiError::on_duplicate(ApiError::CourseExists))
+        .unwrap_err();
+
+    let (status, body) = read(err.into_response()).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body, json!({ "code": "course_exists", "message": "Course already exists" }));
+
+    // Without a more specific conflict the generic one is reported
+    let mut tx = ctx.database.pool().begin().await.unwrap();
+    let err = ApiError::from(CourseRepository::create(&mut tx, &course).await.unwrap_err());
+    let (_, body) = read(err.into_response()).await;
+    assert_eq!(body, json!({ "code": "record_exists", "message": "Record already exists" }));
+}
@@ -0,0 +1,104 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Course authors preview what was imported from their repository through
+//! the course export. These tests need a disposable PostgreSQL database, run
+//! them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test course-export-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+use stackclass::{context::Context, routes, utils::crypto};
+use tower::ServiceExt;
+
+use common::{create_course, create_user, setup, token, unreachable_cluster};
+
+async fn export(ctx: &Arc<Context>, slug: &str, authorization: String) -> (StatusCode, Value) {
+    let req = Request::get(format!("/v1/courses/{slug}/export"))
+        .header(header::AUTHORIZATION, authorization)
+        .body(Body::empty())
+        .unwrap();
+
+    let app = routes::build(ctx.clone());
+    let res = app.oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn admin(ctx: &Context) -> String {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    format!("Basic {}", STANDARD.encode(format!("admin:{password}")))
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_export_nests_stages_in_course_order() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+
+    // Stages are exported by position, whatever their legacy weights say
+    sqlx::query(
+        r#"
+        UPDATE stages
+        SET instruction = '# ' || slug, solution = 'Solve ' || slug, weight = -position
+        WHERE slug LIKE $1 || '-%'
+        "#,
+    )
+    .bind(&slug)
+    .execute(ctx.database.pool())
+    .await
+    .unwrap();
+
+    let (status, body) = export(&ctx, &slug, admin(&ctx)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["slug"], json!(slug));
+    assert_eq!(body["release_status"], json!("beta"));
+
+    let slugs = |stages: &Value| -> Vec<String> {
+        stages.as_array().unwrap().iter().map(|s| s["slug"].as_str().unwrap().into()).collect()
+    };
+    assert_eq!(slugs(&body["stages"]), [format!("{slug}-s1"), format!("{slug}-s2")]);
+    assert_eq!(body["stages"][0]["instruction"], json!(format!("# {slug}-s1")));
+    assert_eq!(body["stages"][1]["solution"], json!(format!("Solve {slug}-s2")));
+
+    let extensions = body["extensions"].as_array().unwrap();
+    assert_eq!(extensions.len(), 1);
+    assert_eq!(extensions[0]["slug"], json!("ext"));
+    assert_eq!(slugs(&extensions[0]["stages"]), [format!("{slug}-e1")]);
+    assert_eq!(extensions[0]["stages"][0]["instruction"], json!(format!("# {slug}-e1")));
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_export_is_for_maintainers() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+
+    let user_id = create_user(&ctx).await;
+    let bearer = format!("Bearer {}", token(&ctx, &user_id).await);
+    assert_eq!(export(&ctx, &slug, bearer).await.0, StatusCode::FORBIDDEN);
+
+    let (status, _) = export(&ctx, "no-such-course", admin(&ctx)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
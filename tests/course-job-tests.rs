@@ -0,0 +1,167 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Courses are created in the background, through jobs whose status is
+//! polled or streamed. These tests need a disposable PostgreSQL database,
+//! run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test course-job-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context, repository::CourseRepository, routes, service::CourseService, utils::crypto,
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{create_course, setup, unreachable_cluster};
+
+/// Sends an admin request, returning the status and the raw body.
+async fn send(
+    ctx: &Arc<Context>,
+    method: Method,
+    uri: &str,
+    body: Option<Value>,
+) -> (StatusCode, String) {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(
+            header::AUTHORIZATION,
+            format!("Basic {}", STANDARD.encode(format!("admin:{password}"))),
+        )
+        .header(header::CONTENT_TYPE, "application/json");
+    let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+
+    let app = routes::build(ctx.clone());
+    let res = app.oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+/// Queues the creation of a course from a repository that cannot be fetched.
+async fn queue_failing_job(ctx: &Arc<Context>) -> String {
+    let req = json!({ "repository": format!("not-a-repository-{}", Uuid::now_v7()) });
+    let (status, body) = send(ctx, Method::POST, "/v1/courses", Some(req)).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{body}");
+
+    let job: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(job["status"], "pending");
+    job["job_id"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_failed_course_job() {
+    let ctx = setup(unreachable_cluster()).await;
+    let id = queue_failing_job(&ctx).await;
+
+    let (status, body) = send(&ctx, Method::GET, &format!("/v1/courses/jobs/{id}"), None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["status"], "pending");
+
+    assert!(CourseService::create_pending(ctx.clone()).await.unwrap() >= 1);
+
+    let (status, body) = send(&ctx, Method::GET, &format!("/v1/courses/jobs/{id}"), None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let job: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(job["status"], "failed");
+    assert!(job["error"].as_str().is_some_and(|error| !error.is_empty()), "{job}");
+    assert!(job.get("course_slug").is_none(), "{job}");
+
+    let uri = format!("/v1/courses/jobs/{}", Uuid::now_v7());
+    let (status, _) = send(&ctx, Method::GET, &uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_stream_finished_course_job() {
+    let ctx = setup(unreachable_cluster()).await;
+    let id = queue_failing_job(&ctx).await;
+    CourseService::create_pending(ctx.clone()).await.unwrap();
+
+    // The stream of a finished job ends right after its status
+    let (status, body) =
+        send(&ctx, Method::GET, &format!("/v1/courses/jobs/{id}/status"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let events: Vec<Value> = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    assert_eq!(events.len(), 1, "{body}");
+    assert_eq!(events[0]["job_id"], id.as_str());
+    assert_eq!(events[0]["status"], "failed");
+
+    let uri = format!("/v1/courses/jobs/{}/status", Uuid::now_v7());
+    let (status, _) = send(&ctx, Method::GET, &uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_course_job_reference() {
+    let ctx = setup(unreachable_cluster()).await;
+
+    let req = json!({ "repository": format!("not-a-repository-{}", Uuid::now_v7()), "reference": " v1.2.0 " });
+    let (status, body) = send(&ctx, Method::POST, "/v1/courses", Some(req)).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{body}");
+    let job: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(job["reference"], "v1.2.0");
+
+    // A blank reference follows the default branch
+    let req =
+        json!({ "repository": format!("not-a-repository-{}", Uuid::now_v7()), "reference": "" });
+    let (status, body) = send(&ctx, Method::POST, "/v1/courses", Some(req)).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{body}");
+    let job: Value = serde_json::from_str(&body).unwrap();
+    assert!(job.get("reference").is_none(), "{job}");
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_pinned_course_detail() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let sha = "0123456789abcdef0123456789abcdef01234567";
+    sqlx::query("UPDATE courses SET commit_sha = $2 WHERE slug = $1")
+        .bind(&slug)
+        .bind(sha)
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+
+    let (_, body) = send(&ctx, Method::GET, &format!("/v1/courses/{slug}"), None).await;
+    let course: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(course["commit"], sha);
+    assert!(course.get("reference").is_none(), "{course}");
+
+    CourseRepository::set_reference(&ctx.database, &slug, Some("v1.2.0")).await.unwrap();
+    let (_, body) = send(&ctx, Method::GET, &format!("/v1/courses/{slug}"), None).await;
+    let course: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(course["commit"], sha);
+    assert_eq!(course["reference"], "v1.2.0");
+}
@@ -0,0 +1,128 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Courses offered in several programming languages have a template per
+//! language, learners enroll with one of them. These tests need a disposable
+//! PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test course-language-tests -- --ignored
+
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context, errors::ApiError, model::UserCourseModel, repository::CourseRepository,
+    request::CreateUserCourseRequest, routes, service::CourseService,
+};
+use tower::ServiceExt;
+
+use common::{create_course, create_user, setup, token, unreachable_cluster};
+
+/// Offers the course in the given languages, the first being the primary.
+async fn offer(ctx: &Context, slug: &str, languages: &[&str]) {
+    sqlx::query("UPDATE courses SET languages = $2 WHERE slug = $1")
+        .bind(slug)
+        .bind(languages)
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+}
+
+fn enrollment(slug: &str, language: Option<&str>) -> CreateUserCourseRequest {
+    CreateUserCourseRequest {
+        course_slug: slug.to_string(),
+        proficiency: "beginner".to_string(),
+        cadence: "weekly".to_string(),
+        accountability: false,
+        cohort: None,
+        cohort_deadline: None,
+        language: language.map(ToString::to_string),
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_language_templates() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+
+    let course = CourseRepository::get_by_slug(&ctx.database, &slug).await.unwrap();
+    assert_eq!(course.template(None), slug);
+    assert_eq!(course.templates(), [(slug.clone(), "template".to_string())]);
+
+    offer(&ctx, &slug, &["rust", "go"]).await;
+    let course = CourseRepository::get_by_slug(&ctx.database, &slug).await.unwrap();
+    assert_eq!(course.template(None), slug);
+    assert_eq!(course.template(Some("rust")), slug);
+    assert_eq!(course.template(Some("go")), format!("{slug}-go"));
+    assert_eq!(
+        course.templates(),
+        [
+            (slug.clone(), "template/rust".to_string()),
+            (format!("{slug}-go"), "template/go".to_string())
+        ]
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_enroll_with_unknown_language() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let user_id = create_user(&ctx).await;
+
+    // Single-language courses have no variants to choose from
+    let req = enrollment(&slug, Some("go"));
+    let res = CourseService::create_user_course(ctx.clone(), &user_id, &req).await;
+    assert!(matches!(res, Err(ApiError::BadRequest(_))), "{res:?}");
+
+    offer(&ctx, &slug, &["rust", "go"]).await;
+    let req = enrollment(&slug, Some("java"));
+    let res = CourseService::create_user_course(ctx.clone(), &user_id, &req).await;
+    assert!(matches!(res, Err(ApiError::BadRequest(_))), "{res:?}");
+
+    let courses = CourseRepository::find_user_courses(&ctx.database, &user_id).await.unwrap();
+    assert!(courses.is_empty());
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_user_course_exposes_language() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    offer(&ctx, &slug, &["rust", "go"]).await;
+    let user_id = create_user(&ctx).await;
+
+    let course = CourseRepository::get_by_slug(&ctx.database, &slug).await.unwrap();
+    let user_course = UserCourseModel::new(&user_id, &course.id).with_language(Some("go"));
+    let mut tx = ctx.database.pool().begin().await.unwrap();
+    CourseRepository::create_user_course(&mut tx, &user_course).await.unwrap();
+    tx.commit().await.unwrap();
+
+    let req = Request::builder()
+        .uri(format!("/v1/user/courses/{slug}"))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token(&ctx, &user_id).await))
+        .body(Body::empty())
+        .unwrap();
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["language"], json!("go"));
+}
@@ -12,9 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::path::PathBuf;
+use std::{fs, path::PathBuf};
 
-use stackclass::schema::{self, Difficulty, Status};
+use stackclass::schema::{self, AssetKind, ChangeKind, Difficulty, Status};
 
 #[test]
 fn test_parse_course() {
@@ -66,3 +66,126 @@ fn test_parse_course() {
     assert!(stage.description.starts_with("In this stage"));
     assert!(stage.instruction.starts_with("In this stage"));
 }
+
+#[test]
+fn test_parse_assets() {
+    let dir = tempfile::tempdir().unwrap();
+    let write = |path: &str, content: &str| {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    };
+
+    write(
+        "course.yml",
+        "slug: redis\nname: Redis\nshort_name: Redis\nrelease_status: beta\n\
+         description: A Redis clone\nsummary: Redis\n",
+    );
+    write("assets/logo.svg", "<svg/>");
+    write(
+        "stages/01-ping/stage.yml",
+        "slug: ping\nname: Ping\ndifficulty: easy\ndescription: Ping\n",
+    );
+    write("stages/01-ping/instruction.md", "Respond to PING");
+    write("stages/01-ping/solution.md", "Write +PONG");
+    write("stages/01-ping/hints/01.md", "Use a socket");
+    write("stages/01-ping/images/flow.png", "png");
+    write("stages/01-ping/images/stage.yml", "not a definition");
+
+    let assets = schema::parse(dir.path()).unwrap().assets;
+    let paths: Vec<_> = assets.iter().map(|a| a.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "assets/logo.svg",
+            "stages/01-ping/hints/01.md",
+            "stages/01-ping/images/flow.png",
+            "stages/01-ping/images/stage.yml"
+        ]
+    );
+
+    assert_eq!(assets[0].stage, None);
+    assert_eq!(assets[1].stage.as_deref(), Some("ping"));
+    assert_eq!(assets[1].kind, AssetKind::Hint);
+    assert_eq!(assets[2].kind, AssetKind::Asset);
+    assert_eq!(assets[2].size, 3);
+    assert_eq!(
+        assets[2].sha256,
+        "8f8cbb7dcf46e0bc7d53265749a6c17d116093a6ba95e442764060c76fd4a86c"
+    );
+}
+
+#[test]
+fn test_parse_starters() {
+    let dir = tempfile::tempdir().unwrap();
+    let write = |path: &str, content: &[u8]| {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    };
+
+    write(
+        "course.yml",
+        b"slug: redis\nname: Redis\nshort_name: Redis\nrelease_status: beta\n\
+          description: A Redis clone\nsummary: Redis\n",
+    );
+    for (dir, slug) in
+        [("01-bind", "bind"), ("02-ping", "ping"), ("03-echo", "echo"), ("04-get", "get")]
+    {
+        let stage = format!("slug: {slug}\nname: {slug}\ndifficulty: easy\ndescription: {slug}\n");
+        write(&format!("stages/{dir}/stage.yml"), stage.as_bytes());
+        write(&format!("stages/{dir}/instruction.md"), slug.as_bytes());
+    }
+
+    write("starter/bind/src/main.rs", b"fn main() {\n    listen();\n}\n");
+    write("starter/bind/README.md", b"Redis\n");
+    write("starter/ping/src/main.rs", b"fn main() {\n    listen();\n    ping();\n}\n");
+    write("starter/ping/README.md", b"Redis\n");
+    write("starter/ping/src/resp.rs", b"// RESP\n");
+    write("starter/echo/src/main.rs", b"fn main() {\n    listen();\n    ping();\n}\n");
+    write("starter/echo/src/resp.rs", b"// RESP\npub fn parse() {}\n");
+    write("starter/echo/logo.png", &[0x89, 0x50, 0xff, 0xfe]);
+
+    let course = schema::parse(dir.path()).unwrap();
+    assert_eq!(course.starter_files.len(), 8);
+    assert!(course.starter_files.iter().all(|f| f.stage != "get"));
+
+    // Stages without starter code have no diff
+    let stages: Vec<_> = course.starter_diffs.iter().map(|d| d.stage.as_str()).collect();
+    assert_eq!(stages, ["bind", "ping", "echo"]);
+
+    let changes = |stage: usize| {
+        let diff = &course.starter_diffs[stage];
+        diff.changes.iter().map(|c| (c.path.as_str(), c.kind)).collect::<Vec<_>>()
+    };
+    assert_eq!(changes(0), [("README.md", ChangeKind::Added), ("src/main.rs", ChangeKind::Added)]);
+    assert_eq!(
+        changes(1),
+        [("src/main.rs", ChangeKind::Modified), ("src/resp.rs", ChangeKind::Added)]
+    );
+    assert_eq!(
+        changes(2),
+        [
+            ("README.md", ChangeKind::Removed),
+            ("logo.png", ChangeKind::Added),
+            ("src/resp.rs", ChangeKind::Modified)
+        ]
+    );
+
+    let diff =
+        |stage: usize, change: usize| course.starter_diffs[stage].changes[change].diff.as_deref();
+    assert_eq!(diff(0, 0), Some("--- /dev/null\n+++ b/README.md\n@@ -0,0 +1 @@\n+Redis\n"));
+    assert_eq!(
+        diff(1, 0),
+        Some(
+            "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1,3 +1,4 @@\n fn main() {\n     listen();\n\
+             +    ping();\n }\n"
+        )
+    );
+    assert_eq!(diff(2, 0), Some("--- a/README.md\n+++ /dev/null\n@@ -1 +0,0 @@\n-Redis\n"));
+    assert_eq!(diff(2, 1), None);
+
+    // Starter directories must belong to a stage
+    write("starter/set/src/main.rs", b"fn main() {}\n");
+    assert!(matches!(schema::parse(dir.path()), Err(schema::ParseError::Validation(_))));
+}
@@ -0,0 +1,162 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Courses pick the pipeline grading them, along with its workspace size,
+//! timeout and service account. These tests need a disposable PostgreSQL
+//! database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test course-pipeline-tests -- --ignored
+
+mod common;
+
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    body::Body,
+    http::{Method, Request, Response, StatusCode},
+};
+use serde_json::{Value, json};
+use stackclass::{
+    errors::ApiError,
+    repository::CourseRepository,
+    schema::{Course, PipelineSettings},
+    service::{CourseService, PipelineService},
+};
+
+use common::{create_course, enroll, setup};
+
+/// Name of the only Tekton Pipeline of the mock cluster.
+const PIPELINE: &str = "rust-test-pipeline";
+
+/// A Kubernetes API server holding a single Tekton Pipeline, along with the
+/// PipelineRuns it was asked to create.
+#[derive(Clone, Default)]
+struct MockCluster {
+    created: Arc<Mutex<Vec<Value>>>,
+}
+
+impl MockCluster {
+    fn client(&self) -> kube::Client {
+        let mock = self.clone();
+        let service = tower::service_fn(move |req: Request<kube::client::Body>| {
+            let mock = mock.clone();
+            async move {
+                let path = req.uri().path().to_string();
+                let (status, body) = match *req.method() {
+                    Method::POST => {
+                        let body = Body::new(req.into_body());
+                        let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+                        let run: Value = serde_json::from_slice(&body).unwrap();
+                        mock.created.lock().unwrap().push(run.clone());
+                        (StatusCode::CREATED, run)
+                    }
+                    _ if path.ends_with(&format!("/pipelines/{PIPELINE}")) => (
+                        StatusCode::OK,
+                        json!({
+                            "apiVersion": "tekton.dev/v1",
+                            "kind": "Pipeline",
+                            "metadata": { "name": PIPELINE }
+                        }),
+                    ),
+                    _ => (
+                        StatusCode::NOT_FOUND,
+                        json!({
+                            "kind": "Status",
+                            "apiVersion": "v1",
+                            "status": "Failure",
+                            "reason": "NotFound",
+                            "code": 404
+                        }),
+                    ),
+                };
+                let mut res = Response::new(kube::client::Body::from(serde_json::to_vec(&body)?));
+                *res.status_mut() = status;
+                Ok::<_, serde_json::Error>(res)
+            }
+        });
+        kube::Client::new(service, "stackclass")
+    }
+}
+
+/// A course with the given `pipeline` section of `course.yml`.
+fn course(slug: &str, pipeline: &str) -> Course {
+    let yaml = format!(
+        "slug: {slug}\nname: Course\nshort_name: Course\nrelease_status: beta\n\
+         description: A course\nsummary: A course\npipeline: {pipeline}\n"
+    );
+    Course::from_str(&yaml).unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_runs_apply_course_pipeline() {
+    let cluster = MockCluster::default();
+    let ctx = setup(cluster.client()).await;
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    let (repo, stage) = (user_course.id.to_string(), format!("{slug}-s1"));
+    let pipeline = PipelineService::new(ctx.clone());
+
+    // Courses which set nothing keep the defaults
+    pipeline.trigger(&repo, &slug, &stage, "", "", "").await.unwrap();
+    let spec = cluster.created.lock().unwrap()[0]["spec"].clone();
+    assert_eq!(spec["pipelineRef"]["name"], "course-test-pipeline");
+    let storage = &spec["workspaces"][0]["volumeClaimTemplate"]["spec"]["resources"];
+    assert_eq!(storage["requests"]["storage"], "5Gi");
+    assert!(spec.get("timeouts").is_none());
+    assert!(spec.get("taskRunTemplate").is_none());
+
+    // The settings of course.yml are kept by syncs, and applied to new runs
+    let pipeline_yml = format!(
+        "{{ pipeline_ref: {PIPELINE}, workspace_size: 20Gi, timeout: 2h, \
+         service_account: tester }}"
+    );
+    CourseService::update_course(ctx.clone(), &course(&slug, &pipeline_yml), "c1", "admin")
+        .await
+        .unwrap();
+    let model = CourseRepository::get_by_slug(&ctx.database, &slug).await.unwrap();
+    assert_eq!(model.pipeline(), course(&slug, &pipeline_yml).pipeline);
+
+    pipeline.trigger(&repo, &slug, &stage, "", "", "").await.unwrap();
+    let spec = cluster.created.lock().unwrap()[1]["spec"].clone();
+    assert_eq!(spec["pipelineRef"]["name"], PIPELINE);
+    let storage = &spec["workspaces"][0]["volumeClaimTemplate"]["spec"]["resources"];
+    assert_eq!(storage["requests"]["storage"], "20Gi");
+    assert_eq!(spec["timeouts"], json!({ "pipeline": "2h" }));
+    assert_eq!(spec["taskRunTemplate"], json!({ "serviceAccountName": "tester" }));
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_unknown_pipeline_is_rejected() {
+    let cluster = MockCluster::default();
+    let ctx = setup(cluster.client()).await;
+    let pipeline = PipelineService::new(ctx.clone());
+
+    assert!(pipeline.ensure_pipeline(PIPELINE).await.is_ok());
+    match pipeline.ensure_pipeline("rust-tset-pipeline").await {
+        Err(ApiError::BadRequest(message)) => {
+            assert!(message.contains("rust-tset-pipeline"), "{message}")
+        }
+        other => panic!("expected a bad request, got {other:?}"),
+    }
+
+    // Courses which pick none need no lookup
+    assert_eq!(PipelineSettings::default().pipeline_ref(), "course-test-pipeline");
+}
@@ -0,0 +1,96 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Learners reset their progress in a course and start over from the first
+//! stage. These tests need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test course-reset-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use stackclass::{
+    context::Context,
+    repository::{CourseRepository, ProgressRepository, StageRepository},
+    routes,
+    service::{CourseService, StageService},
+};
+use tower::ServiceExt;
+
+use common::{create_course, create_user, enroll, setup, token, unreachable_cluster};
+
+async fn reset(ctx: &Arc<Context>, user_id: &str, slug: &str) -> StatusCode {
+    let req = Request::post(format!("/v1/user/courses/{slug}/reset"))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token(ctx, user_id).await))
+        .body(Body::empty())
+        .unwrap();
+
+    let app = routes::build(ctx.clone());
+    app.oneshot(req).await.unwrap().status()
+}
+
+async fn activate(ctx: &Arc<Context>, user_id: &str, slug: &str) {
+    let mut user_course =
+        CourseRepository::get_user_course(&ctx.database, user_id, slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_reset_starts_over_from_first_stage() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let (s1, s2) = (format!("{slug}-s1"), format!("{slug}-s2"));
+
+    activate(&ctx, &user_id, &slug).await;
+    StageService::complete(ctx.clone(), &user_id, &slug, &s1, None).await.unwrap();
+
+    assert_eq!(reset(&ctx, &user_id, &slug).await, StatusCode::NO_CONTENT);
+    let user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    assert!(!user_course.activated);
+    assert_eq!(user_course.completed_stage_count, 0);
+    assert_eq!(user_course.current_stage_id, None);
+    let stages = StageRepository::find_user_stages(&ctx.database, &user_id, &slug).await.unwrap();
+    assert!(stages.is_empty());
+
+    let summary = ProgressRepository::find_by_course(&ctx.database, &slug).await.unwrap();
+    let live = ProgressRepository::find_live_by_course(&ctx.database, &slug).await.unwrap();
+    assert_eq!(summary, live);
+    assert_eq!(summary[0].completed_stage_count, 0);
+
+    // The first stage is completed anew and the second one comes next
+    activate(&ctx, &user_id, &slug).await;
+    StageService::complete(ctx.clone(), &user_id, &slug, &s1, None).await.unwrap();
+    let user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    assert_eq!(user_course.completed_stage_count, 1);
+    assert_eq!(user_course.current_stage_slug.as_deref(), Some(s2.as_str()));
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_reset_requires_enrollment() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let user_id = create_user(&ctx).await;
+
+    assert_eq!(reset(&ctx, &user_id, &slug).await, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,95 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Released courses are searched by keywords. These tests need a disposable
+//! PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test course-search-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use serde_json::Value;
+use stackclass::{context::Context, routes};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{create_course, setup, unreachable_cluster};
+
+async fn search(ctx: &Arc<Context>, q: &str) -> (StatusCode, Value) {
+    let req = Request::get(format!("/v1/courses/search?q={q}")).body(Body::empty()).unwrap();
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Creates a course of the given release status with the keyword in one of
+/// its texts.
+async fn create_matching_course(ctx: &Context, status: &str, column: &str, text: &str) -> String {
+    let slug = create_course(ctx).await;
+    let sql = format!("UPDATE courses SET release_status = $2, {column} = $3 WHERE slug = $1");
+    sqlx::query(&sql)
+        .bind(&slug)
+        .bind(status)
+        .bind(text)
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+    slug
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_search_ranks_matches() {
+    let ctx = setup(unreachable_cluster()).await;
+    let keyword = format!("kw{}", &Uuid::now_v7().simple().to_string()[20..]);
+
+    let text = format!("Build your own {keyword} server");
+    let described = create_matching_course(&ctx, "live", "description", &text).await;
+    let named = create_matching_course(&ctx, "beta", "name", &text).await;
+    let summarized = create_matching_course(&ctx, "live", "summary", &text).await;
+    create_matching_course(&ctx, "alpha", "name", &text).await;
+
+    let (status, body) = search(&ctx, &keyword).await;
+    assert_eq!(status, StatusCode::OK);
+    let slugs: Vec<&str> =
+        body.as_array().unwrap().iter().map(|c| c["slug"].as_str().unwrap()).collect();
+    assert_eq!(slugs, [named.as_str(), &summarized, &described]);
+
+    // All keywords have to match
+    let (status, body) = search(&ctx, &format!("{keyword}%20missing")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, Value::Array(vec![]));
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_search_validates_keywords() {
+    let ctx = setup(unreachable_cluster()).await;
+
+    assert_eq!(search(&ctx, "r").await.0, StatusCode::BAD_REQUEST);
+    assert_eq!(search(&ctx, "%20r%20").await.0, StatusCode::BAD_REQUEST);
+    assert_eq!(search(&ctx, &"r".repeat(101)).await.0, StatusCode::BAD_REQUEST);
+    assert_eq!(search(&ctx, &"r".repeat(100)).await.0, StatusCode::OK);
+
+    let req = Request::get("/v1/courses/search").body(Body::empty()).unwrap();
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,162 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Maintainers read the course source of the last imported commit. These
+//! tests need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test course-source-tests -- --ignored
+
+mod common;
+
+use std::{fs, path::Path, sync::Arc};
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::Value;
+use stackclass::{context::Context, routes, utils::crypto};
+use tower::ServiceExt;
+
+use common::{create_course, setup, unreachable_cluster};
+
+const COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+
+async fn get(ctx: &Arc<Context>, uri: &str, auth: Option<&str>) -> (StatusCode, Value) {
+    let mut req = Request::get(uri);
+    if let Some(auth) = auth {
+        req = req.header(header::AUTHORIZATION, auth);
+    }
+
+    let app = routes::build(ctx.clone());
+    let res = app.oneshot(req.body(Body::empty()).unwrap()).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn admin(ctx: &Context) -> String {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    format!("Basic {}", STANDARD.encode(format!("admin:{password}")))
+}
+
+fn write(path: &Path, content: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn stage_yml(slug: &str) -> String {
+    format!("slug: {slug}\nname: Stage {slug}\ndifficulty: easy\ndescription: About {slug}\n")
+}
+
+/// Lays out a cached checkout of the course repository, as it would have
+/// been unpacked from the GitHub tarball.
+fn cache_course(root: &Path) {
+    write(
+        &root.join("course.yml"),
+        "slug: redis\nname: Build your own Redis\nshort_name: Redis\nrelease_status: beta\n\
+         description: A Redis clone\nsummary: Redis\n",
+    );
+    write(
+        &root.join("extensions.yml"),
+        "- slug: pubsub\n  name: Pub/Sub\n  description: Channels\n",
+    );
+    write(&root.join("stages/01-ping/stage.yml"), &stage_yml("ping"));
+    write(&root.join("stages/01-ping/instruction.md"), "Respond to PING");
+    write(&root.join("stages/01-ping/solution.md"), "Write +PONG");
+    write(&root.join("extensions/pubsub/01-subscribe/stage.yml"), &stage_yml("subscribe"));
+    write(&root.join("extensions/pubsub/01-subscribe/instruction.md"), "Handle SUBSCRIBE");
+
+    // A stage smuggling a file from outside of the checkout
+    write(&root.join("stages/02-echo/stage.yml"), &stage_yml("echo"));
+    write(&root.parent().unwrap().join("secret"), "top secret");
+    std::os::unix::fs::symlink("../../../secret", root.join("stages/02-echo/instruction.md"))
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_course_source_for_maintainers() {
+    let cache = tempfile::tempdir().unwrap();
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    ctx.config.cache_dir = cache.path().to_path_buf();
+    let ctx = Arc::new(ctx);
+
+    // The checkout is already cached, nothing needs to be fetched
+    cache_course(&cache.path().join(format!("stackclass-redis-{}", &COMMIT[..7])));
+    let slug = create_course(&ctx).await;
+    sqlx::query("UPDATE courses SET repository = $2, commit_sha = $3 WHERE slug = $1")
+        .bind(&slug)
+        .bind("https://github.com/stackclass/redis")
+        .bind(COMMIT)
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+    let auth = admin(&ctx);
+
+    let uri = format!("/v1/courses/{slug}/source");
+    let (status, body) = get(&ctx, &uri, Some(&auth)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["commit"], COMMIT);
+    assert!(body["course_yml"].as_str().unwrap().contains("name: Build your own Redis"));
+    assert_eq!(body["course"]["short_name"], "Redis");
+    assert!(body["extensions_yml"].as_str().unwrap().contains("slug: pubsub"));
+
+    let stages = body["stages"].as_array().unwrap();
+    let paths: Vec<_> = stages.iter().map(|s| s["path"].as_str().unwrap()).collect();
+    assert_eq!(paths, ["stages/01-ping", "stages/02-echo", "extensions/pubsub/01-subscribe"]);
+    assert_eq!(stages[0]["front_matter"]["difficulty"], "easy");
+    assert_eq!(stages[2]["extension"], "pubsub");
+    assert_eq!(stages[0]["content_hash"].as_str().unwrap().len(), 64);
+
+    let (status, body) = get(&ctx, &format!("{uri}/stages/ping"), Some(&auth)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["stage_yml"], stage_yml("ping"));
+    assert_eq!(body["instruction_md"], "Respond to PING");
+    assert_eq!(body["solution_md"], "Write +PONG");
+    assert_eq!(body["content_hash"], stages[0]["content_hash"]);
+
+    let (status, body) = get(&ctx, &format!("{uri}/stages/subscribe"), Some(&auth)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["path"], "extensions/pubsub/01-subscribe");
+    assert_eq!(body["solution_md"], Value::Null);
+
+    // Files outside of the checkout are never served
+    let (status, body) = get(&ctx, &format!("{uri}/stages/echo"), Some(&auth)).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(!body.to_string().contains("top secret"));
+
+    let (status, _) = get(&ctx, &format!("{uri}/stages/missing"), Some(&auth)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Only maintainers may read the source
+    let (status, _) = get(&ctx, &uri, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_course_source_without_import() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    sqlx::query("UPDATE courses SET commit_sha = '' WHERE slug = $1")
+        .bind(&slug)
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+
+    let (status, _) = get(&ctx, &format!("/v1/courses/{slug}/source"), Some(&admin(&ctx))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,141 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Instructors follow the progress of the learners of a course through its
+//! aggregate stats. These tests need a disposable PostgreSQL database, run
+//! them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test course-stats-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+use stackclass::{context::Context, routes, utils::crypto};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{create_course, create_user, enroll_user, setup, unreachable_cluster};
+
+async fn stats(ctx: &Arc<Context>, slug: &str) -> (StatusCode, Value) {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let req = Request::get(format!("/v1/courses/{slug}/stats"))
+        .header(
+            header::AUTHORIZATION,
+            format!("Basic {}", STANDARD.encode(format!("admin:{password}"))),
+        )
+        .body(Body::empty())
+        .unwrap();
+
+    let app = routes::build(ctx.clone());
+    let res = app.oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Enrolls a new learner who reached the given stages with their statuses.
+async fn learner(ctx: &Context, slug: &str, activated: bool, stages: &[(&str, &str)]) {
+    let user_id = create_user(ctx).await;
+    let user_course_id = enroll_user(ctx, &user_id, slug).await;
+    let pool = ctx.database.pool();
+
+    sqlx::query("UPDATE user_courses SET activated = $2 WHERE id = $1")
+        .bind(user_course_id)
+        .bind(activated)
+        .execute(pool)
+        .await
+        .unwrap();
+
+    for (stage, status) in stages {
+        sqlx::query(
+            r#"
+            INSERT INTO user_stages (id, user_course_id, stage_id, status)
+            SELECT $1, $2, id, $4 FROM stages WHERE slug = $3
+            "#,
+        )
+        .bind(Uuid::now_v7())
+        .bind(user_course_id)
+        .bind(format!("{slug}-{stage}"))
+        .bind(status)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+}
+
+fn stage(slug: &str, stage: &str, extension: Option<&str>, reached: i64, completed: i64) -> Value {
+    json!({
+        "stage_slug": format!("{slug}-{stage}"),
+        "extension_slug": extension,
+        "reached_count": reached,
+        "completed_count": completed,
+    })
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_course_stats() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+
+    // Without enrollments every count is zero
+    let (status, body) = stats(&ctx, &slug).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        body,
+        json!({
+            "enrolled_count": 0,
+            "activated_count": 0,
+            "completed_count": 0,
+            "average_completed_stages": 0.0,
+            "stages": [
+                stage(&slug, "s1", None, 0, 0),
+                stage(&slug, "s2", None, 0, 0),
+                stage(&slug, "e1", Some("ext"), 0, 0),
+            ],
+        })
+    );
+
+    learner(&ctx, &slug, true, &[("s1", "completed"), ("s2", "completed"), ("e1", "in_progress")])
+        .await;
+    learner(&ctx, &slug, true, &[("s1", "completed"), ("s2", "in_progress")]).await;
+    learner(&ctx, &slug, false, &[]).await;
+
+    let (status, body) = stats(&ctx, &slug).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        body,
+        json!({
+            "enrolled_count": 3,
+            "activated_count": 2,
+            "completed_count": 1,
+            "average_completed_stages": 1.0,
+            "stages": [
+                stage(&slug, "s1", None, 2, 2),
+                stage(&slug, "s2", None, 2, 1),
+                stage(&slug, "e1", Some("ext"), 1, 0),
+            ],
+        })
+    );
+
+    let (status, _) = stats(&ctx, "missing-course").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
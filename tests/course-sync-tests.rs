@@ -0,0 +1,283 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Course syncs apply reordered extensions and stages atomically, archive the
+//! removed stages learners reached, and record what they changed. These
+//! tests need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test course-sync-tests -- --ignored
+
+mod common;
+
+use std::str::FromStr;
+
+use indexmap::IndexMap;
+use stackclass::{
+    context::Context,
+    errors::ApiError,
+    repository::{CourseRepository, ExtensionRepository, StageRepository},
+    schema::{Course, Extension, Stage},
+    service::{CourseService, StageService},
+};
+use uuid::Uuid;
+
+use common::{create_user, enroll_user, setup, unreachable_cluster};
+
+/// Inserts a bare course row, and returns its slug.
+async fn insert_course(ctx: &Context) -> String {
+    let slug = format!("course-{}", Uuid::now_v7().simple());
+    sqlx::query(
+        r#"
+        INSERT INTO courses (id, slug, name, short_name, release_status, description, summary, repository)
+        VALUES ($1, $2, $2, $2, 'beta', '', '', '')
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(&slug)
+    .execute(ctx.database.pool())
+    .await
+    .unwrap();
+    slug
+}
+
+fn stage(slug: &str, name: &str) -> (String, Stage) {
+    let yaml = format!("slug: {slug}\nname: {name:?}\ndifficulty: easy\ndescription: A stage\n");
+    (slug.to_string(), Stage::from_str(&yaml).unwrap())
+}
+
+/// A course with two base stages and the given extensions, each listing
+/// its stage suffixes in order.
+fn course(slug: &str, extensions: &[(&str, &[&str])]) -> Course {
+    let yaml = format!(
+        "slug: {slug}\nname: Course\nshort_name: Course\nrelease_status: beta\ndescription: A course\nsummary: A course\n"
+    );
+    let mut course = Course::from_str(&yaml).unwrap();
+    course.stages = ["b0", "b1"].iter().map(|s| stage(&format!("{slug}-{s}"), s)).collect();
+
+    let extensions = extensions.iter().map(|(ext, stages)| {
+        let ext_slug = format!("{slug}-{ext}");
+        let stages = stages.iter().map(|s| stage(&format!("{slug}-{s}"), s)).collect();
+        let extension = Extension {
+            slug: ext_slug.clone(),
+            name: ext.to_string(),
+            description: String::new(),
+            stages,
+        };
+        (ext_slug, extension)
+    });
+    course.extensions = Some(extensions.collect::<IndexMap<_, _>>());
+    course
+}
+
+/// Stage slugs without the course prefix, in progression order.
+async fn order(ctx: &Context, slug: &str) -> Vec<String> {
+    let stages = StageRepository::find_by_course(&ctx.database, slug).await.unwrap();
+    stages.iter().map(|stage| stage.slug.trim_start_matches(&format!("{slug}-")).into()).collect()
+}
+
+/// Extension weights, in order.
+async fn weights(ctx: &Context, slug: &str) -> Vec<(String, i32)> {
+    let extensions = ExtensionRepository::find_by_course(&ctx.database, slug).await.unwrap();
+    let prefix = format!("{slug}-");
+    extensions.iter().map(|e| (e.slug.trim_start_matches(&prefix).into(), e.weight)).collect()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_swap_extensions() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = insert_course(&ctx).await;
+
+    let initial = course(&slug, &[("x", &["x0", "x1"]), ("y", &["y0", "y1"])]);
+    CourseService::update_course(ctx.clone(), &initial, "c1", "admin").await.unwrap();
+    assert_eq!(order(&ctx, &slug).await, ["b0", "b1", "x0", "x1", "y0", "y1"]);
+
+    // Swap both the extensions and the stages of one of them
+    let swapped = course(&slug, &[("y", &["y1", "y0"]), ("x", &["x0", "x1"])]);
+    CourseService::update_course(ctx.clone(), &swapped, "c2", "admin").await.unwrap();
+    assert_eq!(order(&ctx, &slug).await, ["b0", "b1", "y1", "y0", "x0", "x1"]);
+    assert_eq!(weights(&ctx, &slug).await, [("y".into(), 0), ("x".into(), 1)]);
+
+    let positions: Vec<i32> =
+        sqlx::query_scalar("SELECT position FROM stages WHERE slug LIKE $1 || '-%' ORDER BY slug")
+            .bind(&slug)
+            .fetch_all(ctx.database.pool())
+            .await
+            .unwrap();
+    assert_eq!(positions, [0, 1, 0, 1, 1, 0]);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_failed_sync_rolls_back() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = insert_course(&ctx).await;
+
+    let initial = course(&slug, &[("x", &["x0", "x1"]), ("y", &["y0", "y1"])]);
+    CourseService::update_course(ctx.clone(), &initial, "c1", "admin").await.unwrap();
+
+    // The database rejects the stage of the last extension, after the order
+    // was shifted and the first extension written
+    let mut broken = course(&slug, &[("y", &["y0", "y1"]), ("x", &["x1", "x0"])]);
+    let x = broken.extensions.as_mut().unwrap().get_mut(&format!("{slug}-x")).unwrap();
+    x.stages.get_mut(&format!("{slug}-x0")).unwrap().name = "x0\0".into();
+    assert!(CourseService::update_course(ctx.clone(), &broken, "c2", "admin").await.is_err());
+
+    assert_eq!(order(&ctx, &slug).await, ["b0", "b1", "x0", "x1", "y0", "y1"]);
+    assert_eq!(weights(&ctx, &slug).await, [("x".into(), 0), ("y".into(), 1)]);
+    assert_eq!(CourseService::find_revisions(ctx.clone(), &slug).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_concurrent_sync_conflicts() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = insert_course(&ctx).await;
+    let initial = course(&slug, &[("x", &["x0", "x1"])]);
+
+    // Another sync of the course holds its lock
+    let mut tx = ctx.database.pool().begin().await.unwrap();
+    CourseRepository::lock(&mut tx, &slug).await.unwrap();
+    let result = CourseService::update_course(ctx.clone(), &initial, "c1", "admin").await;
+    assert!(matches!(result, Err(ApiError::Conflict(_))), "{result:?}");
+    assert!(order(&ctx, &slug).await.is_empty());
+
+    tx.rollback().await.unwrap();
+    CourseService::update_course(ctx.clone(), &initial, "c1", "admin").await.unwrap();
+    assert_eq!(order(&ctx, &slug).await, ["b0", "b1", "x0", "x1"]);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_sync_records_revisions() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = insert_course(&ctx).await;
+    let prefixed = |stages: &[&str]| -> Vec<String> {
+        stages.iter().map(|stage| format!("{slug}-{stage}")).collect()
+    };
+
+    let initial = course(&slug, &[("x", &["x0", "x1"])]);
+    CourseService::update_course(ctx.clone(), &initial, "c1", "admin").await.unwrap();
+
+    // One stage replaces another and a base stage changes its description
+    let mut changed = course(&slug, &[("x", &["x0", "x2"])]);
+    changed.stages.get_mut(&format!("{slug}-b1")).unwrap().description = "Reworded".into();
+    CourseService::update_course(ctx.clone(), &changed, "c2", "maintainer").await.unwrap();
+
+    let revisions = CourseService::find_revisions(ctx.clone(), &slug).await.unwrap();
+    assert_eq!(revisions.len(), 2);
+    assert_eq!((revisions[0].commit.as_str(), revisions[0].actor.as_str()), ("c2", "maintainer"));
+    assert_eq!(revisions[0].stages_added, prefixed(&["x2"]));
+    assert_eq!(revisions[0].stages_removed, prefixed(&["x1"]));
+    assert_eq!(revisions[0].stages_updated, prefixed(&["b1"]));
+
+    assert_eq!((revisions[1].commit.as_str(), revisions[1].actor.as_str()), ("c1", "admin"));
+    assert_eq!(revisions[1].stages_added, prefixed(&["b0", "b1", "x0", "x1"]));
+    assert!(revisions[1].stages_removed.is_empty() && revisions[1].stages_updated.is_empty());
+
+    let result = CourseService::find_revisions(ctx.clone(), "no-such-course").await;
+    assert!(matches!(result, Err(ApiError::NotFound)), "{result:?}");
+}
+
+/// Whether a stage is left in the database, archived or not.
+async fn stage_exists(ctx: &Context, slug: &str) -> bool {
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM stages WHERE slug = $1)")
+        .bind(slug)
+        .fetch_one(ctx.database.pool())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_removed_current_stage_is_archived() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = insert_course(&ctx).await;
+    let (b0, b1) = (format!("{slug}-b0"), format!("{slug}-b1"));
+
+    let initial = course(&slug, &[("x", &["x0", "x1"])]);
+    CourseService::update_course(ctx.clone(), &initial, "c1", "admin").await.unwrap();
+
+    // A learner completed the first stage and works on the second one
+    let user_id = create_user(&ctx).await;
+    enroll_user(&ctx, &user_id, &slug).await;
+    let mut user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+    StageService::complete(ctx.clone(), &user_id, &slug, &b0, None).await.unwrap();
+
+    // The course drops their current stage, and one nobody reached
+    let mut removed = course(&slug, &[("x", &["x0"])]);
+    removed.stages.shift_remove(&b1);
+    CourseService::update_course(ctx.clone(), &removed, "c2", "admin").await.unwrap();
+    assert_eq!(order(&ctx, &slug).await, ["b0", "x0"]);
+    assert!(stage_exists(&ctx, &b1).await);
+    assert!(!stage_exists(&ctx, &format!("{slug}-x1")).await);
+
+    // Later syncs keep the archived stage aside
+    CourseService::update_course(ctx.clone(), &removed, "c3", "admin").await.unwrap();
+    assert_eq!(order(&ctx, &slug).await, ["b0", "x0"]);
+
+    // The learner keeps their progress and moves on past the archived stage
+    let stages = StageRepository::find_user_stages(&ctx.database, &user_id, &slug).await.unwrap();
+    assert_eq!(stages.len(), 2);
+    let user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    assert_eq!(user_course.current_stage_slug.as_deref(), Some(b1.as_str()));
+    StageService::complete(ctx.clone(), &user_id, &slug, &b1, None).await.unwrap();
+    let user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    assert_eq!(user_course.current_stage_slug, Some(format!("{slug}-x0")));
+
+    // Once no learner references it, the archived stage goes away
+    sqlx::query("DELETE FROM user_courses WHERE id = $1")
+        .bind(user_course.id)
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+    CourseService::update_course(ctx.clone(), &removed, "c4", "admin").await.unwrap();
+    assert!(!stage_exists(&ctx, &b1).await);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_archived_stage_is_restored() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = insert_course(&ctx).await;
+    let b1 = format!("{slug}-b1");
+
+    let initial = course(&slug, &[("x", &["x0"])]);
+    CourseService::update_course(ctx.clone(), &initial, "c1", "admin").await.unwrap();
+
+    let user_id = create_user(&ctx).await;
+    enroll_user(&ctx, &user_id, &slug).await;
+    let mut user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+    StageService::complete(ctx.clone(), &user_id, &slug, &format!("{slug}-b0"), None)
+        .await
+        .unwrap();
+
+    // The stage comes back at its place once the course lists it again
+    let mut removed = course(&slug, &[("x", &["x0"])]);
+    removed.stages.shift_remove(&b1);
+    CourseService::update_course(ctx.clone(), &removed, "c2", "admin").await.unwrap();
+    assert_eq!(order(&ctx, &slug).await, ["b0", "x0"]);
+
+    CourseService::update_course(ctx.clone(), &initial, "c3", "admin").await.unwrap();
+    assert_eq!(order(&ctx, &slug).await, ["b0", "b1", "x0"]);
+    let user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    assert_eq!(user_course.current_stage_slug, Some(b1));
+}
@@ -0,0 +1,235 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deleting a course tears down its repositories on Gitea, the images of its
+//! learner repositories, its robot accounts and project on Harbor, and the
+//! Secrets of its robot accounts. These tests need a disposable PostgreSQL
+//! database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test course-teardown-tests -- --ignored
+
+mod common;
+
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::Path,
+    http::{Method, Request, Response, StatusCode, header},
+    routing::{delete, get},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    model::{PooledRepoModel, RegistryCredentialModel},
+    repository::{CourseRepository, RegistryRepository, RepoPoolRepository},
+    routes,
+    utils::crypto,
+};
+use tower::ServiceExt;
+
+use common::{artifact, create_course, create_user, enroll_user, mock_context};
+
+/// A Kubernetes API server recording the Secrets deleted from it.
+fn cluster(deleted: Arc<Mutex<Vec<String>>>) -> kube::Client {
+    let service = tower::service_fn(move |req: Request<kube::client::Body>| {
+        let deleted = deleted.clone();
+        async move {
+            assert_eq!(req.method(), Method::DELETE);
+            let name = req.uri().path().rsplit('/').next().unwrap().to_string();
+            deleted.lock().unwrap().push(format!("secret {name}"));
+            let secret =
+                json!({ "apiVersion": "v1", "kind": "Secret", "metadata": { "name": name } });
+            let body = kube::client::Body::from(serde_json::to_vec(&secret).unwrap());
+            Ok::<_, Infallible>(Response::new(body))
+        }
+    });
+    kube::Client::new(service, "stackclass")
+}
+
+/// A Gitea server recording the repositories deleted from it, failing for
+/// the one named `broken`, which also serves as a Harbor server where every
+/// repository of the course projects holds an image until deleted, and a
+/// Kubernetes API server, all recording what is deleted from them.
+async fn context(deleted: Arc<Mutex<Vec<String>>>, broken: &str) -> Arc<Context> {
+    let broken = broken.to_string();
+    let (listed, removed) = (deleted.clone(), deleted.clone());
+    let (robots, projects, secrets) = (deleted.clone(), deleted.clone(), deleted.clone());
+    let app = Router::new()
+        .route(
+            "/api/v1/repos/{owner}/{repo}",
+            delete(move |Path((owner, repo)): Path<(String, String)>| async move {
+                if repo == broken {
+                    return StatusCode::INTERNAL_SERVER_ERROR;
+                }
+                deleted.lock().unwrap().push(format!("{owner}/{repo}"));
+                StatusCode::NO_CONTENT
+            }),
+        )
+        .route(
+            "/api/v2.0/projects/{project}/repositories/{repo}/artifacts",
+            get(move |Path((project, repo)): Path<(String, String)>| async move {
+                let image = format!("image {project}/{repo}");
+                match project.contains('-') {
+                    true if !listed.lock().unwrap().contains(&image) => {
+                        Ok(Json(vec![artifact(&repo)]))
+                    }
+                    true => Ok(Json(vec![])),
+                    false => Err(StatusCode::NOT_FOUND),
+                }
+            }),
+        )
+        .route(
+            "/api/v2.0/projects/{project}/repositories/{repo}/artifacts/{reference}",
+            delete(move |Path((project, repo, reference)): Path<(String, String, String)>| {
+                assert_eq!(reference, format!("sha256:{repo}"));
+                removed.lock().unwrap().push(format!("image {project}/{repo}"));
+                async { StatusCode::OK }
+            }),
+        )
+        .route(
+            "/api/v2.0/robots/{id}",
+            delete(move |Path(id): Path<i64>| async move {
+                robots.lock().unwrap().push(format!("robot {id}"));
+                StatusCode::OK
+            }),
+        )
+        .route(
+            "/api/v2.0/projects/{project}",
+            delete(move |Path(project): Path<String>| async move {
+                projects.lock().unwrap().push(format!("project {project}"));
+                StatusCode::OK
+            }),
+        );
+
+    let mut ctx = mock_context(app).await;
+    ctx.k8s = cluster(secrets);
+    Arc::new(ctx)
+}
+
+async fn delete_course(ctx: &Arc<Context>, uri: &str) -> (StatusCode, Value) {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let auth = format!("Basic {}", STANDARD.encode(format!("admin:{password}")));
+    let req = Request::delete(uri).header(header::AUTHORIZATION, auth).body(Body::empty()).unwrap();
+
+    let app = routes::build(ctx.clone());
+    let res = app.oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_delete_course_tears_down_repositories() {
+    let deleted = Arc::new(Mutex::new(Vec::new()));
+    let ctx = context(deleted.clone(), "").await;
+    let slug = create_course(&ctx).await;
+    let course = CourseRepository::get_by_slug(&ctx.database, &slug).await.unwrap();
+
+    let user_id = create_user(&ctx).await;
+    let user_course_id = enroll_user(&ctx, &user_id, &slug).await;
+    let pooled = PooledRepoModel::new(course.id, "0123abc");
+    RepoPoolRepository::create(&ctx.database, &pooled).await.unwrap();
+
+    // A retired robot account of a previous rotation and the active one
+    let project = format!("{}-{slug}", ctx.config.namespace);
+    for robot_id in [1, 2] {
+        let secret_name = format!("{slug}-registry-{robot_id}");
+        let robot_name = format!("robot${project}+pipeline-{robot_id}");
+        let credential = RegistryCredentialModel::new_at(
+            course.id,
+            &project,
+            robot_id,
+            &robot_name,
+            &secret_name,
+            "",
+            ctx.clock.now(),
+        );
+        let mut tx = ctx.database.pool().begin().await.unwrap();
+        RegistryRepository::activate(&mut tx, &credential).await.unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    let (status, body) = delete_course(&ctx, &format!("/v1/courses/{slug}")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        body,
+        json!({
+            "slug": slug,
+            "deleted_repositories": [user_course_id.to_string(), pooled.repo(), slug],
+            "failed_repositories": [],
+            "deleted_images": 2,
+            "failed_images": [],
+        })
+    );
+
+    let org = &ctx.config.namespace;
+    let deleted = deleted.lock().unwrap().clone();
+    assert_eq!(
+        deleted,
+        vec![
+            format!("image {org}-{slug}/{user_course_id}"),
+            format!("image {org}-{slug}/{user_course_id}-test"),
+            format!("{org}/{user_course_id}"),
+            format!("{org}/{}", pooled.repo()),
+            format!("{org}/{slug}"),
+            "robot 1".to_string(),
+            format!("secret {slug}-registry-1"),
+            "robot 2".to_string(),
+            format!("secret {slug}-registry-2"),
+            format!("project {project}"),
+        ]
+    );
+    assert!(CourseRepository::get_by_slug(&ctx.database, &slug).await.is_err());
+    let credentials = RegistryRepository::find_by_course(&ctx.database, &course.id);
+    assert!(credentials.await.unwrap().is_empty());
+
+    // A course already gone has nothing left to tear down.
+    let (status, _) = delete_course(&ctx, &format!("/v1/courses/{slug}")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_failed_teardown_keeps_course() {
+    let deleted = Arc::new(Mutex::new(Vec::new()));
+    let ctx = context(deleted.clone(), "").await;
+    let slug = create_course(&ctx).await;
+
+    let user_id = create_user(&ctx).await;
+    let user_course_id = enroll_user(&ctx, &user_id, &slug).await;
+
+    // The other repositories are deleted regardless, while every record that
+    // names a repository stays in place for a retry
+    let ctx = context(deleted.clone(), &slug).await;
+    let (status, body) = delete_course(&ctx, &format!("/v1/courses/{slug}")).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    assert!(body["message"].as_str().unwrap().contains(&slug), "{body}");
+    assert!(CourseRepository::get_by_slug(&ctx.database, &slug).await.is_ok());
+    let org = &ctx.config.namespace;
+    assert!(deleted.lock().unwrap().contains(&format!("{org}/{user_course_id}")));
+
+    // Forcing the deletion leaves the template behind
+    let (status, body) = delete_course(&ctx, &format!("/v1/courses/{slug}?force=true")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["deleted_repositories"], json!([user_course_id.to_string()]));
+    assert_eq!(body["failed_repositories"], json!([slug]));
+    assert!(CourseRepository::get_by_slug(&ctx.database, &slug).await.is_err());
+}
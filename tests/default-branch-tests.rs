@@ -0,0 +1,204 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Repositories are generated, protected, hooked and graded on the default
+//! branch of the configuration. These tests need a disposable PostgreSQL
+//! database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test default-branch-tests -- --ignored
+
+#![recursion_limit = "256"]
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    Json, Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+    routing::{get, post},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    repository::CourseRepository,
+    routes,
+    service::{CourseService, RepoService},
+    utils::crypto,
+};
+use tower::ServiceExt;
+
+use common::{TIMESTAMP, create_course, enroll, mock_api, repository, setup, unreachable_cluster};
+
+/// Default branch of the repositories in these tests.
+const BRANCH: &str = "trunk";
+
+fn protection(rule_name: &str) -> Value {
+    json!({
+        "rule_name": rule_name,
+        "enable_push": true,
+        "enable_push_whitelist": false,
+        "push_whitelist_usernames": null,
+        "enable_force_push": true,
+        "enable_force_push_allowlist": true,
+        "force_push_allowlist_usernames": ["admin"],
+        "created_at": TIMESTAMP,
+        "updated_at": TIMESTAMP
+    })
+}
+
+/// The calls received by the fake Gitea server, with their bodies.
+type Calls = Arc<Mutex<Vec<(String, Value)>>>;
+
+/// A Gitea server without repositories, recording the generations, branch
+/// protections and webhooks it is asked for.
+async fn context() -> (Arc<Context>, Calls) {
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    ctx.config.default_branch = BRANCH.to_string();
+    ctx.config.repo_webhooks = true;
+    let calls = Calls::default();
+
+    let not_found = || (StatusCode::NOT_FOUND, Json(json!({ "message": "not found" })));
+    let (generated, protected, hooked) = (calls.clone(), calls.clone(), calls.clone());
+    let app = Router::new()
+        .route("/api/v1/repos/{owner}/{repo}", get(move || async move { not_found() }))
+        .route(
+            "/api/v1/repos/{owner}/{template}/generate",
+            post(move |Json(req): Json<Value>| async move {
+                let (owner, name) = (req["owner"].as_str().unwrap(), req["name"].as_str().unwrap());
+                let repository = repository(owner, name);
+                generated.lock().unwrap().push(("generate".into(), req));
+                (StatusCode::CREATED, Json(repository))
+            }),
+        )
+        .route(
+            "/api/v1/repos/{owner}/{repo}/branch_protections",
+            post(move |Json(req): Json<Value>| async move {
+                let rule_name = req["rule_name"].as_str().unwrap().to_string();
+                protected.lock().unwrap().push(("protect".into(), req));
+                (StatusCode::CREATED, Json(protection(&rule_name)))
+            }),
+        )
+        .route(
+            "/api/v1/repos/{owner}/{repo}/branch_protections/{name}",
+            get(move || async move { not_found() }),
+        )
+        .route(
+            "/api/v1/repos/{owner}/{repo}/hooks",
+            get(|| async { Json(Vec::<Value>::new()) }).post(move |Json(req): Json<Value>| {
+                let hooked = hooked.clone();
+                async move {
+                    let mut hook = req.clone();
+                    hooked.lock().unwrap().push(("hook".into(), req));
+                    hook["id"] = json!(1);
+                    hook["created_at"] = json!(TIMESTAMP);
+                    hook["updated_at"] = json!(TIMESTAMP);
+                    (StatusCode::CREATED, Json(hook))
+                }
+            }),
+        );
+
+    mock_api(&mut ctx, app).await;
+    (Arc::new(ctx), calls)
+}
+
+/// Sends a push event of the reference in the repository to the Gitea
+/// webhook.
+async fn push(ctx: &Arc<Context>, repo: &str, reference: &str) -> StatusCode {
+    let user = json!({ "name": "learner", "email": "learner@local", "username": "learner" });
+    let commit = json!({
+        "id": "0123456789abcdef0123456789abcdef01234567",
+        "message": "Pass the first stage",
+        "url": "",
+        "author": user,
+        "committer": user,
+        "timestamp": TIMESTAMP,
+    });
+    let repository = repository(&ctx.config.namespace, repo);
+    let event = json!({
+        "ref": reference,
+        "before": "0000000000000000000000000000000000000000",
+        "after": commit["id"],
+        "compare_url": "",
+        "commits": [commit],
+        "total_commits": 1,
+        "head_commit": commit,
+        "pusher": repository["owner"],
+        "sender": repository["owner"],
+        "repository": repository,
+    });
+
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let auth = format!("Basic {}", STANDARD.encode(format!("admin:{password}")));
+    let req = Request::post("/v1/webhooks/gitea")
+        .header(header::AUTHORIZATION, auth)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(event.to_string()))
+        .unwrap();
+    routes::build(ctx.clone()).oneshot(req).await.unwrap().status()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_repositories_are_set_up_on_the_default_branch() {
+    let (ctx, calls) = context().await;
+    RepoService::new(ctx.clone()).generate_in("stackclass", "redis", "learner").await.unwrap();
+
+    let calls = calls.lock().unwrap().clone();
+    let names: Vec<&str> = calls.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["generate", "protect", "hook"]);
+    assert_eq!(calls[0].1["default_branch"], BRANCH);
+    assert_eq!(calls[1].1["rule_name"], BRANCH);
+    assert_eq!(calls[2].1["branch_filter"], BRANCH);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_only_pushes_to_the_default_branch_are_graded() {
+    let (ctx, _) = context().await;
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let user_course = CourseRepository::get_user_course(&ctx.database, &user_id, &slug);
+    let repo = user_course.await.unwrap().id.to_string();
+
+    // Pushes to main are now like pushes to any other branch
+    assert_eq!(push(&ctx, &repo, "refs/heads/main").await, StatusCode::OK);
+    let user_course = CourseRepository::get_user_course(&ctx.database, &user_id, &slug);
+    assert!(!user_course.await.unwrap().activated);
+
+    // The first push to the default branch activates the course
+    assert_eq!(push(&ctx, &repo, &format!("refs/heads/{BRANCH}")).await, StatusCode::OK);
+    let user_course = CourseRepository::get_user_course(&ctx.database, &user_id, &slug);
+    assert!(user_course.await.unwrap().activated);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_identity_instructions_name_the_default_branch() {
+    let (ctx, _) = context().await;
+    let slug = create_course(&ctx).await;
+    sqlx::query("UPDATE courses SET require_verified_identity = TRUE WHERE slug = $1")
+        .bind(&slug)
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+    let user_id = enroll(&ctx, &slug).await;
+
+    let courses = CourseService::find_user_courses(ctx.clone(), &user_id).await.unwrap();
+    assert_eq!(courses[0].status, "awaiting_identity_verification");
+    let instructions = courses[0].instructions.as_deref().unwrap();
+    assert!(instructions.ends_with(&format!("push to the {BRANCH} branch.")), "{instructions}");
+}
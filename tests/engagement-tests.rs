@@ -0,0 +1,192 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! First-party engagement events and their aggregation. These tests need a
+//! disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test engagement-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{Duration, Utc};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    model::StageAttemptModel,
+    repository::{CourseRepository, StageRepository},
+    routes,
+    service::{CourseService, EngagementService},
+    utils::crypto,
+};
+use tower::ServiceExt;
+
+use common::{create_course, create_user, enroll, setup, token, unreachable_cluster};
+
+async fn send(
+    ctx: &Arc<Context>,
+    method: Method,
+    uri: &str,
+    auth: &str,
+    body: String,
+) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, auth)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap();
+
+    let app = routes::build(ctx.clone());
+    let res = app.oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Reports an event of the given type with the first stage of the course.
+async fn report(ctx: &Arc<Context>, slug: &str, user_id: &str, kind: &str) -> StatusCode {
+    let bearer = format!("Bearer {}", token(ctx, user_id).await);
+    let event =
+        json!({ "type": kind, "stage_slug": format!("{slug}-s1"), "occurred_at": Utc::now() });
+    let uri = format!("/v1/user/courses/{slug}/events");
+    send(ctx, Method::POST, &uri, &bearer, event.to_string()).await.0
+}
+
+async fn engagement(ctx: &Arc<Context>, slug: &str) -> Value {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let auth = format!("Basic {}", STANDARD.encode(format!("admin:{password}")));
+    let uri = format!("/v1/admin/courses/{slug}/engagement");
+    let (status, body) = send(ctx, Method::GET, &uri, &auth, String::new()).await;
+    assert_eq!(status, StatusCode::OK);
+    body
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_events_are_validated_and_rate_limited() {
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    ctx.config.engagement_rate_limit = 2;
+    let ctx = Arc::new(ctx);
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let bearer = format!("Bearer {}", token(&ctx, &user_id).await);
+    let uri = format!("/v1/user/courses/{slug}/events");
+
+    // Only allowlisted types, known stages and plausible times are accepted
+    assert_eq!(
+        report(&ctx, &slug, &user_id, "page_scrolled").await,
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+
+    let future = Utc::now() + Duration::hours(1);
+    let event =
+        json!({ "type": "hint_opened", "stage_slug": format!("{slug}-s1"), "occurred_at": future });
+    let (status, _) = send(&ctx, Method::POST, &uri, &bearer, event.to_string()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let event =
+        json!({ "type": "hint_opened", "stage_slug": "unknown", "occurred_at": Utc::now() });
+    let (status, _) = send(&ctx, Method::POST, &uri, &bearer, event.to_string()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let event =
+        json!({ "type": "hint_opened", "stage_slug": "x".repeat(2048), "occurred_at": Utc::now() });
+    let (status, _) = send(&ctx, Method::POST, &uri, &bearer, event.to_string()).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+    // Learners can only report events of their own courses
+    let stranger = create_user(&ctx).await;
+    assert_eq!(report(&ctx, &slug, &stranger, "hint_opened").await, StatusCode::NOT_FOUND);
+
+    assert_eq!(report(&ctx, &slug, &user_id, "hint_opened").await, StatusCode::ACCEPTED);
+    assert_eq!(report(&ctx, &slug, &user_id, "copy_clone_url").await, StatusCode::ACCEPTED);
+    assert_eq!(report(&ctx, &slug, &user_id, "hint_opened").await, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_conversion_survives_rollup() {
+    let ctx = setup(unreachable_cluster()).await;
+    let db = &ctx.database;
+    let slug = create_course(&ctx).await;
+    let (pusher, viewer) = (enroll(&ctx, &slug).await, enroll(&ctx, &slug).await);
+
+    for user_id in [&pusher, &viewer] {
+        let mut user_course = CourseRepository::get_user_course(db, user_id, &slug).await.unwrap();
+        CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+        assert_eq!(report(&ctx, &slug, user_id, "instruction_viewed").await, StatusCode::ACCEPTED);
+    }
+    assert_eq!(report(&ctx, &slug, &viewer, "instruction_viewed").await, StatusCode::ACCEPTED);
+    assert_eq!(report(&ctx, &slug, &viewer, "solution_viewed").await, StatusCode::ACCEPTED);
+
+    // Only the first learner pushes after viewing the instructions
+    let stage_slug = format!("{slug}-s1");
+    let user_stage =
+        StageRepository::get_user_stage(db, &pusher, &slug, &stage_slug).await.unwrap();
+    let attempt = StageAttemptModel::new(user_stage.id, "");
+    StageRepository::create_attempt(db, &attempt).await.unwrap();
+
+    let body = engagement(&ctx, &slug).await;
+    let expected = json!({
+        "stage_slug": stage_slug,
+        "extension_slug": null,
+        "instruction_viewed": 3,
+        "hint_opened": 0,
+        "solution_viewed": 1,
+        "copy_clone_url": 0,
+        "viewers": 2,
+        "viewers_pushed": 1,
+        "conversion": 0.5,
+    });
+    assert_eq!(body[0], expected);
+    assert_eq!(body.as_array().unwrap().len(), 3);
+    assert_eq!(body[1]["viewers"], 0);
+
+    // Events out of retention are purged, their counts are kept
+    sqlx::query(
+        r#"
+        UPDATE engagement_events SET received_at = NOW() - INTERVAL '31 days'
+        WHERE stage_id = (SELECT id FROM stages WHERE slug = $1)
+        "#,
+    )
+    .bind(&stage_slug)
+    .execute(db.pool())
+    .await
+    .unwrap();
+    assert!(EngagementService::roll_up(ctx.clone()).await.unwrap() >= 4);
+
+    let remaining = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM engagement_events WHERE stage_id = (SELECT id FROM stages WHERE slug = $1)",
+    )
+    .bind(&stage_slug)
+    .fetch_one(db.pool())
+    .await
+    .unwrap();
+    assert_eq!(remaining, 0);
+
+    // Newer events add up with the rolled up ones
+    assert_eq!(report(&ctx, &slug, &viewer, "instruction_viewed").await, StatusCode::ACCEPTED);
+    let body = engagement(&ctx, &slug).await;
+    assert_eq!(body[0]["instruction_viewed"], 4);
+    assert_eq!(body[0]["viewers"], 2);
+    assert_eq!(body[0]["viewers_pushed"], 1);
+}
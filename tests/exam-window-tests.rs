@@ -0,0 +1,170 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Courses run as timed assessments. These tests need a disposable
+//! PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test exam-window-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{Duration, Utc};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    errors::ApiError,
+    model::StageAttemptModel,
+    repository::{CourseRepository, StageRepository},
+    request::CreateUserCourseRequest,
+    routes,
+    service::CourseService,
+    utils::crypto,
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{create_course, create_user, enroll, pipeline_secret, setup, unreachable_cluster};
+
+async fn send(ctx: &Arc<Context>, method: Method, uri: &str, body: Value) -> (StatusCode, Value) {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(
+            header::AUTHORIZATION,
+            format!("Basic {}", STANDARD.encode(format!("admin:{password}"))),
+        )
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let app = routes::build(ctx.clone());
+    let res = app.oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Pushes to the first stage of the user course and reports a passing
+/// pipeline run for it, returning the recorded attempt.
+async fn pass_first_stage(ctx: &Arc<Context>, slug: &str, user_id: &str) -> StageAttemptModel {
+    let db = &ctx.database;
+    let user_course = CourseRepository::get_user_course(db, user_id, slug).await.unwrap();
+    let stage_slug = format!("{slug}-s1");
+    let user_stage = StageRepository::get_user_stage(db, user_id, slug, &stage_slug).await.unwrap();
+
+    let name = format!("run-{}", Uuid::now_v7().simple());
+    let attempt = StageAttemptModel::new(user_stage.id, "").with_pipeline_run(&name);
+    let attempt = StageRepository::create_attempt(db, &attempt).await.unwrap();
+
+    let repo = user_course.id.to_string();
+    let nonce = crypto::random_hex(16).unwrap();
+    let event = json!({
+        "name": name,
+        "nonce": nonce,
+        "status": "Succeeded",
+        "repo": repo,
+        "course": slug,
+        "stage": stage_slug,
+        "secret": pipeline_secret(ctx, &name, &nonce, &repo, slug, &stage_slug),
+        "tasks": { "test": { "status": "Succeeded", "reason": "Succeeded" } }
+    });
+    let (status, _) = send(ctx, Method::POST, "/v1/webhooks/tekton", event).await;
+    assert_eq!(status, StatusCode::OK);
+
+    attempt
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_late_attempts_do_not_count() {
+    let ctx = setup(unreachable_cluster()).await;
+    let db = &ctx.database;
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let mut user_course = CourseRepository::get_user_course(db, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+
+    let closes_at = Utc::now() - Duration::hours(1);
+    let window = json!({ "opens_at": closes_at - Duration::days(7), "closes_at": closes_at });
+    let uri = format!("/v1/admin/courses/{slug}/exam-window");
+    let (status, body) = send(&ctx, Method::PUT, &uri, window).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["closes_at"].is_string());
+
+    let res = CourseService::get_user_course(ctx.clone(), &user_id, &slug).await.unwrap();
+    assert!(res.remaining_seconds.unwrap() < 0);
+
+    // Passing after the close is recorded, but the stage stays in progress
+    let attempt = pass_first_stage(&ctx, &slug, &user_id).await;
+    assert!(attempt.late);
+    let stage_slug = format!("{slug}-s1");
+    let user_stage = StageRepository::get_user_stage(db, &user_id, &slug, &stage_slug);
+    assert_eq!(user_stage.await.unwrap().status, "in_progress");
+
+    // Extensions must reach past the close
+    let uri = format!("/v1/admin/users/{user_id}/courses/{slug}/extend-deadline");
+    let early = json!({ "deadline": closes_at - Duration::minutes(1) });
+    assert_eq!(send(&ctx, Method::POST, &uri, early).await.0, StatusCode::BAD_REQUEST);
+
+    let deadline = json!({ "deadline": Utc::now() + Duration::days(1) });
+    let (status, body) = send(&ctx, Method::POST, &uri, deadline).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["remaining_seconds"].as_i64().unwrap() > 0);
+    assert_eq!(body["completed_late"], false);
+
+    // Within the extension the attempt counts, and is remembered as late
+    let attempt = pass_first_stage(&ctx, &slug, &user_id).await;
+    assert!(!attempt.late);
+    let user_stage = StageRepository::get_user_stage(db, &user_id, &slug, &stage_slug);
+    assert_eq!(user_stage.await.unwrap().status, "completed");
+
+    let res = CourseService::get_user_course(ctx.clone(), &user_id, &slug).await.unwrap();
+    assert!(res.completed_late);
+    assert_eq!(res.completed_stage_count, 1);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_enrollment_waits_for_window() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let opens_at = Utc::now() + Duration::days(1);
+    let window = json!({ "opens_at": opens_at, "closes_at": opens_at - Duration::hours(1) });
+    let uri = format!("/v1/admin/courses/{slug}/exam-window");
+    assert_eq!(send(&ctx, Method::PUT, &uri, window).await.0, StatusCode::BAD_REQUEST);
+
+    let window = json!({ "opens_at": opens_at, "closes_at": opens_at + Duration::days(7) });
+    assert_eq!(send(&ctx, Method::PUT, &uri, window).await.0, StatusCode::OK);
+
+    let req = CreateUserCourseRequest {
+        course_slug: slug.clone(),
+        proficiency: "beginner".to_string(),
+        cadence: "weekly".to_string(),
+        accountability: false,
+        cohort: None,
+        cohort_deadline: None,
+        language: None,
+    };
+    let user_id = create_user(&ctx).await;
+    let res = CourseService::create_user_course(ctx.clone(), &user_id, &req).await;
+    assert!(matches!(res, Err(ApiError::Forbidden(_))));
+}
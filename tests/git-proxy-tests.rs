@@ -0,0 +1,176 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Oversized pushes are cut off while streaming through the git proxy, gzip
+//! encoded ones once inflated. These tests need a disposable PostgreSQL
+//! database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test git-proxy-tests -- --ignored
+
+mod common;
+
+use std::{
+    convert::Infallible,
+    io::Write,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+};
+
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+    routing::post,
+};
+use bytes::Bytes;
+use flate2::{Compression, write::GzEncoder};
+use futures::{StreamExt, stream};
+use stackclass::{context::Context, routes, utils::endpoints::Endpoints};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{serve, setup, unreachable_cluster};
+
+const LIMIT: u64 = 1024 * 1024;
+const CHUNK: usize = 64 * 1024;
+
+/// Commands of a push asking for a side band, ahead of its pack.
+const COMMANDS: &[u8] = b"00a0 refs/heads/main\0report-status side-band-64k\n0000";
+
+/// Starts a git server that drains push bodies, and returns the number of
+/// bytes it received.
+async fn git_server(ctx: &mut Context) -> Arc<AtomicU64> {
+    let received = Arc::new(AtomicU64::new(0));
+    let counter = received.clone();
+    let app = Router::new().route(
+        "/{*path}",
+        post(move |body: Body| async move {
+            let mut data = body.into_data_stream();
+            while let Some(Ok(chunk)) = data.next().await {
+                counter.fetch_add(chunk.len() as u64, Ordering::SeqCst);
+            }
+            "0000"
+        }),
+    );
+
+    ctx.config.git_server_endpoint = serve(app).await;
+    ctx.config.max_receive_pack_size = LIMIT;
+    ctx.endpoints = Endpoints::new(&ctx.config).unwrap();
+    received
+}
+
+/// An endless push, counting the chunks the proxy pulled from it.
+fn endless_push(produced: Arc<AtomicUsize>) -> Body {
+    let commands = Bytes::from_static(COMMANDS);
+    let pack = stream::repeat_with(move || {
+        produced.fetch_add(1, Ordering::SeqCst);
+        Bytes::from(vec![0u8; CHUNK])
+    });
+    Body::from_stream(stream::once(async { commands }).chain(pack).map(Ok::<_, Infallible>))
+}
+
+/// A gzip encoded push of the commands followed by a pack of the given size.
+fn gzip_push(size: usize) -> Request<Body> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(COMMANDS).unwrap();
+    encoder.write_all(&vec![0u8; size]).unwrap();
+    let body = encoder.finish().unwrap();
+
+    Request::post(format!("/{}/git-receive-pack", Uuid::now_v7()))
+        .header(header::CONTENT_TYPE, "application/x-git-receive-pack-request")
+        .header(header::CONTENT_ENCODING, "gzip")
+        .header(header::CONTENT_LENGTH, body.len())
+        .body(Body::from(body))
+        .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_oversized_push_is_cut_off() {
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    let received = git_server(&mut ctx).await;
+    let app = routes::build(Arc::new(ctx));
+
+    let produced = Arc::new(AtomicUsize::new(0));
+    let req = Request::post(format!("/{}/git-receive-pack", Uuid::now_v7()))
+        .header(header::CONTENT_TYPE, "application/x-git-receive-pack-request")
+        .body(endless_push(produced.clone()))
+        .unwrap();
+    let res = app.clone().oneshot(req).await.unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[header::CONTENT_TYPE], "application/x-git-receive-pack-result");
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let body = String::from_utf8_lossy(&body);
+    assert!(body.contains("\x03push rejected"), "{body}");
+
+    // Reading stopped right after the limit, nothing past it was forwarded
+    assert!(produced.load(Ordering::SeqCst) <= LIMIT as usize / CHUNK + 2);
+    assert!(received.load(Ordering::SeqCst) <= LIMIT);
+
+    // Other clients get a plain 413, announced sizes are refused upfront
+    let req = Request::post(format!("/{}/git-receive-pack", Uuid::now_v7()))
+        .body(endless_push(Arc::default()))
+        .unwrap();
+    assert_eq!(app.clone().oneshot(req).await.unwrap().status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let req = Request::post(format!("/{}/git-receive-pack", Uuid::now_v7()))
+        .header(header::CONTENT_LENGTH, LIMIT + 1)
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(app.oneshot(req).await.unwrap().status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_push_within_limit_is_forwarded() {
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    let received = git_server(&mut ctx).await;
+    let app = routes::build(Arc::new(ctx));
+
+    let pack = vec![0u8; LIMIT as usize];
+    let req = Request::post(format!("/{}/git-receive-pack", Uuid::now_v7()))
+        .header(header::CONTENT_TYPE, "application/x-git-receive-pack-request")
+        .body(Body::from(pack))
+        .unwrap();
+    let res = app.oneshot(req).await.unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(received.load(Ordering::SeqCst), LIMIT);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_gzip_push_is_inflated() {
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    let received = git_server(&mut ctx).await;
+    let app = routes::build(Arc::new(ctx));
+
+    // The git server gets the plain body
+    let res = app.clone().oneshot(gzip_push(LIMIT as usize / 2)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(received.load(Ordering::SeqCst), LIMIT / 2 + COMMANDS.len() as u64);
+
+    // A small body inflating past the limit is cut off, with a message in
+    // the side band the client asked for
+    received.store(0, Ordering::SeqCst);
+    let res = app.oneshot(gzip_push(16 * LIMIT as usize)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let body = String::from_utf8_lossy(&body);
+    assert!(body.contains("\x03push rejected"), "{body}");
+    assert!(received.load(Ordering::SeqCst) <= LIMIT);
+}
@@ -0,0 +1,83 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The user course reports the latest commit of its repository, if the git
+//! server can tell. These tests need a disposable PostgreSQL database, run
+//! them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test last-commit-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{Json, Router, http::StatusCode, routing::get};
+use serde_json::{Value, json};
+use stackclass::{context::Context, service::CourseService};
+
+use common::{create_course, create_user, enroll_user, mock_api, setup, unreachable_cluster};
+
+/// Points the context at a Gitea server answering every commit listing with
+/// the given status and body.
+async fn with_gitea(ctx: Arc<Context>, status: StatusCode, body: Value) -> Arc<Context> {
+    let app = Router::new().route(
+        "/api/v1/repos/{owner}/{repo}/commits",
+        get(move || async move { (status, Json(body)) }),
+    );
+
+    let mut ctx = Arc::into_inner(ctx).unwrap();
+    mock_api(&mut ctx, app).await;
+    Arc::new(ctx)
+}
+
+fn commit(message: &str, date: &str) -> Value {
+    let user = json!({ "name": "Alice", "email": "alice@example.com", "date": date });
+    json!({
+        "sha": "abc123",
+        "html_url": "",
+        "created": date,
+        "commit": { "message": message, "author": user, "committer": user },
+    })
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_user_course_reports_latest_commit() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let user_id = create_user(&ctx).await;
+    enroll_user(&ctx, &user_id, &slug).await;
+
+    let latest = vec![commit("Pass stage 2\n", "2025-01-02T03:04:05Z")];
+    let ctx = with_gitea(ctx, StatusCode::OK, json!(latest)).await;
+    let res = CourseService::get_user_course(ctx, &user_id, &slug).await.unwrap();
+    assert_eq!(res.last_pushed_at.unwrap().to_rfc3339(), "2025-01-02T03:04:05+00:00");
+    assert_eq!(res.last_commit_message.as_deref(), Some("Pass stage 2"));
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_user_course_is_served_without_git_server() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let user_id = create_user(&ctx).await;
+    enroll_user(&ctx, &user_id, &slug).await;
+
+    let error = json!({ "message": "internal error" });
+    let ctx = with_gitea(ctx, StatusCode::INTERNAL_SERVER_ERROR, error).await;
+    let res = CourseService::get_user_course(ctx, &user_id, &slug).await.unwrap();
+    assert_eq!(res.course_slug, slug);
+    assert!(res.last_pushed_at.is_none());
+    assert!(res.last_commit_message.is_none());
+}
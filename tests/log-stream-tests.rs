@@ -0,0 +1,247 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The tester output of a running attempt is streamed to its learner, and to
+//! the maintainers of the course. These tests need a disposable PostgreSQL
+//! database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test log-stream-tests -- --ignored
+
+mod common;
+
+use std::{
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{
+    body::Body,
+    http::{Request, Response, StatusCode, header},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    model::{AttemptOutput, StageAttemptModel},
+    repository::{CourseRepository, StageRepository},
+    routes,
+    service::CourseService,
+    utils::crypto,
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{create_course, enroll, setup, token, unreachable_cluster};
+
+/// A Kubernetes API server with a test pod that is pending at first, whose
+/// log is cut off once while it runs, as on a log rotation.
+#[derive(Clone, Default)]
+struct MockCluster {
+    lists: Arc<AtomicUsize>,
+    logs: Arc<AtomicUsize>,
+    resumed: Arc<AtomicBool>,
+}
+
+impl MockCluster {
+    fn client(&self) -> kube::Client {
+        let mock = self.clone();
+        let service = tower::service_fn(move |req: Request<kube::client::Body>| {
+            let mock = mock.clone();
+            async move {
+                let (path, query) = (req.uri().path(), req.uri().query().unwrap_or_default());
+                let body = if path.ends_with("/log") {
+                    assert!(query.contains("follow=true") && query.contains("container=step-test"));
+                    match mock.logs.fetch_add(1, Ordering::SeqCst) {
+                        0 => "2026-01-01T00:00:00.100Z \x1b[1;32mPASS\x1b[0m stage #1\n\
+                              2026-01-01T00:00:00.200Z Running stage #2\n"
+                            .to_string(),
+                        1 => {
+                            assert!(
+                                query.contains("sinceTime=2026-01-01T00%3A00%3A00Z"),
+                                "{query}"
+                            );
+                            mock.resumed.store(true, Ordering::SeqCst);
+                            "2026-01-01T00:00:00.100Z \x1b[1;32mPASS\x1b[0m stage #1\n\
+                             2026-01-01T00:00:00.200Z Running stage #2\n\
+                             2026-01-01T00:00:01.500Z \x1b[1;31mFAIL\x1b[0m stage #2\n"
+                                .to_string()
+                        }
+                        // Read to the end once the tester terminated
+                        _ => "2026-01-01T00:00:01.500Z FAIL stage #2\n".to_string(),
+                    }
+                } else if path.ends_with("/pods") {
+                    assert!(query.contains("tekton.dev%2FpipelineTask%3Dtest"), "{query}");
+                    let state = match mock.lists.fetch_add(1, Ordering::SeqCst) {
+                        0 => None,
+                        _ if mock.resumed.load(Ordering::SeqCst) => {
+                            Some(json!({ "terminated": { "exitCode": 1 } }))
+                        }
+                        _ => Some(json!({ "running": {} })),
+                    };
+                    pods(state).to_string()
+                } else {
+                    return Err(io::Error::new(io::ErrorKind::Unsupported, path.to_string()));
+                };
+                Ok(Response::new(kube::client::Body::from(body.into_bytes())))
+            }
+        });
+        kube::Client::new(service, "stackclass")
+    }
+}
+
+/// A list with the test pod, its tester in the given state.
+fn pods(state: Option<Value>) -> Value {
+    let (phase, statuses) = match state {
+        None => ("Pending", json!([])),
+        Some(state) => (
+            "Running",
+            json!([{
+                "name": "step-test",
+                "image": "tester",
+                "imageID": "",
+                "ready": true,
+                "restartCount": 0,
+                "state": state
+            }]),
+        ),
+    };
+    json!({
+        "apiVersion": "v1",
+        "kind": "PodList",
+        "metadata": {},
+        "items": [{
+            "metadata": { "name": "test-pod" },
+            "status": { "phase": phase, "containerStatuses": statuses }
+        }]
+    })
+}
+
+/// Creates an attempt of the first stage of a new course for a new user,
+/// returning the course slug, the user id and the attempt.
+async fn attempt(ctx: &Arc<Context>, status: &str) -> (String, String, StageAttemptModel) {
+    let db = &ctx.database;
+    let slug = create_course(ctx).await;
+    let user_id = enroll(ctx, &slug).await;
+    let mut user_course = CourseRepository::get_user_course(db, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+
+    let stage_slug = format!("{slug}-s1");
+    let user_stage =
+        StageRepository::get_user_stage(db, &user_id, &slug, &stage_slug).await.unwrap();
+    let attempt = StageAttemptModel::new(user_stage.id, "hash")
+        .with_pipeline_run(&Uuid::now_v7().to_string())
+        .with_status(status);
+    let attempt = StageRepository::create_attempt(db, &attempt).await.unwrap();
+    (slug, user_id, attempt)
+}
+
+/// Streams the logs of the first stage, returning the status and the events.
+async fn stream(ctx: &Arc<Context>, slug: &str, user_id: &str) -> (StatusCode, Vec<Value>) {
+    let uri = format!("/v1/user/courses/{slug}/stages/{slug}-s1/logs/stream");
+    send(ctx, &uri, format!("Bearer {}", token(ctx, user_id).await)).await
+}
+
+/// Sends a request for a log stream, returning the status and the events.
+async fn send(ctx: &Arc<Context>, uri: &str, authorization: String) -> (StatusCode, Vec<Value>) {
+    let req =
+        Request::get(uri).header(header::AUTHORIZATION, authorization).body(Body::empty()).unwrap();
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    let status = res.status();
+
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let events = String::from_utf8(body.to_vec())
+        .unwrap()
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    (status, events)
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_running_attempt_is_followed() {
+    let cluster = MockCluster::default();
+    let ctx = setup(cluster.client()).await;
+    let (slug, user_id, attempt) = attempt(&ctx, "pending").await;
+
+    // The outcome is reported once the tester terminated
+    let outcome = {
+        let (ctx, cluster) = (ctx.clone(), cluster.clone());
+        tokio::spawn(async move {
+            while !cluster.resumed.load(Ordering::SeqCst) {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            let run = attempt.pipeline_run.unwrap();
+            let output = AttemptOutput::default();
+            StageRepository::complete_attempt(&ctx.database, &run, "failed", "", "", "", &output)
+                .await
+                .unwrap();
+        })
+    };
+
+    let (status, events) = stream(&ctx, &slug, &user_id).await;
+    outcome.await.unwrap();
+    assert_eq!(status, StatusCode::OK);
+
+    // Waited for the pod to be scheduled, skipped the lines sent twice
+    let lines: Vec<_> = events.iter().filter(|e| e["type"] == "line").map(|e| &e["line"]).collect();
+    assert_eq!(lines, ["PASS stage #1", "Running stage #2", "FAIL stage #2"]);
+    assert!(cluster.lists.load(Ordering::SeqCst) >= 3);
+    assert_eq!(cluster.logs.load(Ordering::SeqCst), 3);
+
+    let end = events.last().unwrap();
+    assert_eq!(end["type"], "end");
+    assert_eq!(end["status"], "failed");
+    assert_eq!(end["dropped_lines"], 0);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_finished_attempt_ends_right_away() {
+    let ctx = setup(unreachable_cluster()).await;
+    let (slug, user_id, _) = attempt(&ctx, "passed").await;
+
+    let (status, events) = stream(&ctx, &slug, &user_id).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(events, [json!({ "type": "end", "status": "passed", "dropped_lines": 0 })]);
+
+    // Others can not follow the attempts of the learner
+    let other = enroll(&ctx, &create_course(&ctx).await).await;
+    let (status, _) = stream(&ctx, &slug, &other).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_maintainers_follow_learners() {
+    let ctx = setup(unreachable_cluster()).await;
+    let (slug, user_id, _) = attempt(&ctx, "passed").await;
+    let uri = format!("/v1/admin/users/{user_id}/courses/{slug}/stages/{slug}-s1/logs/stream");
+
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let admin = format!("Basic {}", STANDARD.encode(format!("admin:{password}")));
+    let (status, events) = send(&ctx, &uri, admin).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(events, [json!({ "type": "end", "status": "passed", "dropped_lines": 0 })]);
+
+    // Learners can not follow each other through it
+    let other = enroll(&ctx, &slug).await;
+    let (status, _) = send(&ctx, &uri, format!("Bearer {}", token(&ctx, &other).await)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
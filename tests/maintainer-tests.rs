@@ -0,0 +1,131 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Course maintainers may operate their own course only. These tests need a
+//! disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test maintainer-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::Value;
+use stackclass::{context::Context, routes, service::CourseService, utils::crypto};
+use tower::ServiceExt;
+
+use common::{create_course, create_user, setup, token, unreachable_cluster};
+
+/// Sends a request with the given `Authorization` header value.
+async fn send(
+    ctx: &Arc<Context>,
+    method: Method,
+    uri: &str,
+    auth: Option<&str>,
+) -> (StatusCode, Value) {
+    let mut req = Request::builder().method(method).uri(uri);
+    if let Some(auth) = auth {
+        req = req.header(header::AUTHORIZATION, auth);
+    }
+
+    let app = routes::build(ctx.clone());
+    let res = app.oneshot(req.body(Body::empty()).unwrap()).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn admin(ctx: &Context) -> String {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    format!("Basic {}", STANDARD.encode(format!("admin:{password}")))
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_maintainer_is_scoped_to_course() {
+    let ctx = setup(unreachable_cluster()).await;
+    let (course_a, course_b) = (create_course(&ctx).await, create_course(&ctx).await);
+    let user_id = create_user(&ctx).await;
+    CourseService::add_maintainer(ctx.clone(), &course_a, &user_id, "admin").await.unwrap();
+    let bearer = format!("Bearer {}", token(&ctx, &user_id).await);
+
+    // Another course's operational endpoints are off limits
+    let (status, _) =
+        send(&ctx, Method::PATCH, &format!("/v1/courses/{course_b}"), Some(&bearer)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // The own course passes authorization, the sync itself then fails as
+    // the test course has no repository
+    let (status, _) =
+        send(&ctx, Method::PATCH, &format!("/v1/courses/{course_a}"), Some(&bearer)).await;
+    assert_ne!(status, StatusCode::UNAUTHORIZED);
+    assert_ne!(status, StatusCode::FORBIDDEN);
+
+    // Unauthenticated callers are rejected, the admin is still accepted
+    let (status, _) = send(&ctx, Method::PATCH, &format!("/v1/courses/{course_b}"), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) =
+        send(&ctx, Method::PATCH, &format!("/v1/courses/{course_b}"), Some(&admin(&ctx))).await;
+    assert_ne!(status, StatusCode::UNAUTHORIZED);
+    assert_ne!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_maintainer_flag_and_revocation() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let (maintainer, learner) = (create_user(&ctx).await, create_user(&ctx).await);
+    let auth = admin(&ctx);
+
+    let uri = format!("/v1/admin/courses/{slug}/maintainers");
+    let req = Request::post(&uri)
+        .header(header::AUTHORIZATION, &auth)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(format!(r#"{{"user_id":"{maintainer}"}}"#)))
+        .unwrap();
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let (status, body) = send(&ctx, Method::GET, &uri, Some(&auth)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body[0]["user_id"], maintainer.as_str());
+    assert_eq!(body[0]["added_by"], "admin");
+
+    // Only the maintainer sees the flag
+    let detail = format!("/v1/courses/{slug}");
+    let bearer = format!("Bearer {}", token(&ctx, &maintainer).await);
+    let (_, body) = send(&ctx, Method::GET, &detail, Some(&bearer)).await;
+    assert_eq!(body["maintainer"], true);
+
+    let other = format!("Bearer {}", token(&ctx, &learner).await);
+    let (_, body) = send(&ctx, Method::GET, &detail, Some(&other)).await;
+    assert!(body.get("maintainer").is_none());
+    let (_, body) = send(&ctx, Method::GET, &detail, None).await;
+    assert!(body.get("maintainer").is_none());
+
+    // Revoking the role closes the operational endpoints again
+    let (status, _) = send(&ctx, Method::DELETE, &format!("{uri}/{maintainer}"), Some(&auth)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&ctx, Method::DELETE, &format!("{uri}/{maintainer}"), Some(&auth)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send(&ctx, Method::PATCH, &detail, Some(&bearer)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
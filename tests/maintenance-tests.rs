@@ -0,0 +1,179 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The read-only maintenance mode refuses writes and lets reads through.
+//! These tests need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test maintenance-tests -- --ignored
+
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::Utc;
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    routes,
+    utils::{clock::Clock, crypto, settings::SettingsCache},
+};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{create_course, setup, stop_clock, unreachable_cluster};
+
+/// The maintenance mode is global, the tests must not overlap.
+static LOCK: Mutex<()> = Mutex::const_new(());
+
+struct Reply {
+    status: StatusCode,
+    retry_after: Option<String>,
+    body: Value,
+}
+
+async fn send(ctx: &Arc<Context>, method: Method, uri: &str, body: Option<Value>) -> Reply {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let auth = format!("Basic {}", STANDARD.encode(format!("admin:{password}")));
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, auth)
+        .header(header::CONTENT_TYPE, "application/json");
+    let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+
+    let res = routes::build(ctx.clone()).oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = res.status();
+    let retry_after = res.headers().get(header::RETRY_AFTER).map(|v| v.to_str().unwrap().into());
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    Reply { status, retry_after, body: serde_json::from_slice(&body).unwrap_or(Value::Null) }
+}
+
+async fn set(ctx: &Arc<Context>, body: Value) -> Reply {
+    send(ctx, Method::PUT, "/v1/admin/maintenance", Some(body)).await
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_writes_are_refused_during_maintenance() {
+    let _lock = LOCK.lock().await;
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+
+    // Another replica, caching the settings for a short while
+    let mut other = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    other.settings = SettingsCache::new(Duration::from_millis(200));
+    let other = Arc::new(other);
+    let course = format!("/v1/courses/{slug}");
+    assert_ne!(
+        send(&other, Method::PATCH, &course, None).await.status,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+
+    // A leftover maintenance of an aborted run ends on its own
+    let expires_at = Utc::now() + chrono::Duration::seconds(60);
+    let body =
+        json!({ "enabled": true, "message": "Upgrading the database", "expires_at": expires_at });
+    let res = set(&ctx, body).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["enabled"], true);
+
+    // Mutating API requests are refused with the operator's message
+    let res = send(&ctx, Method::PATCH, &course, None).await;
+    assert_eq!(res.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(res.body["message"], "Upgrading the database");
+    let retry_after: u64 = res.retry_after.unwrap().parse().unwrap();
+    assert!((1..=60).contains(&retry_after));
+
+    // Reads go on, the readiness and admin summary report the mode
+    assert_eq!(send(&ctx, Method::GET, &course, None).await.status, StatusCode::OK);
+    let res = send(&ctx, Method::GET, "/readyz", None).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["status"], "read_only");
+    assert_eq!(res.body["maintenance"]["message"], "Upgrading the database");
+    let res = send(&ctx, Method::GET, "/v1/admin/summary", None).await;
+    assert_eq!(res.body["maintenance"]["enabled"], true);
+
+    // Clones and fetches pass through the git proxy, pushes do not
+    let repo = Uuid::now_v7();
+    let res = send(&ctx, Method::POST, &format!("/{repo}/git-upload-pack"), None).await;
+    assert_ne!(res.status, StatusCode::SERVICE_UNAVAILABLE);
+    let res = send(&ctx, Method::POST, &format!("/{repo}/git-receive-pack"), None).await;
+    assert_eq!(res.status, StatusCode::SERVICE_UNAVAILABLE);
+    let uri = format!("/{repo}/info/refs?service=git-receive-pack");
+    assert_eq!(send(&ctx, Method::GET, &uri, None).await.status, StatusCode::SERVICE_UNAVAILABLE);
+
+    // Webhooks are queued unless the maintenance rejects them
+    let webhook = "/v1/webhooks/tekton/validate";
+    let res = send(&ctx, Method::POST, webhook, Some(json!({}))).await;
+    assert_ne!(res.status, StatusCode::SERVICE_UNAVAILABLE);
+    let body = json!({ "enabled": true, "expires_at": expires_at, "reject_webhooks": true });
+    assert_eq!(set(&ctx, body).await.status, StatusCode::OK);
+    let res = send(&ctx, Method::POST, webhook, Some(json!({}))).await;
+    assert_eq!(res.status, StatusCode::SERVICE_UNAVAILABLE);
+
+    // The other replica picks the mode up once its cache expires
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let res = send(&other, Method::PATCH, &course, None).await;
+    assert_eq!(res.status, StatusCode::SERVICE_UNAVAILABLE);
+
+    // The maintenance itself can still be ended
+    assert_eq!(set(&ctx, json!({ "enabled": false })).await.status, StatusCode::OK);
+    assert_ne!(
+        send(&ctx, Method::PATCH, &course, None).await.status,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    let res = send(&ctx, Method::GET, "/readyz", None).await;
+    assert_eq!(res.body["status"], "ready");
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_maintenance_expires() {
+    let _lock = LOCK.lock().await;
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    let clock = stop_clock(&mut ctx);
+    let ctx = Arc::new(ctx);
+    let slug = create_course(&ctx).await;
+    let course = format!("/v1/courses/{slug}");
+
+    let expires_at = clock.now() - chrono::Duration::seconds(1);
+    let res = set(&ctx, json!({ "enabled": true, "expires_at": expires_at })).await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+
+    let expires_at = clock.now() + chrono::Duration::seconds(60);
+    let res = set(&ctx, json!({ "enabled": true, "expires_at": expires_at })).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let res = send(&ctx, Method::PATCH, &course, None).await;
+    assert_eq!(res.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(res.retry_after.as_deref(), Some("60"));
+    assert!(res.body["message"].as_str().unwrap().contains("maintenance"));
+
+    // Writes are allowed again once it ends, without anyone turning it off
+    clock.advance(chrono::Duration::seconds(60));
+    assert_ne!(
+        send(&ctx, Method::PATCH, &course, None).await.status,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    let res = send(&ctx, Method::GET, "/readyz", None).await;
+    assert_eq!(res.body["status"], "ready");
+
+    // It only ended for this clock, other replicas still refuse writes
+    assert_eq!(set(&ctx, json!({ "enabled": false })).await.status, StatusCode::OK);
+}
@@ -0,0 +1,102 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Link preview documents of shared pages. These tests need a disposable
+//! PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test meta-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, Response, StatusCode, header},
+};
+use stackclass::{context::Context, routes, service::MetaService};
+use tower::ServiceExt;
+
+use common::{create_course, setup, unreachable_cluster};
+
+async fn get(ctx: &Arc<Context>, uri: &str, etag: Option<&str>) -> Response<Body> {
+    let mut req = Request::get(uri);
+    if let Some(etag) = etag {
+        req = req.header(header::IF_NONE_MATCH, etag);
+    }
+    let app = routes::build(ctx.clone());
+    app.oneshot(req.body(Body::empty()).unwrap()).await.unwrap()
+}
+
+async fn text(res: Response<Body>) -> String {
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_course_text_is_escaped() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    sqlx::query("UPDATE courses SET name = $2, summary = $3 WHERE slug = $1")
+        .bind(&slug)
+        .bind("Redis</title><script>alert(1)</script>")
+        .bind("x".repeat(300))
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+
+    let res = get(&ctx, &format!("/v1/meta/courses/{slug}"), None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[header::CONTENT_TYPE], "text/html; charset=utf-8");
+    let etag = res.headers()[header::ETAG].to_str().unwrap().to_string();
+
+    let html = text(res).await;
+    assert!(!html.contains("<script>"), "{html}");
+    assert!(
+        html.contains("<title>Redis&lt;/title&gt;&lt;script&gt;alert(1)&lt;/script&gt;</title>")
+    );
+    assert!(html.contains(&format!("{}…", "x".repeat(199))));
+    assert!(html.contains(&format!("url=https://stackclass.dev/courses/{slug}")));
+
+    // Unchanged courses are served from cache
+    let res = get(&ctx, &format!("/v1/meta/courses/{slug}"), Some(&etag)).await;
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+    let res = get(&ctx, &format!("/v1/meta/courses/{slug}/stages/{slug}-s1"), None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(!text(res).await.contains("<script>"));
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_unreleased_course_needs_preview_token() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    sqlx::query("UPDATE courses SET release_status = 'alpha' WHERE slug = $1")
+        .bind(&slug)
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+
+    let uri = format!("/v1/meta/courses/{slug}");
+    assert_eq!(get(&ctx, &uri, None).await.status(), StatusCode::NOT_FOUND);
+    let res = get(&ctx, &format!("{uri}?preview=invalid"), None).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let token = MetaService::preview_token(&ctx, &slug).unwrap();
+    let res = get(&ctx, &format!("{uri}?preview={token}"), None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[header::CACHE_CONTROL], "private, no-cache");
+}
@@ -0,0 +1,210 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Notifications honor the preferences of learners. These tests need a
+//! disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test notification-tests -- --ignored
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    Json, Router,
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+    routing::post,
+};
+use chrono::Utc;
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    model::NotificationPreferencesModel,
+    repository::NotificationRepository,
+    routes,
+    service::{Notification, NotificationEvent, NotificationService},
+};
+use tower::ServiceExt;
+
+use common::{create_course, create_user, enroll, serve, setup, token, unreachable_cluster};
+
+type Received = Arc<Mutex<Vec<Value>>>;
+
+/// Starts a server recording the notifications posted to it, and returns its
+/// URL.
+async fn receiver() -> (String, Received) {
+    let received = Received::default();
+    let recorded = received.clone();
+    let app = Router::new().route(
+        "/notify",
+        post(move |Json(body): Json<Value>| async move {
+            recorded.lock().unwrap().push(body);
+        }),
+    );
+
+    (format!("{}/notify", serve(app).await), received)
+}
+
+async fn send(
+    ctx: &Arc<Context>,
+    method: Method,
+    auth: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(method)
+        .uri("/v1/user/notification-preferences")
+        .header(header::AUTHORIZATION, auth)
+        .header(header::CONTENT_TYPE, "application/json");
+    let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+
+    let app = routes::build(ctx.clone());
+    let res = app.oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn preferences(channel: &str, destination: Option<&str>) -> Value {
+    json!({
+        "channel": channel,
+        "destination": destination,
+        "announcements": true,
+        "feedback": false,
+        "deadline_reminders": true,
+        "accountability": false
+    })
+}
+
+fn notification(user_id: &str, event: NotificationEvent, course: Option<&str>) -> Notification {
+    Notification {
+        user_id: user_id.to_string(),
+        event,
+        course: course.map(str::to_string),
+        subject: "Subject".to_string(),
+        body: "Body".to_string(),
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_preferences_are_validated() {
+    let ctx = setup(unreachable_cluster()).await;
+    let user_id = create_user(&ctx).await;
+    let auth = format!("Bearer {}", token(&ctx, &user_id).await);
+
+    // Users without preferences get the legacy defaults
+    let (status, body) = send(&ctx, Method::GET, &auth, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["channel"], "email");
+    assert_eq!(body["feedback"], true);
+    assert_eq!(body["updated_at"], Value::Null);
+
+    let invalid = [
+        preferences("webhook", None),
+        preferences("webhook", Some("http://hooks.example.com/notify")),
+        preferences("webhook", Some("not a url")),
+        preferences("email", Some("ada@localhost")),
+    ];
+    for prefs in invalid {
+        let (status, _) = send(&ctx, Method::PUT, &auth, Some(prefs.clone())).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{prefs}");
+    }
+    let (status, _) = send(&ctx, Method::PUT, &auth, Some(preferences("sms", None))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let prefs = preferences("webhook", Some("https://hooks.example.com/notify"));
+    let (status, body) = send(&ctx, Method::PUT, &auth, Some(prefs)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["destination"], "https://hooks.example.com/notify");
+
+    let (_, body) = send(&ctx, Method::GET, &auth, None).await;
+    assert_eq!(body["channel"], "webhook");
+    assert_eq!(body["feedback"], false);
+    assert_ne!(body["updated_at"], Value::Null);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_disabled_event_is_never_dispatched() {
+    let (relay, emails) = receiver().await;
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    ctx.config.notification_relay_url = Some(relay);
+    let ctx = Arc::new(ctx);
+
+    let user_id = create_user(&ctx).await;
+    let auth = format!("Bearer {}", token(&ctx, &user_id).await);
+    let prefs = preferences("email", Some("ada@example.com"));
+    assert_eq!(send(&ctx, Method::PUT, &auth, Some(prefs)).await.0, StatusCode::OK);
+
+    let feedback = notification(&user_id, NotificationEvent::Feedback, None);
+    assert!(!NotificationService::dispatch(&ctx, &feedback).await.unwrap());
+    let accountability = notification(&user_id, NotificationEvent::Accountability, None);
+    assert!(!NotificationService::dispatch(&ctx, &accountability).await.unwrap());
+    assert!(emails.lock().unwrap().is_empty());
+
+    let announcement = notification(&user_id, NotificationEvent::Announcement, None);
+    assert!(NotificationService::dispatch(&ctx, &announcement).await.unwrap());
+    let emails = emails.lock().unwrap().clone();
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0]["to"], "ada@example.com");
+    assert_eq!(emails[0]["event"], "announcement");
+
+    // Webhooks are subject to the same preferences
+    let (url, hooks) = receiver().await;
+    let prefs = NotificationPreferencesModel {
+        channel: "webhook".to_string(),
+        destination: Some(url),
+        feedback: false,
+        updated_at: Utc::now(),
+        ..NotificationPreferencesModel::new(&user_id)
+    };
+    NotificationRepository::upsert(&ctx.database, &prefs).await.unwrap();
+
+    assert!(!NotificationService::dispatch(&ctx, &feedback).await.unwrap());
+    assert!(NotificationService::dispatch(&ctx, &announcement).await.unwrap());
+    let hooks = hooks.lock().unwrap().clone();
+    assert_eq!(hooks.len(), 1);
+    assert_eq!(hooks[0]["subject"], "Subject");
+    assert!(hooks[0].get("to").is_none());
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_accountability_defaults_to_course_setting() {
+    let (relay, emails) = receiver().await;
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    ctx.config.notification_relay_url = Some(relay);
+    let ctx = Arc::new(ctx);
+
+    // Enrolled without accountability emails, and no preferences saved
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let accountability = notification(&user_id, NotificationEvent::Accountability, Some(&slug));
+    assert!(!NotificationService::dispatch(&ctx, &accountability).await.unwrap());
+
+    sqlx::query("UPDATE user_courses SET accountability = true WHERE user_id = $1")
+        .bind(&user_id)
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+    assert!(NotificationService::dispatch(&ctx, &accountability).await.unwrap());
+
+    // Sent to the email of the account
+    let emails = emails.lock().unwrap().clone();
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0]["to"], user_id.as_str());
+    assert_eq!(emails[0]["course"], slug.as_str());
+}
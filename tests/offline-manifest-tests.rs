@@ -0,0 +1,234 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Offline clients prefetch courses through the manifest and the asset
+//! index. These tests need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test offline-manifest-tests -- --ignored
+
+mod common;
+
+use std::{fs, path::Path, sync::Arc};
+
+use axum::{
+    body::Body,
+    http::{Request, Response, StatusCode, header},
+};
+use serde_json::Value;
+use stackclass::{context::Context, routes, schema, service::CourseService};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{setup, unreachable_cluster};
+
+const COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+
+async fn get(ctx: &Arc<Context>, uri: &str, range: Option<&str>) -> Response<Body> {
+    let mut req = Request::get(uri);
+    if let Some(range) = range {
+        req = req.header(header::RANGE, range);
+    }
+    let app = routes::build(ctx.clone());
+    app.oneshot(req.body(Body::empty()).unwrap()).await.unwrap()
+}
+
+async fn bytes(res: Response<Body>) -> Vec<u8> {
+    axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap().to_vec()
+}
+
+async fn manifest(ctx: &Arc<Context>, slug: &str) -> Value {
+    let res = get(ctx, &format!("/v1/courses/{slug}/offline-manifest"), None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    serde_json::from_slice(&bytes(res).await).unwrap()
+}
+
+fn write(path: &Path, content: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+/// Lays out a cached checkout of the course repository, as it would have
+/// been unpacked from the GitHub tarball.
+fn cache_course(root: &Path, slug: &str) {
+    write(
+        &root.join("course.yml"),
+        &format!(
+            "slug: {slug}\nname: Redis\nshort_name: Redis\nrelease_status: beta\n\
+             description: A Redis clone\nsummary: Redis\n"
+        ),
+    );
+    write(
+        &root.join("extensions.yml"),
+        &format!("- slug: {slug}-pubsub\n  name: Pub/Sub\n  description: Channels\n"),
+    );
+    for (dir, stage) in [
+        ("stages/01-ping", "ping"),
+        (&format!("extensions/{slug}-pubsub/01-subscribe"), "subscribe"),
+    ] {
+        let yml = format!(
+            "slug: {slug}-{stage}\nname: {stage}\ndifficulty: easy\ndescription: {stage}\n"
+        );
+        write(&root.join(dir).join("stage.yml"), &yml);
+        write(&root.join(dir).join("instruction.md"), "Read the diagram");
+    }
+    write(&root.join("stages/01-ping/solution.md"), "Write +PONG");
+    write(&root.join("stages/01-ping/hints/01.md"), "Use a socket");
+    write(&root.join("stages/01-ping/hints/02.md"), "Reply with +PONG");
+    write(&root.join("stages/01-ping/diagram.txt"), "0123456789");
+    write(&root.join("assets/logo.svg"), "<svg/>");
+}
+
+/// Inserts a course row pointing at the cached checkout, and syncs it.
+async fn sync_course(ctx: &Arc<Context>, slug: &str, root: &Path) {
+    sqlx::query(
+        r#"
+        INSERT INTO courses (id, slug, name, short_name, release_status, description, summary, repository)
+        VALUES ($1, $2, $2, $2, 'beta', '', '', 'https://github.com/stackclass/redis')
+        ON CONFLICT (slug) DO NOTHING
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(slug)
+    .execute(ctx.database.pool())
+    .await
+    .unwrap();
+
+    let course = schema::parse(root).unwrap();
+    CourseService::update_course(ctx.clone(), &course, COMMIT, "admin").await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_manifest_and_assets() {
+    let cache = tempfile::tempdir().unwrap();
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    ctx.config.cache_dir = cache.path().to_path_buf();
+    let ctx = Arc::new(ctx);
+
+    let slug = format!("course-{}", Uuid::now_v7().simple());
+    let root = cache.path().join(format!("stackclass-redis-{}", &COMMIT[..7]));
+    cache_course(&root, &slug);
+    sync_course(&ctx, &slug, &root).await;
+
+    let body = manifest(&ctx, &slug).await;
+    assert_eq!(body["commit"], COMMIT);
+    assert_eq!(body["content_hash"].as_str().unwrap().len(), 64);
+
+    let stages = body["stages"].as_array().unwrap();
+    assert_eq!(stages.len(), 2);
+    assert_eq!(stages[0]["slug"], format!("{slug}-ping"));
+    assert_eq!(stages[0]["hint_count"], 2);
+    assert_eq!(stages[0]["size"], 16 + 12 + 16 + 10);
+    assert_eq!(stages[1]["extension"], format!("{slug}-pubsub"));
+    assert_eq!(stages[1]["hint_count"], 0);
+
+    let paths: Vec<_> = body["assets"].as_array().unwrap().iter().map(|a| &a["path"]).collect();
+    assert_eq!(
+        paths,
+        [
+            "assets/logo.svg",
+            "stages/01-ping/diagram.txt",
+            "stages/01-ping/hints/01.md",
+            "stages/01-ping/hints/02.md"
+        ]
+    );
+
+    // Whole assets and single ranges are served
+    let uri = format!("/v1/courses/{slug}/assets/stages/01-ping/diagram.txt");
+    let res = get(&ctx, &uri, None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[header::ACCEPT_RANGES], "bytes");
+    assert_eq!(res.headers()[header::CONTENT_TYPE], "text/plain; charset=utf-8");
+    assert_eq!(bytes(res).await, b"0123456789");
+
+    let res = get(&ctx, &uri, Some("bytes=2-4")).await;
+    assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(res.headers()[header::CONTENT_RANGE], "bytes 2-4/10");
+    assert_eq!(bytes(res).await, b"234");
+
+    let res = get(&ctx, &uri, Some("bytes=10-")).await;
+    assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(res.headers()[header::CONTENT_RANGE], "bytes */10");
+
+    // Definitions and solutions are not part of the index
+    for path in ["stages/01-ping/stage.yml", "stages/01-ping/solution.md", "../secret"] {
+        let res = get(&ctx, &format!("/v1/courses/{slug}/assets/{path}"), None).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND, "{path}");
+    }
+
+    // The manifest is served from the index alone
+    fs::remove_dir_all(&root).unwrap();
+    assert_eq!(manifest(&ctx, &slug).await, body);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_content_hash_follows_assets() {
+    let cache = tempfile::tempdir().unwrap();
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    ctx.config.cache_dir = cache.path().to_path_buf();
+    let ctx = Arc::new(ctx);
+
+    let slug = format!("course-{}", Uuid::now_v7().simple());
+    let root = cache.path().join(format!("stackclass-redis-{}", &COMMIT[..7]));
+    cache_course(&root, &slug);
+    sync_course(&ctx, &slug, &root).await;
+    let before = manifest(&ctx, &slug).await;
+
+    // Resyncing unchanged content keeps the hash
+    sync_course(&ctx, &slug, &root).await;
+    assert_eq!(manifest(&ctx, &slug).await["content_hash"], before["content_hash"]);
+
+    write(&root.join("assets/logo.svg"), "<svg></svg>");
+    sync_course(&ctx, &slug, &root).await;
+    let after = manifest(&ctx, &slug).await;
+    assert_ne!(after["content_hash"], before["content_hash"]);
+    assert_eq!(after["stages"], before["stages"]);
+    assert_eq!(after["assets"][0]["size"], 11);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_starter_diff_is_stored() {
+    let cache = tempfile::tempdir().unwrap();
+    let ctx = setup(unreachable_cluster()).await;
+
+    let slug = format!("course-{}", Uuid::now_v7().simple());
+    let root = cache.path().join("redis");
+    cache_course(&root, &slug);
+    write(&root.join(format!("starter/{slug}-ping/src/main.rs")), "fn main() {}\n");
+    sync_course(&ctx, &slug, &root).await;
+
+    // Diffs are served from the database, not the checkout
+    fs::remove_dir_all(&root).unwrap();
+
+    let res = get(&ctx, &format!("/v1/courses/{slug}/stages/{slug}-ping/starter-diff"), None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: Value = serde_json::from_slice(&bytes(res).await).unwrap();
+    assert_eq!(body["stage_slug"], format!("{slug}-ping"));
+    assert_eq!(body["files"][0]["path"], "src/main.rs");
+    assert_eq!(body["files"][0]["kind"], "added");
+    assert_eq!(body["files"][0]["truncated"], false);
+    assert!(body["files"][0]["diff"].as_str().unwrap().ends_with("+fn main() {}\n"));
+
+    // Stages without starter code have an empty diff
+    let uri = format!("/v1/courses/{slug}/stages/{slug}-subscribe/starter-diff");
+    let res = get(&ctx, &uri, None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: Value = serde_json::from_slice(&bytes(res).await).unwrap();
+    assert_eq!(body["files"], Value::Array(vec![]));
+
+    let res = get(&ctx, &format!("/v1/courses/{slug}/stages/missing/starter-diff"), None).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
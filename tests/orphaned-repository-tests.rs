@@ -0,0 +1,97 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Repositories of the organization belonging to nothing are reported as
+//! orphans. These tests need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test orphaned-repository-tests -- --ignored
+
+#![recursion_limit = "256"]
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::Query,
+    http::{Request, StatusCode, header},
+    routing::get,
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use http_body_util::BodyExt;
+use serde::Deserialize;
+use serde_json::Value;
+use stackclass::{context::Context, routes, utils::crypto};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{create_course, create_user, enroll_user, mock_context, repository};
+
+#[derive(Deserialize)]
+struct PageQuery {
+    page: usize,
+    limit: usize,
+}
+
+/// A Gitea server whose organization holds the given repositories, listed
+/// in pages of the requested size.
+async fn context(names: Arc<Mutex<Vec<String>>>) -> Arc<Context> {
+    let app = Router::new().route(
+        "/api/v1/orgs/{org}/repos",
+        get(move |Query(query): Query<PageQuery>| async move {
+            let names = names.lock().unwrap().clone();
+            let page: Vec<Value> = names
+                .iter()
+                .skip((query.page - 1) * query.limit)
+                .take(query.limit)
+                .map(|name| repository("stackclass", name))
+                .collect();
+            Json(page)
+        }),
+    );
+
+    Arc::new(mock_context(app).await)
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_find_orphaned_repositories() {
+    let names = Arc::new(Mutex::new(Vec::new()));
+    let ctx = context(names.clone()).await;
+    let slug = create_course(&ctx).await;
+    let user_id = create_user(&ctx).await;
+    let enrolled = enroll_user(&ctx, &user_id, &slug).await;
+    let stale = Uuid::now_v7();
+
+    // Enough pooled repositories to span several pages
+    let mut repos: Vec<String> = (0..60).map(|i| format!("pool-{i}")).collect();
+    repos.extend([slug, enrolled.to_string(), stale.to_string(), "scratch".to_string()]);
+    *names.lock().unwrap() = repos;
+
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let auth = format!("Basic {}", STANDARD.encode(format!("admin:{password}")));
+    let req = Request::get("/v1/admin/repositories/orphans")
+        .header(header::AUTHORIZATION, auth)
+        .body(Body::empty())
+        .unwrap();
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    let orphans: Vec<Value> = serde_json::from_slice(&body).unwrap();
+    let orphans: Vec<&str> = orphans.iter().map(|repo| repo["name"].as_str().unwrap()).collect();
+    assert_eq!(orphans, [stale.to_string().as_str(), "scratch"]);
+}
@@ -0,0 +1,309 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Listings are walked page by page through signed cursors, or by page number
+//! for the courses and attempts. These tests need a disposable PostgreSQL
+//! database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test pagination-tests -- --ignored
+
+mod common;
+
+use std::{collections::HashSet, sync::Arc};
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
+use chrono::{Duration, Utc};
+use serde_json::Value;
+use stackclass::{
+    context::Context,
+    model::AttemptModel,
+    repository::CourseRepository,
+    routes,
+    utils::{crypto, pagination::CURSOR_TTL},
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{create_course, create_user, enroll_user, setup, stop_clock, unreachable_cluster};
+
+async fn get(ctx: &Arc<Context>, uri: &str) -> (StatusCode, Value) {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let auth = format!("Basic {}", STANDARD.encode(format!("admin:{password}")));
+    let req = Request::get(uri).header(header::AUTHORIZATION, auth).body(Body::empty()).unwrap();
+
+    let app = routes::build(ctx.clone());
+    let res = app.oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Seeds 250 audit logs under a fresh action, half of them sharing a single
+/// timestamp so that only the insertion sequence tells them apart.
+async fn seed(ctx: &Context) -> (String, HashSet<String>) {
+    let action = format!("pagination-{}", Uuid::now_v7());
+    let ids: Vec<(String,)> = sqlx::query_as(
+        r#"
+        INSERT INTO audit_logs (id, actor, action, target, details, created_at)
+        SELECT gen_random_uuid(), 'admin', $1, 'seed/' || n, '{}'::JSONB,
+            CASE WHEN n % 2 = 0 THEN NOW() - INTERVAL '1 hour' ELSE NOW() - n * INTERVAL '1 second' END
+        FROM generate_series(1, 250) AS n
+        RETURNING id::TEXT
+        "#,
+    )
+    .bind(&action)
+    .fetch_all(ctx.database.pool())
+    .await
+    .unwrap();
+
+    (action, ids.into_iter().map(|(id,)| id).collect())
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_walk_audit_logs_by_cursor() {
+    let ctx = setup(unreachable_cluster()).await;
+    let (action, expected) = seed(&ctx).await;
+
+    // Oversized limits are clamped to the maximum page size
+    let (mut seen, mut pages) = (Vec::new(), 0);
+    let mut uri = format!("/v1/admin/audit-logs?action={action}&limit=1000");
+    loop {
+        let (status, body) = get(&ctx, &uri).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        pages += 1;
+
+        let items = body["items"].as_array().unwrap();
+        assert!(items.len() <= 200);
+        seen.extend(items.iter().map(|item| item["id"].as_str().unwrap().to_string()));
+
+        match body["next_cursor"].as_str() {
+            Some(cursor) => {
+                assert_eq!(body["has_more"], true);
+                uri = format!("/v1/admin/audit-logs?action={action}&limit=1000&cursor={cursor}");
+            }
+            None => {
+                assert_eq!(body["has_more"], false);
+                break;
+            }
+        }
+    }
+    assert_eq!(pages, 2);
+
+    // Every row shows up exactly once, across odd page sizes too
+    assert_eq!(seen.len(), 250);
+    assert_eq!(seen.iter().cloned().collect::<HashSet<_>>(), expected);
+
+    let mut seen = Vec::new();
+    let mut cursor = String::new();
+    loop {
+        let uri = format!("/v1/admin/audit-logs?action={action}&limit=7&cursor={cursor}");
+        let (_, body) = get(&ctx, &uri).await;
+        seen.extend(body["items"].as_array().unwrap().iter().map(|i| i["id"].clone()));
+        match body["next_cursor"].as_str() {
+            Some(next) => cursor = next.to_string(),
+            None => break,
+        }
+    }
+    assert_eq!(seen.len(), 250);
+    assert_eq!(seen.iter().map(|id| id.as_str().unwrap()).collect::<HashSet<_>>().len(), 250);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_invalid_cursors_are_rejected() {
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    let clock = stop_clock(&mut ctx);
+    let ctx = Arc::new(ctx);
+    let (action, _) = seed(&ctx).await;
+
+    let uri = format!("/v1/admin/audit-logs?action={action}&limit=10");
+    let (_, body) = get(&ctx, &uri).await;
+    let cursor = body["next_cursor"].as_str().unwrap();
+
+    // Moving the position within the token breaks the signature
+    let decoded = String::from_utf8(URL_SAFE_NO_PAD.decode(cursor).unwrap()).unwrap();
+    let (micros, rest) = decoded.split_once('.').unwrap();
+    let forged = format!("{}.{rest}", micros.parse::<i64>().unwrap() + 1);
+    let forged = URL_SAFE_NO_PAD.encode(forged);
+    let (status, body) = get(&ctx, &format!("{uri}&cursor={forged}")).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "cursor_tampered");
+
+    // A cursor is valid for a day, then the listing has to be restarted
+    clock.advance(CURSOR_TTL);
+    let (status, _) = get(&ctx, &format!("{uri}&cursor={cursor}")).await;
+    assert_eq!(status, StatusCode::OK);
+
+    clock.advance(Duration::seconds(1));
+    let (status, body) = get(&ctx, &format!("{uri}&cursor={cursor}")).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "cursor_expired");
+
+    let (status, body) = get(&ctx, &format!("{uri}&cursor=garbage")).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "cursor_malformed");
+}
+
+/// Creates a course of the given release status, dated back to the epoch so
+/// that it is listed before the courses of the other tests.
+async fn create_dated_course(ctx: &Context, status: &str, secs: i32) -> String {
+    let slug = create_course(ctx).await;
+    sqlx::query(
+        "UPDATE courses SET release_status = $2, created_at = to_timestamp($3) WHERE slug = $1",
+    )
+    .bind(&slug)
+    .bind(status)
+    .bind(secs)
+    .execute(ctx.database.pool())
+    .await
+    .unwrap();
+    slug
+}
+
+fn slugs(body: &Value) -> Vec<&str> {
+    body["items"].as_array().unwrap().iter().map(|c| c["slug"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_courses_by_page_number() {
+    let ctx = setup(unreachable_cluster()).await;
+
+    // Move the courses left by previous runs out of the way
+    sqlx::query("UPDATE courses SET created_at = NOW() WHERE created_at < '1971-01-01'")
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+    create_dated_course(&ctx, "alpha", 0).await;
+    let first = create_dated_course(&ctx, "live", 1).await;
+    let second = create_dated_course(&ctx, "live", 2).await;
+    let third = create_dated_course(&ctx, "beta", 3).await;
+
+    let (status, body) = get(&ctx, "/v1/courses?per_page=2").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(slugs(&body), [first.as_str(), &second]);
+    assert_eq!((body["page"].as_i64(), body["per_page"].as_i64()), (Some(1), Some(2)));
+    let total = body["total"].as_i64().unwrap();
+    assert!(total >= 3);
+
+    let (_, body) = get(&ctx, "/v1/courses?per_page=2&page=2").await;
+    assert_eq!(slugs(&body)[0], third);
+    assert_eq!(body["total"].as_i64(), Some(total));
+
+    let (_, body) = get(&ctx, "/v1/courses?release_status=live&per_page=1&page=2").await;
+    assert_eq!(slugs(&body), [second.as_str()]);
+
+    // Without a page size, all released courses are on the first page
+    let (_, body) = get(&ctx, "/v1/courses?page=3").await;
+    let items = slugs(&body);
+    assert_eq!(&items[..3], [first.as_str(), &second, &third]);
+    assert_eq!(body["page"].as_i64(), Some(1));
+    assert_eq!(body["per_page"].as_i64(), body["total"].as_i64());
+    assert_eq!(items.len() as i64, body["total"].as_i64().unwrap());
+
+    let (_, body) = get(&ctx, "/v1/courses?per_page=10000").await;
+    assert_eq!(body["per_page"].as_i64(), Some(200));
+
+    let (status, _) = get(&ctx, "/v1/courses?release_status=unknown").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Pages before the first or too far out to be reached are refused
+    for page in ["0", "-1", &i64::MAX.to_string()] {
+        let (status, body) = get(&ctx, &format!("/v1/courses?per_page=2&page={page}")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{page}: {body}");
+    }
+}
+
+/// Enrolls a new user in the course, started the given number of hours ago.
+async fn enroll_at(ctx: &Context, slug: &str, hours: i32) -> String {
+    let user_id = create_user(ctx).await;
+    let id = enroll_user(ctx, &user_id, slug).await;
+    sqlx::query(
+        "UPDATE user_courses SET started_at = NOW() - $2 * INTERVAL '1 hour' WHERE id = $1",
+    )
+    .bind(id)
+    .bind(hours)
+    .execute(ctx.database.pool())
+    .await
+    .unwrap();
+    user_id
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_find_attempts_filtered() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let oldest = enroll_at(&ctx, &slug, 3).await;
+    let older = enroll_at(&ctx, &slug, 2).await;
+    let newest = enroll_at(&ctx, &slug, 1).await;
+    let db = &ctx.database;
+
+    let users = |rows: Vec<AttemptModel>| rows.into_iter().map(|a| a.user_id).collect::<Vec<_>>();
+    let (rows, total) = CourseRepository::find_attempts(db, &slug, None, None, 0, 2).await.unwrap();
+    assert_eq!((users(rows), total), (vec![newest.clone(), older.clone()], 3));
+    let (rows, _) = CourseRepository::find_attempts(db, &slug, None, None, 2, 2).await.unwrap();
+    assert_eq!(users(rows), vec![oldest.clone()]);
+
+    let user = Some(older.as_str());
+    let (rows, total) =
+        CourseRepository::find_attempts(db, &slug, user, None, 0, 10).await.unwrap();
+    assert_eq!((users(rows), total), (vec![older.clone()], 1));
+
+    let since = Some(Utc::now() - Duration::minutes(150));
+    let (rows, total) =
+        CourseRepository::find_attempts(db, &slug, None, since, 0, 10).await.unwrap();
+    assert_eq!((users(rows), total), (vec![newest.clone(), older.clone()], 2));
+    let (rows, total) =
+        CourseRepository::find_attempts(db, &slug, Some(&oldest), since, 0, 10).await.unwrap();
+    assert_eq!((rows.len(), total), (0, 0));
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_attempts_by_page_number() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    enroll_at(&ctx, &slug, 2).await;
+    let newest = enroll_at(&ctx, &slug, 1).await;
+
+    let (status, body) = get(&ctx, &format!("/v1/courses/{slug}/attempts?per_page=1")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["items"][0]["user_id"].as_str(), Some(newest.as_str()));
+    assert_eq!(body["items"].as_array().unwrap().len(), 1);
+    assert_eq!((body["total"].as_i64(), body["page"].as_i64()), (Some(2), Some(1)));
+
+    let uri = format!("/v1/courses/{slug}/attempts?user_id={newest}&page=1");
+    let (_, body) = get(&ctx, &uri).await;
+    assert_eq!((body["total"].as_i64(), body["per_page"].as_i64()), (Some(1), Some(50)));
+
+    let (status, _) = get(&ctx, &format!("/v1/courses/{slug}/attempts?since=yesterday")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    for page in ["0", "-1", &i64::MAX.to_string()] {
+        let (status, body) = get(&ctx, &format!("/v1/courses/{slug}/attempts?page={page}")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{page}: {body}");
+    }
+
+    let (status, _) = get(&ctx, "/v1/courses/missing/attempts").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,196 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! PipelineRuns superseded by a newer push are cancelled, and the runs of a
+//! repository are listed for debugging. These tests need a disposable
+//! PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test pipeline-cancel-tests -- --ignored
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    body::Body,
+    http::{Method, Request, Response, StatusCode, header},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    model::StageAttemptModel,
+    repository::{CourseRepository, StageRepository},
+    routes,
+    service::{CourseService, PipelineService},
+    utils::crypto,
+};
+use tower::ServiceExt;
+
+use common::{create_course, enroll, setup, token};
+
+/// A Kubernetes API server holding PipelineRuns, along with the patches it
+/// was sent.
+#[derive(Clone, Default)]
+struct MockCluster {
+    runs: Arc<Mutex<Vec<Value>>>,
+    patches: Arc<Mutex<Vec<(String, Value)>>>,
+}
+
+impl MockCluster {
+    fn client(&self) -> kube::Client {
+        let mock = self.clone();
+        let service = tower::service_fn(move |req: Request<kube::client::Body>| {
+            let mock = mock.clone();
+            async move {
+                let body = match *req.method() {
+                    Method::PATCH => {
+                        let name = req.uri().path().rsplit('/').next().unwrap().to_string();
+                        let body = Body::new(req.into_body());
+                        let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+                        let patch: Value = serde_json::from_slice(&body).unwrap();
+                        mock.patches.lock().unwrap().push((name.clone(), patch));
+                        let runs = mock.runs.lock().unwrap();
+                        runs.iter().find(|run| run["metadata"]["name"] == name).unwrap().clone()
+                    }
+                    _ => json!({
+                        "apiVersion": "tekton.dev/v1",
+                        "kind": "PipelineRunList",
+                        "metadata": {},
+                        "items": *mock.runs.lock().unwrap()
+                    }),
+                };
+                let body = serde_json::to_vec(&body).unwrap();
+                Ok::<_, std::io::Error>(Response::new(kube::client::Body::from(body)))
+            }
+        });
+        kube::Client::new(service, "stackclass")
+    }
+
+    /// Adds a PipelineRun grading a stage of the repository, completed with
+    /// the given reason if any.
+    fn add(&self, name: &str, repo: &str, stage: &str, created: &str, reason: Option<&str>) {
+        let status = match reason {
+            Some(reason) => json!({
+                "completionTime": created,
+                "conditions": [{ "type": "Succeeded", "status": "False", "reason": reason }]
+            }),
+            None => json!({
+                "conditions": [{ "type": "Succeeded", "status": "Unknown", "reason": "Running" }]
+            }),
+        };
+        self.runs.lock().unwrap().push(json!({
+            "apiVersion": "tekton.dev/v1",
+            "kind": "PipelineRun",
+            "metadata": {
+                "name": name,
+                "creationTimestamp": created,
+                "labels": { "stackclass.dev/repo": repo, "stackclass.dev/stage": stage }
+            },
+            "spec": {},
+            "status": status
+        }));
+    }
+}
+
+/// Sends a GET request with the given `Authorization` header value.
+async fn get(ctx: &Arc<Context>, uri: &str, auth: &str) -> (StatusCode, Value) {
+    let req = Request::get(uri).header(header::AUTHORIZATION, auth).body(Body::empty()).unwrap();
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn admin(ctx: &Context) -> String {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    format!("Basic {}", STANDARD.encode(format!("admin:{password}")))
+}
+
+/// Enrolls a new user into a new course and starts its first stage,
+/// returning the course slug, the user id and the repository.
+async fn start(ctx: &Arc<Context>) -> (String, String, String) {
+    let slug = create_course(ctx).await;
+    let user_id = enroll(ctx, &slug).await;
+    let mut user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+    (slug, user_id, user_course.id.to_string())
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_superseded_runs_are_cancelled() {
+    let cluster = MockCluster::default();
+    let ctx = setup(cluster.client()).await;
+    let db = &ctx.database;
+    let (slug, user_id, repo) = start(&ctx).await;
+    let s1 = format!("{slug}-s1");
+
+    cluster.add("run-old", &repo, &s1, "2025-01-01T00:00:00Z", Some("Failed"));
+    cluster.add("run-new", &repo, &s1, "2025-01-01T00:01:00Z", None);
+    let user_stage = StageRepository::get_user_stage(db, &user_id, &slug, &s1).await.unwrap();
+    let pending = StageAttemptModel::new(user_stage.id, "").with_pipeline_run("run-new");
+    StageRepository::create_attempt(db, &pending).await.unwrap();
+
+    // Only the run still grading is cancelled
+    let cancelled = PipelineService::new(ctx.clone()).cancel_superseded(&repo).await.unwrap();
+    assert_eq!(cancelled, ["run-new"]);
+    let patches = cluster.patches.lock().unwrap().clone();
+    assert_eq!(patches, [("run-new".to_string(), json!({ "spec": { "status": "Cancelled" } }))]);
+
+    // Its attempt is cancelled, and does not count toward the budget
+    let attempt = StageRepository::find_latest_attempt(db, &user_stage.id).await.unwrap().unwrap();
+    assert_eq!(attempt.status, "cancelled");
+    assert_eq!(StageRepository::count_attempts(db, &user_stage.id, None).await.unwrap(), 0);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_find_pipelines() {
+    let cluster = MockCluster::default();
+    let ctx = setup(cluster.client()).await;
+    let (slug, user_id, repo) = start(&ctx).await;
+    let s1 = format!("{slug}-s1");
+
+    cluster.add("run-old", &repo, &s1, "2025-01-01T00:00:00Z", Some("Cancelled"));
+    cluster.add("run-new", &repo, &s1, "2025-01-01T00:01:00Z", None);
+
+    // Learners see the runs of their repository, newest first
+    let bearer = format!("Bearer {}", token(&ctx, &user_id).await);
+    let (status, body) = get(&ctx, &format!("/v1/user/courses/{slug}/pipelines"), &bearer).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let names: Vec<_> = body.as_array().unwrap().iter().map(|run| &run["name"]).collect();
+    assert_eq!(names, ["run-new", "run-old"]);
+    assert_eq!(body[0]["stage_slug"], s1);
+    assert_eq!(body[0]["active"], true);
+    assert_eq!(body[1]["active"], false);
+    assert_eq!(
+        body[1]["conditions"],
+        json!([{ "type": "Succeeded", "status": "False", "reason": "Cancelled" }])
+    );
+
+    // Admins see the runs of any learner
+    let uri = format!("/v1/admin/users/{user_id}/courses/{slug}/pipelines");
+    let (status, body) = get(&ctx, &uri, &admin(&ctx)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body.as_array().unwrap().len(), 2);
+    assert_eq!(get(&ctx, &uri, &bearer).await.0, StatusCode::UNAUTHORIZED);
+
+    // Others have no runs in the course
+    let other = enroll(&ctx, &create_course(&ctx).await).await;
+    let bearer = format!("Bearer {}", token(&ctx, &other).await);
+    let (status, _) = get(&ctx, &format!("/v1/user/courses/{slug}/pipelines"), &bearer).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
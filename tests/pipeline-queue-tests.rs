@@ -0,0 +1,148 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pipeline triggers survive a Kubernetes API outage. These tests need a
+//! disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test pipeline-queue-tests -- --ignored
+
+mod common;
+
+use std::{
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+};
+
+use axum::http::{Method, Request, Response};
+use serde_json::json;
+use stackclass::{
+    model::StageAttemptModel,
+    repository::{CourseRepository, StageRepository},
+    service::{CourseService, PipelineService, StageService},
+    utils::pagination::Page,
+};
+
+use common::{create_course, enroll, setup};
+
+/// A Kubernetes API server that refuses connections while it is down.
+#[derive(Clone, Default)]
+struct MockCluster {
+    up: Arc<AtomicBool>,
+    created: Arc<AtomicUsize>,
+}
+
+impl MockCluster {
+    fn client(&self) -> kube::Client {
+        let mock = self.clone();
+        let service = tower::service_fn(move |req: Request<kube::client::Body>| {
+            let mock = mock.clone();
+            async move {
+                if !mock.up.load(Ordering::SeqCst) {
+                    return Err(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"));
+                }
+
+                let body = match *req.method() {
+                    Method::POST => {
+                        let n = mock.created.fetch_add(1, Ordering::SeqCst);
+                        json!({
+                            "apiVersion": "tekton.dev/v1",
+                            "kind": "PipelineRun",
+                            "metadata": { "name": format!("run-{n}") }
+                        })
+                    }
+                    _ => json!({
+                        "apiVersion": "tekton.dev/v1",
+                        "kind": "PipelineRunList",
+                        "metadata": {},
+                        "items": []
+                    }),
+                };
+                let body = serde_json::to_vec(&body).unwrap();
+                Ok(Response::new(kube::client::Body::from(body)))
+            }
+        });
+        kube::Client::new(service, "stackclass")
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_trigger_is_queued_and_dispatched_once() {
+    let cluster = MockCluster::default();
+    let ctx = setup(cluster.client()).await;
+    let db = &ctx.database;
+    let pipeline = PipelineService::new(ctx.clone());
+
+    // Start from an empty queue, the database may be shared with other runs
+    sqlx::query("UPDATE stage_attempts SET status = 'failed' WHERE status = 'queued'")
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let mut user_course = CourseRepository::get_user_course(db, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+
+    let stage_slug = format!("{slug}-s1");
+    let user_stage =
+        StageRepository::get_user_stage(db, &user_id, &slug, &stage_slug).await.unwrap();
+    let stage = StageRepository::get_by_id(db, user_stage.stage_id).await.unwrap();
+    let repo = user_course.id.to_string();
+
+    // Pushes during the outage are accepted and queued
+    for _ in 0..2 {
+        let attempt = StageAttemptModel::new(user_stage.id, &stage.content_hash);
+        let attempt = pipeline.schedule(&repo, &slug, &stage_slug, attempt).await.unwrap();
+        assert_eq!(attempt.status, "queued");
+        assert_eq!(attempt.pipeline_run, None);
+    }
+    assert!(!ctx.cluster.is_available());
+
+    let status = StageService::get_user_stage_status(&ctx, &user_id, &slug, &stage_slug);
+    assert_eq!(status.await.unwrap().grading.as_deref(), Some("grading_delayed"));
+
+    // Nothing is dispatched while the API server is still down
+    assert!(!pipeline.check_health().await);
+    assert_eq!(pipeline.dispatch_queued().await.unwrap(), 0);
+    assert_eq!(cluster.created.load(Ordering::SeqCst), 0);
+
+    // Once it recovers, concurrent dispatchers trigger every attempt once
+    cluster.up.store(true, Ordering::SeqCst);
+    assert!(pipeline.check_health().await);
+    let other = PipelineService::new(ctx.clone());
+    let (a, b) = tokio::join!(pipeline.dispatch_queued(), other.dispatch_queued());
+    assert_eq!(a.unwrap() + b.unwrap(), 2);
+    assert_eq!(pipeline.dispatch_queued().await.unwrap(), 0);
+    assert_eq!(cluster.created.load(Ordering::SeqCst), 2);
+
+    let page = Page::first(100);
+    let attempts = StageService::find_attempts(ctx.clone(), &slug, None, None, None, &page);
+    for attempt in attempts.await.unwrap().items {
+        assert_eq!(attempt.status, "pending");
+        assert!(attempt.pipeline_run.is_some());
+    }
+
+    let status = StageService::get_user_stage_status(&ctx, &user_id, &slug, &stage_slug);
+    assert_eq!(status.await.unwrap().grading, None);
+
+    // Later pushes are triggered right away
+    let attempt = StageAttemptModel::new(user_stage.id, &stage.content_hash);
+    let attempt = pipeline.schedule(&repo, &slug, &stage_slug, attempt).await.unwrap();
+    assert_eq!(attempt.status, "pending");
+    assert_eq!(cluster.created.load(Ordering::SeqCst), 3);
+}
@@ -0,0 +1,211 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The outcome of PipelineRuns whose webhook was lost is recorded from the
+//! cluster. These tests need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test pipeline-reconcile-tests -- --ignored
+
+mod common;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::http::{Method, Request, Response, StatusCode};
+use chrono::{Duration, Utc};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    model::StageAttemptModel,
+    repository::{CourseRepository, StageRepository},
+    service::{CourseService, PipelineService, StageService},
+};
+
+use common::{create_course, enroll, setup};
+
+/// A Kubernetes API server holding Tekton runs by name, along with the
+/// names of those it was asked to delete.
+#[derive(Clone, Default)]
+struct MockCluster {
+    objects: Arc<Mutex<HashMap<String, Value>>>,
+    deleted: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockCluster {
+    fn client(&self) -> kube::Client {
+        let mock = self.clone();
+        let service = tower::service_fn(move |req: Request<kube::client::Body>| {
+            let mock = mock.clone();
+            async move {
+                // Only Tekton resources are held, test cases and deploy keys
+                // are never found
+                let path = req.uri().path();
+                let name = path.rsplit('/').next().unwrap().to_string();
+                let object = match path.starts_with("/apis/tekton.dev/") {
+                    true => mock.objects.lock().unwrap().get(&name).cloned(),
+                    false => None,
+                };
+                if *req.method() == Method::DELETE && object.is_some() {
+                    mock.deleted.lock().unwrap().push(name);
+                }
+
+                let (status, body) = match object {
+                    Some(object) => (StatusCode::OK, object),
+                    None => (
+                        StatusCode::NOT_FOUND,
+                        json!({
+                            "kind": "Status",
+                            "apiVersion": "v1",
+                            "status": "Failure",
+                            "reason": "NotFound",
+                            "code": 404
+                        }),
+                    ),
+                };
+                let mut res = Response::new(kube::client::Body::from(serde_json::to_vec(&body)?));
+                *res.status_mut() = status;
+                Ok::<_, serde_json::Error>(res)
+            }
+        });
+        kube::Client::new(service, "stackclass")
+    }
+
+    /// Adds a PipelineRun grading a stage which finished a while ago, along
+    /// with its test TaskRun.
+    fn add_run(&self, name: &str, repo: &str, stage: &str, succeeded: [&str; 2], test: bool) {
+        let finished = (Utc::now() - Duration::minutes(10)).to_rfc3339();
+        let course = stage.trim_end_matches("-s1");
+        let params = json!([
+            { "name": "REPO", "value": repo },
+            { "name": "COURSE", "value": course },
+            { "name": "STAGE", "value": stage },
+        ]);
+        let condition =
+            json!([{ "type": "Succeeded", "status": succeeded[0], "reason": succeeded[1] }]);
+        let task = format!("{name}-test");
+
+        let mut objects = self.objects.lock().unwrap();
+        objects.insert(
+            name.to_string(),
+            json!({
+                "apiVersion": "tekton.dev/v1",
+                "kind": "PipelineRun",
+                "metadata": { "name": name },
+                "spec": { "params": params },
+                "status": {
+                    "completionTime": finished,
+                    "conditions": condition,
+                    "childReferences": [{ "kind": "TaskRun", "name": task, "pipelineTaskName": "test" }]
+                }
+            }),
+        );
+        let status = if test { "True" } else { "False" };
+        objects.insert(
+            task.clone(),
+            json!({
+                "apiVersion": "tekton.dev/v1",
+                "kind": "TaskRun",
+                "metadata": { "name": task },
+                "status": {
+                    "conditions": [{ "type": "Succeeded", "status": status, "reason": "Failed" }]
+                }
+            }),
+        );
+    }
+}
+
+/// Enrolls a new user into the course, starts its first stage and records
+/// an attempt at it made some minutes ago, returning the user id, the
+/// repository and the name of the run.
+async fn attempt(ctx: &Arc<Context>, slug: &str, minutes_ago: i64) -> (String, String, String) {
+    let db = &ctx.database;
+    let user_id = enroll(ctx, slug).await;
+    let mut user_course = CourseRepository::get_user_course(db, &user_id, slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+
+    let stage = format!("{slug}-s1");
+    let user_stage = StageRepository::get_user_stage(db, &user_id, slug, &stage).await.unwrap();
+    let name = format!("run-{}", uuid::Uuid::now_v7().simple());
+    let created = Utc::now() - Duration::minutes(minutes_ago);
+    let attempt = StageAttemptModel::new_at(user_stage.id, "", created).with_pipeline_run(&name);
+    StageRepository::create_attempt(db, &attempt).await.unwrap();
+
+    (user_id, user_course.id.to_string(), name)
+}
+
+async fn latest(ctx: &Arc<Context>, slug: &str, user_id: &str) -> StageAttemptModel {
+    let db = &ctx.database;
+    let stage = format!("{slug}-s1");
+    let user_stage = StageRepository::get_user_stage(db, user_id, slug, &stage).await.unwrap();
+    StageRepository::find_latest_attempt(db, &user_stage.id).await.unwrap().unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_lost_outcomes_are_reconciled() {
+    let cluster = MockCluster::default();
+    let ctx = setup(cluster.client()).await;
+    let slug = create_course(&ctx).await;
+    let stage = format!("{slug}-s1");
+
+    // Start from no pending attempts, the database may be shared with other runs
+    sqlx::query("UPDATE stage_attempts SET status = 'failed' WHERE status = 'pending'")
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+
+    let (passer, repo, passed) = attempt(&ctx, &slug, 20).await;
+    cluster.add_run(&passed, &repo, &stage, ["True", "Succeeded"], true);
+    let (failer, repo, failed) = attempt(&ctx, &slug, 20).await;
+    cluster.add_run(&failed, &repo, &stage, ["True", "Succeeded"], false);
+    let (timer, repo, timed_out) = attempt(&ctx, &slug, 90).await;
+    cluster.add_run(&timed_out, &repo, &stage, ["False", "PipelineRunTimeout"], false);
+    let (loser, _, _) = attempt(&ctx, &slug, 20).await;
+    let (newcomer, _, _) = attempt(&ctx, &slug, 1).await;
+
+    let pipeline = PipelineService::new(ctx.clone());
+    assert_eq!(pipeline.reconcile().await.unwrap(), 4);
+
+    // A run whose test task passed completes the stage
+    let attempt = latest(&ctx, &slug, &passer).await;
+    assert_eq!(attempt.status, "passed");
+    assert!(attempt.finished_at.is_some());
+    let status = StageService::get_user_stage_status(&ctx, &passer, &slug, &stage).await.unwrap();
+    assert_eq!(status.status, "completed");
+    assert_eq!(status.pipeline_status.as_deref(), Some("finished"));
+
+    // Failed test tasks and runs fail the attempt with their reason
+    let attempt = latest(&ctx, &slug, &failer).await;
+    assert_eq!((attempt.status.as_str(), attempt.reason.as_deref()), ("failed", Some("Failed")));
+    let attempt = latest(&ctx, &slug, &timer).await;
+    assert_eq!(attempt.reason.as_deref(), Some("PipelineRunTimeout"));
+
+    // Attempts whose run is gone fail, recent ones are left to their webhook
+    let attempt = latest(&ctx, &slug, &loser).await;
+    assert_eq!(attempt.reason.as_deref(), Some("PipelineRunMissing"));
+    let attempt = latest(&ctx, &slug, &newcomer).await;
+    assert_eq!(attempt.status, "pending");
+    let status = StageService::get_user_stage_status(&ctx, &newcomer, &slug, &stage).await.unwrap();
+    assert_eq!(status.pipeline_status.as_deref(), Some("running"));
+
+    // Reconciled runs are deleted, and reconciled once
+    let mut deleted = cluster.deleted.lock().unwrap().clone();
+    deleted.sort();
+    let mut expected = vec![passed, failed, timed_out];
+    expected.sort();
+    assert_eq!(deleted, expected);
+    assert_eq!(pipeline.reconcile().await.unwrap(), 0);
+}
@@ -0,0 +1,197 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! PipelineRuns are swept once kept past their retention, or stuck without
+//! finishing. These tests need a disposable PostgreSQL database, run them
+//! with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test pipeline-sweep-tests -- --ignored
+
+mod common;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::http::{Method, Request, Response, StatusCode};
+use chrono::{Duration, Utc};
+use serde_json::{Value, json};
+use stackclass::{
+    model::StageAttemptModel,
+    repository::{CourseRepository, StageRepository},
+    service::{CourseService, PipelineService},
+};
+
+use common::{create_course, enroll, setup};
+
+/// A Kubernetes API server holding Tekton runs by name, along with the
+/// names of those it was asked to delete and the queries runs were listed
+/// with.
+#[derive(Clone, Default)]
+struct MockCluster {
+    objects: Arc<Mutex<HashMap<String, Value>>>,
+    deleted: Arc<Mutex<Vec<String>>>,
+    queries: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockCluster {
+    fn client(&self) -> kube::Client {
+        let mock = self.clone();
+        let service = tower::service_fn(move |req: Request<kube::client::Body>| {
+            let mock = mock.clone();
+            async move {
+                // Only Tekton resources are held, test cases and deploy keys
+                // are never found
+                let path = req.uri().path();
+                let name = path.rsplit('/').next().unwrap().to_string();
+                let object = match path.starts_with("/apis/tekton.dev/") {
+                    true if name == "pipelineruns" => {
+                        let query = req.uri().query().unwrap_or_default().to_string();
+                        mock.queries.lock().unwrap().push(query);
+                        let objects = mock.objects.lock().unwrap();
+                        let runs = objects.values().filter(|o| o["kind"] == "PipelineRun");
+                        Some(json!({
+                            "apiVersion": "tekton.dev/v1",
+                            "kind": "PipelineRunList",
+                            "metadata": {},
+                            "items": runs.cloned().collect::<Vec<_>>()
+                        }))
+                    }
+                    true => mock.objects.lock().unwrap().get(&name).cloned(),
+                    false => None,
+                };
+                if *req.method() == Method::DELETE && object.is_some() {
+                    mock.deleted.lock().unwrap().push(name);
+                }
+
+                let (status, body) = match object {
+                    Some(object) => (StatusCode::OK, object),
+                    None => (
+                        StatusCode::NOT_FOUND,
+                        json!({
+                            "kind": "Status",
+                            "apiVersion": "v1",
+                            "status": "Failure",
+                            "reason": "NotFound",
+                            "code": 404
+                        }),
+                    ),
+                };
+                let mut res = Response::new(kube::client::Body::from(serde_json::to_vec(&body)?));
+                *res.status_mut() = status;
+                Ok::<_, serde_json::Error>(res)
+            }
+        });
+        kube::Client::new(service, "stackclass")
+    }
+
+    /// Adds a PipelineRun grading a stage which passed the given time ago,
+    /// along with its test TaskRun.
+    fn add_finished_run(&self, name: &str, repo: &str, stage: &str, ago: Duration) {
+        let finished = Utc::now() - ago;
+        let created = (finished - Duration::minutes(1)).to_rfc3339();
+        let course = stage.trim_end_matches("-s1");
+        let params = json!([
+            { "name": "REPO", "value": repo },
+            { "name": "COURSE", "value": course },
+            { "name": "STAGE", "value": stage },
+        ]);
+        let condition = json!([{ "type": "Succeeded", "status": "True", "reason": "Succeeded" }]);
+        let task = format!("{name}-test");
+
+        let mut objects = self.objects.lock().unwrap();
+        objects.insert(
+            name.to_string(),
+            json!({
+                "apiVersion": "tekton.dev/v1",
+                "kind": "PipelineRun",
+                "metadata": { "name": name, "creationTimestamp": created },
+                "spec": { "params": params },
+                "status": {
+                    "completionTime": finished.to_rfc3339(),
+                    "conditions": condition,
+                    "childReferences": [{ "kind": "TaskRun", "name": task, "pipelineTaskName": "test" }]
+                }
+            }),
+        );
+        objects.insert(
+            task.clone(),
+            json!({
+                "apiVersion": "tekton.dev/v1",
+                "kind": "TaskRun",
+                "metadata": { "name": task },
+                "status": { "conditions": condition }
+            }),
+        );
+    }
+
+    /// Adds a PipelineRun which started the given time ago and never
+    /// finished.
+    fn add_stuck_run(&self, name: &str, ago: Duration) {
+        let created = (Utc::now() - ago).to_rfc3339();
+        self.objects.lock().unwrap().insert(
+            name.to_string(),
+            json!({
+                "apiVersion": "tekton.dev/v1",
+                "kind": "PipelineRun",
+                "metadata": { "name": name, "creationTimestamp": created },
+                "status": { "startTime": created }
+            }),
+        );
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_expired_runs_are_swept() {
+    let cluster = MockCluster::default();
+    let mut ctx = Arc::into_inner(setup(cluster.client()).await).unwrap();
+    ctx.config.pipeline_run_retention = 1800;
+    let ctx = Arc::new(ctx);
+    let db = &ctx.database;
+
+    let slug = create_course(&ctx).await;
+    let stage = format!("{slug}-s1");
+    let user_id = enroll(&ctx, &slug).await;
+    let mut user_course = CourseRepository::get_user_course(db, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+    let repo = user_course.id.to_string();
+
+    // The webhook of a run past its retention was lost
+    let user_stage = StageRepository::get_user_stage(db, &user_id, &slug, &stage).await.unwrap();
+    let attempt = StageAttemptModel::new(user_stage.id, "").with_pipeline_run("run-expired");
+    StageRepository::create_attempt(db, &attempt).await.unwrap();
+    cluster.add_finished_run("run-expired", &repo, &stage, Duration::minutes(40));
+
+    // Recent runs are kept for debugging, runs are given until the hard
+    // timeout to finish
+    cluster.add_finished_run("run-kept", &repo, &stage, Duration::minutes(20));
+    cluster.add_stuck_run("run-stuck", Duration::days(3));
+    cluster.add_stuck_run("run-running", Duration::hours(1));
+
+    let pipeline = PipelineService::new(ctx.clone());
+    assert_eq!(pipeline.remove_expired_runs().await.unwrap(), 2);
+
+    // Only runs grading repositories are listed
+    let queries = cluster.queries.lock().unwrap().clone();
+    assert_eq!(queries, ["&labelSelector=stackclass.dev%2Frepo"]);
+
+    // The outcome of the expired run is recorded before it is deleted
+    let latest = StageRepository::find_latest_attempt(db, &user_stage.id).await.unwrap();
+    assert_eq!(latest.unwrap().status, "passed");
+    let mut deleted = cluster.deleted.lock().unwrap().clone();
+    deleted.sort();
+    assert_eq!(deleted, ["run-expired", "run-stuck"]);
+}
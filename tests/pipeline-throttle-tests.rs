@@ -0,0 +1,202 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Users run a limited number of PipelineRuns at once, across all their
+//! courses. These tests need a disposable PostgreSQL database, run them
+//! with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test pipeline-throttle-tests -- --ignored
+
+mod common;
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use axum::http::{Method, Request, Response};
+use serde_json::json;
+use stackclass::{
+    context::Context,
+    errors::ApiError,
+    model::{StageAttemptModel, UserStageModel},
+    repository::{CourseRepository, StageRepository},
+    service::{CourseService, PipelineService, StageService},
+};
+use uuid::Uuid;
+
+use common::{create_course, enroll, enroll_user, setup};
+
+/// Most PipelineRuns running at once for a user in these tests.
+const LIMIT: usize = 2;
+
+/// A Kubernetes API server creating every PipelineRun it is asked to, and
+/// listing none.
+#[derive(Clone, Default)]
+struct MockCluster {
+    created: Arc<AtomicUsize>,
+}
+
+impl MockCluster {
+    fn client(&self) -> kube::Client {
+        let mock = self.clone();
+        let service = tower::service_fn(move |req: Request<kube::client::Body>| {
+            let mock = mock.clone();
+            async move {
+                let body = match *req.method() {
+                    Method::POST => {
+                        let n = mock.created.fetch_add(1, Ordering::SeqCst);
+                        json!({
+                            "apiVersion": "tekton.dev/v1",
+                            "kind": "PipelineRun",
+                            "metadata": { "name": format!("run-{}-{n}", Uuid::now_v7().simple()) }
+                        })
+                    }
+                    _ => json!({
+                        "apiVersion": "tekton.dev/v1",
+                        "kind": "PipelineRunList",
+                        "metadata": {},
+                        "items": []
+                    }),
+                };
+                let body = serde_json::to_vec(&body).unwrap();
+                Ok::<_, std::io::Error>(Response::new(kube::client::Body::from(body)))
+            }
+        });
+        kube::Client::new(service, "stackclass")
+    }
+}
+
+/// Activates the course of the user, returning the repository and the user
+/// stage of its first stage.
+async fn start(ctx: &Arc<Context>, user_id: &str, slug: &str) -> (String, UserStageModel) {
+    let db = &ctx.database;
+    let mut user_course = CourseRepository::get_user_course(db, user_id, slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+
+    let stage = format!("{slug}-s1");
+    let user_stage = StageRepository::get_user_stage(db, user_id, slug, &stage).await.unwrap();
+    (user_course.id.to_string(), user_stage)
+}
+
+/// Records an attempt at the user stage with the given status, graded by a
+/// PipelineRun if it has a name.
+async fn attempt(ctx: &Context, user_stage: &UserStageModel, status: &str, run: Option<&str>) {
+    let mut attempt = StageAttemptModel::new(user_stage.id, "").with_status(status);
+    if let Some(run) = run {
+        attempt = attempt.with_pipeline_run(run);
+    }
+    StageRepository::create_attempt(&ctx.database, &attempt).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_running_attempts_are_counted_per_user() {
+    let ctx = setup(MockCluster::default().client()).await;
+    let (first, second) = (create_course(&ctx).await, create_course(&ctx).await);
+    let user_id = enroll(&ctx, &first).await;
+    enroll_user(&ctx, &user_id, &second).await;
+    let (_, user_stage) = start(&ctx, &user_id, &first).await;
+    let (_, other_stage) = start(&ctx, &user_id, &second).await;
+
+    // Only attempts whose run has not reported its outcome count, in every
+    // course of the user
+    attempt(&ctx, &user_stage, "pending", Some("run-a")).await;
+    attempt(&ctx, &user_stage, "passed", Some("run-b")).await;
+    attempt(&ctx, &user_stage, "queued", None).await;
+    attempt(&ctx, &other_stage, "pending", Some("run-c")).await;
+    attempt(&ctx, &other_stage, "cancelled", Some("run-d")).await;
+    let count = StageRepository::count_running_attempts(&ctx.database, &user_stage.id);
+    assert_eq!(count.await.unwrap(), 2);
+
+    // Runs of other users do not count
+    let stranger = enroll(&ctx, &first).await;
+    let (_, stranger_stage) = start(&ctx, &stranger, &first).await;
+    let count = StageRepository::count_running_attempts(&ctx.database, &stranger_stage.id);
+    assert_eq!(count.await.unwrap(), 0);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_attempts_over_the_limit_are_throttled() {
+    let cluster = MockCluster::default();
+    let mut ctx = Arc::into_inner(setup(cluster.client()).await).unwrap();
+    ctx.config.max_concurrent_pipelines_per_user = LIMIT;
+    let ctx = Arc::new(ctx);
+    let db = &ctx.database;
+    let pipeline = PipelineService::new(ctx.clone());
+
+    // Start from an empty queue, the database may be shared with other runs
+    sqlx::query("UPDATE stage_attempts SET status = 'failed' WHERE status = 'queued'")
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+    let slug = create_course(&ctx).await;
+    let stage = format!("{slug}-s1");
+    let user_id = enroll(&ctx, &slug).await;
+    let (repo, user_stage) = start(&ctx, &user_id, &slug).await;
+
+    // Pushes up to the limit trigger their runs, the next one waits
+    for _ in 0..LIMIT {
+        let attempt = StageAttemptModel::new(user_stage.id, "");
+        let attempt = pipeline.schedule(&repo, &slug, &stage, attempt).await.unwrap();
+        assert_eq!(attempt.status, "pending");
+    }
+    let attempt = StageAttemptModel::new(user_stage.id, "");
+    let throttled = pipeline.schedule(&repo, &slug, &stage, attempt).await.unwrap();
+    assert_eq!(throttled.status, "queued");
+    assert_eq!(throttled.reason.as_deref(), Some("throttled"));
+    assert_eq!(cluster.created.load(Ordering::SeqCst), LIMIT);
+
+    let status = StageService::get_user_stage_status(&ctx, &user_id, &slug, &stage);
+    assert_eq!(status.await.unwrap().grading.as_deref(), Some("grading_throttled"));
+
+    // Retests are refused rather than queued
+    match StageService::retest(ctx.clone(), &user_id, &slug, &stage).await {
+        Err(ApiError::TooManyRequests(_)) => {}
+        other => panic!("expected too many requests, got {other:?}"),
+    }
+
+    // Other users are not held back
+    let stranger = enroll(&ctx, &slug).await;
+    let (stranger_repo, stranger_stage) = start(&ctx, &stranger, &slug).await;
+    let attempt = StageAttemptModel::new(stranger_stage.id, "");
+    let attempt = pipeline.schedule(&stranger_repo, &slug, &stage, attempt).await.unwrap();
+    assert_eq!(attempt.status, "pending");
+
+    // The throttled attempt stays queued until a run of the user finishes
+    assert_eq!(pipeline.dispatch_queued().await.unwrap(), 0);
+    sqlx::query(
+        "UPDATE stage_attempts SET status = 'failed' WHERE id = (
+            SELECT id FROM stage_attempts
+            WHERE user_stage_id = $1 AND status = 'pending'
+            ORDER BY created_at LIMIT 1
+        )",
+    )
+    .bind(user_stage.id)
+    .execute(db.pool())
+    .await
+    .unwrap();
+    assert_eq!(pipeline.dispatch_queued().await.unwrap(), 1);
+
+    let latest = StageRepository::find_latest_attempt(db, &user_stage.id).await.unwrap().unwrap();
+    assert_eq!(latest.id, throttled.id);
+    assert_eq!(latest.status, "pending");
+    assert_eq!(latest.reason, None);
+    assert!(latest.pipeline_run.is_some());
+
+    let status = StageService::get_user_stage_status(&ctx, &user_id, &slug, &stage);
+    assert_eq!(status.await.unwrap().grading, None);
+}
@@ -0,0 +1,98 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Consistency checks between the progress summary and a from-scratch
+//! aggregation of the user stages. These tests need a disposable PostgreSQL
+//! database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test progress-tests -- --ignored
+
+mod common;
+
+use stackclass::{
+    context::Context,
+    repository::{CourseRepository, ProgressRepository},
+    service::{CourseService, StageService},
+};
+
+use common::{create_course, enroll, setup, unreachable_cluster};
+
+async fn assert_consistent(ctx: &Context, slug: &str) {
+    let summary = ProgressRepository::find_by_course(&ctx.database, slug).await.unwrap();
+    let live = ProgressRepository::find_live_by_course(&ctx.database, slug).await.unwrap();
+    assert_eq!(summary, live);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_summary_matches_live_aggregation() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let users = [enroll(&ctx, &slug).await, enroll(&ctx, &slug).await];
+    assert_consistent(&ctx, &slug).await;
+
+    for user_id in &users {
+        let mut user_course =
+            CourseRepository::get_user_course(&ctx.database, user_id, &slug).await.unwrap();
+        CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+        assert_consistent(&ctx, &slug).await;
+    }
+
+    // The first learner completes every stage, the second one only the first.
+    for stage in ["s1", "s2", "e1"] {
+        let stage_slug = format!("{slug}-{stage}");
+        StageService::complete(ctx.clone(), &users[0], &slug, &stage_slug, None).await.unwrap();
+        assert_consistent(&ctx, &slug).await;
+    }
+    let stage_slug = format!("{slug}-s1");
+    StageService::complete(ctx.clone(), &users[1], &slug, &stage_slug, None).await.unwrap();
+    assert_consistent(&ctx, &slug).await;
+
+    let progress = CourseService::find_progress(ctx.clone(), &slug, false).await.unwrap();
+    assert_eq!(progress[0].user_id, users[0]);
+    assert_eq!(progress[0].completed_stage_count, 3);
+    assert_eq!(progress[0].extension_progress.get("ext"), Some(&1));
+    assert_eq!(progress[1].completed_stage_count, 1);
+    assert!(progress[1].extension_progress.is_empty());
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_rebuild_repairs_summary() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+
+    let mut user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+    StageService::complete(ctx.clone(), &user_id, &slug, &format!("{slug}-s1"), None)
+        .await
+        .unwrap();
+
+    // Simulate a summary that drifted, e.g. after a manual data fix.
+    sqlx::query(
+        "UPDATE user_course_progress SET completed_stage_count = 42 WHERE user_course_id = $1",
+    )
+    .bind(user_course.id)
+    .execute(ctx.database.pool())
+    .await
+    .unwrap();
+
+    let fresh = CourseService::find_progress(ctx.clone(), &slug, true).await.unwrap();
+    assert_eq!(fresh[0].completed_stage_count, 1);
+
+    assert_eq!(CourseService::rebuild_progress(ctx.clone(), &slug).await.unwrap(), 1);
+    assert_consistent(&ctx, &slug).await;
+}
@@ -0,0 +1,431 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Per-course registry credentials and their rotation, the quota of the
+//! course projects, and garbage collections of the registry. These tests need a disposable
+//! PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test registry-tests -- --ignored
+
+mod common;
+
+use std::{
+    convert::Infallible,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicI64, Ordering},
+    },
+};
+
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::Path,
+    http::{Method, Request, Response, StatusCode, header},
+    routing::{delete, get, head, post, put},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::Duration;
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    routes,
+    service::{PipelineService, RegistryService},
+    utils::{clock::ManualClock, crypto},
+};
+use tower::ServiceExt;
+
+use common::{create_course, mock_api, setup, stop_clock};
+
+/// Storage used by the images of every project.
+const STORAGE_USED: i64 = 50 << 20;
+
+/// A Harbor server recording the robot accounts it manages, the storage limit
+/// of its projects, unlimited until set, and the running garbage collection.
+#[derive(Clone, Default)]
+struct MockHarbor {
+    next_id: Arc<AtomicI64>,
+    robots: Arc<Mutex<Vec<(i64, Value)>>>,
+    storage_limit: Arc<Mutex<Option<i64>>>,
+    gc_job: Arc<Mutex<Option<i64>>>,
+}
+
+impl MockHarbor {
+    fn router(&self) -> Router {
+        let (created, deleted) = (self.clone(), self.clone());
+        let (updated, summarized) = (self.clone(), self.clone());
+        let (triggered, polled) = (self.clone(), self.clone());
+        Router::new()
+            .route("/api/v2.0/projects", head(|| async { StatusCode::OK }))
+            .route(
+                "/api/v2.0/projects/{name}",
+                put(move |Json(body): Json<Value>| async move {
+                    let limit = body["storage_limit"].as_i64().unwrap();
+                    if limit != -1 && limit < STORAGE_USED {
+                        let message = "the quota is lower than the current usage";
+                        let errors = json!({ "errors": [{ "code": "CONFLICT", "message": message }] });
+                        return (StatusCode::CONFLICT, Json(errors));
+                    }
+                    *updated.storage_limit.lock().unwrap() = Some(limit);
+                    (StatusCode::OK, Json(Value::Null))
+                }),
+            )
+            .route(
+                "/api/v2.0/projects/{name}/summary",
+                get(move || async move {
+                    let limit = summarized.storage_limit.lock().unwrap().unwrap_or(-1);
+                    Json(json!({
+                        "repo_count": 2,
+                        "quota": { "hard": { "storage": limit }, "used": { "storage": STORAGE_USED } }
+                    }))
+                }),
+            )
+            .route(
+                "/api/v2.0/system/gc/schedule",
+                post(move || async move {
+                    let mut job = triggered.gc_job.lock().unwrap();
+                    if job.is_some() {
+                        let message = "a previous GC job is still running";
+                        let errors = json!({ "errors": [{ "code": "CONFLICT", "message": message }] });
+                        return Response::builder()
+                            .status(StatusCode::CONFLICT)
+                            .body(Body::from(errors.to_string()))
+                            .unwrap();
+                    }
+                    let id = triggered.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+                    *job = Some(id);
+                    Response::builder()
+                        .status(StatusCode::CREATED)
+                        .header(header::LOCATION, format!("/api/v2.0/system/gc/{id}"))
+                        .body(Body::empty())
+                        .unwrap()
+                }),
+            )
+            .route(
+                "/api/v2.0/system/gc/{id}",
+                get(move |Path(id): Path<i64>| async move {
+                    if *polled.gc_job.lock().unwrap() != Some(id) {
+                        return (StatusCode::NOT_FOUND, Json(Value::Null));
+                    }
+                    let job = json!({
+                        "id": id,
+                        "job_kind": "MANUAL",
+                        "job_status": "Running",
+                        "creation_time": "2025-06-01T10:00:00Z",
+                        "update_time": "2025-06-01T10:00:05Z"
+                    });
+                    (StatusCode::OK, Json(job))
+                }),
+            )
+            .route(
+                "/api/v2.0/robots",
+                post(move |Json(body): Json<Value>| async move {
+                    let id = created.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+                    let project = body["permissions"][0]["namespace"].as_str().unwrap();
+                    let name = format!("robot${project}+{}", body["name"].as_str().unwrap());
+                    created.robots.lock().unwrap().push((id, body));
+                    let robot = json!({ "id": id, "name": name, "secret": format!("secret-{id}") });
+                    (StatusCode::CREATED, Json(robot))
+                }),
+            )
+            .route(
+                "/api/v2.0/robots/{id}",
+                delete(move |Path(id): Path<i64>| async move {
+                    deleted.robots.lock().unwrap().retain(|(robot, _)| *robot != id);
+                    StatusCode::OK
+                }),
+            )
+    }
+
+    fn robot_ids(&self) -> Vec<i64> {
+        self.robots.lock().unwrap().iter().map(|(id, _)| *id).collect()
+    }
+}
+
+/// A Kubernetes API server recording the Secrets and PipelineRuns created.
+#[derive(Clone, Default)]
+struct MockCluster {
+    reject_secrets: Arc<AtomicBool>,
+    secrets: Arc<Mutex<Vec<(String, Value)>>>,
+    runs: Arc<Mutex<Vec<Value>>>,
+}
+
+impl MockCluster {
+    fn client(&self) -> kube::Client {
+        let mock = self.clone();
+        let service = tower::service_fn(move |req: Request<kube::client::Body>| {
+            let mock = mock.clone();
+            async move {
+                let (method, path) = (req.method().clone(), req.uri().path().to_string());
+                let body = req.into_body().collect().await.unwrap().to_bytes();
+                let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+                let name = path.rsplit('/').next().unwrap().to_string();
+
+                let (status, body) = match (method, path.contains("/secrets")) {
+                    (Method::PATCH, true) if mock.reject_secrets.load(Ordering::SeqCst) => {
+                        let status = json!({ "kind": "Status", "apiVersion": "v1", "code": 403, "status": "Failure", "message": "forbidden", "reason": "Forbidden" });
+                        (403, status)
+                    }
+                    (Method::PATCH, true) => {
+                        let mut secrets = mock.secrets.lock().unwrap();
+                        secrets.retain(|(secret, _)| *secret != name);
+                        secrets.push((name, body.clone()));
+                        (200, body)
+                    }
+                    (Method::DELETE, true) => {
+                        mock.secrets.lock().unwrap().retain(|(secret, _)| *secret != name);
+                        (
+                            200,
+                            json!({ "apiVersion": "v1", "kind": "Secret", "metadata": { "name": name } }),
+                        )
+                    }
+                    _ => {
+                        mock.runs.lock().unwrap().push(body.clone());
+                        (201, body)
+                    }
+                };
+
+                let body = serde_json::to_vec(&body).unwrap();
+                let mut res = Response::new(kube::client::Body::from(body));
+                *res.status_mut() = StatusCode::from_u16(status).unwrap();
+                Ok::<_, Infallible>(res)
+            }
+        });
+        kube::Client::new(service, "stackclass")
+    }
+
+    fn secret_names(&self) -> Vec<String> {
+        self.secrets.lock().unwrap().iter().map(|(name, _)| name.clone()).collect()
+    }
+}
+
+async fn context(harbor: &MockHarbor, cluster: &MockCluster) -> (Arc<Context>, Arc<ManualClock>) {
+    let mut ctx = Arc::into_inner(setup(cluster.client()).await).unwrap();
+    mock_api(&mut ctx, harbor.router()).await;
+    let clock = stop_clock(&mut ctx);
+    (Arc::new(ctx), clock)
+}
+
+async fn rotate(ctx: &Arc<Context>, slug: &str) -> (StatusCode, Value) {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let auth = format!("Basic {}", STANDARD.encode(format!("admin:{password}")));
+    let req = Request::post(format!("/v1/admin/courses/{slug}/registry-credentials/rotate"))
+        .header(header::AUTHORIZATION, auth)
+        .body(Body::empty())
+        .unwrap();
+
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Gets the registry summary of a course, or sets its quota when given a
+/// request.
+async fn registry(ctx: &Arc<Context>, slug: &str, req: Option<Value>) -> (StatusCode, Value) {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let auth = format!("Basic {}", STANDARD.encode(format!("admin:{password}")));
+    let uri = format!("/v1/courses/{slug}/registry");
+    let req = match req {
+        Some(req) => Request::put(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, auth)
+            .body(Body::from(req.to_string())),
+        None => Request::get(uri).header(header::AUTHORIZATION, auth).body(Body::empty()),
+    };
+
+    let res = routes::build(ctx.clone()).oneshot(req.unwrap()).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Triggers a garbage collection of the registry, or gets the status of the
+/// given one.
+async fn gc(ctx: &Arc<Context>, id: Option<i64>) -> (StatusCode, Value) {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let auth = format!("Basic {}", STANDARD.encode(format!("admin:{password}")));
+    let req = match id {
+        Some(id) => Request::get(format!("/v1/admin/registry/gc/{id}")),
+        None => Request::post("/v1/admin/registry/gc"),
+    };
+    let req = req.header(header::AUTHORIZATION, auth).body(Body::empty()).unwrap();
+
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Workspace secret and course image of the latest PipelineRun.
+fn latest_run(cluster: &MockCluster) -> (String, String) {
+    let runs = cluster.runs.lock().unwrap();
+    let spec = &runs.last().unwrap()["spec"];
+    let secret = spec["workspaces"][1]["secret"]["secretName"].as_str().unwrap();
+    let params = spec["params"].as_array().unwrap();
+    let image = params.iter().find(|p| p["name"] == "COURSE_IMAGE").unwrap()["value"].as_str();
+    (secret.to_string(), image.unwrap().to_string())
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_rotation_keeps_old_credentials_until_retired() {
+    let (harbor, cluster) = (MockHarbor::default(), MockCluster::default());
+    let (ctx, clock) = context(&harbor, &cluster).await;
+    let slug = create_course(&ctx).await;
+    let pipeline = PipelineService::new(ctx.clone());
+
+    // Courses without a robot account keep using the shared secret
+    pipeline.trigger("repo", &slug, &format!("{slug}-s1"), "", "", "").await.unwrap();
+    assert_eq!(
+        latest_run(&cluster),
+        ("docker-credentials".into(), "docker.local/stackclass/repo:latest".into())
+    );
+
+    let (status, first) = rotate(&ctx, &slug).await;
+    assert_eq!(status, StatusCode::OK);
+    let project = format!("stackclass-{slug}");
+    assert_eq!(first["project"], project.as_str());
+    assert!(first.get("secret").is_none());
+
+    // The robot may only push and pull the course project
+    let robot = harbor.robots.lock().unwrap()[0].1.clone();
+    assert_eq!(robot["level"], "project");
+    assert_eq!(robot["permissions"][0]["namespace"], project.as_str());
+    let access = robot["permissions"][0]["access"].as_array().unwrap();
+    assert_eq!(access.len(), 2);
+    assert!(access.iter().all(|a| a["resource"] == "repository"));
+
+    // Its docker config is materialized as a Secret named after the course
+    let first_secret = first["secret_name"].as_str().unwrap().to_string();
+    assert!(first_secret.starts_with(&format!("{slug}-registry-")));
+    let config = cluster.secrets.lock().unwrap()[0].1["data"]["config.json"].clone();
+    let config: Value =
+        serde_json::from_slice(&STANDARD.decode(config.as_str().unwrap()).unwrap()).unwrap();
+    assert_eq!(config["auths"]["docker.local"]["password"], "secret-1");
+
+    pipeline.trigger("repo", &slug, &format!("{slug}-s1"), "", "", "").await.unwrap();
+    assert_eq!(
+        latest_run(&cluster),
+        (first_secret.clone(), format!("docker.local/{project}/repo:latest"))
+    );
+
+    // After a rotation new pipelines use the new credentials, while the old
+    // ones stay valid for the pipelines already running
+    let (status, second) = rotate(&ctx, &slug).await;
+    assert_eq!(status, StatusCode::OK);
+    let second_secret = second["secret_name"].as_str().unwrap().to_string();
+    assert_ne!(second_secret, first_secret);
+    assert_eq!(harbor.robot_ids(), vec![1, 2]);
+    assert_eq!(cluster.secret_names(), vec![first_secret.clone(), second_secret.clone()]);
+
+    pipeline.trigger("repo", &slug, &format!("{slug}-s1"), "", "", "").await.unwrap();
+    assert_eq!(latest_run(&cluster).0, second_secret);
+
+    // Once the grace period is over only the active credentials remain
+    RegistryService::remove_retired(&ctx).await.unwrap();
+    assert_eq!(harbor.robot_ids(), vec![1, 2]);
+    clock.advance(Duration::seconds(ctx.config.registry_credentials_grace + 1));
+    assert_eq!(RegistryService::remove_retired(&ctx).await.unwrap(), 1);
+    assert_eq!(harbor.robot_ids(), vec![2]);
+    assert_eq!(cluster.secret_names(), vec![second_secret]);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_failed_rotation_leaves_credentials_unchanged() {
+    let (harbor, cluster) = (MockHarbor::default(), MockCluster::default());
+    let (ctx, _) = context(&harbor, &cluster).await;
+    let slug = create_course(&ctx).await;
+
+    let active = RegistryService::provision(&ctx, &slug, "admin").await.unwrap();
+    cluster.reject_secrets.store(true, Ordering::SeqCst);
+
+    let (status, _) = rotate(&ctx, &slug).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+
+    // The robot created for the failed rotation was removed again
+    assert_eq!(harbor.robot_ids(), vec![active.robot_id]);
+    assert_eq!(cluster.secret_names(), vec![active.secret_name.clone()]);
+    let pipeline = RegistryService::pipeline_credentials(&ctx, &slug).await.unwrap();
+    assert_eq!(pipeline.1, active.secret_name);
+
+    // Provisioning again restores the Secret of the active credentials
+    cluster.reject_secrets.store(false, Ordering::SeqCst);
+    cluster.secrets.lock().unwrap().clear();
+    let restored = RegistryService::provision(&ctx, &slug, "admin").await.unwrap();
+    assert_eq!(restored.id, active.id);
+    assert_eq!(cluster.secret_names(), vec![active.secret_name]);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_registry_quota() {
+    let (harbor, cluster) = (MockHarbor::default(), MockCluster::default());
+    let (ctx, _) = context(&harbor, &cluster).await;
+    let slug = create_course(&ctx).await;
+
+    // Courses pushing to the shared project have no quota of their own
+    assert_eq!(registry(&ctx, &slug, None).await.0, StatusCode::NOT_FOUND);
+    assert_eq!(registry(&ctx, "missing", None).await.0, StatusCode::NOT_FOUND);
+
+    RegistryService::provision(&ctx, &slug, "admin").await.unwrap();
+    let (status, summary) = registry(&ctx, &slug, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        summary,
+        json!({
+            "project": format!("stackclass-{slug}"),
+            "repo_count": 2,
+            "storage_used": STORAGE_USED,
+            "storage_limit": -1
+        })
+    );
+
+    let (status, summary) = registry(&ctx, &slug, Some(json!({ "storage_limit": 1 << 30 }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(summary["storage_limit"], 1 << 30);
+
+    // A quota below the current usage is refused, and the previous one kept
+    let (status, body) = registry(&ctx, &slug, Some(json!({ "storage_limit": 1 << 20 }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "conflict");
+    assert_eq!(*harbor.storage_limit.lock().unwrap(), Some(1 << 30));
+
+    let (status, _) = registry(&ctx, &slug, Some(json!({ "storage_limit": 0 }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_garbage_collection() {
+    let (harbor, cluster) = (MockHarbor::default(), MockCluster::default());
+    let (ctx, _) = context(&harbor, &cluster).await;
+
+    let (status, body) = gc(&ctx, None).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let id = body["id"].as_i64().unwrap();
+
+    let (status, body) = gc(&ctx, Some(id)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "Running");
+    assert_eq!(body["created_at"], "2025-06-01T10:00:00Z");
+    assert_eq!(gc(&ctx, Some(id + 1)).await.0, StatusCode::NOT_FOUND);
+
+    // Only one garbage collection runs at a time
+    let (status, body) = gc(&ctx, None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "conflict");
+}
@@ -0,0 +1,189 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Registry projects notify us of pushed images, which are recorded on the
+//! stage of the learner. These tests need a disposable PostgreSQL database,
+//! run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test registry-webhook-tests -- --ignored
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::Path,
+    http::{Request, StatusCode, header},
+    routing::{delete, get, head},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::DateTime;
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    repository::{CourseRepository, StageRepository},
+    routes,
+    service::{CourseService, RegistryService},
+    utils::crypto,
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{create_course, create_user, enroll_user, mock_context};
+
+/// A Harbor server recording the webhook policies of its projects.
+#[derive(Clone, Default)]
+struct MockHarbor {
+    policies: Arc<Mutex<Vec<Value>>>,
+}
+
+impl MockHarbor {
+    fn router(&self) -> Router {
+        let (listed, created, deleted) = (self.clone(), self.clone(), self.clone());
+        Router::new()
+                .route("/api/v2.0/projects", head(|| async { StatusCode::OK }))
+                .route(
+                    "/api/v2.0/projects/{project}/webhook/policies",
+                    get(move || async move {
+                        Json(Value::from(listed.policies.lock().unwrap().clone()))
+                    })
+                    .post(move |Json(mut body): Json<Value>| async move {
+                        let mut policies = created.policies.lock().unwrap();
+                        body["id"] = json!(policies.len() + 10);
+                        policies.push(body);
+                        StatusCode::CREATED
+                    }),
+                )
+                .route(
+                    "/api/v2.0/projects/{project}/webhook/policies/{id}",
+                    delete(move |Path((_, id)): Path<(String, i64)>| async move {
+                        deleted.policies.lock().unwrap().retain(|policy| policy["id"] != id);
+                        StatusCode::OK
+                    }),
+                )
+    }
+}
+
+async fn context(harbor: &MockHarbor, registry_webhooks: bool) -> Arc<Context> {
+    let mut ctx = mock_context(harbor.router()).await;
+    ctx.config.registry_webhooks = registry_webhooks;
+    Arc::new(ctx)
+}
+
+fn admin_auth(ctx: &Context) -> String {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    format!("Basic {}", STANDARD.encode(format!("admin:{password}")))
+}
+
+/// Delivers a push event of an image to the webhook handler.
+async fn push(ctx: &Arc<Context>, auth: Option<String>, repository: &str) -> StatusCode {
+    let event = json!({
+        "type": "PUSH_ARTIFACT",
+        "occur_at": 1748772000,
+        "operator": "robot$stackclass+pipeline",
+        "event_data": {
+            "resources": [{
+                "digest": "sha256:0123",
+                "tag": "latest",
+                "resource_url": format!("docker.local/stackclass/{repository}:latest")
+            }],
+            "repository": {
+                "name": repository,
+                "namespace": "stackclass",
+                "repo_full_name": format!("stackclass/{repository}"),
+                "repo_type": "public"
+            }
+        }
+    });
+
+    let mut req =
+        Request::post("/v1/webhooks/harbor").header(header::CONTENT_TYPE, "application/json");
+    if let Some(auth) = auth {
+        req = req.header(header::AUTHORIZATION, auth);
+    }
+    let req = req.body(Body::from(event.to_string())).unwrap();
+    routes::build(ctx.clone()).oneshot(req).await.unwrap().status()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_webhook_policy_is_set_up_once() {
+    let harbor = MockHarbor::default();
+    let ctx = context(&harbor, true).await;
+
+    // A policy left by a previous endpoint is replaced
+    let stale = json!({
+        "id": 1,
+        "name": "stackclass",
+        "targets": [{ "type": "http", "address": "http://old.local/v1/webhooks/harbor" }],
+        "event_types": ["PUSH_ARTIFACT"],
+        "enabled": true
+    });
+    harbor.policies.lock().unwrap().push(stale);
+
+    RegistryService::ensure_project(&ctx, "stackclass").await.unwrap();
+    RegistryService::ensure_project(&ctx, "stackclass").await.unwrap();
+
+    let policies = harbor.policies.lock().unwrap().clone();
+    assert_eq!(policies.len(), 1);
+    let target = &policies[0]["targets"][0];
+    assert_eq!(target["address"], "http://api.local/v1/webhooks/harbor");
+    assert_eq!(target["auth_header"], admin_auth(&ctx));
+    assert_eq!(policies[0]["event_types"], json!(["PUSH_ARTIFACT"]));
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_webhook_policy_is_opt_in() {
+    let harbor = MockHarbor::default();
+    let ctx = context(&harbor, false).await;
+
+    RegistryService::ensure_project(&ctx, "stackclass").await.unwrap();
+    assert!(harbor.policies.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_push_is_recorded_on_current_stage() {
+    let ctx = context(&MockHarbor::default(), false).await;
+    let slug = create_course(&ctx).await;
+    let user_id = create_user(&ctx).await;
+    let id = enroll_user(&ctx, &user_id, &slug).await;
+    let mut user_course =
+        CourseRepository::get_user_course_by_id(&ctx.database, &id).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+
+    let stage = format!("{slug}-s1");
+    let pushed_at = || async {
+        let user_stage =
+            StageRepository::get_user_stage(&ctx.database, &user_id, &slug, &stage).await.unwrap();
+        user_stage.image_pushed_at
+    };
+
+    // Only the admin may deliver events, and test images are not recorded
+    assert_eq!(push(&ctx, None, &id.to_string()).await, StatusCode::UNAUTHORIZED);
+    let status = push(&ctx, Some(admin_auth(&ctx)), &format!("{id}-test")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(pushed_at().await, None);
+
+    let status = push(&ctx, Some(admin_auth(&ctx)), &id.to_string()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(pushed_at().await, DateTime::from_timestamp(1748772000, 0));
+
+    // Unknown repositories are skipped
+    let status = push(&ctx, Some(admin_auth(&ctx)), &Uuid::now_v7().to_string()).await;
+    assert_eq!(status, StatusCode::OK);
+}
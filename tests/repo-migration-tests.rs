@@ -0,0 +1,164 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Imports of learner repositories from another Gitea instance. These tests
+//! need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test repo-migration-tests -- --ignored
+
+#![recursion_limit = "256"]
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::Path,
+    http::{Method, Request, StatusCode, header},
+    routing::{get, post},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context, repository::CourseRepository, routes, service::RepoMigrationService,
+    utils::crypto,
+};
+use tower::ServiceExt;
+
+use common::{TIMESTAMP, create_course, enroll, mock_context, repository};
+
+/// A Gitea server accepting migrations of the source repositories named
+/// "ok", and rejecting the credentials for all others. Returns the received
+/// migration requests with the context using it.
+async fn gitea_server() -> (Context, Arc<Mutex<Vec<Value>>>) {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let requests = received.clone();
+    let app = Router::new()
+        .route(
+            "/api/v1/repos/migrate",
+            post(move |Json(req): Json<Value>| async move {
+                requests.lock().unwrap().push(req.clone());
+                let clone_addr = req["clone_addr"].as_str().unwrap();
+                if clone_addr.ends_with("/ok.git") {
+                    let (owner, name) = (&req["repo_owner"], &req["repo_name"]);
+                    let mut repo = repository(owner.as_str().unwrap(), name.as_str().unwrap());
+                    repo["original_url"] = json!(clone_addr);
+                    (StatusCode::CREATED, Json(repo))
+                } else {
+                    let message = "Authentication failed: Remote Gitea rejected the credentials.";
+                    (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({ "message": message })))
+                }
+            }),
+        )
+        .route(
+            "/api/v1/orgs/{org}/hooks",
+            get(|| async { Json(json!([])) }).post(
+                |Path(_): Path<String>, Json(req): Json<Value>| async move {
+                    let hook = json!({
+                        "active": true,
+                        "config": req["config"],
+                        "created_at": TIMESTAMP,
+                        "events": req["events"],
+                        "id": 1,
+                        "type": req["type"],
+                        "updated_at": TIMESTAMP
+                    });
+                    (StatusCode::CREATED, Json(hook))
+                },
+            ),
+        );
+
+    (mock_context(app).await, received)
+}
+
+async fn send(ctx: &Arc<Context>, method: Method, uri: &str, body: Option<Value>) -> Value {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let auth = format!("Basic {}", STANDARD.encode(format!("admin:{password}")));
+    let mut req = Request::builder().method(method).uri(uri).header(header::AUTHORIZATION, auth);
+    let body = match body {
+        Some(body) => {
+            req = req.header(header::CONTENT_TYPE, "application/json");
+            Body::from(body.to_string())
+        }
+        None => Body::empty(),
+    };
+
+    let app = routes::build(ctx.clone());
+    let res = app.oneshot(req.body(body).unwrap()).await.unwrap();
+    assert!(res.status().is_success(), "{}", res.status());
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_migration_is_tracked_and_rerun() {
+    let (ctx, received) = gitea_server().await;
+    let ctx = Arc::new(ctx);
+    let db = &ctx.database;
+
+    // Start from an empty queue, the database may be shared with other runs
+    sqlx::query("UPDATE repo_migrations SET status = 'failed' WHERE status = 'pending'")
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+    let slug = create_course(&ctx).await;
+    let users = [enroll(&ctx, &slug).await, enroll(&ctx, &slug).await];
+    let mut ids = Vec::new();
+    for user_id in &users {
+        ids.push(CourseRepository::get_user_course(db, user_id, &slug).await.unwrap().id);
+    }
+
+    let uri = "/v1/admin/migrations/repositories";
+    let body = json!({
+        "source_url": "https://old.example.com/",
+        "token": "secret",
+        "repositories": { "learners/ok": ids[0], "learners/denied": ids[1] }
+    });
+    let res = send(&ctx, Method::POST, uri, Some(body.clone())).await;
+    assert_eq!(res["queued"], 2);
+
+    let user_course = CourseRepository::get_user_course(db, &users[0], &slug).await.unwrap();
+    assert_eq!(user_course.repository_status, "migrating");
+
+    assert_eq!(RepoMigrationService::migrate_pending(ctx.clone()).await.unwrap(), 2);
+    {
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert!(received.iter().all(|req| req["auth_token"] == "secret"));
+        assert!(received.iter().all(|req| req["mirror"] == false));
+    }
+
+    // The enrollments reflect the outcome of their migration
+    for (user_id, status) in users.iter().zip(["ready", "failed"]) {
+        let user_course = CourseRepository::get_user_course(db, user_id, &slug).await.unwrap();
+        assert_eq!(user_course.repository_status, status);
+    }
+
+    let report = send(&ctx, Method::GET, &format!("{uri}?status=failed"), None).await;
+    let failed = report["repositories"].as_array().unwrap();
+    let failed = failed.iter().find(|m| m["user_course_id"] == ids[1].to_string()).unwrap();
+    assert_eq!(failed["source_repo"], "learners/denied");
+    assert!(failed["error"].as_str().unwrap().contains("Authentication failed"));
+
+    // Re-runs skip the repositories already migrated
+    let res = send(&ctx, Method::POST, uri, Some(body)).await;
+    assert_eq!(res["queued"], 1);
+    assert_eq!(res["skipped"], json!(["learners/ok"]));
+    assert_eq!(RepoMigrationService::migrate_pending(ctx.clone()).await.unwrap(), 1);
+    assert_eq!(received.lock().unwrap().len(), 3);
+}
@@ -0,0 +1,262 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Enrollments take pre-provisioned repositories from the pool. These tests
+//! need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test repo-pool-tests -- --ignored
+
+#![recursion_limit = "256"]
+
+mod common;
+
+use std::{
+    collections::HashSet,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::{Path, State},
+    http::{Request, StatusCode, header},
+    routing::{get, post},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    model::PooledRepoModel,
+    repository::{CourseRepository, RepoPoolRepository},
+    request::CreateUserCourseRequest,
+    routes,
+    service::{CourseService, RepoPoolService},
+    utils::crypto,
+};
+use tower::ServiceExt;
+
+use common::{TIMESTAMP, create_course, create_user, mock_context, repository};
+
+/// The repositories, generations and protected repositories of the fake
+/// Gitea server.
+#[derive(Clone, Default)]
+struct Gitea {
+    repos: Arc<Mutex<HashSet<String>>>,
+    generated: Arc<AtomicUsize>,
+    protected: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Gitea {
+    fn names(&self) -> HashSet<String> {
+        self.repos.lock().unwrap().clone()
+    }
+
+    fn protected(&self) -> HashSet<String> {
+        self.protected.lock().unwrap().clone()
+    }
+}
+
+fn protection() -> Value {
+    json!({
+        "rule_name": "main",
+        "enable_push": true,
+        "enable_push_whitelist": false,
+        "push_whitelist_usernames": null,
+        "enable_force_push": true,
+        "enable_force_push_allowlist": true,
+        "force_push_allowlist_usernames": ["admin"],
+        "created_at": TIMESTAMP,
+        "updated_at": TIMESTAMP
+    })
+}
+
+/// A Gitea server keeping repositories in memory, which can be generated,
+/// renamed and deleted, with the context using it.
+async fn gitea_server() -> (Context, Gitea) {
+    let gitea = Gitea::default();
+    let not_found = || (StatusCode::NOT_FOUND, Json(json!({ "message": "not found" })));
+
+    let app = Router::new()
+        .route(
+            "/api/v1/repos/{owner}/{repo}",
+            get(move |State(gitea): State<Gitea>, Path((owner, repo)): Path<(String, String)>| {
+                async move {
+                    match gitea.repos.lock().unwrap().contains(&repo) {
+                        true => (StatusCode::OK, Json(repository(&owner, &repo))),
+                        false => not_found(),
+                    }
+                }
+            })
+            .patch(
+                move |State(gitea): State<Gitea>,
+                      Path((owner, repo)): Path<(String, String)>,
+                      Json(req): Json<Value>| async move {
+                    let name = req["name"].as_str().unwrap().to_string();
+                    let mut repos = gitea.repos.lock().unwrap();
+                    match repos.remove(&repo) {
+                        true => {
+                            repos.insert(name.clone());
+                            (StatusCode::OK, Json(repository(&owner, &name)))
+                        }
+                        false => not_found(),
+                    }
+                },
+            )
+            .delete(
+                |State(gitea): State<Gitea>, Path((_, repo)): Path<(String, String)>| async move {
+                    match gitea.repos.lock().unwrap().remove(&repo) {
+                        true => StatusCode::NO_CONTENT,
+                        false => StatusCode::NOT_FOUND,
+                    }
+                },
+            ),
+        )
+        .route(
+            "/api/v1/repos/{owner}/{repo}/branch_protections",
+            post(|State(gitea): State<Gitea>, Path((_, repo)): Path<(String, String)>| async move {
+                gitea.protected.lock().unwrap().insert(repo);
+                (StatusCode::CREATED, Json(protection()))
+            }),
+        )
+        .route(
+            "/api/v1/repos/{owner}/{repo}/branch_protections/{name}",
+            get(
+                move |State(gitea): State<Gitea>,
+                      Path((_, repo, _)): Path<(String, String, String)>| async move {
+                    match gitea.protected.lock().unwrap().contains(&repo) {
+                        true => (StatusCode::OK, Json(protection())),
+                        false => not_found(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/api/v1/repos/{owner}/{template}/generate",
+            post(|State(gitea): State<Gitea>, Json(req): Json<Value>| async move {
+                let (owner, name) = (req["owner"].as_str().unwrap(), req["name"].as_str().unwrap());
+                gitea.generated.fetch_add(1, Ordering::SeqCst);
+                gitea.repos.lock().unwrap().insert(name.to_string());
+                (StatusCode::CREATED, Json(repository(owner, name)))
+            }),
+        )
+        .with_state(gitea.clone());
+
+    (mock_context(app).await, gitea)
+}
+
+async fn preprovision(ctx: &Arc<Context>, slug: &str, count: u32) -> Value {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let auth = format!("Basic {}", STANDARD.encode(format!("admin:{password}")));
+    let req = Request::post(format!("/v1/admin/courses/{slug}/preprovision"))
+        .header(header::AUTHORIZATION, auth)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(json!({ "count": count }).to_string()))
+        .unwrap();
+
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+/// Enrolls a new user in the course and returns the name of the repository.
+async fn enroll(ctx: &Arc<Context>, slug: &str) -> String {
+    let user_id = create_user(ctx).await;
+    let req = CreateUserCourseRequest {
+        course_slug: slug.to_string(),
+        proficiency: "beginner".into(),
+        cadence: "weekly".into(),
+        accountability: false,
+        cohort: None,
+        cohort_deadline: None,
+        language: None,
+    };
+    CourseService::create_user_course(ctx.clone(), &user_id, &req).await.unwrap();
+
+    let user_course = CourseRepository::get_user_course(&ctx.database, &user_id, slug).await;
+    user_course.unwrap().id.to_string()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_last_pooled_repo_is_claimed_once() {
+    let (ctx, gitea) = gitea_server().await;
+    let ctx = Arc::new(ctx);
+    let db = &ctx.database;
+    let slug = create_course(&ctx).await;
+
+    // Concurrent claims never take the same repository
+    let course = CourseRepository::get_by_slug(db, &slug).await.unwrap();
+    RepoPoolRepository::create(db, &PooledRepoModel::new(course.id, "")).await.unwrap();
+    let cutoff = RepoPoolService::cutoff(&ctx);
+    let (a, b) = tokio::join!(
+        RepoPoolRepository::claim(db, &slug, "a", cutoff),
+        RepoPoolRepository::claim(db, &slug, "b", cutoff)
+    );
+    assert_eq!(a.unwrap().is_some() as u8 + b.unwrap().is_some() as u8, 1);
+
+    // Two enrollments race for the last pooled repository
+    let body = preprovision(&ctx, &slug, 1).await;
+    assert_eq!(body, json!({ "created": 1, "available": 1 }));
+    let (a, b) = tokio::join!(enroll(&ctx, &slug), enroll(&ctx, &slug));
+
+    // One renamed the pooled repository, the other generated its own
+    assert_eq!(gitea.generated.load(Ordering::SeqCst), 2);
+    assert_eq!(gitea.names(), HashSet::from([a.clone(), b.clone()]));
+    assert!(gitea.protected().is_superset(&gitea.names()));
+
+    let claimed_as: Vec<Option<String>> =
+        sqlx::query_scalar("SELECT claimed_as FROM repo_pool WHERE course_id = $1 AND claimed")
+            .bind(course.id)
+            .fetch_all(db.pool())
+            .await
+            .unwrap();
+    assert_eq!(claimed_as.len(), 2);
+    let learners = [Some(a), Some(b)];
+    assert_eq!(claimed_as.iter().filter(|name| learners.contains(name)).count(), 1);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_enrollment_falls_back_to_generation() {
+    let (ctx, gitea) = gitea_server().await;
+    let ctx = Arc::new(ctx);
+    let slug = create_course(&ctx).await;
+
+    // Without a pool the repository is generated live
+    let first = enroll(&ctx, &slug).await;
+    assert_eq!(gitea.generated.load(Ordering::SeqCst), 1);
+    assert!(gitea.names().contains(&first));
+    assert!(gitea.protected().contains(&first));
+
+    // Filling the pool tops it up to the requested size
+    assert_eq!(preprovision(&ctx, &slug, 2).await, json!({ "created": 2, "available": 2 }));
+    assert_eq!(preprovision(&ctx, &slug, 1).await, json!({ "created": 0, "available": 2 }));
+    assert_eq!(gitea.generated.load(Ordering::SeqCst), 3);
+
+    // A template sync makes the pool stale, it is skipped and reaped
+    sqlx::query("UPDATE courses SET commit_sha = 'updated' WHERE slug = $1")
+        .bind(&slug)
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+    let second = enroll(&ctx, &slug).await;
+    assert_eq!(gitea.generated.load(Ordering::SeqCst), 4);
+
+    assert_eq!(RepoPoolService::reap_stale(ctx.clone()).await.unwrap(), 2);
+    assert_eq!(gitea.names(), HashSet::from([first, second]));
+}
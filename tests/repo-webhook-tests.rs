@@ -0,0 +1,125 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! With repository webhooks, the webhook of the organization is moved to
+//! its repositories. These tests need a disposable PostgreSQL database, run
+//! them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test repo-webhook-tests -- --ignored
+
+#![recursion_limit = "256"]
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    Json, Router,
+    extract::Path,
+    http::StatusCode,
+    routing::{delete, get},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+use stackclass::{context::Context, service::RepoService, utils::crypto};
+
+use common::{TIMESTAMP, mock_api, repository, setup, unreachable_cluster};
+
+/// Our webhook as Gitea returns it.
+fn hook(ctx: &Context, id: u64) -> Value {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    json!({
+        "active": true,
+        "authorization_header": format!("Basic {}", STANDARD.encode(format!("admin:{password}"))),
+        "branch_filter": "main",
+        "config": { "content_type": "json", "url": ctx.endpoints.webhook_url("gitea") },
+        "created_at": TIMESTAMP,
+        "events": ["push"],
+        "id": id,
+        "type": "gitea",
+        "updated_at": TIMESTAMP,
+    })
+}
+
+/// The calls received by the fake Gitea server.
+type Calls = Arc<Mutex<Vec<String>>>;
+
+/// A Gitea server whose organization has our webhook and two repositories,
+/// one of them hooked already.
+async fn context(repo_webhooks: bool) -> (Arc<Context>, Calls) {
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    ctx.config.repo_webhooks = repo_webhooks;
+    let calls = Calls::default();
+
+    let (org_hook, repo_hook) = (hook(&ctx, 1), hook(&ctx, 2));
+    let (created, deleted) = (calls.clone(), calls.clone());
+    let app = Router::new()
+        .route("/api/v1/orgs/{org}/hooks", get(move || async move { Json(vec![org_hook]) }))
+        .route(
+            "/api/v1/orgs/{org}/hooks/{id}",
+            delete(move |Path((org, id)): Path<(String, u64)>| async move {
+                deleted.lock().unwrap().push(format!("delete {org} hook {id}"));
+                StatusCode::NO_CONTENT
+            }),
+        )
+        .route(
+            "/api/v1/orgs/{org}/repos",
+            get(|Path(org): Path<String>| async move {
+                Json(vec![repository(&org, "hooked"), repository(&org, "bare")])
+            }),
+        )
+        .route(
+            "/api/v1/repos/{owner}/{repo}/hooks",
+            get(move |Path((_, repo)): Path<(String, String)>| async move {
+                Json(if repo == "hooked" { vec![repo_hook] } else { vec![] })
+            })
+            .post(
+                move |Path((owner, repo)): Path<(String, String)>, Json(body): Json<Value>| {
+                    let created = created.clone();
+                    async move {
+                        created.lock().unwrap().push(format!("create {owner}/{repo} hook"));
+                        let mut hook = body;
+                        hook["id"] = json!(3);
+                        hook["created_at"] = json!(TIMESTAMP);
+                        hook["updated_at"] = json!(TIMESTAMP);
+                        (StatusCode::CREATED, Json(hook))
+                    }
+                },
+            ),
+        );
+
+    mock_api(&mut ctx, app).await;
+    (Arc::new(ctx), calls)
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_org_webhook_moves_to_repositories() {
+    let (ctx, calls) = context(true).await;
+    RepoService::new(ctx.clone()).setup_webhooks("stackclass").await.unwrap();
+
+    let calls = calls.lock().unwrap().clone();
+    assert_eq!(calls, ["create stackclass/bare hook", "delete stackclass hook 1"]);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_org_webhook_is_kept_by_default() {
+    let (ctx, calls) = context(false).await;
+    let repos = RepoService::new(ctx.clone());
+    repos.setup_webhooks("stackclass").await.unwrap();
+    repos.attach_webhook("stackclass", "bare").await.unwrap();
+
+    assert!(calls.lock().unwrap().is_empty());
+}
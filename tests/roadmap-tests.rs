@@ -0,0 +1,70 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The roadmap agrees with the stages progression actually unlocks. These
+//! tests need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test roadmap-tests -- --ignored
+
+mod common;
+
+use stackclass::{
+    errors::ApiError,
+    repository::CourseRepository,
+    response::StageState::*,
+    service::{CourseService, RoadmapService, StageService},
+};
+
+use common::{create_course, create_user, enroll, setup, unreachable_cluster};
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_roadmap_follows_progression() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+
+    let roadmap = RoadmapService::get(ctx.clone(), &user_id, &slug).await.unwrap();
+    let states: Vec<_> = roadmap.stages.iter().map(|stage| stage.state).collect();
+    assert_eq!(states, vec![Unlocked, LockedPrerequisite, LockedExtension]);
+    assert_eq!(roadmap.percent_complete, 0.0);
+
+    let mut user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+
+    // The current stage is always the one completion accepts next
+    for _ in 0..3 {
+        let roadmap = RoadmapService::get(ctx.clone(), &user_id, &slug).await.unwrap();
+        let current = roadmap.stages.iter().find(|stage| stage.state == Current).unwrap();
+        StageService::complete(ctx.clone(), &user_id, &slug, &current.slug, None).await.unwrap();
+
+        let user_course =
+            CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+        let roadmap = RoadmapService::get(ctx.clone(), &user_id, &slug).await.unwrap();
+        if let Some(next) = roadmap.stages.iter().find(|stage| stage.state == Current) {
+            assert_eq!(user_course.current_stage_slug.as_deref(), Some(next.slug.as_str()));
+        }
+    }
+
+    let roadmap = RoadmapService::get(ctx.clone(), &user_id, &slug).await.unwrap();
+    assert!(roadmap.stages.iter().all(|stage| stage.state == Completed));
+    assert_eq!((roadmap.completed_stage_count, roadmap.remaining_stage_count), (3, 0));
+    assert_eq!(roadmap.percent_complete, 100.0);
+
+    // Learners not enrolled in the course have no roadmap
+    let stranger = create_user(&ctx).await;
+    let res = RoadmapService::get(ctx.clone(), &stranger, &slug).await;
+    assert!(matches!(res, Err(ApiError::NotFound)));
+}
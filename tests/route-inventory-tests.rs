@@ -0,0 +1,126 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Every route declares its authentication and is documented. The tests
+//! serving requests need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test route-inventory-tests -- --ignored
+
+mod common;
+
+use axum::{
+    Router,
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::Value;
+use stackclass::{
+    extractor::Claims,
+    router::{self, Auth, Route},
+    routes,
+    swagger::ApiDoc,
+    utils::crypto,
+};
+use tower::ServiceExt;
+use utoipa::OpenApi;
+
+use common::{create_user, setup, token, unreachable_cluster};
+
+#[test]
+fn test_routes_match_openapi_paths() {
+    let doc = ApiDoc::openapi();
+    let table = routes::table();
+
+    let undocumented: Vec<_> = table
+        .iter()
+        .filter(|route| route.documented(&doc) == route.is_hidden())
+        .map(|route| format!("{} {}", route.method(), route.path()))
+        .collect();
+    assert!(undocumented.is_empty(), "Undocumented or hidden but documented: {undocumented:?}");
+
+    let methods = [Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE];
+    let mut orphaned = Vec::new();
+    for (path, item) in &doc.paths.paths {
+        for method in methods.iter().filter(|method| router::operation(item, method).is_some()) {
+            if !table
+                .iter()
+                .any(|route| route.openapi_path() == *path && route.method() == method.as_str())
+            {
+                orphaned.push(format!("{method} {path}"));
+            }
+        }
+    }
+    assert!(orphaned.is_empty(), "Documented but not routed: {orphaned:?}");
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "Route GET /v1/user/me is declared public, but its handler requires jwt")]
+fn test_mismatched_auth_panics() {
+    async fn me(claims: Claims) -> String {
+        claims.id
+    }
+    Route::get("/v1/user/me", Auth::Public, me);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_declared_auth_is_enforced() {
+    let ctx = setup(unreachable_cluster()).await;
+
+    // The handler takes no extractor, the route layer checks the token
+    let routes = vec![Route::get("/v1/user/ping", Auth::Jwt, || async { "pong" })];
+    let app: Router = router::build(ctx.clone(), routes).with_state(ctx.clone());
+
+    let req = Request::get("/v1/user/ping").body(Body::empty()).unwrap();
+    let res = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let bearer = format!("Bearer {}", token(&ctx, &create_user(&ctx).await).await);
+    let req = Request::get("/v1/user/ping").header(header::AUTHORIZATION, bearer);
+    let res = app.oneshot(req.body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_route_inventory() {
+    let ctx = setup(unreachable_cluster()).await;
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let auth = format!("Basic {}", STANDARD.encode(format!("admin:{password}")));
+
+    let req = Request::get("/v1/admin/routes").body(Body::empty()).unwrap();
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let req = Request::get("/v1/admin/routes").header(header::AUTHORIZATION, auth);
+    let res = routes::build(ctx.clone()).oneshot(req.body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let routes: Vec<Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(routes.len(), routes::table().len());
+
+    let find = |method: &str, path: &str| {
+        routes.iter().find(|r| r["method"] == method && r["path"] == path).unwrap().clone()
+    };
+    let route = find("GET", "/v1/admin/routes");
+    assert_eq!(
+        (route["auth"].as_str(), route["documented"].as_bool()),
+        (Some("admin_basic"), Some(true))
+    );
+    assert_eq!(find("PATCH", "/v1/courses/{slug}")["auth"], "instructor");
+    assert_eq!(find("GET", "/v1/user/courses")["auth"], "jwt");
+    assert_eq!(find("ANY", "/{uuid}/{*path}")["documented"], false);
+}
@@ -0,0 +1,138 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Course settings resolved from the instance defaults, course.yml and the
+//! overrides of admins. These tests need a disposable PostgreSQL database,
+//! run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test settings-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    repository::CourseRepository,
+    routes,
+    service::{CourseService, StageService},
+    utils::crypto,
+};
+use tower::ServiceExt;
+
+use common::{create_course, enroll, setup, unreachable_cluster};
+
+async fn send(ctx: &Arc<Context>, method: Method, uri: &str, body: Value) -> (StatusCode, Value) {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(
+            header::AUTHORIZATION,
+            format!("Basic {}", STANDARD.encode(format!("admin:{password}"))),
+        )
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_settings_precedence() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let effective = format!("/v1/courses/{slug}/effective-settings");
+
+    // Without any setting, the course gets the built-in values
+    let (status, body) = send(&ctx, Method::GET, &effective, Value::Null).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["max_attempts"], Value::Null);
+    assert_eq!(body["resources"], "small");
+    assert_eq!(body["tester_image"], format!("ghcr.io/stackclass/{slug}-tester"));
+
+    // The instance defaults apply to courses which set nothing, without a sync
+    let defaults = json!({ "tester_image": "registry.local/{course}-tester" });
+    let (status, body) = send(&ctx, Method::PUT, "/v1/admin/course-defaults", defaults).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "tester_image": "registry.local/{course}-tester" }));
+    let (_, body) = send(&ctx, Method::GET, &effective, Value::Null).await;
+    assert_eq!(body["tester_image"], format!("registry.local/{slug}-tester"));
+
+    // Values of course.yml take precedence over the defaults
+    sqlx::query("UPDATE courses SET max_attempts = 3, tester_image = 'pinned' WHERE slug = $1")
+        .bind(&slug)
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+    let (_, body) = send(&ctx, Method::GET, &effective, Value::Null).await;
+    assert_eq!(body["max_attempts"], 3);
+    assert_eq!(body["tester_image"], "pinned");
+    assert_eq!(body["course"], json!({ "max_attempts": 3, "tester_image": "pinned" }));
+
+    // Overrides take precedence over course.yml, and bound the attempts
+    let overrides = format!("/v1/admin/courses/{slug}/setting-overrides");
+    let (status, body) =
+        send(&ctx, Method::PUT, &overrides, json!({ "max_attempts": 1, "resources": "large" }))
+            .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["max_attempts"], 1);
+    assert_eq!(body["resources"], "large");
+    assert_eq!(body["tester_image"], "pinned");
+
+    let user_id = enroll(&ctx, &slug).await;
+    let mut user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+    let stage = StageService::get_user_stage(ctx.clone(), &user_id, &slug, &format!("{slug}-s1"))
+        .await
+        .unwrap();
+    assert_eq!(stage.remaining_attempts, Some(1));
+
+    // Clearing the defaults leaves the other courses of the database alone
+    let (status, body) = send(&ctx, Method::PUT, "/v1/admin/course-defaults", json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({}));
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_invalid_settings() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let overrides = format!("/v1/admin/courses/{slug}/setting-overrides");
+
+    let (status, _) = send(&ctx, Method::PUT, &overrides, json!({ "max_attempts": 0 })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = send(&ctx, Method::PUT, &overrides, json!({ "resources": "huge" })).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, _) = send(&ctx, Method::PUT, &overrides, json!({ "max_attemps": 2 })).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, _) =
+        send(&ctx, Method::PUT, "/v1/admin/courses/missing/setting-overrides", json!({})).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,183 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Snapshots of learner repositories at the commit which completed a stage.
+//! These tests need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test snapshot-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    body::Body,
+    extract::Path,
+    http::{Request, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use flate2::{Compression, write::GzEncoder};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    repository::CourseRepository,
+    routes,
+    service::{CourseService, StageService},
+    utils::crypto,
+};
+use tar::{Builder, Header};
+use tower::ServiceExt;
+
+use common::{create_course, create_user, enroll, mock_context, token};
+
+/// Commit the mocked Gitea still has an archive of.
+const COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+
+/// Builds the tarball Gitea serves for [`COMMIT`].
+fn tarball() -> Vec<u8> {
+    let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for (path, data) in [("repo/README.md", "# Hello"), ("repo/src/main.rs", "fn main() {}")] {
+        let mut header = Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, data.as_bytes()).unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+/// Starts a Gitea server serving the archive of [`COMMIT`] only, and returns
+/// a context using it.
+async fn context() -> Arc<Context> {
+    let app = Router::new().route(
+        "/api/v1/repos/{owner}/{repo}/archive/{archive}",
+        get(|Path((_, _, archive)): Path<(String, String, String)>| async move {
+            match archive == format!("{COMMIT}.tar.gz") {
+                true => tarball().into_response(),
+                false => (StatusCode::NOT_FOUND, "{}").into_response(),
+            }
+        }),
+    );
+
+    Arc::new(mock_context(app).await)
+}
+
+fn admin(ctx: &Context) -> String {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    format!("Basic {}", STANDARD.encode(format!("admin:{password}")))
+}
+
+async fn send(ctx: &Arc<Context>, uri: &str, authorization: &str) -> Response {
+    let req = Request::get(uri).header(header::AUTHORIZATION, authorization).body(Body::empty());
+    routes::build(ctx.clone()).oneshot(req.unwrap()).await.unwrap()
+}
+
+async fn read(res: Response) -> (StatusCode, Value) {
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_snapshot_of_completed_stage() {
+    let ctx = context().await;
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let admin = admin(&ctx);
+
+    let mut user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+    let stage_slug = format!("{slug}-s1");
+    StageService::complete(ctx.clone(), &user_id, &slug, &stage_slug, Some(COMMIT)).await.unwrap();
+
+    let stage = StageService::get_user_stage(ctx.clone(), &user_id, &slug, &stage_slug).await;
+    assert_eq!(stage.unwrap().completed_commit.as_deref(), Some(COMMIT));
+
+    // The tarball is streamed as Gitea built it
+    let uri = format!("/v1/admin/users/{user_id}/courses/{slug}/stages/{stage_slug}/snapshot");
+    let res = send(&ctx, &uri, &admin).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[header::CONTENT_TYPE], "application/gzip");
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(body.to_vec(), tarball());
+
+    // The tree lists its files without the directory of the repository
+    let (status, body) = read(send(&ctx, &format!("{uri}/tree"), &admin).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body,
+        json!({
+            "commit": COMMIT,
+            "files": [
+                { "path": "README.md", "size": 7 },
+                { "path": "src/main.rs", "size": 12 },
+            ]
+        })
+    );
+
+    // Each read is audited
+    let reads: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM audit_logs WHERE action = 'read_snapshot' AND target = $1",
+    )
+    .bind(format!("users/{user_id}/courses/{slug}/stages/{stage_slug}"))
+    .fetch_one(ctx.database.pool())
+    .await
+    .unwrap();
+    assert_eq!(reads, 2);
+
+    // Learners who do not maintain the course cannot read it
+    let bearer = format!("Bearer {}", token(&ctx, &create_user(&ctx).await).await);
+    assert_eq!(send(&ctx, &uri, &bearer).await.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_snapshot_unavailable() {
+    let ctx = context().await;
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let admin = admin(&ctx);
+
+    let mut user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+    let uri = |stage: &str| {
+        format!("/v1/admin/users/{user_id}/courses/{slug}/stages/{slug}-{stage}/snapshot")
+    };
+
+    // A stage completed without a recorded commit has no snapshot
+    StageService::complete(ctx.clone(), &user_id, &slug, &format!("{slug}-s1"), None)
+        .await
+        .unwrap();
+    assert_eq!(send(&ctx, &uri("s1"), &admin).await.status(), StatusCode::NOT_FOUND);
+
+    // Nor does a stage which was never started
+    assert_eq!(send(&ctx, &uri("e1"), &admin).await.status(), StatusCode::NOT_FOUND);
+
+    // A commit Gitea no longer has is gone
+    StageService::complete(ctx.clone(), &user_id, &slug, &format!("{slug}-s2"), Some("deadbeef"))
+        .await
+        .unwrap();
+    let (status, body) = read(send(&ctx, &format!("{}/tree", uri("s2")), &admin).await).await;
+    assert_eq!(status, StatusCode::GONE);
+    assert_eq!(
+        body,
+        json!({ "message": "Commit deadbeef is no longer available in the repository" })
+    );
+}
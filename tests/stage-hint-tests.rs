@@ -0,0 +1,149 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Learners reveal the hints of a stage one at a time. The tests unlocking
+//! hints need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test stage-hint-tests -- --ignored
+
+mod common;
+
+use std::{fs, path::Path, sync::Arc};
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context, repository::CourseRepository, routes, schema, service::CourseService,
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{enroll, setup, token, unreachable_cluster};
+
+fn write(path: &Path, content: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+/// Lays out a course of two stages, with the given files in the hints
+/// directory of the first one.
+fn write_course(root: &Path, slug: &str, hints: &[(&str, &str)]) {
+    write(
+        &root.join("course.yml"),
+        &format!(
+            "slug: {slug}\nname: Redis\nshort_name: Redis\nrelease_status: beta\n\
+             description: A Redis clone\nsummary: Redis\n"
+        ),
+    );
+    for (dir, stage) in [("stages/01-ping", "ping"), ("stages/02-echo", "echo")] {
+        let yml = format!(
+            "slug: {slug}-{stage}\nname: {stage}\ndifficulty: easy\ndescription: {stage}\n"
+        );
+        write(&root.join(dir).join("stage.yml"), &yml);
+        write(&root.join(dir).join("instruction.md"), "Reply");
+    }
+
+    let _ = fs::remove_dir_all(root.join("stages/01-ping/hints"));
+    for (name, content) in hints {
+        write(&root.join("stages/01-ping/hints").join(name), content);
+    }
+}
+
+/// Syncs the course laid out in the directory, inserting it first.
+async fn sync_course(ctx: &Arc<Context>, slug: &str, root: &Path) {
+    sqlx::query(
+        r#"
+        INSERT INTO courses (id, slug, name, short_name, release_status, description, summary, repository)
+        VALUES ($1, $2, $2, $2, 'beta', '', '', 'https://github.com/stackclass/redis')
+        ON CONFLICT (slug) DO NOTHING
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(slug)
+    .execute(ctx.database.pool())
+    .await
+    .unwrap();
+
+    let course = schema::parse(root).unwrap();
+    CourseService::update_course(ctx.clone(), &course, "main", "admin").await.unwrap();
+}
+
+async fn call(ctx: &Arc<Context>, user_id: &str, method: Method, uri: &str) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token(ctx, user_id).await))
+        .body(Body::empty())
+        .unwrap();
+
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[test]
+fn test_parse_hints() {
+    let root = tempfile::tempdir().unwrap();
+    let hints = [("02.md", "Reply with +PONG"), ("01.md", "Use a socket"), ("notes.txt", "-")];
+    write_course(root.path(), "redis", &hints);
+
+    let course = schema::parse(root.path()).unwrap();
+    assert_eq!(course.stages["01-ping"].hints, ["Use a socket", "Reply with +PONG"]);
+    assert!(course.stages["02-echo"].hints.is_empty());
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_unlock_hints() {
+    let ctx = setup(unreachable_cluster()).await;
+    let root = tempfile::tempdir().unwrap();
+    let slug = format!("course-{}", Uuid::now_v7().simple());
+    write_course(root.path(), &slug, &[("01.md", "Use a socket"), ("02.md", "Reply with +PONG")]);
+    sync_course(&ctx, &slug, root.path()).await;
+
+    let user_id = enroll(&ctx, &slug).await;
+    let mut user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+
+    let uri = format!("/v1/user/courses/{slug}/stages/{slug}-ping");
+    let (status, body) = call(&ctx, &user_id, Method::GET, &uri).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!((&body["hints_total"], &body["hints_unlocked"]), (&json!(2), &json!(0)));
+    assert_eq!(call(&ctx, &user_id, Method::GET, &format!("{uri}/hints")).await.1, json!([]));
+
+    // Hints are revealed in order, until there is none left
+    let (status, body) = call(&ctx, &user_id, Method::POST, &format!("{uri}/hints/unlock")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body, json!({ "position": 0, "content": "Use a socket" }));
+
+    let (_, body) = call(&ctx, &user_id, Method::POST, &format!("{uri}/hints/unlock")).await;
+    assert_eq!(body["content"], "Reply with +PONG");
+    let (status, _) = call(&ctx, &user_id, Method::POST, &format!("{uri}/hints/unlock")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, body) = call(&ctx, &user_id, Method::GET, &format!("{uri}/hints")).await;
+    assert_eq!(body.as_array().unwrap().len(), 2);
+    assert_eq!(call(&ctx, &user_id, Method::GET, &uri).await.1["hints_unlocked"], 2);
+
+    // Syncs replace the hints of the stage
+    write_course(root.path(), &slug, &[("01.md", "Use a TCP socket")]);
+    sync_course(&ctx, &slug, root.path()).await;
+    let (_, body) = call(&ctx, &user_id, Method::GET, &format!("{uri}/hints")).await;
+    assert_eq!(body, json!([{ "position": 0, "content": "Use a TCP socket" }]));
+}
@@ -0,0 +1,218 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Characterization of the stage order resolved by the repository, for a
+//! course with base stages and two extensions. These tests need a
+//! disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test stage-order-tests -- --ignored
+
+mod common;
+
+use stackclass::{
+    context::Context, model::StageModel, repository::StageRepository, service::StageService,
+};
+use uuid::Uuid;
+
+use common::{setup, unreachable_cluster};
+
+/// Inserts a course with three base stages and the extensions `x` and `y`
+/// of two stages each, and returns its slug and the extension ids. Stages
+/// are inserted in reverse so that insertion order can not leak into the
+/// results.
+async fn create_course(ctx: &Context) -> (String, Uuid, Vec<Uuid>) {
+    let slug = format!("course-{}", Uuid::now_v7().simple());
+    let pool = ctx.database.pool();
+
+    let course_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO courses (id, slug, name, short_name, release_status, description, summary, repository, stage_count)
+        VALUES ($1, $2, $2, $2, 'beta', '', '', '', 7)
+        RETURNING id
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(&slug)
+    .fetch_one(pool)
+    .await
+    .unwrap();
+
+    let mut extensions = Vec::new();
+    for (index, ext) in ["x", "y"].into_iter().enumerate() {
+        let id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO extensions (id, course_id, slug, name, description, stage_count, weight)
+            VALUES ($1, $2, $3, $3, '', 2, $4)
+            RETURNING id
+            "#,
+        )
+        .bind(Uuid::now_v7())
+        .bind(course_id)
+        .bind(format!("{slug}-{ext}"))
+        .bind(index as i32)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        extensions.push(id);
+    }
+
+    let stages = [
+        ("b0", None, 0),
+        ("b1", None, 1),
+        ("b2", None, 2),
+        ("x0", Some(extensions[0]), 0),
+        ("x1", Some(extensions[0]), 1),
+        ("y0", Some(extensions[1]), 0),
+        ("y1", Some(extensions[1]), 1),
+    ];
+    for (stage, ext, position) in stages.into_iter().rev() {
+        insert_stages(ctx, course_id, ext, &format!("{slug}-{stage}"), position, 1).await;
+    }
+
+    (slug, course_id, extensions)
+}
+
+/// Inserts `count` stages from `position` on, suffixing slugs with their
+/// index when more than one. Legacy weights are written the way course
+/// syncs used to, `(extension index + 1) * 1000 + position`.
+async fn insert_stages(
+    ctx: &Context,
+    course_id: Uuid,
+    ext: Option<Uuid>,
+    slug: &str,
+    position: i32,
+    count: i32,
+) {
+    sqlx::query(
+        r#"
+        INSERT INTO stages (id, course_id, extension_id, slug, name, difficulty, description, instruction, weight, position)
+        SELECT gen_random_uuid(), $1, $2, s.slug, s.slug, 'easy', '', '', COALESCE((e.weight + 1) * 1000, 0) + n, n
+        FROM generate_series($4, $4 + $5 - 1) n
+        LEFT JOIN extensions e ON e.id = $2
+        CROSS JOIN LATERAL (SELECT CASE WHEN $5 = 1 THEN $3 ELSE $3 || '-' || n END AS slug) s
+        "#,
+    )
+    .bind(course_id)
+    .bind(ext)
+    .bind(slug)
+    .bind(position)
+    .bind(count)
+    .execute(ctx.database.pool())
+    .await
+    .unwrap();
+}
+
+/// Stage slugs without the course prefix.
+fn names(slug: &str, stages: &[StageModel]) -> Vec<String> {
+    stages.iter().map(|stage| stage.slug.trim_start_matches(&format!("{slug}-")).into()).collect()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_stage_order() {
+    let ctx = setup(unreachable_cluster()).await;
+    let db = &ctx.database;
+    let (slug, ..) = create_course(&ctx).await;
+
+    let stages = StageRepository::find_by_course(db, &slug).await.unwrap();
+    assert_eq!(names(&slug, &stages), ["b0", "b1", "b2", "x0", "x1", "y0", "y1"]);
+
+    let stages = StageRepository::find_base_by_course(db, &slug).await.unwrap();
+    assert_eq!(names(&slug, &stages), ["b0", "b1", "b2"]);
+
+    let stages = StageRepository::find_extended_by_course(db, &slug).await.unwrap();
+    assert_eq!(names(&slug, &stages), ["x0", "x1", "y0", "y1"]);
+
+    let stages = StageRepository::find_by_extension(db, &format!("{slug}-y")).await.unwrap();
+    assert_eq!(names(&slug, &stages), ["y0", "y1"]);
+
+    // Progression runs through the base stages, then every extension
+    let first = StageRepository::first(db, &slug).await.unwrap().unwrap();
+    let mut path = vec![first];
+    while let Some(next) =
+        StageRepository::next(db, &slug, &path.last().unwrap().slug).await.unwrap()
+    {
+        path.push(next);
+    }
+    assert_eq!(names(&slug, &path), ["b0", "b1", "b2", "x0", "x1", "y0", "y1"]);
+
+    let until = StageRepository::find_stages_until(db, &slug, &format!("{slug}-b1")).await;
+    assert_eq!(names(&slug, &until.unwrap()), ["b0", "b1"]);
+
+    let until = StageRepository::find_stages_until(db, &slug, &format!("{slug}-x1")).await;
+    assert_eq!(names(&slug, &until.unwrap()), ["b0", "b1", "b2", "x0", "x1"]);
+
+    // Stages of other extensions are not on the way to an extension stage
+    let until = StageRepository::find_stages_until(db, &slug, &format!("{slug}-y1")).await;
+    assert_eq!(names(&slug, &until.unwrap()), ["b0", "b1", "b2", "y0", "y1"]);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_adjacent_stages() {
+    let ctx = setup(unreachable_cluster()).await;
+    let db = &ctx.database;
+    let (slug, ..) = create_course(&ctx).await;
+
+    let adjacent = |stage: &'static str| {
+        let slug = slug.clone();
+        async move {
+            let (previous, next) =
+                StageRepository::find_adjacent(db, &slug, &format!("{slug}-{stage}"))
+                    .await
+                    .unwrap();
+            let name = |s: Option<String>| {
+                s.map(|s| s.trim_start_matches(&format!("{slug}-")).to_string())
+            };
+            (name(previous), name(next))
+        }
+    };
+
+    assert_eq!(adjacent("b0").await, (None, Some("b1".into())));
+
+    // Extension boundaries follow the progression order
+    assert_eq!(adjacent("b2").await, (Some("b1".into()), Some("x0".into())));
+    assert_eq!(adjacent("x1").await, (Some("x0".into()), Some("y0".into())));
+    assert_eq!(adjacent("y1").await, (Some("y0".into()), None));
+
+    let stage = StageService::get(ctx.clone(), &slug, &format!("{slug}-b2")).await.unwrap();
+    assert_eq!(stage.previous_slug, Some(format!("{slug}-b1")));
+    assert_eq!(stage.next_slug, Some(format!("{slug}-x0")));
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_large_extension() {
+    let ctx = setup(unreachable_cluster()).await;
+    let db = &ctx.database;
+    let (slug, course_id, extensions) = create_course(&ctx).await;
+
+    // With 1000 more stages, the legacy weights of `x` run into those of `y`
+    insert_stages(&ctx, course_id, Some(extensions[0]), &format!("{slug}-x"), 2, 1000).await;
+
+    let stages = StageRepository::find_by_extension(db, &format!("{slug}-x")).await.unwrap();
+    assert_eq!(stages.len(), 1002);
+    assert_eq!(names(&slug, &stages[..3]), ["x0", "x1", "x-2"]);
+
+    let next = StageRepository::next(db, &slug, &format!("{slug}-x1")).await.unwrap();
+    assert_eq!(names(&slug, &[next.unwrap()]), ["x-2"]);
+    let next = StageRepository::next(db, &slug, &format!("{slug}-x-1001")).await.unwrap();
+    assert_eq!(names(&slug, &[next.unwrap()]), ["y0"]);
+
+    let stages = StageRepository::find_by_course(db, &slug).await.unwrap();
+    assert_eq!(names(&slug, &stages[1004..]), ["x-1001", "y0", "y1"]);
+
+    let until = StageRepository::find_stages_until(db, &slug, &format!("{slug}-y0")).await;
+    assert_eq!(names(&slug, &until.unwrap()), ["b0", "b1", "b2", "y0"]);
+}
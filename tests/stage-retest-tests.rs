@@ -0,0 +1,145 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Learners run the tests of their current stage again without pushing.
+//! These tests need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test stage-retest-tests -- --ignored
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    body::Body,
+    http::{Method, Request, Response, StatusCode, header},
+};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    model::StageAttemptModel,
+    repository::{CourseRepository, StageRepository},
+    routes,
+    service::CourseService,
+};
+use tower::ServiceExt;
+
+use common::{create_course, enroll, setup, token};
+
+/// A Kubernetes API server holding the PipelineRuns it was asked to create,
+/// along with the queries they were listed with.
+#[derive(Clone, Default)]
+struct MockCluster {
+    runs: Arc<Mutex<Vec<Value>>>,
+    queries: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockCluster {
+    fn client(&self) -> kube::Client {
+        let mock = self.clone();
+        let service = tower::service_fn(move |req: Request<kube::client::Body>| {
+            let mock = mock.clone();
+            async move {
+                let body = match *req.method() {
+                    Method::POST => {
+                        let body = Body::new(req.into_body());
+                        let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+                        let run: Value = serde_json::from_slice(&body).unwrap();
+                        mock.runs.lock().unwrap().push(run.clone());
+                        run
+                    }
+                    _ => {
+                        let query = req.uri().query().unwrap_or_default().to_string();
+                        mock.queries.lock().unwrap().push(query);
+                        json!({
+                            "apiVersion": "tekton.dev/v1",
+                            "kind": "PipelineRunList",
+                            "metadata": {},
+                            "items": *mock.runs.lock().unwrap()
+                        })
+                    }
+                };
+                let body = serde_json::to_vec(&body).unwrap();
+                Ok::<_, std::io::Error>(Response::new(kube::client::Body::from(body)))
+            }
+        });
+        kube::Client::new(service, "stackclass")
+    }
+
+    /// Marks every PipelineRun as completed.
+    fn complete(&self) {
+        for run in self.runs.lock().unwrap().iter_mut() {
+            run["status"] = json!({ "completionTime": "2025-01-01T00:00:00Z" });
+        }
+    }
+}
+
+async fn retest(ctx: &Arc<Context>, user_id: &str, slug: &str, stage: &str) -> (StatusCode, Value) {
+    let req = Request::post(format!("/v1/user/courses/{slug}/stages/{stage}/retest"))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token(ctx, user_id).await))
+        .body(Body::empty())
+        .unwrap();
+
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_retest_current_stage() {
+    let cluster = MockCluster::default();
+    let ctx = setup(cluster.client()).await;
+    let db = &ctx.database;
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let mut user_course = CourseRepository::get_user_course(db, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+
+    let (s1, s2) = (format!("{slug}-s1"), format!("{slug}-s2"));
+
+    // Nothing to run again before a push was graded
+    let (status, body) = retest(&ctx, &user_id, &slug, &s1).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+
+    let user_stage = StageRepository::get_user_stage(db, &user_id, &slug, &s1).await.unwrap();
+    let failed =
+        StageAttemptModel::new(user_stage.id, "").with_repo_commit("abc123").with_status("failed");
+    StageRepository::create_attempt(db, &failed).await.unwrap();
+
+    let (status, body) = retest(&ctx, &user_id, &slug, &s1).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let run = body["pipeline_run"].as_str().unwrap().to_string();
+    assert_eq!(cluster.runs.lock().unwrap()[0]["metadata"]["name"], run);
+
+    // Runs are listed by the label of the repository
+    let selector = format!("labelSelector=stackclass.dev%2Frepo%3D{}", user_course.id);
+    assert!(cluster.queries.lock().unwrap()[0].contains(&selector));
+
+    // The new attempt grades the same commit
+    let attempt = StageRepository::find_latest_attempt(db, &user_stage.id).await.unwrap().unwrap();
+    assert_eq!(attempt.pipeline_run, Some(run));
+    assert_eq!((attempt.status.as_str(), attempt.repo_commit.as_str()), ("pending", "abc123"));
+
+    // Only one run at a time
+    let (status, body) = retest(&ctx, &user_id, &slug, &s1).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+
+    cluster.complete();
+    assert_eq!(retest(&ctx, &user_id, &slug, &s1).await.0, StatusCode::OK);
+
+    // Stages other than the current one are not tested
+    assert_eq!(retest(&ctx, &user_id, &slug, &s2).await.0, StatusCode::NOT_FOUND);
+}
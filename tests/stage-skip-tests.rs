@@ -0,0 +1,118 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Learners skip the stages course authors mark optional. These tests need a
+//! disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test stage-skip-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use serde_json::Value;
+use stackclass::{
+    context::Context,
+    repository::{CourseRepository, StageRepository},
+    routes,
+    service::{CourseService, StageService},
+};
+use tower::ServiceExt;
+
+use common::{create_course, enroll, setup, token, unreachable_cluster};
+
+async fn skip(ctx: &Arc<Context>, user_id: &str, slug: &str, stage: &str) -> (StatusCode, Value) {
+    let req = Request::post(format!("/v1/user/courses/{slug}/stages/{stage}/skip"))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token(ctx, user_id).await))
+        .body(Body::empty())
+        .unwrap();
+
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn make_optional(ctx: &Context, stage: &str) {
+    sqlx::query("UPDATE stages SET optional = true WHERE slug = $1")
+        .bind(stage)
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+}
+
+async fn activate(ctx: &Arc<Context>, user_id: &str, slug: &str) {
+    let mut user_course =
+        CourseRepository::get_user_course(&ctx.database, user_id, slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_skip_optional_stage() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let (s1, s2) = (format!("{slug}-s1"), format!("{slug}-s2"));
+    make_optional(&ctx, &s1).await;
+    activate(&ctx, &user_id, &slug).await;
+
+    let (status, body) = skip(&ctx, &user_id, &slug, &s1).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "skipped");
+
+    // The next stage is current, without the skipped one counting
+    let user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    assert_eq!(user_course.completed_stage_count, 0);
+    assert_eq!(user_course.current_stage_slug.as_deref(), Some(s2.as_str()));
+
+    let stages = StageRepository::find_user_stages(&ctx.database, &user_id, &slug).await.unwrap();
+    let status = |slug: &str| stages.iter().find(|s| s.stage_slug == slug).unwrap().status.clone();
+    assert_eq!((status(&s1), status(&s2)), ("skipped".to_string(), "in_progress".to_string()));
+
+    // Skipped stages can be neither skipped nor completed again
+    assert_eq!(skip(&ctx, &user_id, &slug, &s1).await.0, StatusCode::BAD_REQUEST);
+    let res = StageService::complete(ctx.clone(), &user_id, &slug, &s1, None).await;
+    assert!(res.is_err());
+
+    StageService::complete(ctx.clone(), &user_id, &slug, &s2, None).await.unwrap();
+    let user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    assert_eq!(user_course.completed_stage_count, 1);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_skip_requires_optional_current_stage() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let (s1, s2) = (format!("{slug}-s1"), format!("{slug}-s2"));
+    make_optional(&ctx, &s2).await;
+    activate(&ctx, &user_id, &slug).await;
+
+    let (status, body) = skip(&ctx, &user_id, &slug, &s1).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+
+    // The optional stage is not started yet
+    assert_eq!(skip(&ctx, &user_id, &slug, &s2).await.0, StatusCode::NOT_FOUND);
+
+    StageService::complete(ctx.clone(), &user_id, &slug, &s1, None).await.unwrap();
+    assert_eq!(skip(&ctx, &user_id, &slug, &s2).await.0, StatusCode::OK);
+}
@@ -0,0 +1,141 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Stage solutions are only served to learners who completed the stage or
+//! failed it often enough. These tests need a disposable PostgreSQL
+//! database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test stage-solution-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use serde_json::Value;
+use stackclass::{
+    context::Context,
+    repository::{CourseRepository, StageRepository},
+    routes,
+    service::{CourseService, StageService},
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{create_course, enroll, setup, token, unreachable_cluster};
+
+async fn get(ctx: &Arc<Context>, user_id: Option<&str>, uri: &str) -> (StatusCode, Value) {
+    let mut req = Request::get(uri);
+    if let Some(user_id) = user_id {
+        req = req.header(header::AUTHORIZATION, format!("Bearer {}", token(ctx, user_id).await));
+    }
+
+    let res = routes::build(ctx.clone()).oneshot(req.body(Body::empty()).unwrap()).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Sets the solution of every stage of the course and starts the first one
+/// for the user.
+async fn start(ctx: &Arc<Context>, user_id: &str, slug: &str) {
+    sqlx::query("UPDATE stages SET solution = 'Solve ' || slug WHERE slug LIKE $1 || '-%'")
+        .bind(slug)
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+
+    let mut user_course =
+        CourseRepository::get_user_course(&ctx.database, user_id, slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+}
+
+async fn fail(ctx: &Context, user_id: &str, slug: &str, stage: &str) {
+    let user_stage =
+        StageRepository::get_user_stage(&ctx.database, user_id, slug, stage).await.unwrap();
+    sqlx::query("INSERT INTO stage_attempts (id, user_stage_id, status) VALUES ($1, $2, 'failed')")
+        .bind(Uuid::now_v7())
+        .bind(user_stage.id)
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_solution_unlocks_on_completion() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let s1 = format!("{slug}-s1");
+    start(&ctx, &user_id, &slug).await;
+
+    // The public stage detail never carries the solution
+    let (status, body) = get(&ctx, None, &format!("/v1/courses/{slug}/stages/{s1}")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.get("solution").is_none(), "{body}");
+
+    let uri = format!("/v1/user/courses/{slug}/stages/{s1}");
+    let (status, body) = get(&ctx, Some(&user_id), &format!("{uri}/solution")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "{body}");
+    assert_eq!(get(&ctx, Some(&user_id), &uri).await.1["solution_unlocked"], false);
+
+    // Failed attempts do not count unless configured
+    fail(&ctx, &user_id, &slug, &s1).await;
+    assert_eq!(
+        get(&ctx, Some(&user_id), &format!("{uri}/solution")).await.0,
+        StatusCode::FORBIDDEN
+    );
+
+    StageService::complete(ctx.clone(), &user_id, &slug, &s1, None).await.unwrap();
+    assert_eq!(get(&ctx, Some(&user_id), &uri).await.1["solution_unlocked"], true);
+
+    let (status, body) = get(&ctx, Some(&user_id), &format!("{uri}/solution")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["solution"], format!("Solve {s1}"));
+
+    // Anonymous requests and stages not started are refused
+    assert_eq!(get(&ctx, None, &format!("{uri}/solution")).await.0, StatusCode::UNAUTHORIZED);
+    let uri = format!("/v1/user/courses/{slug}/stages/{slug}-e1/solution");
+    assert_eq!(get(&ctx, Some(&user_id), &uri).await.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_solution_unlocks_after_failures() {
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    ctx.config.solution_unlock_failures = Some(2);
+    let ctx = Arc::new(ctx);
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let s1 = format!("{slug}-s1");
+    start(&ctx, &user_id, &slug).await;
+
+    let uri = format!("/v1/user/courses/{slug}/stages/{s1}");
+    fail(&ctx, &user_id, &slug, &s1).await;
+    assert_eq!(
+        get(&ctx, Some(&user_id), &format!("{uri}/solution")).await.0,
+        StatusCode::FORBIDDEN
+    );
+
+    fail(&ctx, &user_id, &slug, &s1).await;
+    assert_eq!(get(&ctx, Some(&user_id), &uri).await.1["solution_unlocked"], true);
+
+    let (status, body) = get(&ctx, Some(&user_id), &format!("{uri}/solution")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["solution"], format!("Solve {s1}"));
+}
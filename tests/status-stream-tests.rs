@@ -0,0 +1,202 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Status streams are pushed the updates of their user course. These tests
+//! need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test status-stream-tests -- --ignored
+
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    body::{Body, BodyDataStream},
+    http::{Request, StatusCode, header},
+};
+use futures::StreamExt;
+use serde_json::Value;
+use stackclass::{
+    context::Context,
+    repository::CourseRepository,
+    routes,
+    service::{CourseService, StageService},
+};
+use tower::ServiceExt;
+
+use common::{create_course, enroll, setup, token, unreachable_cluster};
+
+/// An event as sent on a stream.
+#[derive(Debug)]
+struct Frame {
+    name: Option<String>,
+    id: Option<i64>,
+    data: Value,
+}
+
+/// Parses the fields of a raw event.
+fn parse(text: &str) -> Frame {
+    let field = |name: &str| {
+        let prefix = format!("{name}: ");
+        text.lines().find_map(|line| line.strip_prefix(prefix.as_str()).map(str::to_string))
+    };
+    Frame {
+        name: field("event"),
+        id: field("id").map(|id| id.parse().unwrap()),
+        data: serde_json::from_str(&field("data").unwrap()).unwrap(),
+    }
+}
+
+/// Reads the next event sent on a stream, skipping keep-alive comments, or
+/// `None` once the stream ended.
+async fn next_event(body: &mut BodyDataStream) -> Option<Frame> {
+    loop {
+        let chunk = tokio::time::timeout(Duration::from_secs(10), body.next())
+            .await
+            .expect("no event was sent")?
+            .unwrap();
+        let text = String::from_utf8(chunk.to_vec()).unwrap();
+        if text.lines().any(|line| line.starts_with("data: ")) {
+            return Some(parse(&text));
+        }
+    }
+}
+
+/// Opens the status stream of the first stage of a course, resuming from
+/// the given event id if any.
+async fn open(
+    ctx: &Arc<Context>,
+    slug: &str,
+    user_id: &str,
+    resume: Option<&str>,
+) -> BodyDataStream {
+    let mut req = Request::get(format!("/v1/user/courses/{slug}/stages/{slug}-s1/status"))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token(ctx, user_id).await));
+    if let Some(id) = resume {
+        req = req.header("Last-Event-ID", id);
+    }
+    let res = routes::build(ctx.clone()).oneshot(req.body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    res.into_body().into_data_stream()
+}
+
+/// Enrolls a new user into a new course and starts its first stage,
+/// returning the course slug and the user id.
+async fn start(ctx: &Arc<Context>) -> (String, String) {
+    let slug = create_course(ctx).await;
+    let user_id = enroll(ctx, &slug).await;
+    let mut user_course =
+        CourseRepository::get_user_course(&ctx.database, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+    (slug, user_id)
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_stage_status_is_pushed() {
+    let ctx = setup(unreachable_cluster()).await;
+    let listener = ctx.status.start(ctx.database.pool()).await.unwrap();
+    let (slug, user_id) = start(&ctx).await;
+
+    // Unknown stages are rejected before streaming
+    let req = Request::get(format!("/v1/user/courses/{slug}/stages/unknown/status"))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token(&ctx, &user_id).await))
+        .body(Body::empty())
+        .unwrap();
+    let res = routes::build(ctx.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // The current status is sent right away
+    let mut body = open(&ctx, &slug, &user_id, None).await;
+    let chunk = body.next().await.unwrap().unwrap();
+    let text = String::from_utf8(chunk.to_vec()).unwrap();
+    assert!(text.starts_with("event: status\n"), "{text}");
+    let snapshot = parse(&text);
+    assert_eq!(snapshot.data["status"], "in_progress");
+
+    // The completion is pushed as a final event, and ends the stream
+    StageService::complete(ctx.clone(), &user_id, &slug, &format!("{slug}-s1"), None)
+        .await
+        .unwrap();
+    let done = next_event(&mut body).await.unwrap();
+    assert_eq!(done.name.as_deref(), Some("done"));
+    assert_eq!(done.data["status"], "completed");
+    assert!(done.id.unwrap() > snapshot.id.unwrap());
+    assert!(next_event(&mut body).await.is_none());
+
+    // The stream slot is released once the stream ended
+    drop(body);
+    assert_eq!(ctx.streams.count(&user_id), 0);
+
+    listener.abort();
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_stream_resumes_from_last_event_id() {
+    let ctx = setup(unreachable_cluster()).await;
+    let listener = ctx.status.start(ctx.database.pool()).await.unwrap();
+    let (slug, user_id) = start(&ctx).await;
+
+    let mut body = open(&ctx, &slug, &user_id, None).await;
+    let seen = next_event(&mut body).await.unwrap().id.unwrap();
+    drop(body);
+
+    // Clients which missed an update are sent the current status, as are
+    // those with an id that is not ours
+    for resume in [(seen - 1).to_string(), "unknown".to_string()] {
+        let mut body = open(&ctx, &slug, &user_id, Some(&resume)).await;
+        let frame = next_event(&mut body).await.unwrap();
+        assert_eq!((frame.name.as_deref(), frame.id), (Some("status"), Some(seen)));
+    }
+
+    // Clients up to date are only sent the next update
+    let mut body = open(&ctx, &slug, &user_id, Some(&seen.to_string())).await;
+    StageService::complete(ctx.clone(), &user_id, &slug, &format!("{slug}-s1"), None)
+        .await
+        .unwrap();
+    let frame = next_event(&mut body).await.unwrap();
+    assert_eq!(frame.name.as_deref(), Some("done"));
+    assert!(frame.id.unwrap() > seen);
+    drop(body);
+
+    // The final status is sent again to clients reconnecting after it
+    let resume = frame.id.unwrap().to_string();
+    let mut body = open(&ctx, &slug, &user_id, Some(&resume)).await;
+    let again = next_event(&mut body).await.unwrap();
+    assert_eq!((again.name.as_deref(), again.id), (Some("done"), frame.id));
+    assert!(next_event(&mut body).await.is_none());
+
+    listener.abort();
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_stream_times_out() {
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    ctx.config.max_stream_secs = 1;
+    let ctx = Arc::new(ctx);
+    let (slug, user_id) = start(&ctx).await;
+
+    let mut body = open(&ctx, &slug, &user_id, None).await;
+    let status = next_event(&mut body).await.unwrap();
+    assert_eq!(status.name.as_deref(), Some("status"));
+    assert_eq!(status.data["status"], "in_progress");
+
+    // Without updates, the stream is closed once it is open for too long
+    let timeout = next_event(&mut body).await.unwrap();
+    assert_eq!((timeout.name.as_deref(), timeout.id), (Some("timeout"), None));
+    assert_eq!(timeout.data["lifetime_secs"], 1);
+    assert!(next_event(&mut body).await.is_none());
+}
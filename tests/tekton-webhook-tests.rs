@@ -0,0 +1,288 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pipelines learn why the Tekton webhook dropped their completion, and can
+//! validate their configuration upfront. These tests need a disposable
+//! PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test tekton-webhook-tests -- --ignored
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use chrono::{Duration, Utc};
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    model::StageAttemptModel,
+    repository::{CourseRepository, StageRepository},
+    routes,
+    service::{CourseService, StageService},
+    utils::crypto,
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{create_course, enroll, pipeline_secret, setup, unreachable_cluster};
+
+async fn post(ctx: &Arc<Context>, uri: &str, event: &Value) -> (StatusCode, Value) {
+    let req = Request::post(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(event.to_string()))
+        .unwrap();
+
+    let app = routes::build(ctx.clone());
+    let res = app.oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// A passing pipeline event of the given run, signed with the auth secret.
+fn event(ctx: &Context, name: &str, repo: &str, course: &str, stage: &str) -> Value {
+    let nonce = crypto::random_hex(16).unwrap();
+    json!({
+        "name": name,
+        "nonce": nonce,
+        "status": "Succeeded",
+        "repo": repo,
+        "course": course,
+        "stage": stage,
+        "secret": pipeline_secret(ctx, name, &nonce, repo, course, stage),
+        "tasks": { "test": { "status": "Succeeded", "reason": "Succeeded" } }
+    })
+}
+
+/// Enrolls a learner and records a pending attempt at the first stage,
+/// returning the repository and the name of its run.
+async fn pending_attempt(ctx: &Arc<Context>, slug: &str) -> (String, String) {
+    let db = &ctx.database;
+    let user_id = enroll(ctx, slug).await;
+    let mut user_course = CourseRepository::get_user_course(db, &user_id, slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+
+    let stage_slug = format!("{slug}-s1");
+    let user_stage =
+        StageRepository::get_user_stage(db, &user_id, slug, &stage_slug).await.unwrap();
+    let name = format!("run-{}", Uuid::now_v7().simple());
+    let attempt = StageAttemptModel::new(user_stage.id, "").with_pipeline_run(&name);
+    StageRepository::create_attempt(db, &attempt).await.unwrap();
+
+    (user_course.id.to_string(), name)
+}
+
+async fn attempt_status(ctx: &Context, name: &str) -> String {
+    sqlx::query_scalar("SELECT status FROM stage_attempts WHERE pipeline_run = $1")
+        .bind(name)
+        .fetch_one(ctx.database.pool())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_rejections_carry_a_reason() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let (repo, name) = pending_attempt(&ctx, &slug).await;
+    let stage = format!("{slug}-s1");
+    let uri = "/v1/webhooks/tekton";
+
+    let mut forged = event(&ctx, &name, &repo, &slug, &stage);
+    forged["secret"] = json!("forged");
+    let unknown = Uuid::now_v7().to_string();
+    let cases = [
+        (forged, StatusCode::UNAUTHORIZED, "invalid_signature"),
+        (event(&ctx, &name, "not-a-repo", &slug, &stage), StatusCode::NOT_FOUND, "unknown_repo"),
+        (event(&ctx, &name, &unknown, &slug, &stage), StatusCode::NOT_FOUND, "unknown_repo"),
+        (event(&ctx, &name, &repo, &slug, "nope"), StatusCode::NOT_FOUND, "unknown_stage"),
+        (event(&ctx, "run-unknown", &repo, &slug, &stage), StatusCode::CONFLICT, "stale_pipeline"),
+    ];
+    for (event, expected, reason) in cases {
+        let (status, body) = post(&ctx, uri, &event).await;
+        assert_eq!(status, expected, "{body}");
+        assert_eq!(body["rejection_reason"], reason);
+    }
+    assert_eq!(attempt_status(&ctx, &name).await, "pending");
+
+    // The completion is recorded once, replays are stale
+    let completion = event(&ctx, &name, &repo, &slug, &stage);
+    assert_eq!(post(&ctx, uri, &completion).await.0, StatusCode::OK);
+    assert_eq!(attempt_status(&ctx, &name).await, "passed");
+
+    let (status, body) = post(&ctx, uri, &completion).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["rejection_reason"], "stale_pipeline");
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_validation_reports_each_check() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let (repo, name) = pending_attempt(&ctx, &slug).await;
+    let stage = format!("{slug}-s1");
+    let uri = "/v1/webhooks/tekton/validate";
+
+    let (status, body) = post(&ctx, uri, &event(&ctx, &name, &repo, &slug, &stage)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["valid"], true);
+    assert_eq!(body["rejection_reason"], Value::Null);
+    let checks = body["checks"].as_array().unwrap();
+    let names: Vec<_> = checks.iter().map(|c| c["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["signature", "repo", "user_course", "stage"]);
+    assert!(checks.iter().all(|c| c["status"] == "passed" && c["message"].is_string()));
+
+    // Nothing is recorded
+    assert_eq!(attempt_status(&ctx, &name).await, "pending");
+
+    let (status, body) = post(&ctx, uri, &event(&ctx, &name, &repo, &slug, "nope")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["valid"], false);
+    assert_eq!(body["rejection_reason"], "unknown_stage");
+    assert_eq!(body["checks"][3]["status"], "failed");
+
+    let (_, body) = post(&ctx, uri, &event(&ctx, &name, "not-a-repo", &slug, &stage)).await;
+    assert_eq!(body["rejection_reason"], "unknown_repo");
+    assert_eq!(body["checks"][1]["status"], "failed");
+    assert_eq!(body["checks"][2]["status"], "skipped");
+    assert_eq!(body["checks"][3]["status"], "passed");
+
+    // Unsigned events are not looked up any further
+    let mut forged = event(&ctx, &name, &repo, &slug, &stage);
+    forged["secret"] = json!("forged");
+    let (_, body) = post(&ctx, uri, &forged).await;
+    assert_eq!(body["rejection_reason"], "invalid_signature");
+    let statuses: Vec<_> =
+        body["checks"].as_array().unwrap().iter().map(|c| &c["status"]).collect();
+    assert_eq!(statuses, ["failed", "skipped", "skipped", "skipped"]);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_failed_run_keeps_its_output() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let (repo, name) = pending_attempt(&ctx, &slug).await;
+    let stage = format!("{slug}-s1");
+
+    let mut failure = event(&ctx, &name, &repo, &slug, &stage);
+    failure["tasks"]["test"] = json!({ "status": "Failed", "reason": "Failed" });
+    failure["log"] = json!(format!("{}\nexpected 42, got 41", "x".repeat(100_000)));
+    failure["results"] = json!([
+        { "slug": stage, "passed": false, "message": "expected 42, got 41" },
+    ]);
+    assert_eq!(post(&ctx, "/v1/webhooks/tekton", &failure).await.0, StatusCode::OK);
+    assert_eq!(attempt_status(&ctx, &name).await, "failed");
+
+    // The status of the stage carries the output of the latest attempt
+    let user_course_id = Uuid::parse_str(&repo).unwrap();
+    let user_course =
+        CourseRepository::get_user_course_by_id(&ctx.database, &user_course_id).await.unwrap();
+    let status =
+        StageService::get_user_stage_status(&ctx, &user_course.user_id, &slug, &stage).await;
+    let attempt = status.unwrap().latest_attempt.unwrap();
+    assert_eq!(attempt.reason.as_deref(), Some("Failed"));
+
+    let log = attempt.log.unwrap();
+    assert!(log.len() < 100_000);
+    assert!(log.ends_with("expected 42, got 41"));
+    assert_eq!(attempt.results.len(), 1);
+    assert!(!attempt.results[0].passed);
+    assert_eq!(attempt.results[0].message.as_deref(), Some("expected 42, got 41"));
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_events_are_bound_to_their_run() {
+    let ctx = setup(unreachable_cluster()).await;
+    let slug = create_course(&ctx).await;
+    let (repo, older) = pending_attempt(&ctx, &slug).await;
+    let stage = format!("{slug}-s1");
+    let uri = "/v1/webhooks/tekton";
+
+    // The older run failed, and the learner pushed again
+    let mut failure = event(&ctx, &older, &repo, &slug, &stage);
+    failure["tasks"]["test"] = json!({ "status": "Failed", "reason": "Failed" });
+    assert_eq!(post(&ctx, uri, &failure).await.0, StatusCode::OK);
+    let user_course_id = Uuid::parse_str(&repo).unwrap();
+    let user_course =
+        CourseRepository::get_user_course_by_id(&ctx.database, &user_course_id).await.unwrap();
+    let user_stage =
+        StageRepository::get_user_stage(&ctx.database, &user_course.user_id, &slug, &stage)
+            .await
+            .unwrap();
+    let newer = format!("run-{}", Uuid::now_v7().simple());
+    let attempt = StageAttemptModel::new(user_stage.id, "").with_pipeline_run(&newer);
+    StageRepository::create_attempt(&ctx.database, &attempt).await.unwrap();
+
+    // An event of the older run replayed as one of the newer run is rejected,
+    // as is one with another nonce
+    let passed = event(&ctx, &older, &repo, &slug, &stage);
+    let mut replayed = passed.clone();
+    replayed["name"] = json!(newer);
+    let mut renonced = passed.clone();
+    renonced["nonce"] = json!("0".repeat(32));
+    for forged in [replayed, renonced] {
+        let (status, body) = post(&ctx, uri, &forged).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{body}");
+        assert_eq!(body["rejection_reason"], "invalid_signature");
+    }
+    assert_eq!(attempt_status(&ctx, &newer).await, "pending");
+
+    // The event of the newer run itself is recorded
+    let completion = event(&ctx, &newer, &repo, &slug, &stage);
+    assert_eq!(post(&ctx, uri, &completion).await.0, StatusCode::OK);
+    assert_eq!(attempt_status(&ctx, &newer).await, "passed");
+    assert_eq!(attempt_status(&ctx, &older).await, "failed");
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_legacy_signatures_within_grace_period() {
+    let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    ctx.config.legacy_signatures_until = Some(Utc::now() + Duration::hours(1));
+    let ctx = Arc::new(ctx);
+    let slug = create_course(&ctx).await;
+    let (repo, name) = pending_attempt(&ctx, &slug).await;
+    let stage = format!("{slug}-s1");
+    let uri = "/v1/webhooks/tekton";
+
+    // A run triggered before the upgrade echoes no nonce and is signed
+    // without its name
+    let payload = format!("{repo}{slug}{stage}");
+    let mut legacy = event(&ctx, &name, &repo, &slug, &stage);
+    legacy["nonce"] = json!("");
+    legacy["secret"] = json!(crypto::hmac_sha256_sign(&payload, &ctx.config.auth_secret).unwrap());
+
+    // Neither a legacy signature with a nonce nor one past the grace period
+    // is accepted
+    let mut renonced = legacy.clone();
+    renonced["nonce"] = json!("0".repeat(32));
+    let (status, body) = post(&ctx, uri, &renonced).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "{body}");
+    let mut expired = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+    expired.config.legacy_signatures_until = Some(Utc::now() - Duration::hours(1));
+    let (status, body) = post(&Arc::new(expired), uri, &legacy).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "{body}");
+    assert_eq!(attempt_status(&ctx, &name).await, "pending");
+
+    assert_eq!(post(&ctx, uri, &legacy).await.0, StatusCode::OK);
+    assert_eq!(attempt_status(&ctx, &name).await, "passed");
+}
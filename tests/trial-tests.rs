@@ -0,0 +1,255 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Trials of the first stage of a course by visitors who have not signed up.
+//! These tests need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test trial-tests -- --ignored
+
+mod common;
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use axum::{
+    Form, Json, Router,
+    body::Body,
+    extract::{ConnectInfo, Path},
+    http::{Method, Request, StatusCode, header},
+    routing::{delete, post},
+};
+use chrono::Duration;
+use serde_json::{Value, json};
+use stackclass::{
+    context::Context,
+    jobs::{Job, ReapExpiredTrials},
+    model::TrialModel,
+    repository::{CourseRepository, StageRepository, TrialRepository},
+    routes,
+    service::TrialService,
+    utils::clock::ManualClock,
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{create_course, create_user, mock_api, setup, token, unreachable_cluster};
+
+/// Address of the trusted proxy the requests come through.
+const PROXY: &str = "10.0.0.1";
+
+/// A captcha verification endpoint accepting the token "valid", next to a
+/// Gitea server recording the repositories deleted from it.
+#[derive(Clone, Default)]
+struct MockServer {
+    verified: Arc<AtomicUsize>,
+    deleted: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    /// Starts the server and returns a context using it, with the config
+    /// adjusted by `configure`.
+    async fn context(&self, configure: impl FnOnce(&mut Context)) -> Arc<Context> {
+        let (verified, deleted) = (self.verified.clone(), self.deleted.clone());
+        let app = Router::new()
+            .route(
+                "/siteverify",
+                post(move |Form(form): Form<Vec<(String, String)>>| async move {
+                    verified.fetch_add(1, Ordering::SeqCst);
+                    let valid = form.iter().any(|(k, v)| k == "response" && v == "valid");
+                    Json(json!({ "success": valid }))
+                }),
+            )
+            .route(
+                "/api/v1/repos/{owner}/{repo}",
+                delete(move |Path((owner, repo)): Path<(String, String)>| async move {
+                    deleted.lock().unwrap().push(format!("{owner}/{repo}"));
+                    StatusCode::NO_CONTENT
+                }),
+            );
+
+        let mut ctx = Arc::into_inner(setup(unreachable_cluster()).await).unwrap();
+        let url = mock_api(&mut ctx, app).await;
+        ctx.config.captcha_verify_url = Some(format!("{url}/siteverify"));
+        ctx.config.trusted_proxies = vec![PROXY.parse().unwrap()];
+        configure(&mut ctx);
+        Arc::new(ctx)
+    }
+}
+
+/// Sends a request through the proxy, or from the given peer.
+async fn send_from(
+    ctx: &Arc<Context>,
+    peer: &str,
+    method: Method,
+    uri: &str,
+    body: Option<Value>,
+    headers: &[(&str, &str)],
+) -> (StatusCode, Value) {
+    let peer: SocketAddr = format!("{peer}:40000").parse().unwrap();
+    let mut req = Request::builder().method(method).uri(uri).extension(ConnectInfo(peer));
+    for (name, value) in headers {
+        req = req.header(*name, *value);
+    }
+    let body = match body {
+        Some(body) => {
+            req = req.header(header::CONTENT_TYPE, "application/json");
+            Body::from(body.to_string())
+        }
+        None => Body::empty(),
+    };
+
+    let app = routes::build(ctx.clone());
+    let res = app.oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn send(
+    ctx: &Arc<Context>,
+    method: Method,
+    uri: &str,
+    body: Option<Value>,
+    headers: &[(&str, &str)],
+) -> (StatusCode, Value) {
+    send_from(ctx, PROXY, method, uri, body, headers).await
+}
+
+/// Inserts an active trial of the first stage of the course.
+async fn create_trial(ctx: &Context, slug: &str, client_key: &str) -> TrialModel {
+    let db = &ctx.database;
+    let course = CourseRepository::get_by_slug(db, slug).await.unwrap();
+    let stage = StageRepository::first(db, slug).await.unwrap().unwrap();
+    let trial = TrialModel::new(course.id, stage.id, client_key, Duration::hours(1));
+
+    let mut tx = db.pool().begin().await.unwrap();
+    let trial = TrialRepository::create(&mut tx, &trial).await.unwrap();
+    tx.commit().await.unwrap();
+    trial
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_trial_admission() {
+    let mock = MockServer::default();
+    let ctx = mock.context(|_| {}).await;
+    let slug = create_course(&ctx).await;
+    let address = format!("203.0.113.{}", Uuid::now_v7().as_u128() % 250);
+    let forwarded = [("x-forwarded-for", address.as_str())];
+    let request = |captcha: &str| json!({ "course_slug": slug, "captcha_token": captcha });
+
+    // Invalid captchas are refused before anything else
+    let (status, _) =
+        send(&ctx, Method::POST, "/v1/trials", Some(request("invalid")), &forwarded).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(mock.verified.load(Ordering::SeqCst), 1);
+
+    let missing = json!({ "course_slug": "missing", "captcha_token": "valid" });
+    let (status, _) = send(&ctx, Method::POST, "/v1/trials", Some(missing), &forwarded).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // A client that used up its trials is rate limited
+    let client_key = TrialService::client_key(&ctx, &address).unwrap();
+    for _ in 0..ctx.config.trial_rate_limit {
+        create_trial(&ctx, &slug, &client_key).await;
+    }
+    let (status, _) =
+        send(&ctx, Method::POST, "/v1/trials", Some(request("valid")), &forwarded).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // Neither prepending a forged address nor skipping the proxy escapes it
+    let forged = format!("198.51.100.7, {address}");
+    let forged = [("x-forwarded-for", forged.as_str())];
+    let (status, _) = send(&ctx, Method::POST, "/v1/trials", Some(request("valid")), &forged).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    let spoofed = [("x-forwarded-for", "198.51.100.8"), ("x-real-ip", "198.51.100.9")];
+    let trial = Some(request("valid"));
+    let (status, _) = send_from(&ctx, &address, Method::POST, "/v1/trials", trial, &spoofed).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // Other clients are refused once the global cap is reached
+    let ctx = mock.context(|ctx| ctx.config.max_active_trials = 0).await;
+    let other = [("x-forwarded-for", "198.51.100.1, 10.0.0.1")];
+    let (status, _) = send(&ctx, Method::POST, "/v1/trials", Some(request("valid")), &other).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // Without a captcha verification URL trials are disabled
+    let ctx = mock.context(|ctx| ctx.config.captcha_verify_url = None).await;
+    let (status, _) = send(&ctx, Method::POST, "/v1/trials", Some(request("valid")), &other).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_trial_lifecycle() {
+    let mock = MockServer::default();
+    let clock = Arc::new(ManualClock::default());
+    let ctx = mock.context(|ctx| ctx.clock = clock.clone()).await;
+    let db = &ctx.database;
+    let slug = create_course(&ctx).await;
+    let trial = create_trial(&ctx, &slug, "client").await;
+    let trial_token = TrialService::token(&ctx, &trial.id).unwrap();
+    assert_eq!(TrialService::parse_repo(&trial.repo()), Some(trial.id));
+    assert_eq!(TrialService::parse_repo(&Uuid::now_v7().to_string()), None);
+
+    // The status is only readable with the token of the trial
+    let uri = format!("/v1/trials/{}/status", trial.id);
+    let other = TrialService::token(&ctx, &Uuid::now_v7()).unwrap();
+    let (status, _) = send(&ctx, Method::GET, &format!("{uri}?token={other}"), None, &[]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let status_uri = format!("{uri}?token={trial_token}");
+    let (status, body) = send(&ctx, Method::GET, &status_uri, None, &[]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "active");
+    assert_eq!(body["test"], Value::Null);
+    assert_eq!(body["stage_slug"], format!("{slug}-s1"));
+
+    // Results of superseded pushes are ignored
+    TrialRepository::start_attempt(db, &trial.id, "run-1").await.unwrap();
+    TrialService::record_result(&ctx, &trial.id, "run-0", "failed").await.unwrap();
+    let (_, body) = send(&ctx, Method::GET, &status_uri, None, &[]).await;
+    assert_eq!(body["test"], "pending");
+
+    TrialService::record_result(&ctx, &trial.id, "run-1", "passed").await.unwrap();
+    let (_, body) = send(&ctx, Method::GET, &status_uri, None, &[]).await;
+    assert_eq!(body["test"], "passed");
+
+    // Expired trials can not be converted anymore, and are reaped
+    clock.advance(Duration::hours(1) + Duration::minutes(1));
+    let bearer = format!("Bearer {}", token(&ctx, &create_user(&ctx).await).await);
+    let convert = json!({
+        "token": trial_token,
+        "proficiency": "beginner",
+        "cadence": "weekly",
+        "accountability": false
+    });
+    let uri = format!("/v1/user/trials/{}/convert", trial.id);
+    let (status, _) =
+        send(&ctx, Method::POST, &uri, Some(convert), &[("authorization", &bearer)]).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, body) = send(&ctx, Method::GET, &status_uri, None, &[]).await;
+    assert_eq!(body["status"], "expired");
+
+    ReapExpiredTrials::new(ctx.clone()).run().await.unwrap();
+    assert!(mock.deleted.lock().unwrap().contains(&format!("trials/{}", trial.repo())));
+    let trial = TrialRepository::get_by_id(db, &trial.id).await.unwrap();
+    assert_eq!(trial.status, "expired");
+}
@@ -0,0 +1,130 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Learners leave a course, which deletes their enrollment, repository and
+//! images. These tests need a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test unenroll-tests -- --ignored
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::Path,
+    http::{Request, StatusCode, header},
+    routing::{delete, get},
+};
+use stackclass::{context::Context, repository::CourseRepository, routes};
+use tower::ServiceExt;
+
+use common::{artifact, create_course, create_user, enroll_user, mock_context, token};
+
+/// A Gitea server recording the repositories deleted from it, failing for
+/// all of them when `broken`, which also serves as a Harbor server where
+/// every repository of the course projects holds an image until deleted.
+async fn context(deleted: Arc<Mutex<Vec<String>>>, broken: bool) -> Arc<Context> {
+    let (listed, removed) = (deleted.clone(), deleted.clone());
+    let app = Router::new()
+        .route(
+            "/api/v1/repos/{owner}/{repo}",
+            delete(move |Path((owner, repo)): Path<(String, String)>| async move {
+                if broken {
+                    return StatusCode::INTERNAL_SERVER_ERROR;
+                }
+                deleted.lock().unwrap().push(format!("{owner}/{repo}"));
+                StatusCode::NO_CONTENT
+            }),
+        )
+        .route(
+            "/api/v2.0/projects/{project}/repositories/{repo}/artifacts",
+            get(move |Path((project, repo)): Path<(String, String)>| async move {
+                let image = format!("image {project}/{repo}");
+                match project.contains('-') {
+                    true if !listed.lock().unwrap().contains(&image) => {
+                        Ok(Json(vec![artifact(&repo)]))
+                    }
+                    true => Ok(Json(vec![])),
+                    false => Err(StatusCode::NOT_FOUND),
+                }
+            }),
+        )
+        .route(
+            "/api/v2.0/projects/{project}/repositories/{repo}/artifacts/{reference}",
+            delete(move |Path((project, repo, _)): Path<(String, String, String)>| {
+                removed.lock().unwrap().push(format!("image {project}/{repo}"));
+                async { StatusCode::OK }
+            }),
+        );
+
+    Arc::new(mock_context(app).await)
+}
+
+async fn unenroll(ctx: &Arc<Context>, user_id: &str, slug: &str) -> StatusCode {
+    let req = Request::delete(format!("/v1/user/courses/{slug}"))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token(ctx, user_id).await))
+        .body(Body::empty())
+        .unwrap();
+
+    let app = routes::build(ctx.clone());
+    app.oneshot(req).await.unwrap().status()
+}
+
+async fn is_enrolled(ctx: &Context, user_id: &str, slug: &str) -> bool {
+    CourseRepository::get_user_course(&ctx.database, user_id, slug).await.is_ok()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_unenroll_deletes_repository() {
+    let deleted = Arc::new(Mutex::new(Vec::new()));
+    let ctx = context(deleted.clone(), false).await;
+    let slug = create_course(&ctx).await;
+    let user_id = create_user(&ctx).await;
+    let user_course_id = enroll_user(&ctx, &user_id, &slug).await;
+
+    assert_eq!(unenroll(&ctx, &user_id, &slug).await, StatusCode::NO_CONTENT);
+    assert!(!is_enrolled(&ctx, &user_id, &slug).await);
+
+    let deleted = deleted.lock().unwrap().clone();
+    assert!(deleted.contains(&format!("{}/{user_course_id}", ctx.config.namespace)), "{deleted:?}");
+    let images: Vec<&String> = deleted.iter().filter(|d| d.starts_with("image ")).collect();
+    assert_eq!(images.len(), 2, "{deleted:?}");
+    assert!(images.iter().all(|image| image.contains(&user_course_id.to_string())));
+
+    // Nothing is left to unenroll from, and the learner may enroll again
+    assert_eq!(unenroll(&ctx, &user_id, &slug).await, StatusCode::NOT_FOUND);
+    let again = enroll_user(&ctx, &user_id, &slug).await;
+    assert_ne!(again, user_course_id);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_unenroll_while_gitea_fails() {
+    let deleted = Arc::new(Mutex::new(Vec::new()));
+    let ctx = context(deleted.clone(), true).await;
+    let slug = create_course(&ctx).await;
+    let user_id = create_user(&ctx).await;
+    enroll_user(&ctx, &user_id, &slug).await;
+
+    // The repository is left to the orphan cleanup
+    assert_eq!(unenroll(&ctx, &user_id, &slug).await, StatusCode::NO_CONTENT);
+    assert!(!is_enrolled(&ctx, &user_id, &slug).await);
+    assert!(!deleted.lock().unwrap().iter().any(|d| !d.starts_with("image ")));
+
+    let other = create_user(&ctx).await;
+    assert_eq!(unenroll(&ctx, &other, &slug).await, StatusCode::NOT_FOUND);
+}
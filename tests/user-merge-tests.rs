@@ -0,0 +1,249 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Duplicate accounts are merged into the account to keep. These tests need
+//! a disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test user-merge-tests -- --ignored
+
+#![recursion_limit = "256"]
+
+mod common;
+
+use std::{
+    collections::HashSet,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::Path,
+    http::{Method, Request, StatusCode, header},
+    routing::patch,
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+use stackclass::{context::Context, repository::CourseRepository, routes, utils::crypto};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use common::{create_course, create_user, enroll_user, mock_context, repository, token};
+
+/// A Gitea server renaming repositories, failing while it is down.
+#[derive(Clone, Default)]
+struct MockGitea {
+    down: Arc<AtomicBool>,
+    renamed: Arc<Mutex<HashSet<String>>>,
+}
+
+impl MockGitea {
+    async fn start(&self) -> Arc<Context> {
+        let mock = self.clone();
+        let app = Router::new().route(
+            "/api/v1/repos/{owner}/{repo}",
+            patch(move |Path((owner, repo)): Path<(String, String)>, Json(req): Json<Value>| {
+                let mock = mock.clone();
+                async move {
+                    if mock.down.load(Ordering::SeqCst) {
+                        return (StatusCode::SERVICE_UNAVAILABLE, Json(json!({})));
+                    }
+                    if !mock.renamed.lock().unwrap().insert(repo.clone()) {
+                        return (StatusCode::NOT_FOUND, Json(json!({ "message": "Not Found" })));
+                    }
+                    (StatusCode::OK, Json(repository(&owner, req["name"].as_str().unwrap())))
+                }
+            }),
+        );
+
+        Arc::new(mock_context(app).await)
+    }
+
+    fn renamed(&self) -> HashSet<String> {
+        self.renamed.lock().unwrap().clone()
+    }
+}
+
+/// Sends a request with the given `Authorization` header value and body.
+async fn send(
+    ctx: &Arc<Context>,
+    method: Method,
+    uri: &str,
+    auth: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, auth)
+        .header(header::CONTENT_TYPE, "application/json");
+    let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+
+    let app = routes::build(ctx.clone());
+    let res = app.oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn merge(ctx: &Arc<Context>, source: &str, target: &str) -> (StatusCode, Value) {
+    let body = json!({ "source_user_id": source, "target_user_id": target });
+    send(ctx, Method::POST, "/v1/admin/users/merge", &admin(ctx), Some(body)).await
+}
+
+fn admin(ctx: &Context) -> String {
+    let password = crypto::hmac_sha256_sign("admin", &ctx.config.auth_secret).unwrap();
+    format!("Basic {}", STANDARD.encode(format!("admin:{password}")))
+}
+
+/// Completes the first stages of the course in the user course.
+async fn complete(ctx: &Context, user_course_id: Uuid, slug: &str, stages: i64) {
+    sqlx::query(
+        r#"
+        INSERT INTO user_stages (id, user_course_id, stage_id, status, completed_at)
+        SELECT gen_random_uuid(), $1, s.id, 'completed', NOW()
+        FROM stages s JOIN courses c ON s.course_id = c.id
+        WHERE c.slug = $2 ORDER BY s.slug LIMIT $3
+        "#,
+    )
+    .bind(user_course_id)
+    .bind(slug)
+    .bind(stages)
+    .execute(ctx.database.pool())
+    .await
+    .unwrap();
+}
+
+async fn enrollment(ctx: &Context, user_id: &str, slug: &str) -> Uuid {
+    CourseRepository::get_user_course(&ctx.database, user_id, slug).await.unwrap().id
+}
+
+async fn audit_count(ctx: &Context, source: &str) -> i64 {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM audit_logs WHERE action = 'merge_users' AND target = $1",
+    )
+    .bind(format!("users/{source}"))
+    .fetch_one(ctx.database.pool())
+    .await
+    .unwrap()
+}
+
+fn archived(body: &Value) -> HashSet<String> {
+    let archived = body["archived"].as_array().unwrap();
+    archived.iter().map(|a| a["user_course_id"].as_str().unwrap().to_string()).collect()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_both_enrolled_keeps_more_progress() {
+    let gitea = MockGitea::default();
+    let ctx = gitea.start().await;
+
+    let (source, target) = (create_user(&ctx).await, create_user(&ctx).await);
+    let (ahead, tied, only) =
+        (create_course(&ctx).await, create_course(&ctx).await, create_course(&ctx).await);
+
+    // The source is ahead in one course, both are on par in another
+    let source_ahead = enroll_user(&ctx, &source, &ahead).await;
+    let target_behind = enroll_user(&ctx, &target, &ahead).await;
+    complete(&ctx, source_ahead, &ahead, 2).await;
+    complete(&ctx, target_behind, &ahead, 1).await;
+    let source_tied = enroll_user(&ctx, &source, &tied).await;
+    let target_tied = enroll_user(&ctx, &target, &tied).await;
+    let source_only = enroll_user(&ctx, &source, &only).await;
+
+    sqlx::query("INSERT INTO user_notification_preferences (user_id, feedback) VALUES ($1, false)")
+        .bind(&source)
+        .execute(ctx.database.pool())
+        .await
+        .unwrap();
+
+    let (status, body) = merge(&ctx, &source, &target).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let mut moved: Vec<_> = body["moved"].as_array().unwrap().iter().collect();
+    moved.sort_by_key(|slug| slug.as_str());
+    let mut expected = [&ahead, &only];
+    expected.sort();
+    assert_eq!(moved, expected);
+
+    let losers = HashSet::from([target_behind.to_string(), source_tied.to_string()]);
+    assert_eq!(archived(&body), losers);
+    assert_eq!(gitea.renamed(), losers);
+    assert!(body["archived"][0]["repository"].as_str().unwrap().ends_with("-merged"));
+
+    // The target ends up with the enrollments making the most progress
+    assert_eq!(enrollment(&ctx, &target, &ahead).await, source_ahead);
+    assert_eq!(enrollment(&ctx, &target, &tied).await, target_tied);
+    assert_eq!(enrollment(&ctx, &target, &only).await, source_only);
+
+    let (status, body) = send(
+        &ctx,
+        Method::GET,
+        "/v1/user/notification-preferences",
+        &format!("Bearer {}", token(&ctx, &target).await),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["feedback"], false);
+
+    // Tokens of the merged-away user act on behalf of the target
+    let bearer = format!("Bearer {}", token(&ctx, &source).await);
+    let (status, body) = send(&ctx, Method::GET, "/v1/user/courses", &bearer, None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body.as_array().unwrap().len(), 3);
+
+    assert_eq!(audit_count(&ctx, &source).await, 1);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_merge_is_idempotent() {
+    let gitea = MockGitea::default();
+    let ctx = gitea.start().await;
+
+    let (source, target) = (create_user(&ctx).await, create_user(&ctx).await);
+    let slug = create_course(&ctx).await;
+    let source_course = enroll_user(&ctx, &source, &slug).await;
+    let target_course = enroll_user(&ctx, &target, &slug).await;
+
+    // The accounts are merged even though the repository cannot be renamed
+    gitea.down.store(true, Ordering::SeqCst);
+    let (status, _) = merge(&ctx, &source, &target).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(gitea.renamed().is_empty());
+
+    // Re-running the merge completes the rename, and changes nothing else
+    gitea.down.store(false, Ordering::SeqCst);
+    let (status, first) = merge(&ctx, &source, &target).await;
+    assert_eq!(status, StatusCode::OK, "{first}");
+    assert_eq!(first["moved"], json!([]));
+    assert_eq!(archived(&first), HashSet::from([source_course.to_string()]));
+    assert_eq!(gitea.renamed(), HashSet::from([source_course.to_string()]));
+
+    let (status, second) = merge(&ctx, &source, &target).await;
+    assert_eq!(status, StatusCode::OK, "{second}");
+    assert_eq!(second, first);
+    assert_eq!(enrollment(&ctx, &target, &slug).await, target_course);
+    assert_eq!(audit_count(&ctx, &source).await, 1);
+
+    // The source cannot be merged anywhere else, nor be merged into
+    let other = create_user(&ctx).await;
+    assert_eq!(merge(&ctx, &source, &other).await.0, StatusCode::BAD_REQUEST);
+    assert_eq!(merge(&ctx, &other, &source).await.0, StatusCode::BAD_REQUEST);
+    assert_eq!(merge(&ctx, &other, &other).await.0, StatusCode::BAD_REQUEST);
+}
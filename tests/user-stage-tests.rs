@@ -0,0 +1,105 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! User stage updates persist every field they carry. These tests need a
+//! disposable PostgreSQL database, run them with:
+//!
+//! TEST_DATABASE_URL=postgres://... cargo test --test user-stage-tests -- --ignored
+
+mod common;
+
+use futures::future;
+use stackclass::{
+    errors::ApiError,
+    repository::{AuditRepository, CourseRepository, StageRepository},
+    service::{CourseService, StageService},
+    utils::pagination::Page,
+};
+
+use common::{create_course, enroll, setup, unreachable_cluster};
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_completion_is_persisted() {
+    let ctx = setup(unreachable_cluster()).await;
+    let db = &ctx.database;
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let mut user_course = CourseRepository::get_user_course(db, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+
+    let s1 = format!("{slug}-s1");
+    let user_stage = StageRepository::get_user_stage(db, &user_id, &slug, &s1).await.unwrap();
+    assert_eq!((user_stage.test.as_str(), user_stage.completed_at), ("failed", None));
+
+    StageService::complete(ctx.clone(), &user_id, &slug, &s1, Some("abc123")).await.unwrap();
+
+    // Re-read the row rather than trusting what the update returned
+    let user_stage = StageRepository::get_user_stage(db, &user_id, &slug, &s1).await.unwrap();
+    assert_eq!(user_stage.status, "completed");
+    assert_eq!(user_stage.test, "passed");
+    assert!(user_stage.completed_at.is_some());
+    assert_eq!(user_stage.completed_commit.as_deref(), Some("abc123"));
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_granted_attempts_are_persisted() {
+    let ctx = setup(unreachable_cluster()).await;
+    let db = &ctx.database;
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let mut user_course = CourseRepository::get_user_course(db, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+
+    let s1 = format!("{slug}-s1");
+    StageService::grant_attempts(ctx.clone(), &user_id, &slug, &s1, 3).await.unwrap();
+
+    let user_stage = StageRepository::get_user_stage(db, &user_id, &slug, &s1).await.unwrap();
+    assert_eq!(user_stage.granted_attempts, 3);
+    assert_eq!((user_stage.status.as_str(), user_stage.test.as_str()), ("in_progress", "failed"));
+    assert_eq!(user_stage.completed_at, None);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_concurrent_grants_all_count() {
+    let ctx = setup(unreachable_cluster()).await;
+    let db = &ctx.database;
+    let slug = create_course(&ctx).await;
+    let user_id = enroll(&ctx, &slug).await;
+    let mut user_course = CourseRepository::get_user_course(db, &user_id, &slug).await.unwrap();
+    CourseService::activate(ctx.clone(), &mut user_course).await.unwrap();
+
+    let s1 = format!("{slug}-s1");
+    let grants = (0..8).map(|_| StageService::grant_attempts(ctx.clone(), &user_id, &slug, &s1, 2));
+    for res in future::join_all(grants).await {
+        res.unwrap();
+    }
+
+    let user_stage = StageRepository::get_user_stage(db, &user_id, &slug, &s1).await.unwrap();
+    assert_eq!(user_stage.granted_attempts, 16);
+
+    // Every grant is audited
+    let target = format!("users/{user_id}/courses/{slug}/stages/{s1}");
+    let (action, page) = (Some("grant_attempts"), Page::first(50));
+    let logs = AuditRepository::find(db, None, action, Some(&target), &page).await.unwrap();
+    assert_eq!(logs.len(), 8);
+
+    // Grants past the largest count are refused, and change nothing
+    let res = StageService::grant_attempts(ctx.clone(), &user_id, &slug, &s1, i32::MAX).await;
+    assert!(matches!(res, Err(ApiError::BadRequest(_))), "{res:?}");
+    let user_stage = StageRepository::get_user_stage(db, &user_id, &slug, &s1).await.unwrap();
+    assert_eq!(user_stage.granted_attempts, 16);
+}
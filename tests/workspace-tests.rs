@@ -0,0 +1,320 @@
+// Copyright (c) The StackClass Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Course syncs reuse a working clone of the template repository and push
+//! only what changed, and template updates reach learner repositories on a
+//! branch of their own. These tests need `git` on the PATH.
+
+use std::{
+    collections::HashMap,
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use stackclass::{
+    service::{
+        IGNORE_FILE, SyncOutcome, TEMPLATE_UPDATE_BRANCH, TemplateUpdate, WorkspaceService,
+        check_submodules,
+    },
+    utils::git::{Submodule, parse_submodules},
+};
+use tempfile::TempDir;
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git").args(args).current_dir(dir).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Commit of the course the templates of these tests come from.
+const COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+
+/// A bare remote repository counting the pushes it receives, a template
+/// directory and a workspace service working under the same root.
+struct Fixture {
+    root: TempDir,
+    remote: PathBuf,
+    template: PathBuf,
+    workspace: WorkspaceService,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let root = tempfile::tempdir().unwrap();
+        let remote = root.path().join("remote.git");
+        fs::create_dir(&remote).unwrap();
+        git(&remote, &["init", "--bare", "-b", "main"]);
+
+        let hook = remote.join("hooks/pre-receive");
+        fs::write(&hook, "#!/bin/sh\necho push >> pushes\n").unwrap();
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let template = root.path().join("template");
+        fs::create_dir_all(template.join("src")).unwrap();
+        fs::write(template.join("README.md"), "# Build your own Redis\n").unwrap();
+        fs::write(template.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(template.join("src/lib.rs"), "pub fn ping() {}\n").unwrap();
+
+        let workspace = WorkspaceService::new(&root.path().join("work"), "Tests", "tests@local");
+        Self { root, remote, template, workspace }
+    }
+
+    async fn sync(&self) -> SyncOutcome {
+        let remote = self.remote.to_str().unwrap();
+        let workspace = &self.workspace;
+        workspace.sync("redis", &self.template, COMMIT, remote, &HashMap::new()).await.unwrap()
+    }
+
+    /// Creates a course repository whose template vendors a harness as a
+    /// submodule and keeps notes out of exports, returning its directory and
+    /// the harness commit.
+    fn course(&self) -> (PathBuf, String) {
+        let root = self.root.path();
+        let harness = root.join("harness");
+        fs::create_dir(&harness).unwrap();
+        git(&harness, &["init", "-b", "main"]);
+        fs::write(harness.join("run.sh"), "#!/bin/sh\n").unwrap();
+        git(&harness, &["add", "."]);
+        git(&harness, &["-c", "user.name=T", "-c", "user.email=t@local", "commit", "-m", "init"]);
+
+        let course = root.join("course");
+        fs::create_dir_all(course.join("template/notes")).unwrap();
+        git(&course, &["init", "-b", "main"]);
+        fs::write(course.join("template/README.md"), "# Build your own Redis\n").unwrap();
+        fs::write(course.join("template/notes/todo.md"), "- hidden\n").unwrap();
+        fs::write(course.join("template/.gitattributes"), "notes export-ignore\n").unwrap();
+        let url = harness.to_str().unwrap();
+        git(
+            &course,
+            &["-c", "protocol.file.allow=always", "submodule", "add", url, "template/harness"],
+        );
+        git(&course, &["add", "."]);
+        git(&course, &["-c", "user.name=T", "-c", "user.email=t@local", "commit", "-m", "init"]);
+
+        (course, git(&harness, &["rev-parse", "HEAD"]).trim().to_string())
+    }
+
+    fn pushes(&self) -> usize {
+        fs::read_to_string(self.remote.join("pushes")).map_or(0, |s| s.lines().count())
+    }
+}
+
+#[tokio::test]
+async fn test_unchanged_template_is_not_pushed() {
+    let fixture = Fixture::new();
+
+    let SyncOutcome::Pushed(paths) = fixture.sync().await else { panic!("nothing pushed") };
+    assert_eq!(paths, ["README.md", "src/lib.rs", "src/main.rs"]);
+    assert_eq!(fixture.pushes(), 1);
+
+    // A second sync finds nothing to push
+    assert_eq!(fixture.sync().await, SyncOutcome::Unchanged);
+    assert_eq!(fixture.pushes(), 1);
+
+    // So does a fresh clone, e.g. after the cleanup job removed it
+    fixture.workspace.remove("redis").unwrap();
+    assert!(!fixture.root.path().join("work/redis").exists());
+    assert_eq!(fixture.sync().await, SyncOutcome::Unchanged);
+    assert_eq!(fixture.pushes(), 1);
+    assert_eq!(git(&fixture.remote, &["rev-list", "--count", "main"]).trim(), "1");
+}
+
+#[tokio::test]
+async fn test_changed_file_makes_a_one_file_commit() {
+    let fixture = Fixture::new();
+    fixture.sync().await;
+
+    fs::write(fixture.template.join("src/main.rs"), "fn main() { ping() }\n").unwrap();
+    assert_eq!(fixture.sync().await, SyncOutcome::Pushed(vec!["src/main.rs".into()]));
+    assert_eq!(fixture.pushes(), 2);
+
+    let changed =
+        git(&fixture.remote, &["diff-tree", "--no-commit-id", "--name-only", "-r", "main"]);
+    assert_eq!(changed, "src/main.rs\n");
+    let content = git(&fixture.remote, &["show", "main:src/main.rs"]);
+    assert_eq!(content, "fn main() { ping() }\n");
+    let message = git(&fixture.remote, &["log", "-1", "--format=%B", "main"]);
+    assert_eq!(message, format!("Sync with template\n\nCourse commit: {COMMIT}\n\n"));
+
+    // Files removed from the template are removed from the repository
+    fs::remove_file(fixture.template.join("src/lib.rs")).unwrap();
+    assert_eq!(fixture.sync().await, SyncOutcome::Pushed(vec!["src/lib.rs".into()]));
+    let files = git(&fixture.remote, &["ls-tree", "-r", "--name-only", "main"]);
+    assert_eq!(files, "README.md\nsrc/main.rs\n");
+    assert_eq!(fixture.pushes(), 3);
+}
+
+#[tokio::test]
+async fn test_template_with_submodule_keeps_its_pointer() {
+    let fixture = Fixture::new();
+    let (course, harness) = fixture.course();
+    let url = fixture.root.path().join("harness").to_str().unwrap().to_string();
+    let submodules = [Submodule { name: "harness".into(), path: "harness".into(), url }];
+    let remote = fixture.remote.to_str().unwrap();
+
+    let outcome = fixture.workspace.sync_tree("redis", &course, "template", &submodules, remote);
+    let SyncOutcome::Pushed(paths) = outcome.await.unwrap() else { panic!("nothing pushed") };
+    assert_eq!(paths, [".gitattributes", ".gitmodules", "README.md", "harness"]);
+
+    // The commit names the one of the course
+    let head = git(&course, &["rev-parse", "HEAD"]);
+    let message = git(&fixture.remote, &["log", "-1", "--format=%b", "main"]);
+    assert_eq!(message.trim(), format!("Course commit: {}", head.trim()));
+
+    // The harness is a submodule pointer, the export-ignored notes are left out
+    let tree = git(&fixture.remote, &["ls-tree", "main", "harness"]);
+    assert_eq!(tree, format!("160000 commit {harness}\tharness\n"));
+    let files = git(&fixture.remote, &["ls-tree", "-r", "--name-only", "main"]);
+    assert_eq!(files, ".gitattributes\n.gitmodules\nREADME.md\nharness\n");
+    let gitmodules = git(&fixture.remote, &["show", "main:.gitmodules"]);
+    assert_eq!(parse_submodules(&gitmodules), submodules);
+
+    // Nothing changed upstream, nothing is pushed
+    let outcome = fixture.workspace.sync_tree("redis", &course, "template", &submodules, remote);
+    assert_eq!(outcome.await.unwrap(), SyncOutcome::Unchanged);
+    assert_eq!(fixture.pushes(), 1);
+
+    // Learners can fetch the harness, but not a repository that is missing
+    assert!(check_submodules(fixture.root.path(), &submodules).await.is_empty());
+    let missing = fixture.root.path().join("private").to_str().unwrap().to_string();
+    let private = [Submodule { url: missing, ..submodules[0].clone() }];
+    let warnings = check_submodules(fixture.root.path(), &private).await;
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("will not be able to fetch"), "{}", warnings[0]);
+}
+
+#[tokio::test]
+async fn test_ignored_files_are_not_shipped() {
+    let fixture = Fixture::new();
+    let template = &fixture.template;
+    fs::create_dir_all(template.join(".github/workflows")).unwrap();
+    fs::create_dir_all(template.join(".vscode")).unwrap();
+    fs::write(template.join(".github/workflows/ci.yml"), "on: push\n").unwrap();
+    fs::write(template.join(".vscode/settings.json"), "{}\n").unwrap();
+    fs::write(template.join("NOTES.md"), "- solution in stage 3\n").unwrap();
+    fs::write(template.join("src/scratch.rs"), "fn solve() {}\n").unwrap();
+    fs::write(template.join(IGNORE_FILE), ".github/\n.vscode\n*.md\n!README.md\nscratch.rs\n")
+        .unwrap();
+
+    let SyncOutcome::Pushed(paths) = fixture.sync().await else { panic!("nothing pushed") };
+    assert_eq!(paths, ["README.md", "src/lib.rs", "src/main.rs"]);
+
+    // Files ignored later are removed from the repository
+    fs::write(template.join(IGNORE_FILE), ".github/\n.vscode\n*.md\n!README.md\nsrc/\n").unwrap();
+    let SyncOutcome::Pushed(paths) = fixture.sync().await else { panic!("nothing pushed") };
+    assert_eq!(paths, ["src/lib.rs", "src/main.rs"]);
+    let files = git(&fixture.remote, &["ls-tree", "-r", "--name-only", "main"]);
+    assert_eq!(files, "README.md\n");
+}
+
+#[tokio::test]
+async fn test_ignored_files_are_left_out_of_the_tree() {
+    let fixture = Fixture::new();
+    let (course, _) = fixture.course();
+    fs::create_dir_all(course.join("template/.github")).unwrap();
+    fs::write(course.join("template/.github/ci.yml"), "on: push\n").unwrap();
+    fs::write(course.join("template/solution.rs"), "fn solve() {}\n").unwrap();
+    fs::write(course.join("template").join(IGNORE_FILE), ".github\nsolution.rs\n").unwrap();
+    git(&course, &["add", "."]);
+    git(&course, &["-c", "user.name=T", "-c", "user.email=t@local", "commit", "-m", "ci"]);
+
+    let remote = fixture.remote.to_str().unwrap();
+    let outcome = fixture.workspace.sync_tree("redis", &course, "template", &[], remote);
+    let SyncOutcome::Pushed(paths) = outcome.await.unwrap() else { panic!("nothing pushed") };
+    assert_eq!(paths, [".gitattributes", "README.md", "harness"]);
+}
+
+/// Commits the files in the clone `dir` and pushes it to its origin,
+/// returning the new commit.
+fn commit(dir: &Path, files: &[(&str, &str)], message: &str) -> String {
+    for (path, content) in files {
+        fs::write(dir.join(path), content).unwrap();
+    }
+    git(dir, &["add", "."]);
+    git(dir, &["-c", "user.name=T", "-c", "user.email=t@local", "commit", "-m", message]);
+    git(dir, &["push", "-q", "origin", "HEAD:main"]);
+    git(dir, &["rev-parse", "HEAD"]).trim().to_string()
+}
+
+/// Creates a bare repository `name` and a clone of it to commit from.
+fn repository(root: &Path, name: &str) -> (PathBuf, PathBuf) {
+    let bare = root.join(format!("{name}.git"));
+    fs::create_dir(&bare).unwrap();
+    git(&bare, &["init", "--bare", "-b", "main"]);
+    git(root, &["clone", "-q", bare.to_str().unwrap(), name]);
+    git(&root.join(name), &["checkout", "-q", "-b", "main"]);
+    (bare, root.join(name))
+}
+
+/// A template repository and a learner repository generated from it, the
+/// learner having changed a file of their own.
+fn generated(root: &Path) -> (PathBuf, PathBuf, PathBuf) {
+    let files = [("README.md", "# Build your own Redis\n"), ("main.rs", "fn main() {}\n")];
+    let (_, template_clone) = repository(root, "template");
+    commit(&template_clone, &files, "Initial commit");
+
+    // Generated repositories start from the same files, with a fresh history
+    let (learner, learner_clone) = repository(root, "learner");
+    commit(&learner_clone, &files, "Initial commit from template");
+    commit(&learner_clone, &[("main.rs", "fn main() { serve() }\n")], "Serve");
+    (template_clone, learner, learner_clone)
+}
+
+#[tokio::test]
+async fn test_template_updates_are_merged_on_a_branch() {
+    let root = tempfile::tempdir().unwrap();
+    let (template, learner, learner_clone) = generated(root.path());
+    let main = git(&learner, &["rev-parse", "main"]);
+    let workspace = WorkspaceService::new(&root.path().join("work"), "Tests", "tests@local");
+    let (repo_url, template_url) = (learner.to_str().unwrap(), template.to_str().unwrap());
+
+    // Nothing changed in the template yet
+    let update = workspace.update_from_template(repo_url, template_url).await.unwrap();
+    assert_eq!(update, TemplateUpdate::UpToDate);
+
+    let fix = commit(&template, &[("README.md", "# Build your own Redis server\n")], "Fix");
+    let update = workspace.update_from_template(repo_url, template_url).await.unwrap();
+    assert_eq!(update, TemplateUpdate::Pushed(fix));
+
+    // The branch holds both changes, the main branch is left alone
+    let branch = format!("{TEMPLATE_UPDATE_BRANCH}:");
+    let readme = git(&learner, &["show", &format!("{branch}README.md")]);
+    assert_eq!(readme, "# Build your own Redis server\n");
+    assert_eq!(git(&learner, &["show", &format!("{branch}main.rs")]), "fn main() { serve() }\n");
+    assert_eq!(git(&learner, &["rev-parse", "main"]), main);
+
+    // Once the learner merged the branch, the histories are tied
+    git(&learner_clone, &["pull", "-q", "origin", TEMPLATE_UPDATE_BRANCH]);
+    git(&learner_clone, &["push", "-q", "origin", "HEAD:main"]);
+    let update = workspace.update_from_template(repo_url, template_url).await.unwrap();
+    assert_eq!(update, TemplateUpdate::UpToDate);
+}
+
+#[tokio::test]
+async fn test_conflicting_template_updates_are_reported() {
+    let root = tempfile::tempdir().unwrap();
+    let (template, learner, _) = generated(root.path());
+    let workspace = WorkspaceService::new(&root.path().join("work"), "Tests", "tests@local");
+    let (repo_url, template_url) = (learner.to_str().unwrap(), template.to_str().unwrap());
+
+    commit(&template, &[("main.rs", "fn main() { todo!() }\n")], "Hint");
+    let update = workspace.update_from_template(repo_url, template_url).await.unwrap();
+    assert_eq!(update, TemplateUpdate::Conflict(vec!["main.rs".into()]));
+
+    // Nothing is pushed, the learner resolves it on their own terms
+    let branches = git(&learner, &["branch", "--list", TEMPLATE_UPDATE_BRANCH]);
+    assert!(branches.is_empty(), "{branches}");
+}